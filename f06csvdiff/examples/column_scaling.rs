@@ -0,0 +1,66 @@
+//! Times comparing a 10,000-column table with the float columns split among
+//! 1, 2, 4 and 8 threads, checking that every split gives the same report.
+//!
+//! Run with `cargo run --release --example column_scaling [ROWS]`.
+
+use std::time::Instant;
+
+use csv::StringRecord;
+use f06csvdiff::compare::{self, DiffConfig};
+use f06csvdiff::verdict::Tolerances;
+
+/// The width of the table.
+const COLUMNS: usize = 10_000;
+
+/// Builds a table of floats, with the second one perturbed every few cells.
+fn fixture(rows: usize) -> (Vec<StringRecord>, Vec<StringRecord>) {
+  let cell = |row: usize, col: usize, nudge: f64| {
+    let value = ((row * COLUMNS + col) % 9973) as f64 / 997.0 + 1.0 + nudge;
+    format!("{value:.6E}")
+  };
+  (0..rows)
+    .map(|row| {
+      let r1: StringRecord = (0..COLUMNS).map(|c| cell(row, c, 0.0)).collect();
+      let r2: StringRecord = (0..COLUMNS)
+        .map(|c| cell(row, c, if (row + c) % 7 == 0 { 1e-4 } else { 0.0 }))
+        .collect();
+      (r1, r2)
+    })
+    .unzip()
+}
+
+fn main() {
+  let rows = std::env::args()
+    .nth(1)
+    .map_or(200, |n| n.parse().expect("ROWS must be a number"));
+  let (t1, t2) = fixture(rows);
+  let mut config = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(1e-5),
+      max_diff: Some(1e-5),
+      min_digits: None,
+      max_fail_fraction: None,
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let mut serial = None;
+  let mut serial_time = 0.0;
+  for workers in [1, 2, 4, 8] {
+    config.column_workers = workers;
+    let start = Instant::now();
+    let report = compare::compare(&t1, &t2, &config, ("a", "b")).unwrap();
+    let elapsed = start.elapsed().as_secs_f64();
+    match &serial {
+      None => {
+        serial = Some(report);
+        serial_time = elapsed;
+      }
+      Some(serial) => assert_eq!(&report, serial, "{workers} workers"),
+    }
+    println!(
+      "{workers} worker(s): {elapsed:.3} s, speedup {:.2}",
+      serial_time / elapsed
+    );
+  }
+}
@@ -0,0 +1,79 @@
+//! Times comparing a table with and without profiling its phases, checking
+//! that both give the same report and printing what profiling costs.
+//!
+//! Run with `cargo run --release --example profile_overhead [ROWS]`.
+
+use std::time::{Duration, Instant};
+
+use csv::StringRecord;
+use f06csvdiff::compare::{self, DiffConfig};
+use f06csvdiff::profile::Phase;
+use f06csvdiff::verdict::Tolerances;
+
+/// The width of the table.
+const COLUMNS: usize = 20;
+
+/// How many times each way is timed; the fastest counts.
+const RUNS: usize = 7;
+
+/// Builds a table of floats, with the second one perturbed every few cells.
+fn fixture(rows: usize) -> (Vec<StringRecord>, Vec<StringRecord>) {
+  let cell = |row: usize, col: usize, nudge: f64| {
+    let value = ((row * COLUMNS + col) % 9973) as f64 / 997.0 + 1.0 + nudge;
+    format!("{value:.6E}")
+  };
+  (0..rows)
+    .map(|row| {
+      let r1: StringRecord = (0..COLUMNS).map(|c| cell(row, c, 0.0)).collect();
+      let r2: StringRecord = (0..COLUMNS)
+        .map(|c| cell(row, c, if (row + c) % 7 == 0 { 1e-4 } else { 0.0 }))
+        .collect();
+      (r1, r2)
+    })
+    .unzip()
+}
+
+fn main() {
+  let rows = std::env::args()
+    .nth(1)
+    .map_or(200_000, |n| n.parse().expect("ROWS must be a number"));
+  let (t1, t2) = fixture(rows);
+  let config = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(1e-5),
+      max_diff: Some(1e-5),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let paths = ("a", "b");
+  let (mut plain, mut profiled) = (Duration::MAX, Duration::MAX);
+  let mut phases = None;
+  for _ in 0..RUNS {
+    let start = Instant::now();
+    let report = compare::compare(&t1, &t2, &config, paths).unwrap();
+    plain = plain.min(start.elapsed());
+    let start = Instant::now();
+    let (timed, times) =
+      compare::compare_profiled(&t1, &t2, &config, paths).unwrap();
+    let elapsed = start.elapsed();
+    assert_eq!(report, timed);
+    if elapsed < profiled {
+      profiled = elapsed;
+      phases = Some(times);
+    }
+  }
+  let (plain, profiled) = (plain.as_secs_f64(), profiled.as_secs_f64());
+  println!("without profiling: {plain:.3} s");
+  println!(
+    "with profiling: {profiled:.3} s, overhead {:+.2}%",
+    (profiled / plain - 1.0) * 100.0
+  );
+  // of the fastest profiled run, whose phases should add up to about it
+  let phases = phases.unwrap();
+  for phase in [Phase::Classifying, Phase::FloatParsing, Phase::Comparing] {
+    let seconds = phases.get(phase).as_secs_f64();
+    println!("  {}: {seconds:.3} s", phase.name());
+  }
+  println!("  in all: {:.3} s", phases.total().as_secs_f64());
+}
@@ -0,0 +1,65 @@
+//! Times reading and comparing files of a single record a few megabytes to
+//! a few tens of megabytes wide, like a wide matrix row, printing the time
+//! per megabyte, which should stay about the same as the records grow.
+//!
+//! Run with `cargo run --release --example wide_record [MEGABYTES]`.
+
+use std::fmt::Write as _;
+use std::time::Instant;
+
+use f06csvdiff::compare::DiffConfig;
+use f06csvdiff::verdict::Tolerances;
+
+/// Writes a file of one record of floats about `bytes` long, the second
+/// file's perturbed every few cells, returning their paths.
+fn fixture(bytes: usize) -> (String, String) {
+  let dir = std::env::temp_dir();
+  let mut paths = Vec::new();
+  for (name, nudge) in [("a", 0.0), ("b", 1e-4)] {
+    let mut line = String::with_capacity(bytes + 64);
+    let mut col = 0;
+    while line.len() < bytes {
+      let nudge = if col % 7 == 0 { nudge } else { 0.0 };
+      let value = (col % 9973) as f64 / 997.0 + 1.0 + nudge;
+      if col > 0 {
+        line.push(',');
+      }
+      write!(line, "{value:.6E}").unwrap();
+      col += 1;
+    }
+    line.push('\n');
+    let path = dir.join(format!("wide-{}-{name}.csv", std::process::id()));
+    std::fs::write(&path, line).unwrap();
+    paths.push(path.to_str().unwrap().to_string());
+  }
+  (paths[0].clone(), paths[1].clone())
+}
+
+fn main() {
+  let largest = std::env::args()
+    .nth(1)
+    .map_or(32, |n| n.parse().expect("MEGABYTES must be a number"));
+  let config = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(1e-3),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let mut megabytes = 1;
+  while megabytes <= largest {
+    let (a, b) = fixture(megabytes << 20);
+    let start = Instant::now();
+    let report = f06csvdiff::compare_files(&a, &b, &config).unwrap();
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+      "{megabytes:>4} MB, {} columns: {elapsed:.3} s, {:.3} s/MB",
+      report.columns.len(),
+      elapsed / megabytes as f64
+    );
+    for path in [a, b] {
+      std::fs::remove_file(path).unwrap();
+    }
+    megabytes *= 2;
+  }
+}
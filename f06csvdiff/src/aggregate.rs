@@ -0,0 +1,143 @@
+//! The aggregate subcommand: a summary of many runs, read back from their
+//! result lines, such as a night's runs appended to one file. Each line is
+//! either an F06CSVDIFF-RESULT line or a --format csv row, whose header
+//! rows are skipped; batches' aggregate result lines are skipped too, since
+//! their pairs have lines of their own.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::CSV_FIELDS;
+use crate::summary::{ResultLine, TAG};
+
+/// The summary of a set of runs' results.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct Summary {
+  /// How many comparisons there were.
+  pub(crate) comparisons: usize,
+  /// How many passed without exceeding a warning tolerance.
+  pub(crate) passed: usize,
+  /// How many passed but exceeded a warning tolerance.
+  pub(crate) warned: usize,
+  /// How many failed with waivers excusing it.
+  pub(crate) waived: usize,
+  /// How many failed.
+  pub(crate) failed: usize,
+  /// The worst relative difference, in percent, of any comparing it.
+  pub(crate) worst_ratio_pct: Option<Worst>,
+  /// The worst absolute difference of any comparing it.
+  pub(crate) worst_diff: Option<Worst>,
+}
+
+/// The worst difference of a set of runs, and the pair it's in.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct Worst {
+  /// The difference.
+  pub(crate) value: f64,
+  /// The pair's first file.
+  pub(crate) file1: String,
+  /// The pair's second file.
+  pub(crate) file2: String,
+}
+
+impl Summary {
+  /// Sums up the result lines of pairs.
+  pub(crate) fn of(lines: &[ResultLine]) -> Self {
+    let mut summary = Summary {
+      comparisons: lines.len(),
+      ..Default::default()
+    };
+    for line in lines {
+      match line.status() {
+        "PASSED" => summary.passed += 1,
+        "WARNED" => summary.warned += 1,
+        "WAIVED" => summary.waived += 1,
+        _ => summary.failed += 1,
+      }
+      let (file1, file2) = line.pair.clone().unwrap_or_default();
+      for (worst, value) in [
+        (&mut summary.worst_ratio_pct, line.worst_ratio_pct),
+        (&mut summary.worst_diff, line.worst_diff),
+      ] {
+        // the first of equal differences is the worst
+        if let Some(value) = value
+          && worst.as_ref().is_none_or(|w| value > w.value)
+        {
+          *worst = Some(Worst {
+            value,
+            file1: file1.clone(),
+            file2: file2.clone(),
+          });
+        }
+      }
+    }
+    summary
+  }
+}
+
+/// Reads the result lines of a file of them, skipping blank lines, --format
+/// csv header rows and aggregate result lines. Fails at the first line that
+/// isn't a result line or a --format csv row, naming it.
+pub(crate) fn read(path: &Path) -> Result<Vec<ResultLine>, String> {
+  let text = std::fs::read_to_string(path)
+    .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+  let mut lines = Vec::new();
+  for (i, line) in text.lines().enumerate() {
+    let at = |e: String| format!("{}:{}: {e}", path.display(), i + 1);
+    if line.trim().is_empty() {
+      continue;
+    }
+    let result = match line.trim_start().starts_with(TAG) {
+      true => line.parse::<ResultLine>().map_err(at)?,
+      false => match csv_result(line).map_err(at)? {
+        Some(result) => result,
+        None => continue,
+      },
+    };
+    if result.pairs.is_none() {
+      lines.push(result);
+    }
+  }
+  Ok(lines)
+}
+
+/// The result of a --format csv row, or none for its header row. Fields
+/// of gates that weren't enabled are empty.
+fn csv_result(line: &str) -> Result<Option<ResultLine>, String> {
+  let mut reader = csv::ReaderBuilder::new()
+    .has_headers(false)
+    .from_reader(line.as_bytes());
+  let record = match reader.records().next() {
+    Some(record) => record.map_err(|e| e.to_string())?,
+    None => return Ok(None),
+  };
+  if record.len() != CSV_FIELDS.len() {
+    return Err(format!(
+      "neither a {TAG} line nor a --format csv row of {} fields",
+      CSV_FIELDS.len()
+    ));
+  }
+  if record.iter().eq(CSV_FIELDS) {
+    return Ok(None);
+  }
+  let value = |i: usize| match &record[i] {
+    "" => Ok(None),
+    text => text
+      .parse::<f64>()
+      .map(Some)
+      .map_err(|_| format!("{}: {text:?} isn't a number", CSV_FIELDS[i])),
+  };
+  let failed = |i: usize| match &record[i] {
+    "" | "PASS" => Ok(false),
+    "FAIL" => Ok(true),
+    text => Err(format!("{}: unknown status {text}", CSV_FIELDS[i])),
+  };
+  Ok(Some(ResultLine {
+    pair: Some((record[0].to_string(), record[1].to_string())),
+    passed: !failed(6)? && !failed(11)?,
+    worst_ratio_pct: value(2)?,
+    worst_diff: value(7)?,
+    ..Default::default()
+  }))
+}
@@ -0,0 +1,120 @@
+//! Annotations: flags some exporters append to numbers, such as the `*` on
+//! "1.2345E+03*" for a value at a constrained DOF. They're taken off before
+//! the comparison, like headers, so that annotated cells still count as
+//! floats, and may be compared between the files on their own.
+
+use csv::StringRecord;
+use regex::Regex;
+
+use crate::compare::{DiffConfig, DiffReport, is_float};
+
+/// The annotation recognized by default: a single trailing `*`.
+pub const DEFAULT_SUFFIX: &str = r"\*";
+
+/// An annotation taken off a cell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+  /// The (0-based) row of the cell, among the records it was taken from.
+  pub row: usize,
+  /// The (0-based) column of the cell.
+  pub col: usize,
+  /// The annotation.
+  pub suffix: String,
+}
+
+/// A compared cell annotated differently in each file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnnotationMismatch {
+  /// The (1-based) physical line of the cell, in csv1.
+  pub line: usize,
+  /// The (1-based) column of the cell, in csv1.
+  pub col: usize,
+  /// The cell's annotation in each file, if it has one.
+  pub suffixes: (Option<String>, Option<String>),
+}
+
+/// Takes off every cell's trailing match of `suffix`, when the rest of the
+/// cell is a float by `float_re`, and returns what was taken off, in
+/// row-major order. Records keep their positions, so lines are reported as
+/// they were.
+pub fn take_annotations(
+  records: &mut [StringRecord],
+  suffix: &Regex,
+  float_re: &Regex,
+) -> Vec<Annotation> {
+  // only a match ending the cell is an annotation
+  let suffix = Regex::new(&format!("(?:{})$", suffix.as_str())).unwrap();
+  let mut taken = Vec::new();
+  for (row, record) in records.iter_mut().enumerate() {
+    let stripped: Vec<Option<(&str, &str)>> = record
+      .iter()
+      .map(|cell| {
+        let at = suffix.find(cell).filter(|m| !m.is_empty())?.start();
+        let (value, annotation) = cell.split_at(at);
+        is_float(value, float_re).then_some((value, annotation))
+      })
+      .collect();
+    if stripped.iter().all(Option::is_none) {
+      continue;
+    }
+    let mut cells = StringRecord::new();
+    for (col, (cell, stripped)) in record.iter().zip(&stripped).enumerate() {
+      match stripped {
+        Some((value, annotation)) => {
+          cells.push_field(value);
+          taken.push(Annotation {
+            row,
+            col,
+            suffix: annotation.to_string(),
+          });
+        }
+        None => cells.push_field(cell),
+      }
+    }
+    cells.set_position(record.position().cloned());
+    *record = cells;
+  }
+  taken
+}
+
+/// The compared cells of a report whose annotations differ between the
+/// files, given what [`take_annotations`] took off each, in row-major order.
+pub fn mismatches(
+  records1: &[StringRecord],
+  (taken1, taken2): (&[Annotation], &[Annotation]),
+  opts: &DiffConfig,
+  report: &DiffReport,
+) -> Vec<AnnotationMismatch> {
+  let compared = |col: usize| report.columns.iter().any(|c| c.col == col + 1);
+  let find = |taken: &[Annotation], row: usize, col: usize| {
+    taken
+      .iter()
+      .find(|a| (a.row, a.col) == (row, col))
+      .map(|a| a.suffix.clone())
+  };
+  // the cells annotated in either file, by csv1's column
+  let mut cells: Vec<(usize, usize)> = taken1
+    .iter()
+    .map(|a| (a.row, a.col))
+    .chain(taken2.iter().filter_map(|a| {
+      let col =
+        (0..records1.first()?.len()).find(|&i| opts.col2(i) == Some(a.col))?;
+      Some((a.row, col))
+    }))
+    .filter(|&(row, col)| row < report.rows && compared(col))
+    .collect();
+  cells.sort_unstable();
+  cells.dedup();
+  cells
+    .into_iter()
+    .filter_map(|(row, col)| {
+      let suffix1 = find(taken1, row, col);
+      let suffix2 = opts.col2(col).and_then(|j| find(taken2, row, j));
+      (suffix1 != suffix2).then(|| AnnotationMismatch {
+        line: crate::compare::physical_line(records1, row),
+        col: col + 1,
+        suffixes: (suffix1, suffix2),
+      })
+    })
+    .collect()
+}
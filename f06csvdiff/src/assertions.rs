@@ -0,0 +1,271 @@
+//! Assertions a tolerance spec makes about the files it's applied to, such
+//! as that a column exists or that enough rows are compared. They're checked
+//! after the comparison, and any that fails fails it, so that the schema or
+//! the data drifting away from what the tolerances were chosen for turns the
+//! gate red instead of quietly weakening it.
+
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+
+use crate::compare::{
+  DiffConfig, DiffReport, is_float, parse_float, physical_line,
+};
+use crate::verdict;
+
+/// The assertions of a spec's `[assert]` section.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Assertions {
+  /// Columns both headers have to name.
+  pub column_exists: Vec<String>,
+  /// The fewest rows to be compared.
+  pub min_rows_compared: Option<usize>,
+  /// Groups of rows, such as a subcase, both files have to have.
+  pub group_present: Vec<GroupPresent>,
+  /// Cells whose values have to agree within their own tolerances.
+  pub cell_within: Vec<CellWithin>,
+}
+
+/// A group of rows: those whose cell in a column holds a value.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupPresent {
+  /// The column, by header name or (1-based) number in csv1.
+  pub column: String,
+  /// The value, equal as text or as a number.
+  pub value: String,
+}
+
+/// A cell, found by a key in its row, and its tolerances.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CellWithin {
+  /// The key's column, by header name or (1-based) number in csv1.
+  pub key: String,
+  /// The key's value in the cell's row, e.g. an element ID.
+  pub value: String,
+  /// The cell's column, by header name or (1-based) number in csv1.
+  pub column: String,
+  /// The largest allowed relative difference, measured as the comparison
+  /// measures it (0.001 is 0.1%).
+  pub max_ratio: Option<f64>,
+  /// The largest allowed absolute difference.
+  pub max_diff: Option<f64>,
+}
+
+/// An assertion that didn't hold.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssertionFailure {
+  /// The assertion, as the spec makes it.
+  pub assertion: String,
+  /// Why it didn't hold.
+  pub reason: String,
+}
+
+/// What the assertions are checked against: the records as compared, with
+/// the headers if the files have them.
+pub struct Compared<'a> {
+  /// The records of each file.
+  pub records: (&'a [StringRecord], &'a [StringRecord]),
+  /// The header of each file.
+  pub headers: Option<(&'a StringRecord, &'a StringRecord)>,
+  /// How the files were compared.
+  pub opts: &'a DiffConfig,
+  /// What the comparison found.
+  pub report: &'a DiffReport,
+}
+
+impl Assertions {
+  /// How many assertions there are.
+  pub fn len(&self) -> usize {
+    self.column_exists.len()
+      + usize::from(self.min_rows_compared.is_some())
+      + self.group_present.len()
+      + self.cell_within.len()
+  }
+
+  /// Whether there are none.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Checks every assertion, returning those that don't hold, in the order
+  /// of the kinds above.
+  pub fn check(&self, compared: &Compared) -> Vec<AssertionFailure> {
+    let mut failures = Vec::new();
+    let mut fail = |assertion: String, reason: String| {
+      failures.push(AssertionFailure { assertion, reason });
+    };
+    for name in &self.column_exists {
+      let assertion = format!("column {name} exists");
+      let Some((header1, header2)) = compared.headers else {
+        fail(assertion, "the files have no headers".to_string());
+        continue;
+      };
+      let lacking: Vec<&str> = [("csv1", header1), ("csv2", header2)]
+        .into_iter()
+        .filter(|(_, header)| !header.iter().any(|n| n.trim() == name.trim()))
+        .map(|(file, _)| file)
+        .collect();
+      match lacking[..] {
+        [] => {}
+        [file] => fail(assertion, format!("{file}'s header lacks it")),
+        _ => fail(assertion, "neither header has it".to_string()),
+      }
+    }
+    if let Some(min) = self.min_rows_compared
+      && compared.report.rows < min
+    {
+      fail(
+        format!("at least {min} rows are compared"),
+        format!("only {} were", compared.report.rows),
+      );
+    }
+    for group in &self.group_present {
+      let assertion = format!("a row has {} = {}", group.column, group.value);
+      if let Err(reason) = group_present(compared, group) {
+        fail(assertion, reason);
+      }
+    }
+    for cell in &self.cell_within {
+      let assertion = cell.describe();
+      if let Err(reason) = cell_within(compared, cell) {
+        fail(assertion, reason);
+      }
+    }
+    failures
+  }
+}
+
+impl CellWithin {
+  /// The assertion, as the spec makes it.
+  pub fn describe(&self) -> String {
+    let mut within = Vec::new();
+    if let Some(r) = self.max_ratio {
+      within.push(format!("{:.4}%", r * 100.0));
+    }
+    if let Some(d) = self.max_diff {
+      within.push(format!("{d:.2E}"));
+    }
+    format!(
+      "{} at {} = {} is within {}",
+      self.column,
+      self.key,
+      self.value,
+      within.join(" and ")
+    )
+  }
+}
+
+/// The (0-based) columns of csv1 and csv2 a spec's column stands for: csv1's
+/// by name or number, and the column of csv2 compared with it.
+fn columns(
+  compared: &Compared,
+  column: &str,
+) -> Result<(usize, usize), String> {
+  let col1 = match column.parse::<usize>() {
+    Ok(0) => return Err("columns are numbered from 1".to_string()),
+    Ok(col) => col - 1,
+    Err(_) => {
+      let Some((header1, _)) = compared.headers else {
+        return Err(format!("column {column} needs a header"));
+      };
+      header1
+        .iter()
+        .position(|n| n.trim() == column.trim())
+        .ok_or_else(|| format!("csv1's header has no column {column}"))?
+    }
+  };
+  let col2 = compared
+    .opts
+    .col2(col1)
+    .ok_or_else(|| format!("column {column} isn't compared with csv2's"))?;
+  Ok((col1, col2))
+}
+
+/// Whether a cell holds a value, as text or as a number.
+fn holds(cell: &str, value: &str) -> bool {
+  let (cell, value) = (cell.trim(), value.trim());
+  cell == value
+    || matches!(
+      (parse_float(cell), parse_float(value)),
+      (Some(a), Some(b)) if a == b
+    )
+}
+
+/// The first (0-based) row whose cell in a column holds a value.
+fn find_row(
+  records: &[StringRecord],
+  col: usize,
+  value: &str,
+) -> Option<usize> {
+  records
+    .iter()
+    .position(|r| r.get(col).is_some_and(|cell| holds(cell, value)))
+}
+
+/// Checks that both files have a row of a group.
+fn group_present(
+  compared: &Compared,
+  group: &GroupPresent,
+) -> Result<(), String> {
+  let (col1, col2) = columns(compared, &group.column)?;
+  let (records1, records2) = compared.records;
+  let missing: Vec<&str> = [("csv1", records1, col1), ("csv2", records2, col2)]
+    .into_iter()
+    .filter(|(_, records, col)| find_row(records, *col, &group.value).is_none())
+    .map(|(file, _, _)| file)
+    .collect();
+  match missing.is_empty() {
+    true => Ok(()),
+    false => Err(format!("no row of {} has it", missing.join(" or "))),
+  }
+}
+
+/// Checks that a cell's values agree within its tolerances.
+fn cell_within(compared: &Compared, cell: &CellWithin) -> Result<(), String> {
+  if cell.max_ratio.is_none() && cell.max_diff.is_none() {
+    return Err("it gives neither max_ratio nor max_diff".to_string());
+  }
+  let (key1, key2) = columns(compared, &cell.key)?;
+  let (col1, col2) = columns(compared, &cell.column)?;
+  let (records1, records2) = compared.records;
+  let float_re = &compared.opts.float_re;
+  let (factor1, factor2) = compared.opts.factors(col1);
+  let value = |file: &str, records: &[StringRecord], (key, col), factor| {
+    let row = find_row(records, key, &cell.value)
+      .ok_or_else(|| format!("no row of {file} has {}", cell.value))?;
+    let text = records[row].get(col).unwrap_or_default();
+    if !is_float(text, float_re) {
+      return Err(format!(
+        "line {} of {file} has {text:?} there, not a float",
+        physical_line(records, row)
+      ));
+    }
+    // is_float checked that it parses
+    Ok(compared.opts.value(factor, parse_float(text).unwrap()))
+  };
+  let a = value("csv1", records1, (key1, col1), factor1)?;
+  let b = value("csv2", records2, (key2, col2), factor2)?;
+  let opts = compared.opts;
+  let diff = (a - b).abs();
+  let mut exceeded = Vec::new();
+  if let Some(max) = cell.max_ratio
+    && a != b
+  {
+    let rel = opts.rel_mode.measure(a, b, opts.rel_floor);
+    let percent = opts.rel_mode.percent(rel);
+    if verdict::exceeds_ratio(percent, max) {
+      exceeded.push(format!("differ by {percent:.4}%"));
+    }
+  }
+  if let Some(max) = cell.max_diff
+    && diff > max
+  {
+    exceeded.push(format!("differ by {diff:.2E}"));
+  }
+  match exceeded.is_empty() {
+    true => Ok(()),
+    false => Err(format!("{a:+.6E} and {b:+.6E} {}", exceeded.join(" and "))),
+  }
+}
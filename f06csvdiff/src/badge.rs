@@ -0,0 +1,152 @@
+//! Status badges for dashboards: an SVG badge showing a pair's label, its
+//! status and its worst percent difference, or the same as JSON for a
+//! shields.io endpoint badge. The SVG template is built into the binary.
+
+use serde::Serialize;
+
+use crate::summary::ResultLine;
+use f06csvdiff::junit::escape;
+
+/// The SVG template, with `{name}` placeholders.
+const TEMPLATE: &str = include_str!("badge.svg");
+
+/// About how wide a character of the badge's 11px Verdana is, in pixels.
+const CHAR_WIDTH: usize = 7;
+
+/// The padding on either side of each half's text, in pixels.
+const PADDING: usize = 5;
+
+/// A badge: a label on the left, a message on the right, colored by status.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Badge {
+  /// What the badge is about, e.g. `a.csv vs b.csv`.
+  pub(crate) label: String,
+  /// The status and the worst percent difference, e.g. `PASSED 0.12%`.
+  pub(crate) message: String,
+  /// PASSED, WAIVED or FAILED, as the result line has it.
+  pub(crate) status: &'static str,
+}
+
+/// A shields.io endpoint badge.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Endpoint<'a> {
+  schema_version: u8,
+  label: &'a str,
+  message: &'a str,
+  color: &'a str,
+  is_error: bool,
+}
+
+impl Badge {
+  /// The badge of a result line, under a label.
+  pub(crate) fn new(label: &str, line: &ResultLine) -> Self {
+    let status = line.status();
+    let message = match line.worst_ratio_pct {
+      Some(pct) => format!("{status} {pct:.2}%"),
+      None => status.to_string(),
+    };
+    Self {
+      label: label.to_string(),
+      message,
+      status,
+    }
+  }
+
+  /// The badge's color, as a shields.io name and as SVG.
+  fn color(&self) -> (&'static str, &'static str) {
+    match self.status {
+      "PASSED" => ("brightgreen", "#4c1"),
+      "WAIVED" => ("yellow", "#dfb317"),
+      "WARNED" => ("orange", "#fe7d37"),
+      _ => ("red", "#e05d44"),
+    }
+  }
+
+  /// The badge as an SVG document.
+  pub(crate) fn svg(&self) -> String {
+    let width = |text: &str| text.chars().count() * CHAR_WIDTH + 2 * PADDING;
+    let (label_width, message_width) =
+      (width(&self.label), width(&self.message));
+    render(
+      TEMPLATE,
+      &[
+        ("width", (label_width + message_width).to_string()),
+        ("label_width", label_width.to_string()),
+        ("message_width", message_width.to_string()),
+        ("label_x", (label_width / 2).to_string()),
+        ("message_x", (label_width + message_width / 2).to_string()),
+        ("color", self.color().1.to_string()),
+        ("label", escape(&self.label)),
+        ("message", escape(&self.message)),
+      ],
+    )
+  }
+
+  /// The badge as JSON for a shields.io endpoint badge.
+  pub(crate) fn json(&self) -> String {
+    let endpoint = Endpoint {
+      schema_version: 1,
+      label: &self.label,
+      message: &self.message,
+      color: self.color().0,
+      is_error: self.status == "FAILED",
+    };
+    // a struct of strings always serializes
+    serde_json::to_string(&endpoint).unwrap()
+  }
+}
+
+/// Fills a template's `{name}` placeholders with their values in one pass,
+/// so that a value holding a placeholder's text is left as it is. Unknown
+/// placeholders are kept.
+pub(crate) fn render(template: &str, values: &[(&str, String)]) -> String {
+  let mut rendered = String::with_capacity(template.len());
+  let mut rest = template;
+  while let Some(start) = rest.find('{') {
+    rendered.push_str(&rest[..start]);
+    rest = &rest[start..];
+    let value = rest.find('}').and_then(|end| {
+      let (_, value) =
+        values.iter().find(|(name, _)| *name == &rest[1..end])?;
+      Some((value, end))
+    });
+    match value {
+      Some((value, end)) => {
+        rendered.push_str(value);
+        rest = &rest[end + 1..];
+      }
+      None => {
+        rendered.push('{');
+        rest = &rest[1..];
+      }
+    }
+  }
+  rendered.push_str(rest);
+  rendered
+}
+
+/// The names of the files for badges with these labels, in batch mode:
+/// each label with anything but letters, digits, dots, dashes and
+/// underscores made an underscore, and numbered from the second on when
+/// labels come out the same.
+pub(crate) fn file_names(labels: &[String], extension: &str) -> Vec<String> {
+  let mut names: Vec<String> = Vec::new();
+  for label in labels {
+    let stem: String = label
+      .chars()
+      .map(|c| match c.is_ascii_alphanumeric() || "._-".contains(c) {
+        true => c,
+        false => '_',
+      })
+      .collect();
+    let mut name = format!("{stem}.{extension}");
+    let mut n = 1;
+    while names.contains(&name) {
+      n += 1;
+      name = format!("{stem}-{n}.{extension}");
+    }
+    names.push(name);
+  }
+  names
+}
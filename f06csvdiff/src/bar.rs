@@ -0,0 +1,185 @@
+//! The --progress bar: a line on stderr, redrawn a few times a second by a
+//! thread of its own, showing how far the file being read or the pair being
+//! compared has got, with an estimate of the time left. It's only drawn on a
+//! terminal, so that logs and stdout stay clean.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use f06csvdiff::progress::Progress;
+
+use crate::exit;
+
+/// How often the bar is redrawn.
+const TICK: Duration = Duration::from_millis(200);
+
+/// How many characters wide the bar itself is.
+const BAR_WIDTH: usize = 30;
+
+/// What a stage's progress is counted in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Unit {
+  /// Bytes of a file read.
+  Bytes,
+  /// Rows of a pair compared.
+  Rows,
+}
+
+/// A stage of the work, such as reading a file.
+struct Stage {
+  /// What's being done, e.g. `reading a.csv`.
+  what: String,
+  /// What it's counted in.
+  unit: Unit,
+  /// How much there is to do.
+  total: u64,
+  /// What the counter stood at when it began.
+  base: u64,
+  /// When it began.
+  started: Instant,
+}
+
+/// The bar's state, shared with the thread drawing it.
+struct Bar {
+  /// The counters the work advances.
+  progress: Arc<Progress>,
+  /// The stage under way, if any.
+  stage: Option<Stage>,
+  /// Whether the bar's line is on screen.
+  drawn: bool,
+}
+
+/// The bar, once started.
+static BAR: Mutex<Option<Bar>> = Mutex::new(None);
+
+/// Starts drawing the bar, if stderr is a terminal and not silenced.
+pub(crate) fn start() {
+  if !io::stderr().is_terminal() || exit::silenced() {
+    return;
+  }
+  *lock() = Some(Bar {
+    progress: Arc::default(),
+    stage: None,
+    drawn: false,
+  });
+  // it lasts as long as the process
+  thread::spawn(|| {
+    loop {
+      thread::sleep(TICK);
+      if let Some(bar) = lock().as_mut() {
+        bar.draw();
+      }
+    }
+  });
+}
+
+/// The bar's state, even if a thread panicked holding it.
+fn lock() -> std::sync::MutexGuard<'static, Option<Bar>> {
+  BAR.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The counters to advance, if the bar is drawn.
+pub(crate) fn counter() -> Option<Arc<Progress>> {
+  lock().as_ref().map(|bar| Arc::clone(&bar.progress))
+}
+
+/// Begins a stage, with `total` bytes or rows to go.
+pub(crate) fn begin(what: String, unit: Unit, total: u64) {
+  if let Some(bar) = lock().as_mut() {
+    let base = match unit {
+      Unit::Bytes => bar.progress.bytes(),
+      Unit::Rows => bar.progress.rows(),
+    };
+    bar.stage = Some(Stage {
+      what,
+      unit,
+      total,
+      base,
+      started: Instant::now(),
+    });
+  }
+}
+
+/// Ends the stage under way, taking the bar off the screen.
+pub(crate) fn end() {
+  if let Some(bar) = lock().as_mut() {
+    bar.stage = None;
+    bar.clear();
+  }
+}
+
+/// Takes the bar off the screen until it's next redrawn, so that a message
+/// to stderr gets a line of its own.
+pub(crate) fn interrupt() {
+  // try_lock, since a message may come while the bar is being drawn
+  if let Ok(mut bar) = BAR.try_lock()
+    && let Some(bar) = bar.as_mut()
+  {
+    bar.clear();
+  }
+}
+
+impl Bar {
+  /// Redraws the bar's line for the stage under way.
+  fn draw(&mut self) {
+    let Some(stage) = &self.stage else {
+      return;
+    };
+    let count = match stage.unit {
+      Unit::Bytes => self.progress.bytes(),
+      Unit::Rows => self.progress.rows(),
+    };
+    let line = stage.line(count.saturating_sub(stage.base));
+    let mut stderr = io::stderr().lock();
+    let _ = write!(stderr, "\r\x1b[K{line}");
+    let _ = stderr.flush();
+    self.drawn = true;
+  }
+
+  /// Erases the bar's line, if it's drawn.
+  fn clear(&mut self) {
+    if self.drawn {
+      let mut stderr = io::stderr().lock();
+      let _ = write!(stderr, "\r\x1b[K");
+      let _ = stderr.flush();
+      self.drawn = false;
+    }
+  }
+}
+
+impl Stage {
+  /// The bar's line with `done` bytes or rows of the stage done, e.g.
+  /// `reading a.csv [#########      ]  30% 12.0/40.0 MB, 0:07 left`.
+  fn line(&self, done: u64) -> String {
+    let done = done.min(self.total);
+    let fraction = match self.total {
+      0 => 1.0,
+      total => done as f64 / total as f64,
+    };
+    let filled = (fraction * BAR_WIDTH as f64) as usize;
+    let bar =
+      format!("{}{}", "#".repeat(filled), " ".repeat(BAR_WIDTH - filled));
+    let amounts = match self.unit {
+      Unit::Bytes => {
+        let mb = |bytes: u64| bytes as f64 / 1e6;
+        format!("{:.1}/{:.1} MB", mb(done), mb(self.total))
+      }
+      Unit::Rows => format!("{done}/{} rows", self.total),
+    };
+    let left = match fraction > 0.0 {
+      true => {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let secs = (elapsed * (1.0 - fraction) / fraction).round() as u64;
+        format!("{}:{:02} left", secs / 60, secs % 60)
+      }
+      false => "time left unknown".to_string(),
+    };
+    format!(
+      "{} [{bar}] {:3.0}% {amounts}, {left}",
+      self.what,
+      fraction * 100.0
+    )
+  }
+}
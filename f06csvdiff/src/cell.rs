@@ -0,0 +1,121 @@
+//! How a single cell was compared, in detail, for following up on a report.
+
+use csv::StringRecord;
+
+use crate::compare::{
+  DiffConfig, DiffReport, Skip, compare_alone, compared_values, parse_float,
+  physical_line,
+};
+use crate::error::DiffError;
+
+/// What became of a cell.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+  /// Its column isn't compared, since not all its cells are floats in both
+  /// files.
+  NotCompared,
+  /// It's listed as excluded, and left out.
+  Excluded,
+  /// Both values are zero, which is always skipped.
+  BothZero,
+  /// Its values are below the column's cutoff, as the threshold mode has
+  /// it: which of them are.
+  BelowCutoff(Skip),
+  /// It was compared: this is the report of comparing it alone, so its
+  /// gates are its verdict against each enabled tolerance.
+  Compared(Box<DiffReport>),
+}
+
+/// Everything about one cell of a comparison.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellExplanation {
+  /// The (1-based) physical line of the cell, in csv1.
+  pub line: usize,
+  /// The (1-based) physical line of its counterpart, in csv2.
+  pub line2: usize,
+  /// The (1-based) column.
+  pub col: usize,
+  /// The cell's text in each file.
+  pub text: (String, String),
+  /// The cell's values in each file, if they parse, as compared: with
+  /// clamped subnormals zero, scaled, and csv2's negated if its column was
+  /// sign-flipped.
+  pub values: (Option<f64>, Option<f64>),
+  /// Whether each value is subnormal and was taken as zero.
+  pub clamped: (bool, bool),
+  /// Whether csv2's column was sign-flipped.
+  pub flipped: bool,
+  /// The magnitude both values had to be below to be skipped, if the column
+  /// was compared.
+  pub cutoff: Option<f64>,
+  /// What became of it.
+  pub outcome: Outcome,
+}
+
+/// Explains how the cell at a physical line of csv1 and a (1-based) column
+/// was compared, given the report of comparing the files with `opts`.
+/// Fails with [`DiffError::NoSuchCell`] if no compared row starts on that
+/// line, or it has no such column.
+pub fn explain(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+  report: &DiffReport,
+  (line, col): (usize, usize),
+) -> Result<CellExplanation, DiffError> {
+  let no_such_cell = || DiffError::NoSuchCell { line, col };
+  let row = (0..report.rows.min(records1.len()).min(records2.len()))
+    .find(|&row| physical_line(records1, row) == line)
+    .ok_or_else(no_such_cell)?;
+  let (rec1, rec2) = (&records1[row], &records2[row]);
+  let i = col.checked_sub(1).ok_or_else(no_such_cell)?;
+  let text2 = opts.col2(i).and_then(|j| rec2.get(j));
+  let (Some(text1), Some(text2)) = (rec1.get(i), text2) else {
+    return Err(no_such_cell());
+  };
+
+  let flipped = report.flipped_columns.contains(&i);
+  let stats = report.columns.iter().find(|c| c.col == col);
+  let parsed = parse_float;
+  let clamps = |v: Option<f64>| v.is_some_and(|v| opts.clamps(v));
+  let clamped = (clamps(parsed(text1)), clamps(parsed(text2)));
+  let factors = opts.factors(i);
+  let value = |factor, text: &str| parsed(text).map(|v| opts.value(factor, v));
+  let (v1, v2) = (value(factors.0, text1), value(factors.1, text2));
+  let mut explanation = CellExplanation {
+    line,
+    line2: physical_line(records2, row),
+    col,
+    text: (text1.to_string(), text2.to_string()),
+    values: (v1, v2.map(|v| if flipped { -v } else { v })),
+    clamped,
+    flipped,
+    cutoff: stats.map(|s| s.cutoff),
+    outcome: Outcome::NotCompared,
+  };
+  let (Some(stats), Some(v1), Some(v2)) = (stats, v1, v2) else {
+    return Ok(explanation);
+  };
+  if opts.excludes(line, i) {
+    explanation.outcome = Outcome::Excluded;
+    return Ok(explanation);
+  }
+  let cutoff = (stats.cutoff, opts.threshold_mode);
+  explanation.outcome = match compared_values(v1, v2, flipped, cutoff) {
+    Err(Skip::Zeros) => Outcome::BothZero,
+    Err(skip) => Outcome::BelowCutoff(skip),
+    Ok((a1, a2)) => {
+      let values = match opts.abs_compare.applies(i) {
+        true => (a1.abs(), a2.abs()),
+        false => (a1, a2),
+      };
+      let values = opts.measured(values, (text1, text2), factors);
+      let scale = opts.diff_scale.map(|_| opts.row_scale(rec1).unwrap_or(1.0));
+      let texts = (text1, text2);
+      let alone =
+        compare_alone(values, texts, (line, col), (stats.cutoff, scale), opts);
+      Outcome::Compared(Box::new(alone))
+    }
+  };
+  Ok(explanation)
+}
@@ -0,0 +1,117 @@
+//! What's judged of two files beyond their cells once they're compared:
+//! groups of rows, columns with tolerances of their own, complex pairs, the
+//! files as matrices and a spec's assertions. A report isn't complete
+//! without them, so the engine's report and the reference's are completed
+//! alike, by [`complete`].
+
+use csv::StringRecord;
+
+use crate::assertions::{Assertions, Compared};
+use crate::compare::{DiffConfig, DiffReport};
+use crate::complex::{self, ComplexPair};
+use crate::error::DiffError;
+use crate::verdict::Tolerances;
+use crate::{groups, matrix, overrides};
+
+/// The checks of a comparison beyond its cells. The default makes none.
+#[derive(Clone, Debug, Default)]
+pub struct Checks {
+  /// Compare the groups of rows holding each value in this (0-based)
+  /// column of csv1, and in that of csv2, each on its own.
+  pub group_by: Option<(usize, usize)>,
+  /// Columns judged by tolerances of their own, left out of the comparison.
+  pub overrides: Option<Overrides>,
+  /// Pairs of columns compared as the parts of complex values.
+  pub complex: Vec<ComplexPair>,
+  /// The largest difference allowed a complex pair's phases, in degrees.
+  pub max_phase_deg: Option<f64>,
+  /// Norms of the difference of the files taken as matrices.
+  pub matrix: Option<MatrixNorms>,
+  /// A spec's assertions about the files.
+  pub assertions: Assertions,
+}
+
+/// Columns with tolerances of their own.
+#[derive(Clone, Debug)]
+pub struct Overrides {
+  /// The (0-based) columns of csv1, with their tolerances, gate by gate
+  /// falling back to the others'.
+  pub columns: Vec<(usize, Tolerances)>,
+  /// The configuration before they were left out of it, which pairs them
+  /// with csv2's.
+  pub whole: DiffConfig,
+}
+
+/// Which norms of the difference of two matrices to report, and how much
+/// they may differ.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MatrixNorms {
+  /// Report the max row-sum norm as well as the Frobenius norm.
+  pub row_sum: bool,
+  /// Fail if a norm of the difference exceeds this fraction of the same
+  /// norm of csv1's matrix.
+  pub max_rel: Option<f64>,
+}
+
+/// The records compared, with each file's header if it has one.
+#[derive(Clone, Copy)]
+pub struct Inputs<'a> {
+  /// The records of each file.
+  pub records: (&'a [StringRecord], &'a [StringRecord]),
+  /// The header of each file.
+  pub headers: Option<(&'a StringRecord, &'a StringRecord)>,
+  /// The files' names, for errors.
+  pub files: (&'a str, &'a str),
+}
+
+/// Completes a report of comparing `inputs` under `opts` with `checks`:
+/// its groups, overrides, complex pairs, matrix norms and assertion
+/// failures. Fails as comparing a group, an overridden column or the
+/// matrices fails.
+pub fn complete(
+  report: &mut DiffReport,
+  inputs: Inputs,
+  opts: &DiffConfig,
+  checks: &Checks,
+) -> Result<(), DiffError> {
+  let records = inputs.records;
+  if let Some(cols) = checks.group_by {
+    report.groups = groups::compare_groups(records, cols, opts, inputs.files)?;
+  }
+  if let Some(overridden) = &checks.overrides {
+    report.overrides = overrides::compare_overrides(
+      records,
+      &overridden.columns,
+      &overridden.whole,
+      inputs.files,
+    )?;
+  }
+  if !checks.complex.is_empty() {
+    report.complex = Some(complex::compare_pairs(
+      records,
+      &checks.complex,
+      report.rows,
+      opts,
+      checks.max_phase_deg,
+    ));
+  }
+  if let Some(norms) = checks.matrix {
+    report.matrix = Some(matrix::matrix_norms(
+      records,
+      opts,
+      report,
+      norms.row_sum,
+      norms.max_rel,
+      inputs.files,
+    )?);
+  }
+  if !checks.assertions.is_empty() {
+    report.assertion_failures = checks.assertions.check(&Compared {
+      records,
+      headers: inputs.headers,
+      opts,
+      report,
+    });
+  }
+  Ok(())
+}
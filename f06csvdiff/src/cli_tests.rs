@@ -0,0 +1,2224 @@
+use csv::StringRecord;
+
+use f06csvdiff::compare::{self, DiffConfig};
+use f06csvdiff::verdict::Tolerances;
+
+/// Builds records from rows of cells.
+fn records(rows: &[&[&str]]) -> Vec<StringRecord> {
+  rows
+    .iter()
+    .map(|r| StringRecord::from(r.to_vec()))
+    .collect()
+}
+
+/// Creates a fresh scratch directory for a test, with the given empty files.
+fn scratch_dir(name: &str, files: &[&str]) -> std::path::PathBuf {
+  let dir = std::env::temp_dir()
+    .join(format!("f06csvdiff-{}-{name}", std::process::id()));
+  let _ = std::fs::remove_dir_all(&dir);
+  for file in files {
+    let path = dir.join(file);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, "").unwrap();
+  }
+  dir
+}
+
+#[test]
+fn test_glob_expansion() {
+  use crate::inputs::{Inputs, resolve};
+  let dir = scratch_dir(
+    "globs",
+    &[
+      "a/x.csv",
+      "a/y.csv",
+      "a/only1.csv",
+      "b/x.csv",
+      "b/y.csv",
+      "c/z.csv",
+    ],
+  );
+  for sep in ["/", std::path::MAIN_SEPARATOR_STR] {
+    let pat =
+      |p: &str| format!("{}{sep}{}", dir.display(), p.replace('/', sep));
+    let path =
+      |p: &str| std::path::Path::new(&pat(p)).to_string_lossy().to_string();
+    // no match is an error
+    assert!(resolve(&pat("a/*.txt"), &pat("b/x.csv")).is_err());
+    // a single match is used directly
+    assert_eq!(
+      resolve(&pat("c/*.csv"), &pat("b/x.csv")),
+      Ok(Inputs::Single(path("c/z.csv"), path("b/x.csv")))
+    );
+    // several matches where one is required is an error naming them
+    let err = resolve(&pat("a/*.csv"), &pat("c/z.csv")).unwrap_err();
+    assert!(err.contains("only1.csv") && err.contains("y.csv"));
+    // several matches on both sides pair up by relative path
+    let Ok(Inputs::Batch {
+      pairs,
+      unpaired1,
+      unpaired2,
+    }) = resolve(&pat("a/*.csv"), &pat("b/*.csv"))
+    else {
+      panic!("expected batch mode");
+    };
+    let names = |p: &str| {
+      std::path::Path::new(p)
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string()
+    };
+    let paired: Vec<_> = pairs
+      .iter()
+      .map(|(p1, p2)| (names(p1), names(p2)))
+      .collect();
+    assert_eq!(
+      paired,
+      vec![
+        ("x.csv".to_string(), "x.csv".to_string()),
+        ("y.csv".to_string(), "y.csv".to_string())
+      ]
+    );
+    assert_eq!(unpaired1.len(), 1);
+    assert!(unpaired2.is_empty());
+  }
+  std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_profile_matrix() {
+  use crate::config::Config;
+  use f06csvdiff::table::profile_matrix;
+  let config: Config = toml::from_str(
+    "[profiles.engineering]\nmax_ratio = 0.05\nmax_diff = 1.0\n\
+     [profiles.regression]\nmax_ratio = 0.001\n",
+  )
+  .unwrap();
+  assert!(toml::from_str::<Config>("[profiles.x]\nmax_ration = 1.0").is_err());
+  let names = ["regression".to_string(), "engineering".to_string()];
+  let profiles = config.profiles(&names).unwrap();
+  assert!(config.profiles(&["nope".to_string()]).is_err());
+  let t1 = records(&[&["1.00E+00"]]);
+  let t2 = records(&[&["1.01E+00"]]);
+  let report =
+    compare::compare(&t1, &t2, &DiffConfig::default(), ("a", "b")).unwrap();
+  let (headers, rows) = profile_matrix(&profiles, &report);
+  assert_eq!(
+    headers,
+    ["profile", "ratio", "margin_ratio", "diff", "margin_diff"]
+  );
+  let text = |row: &Vec<f06csvdiff::table::Cell>| {
+    row
+      .iter()
+      .map(|(t, _)| t.as_str())
+      .collect::<Vec<_>>()
+      .join(" ")
+  };
+  assert_eq!(text(&rows[0]), "regression FAIL -0.90 - -");
+  assert_eq!(text(&rows[1]), "engineering PASS +4.00 PASS +9.90E-1");
+}
+
+#[cfg(feature = "mem-stats")]
+#[test]
+fn test_peak_memory() {
+  use crate::memstats;
+  let dir = scratch_dir("memstats", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  // the peak of comparing a pair of generated files of that size
+  let measure = |rows: usize| {
+    let mut paths = Vec::new();
+    for i in 0..2 {
+      let path = dir.join(format!("{rows}-{i}.csv"));
+      let text: String = (0..rows)
+        .map(|r| {
+          let cells: Vec<String> = (0..6)
+            .map(|c| format!("{:.6E}", (r * 6 + c + i) as f64 * 1.1))
+            .collect();
+          cells.join(",") + "\n"
+        })
+        .collect();
+      std::fs::write(&path, text).unwrap();
+      paths.push(path.to_string_lossy().to_string());
+    }
+    let base = memstats::reset_peak();
+    f06csvdiff::compare_files(&paths[0], &paths[1], &DiffConfig::default())
+      .unwrap();
+    memstats::peak().unwrap().saturating_sub(base)
+  };
+  let small = measure(2_000);
+  let large = measure(20_000);
+  assert!(small > 0);
+  // ten times the input takes roughly ten times the memory
+  let growth = large as f64 / small as f64;
+  assert!((4.0..25.0).contains(&growth), "{small} -> {large}");
+  std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_manifest_round_trip() {
+  use crate::inputs::{Inputs, resolve};
+  use crate::manifest::{Manifest, generate, round_up};
+  let dir = scratch_dir(
+    "manifest",
+    &[
+      "a/x.csv",
+      "a/deep/y.csv",
+      "a/only1.csv",
+      "b/x.csv",
+      "b/deep/y.csv",
+    ],
+  );
+  let pat = |p: &str| format!("{}/{p}", dir.display());
+  let Ok(Inputs::Batch {
+    pairs, unpaired1, ..
+  }) = resolve(&pat("a/**/*.csv"), &pat("b/**/*.csv"))
+  else {
+    panic!("expected batch mode");
+  };
+  assert_eq!(pairs.len(), 2);
+  let mut entries: Vec<_> = pairs
+    .iter()
+    .map(|(p1, p2)| (p1.clone(), p2.clone(), None))
+    .collect();
+  // awkward names survive quoting
+  entries.push((r#"we"ird\name.csv"#.to_string(), "b.csv".to_string(), None));
+  let text = generate(&entries, &unpaired1);
+  assert!(text.contains("# max_ratio = \n"));
+  assert!(text.lines().last().unwrap().ends_with("only1.csv"));
+  let manifest: Manifest = toml::from_str(&text).unwrap();
+  let read: Vec<_> = manifest
+    .pair
+    .iter()
+    .map(|e| (e.csv1.clone(), e.csv2.clone(), None))
+    .collect();
+  assert_eq!(read, entries);
+  std::fs::remove_dir_all(dir).unwrap();
+
+  // suggested tolerances parse back and are never below the observed values
+  let suggested = Tolerances {
+    max_ratio: Some(round_up(0.012345)),
+    max_diff: Some(round_up(3.0e-7)),
+    min_digits: None,
+    max_fail_fraction: None,
+    ..Default::default()
+  };
+  let text = generate(&[("a".into(), "b".into(), Some(suggested))], &[]);
+  let manifest: Manifest = toml::from_str(&text).unwrap();
+  assert_eq!(manifest.pair[0].max_ratio, Some(0.013));
+  assert_eq!(manifest.pair[0].max_diff, Some(3.0e-7));
+  for value in [0.012345, 3.0e-7, 99.5, 1.0, 7.77e-12] {
+    assert!(round_up(value) >= value);
+    assert!(round_up(value) <= value * 1.1);
+  }
+  assert_eq!(round_up(0.0), 0.0);
+
+  // under --allclose, a pair's -r and -d are its parts of the combined
+  // tolerance, not gates of their own
+  let entry: crate::manifest::Entry =
+    toml::from_str("csv1 = \"a\"\ncsv2 = \"b\"\nmax_ratio = 0.05\n").unwrap();
+  let combined = Tolerances {
+    atol: Some(1e-3),
+    rtol: Some(0.01),
+    ..Default::default()
+  };
+  let tolerances = entry.tolerances(&combined);
+  assert_eq!(tolerances.allclose(), Some((1e-3, 0.05)));
+  assert_eq!(tolerances.max_ratio, None);
+}
+
+#[test]
+fn test_histogram_rendering() {
+  use f06csvdiff::histogram::{DEFAULT_EDGES, Histogram};
+  let mut histogram = Histogram::new(&DEFAULT_EDGES);
+  // the edges belong to the bins above them
+  for percent in [0.0, 0.0005, 0.001, 0.5, 1.0, 1.0, 50.0, f64::INFINITY] {
+    histogram.add(percent);
+  }
+  assert_eq!(histogram.counts, [2, 1, 0, 1, 2, 1]);
+  let mut out = Vec::new();
+  crate::write_histogram(&mut out, &histogram, 40).unwrap();
+  let expected = "\
+relative differences of the 8 compared cells:
+  <0.001%      2   25.00% ##############
+  0.001-0.01%  1   12.50% #######
+  0.01-0.1%    0    0.00%
+  0.1-1%       1   12.50% #######
+  1-10%        2   25.00% ##############
+  >=10%        1   12.50% #######
+  undefined    1   12.50% #######
+";
+  assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+#[test]
+fn test_positive_scale() {
+  use crate::positive_scale;
+  assert_eq!(positive_scale("1e-3"), Ok(1e-3));
+  for bad in ["0", "-1", "inf", "mm"] {
+    assert!(positive_scale(bad).is_err(), "{bad}");
+  }
+}
+
+#[test]
+fn test_result_line() {
+  use crate::summary::ResultLine;
+  use std::time::Duration;
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.001),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let t1 = records(&[&["1.0E+00", "2.0E+00"], &["4.0E+00", "8.0E+00"]]);
+  let t2 = records(&[&["1.0E+00", "2.5E+00"], &["4.0E+00", "8.0E+00"]]);
+  let compare = |t2: &[StringRecord], opts: &DiffConfig| {
+    compare::compare(&t1, t2, opts, ("a", "b")).unwrap()
+  };
+  let ms = Duration::from_millis;
+  // the keys and their order are frozen: log pipelines grep for them
+  let failed =
+    ResultLine::of_pair(("a.csv", "b.csv"), &compare(&t2, &opts), ms(812));
+  assert_eq!(
+    failed.to_string(),
+    "F06CSVDIFF-RESULT pair=\"a.csv|b.csv\" status=FAILED \
+     worst_ratio_pct=25 worst_diff=5e-1 cells=4 violations=1 elapsed_ms=812"
+  );
+  // without tolerances there are no violations, and without compared
+  // cells no worst differences
+  let text = records(&[&["x", "y"], &["z", "w"]]);
+  let empty =
+    compare::compare(&text, &text, &DiffConfig::default(), ("a", "b")).unwrap();
+  let passed = ResultLine::of_pair(("c d.csv", "\"e\".csv"), &empty, ms(3));
+  assert_eq!(
+    passed.to_string(),
+    r#"F06CSVDIFF-RESULT pair="c d.csv|\"e\".csv" status=PASSED cells=0 elapsed_ms=3"#
+  );
+  let batch = ResultLine::aggregate(&[failed, passed], ms(900));
+  assert_eq!(
+    batch.to_string(),
+    "F06CSVDIFF-RESULT pairs=2 failed=1 status=FAILED worst_ratio_pct=25 \
+     worst_diff=5e-1 cells=4 violations=1 elapsed_ms=900"
+  );
+}
+
+#[test]
+fn test_quiet_result_line() {
+  use crate::summary::ResultLine;
+  use clap::Parser;
+  let line = ResultLine {
+    pair: Some(("a.csv".to_string(), "b.csv".to_string())),
+    ..Default::default()
+  };
+  let stdout = |argv: &[&str]| {
+    let args = crate::Args::try_parse_from(argv).unwrap();
+    let mut stdout = Vec::new();
+    crate::write_result_line(&args, &line, &mut stdout).unwrap();
+    String::from_utf8(stdout).unwrap()
+  };
+  let argv = ["f06csvdiff", "-r", "0.01", "a.csv", "b.csv"];
+  assert!(stdout(&argv).starts_with("F06CSVDIFF-RESULT "));
+  // -q prints nothing at all, the result line included
+  let argv = ["f06csvdiff", "-q", "-r", "0.01", "a.csv", "b.csv"];
+  assert_eq!(stdout(&argv), "");
+  let argv = ["f06csvdiff", "-qq", "-r", "0.01", "a.csv", "b.csv"];
+  assert_eq!(stdout(&argv), "");
+}
+
+#[test]
+fn test_gha_annotations() {
+  use crate::gha::annotations;
+  let t1 = records(&[&["1.0E+00", "5.0E+00"], &["2.0E+00", "1.0E+00"]]);
+  let t2 = records(&[&["1.0E+00", "5.1E+00"], &["2.0E+00", "1.0E+00"]]);
+  let report =
+    compare::compare(&t1, &t2, &DiffConfig::default(), ("a", "b")).unwrap();
+  let tolerances = Tolerances {
+    max_ratio: Some(0.01),
+    max_diff: Some(1.0),
+    min_digits: None,
+    max_fail_fraction: None,
+    ..Default::default()
+  };
+  let found =
+    annotations(("ref.csv", "out,1:2.csv"), &tolerances, &report, None);
+  // percent signs are escaped in messages, so "%25" shows up as "%"
+  assert_eq!(
+    found,
+    [
+      "::error file=out%2C1%3A2.csv,line=1::max ratio 2.00%25 exceeds 1.00%25 \
+      (values +5.000000E0 and +5.100000E0, column 2)"
+    ]
+  );
+  let tolerances = Tolerances {
+    max_ratio: Some(0.05),
+    ..tolerances
+  };
+  let found = annotations(("ref.csv", "out.csv"), &tolerances, &report, None);
+  assert_eq!(found.len(), 1);
+  assert!(found[0].starts_with("::notice file=out.csv::ref.csv vs out.csv"));
+  assert!(found[0].ends_with("max ratio 2.00%25, max abs diff 1.00E-1"));
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_history() {
+  use crate::history::{Filter, History, Query, query};
+  let dir = scratch_dir("sqlite", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let db = dir.join("history.db");
+  let header = StringRecord::from(vec!["id", "VONMISES"]);
+  let t1 = records(&[&["1", "1.0E+00"], &["2", "2.0E+00"]]);
+  let t2 = records(&[&["1", "1.0E+00"], &["2", "2.1E+00"]]);
+  let config = |max_ratio| DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(max_ratio),
+      max_diff: None,
+      min_digits: None,
+      max_fail_fraction: None,
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  // a failing run and a passing one, reopening the database in between
+  for max_ratio in [0.01, 0.1] {
+    let report =
+      compare::compare(&t1, &t2, &config(max_ratio), ("a", "b")).unwrap();
+    let mut history = History::open(&db, "f06csvdiff a b").unwrap();
+    history.record(("a", "b"), &report, Some(&header)).unwrap();
+    history.finish(report.passed()).unwrap();
+  }
+
+  let (headers, rows) =
+    query(&db, Query::Failures, &Filter::default()).unwrap();
+  assert_eq!(headers[..4], ["csv1", "csv2", "runs", "failures"]);
+  assert_eq!(rows.len(), 1);
+  assert_eq!(rows[0][..4], ["a", "b", "2", "1"]);
+
+  let filter = |column: &str, above| Filter {
+    column: Some(column.to_string()),
+    above,
+    limit: None,
+  };
+  // 2.0 vs 2.1 is a relative difference of 4.76%
+  let (_, rows) =
+    query(&db, Query::Exceeded, &filter("VONMISES", 4.5)).unwrap();
+  assert_eq!(rows.len(), 2);
+  assert!(rows.iter().all(|r| r[3] == "2" && r[4] == "VONMISES"));
+  let (_, rows) = query(&db, Query::Exceeded, &filter("2", 5.0)).unwrap();
+  assert!(rows.is_empty());
+  // the id column agrees, so it's never the worst
+  let (_, rows) = query(&db, Query::WorstColumns, &Filter::default()).unwrap();
+  assert_eq!(rows.len(), 2);
+  assert!(rows.iter().all(|r| r[4] == "VONMISES"));
+  std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_column_map_file() {
+  use crate::colmap::load;
+  let dir = scratch_dir("colmap", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let header1 = StringRecord::from(vec!["GRID", "T1", "T2"]);
+  let header2 = StringRecord::from(vec!["GRID", "T2", "T1"]);
+  let map = |text: &str, headers| {
+    let path = dir.join("map.csv");
+    std::fs::write(&path, text).unwrap();
+    load(&path, headers)
+  };
+  let headers = Some((&header1, &header2));
+  let pairs = |text| map(text, headers).map(|m| m.pairs().collect::<Vec<_>>());
+  // by name or number, with comments and spaces
+  assert_eq!(
+    pairs("# new order\nT1, T1\n3,2\n"),
+    Ok(vec![(1, 2), (2, 1)])
+  );
+  assert!(pairs("T1,T3\n").unwrap_err().contains("no column named T3"));
+  assert!(pairs("0,1\n").unwrap_err().contains("start at 1"));
+  assert!(pairs("2,2\n2,3\n").unwrap_err().contains("paired twice"));
+  assert!(pairs("1,2,3\n").unwrap_err().contains("two columns"));
+  assert!(map("T1,T1\n", None).unwrap_err().contains("--has-headers"));
+}
+
+#[test]
+fn test_waivers() {
+  use crate::summary::ResultLine;
+  use f06csvdiff::junit::{TestCase, testsuite};
+  use f06csvdiff::waivers::{Waivers, civil_date};
+  use std::time::Duration;
+  use toml::value::Date;
+  let date = |year, month, day| Date { year, month, day };
+  assert_eq!(civil_date(0), date(1970, 1, 1));
+  assert_eq!(civil_date(11_016), date(2000, 2, 29));
+  assert_eq!(civil_date(20_742), date(2026, 10, 16));
+  assert_eq!(civil_date(-1), date(1969, 12, 31));
+
+  let dir = scratch_dir("waivers", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let load = |text: &str| {
+    let path = dir.join("waivers.toml");
+    std::fs::write(&path, text).unwrap();
+    Waivers::load(&path).map(|w| w.waiver)
+  };
+  let waivers = load(
+    r#"
+[[waiver]]
+pair = "sol101.csv"
+reason = "new element formulation"
+ticket = "NAS-1"
+expires = 2026-12-31
+
+[[waiver]]
+pair = "a/x.csv|b/x.csv"
+column = 3
+reason = "T3 drifts"
+ticket = "NAS-2"
+expires = 2026-10-16
+"#,
+  )
+  .unwrap();
+  let (whole, column) = (&waivers[0], &waivers[1]);
+  // a pair is named by either file, by path or name, or by both paths
+  assert!(whole.covers(("ref/sol101.csv", "new/sol101.csv")));
+  assert!(whole.covers(("sol101.csv", "other.csv")));
+  assert!(!whole.covers(("ref/sol102.csv", "new/sol102.csv")));
+  assert!(column.covers(("a/x.csv", "b/x.csv")));
+  assert!(!column.covers(("b/x.csv", "a/x.csv")));
+  // a waiver applies through its expiry date
+  assert!(!column.expired(date(2026, 10, 16)));
+  assert!(column.expired(date(2026, 10, 17)));
+  assert_eq!(
+    column.describe(),
+    "NAS-2 on column 3 (T3 drifts), until 2026-10-16"
+  );
+  for (bad, found) in [
+    (
+      "reason = \"r\"\nticket = \"T\"\nexpires = 2026-01-01",
+      "no pair",
+    ),
+    (
+      "column = 0\nreason = \"r\"\nticket = \"T\"\nexpires = 2026-01-01",
+      "start at 1",
+    ),
+    (
+      "column = 1\nreason = \"r\"\nticket = \"T\"\nexpires = 2026-01-01T00:00:00",
+      "on a date",
+    ),
+    ("column = 1\nticket = \"T\"\nexpires = 2026-01-01", "reason"),
+  ] {
+    let err = load(&format!("[[waiver]]\n{bad}")).unwrap_err();
+    assert!(err.contains(found), "{err}");
+  }
+
+  // waived pairs and batches have a status of their own
+  let line = |passed, waivers: &[&str]| ResultLine {
+    pair: Some(("a".to_string(), "b".to_string())),
+    passed,
+    waived: usize::from(!waivers.is_empty()),
+    waivers: waivers.iter().map(|t| t.to_string()).collect(),
+    ..Default::default()
+  };
+  let waived = line(false, &["NAS-1", "NAS-2"]);
+  assert_eq!(
+    waived.to_string(),
+    "F06CSVDIFF-RESULT pair=\"a|b\" status=WAIVED waivers=NAS-1,NAS-2 \
+     elapsed_ms=0"
+  );
+  let batch = |lines: &[ResultLine]| {
+    ResultLine::aggregate(lines, Duration::ZERO).to_string()
+  };
+  assert_eq!(
+    batch(&[waived.clone(), line(true, &[]), line(false, &["NAS-2"])]),
+    "F06CSVDIFF-RESULT pairs=3 failed=0 waived=2 status=WAIVED \
+     waivers=NAS-1,NAS-2 elapsed_ms=0"
+  );
+  assert_eq!(
+    batch(&[waived, line(false, &[])]),
+    "F06CSVDIFF-RESULT pairs=2 failed=1 waived=1 status=FAILED \
+     waivers=NAS-1,NAS-2 elapsed_ms=0"
+  );
+
+  // JUnit reports waived failures as skipped
+  let xml = testsuite(
+    "a vs b",
+    &[],
+    &[TestCase {
+      name: "diff".to_string(),
+      failure: Some("max diff 1".to_string()),
+      waiver: Some(whole.describe()),
+      warning: None,
+    }],
+  );
+  assert!(xml.contains("failures=\"0\" errors=\"0\" skipped=\"1\""));
+  assert!(xml.contains(
+    "<skipped message=\"waived by NAS-1 (new element formulation), until \
+     2026-12-31: max diff 1\"/>"
+  ));
+}
+
+#[test]
+fn test_triage() {
+  use crate::triage::{order, suspicion};
+  let dir = scratch_dir("triage", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let file = |name: &str, text: &str| {
+    let path = dir.join(name);
+    std::fs::write(&path, text).unwrap();
+    path.to_string_lossy().to_string()
+  };
+  let same = file("same.csv", "1,1.0E+00\n2,2.0E+00\n");
+  let close = file("close.csv", "1,1.0E+00\n2,2.0E+00 \n");
+  let far = file("far.csv", "1,1.0E+00\n2,2.5E+00\n");
+  let longer = file("longer.csv", "1,1.0E+00\n2,2.0E+00\n3,3.0E+00\n");
+  let missing = dir.join("missing.csv").to_string_lossy().to_string();
+  let of = |other: &str| suspicion((&same, other), b',');
+  assert_eq!(of(&same), 0.0);
+  // only the sizes differ, by a byte in 21
+  assert_eq!(of(&close), 1.0 / 21.0);
+  assert_eq!(of(&far), 0.2);
+  assert_eq!(of(&longer), f64::INFINITY);
+  assert_eq!(of(&missing), f64::INFINITY);
+  // most suspicious first, ties in their given order
+  assert_eq!(order(&[0.0, 0.2, f64::INFINITY, 0.0, 0.2]), [2, 1, 4, 0, 3]);
+}
+
+#[test]
+fn test_config_defaults() {
+  use clap::{CommandFactory, FromArgMatches};
+  let config: crate::config::Config = toml::from_str(
+    "[defaults]\nmax_ratio = 0.01\nthreshold = 1e-9\nformat = \"junit\"\n\n\
+     [columns.Stress]\nmax_diff = 0.5\n",
+  )
+  .unwrap();
+  let merged = |argv: &[&str]| {
+    let argv = ["f06csvdiff", "a.csv", "b.csv"].iter().chain(argv);
+    let matches = crate::Args::command().get_matches_from(argv);
+    let mut args = crate::Args::from_arg_matches(&matches).unwrap();
+    let taken = crate::apply_defaults(&mut args, &matches, &config.defaults);
+    (args, taken.unwrap())
+  };
+  let (args, taken) = merged(&[]);
+  assert_eq!(args.max_ratio, Some(0.01));
+  assert_eq!(
+    (args.comparison.threshold, args.format),
+    (1e-9, crate::Format::Junit)
+  );
+  assert_eq!(taken, ["max_ratio", "threshold", "format"]);
+  // the command line wins, even when it gives the built-in default
+  let (args, taken) = merged(&["-r", "0.05", "-t", "0"]);
+  assert_eq!(
+    (args.max_ratio, args.comparison.threshold),
+    (Some(0.05), 0.0)
+  );
+  assert_eq!(taken, ["format"]);
+  assert_eq!(config.columns["Stress"].max_diff, Some(0.5));
+
+  // typos are errors
+  let typo =
+    toml::from_str::<crate::config::Config>("[defaults]\nmax_ratoi = 1");
+  assert!(typo.unwrap_err().to_string().contains("max_ratoi"));
+  let bad: crate::config::Config =
+    toml::from_str("[defaults]\nformat = \"xml\"").unwrap();
+  let matches = crate::Args::command().get_matches_from(["x", "a", "b"]);
+  let mut args = crate::Args::from_arg_matches(&matches).unwrap();
+  assert!(crate::apply_defaults(&mut args, &matches, &bad.defaults).is_err());
+}
+
+#[test]
+fn test_badges() {
+  use crate::badge::{Badge, file_names, render};
+  use crate::summary::ResultLine;
+  let line = ResultLine {
+    passed: false,
+    worst_ratio_pct: Some(12.345),
+    ..Default::default()
+  };
+  let badge = Badge::new("<a&b>.csv vs \"c\".csv", &line);
+  assert_eq!(badge.message, "FAILED 12.35%");
+  let svg = badge.svg();
+  assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+  assert!(svg.contains(">&lt;a&amp;b&gt;.csv vs &quot;c&quot;.csv</text>"));
+  assert!(svg.contains("fill=\"#e05d44\""));
+  assert!(!svg.contains('{'));
+
+  let json: serde_json::Value = serde_json::from_str(&badge.json()).unwrap();
+  assert_eq!(json["schemaVersion"], 1);
+  assert_eq!(json["label"], "<a&b>.csv vs \"c\".csv");
+  assert_eq!(json["message"], "FAILED 12.35%");
+  assert_eq!(json["color"], "red");
+  assert_eq!(json["isError"], true);
+  let waived = ResultLine {
+    waived: 1,
+    ..line.clone()
+  };
+  let nothing = ResultLine {
+    passed: true,
+    ..Default::default()
+  };
+  assert_eq!(Badge::new("x", &waived).status, "WAIVED");
+  assert_eq!(Badge::new("x", &nothing).message, "PASSED");
+
+  // values are inserted once, even when they look like placeholders
+  let values = [("a", "{b}".to_string()), ("b", "B".to_string())];
+  assert_eq!(render("{a} {b} {c} {", &values), "{b} B {c} {");
+  let labels = ["a.csv vs a.csv", "a.csv vs a.csv", "x/y"].map(String::from);
+  assert_eq!(
+    file_names(&labels, "svg"),
+    ["a.csv_vs_a.csv.svg", "a.csv_vs_a.csv-2.svg", "x_y.svg"]
+  );
+}
+
+#[test]
+fn test_warning_tier() {
+  use crate::summary::ResultLine;
+  use f06csvdiff::junit;
+  use std::time::Duration;
+
+  let r1 = records(&[&["1.000E+00", "2.000E+00"], &["3.000E+00", "4.000E+00"]]);
+  let r2 = records(&[&["1.007E+00", "2.000E+00"], &["3.000E+00", "4.000E+00"]]);
+  let compare = |max_ratio| {
+    let opts = DiffConfig {
+      tolerances: Tolerances {
+        max_ratio: Some(max_ratio),
+        ..Default::default()
+      },
+      warn_tolerances: Tolerances {
+        max_ratio: Some(0.001),
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+    compare::compare(&r1, &r2, &opts, ("a", "b")).unwrap()
+  };
+  let line = |report| ResultLine::of_pair(("a", "b"), report, Duration::ZERO);
+
+  // a difference between the tolerances warns, and only warns
+  let warned = compare(0.01);
+  assert!(warned.passed() && warned.warned());
+  assert_eq!(line(&warned).status(), "WARNED");
+  let tolerances = Tolerances::default();
+  let files = ("a.csv", "b.csv");
+  let cases = junit::cases(&[], &tolerances, (&warned, &[]), files, None);
+  let warning = cases.iter().find(|c| c.name == "warn.ratio").unwrap();
+  assert!(warning.failure.is_none());
+  assert!(
+    warning
+      .warning
+      .as_ref()
+      .unwrap()
+      .contains("line 1, column 1")
+  );
+  let xml = junit::testsuite("a vs b", &[], &cases);
+  assert!(xml.contains("failures=\"0\"") && xml.contains("<system-out>"));
+
+  // past the hard tolerance, it fails, whatever the warnings say
+  let failed = compare(0.005);
+  assert!(!failed.passed() && !failed.warned());
+  assert_eq!(line(&failed).status(), "FAILED");
+
+  // a batch with a warned pair warns as a whole
+  let passed = ResultLine {
+    passed: true,
+    ..Default::default()
+  };
+  let batch = ResultLine::aggregate(&[passed, line(&warned)], Duration::ZERO);
+  assert_eq!(batch.status(), "WARNED");
+}
+
+#[test]
+fn test_reproduction() {
+  use crate::repro::{self, Shell};
+  use clap::{CommandFactory, Parser};
+  use std::process::Command;
+
+  // quoting that survives each shell
+  assert_eq!(Shell::Posix.quote("a b's"), r"'a b'\''s'");
+  assert_eq!(Shell::PowerShell.quote("a b's"), "'a b''s'");
+  assert_eq!(Shell::Posix.quote("-r0.01"), "-r0.01");
+  let program = r"C:\Program Files\f06csvdiff.exe";
+  let command = Shell::PowerShell.command(program, &["x y".to_string()]);
+  assert_eq!(command, r"& 'C:\Program Files\f06csvdiff.exe' 'x y'");
+
+  // the options given, less those writing elsewhere and those the new scope
+  // replaces, then the new scope and the pair
+  let cli = crate::Args::command();
+  let argv: Vec<String> = [
+    "f06csvdiff",
+    "-qo",
+    "out.txt",
+    "--rows=1-9",
+    "--has-headers",
+    "-r",
+    "0.01",
+    "--columns",
+    "x",
+    "a*.csv",
+    "b*.csv",
+  ]
+  .map(str::to_string)
+  .to_vec();
+  let scope = ["--rows".to_string(), "3".to_string()];
+  let arguments =
+    repro::arguments(&cli, &argv, ("a1.csv", "b1.csv"), None, &scope);
+  assert_eq!(
+    arguments,
+    [
+      "-q",
+      "--has-headers",
+      "-r",
+      "0.01",
+      "--columns",
+      "x",
+      "--rows",
+      "3",
+      "a1.csv",
+      "b1.csv"
+    ]
+  );
+  // a manifest's tolerances replace those given
+  let tolerances = Tolerances {
+    max_diff: Some(0.5),
+    ..Default::default()
+  };
+  let argv =
+    ["f06csvdiff", "-r", "0.01", "--manifest", "m.toml"].map(str::to_string);
+  let arguments =
+    repro::arguments(&cli, &argv, ("-a.csv", "b.csv"), Some(&tolerances), &[]);
+  assert_eq!(arguments, ["-d", "0.5", "--", "-a.csv", "b.csv"]);
+  // and so do the parts of its combined tolerance
+  let tolerances = Tolerances {
+    atol: Some(0.5),
+    rtol: Some(0.01),
+    ..Default::default()
+  };
+  let argv = [
+    "f06csvdiff",
+    "-r",
+    "0.2",
+    "--allclose",
+    "--manifest",
+    "m.toml",
+  ]
+  .map(str::to_string);
+  let arguments =
+    repro::arguments(&cli, &argv, ("a.csv", "b.csv"), Some(&tolerances), &[]);
+  assert_eq!(
+    arguments,
+    ["-r", "0.01", "-d", "0.5", "--allclose", "a.csv", "b.csv"]
+  );
+
+  // an option whose value must follow an = leaves the next argument be,
+  // so the reproduction parses back with the same inputs
+  let optional: Vec<_> = cli
+    .get_arguments()
+    .filter(|arg| arg.is_require_equals_set())
+    .filter_map(|arg| arg.get_long())
+    .collect();
+  assert!(optional.contains(&"list-failures"));
+  for flag in optional {
+    let argv = [
+      "f06csvdiff",
+      "--has-headers",
+      "-r",
+      "0.01",
+      &format!("--{flag}"),
+      "big1.csv",
+      "big2.csv",
+    ]
+    .map(str::to_string);
+    let arguments =
+      repro::arguments(&cli, &argv, ("big1.csv", "big2.csv"), None, &[]);
+    let reproduced: Vec<&str> = ["f06csvdiff"]
+      .into_iter()
+      .chain(arguments.iter().map(String::as_str))
+      .collect();
+    let args = crate::Args::try_parse_from(&reproduced)
+      .unwrap_or_else(|e| panic!("--{flag}: {e}"));
+    assert_eq!(args.csv1.as_deref(), Some("big1.csv"), "--{flag}");
+    assert_eq!(args.csv2.as_deref(), Some("big2.csv"), "--{flag}");
+  }
+
+  // the command printed, run, reproduces the worst cell on its own
+  let dir = scratch_dir("repro", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let (csv1, csv2) = (dir.join("it's a.csv"), dir.join("b.csv"));
+  std::fs::write(&csv1, "id,x,y\n1,1.0E+00,2.0E+00\n2,3.0E+00,4.0E+00\n")
+    .unwrap();
+  std::fs::write(&csv2, "id,x,y\n1,1.1E+00,2.0E+00\n2,3.0E+00,5.0E+00\n")
+    .unwrap();
+  let (csv1, csv2) = (csv1.to_str().unwrap(), csv2.to_str().unwrap());
+  let compare = |argv: &[String]| {
+    let args = crate::Args::try_parse_from(argv).unwrap();
+    let tolerances = Tolerances {
+      max_ratio: args.max_ratio,
+      ..Default::default()
+    };
+    let pair = (args.csv1.clone().unwrap(), args.csv2.clone().unwrap());
+    let (report, _) = crate::run(
+      &args,
+      &[],
+      &tolerances,
+      (&Default::default(), &Default::default()),
+      (&[], None),
+      crate::Painter::new(crate::ColorChoice::Never, false),
+      &mut Vec::new(),
+      &mut None,
+      None,
+      &pair.0,
+      &pair.1,
+      &mut Default::default(),
+    )
+    .unwrap();
+    (args, tolerances, report)
+  };
+  let argv = ["f06csvdiff", "-r", "0.01", "--has-headers", csv1, csv2];
+  let argv = argv.map(str::to_string);
+  let (args, tolerances, report) = compare(&argv);
+  assert!(!report.passed());
+  let header = StringRecord::from(vec!["id", "x", "y"]);
+  let command = crate::reproduction(
+    &args,
+    &argv,
+    (csv1, csv2),
+    &tolerances,
+    (&report, Some(&header)),
+  )
+  .unwrap();
+  assert!(command.contains("--rows 3 --columns y --explain-cell 3:y"));
+  // as the shell splits it
+  if cfg!(unix) {
+    let echo = format!("printf '%s\\n' {command}");
+    let output = Command::new("sh").args(["-c", &echo]).output().unwrap();
+    let split = String::from_utf8(output.stdout).unwrap();
+    let argv: Vec<String> = split.lines().map(str::to_string).collect();
+    let (_, _, narrowed) = compare(&argv);
+    assert!(!narrowed.passed());
+    assert_eq!(narrowed.compared(), 1);
+    assert_eq!(narrowed.max_ratio, report.max_ratio);
+  }
+
+  // normalized by the whole columns, the cell fails; normalized by itself,
+  // it would pass, so the reproduction keeps every row
+  std::fs::write(csv1, "id,x\n1,1.0E+00\n2,2.0E+00\n").unwrap();
+  std::fs::write(csv2, "id,x\n1,1.0E+00\n2,2.07E+00\n").unwrap();
+  let argv = [
+    "f06csvdiff",
+    "-r",
+    "0.01",
+    "--has-headers",
+    "--normalize-columns",
+    "max",
+    csv1,
+    csv2,
+  ];
+  let argv = argv.map(str::to_string);
+  let (args, tolerances, report) = compare(&argv);
+  assert!(!report.passed());
+  let command = crate::reproduction(
+    &args,
+    &argv,
+    (csv1, csv2),
+    &tolerances,
+    (&report, Some(&header)),
+  )
+  .unwrap();
+  assert!(command.contains("--columns x --explain-cell 2:x"));
+  assert!(!command.contains("--rows"));
+  if cfg!(unix) {
+    let echo = format!("printf '%s\\n' {command}");
+    let output = Command::new("sh").args(["-c", &echo]).output().unwrap();
+    let split = String::from_utf8(output.stdout).unwrap();
+    let argv: Vec<String> = split.lines().map(str::to_string).collect();
+    let (_, _, reproduced) = compare(&argv);
+    assert!(!reproduced.passed());
+    assert_eq!(reproduced.max_ratio, report.max_ratio);
+  }
+}
+
+#[test]
+fn test_max_percent() {
+  use clap::Parser;
+  use clap::error::ErrorKind;
+  let parse = |argv: &[&str]| {
+    let argv = ["f06csvdiff"].iter().chain(argv);
+    crate::Args::try_parse_from(argv)
+  };
+  // -r and --max-percent are the same tolerance, given one way or the other
+  let conflict = parse(&["-r", "0.01", "--max-percent", "1", "a", "b"]);
+  assert!(conflict.is_err_and(|e| e.kind() == ErrorKind::ArgumentConflict));
+  let mut args = parse(&["--max-percent", "1", "a", "b"]).unwrap();
+  crate::percent_to_ratio(&mut args);
+  assert_eq!(args.max_ratio, Some(0.01));
+
+  // --explain gives each tolerance beside what was observed
+  let dir = scratch_dir("max_percent", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let (csv1, csv2) = (dir.join("a.csv"), dir.join("b.csv"));
+  std::fs::write(&csv1, "1.0E+00,2.0E+00\n").unwrap();
+  std::fs::write(&csv2, "1.1E+00,2.0E+00\n").unwrap();
+  let (csv1, csv2) = (csv1.to_str().unwrap(), csv2.to_str().unwrap());
+  let argv = ["--max-percent", "1", "-d", "0.5", "--explain", csv1, csv2];
+  let Ok(args) = &mut parse(&argv) else {
+    panic!("bad arguments");
+  };
+  crate::percent_to_ratio(args);
+  let tolerances = Tolerances {
+    max_ratio: args.max_ratio,
+    max_diff: args.max_diff,
+    ..Default::default()
+  };
+  let mut out = Vec::new();
+  let (report, _) = crate::run(
+    args,
+    &[],
+    &tolerances,
+    (&Default::default(), &Default::default()),
+    (&[], None),
+    crate::Painter::new(crate::ColorChoice::Never, false),
+    &mut out,
+    &mut None,
+    None,
+    csv1,
+    csv2,
+    &mut Default::default(),
+  )
+  .unwrap();
+  assert!(!report.passed());
+  let out = String::from_utf8(out).unwrap();
+  assert!(
+    out.contains("maximum percent difference allowed: 1.00%, observed: 10.00%")
+  );
+  assert!(out.contains(
+    "maximum absolute difference allowed: 5.00E-1, observed: 1.00E-1"
+  ));
+}
+
+#[test]
+fn test_segments() {
+  use clap::Parser;
+  let dir = scratch_dir("segments", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let (csv1, csv2) = (dir.join("a.csv"), dir.join("b.csv"));
+  std::fs::write(&csv1, "EID,SXX\n1,1.0E+00\n2,2.0E+00\n").unwrap();
+  std::fs::write(&csv2, "EID,SXX\n1,1.0E+00\n2,2.2E+00\n").unwrap();
+  let (csv1, csv2) = (csv1.to_str().unwrap(), csv2.to_str().unwrap());
+  let explained = |flags: &[&str]| {
+    let argv = ["f06csvdiff", "-r", "0.01", "--has-headers", "--explain"];
+    let argv = argv.iter().chain(flags);
+    let args = crate::Args::try_parse_from(argv.chain(&[csv1, csv2])).unwrap();
+    let mut out = Vec::new();
+    crate::run(
+      &args,
+      &[],
+      &Tolerances {
+        max_ratio: args.max_ratio,
+        ..Default::default()
+      },
+      (&Default::default(), &Default::default()),
+      (&[], None),
+      crate::Painter::new(crate::ColorChoice::Never, false),
+      &mut out,
+      &mut None,
+      None,
+      csv1,
+      csv2,
+      &mut Default::default(),
+    )
+    .unwrap();
+    String::from_utf8(out).unwrap()
+  };
+  // only asked for, and by the columns' names
+  assert!(!explained(&[]).contains("where the worst differences occur"));
+  let out = explained(&["--segments=2"]);
+  assert!(
+    out.contains("column 2 (SXX): [▁█] segment 2/2 (line 3)"),
+    "{out}"
+  );
+}
+
+#[test]
+fn test_fail_only_high_impact() {
+  use crate::config::Config;
+  use crate::summary::ResultLine;
+  use clap::Parser;
+  use std::time::Duration;
+  // column 1's violation is below its significance; column 2, with a
+  // tolerance of its own, has none
+  let dir = scratch_dir("high_impact", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+  let (csv1, csv2, toml) = (path("a.csv"), path("b.csv"), path("f.toml"));
+  std::fs::write(&csv1, "4.0E+01,1.0E+00\n6.0E+01,2.0E+00\n").unwrap();
+  std::fs::write(&csv2, "4.4E+01,1.0E+00\n6.0E+01,2.5E+00\n").unwrap();
+  let spec =
+    "[columns.1]\nsignificance = 50.0\n\n[columns.2]\nmax_ratio = 0.5\n";
+  std::fs::write(&toml, spec).unwrap();
+  let config = Config::load(std::path::Path::new(&toml)).unwrap();
+  let tolerances = Tolerances {
+    max_ratio: Some(0.01),
+    ..Default::default()
+  };
+  let compare = |high_only: bool| {
+    let mut argv = vec!["f06csvdiff", "-r", "0.01", &csv1, &csv2];
+    if high_only {
+      argv.insert(1, "--fail-only-high-impact");
+    }
+    let Ok(args) = crate::Args::try_parse_from(argv) else {
+      panic!("bad arguments");
+    };
+    let mut out = Vec::new();
+    let (report, _) = crate::run(
+      &args,
+      &[],
+      &tolerances,
+      (&Default::default(), &config.columns),
+      (&[], None),
+      crate::Painter::new(crate::ColorChoice::Never, false),
+      &mut out,
+      &mut None,
+      None,
+      &csv1,
+      &csv2,
+      &mut Default::default(),
+    )
+    .unwrap();
+    (report, String::from_utf8(out).unwrap())
+  };
+  let (report, out) = compare(false);
+  assert!(!report.passed());
+  assert!(out.contains("  HIGH-IMPACT: 0 of 3 cells exceed a tolerance"));
+  assert!(out.contains(
+    "  LOW-IMPACT: 1 of 1 cells exceed a tolerance, worst 10.00% (line 1, \
+     column 1)"
+  ));
+  let line = ResultLine::of_pair(("a", "b"), &report, Duration::ZERO);
+  assert!(line.to_string().contains(" high_impact=0 low_impact=1 "));
+
+  let (report, _) = compare(true);
+  assert!(report.passed());
+  assert_eq!(report.impact_classes().unwrap().low.failing_cells.cells, 1);
+}
+
+#[test]
+fn test_exclusions() {
+  use crate::exclusions::Exclusions;
+  let dir = scratch_dir("exclusions", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let load = |text: &str| {
+    let path = dir.join("exclusions.toml");
+    std::fs::write(&path, text).unwrap();
+    Exclusions::load(&path)
+  };
+  let exclusions = load(
+    r#"
+key_column = "GRID"
+
+[[cell]]
+line = 2
+column = 3
+comment = "singular stress"
+
+[[cell]]
+key = "7"
+column = "SXX"
+expires = "NAS-3"
+
+[[cell]]
+pair = "other.csv"
+line = 2
+column = 2
+
+[[cell]]
+line = 40
+column = 2
+"#,
+  )
+  .unwrap();
+  let header = StringRecord::from(vec!["GRID", "SXX", "SYY"]);
+  let records1 = records(&[
+    &["7", "1.0E+00", "2.0E+00"],
+    &["8", "1.0E+00", "2.0E+00"],
+    &["7", "1.0E+00", "2.0E+00"],
+  ]);
+  let paths = ("ref/sol101.csv", "new/sol101.csv");
+  // a key names every row holding it, and a line or key csv1 doesn't have
+  // names no cell; exclusions for other pairs don't apply
+  assert_eq!(
+    exclusions.resolve(paths, &records1, Some(&header)),
+    Ok(vec![
+      (0, vec![(2, 2)]),
+      (1, vec![(1, 1), (3, 1)]),
+      (3, vec![]),
+    ])
+  );
+  assert!(exclusions.resolve(paths, &records1, None).is_err());
+  assert_eq!(
+    exclusions.cell[0].describe(),
+    "line 2, column 3 (singular stress)"
+  );
+  assert_eq!(
+    exclusions.cell[1].describe(),
+    "key 7, column SXX (expires NAS-3)"
+  );
+
+  for (bad, found) in [
+    ("[[cell]]\ncolumn = 1", "either a line or a key"),
+    (
+      "[[cell]]\nline = 1\nkey = \"7\"\ncolumn = 1",
+      "either a line or a key",
+    ),
+    ("[[cell]]\nline = 0\ncolumn = 1", "lines start at 1"),
+    ("[[cell]]\nline = 1\ncolumn = 0", "columns start at 1"),
+    ("[[cell]]\nkey = \"7\"\ncolumn = 1", "needs a key_column"),
+  ] {
+    let err = load(bad).unwrap_err();
+    assert!(err.contains(found), "{err}");
+  }
+}
+
+#[test]
+fn test_labels() {
+  use clap::Parser;
+  let (csv1, csv2) = ("ref/output.csv", "tmp/output.csv");
+  let names = |argv: &[&str]| {
+    let argv = ["f06csvdiff"].iter().chain(argv);
+    let args = crate::Args::try_parse_from(argv).unwrap();
+    crate::report_names(&args, (csv1, csv2))
+  };
+  // files of the same name are told apart by their directories
+  assert_eq!(
+    names(&[csv1, csv2]),
+    ("ref/output.csv".into(), "tmp/output.csv".into())
+  );
+  assert_eq!(
+    crate::distinguishing_names("a/x/r.csv", "b/x/r.csv"),
+    ("a/x/r.csv".into(), "b/x/r.csv".into())
+  );
+  assert_eq!(
+    crate::distinguishing_names("r.csv", "r.csv"),
+    ("r.csv".into(), "r.csv".into())
+  );
+  assert_eq!(
+    names(&["--full-paths", csv1, csv2]),
+    (csv1.to_string(), csv2.to_string())
+  );
+  // a label names its file whether or not --full-paths is given
+  assert_eq!(
+    names(&["--label2", "candidate run", "--full-paths", csv1, csv2]),
+    (csv1.to_string(), "candidate run".to_string())
+  );
+  // the single-line report quotes names that would split into fields
+  assert_eq!(crate::plain_name("output.csv"), "output.csv");
+  assert_eq!(crate::plain_name("candidate run"), "\"candidate run\"");
+  assert_eq!(crate::plain_name("say \"x\""), "\"say \\\"x\\\"\"");
+}
+
+#[test]
+fn test_report_precision() {
+  use f06csvdiff::color::Painter;
+  use f06csvdiff::compare::Extremum;
+  use f06csvdiff::table::{Alignment, Precision, align_table, aligned_row};
+  let precision = |values, percent| Precision { values, percent };
+  // the defaults are those the report always had
+  assert_eq!(precision(6, 2).value(123.456789), "+1.234568E2");
+  assert_eq!(precision(6, 2).percent(3.41237), "3.41");
+  assert_eq!(precision(2, 4).value(-0.001234), "-1.23E-3");
+  assert_eq!(precision(2, 4).percent(3.41237), "3.4124");
+  // zero at any precision, even none
+  assert_eq!(precision(0, 0).value(0.0), "+0E0");
+  assert_eq!(precision(0, 0).percent(0.0), "0");
+  assert_eq!(precision(3, 3).value(0.0), "+0.000E0");
+
+  // the columns are as wide as the values at the precision asked for
+  let worst = Extremum {
+    value: 0.5,
+    vals: (1.0, 1.5),
+    line: 3,
+    col: 2,
+  };
+  let (headers, row) = aligned_row(
+    precision(12, 6),
+    (("a.csv", "b.csv"), None),
+    Some((worst, 33.333333333, false)),
+    None,
+    None,
+    None,
+    (4, Default::default()),
+    None,
+  );
+  assert_eq!(row[2].0, "33.333333");
+  assert_eq!(row[3].0, "+1.000000000000E0");
+  let table = align_table(
+    &headers,
+    &[row],
+    &Alignment::Left,
+    None,
+    true,
+    &Painter::default(),
+  );
+  let lines: Vec<&str> = table.lines().collect();
+  assert_eq!(lines[0].find("val2_r"), lines[1].find("+1.500000000000E0"));
+
+  // with a header, the worst cell's column is given by name
+  let header = StringRecord::from(vec!["EID", "SXX"]);
+  let (_, row) = aligned_row(
+    precision(2, 2),
+    (("a.csv", "b.csv"), Some(&header)),
+    Some((worst, 33.333333333, false)),
+    None,
+    None,
+    None,
+    (4, Default::default()),
+    None,
+  );
+  assert_eq!((row[5].0.as_str(), row[6].0.as_str()), ("3", "SXX"));
+}
+
+#[test]
+fn test_include_integers() {
+  use clap::Parser;
+  let compared = |argv: &[&str]| {
+    let argv = ["f06csvdiff"].iter().chain(argv).chain(&["a", "b"]);
+    let args = crate::Args::try_parse_from(argv).unwrap();
+    let opts = crate::diff_config(&args, &Tolerances::default()).unwrap();
+    // node counts, a column of integers in one file and floats in the
+    // other, and integers too long to be one
+    let t1 = records(&[
+      &["12", "3", "1.0E+00", "12345678901234567890"],
+      &["40", "-7", "2.0E+00", "12345678901234567890"],
+    ]);
+    let t2 = records(&[
+      &["12", "3.0E+00", "1.0E+00", "12345678901234567891"],
+      &["41", "-7.5E+00", "2.0E+00", "12345678901234567890"],
+    ]);
+    let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    let cols: Vec<usize> = report.columns.iter().map(|c| c.col).collect();
+    (cols, report.max_diff.vals)
+  };
+  // integers are left out unless asked for, so ID columns don't fail
+  assert_eq!(compared(&[]), (vec![3], (0.0, 0.0)));
+  // and then compare by value, with floats too
+  assert_eq!(
+    compared(&["--include-integers"]),
+    (vec![1, 2, 3], (40.0, 41.0))
+  );
+  assert_eq!(
+    compared(&["--include-integers", "--float-format", "fortran"]).0,
+    [1, 2, 3]
+  );
+}
+
+#[test]
+fn test_csv_format() {
+  use crate::write_csv;
+  use f06csvdiff::table::{CSV_FIELDS, Precision, csv_row};
+  let t1 = records(&[&["1.0E+00", "2.0E+00"], &["4.0E+00", "5.0E+00"]]);
+  let t2 = records(&[&["1.0E+00", "2.5E+00"], &["4.0E+00", "5.0E+00"]]);
+  let tolerances = Tolerances {
+    max_diff: Some(1.0),
+    ..Default::default()
+  };
+  let opts = DiffConfig {
+    tolerances: tolerances.clone(),
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  let precision = Precision {
+    values: 6,
+    percent: 2,
+  };
+  let names = ("ref, \"old\"", "new.csv");
+  let row = csv_row(names, &report, &tolerances, precision);
+  assert_eq!(row.len(), CSV_FIELDS.len());
+  let mut out = Vec::new();
+  write_csv(&mut out, &CSV_FIELDS.map(String::from)).unwrap();
+  write_csv(&mut out, &row).unwrap();
+  // the ratio gate isn't enabled, so its fields are empty, and a name with
+  // the delimiter or quotes is quoted
+  assert_eq!(
+    String::from_utf8(out).unwrap(),
+    "file1,file2,ratio_percent,ratio_val1,ratio_val2,ratio_line,\
+     ratio_status,diff,diff_val1,diff_val2,diff_line,diff_status\n\
+     \"ref, \"\"old\"\"\",new.csv,,,,,,5.00E-1,+2.000000E0,+2.500000E0,1,PASS\n"
+  );
+}
+
+#[test]
+fn test_json_format() {
+  use f06csvdiff::json;
+  let t1 = records(&[&["1.0E+00", "2.0E+00"], &["0.0E+00", "4.0E+00"]]);
+  let t2 = records(&[&["1.0E+00", "2.5E+00"], &["0.0E+00", "5.0E+00"]]);
+  let tolerances = Tolerances {
+    max_ratio: Some(0.1),
+    max_diff: Some(1.0),
+    ..Default::default()
+  };
+  let opts = DiffConfig {
+    tolerances: tolerances.clone(),
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  let line = json::report(
+    ("a.csv", "b.csv"),
+    &report,
+    (&tolerances, &Tolerances::default()),
+    ("FAILED", &[]),
+    0.5,
+  );
+  assert!(!line.contains('\n'));
+  let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+  assert_eq!(json["status"], "FAILED");
+  assert_eq!(
+    (json["compared"].as_u64(), json["skipped"].as_u64()),
+    (Some(3), Some(1))
+  );
+  assert_eq!(json["failing_cells"], 2);
+  let gates = json["gates"].as_array().unwrap();
+  assert_eq!(gates.len(), 2);
+  // the ratio gate fails at its worst cell, the first of those as bad, in
+  // percent
+  let ratio = &gates[0];
+  assert_eq!(
+    (&ratio["gate"], &ratio["passed"]),
+    (&"ratio".into(), &false.into())
+  );
+  assert_eq!(ratio["worst"], 25.0);
+  assert_eq!((&ratio["line"], &ratio["col"]), (&1.into(), &2.into()));
+  assert_eq!(ratio["values"], serde_json::json!([2.0, 2.5]));
+  // the diff gate passes, its worst cell being as large as allowed
+  let diff = &gates[1];
+  assert_eq!(
+    (&diff["gate"], &diff["passed"]),
+    (&"diff".into(), &true.into())
+  );
+  assert_eq!((&diff["limit"], &diff["worst"]), (&1.0.into(), &1.0.into()));
+  assert_eq!(diff["line"], 2);
+}
+
+#[test]
+fn test_json_details() {
+  // group A agrees exactly, group B differs at its second row
+  let text1 = "A,1.0E+00\nA,2.0E+00\nB,3.0E+00\nB,4.0E+00\n";
+  let text2 = "A,1.0E+00\nA,2.0E+00\nB,3.0E+00\nB,4.4E+00\n";
+  let flags = [
+    "-r",
+    "0.05",
+    "--warn-ratio",
+    "0.02",
+    "--group-by",
+    "1",
+    "--histogram",
+    "--percentiles",
+    "50,100",
+    "--format",
+    "json",
+  ];
+  let out = reported("json-details", (text1, text2), &flags).out;
+  let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+  assert_eq!(json["status"], "FAILED");
+  // the warning tier is held to its own limit, and counts no cells
+  let warning = &json["warnings"][0];
+  assert_eq!(
+    (&warning["gate"], &warning["passed"]),
+    (&"ratio".into(), &false.into())
+  );
+  assert_eq!(warning["limit"], 2.0);
+  assert_eq!(warning["failing_cells"], serde_json::Value::Null);
+  assert_eq!(json["columns"][0]["col"], 2);
+  // 4.0 against 4.4 agree to -log10(0.4/4.4) digits
+  let digits = json["columns"][0]["digits"].as_f64().unwrap();
+  assert!((digits - 1.041).abs() < 1e-3);
+  // the only differing cell carries the whole excess
+  let dominance = &json["dominance"];
+  assert_eq!(dominance[0]["line"], 4);
+  assert_eq!(dominance[0]["share"], 1.0);
+  let groups = json["groups"].as_array().unwrap();
+  assert_eq!(groups.len(), 2);
+  assert_eq!(
+    (&groups[1]["group"], &groups[1]["max_ratio_line"]),
+    (&"B".into(), &4.into())
+  );
+  assert_eq!(json["percentiles"]["counted"], 4);
+  let p100 = &json["percentiles"]["values"][1];
+  assert_eq!(p100[0], 100.0);
+  assert!((p100[1].as_f64().unwrap() - 10.0).abs() < 1e-9);
+  // a 10% difference, up to rounding, falls in the last bin
+  assert_eq!(
+    json["histogram"]["counts"],
+    serde_json::json!([3, 0, 0, 0, 0, 1])
+  );
+  assert_eq!(
+    (&json["sampled_rows"], &json["waivers"]),
+    (&serde_json::Value::Null, &serde_json::json!([]))
+  );
+}
+
+#[test]
+fn test_allclose_tolerances() {
+  use clap::Parser;
+  use f06csvdiff::color::Painter;
+  use f06csvdiff::table::{Alignment, Precision, align_table, aligned_row};
+  use f06csvdiff::verdict::Gate;
+  let tolerances = |argv: &[&str]| {
+    let argv = ["f06csvdiff", "a.csv", "b.csv"].iter().chain(argv);
+    let args = crate::Args::try_parse_from(argv).unwrap();
+    crate::command_line_tolerances(&args)
+  };
+  let separate = tolerances(&["-d", "1e-3", "-r", "0.01"]);
+  assert_eq!(separate.gates(), [Gate::Ratio, Gate::Diff]);
+  // -d and -r become the parts of the combined tolerance
+  let combined = tolerances(&["-d", "1e-3", "-r", "0.01", "--allclose"]);
+  assert_eq!(combined.gates(), [Gate::Allclose]);
+  assert_eq!((combined.atol, combined.rtol), (Some(1e-3), Some(0.01)));
+  let rtol = tolerances(&["-r", "0.01", "--allclose"]);
+  assert_eq!(rtol.allclose(), Some((0.0, 0.01)));
+
+  let t1 = records(&[&["1.0E-06", "1.0E+03"]]);
+  let t2 = records(&[&["5.0E-04", "1.03E+03"]]);
+  let opts = DiffConfig {
+    tolerances: combined.clone(),
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  let (headers, row) = aligned_row(
+    Precision {
+      values: 2,
+      percent: 2,
+    },
+    (("a.csv", "b.csv"), None),
+    None,
+    None,
+    None,
+    Some((report.allclose, report.passed())),
+    (report.compared(), report.failing_cells),
+    None,
+  );
+  let table = align_table(
+    &headers,
+    &[row],
+    &Alignment::Left,
+    None,
+    true,
+    &Painter::default(),
+  );
+  let lines: Vec<&str> = table.lines().map(str::trim_end).collect();
+  assert_eq!(
+    lines,
+    [
+      "csv1  csv2  allclose val1_c  val2_c  line_c col_c status_c fail_c \
+       cells",
+      "a.csv b.csv 2.91     +1.00E3 +1.03E3 1      2     FAIL     1      2",
+    ]
+  );
+}
+
+#[test]
+fn test_headers() {
+  use clap::Parser;
+  let parses = |argv: &[&str]| {
+    let argv = ["f06csvdiff", "-r", "0.01", "a.csv", "b.csv"]
+      .iter()
+      .chain(argv);
+    crate::Args::try_parse_from(argv).is_ok()
+  };
+  // --headers stands for --has-headers and --match-by-header
+  assert!(parses(&["--headers"]));
+  assert!(parses(&["--headers", "--require-all-columns"]));
+  assert!(parses(&["--headers", "--dedup-headers", "--diff-headers"]));
+  assert!(parses(&["--has-headers", "--match-by-header"]));
+  assert!(!parses(&["--match-by-header"]));
+  assert!(!parses(&["--has-headers", "--require-all-columns"]));
+  assert!(!parses(&["--headers", "--col-map", "map.csv"]));
+  // the columns are paired by name, whatever their order
+  let texts = (
+    "id,T1,T2\n1,1.0E+00,2.0E+00\n",
+    "id,T2,T1\n1,2.0E+00,1.0E+00\n",
+  );
+  let flags = ["-r", "0.01", "--headers"];
+  assert!(reported("headers", texts, &flags).report.passed());
+}
+
+#[test]
+fn test_one_based_columns() {
+  use clap::Parser;
+  use clap::error::ErrorKind;
+  use f06csvdiff::compare::{AbsCompare, SignFlip};
+  let parse = |argv: &[&str]| {
+    let argv = ["f06csvdiff", "a.csv", "b.csv"].iter().chain(argv);
+    crate::Args::try_parse_from(argv)
+  };
+  // columns count from 1, so 0 is no column rather than the first
+  for flag in ["--allow-sign-flip-cols", "--abs-compare-cols"] {
+    let zero = parse(&[flag, "2,0"]);
+    assert!(zero.is_err_and(|e| e.kind() == ErrorKind::ValueValidation));
+  }
+  let args = parse(&["--allow-sign-flip-cols", "1,3"]).unwrap();
+  let opts = crate::diff_config(&args, &Tolerances::default()).unwrap();
+  assert!(matches!(opts.sign_flip, SignFlip::Columns(cols) if cols == [0, 2]));
+  let args = parse(&["--abs-compare-cols", "1,3"]).unwrap();
+  let opts = crate::diff_config(&args, &Tolerances::default()).unwrap();
+  let abs = opts.abs_compare;
+  assert!(matches!(abs, AbsCompare::Columns(cols) if cols == [0, 2]));
+}
+
+#[test]
+fn test_trailing_fields() {
+  use clap::Parser;
+  let dir = scratch_dir("trailing", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  // one export ends every line with a delimiter, the other leaves out
+  // empty trailing fields
+  let trailing = dir.join("trailing.csv");
+  std::fs::write(&trailing, "ID,T1,NOTE,\n1,1.0E+00,,\n2,2.0E+00,x,\n")
+    .unwrap();
+  let missing = dir.join("missing.csv");
+  std::fs::write(&missing, "ID,T1,NOTE\n1,1.0E+00\n2,2.5E+00,x\n").unwrap();
+  let (csv1, csv2) = (trailing.to_str().unwrap(), missing.to_str().unwrap());
+  let evened = |flags: &[&str]| {
+    let argv = ["f06csvdiff", "--has-headers"].iter().chain(flags);
+    let args = crate::Args::try_parse_from(argv.chain(&[csv1, csv2])).unwrap();
+    let mut records1 = crate::read_records(&args, csv1).unwrap();
+    let mut records2 = crate::read_records(&args, csv2).unwrap();
+    let headers =
+      crate::strip_headers(&args, (csv1, &mut records1), (csv2, &mut records2));
+    let (header1, header2) = headers.unwrap().unwrap();
+    let width = |r: &[StringRecord]| r.iter().map(|r| r.len()).max();
+    assert_eq!(width(&records1), width(&records2));
+    (header1.len(), header2.len(), records1[0].len())
+  };
+  assert_eq!(evened(&["--pad-missing"]), (4, 4, 4));
+  // the NOTE of the first row is empty too, so only padding evens it out
+  assert_eq!(
+    evened(&["--ignore-trailing-empty", "--pad-missing"]),
+    (3, 3, 3)
+  );
+}
+
+#[test]
+fn test_shown_rows() {
+  use crate::shown_row;
+  let row = StringRecord::from(vec!["1042", "3", "1.2E-02", "4.0E+00", "x"]);
+  assert_eq!(shown_row(&row, 2, ',', 160), "1042,3,>>1.2E-02<<,4.0E+00,x");
+  assert_eq!(shown_row(&row, 0, ';', 160), ">>1042<<;3;1.2E-02;4.0E+00;x");
+  // cells away from the marked one are left out first, on either side
+  assert_eq!(shown_row(&row, 2, ',', 20), "...,3,>>1.2E-02<<,...");
+  assert_eq!(shown_row(&row, 4, ',', 20), "...,4.0E+00,>>x<<");
+  // and the marked cell is kept however narrow
+  assert_eq!(shown_row(&row, 2, ',', 4), "...,>>1.2E-02<<,...");
+  // a column the row lacks marks nothing
+  assert_eq!(shown_row(&row, 7, ',', 160), "1042,3,1.2E-02,4.0E+00,x");
+}
+
+#[test]
+fn test_aggregate() {
+  use crate::aggregate::{Summary, read};
+  use crate::summary::ResultLine;
+  // result lines read back as they were written
+  let line = "F06CSVDIFF-RESULT pair=\"c d.csv|\\\"e\\\".csv\" status=WAIVED \
+              waivers=NAS-1,NAS-2 worst_ratio_pct=25 worst_diff=5e-1 cells=4 \
+              violations=1 high_impact=1 low_impact=0 elapsed_ms=812";
+  let parsed: ResultLine = line.parse().unwrap();
+  assert_eq!(parsed.pair, Some(("c d.csv".into(), "\"e\".csv".into())));
+  assert_eq!(
+    parsed.to_string(),
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+  );
+  assert!(
+    "F06CSVDIFF-RESULT status=MAYBE"
+      .parse::<ResultLine>()
+      .is_err()
+  );
+  assert!(
+    "F06CSVDIFF-RESULT status=PASSED foo=1"
+      .parse::<ResultLine>()
+      .is_err()
+  );
+
+  // result lines and --format csv rows, ratio-only and diff-only, mixed
+  let dir = scratch_dir("aggregate", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let path = dir.join("nightly.txt");
+  std::fs::write(
+    &path,
+    "F06CSVDIFF-RESULT pair=\"a.csv|b.csv\" status=PASSED \
+     worst_ratio_pct=1.5 cells=4 elapsed_ms=3\n\
+     F06CSVDIFF-RESULT pair=\"c.csv|d.csv\" status=WARNED worst_diff=2e-3 \
+     cells=4 elapsed_ms=3\n\
+     \n\
+     file1,file2,ratio_percent,ratio_val1,ratio_val2,ratio_line,\
+     ratio_status,diff,diff_val1,diff_val2,diff_line,diff_status\n\
+     e.csv,f.csv,12.50,1.0E0,1.125E0,7,FAIL,,,,,\n\
+     g.csv,h.csv,,,,,,3.00E-2,1.0E0,1.03E0,2,PASS\n\
+     F06CSVDIFF-RESULT pairs=2 failed=1 status=FAILED elapsed_ms=9\n",
+  )
+  .unwrap();
+  let lines = read(&path).unwrap();
+  let summary = Summary::of(&lines);
+  assert_eq!(
+    (
+      summary.comparisons,
+      summary.passed,
+      summary.warned,
+      summary.failed
+    ),
+    (4, 2, 1, 1)
+  );
+  let worst = summary.worst_ratio_pct.as_ref().unwrap();
+  assert_eq!((worst.value, worst.file1.as_str()), (12.5, "e.csv"));
+  let worst = summary.worst_diff.as_ref().unwrap();
+  assert_eq!((worst.value, worst.file2.as_str()), (3e-2, "h.csv"));
+
+  let written = |format| {
+    let mut out = Vec::new();
+    crate::write_summary(&mut out, format, &summary).unwrap();
+    String::from_utf8(out).unwrap()
+  };
+  assert_eq!(
+    written("plain"),
+    "1 of 4 comparisons failed (2 passed, 1 warned, 0 waived)\n\
+     worst percent difference: 12.50% (e.csv vs f.csv)\n\
+     worst absolute difference: 3.00E-2 (g.csv vs h.csv)\n"
+  );
+  assert!(written("markdown").ends_with(
+    "| 4 | 2 | _1_ | 0 | **1** | 12.50% | e.csv vs f.csv | 3.00E-2 | \
+     g.csv vs h.csv |\n"
+  ));
+  let json: serde_json::Value = serde_json::from_str(&written("json")).unwrap();
+  assert_eq!(json["failed"], 1);
+  assert_eq!(json["worst_diff"]["file1"], "g.csv");
+
+  // anything else is rejected, at its line
+  std::fs::write(&path, "F06CSVDIFF-RESULT status=PASSED\nsome log line\n")
+    .unwrap();
+  let e = read(&path).unwrap_err();
+  assert!(
+    e.ends_with(
+      "nightly.txt:2: neither a F06CSVDIFF-RESULT line nor \
+                       a --format csv row of 12 fields"
+    ),
+    "{e}"
+  );
+}
+
+#[test]
+fn test_gcc_locations() {
+  use crate::locations::{Locating, cell_locations, gate_locations};
+  use f06csvdiff::failures;
+  let dir = scratch_dir("locations", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let (path1, path2) = (dir.join("ref.csv"), dir.join("out.csv"));
+  std::fs::write(&path1, "\"a,b\",1.0E+00,5.0E+00\n").unwrap();
+  std::fs::write(&path2, "\"a,b\",1.0E+00,5.1E+00\n").unwrap();
+  let read = |path: &std::path::Path| -> Vec<StringRecord> {
+    let mut reader = csv::ReaderBuilder::new()
+      .has_headers(false)
+      .from_path(path)
+      .unwrap();
+    reader.records().map(Result::unwrap).collect()
+  };
+  let (t1, t2) = (read(&path1), read(&path2));
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      max_diff: Some(1.0),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  let (path1, path2) = (path1.to_str().unwrap(), path2.to_str().unwrap());
+  let at = Locating {
+    path: path2,
+    file: 2,
+    bytes: false,
+  };
+  assert_eq!(
+    gate_locations(at, (&t1, &t2), &report, &opts),
+    [
+      format!("{path2}:1:3: ratio 2.00% exceeds 1.00% (5.0000E0 vs 5.1000E0)"),
+      format!(
+        "{path2}:1:3: abs diff 1.00E-1 within 1.00E0 (5.0000E0 vs 5.1000E0)"
+      ),
+    ]
+  );
+  // byte columns skip the delimiter in quotes
+  let at = Locating {
+    path: path1,
+    file: 1,
+    bytes: true,
+  };
+  let found = gate_locations(at, (&t1, &t2), &report, &opts);
+  assert!(found[0].starts_with(&format!("{path1}:1:15: ratio 2.00%")));
+
+  // and so does each cell --list-failures lists
+  let listed = failures::failing_cells((&t1, &t2), &opts, &report, None);
+  assert_eq!(
+    cell_locations(at, (&t1, &t2), &listed.cells, &opts),
+    [format!(
+      "{path1}:1:15: ratio 2.00%, abs diff 1.00E-1 exceed ratio (5.0000E0 \
+       vs 5.1000E0)"
+    )]
+  );
+}
+
+#[test]
+fn test_column_requirements() {
+  use clap::Parser;
+  let t1 = records(&[&["a", "1.0E+00", "2.0E+00"], &["b", "N/A", "2.0E+00"]]);
+  let t2 =
+    records(&[&["a", "1.0E+00", "2.0E+00"], &["b", "5.0E+00", "2.0E+00"]]);
+  let report =
+    compare::compare(&t1, &t2, &DiffConfig::default(), ("a", "b")).unwrap();
+  let failures = |argv: &[&str]| {
+    let argv = ["f06csvdiff", "a.csv", "b.csv"].iter().chain(argv);
+    let args = crate::Args::try_parse_from(argv).unwrap();
+    crate::column_failures(&args, &report)
+  };
+  assert!(failures(&[]).is_empty());
+  // the label column isn't a stray cell, the "N/A" is
+  assert_eq!(
+    failures(&["--require-all-float-columns"]),
+    [
+      "--require-all-float-columns: column 2 has \"N/A\" at line 2, after 1 \
+       row(s) of floats"
+    ]
+  );
+  assert!(failures(&["--min-compared-columns", "1"]).is_empty());
+  assert_eq!(
+    failures(&["--min-compared-columns", "2"]),
+    ["--min-compared-columns 2: only 1 column(s) compared"]
+  );
+  assert_eq!(
+    crate::demoted_summary(&report, &report.demoted_columns()),
+    "compared 1 of 3 columns; columns 1, 2 excluded (first non-float at \
+     lines 1, 2)"
+  );
+}
+
+#[test]
+fn test_sampled_rows() {
+  use crate::summary::ResultLine;
+  use clap::Parser;
+  use std::time::Duration;
+  assert_eq!(crate::line_range("3:"), Ok(3..=usize::MAX));
+  assert_eq!(crate::line_range(":5"), Ok(1..=5));
+  assert_eq!(crate::line_range("2-4"), Ok(2..=4));
+  for bad in ["0:3", "5:2", "-3", "3-", "x:"] {
+    assert!(crate::line_range(bad).is_err(), "{bad}");
+  }
+
+  // each row's cell is its line, since these records have no positions
+  let cells: Vec<String> = (1..=10).map(|line| line.to_string()).collect();
+  let rows: Vec<[&str; 1]> = cells.iter().map(|c| [c.as_str()]).collect();
+  let rows: Vec<&[&str]> = rows.iter().map(|r| &r[..]).collect();
+  let sample = |argv: &[&str]| {
+    let argv = ["f06csvdiff", "a.csv", "b.csv"].iter().chain(argv);
+    let args = crate::Args::try_parse_from(argv).unwrap();
+    let (mut records1, mut records2) = (records(&rows), records(&rows));
+    let sampled =
+      crate::keep_rows(&args, ("a.csv", &mut records1), &mut records2).unwrap();
+    let lines: Vec<usize> =
+      records1.iter().map(|r| r[0].parse().unwrap()).collect();
+    assert_eq!(records1, records2);
+    (sampled, lines)
+  };
+  assert_eq!(sample(&[]), (None, (1..=10).collect()));
+  assert_eq!(sample(&["--rows", "1:"]), (None, (1..=10).collect()));
+  // every third row of those kept, starting with the first of them
+  assert_eq!(
+    sample(&["--rows", "2:9", "--stride", "3"]),
+    (Some((3, 10)), vec![2, 5, 8])
+  );
+  assert_eq!(sample(&["--stride", "4"]), (Some((3, 10)), vec![1, 5, 9]));
+  assert!(
+    crate::Args::try_parse_from(["f06csvdiff", "--stride", "0"]).is_err()
+  );
+
+  // the result line says a PASS was sampled, and reads back
+  let line = ResultLine {
+    pair: Some(("a.csv".to_string(), "b.csv".to_string())),
+    passed: true,
+    cells: Some(3),
+    sampled_rows: Some((3, 10)),
+    elapsed: Duration::from_millis(4),
+    ..Default::default()
+  };
+  let text = line.to_string();
+  assert_eq!(
+    text,
+    "F06CSVDIFF-RESULT pair=\"a.csv|b.csv\" status=PASSED cells=3 \
+     sampled_rows=3 total_rows=10 elapsed_ms=4"
+  );
+  assert_eq!(text.parse::<ResultLine>(), Ok(line));
+}
+
+#[test]
+fn test_keys_report() {
+  use f06csvdiff::keys::reconcile;
+  let mut t1 = records(&[
+    &["101", "1.0E+00"],
+    &["102", "2.0E+00"],
+    &["102", "5.0E+00"],
+  ]);
+  let mut t2: Vec<StringRecord> = (0..25)
+    .map(|i| {
+      StringRecord::from(vec![(101 + i).to_string(), "1.0E+00".to_string()])
+    })
+    .collect();
+  let matching = reconcile(("a", &mut t1), ("b", &mut t2), (0, 0)).unwrap();
+  let dir = scratch_dir("keys-report", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let path = dir.join("keys.csv");
+  crate::write_keys_report(&path, &matching).unwrap();
+  let text = std::fs::read_to_string(&path).unwrap();
+  let lines: Vec<&str> = text.lines().collect();
+  assert_eq!(lines.len(), 26);
+  assert_eq!(
+    lines[..4],
+    [
+      "key,status,rows1,rows2,lines1,lines2",
+      "101,matched,1,1,1,1",
+      "102,duplicated,2,1,2 3,2",
+      "103,only_in_2,0,1,,3",
+    ]
+  );
+  // the 23 keys only csv2 has are listed up to the cap
+  let only = crate::keys_only_in(&matching, 2);
+  assert_eq!(only.len(), crate::LISTED_KEYS + 1);
+  assert_eq!(only[0], "key 103: 1 row (line 3)");
+  assert_eq!(only[crate::LISTED_KEYS], "... and 3 more");
+  assert!(crate::keys_only_in(&matching, 1).is_empty());
+}
+
+/// Compares two files of these contents with --verify and the flags given,
+/// the config file's [assert] and [columns] tables being these, returning
+/// the report; a discrepancy with the reference exits.
+fn verified(
+  name: &str,
+  (text1, text2): (&str, &str),
+  flags: &[&str],
+  (assertions, specs): (
+    &f06csvdiff::assertions::Assertions,
+    &std::collections::BTreeMap<String, crate::config::ColumnSpec>,
+  ),
+) -> compare::DiffReport {
+  use clap::Parser;
+  let dir = scratch_dir(name, &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let (csv1, csv2) = (dir.join("a.csv"), dir.join("b.csv"));
+  std::fs::write(&csv1, text1).unwrap();
+  std::fs::write(&csv2, text2).unwrap();
+  let (csv1, csv2) = (csv1.to_str().unwrap(), csv2.to_str().unwrap());
+  let argv = ["f06csvdiff", "--verify", "-r", "0.01"].iter().chain(flags);
+  let args = crate::Args::try_parse_from(argv.chain(&[csv1, csv2])).unwrap();
+  let tolerances = Tolerances {
+    max_ratio: args.max_ratio,
+    ..Default::default()
+  };
+  let (report, _) = crate::run(
+    &args,
+    &[],
+    &tolerances,
+    (assertions, specs),
+    (&[], None),
+    crate::Painter::new(crate::ColorChoice::Never, false),
+    &mut Vec::new(),
+    &mut None,
+    None,
+    csv1,
+    csv2,
+    &mut Default::default(),
+  )
+  .unwrap();
+  report
+}
+
+#[test]
+fn test_verify() {
+  let none = (&Default::default(), &Default::default());
+  // the rows left unpaired are the reference's too
+  let report = verified(
+    "verify",
+    (
+      "1,1.0E+00\n2,2.0E+00\n3,3.0E+00\n",
+      "1,1.0E+00\n3,3.0E+00\n",
+    ),
+    &["--key-col", "1"],
+    none,
+  );
+  assert_eq!(report.unpaired_rows, (vec![2], vec![]));
+  assert!(!report.passed());
+}
+
+#[test]
+fn test_verify_group_by() {
+  let none = (&Default::default(), &Default::default());
+  // the reference's report has the same groups, so identical files pass
+  let text = "1,1.0E+00\n1,2.0E+00\n2,3.0E+00\n";
+  let report =
+    verified("verify-group-by", (text, text), &["--group-by", "1"], none);
+  assert_eq!(report.groups.len(), 2);
+  assert!(report.passed());
+}
+
+#[test]
+fn test_verify_complex_pairs() {
+  let none = (&Default::default(), &Default::default());
+  // the reference's report has the same phases
+  let text = "1,1.0E+00,1.0E+00\n2,3.0E+00,-4.0E+00\n";
+  let flags = ["--complex-pairs", "2:3"];
+  let report = verified("verify-complex", (text, text), &flags, none);
+  assert!(report.complex.is_some());
+  assert!(report.passed());
+}
+
+#[test]
+fn test_verify_matrix_mode() {
+  let none = (&Default::default(), &Default::default());
+  // the reference's report has the same norms
+  let text = "1,1.0E+00,2.0E+00\n2,3.0E+00,4.0E+00\n";
+  let flags = ["--matrix-mode"];
+  let report = verified("verify-matrix", (text, text), &flags, none);
+  assert!(report.matrix.is_some());
+  assert!(report.passed());
+}
+
+#[test]
+fn test_verify_column_overrides() {
+  // a [columns] table of the config, with tolerances of its own
+  let spec = crate::config::ColumnSpec {
+    max_ratio: Some(0.5),
+    ..Default::default()
+  };
+  let specs = [("3".to_string(), spec)].into_iter().collect();
+  let text1 = "1,1.0E+00,2.0E+00\n2,3.0E+00,4.0E+00\n";
+  let text2 = "1,1.0E+00,2.2E+00\n2,3.0E+00,4.0E+00\n";
+  let assertions = Default::default();
+  let report = verified(
+    "verify-overrides",
+    (text1, text2),
+    &[],
+    (&assertions, &specs),
+  );
+  assert_eq!(report.overrides.len(), 1);
+  assert!(report.passed());
+}
+
+#[test]
+fn test_verify_assertions() {
+  // an [assert] that fails fails the reference's report too
+  let assertions = f06csvdiff::assertions::Assertions {
+    min_rows_compared: Some(3),
+    ..Default::default()
+  };
+  let text = "1,1.0E+00\n2,2.0E+00\n";
+  let specs = Default::default();
+  let report = verified(
+    "verify-assertions",
+    (text, text),
+    &[],
+    (&assertions, &specs),
+  );
+  assert_eq!(report.assertion_failures.len(), 1);
+  assert!(!report.passed());
+}
+
+/// A pair compared by `run` as the command line would compare it.
+struct Reported {
+  args: crate::Args,
+  argv: Vec<String>,
+  report: compare::DiffReport,
+  /// What was written of the report.
+  out: String,
+}
+
+/// Compares two files of these contents with the flags given.
+fn reported(
+  name: &str,
+  (text1, text2): (&str, &str),
+  flags: &[&str],
+) -> Reported {
+  use clap::Parser;
+  let dir = scratch_dir(name, &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let (csv1, csv2) = (dir.join("a.csv"), dir.join("b.csv"));
+  std::fs::write(&csv1, text1).unwrap();
+  std::fs::write(&csv2, text2).unwrap();
+  let (csv1, csv2) = (csv1.to_str().unwrap(), csv2.to_str().unwrap());
+  let argv: Vec<String> = ["f06csvdiff"]
+    .iter()
+    .chain(flags)
+    .chain(&[csv1, csv2])
+    .map(|arg| arg.to_string())
+    .collect();
+  let args = crate::Args::try_parse_from(&argv).unwrap();
+  let tolerances = crate::command_line_tolerances(&args);
+  let waivers = match &args.waivers {
+    Some(path) => f06csvdiff::waivers::Waivers::load(path).unwrap().waiver,
+    None => Vec::new(),
+  };
+  let mut out = Vec::new();
+  let (report, _) = crate::run(
+    &args,
+    &[],
+    &tolerances,
+    (&Default::default(), &Default::default()),
+    (&waivers, None),
+    crate::Painter::new(crate::ColorChoice::Never, false),
+    &mut out,
+    &mut None,
+    None,
+    csv1,
+    csv2,
+    &mut Default::default(),
+  )
+  .unwrap();
+  let out = String::from_utf8(out).unwrap();
+  Reported {
+    args,
+    argv,
+    report,
+    out,
+  }
+}
+
+impl Reported {
+  /// The command reproducing the worst violation, if any.
+  fn reproduction(&self) -> Option<String> {
+    let (csv1, csv2) = (&self.argv[self.argv.len() - 2], self.argv.last());
+    crate::reproduction(
+      &self.args,
+      &self.argv,
+      (csv1, csv2.unwrap()),
+      &Tolerances::default(),
+      (&self.report, None),
+    )
+  }
+}
+
+#[test]
+fn test_sum_check_only() {
+  let flags = ["-r", "0.01", "--sum-check", "--sum-check-only"];
+  let junit = [&flags[..], &["--format", "junit"]].concat();
+  // the rows shift load between each other, but the totals agree
+  let shifted = ("1,1.0E+00\n2,3.0E+00\n", "1,2.0E+00\n2,2.0E+00\n");
+  let plain = reported("sums-only-pass", shifted, &flags);
+  assert!(plain.report.passed());
+  assert!(!plain.report.gates[0].passed);
+  assert!(plain.out.contains(" PASS ") && !plain.out.contains("FAIL"));
+  assert_eq!(plain.reproduction(), None);
+  let xml = reported("sums-only-pass-junit", shifted, &junit).out;
+  assert!(xml.contains("tests=\"1\" failures=\"0\""));
+  assert!(xml.contains("name=\"sums\"/>") && !xml.contains("name=\"ratio\""));
+
+  // the totals differ: the sums fail, and they alone are blamed
+  let unbalanced = ("1,1.0E+00\n2,3.0E+00\n", "1,2.0E+00\n2,3.0E+00\n");
+  let plain = reported("sums-only-fail", unbalanced, &flags);
+  assert!(!plain.report.passed());
+  assert!(plain.out.contains(" FAIL "));
+  let command = plain.reproduction().unwrap();
+  assert!(command.contains("--columns 2 ") && !command.contains("--rows"));
+  let xml = reported("sums-only-fail-junit", unbalanced, &junit).out;
+  assert!(xml.contains("tests=\"1\" failures=\"1\""));
+  assert!(xml.contains("1 of 1 column sums fail: column 2: sum1=+4.000000E0"));
+  assert!(!xml.contains("name=\"ratio\""));
+}
+
+#[test]
+fn test_junit_checks() {
+  let text = "1,1.0E+00,2.0E+00\n2,3.0E+00,4.0E+00\n";
+  let flags = ["-r", "0.01", "--format", "junit"];
+  // every gate passes, but too few columns were compared
+  let too_few = [&flags[..], &["--min-compared-columns", "3"]].concat();
+  let junit = reported("junit-columns", (text, text), &too_few);
+  assert!(!junit.report.passed());
+  assert!(junit.out.contains("tests=\"2\" failures=\"1\""));
+  assert!(junit.out.contains("name=\"ratio\"/>"));
+  assert!(junit.out.contains("name=\"columns\">"));
+  assert!(junit.out.contains("only 2 column(s) compared</failure>"));
+  let enough = [&flags[..], &["--min-compared-columns", "2"]].concat();
+  let junit = reported("junit-columns-pass", (text, text), &enough);
+  assert!(junit.report.passed());
+  assert!(junit.out.contains("tests=\"2\" failures=\"0\""));
+  assert!(junit.out.contains("name=\"columns\"/>"));
+}
+
+#[test]
+fn test_group_lines() {
+  // group A agrees exactly, group B differs at its second row
+  let text1 = "A,1.0E+00\nA,2.0E+00\nB,3.0E+00\nB,4.0E+00\n";
+  let text2 = "A,1.0E+00\nA,2.0E+00\nB,3.0E+00\nB,4.4E+00\n";
+  let flags = ["-r", "0.01", "-d", "1.0", "--group-by", "1"];
+  let out = reported("group-lines", (text1, text2), &flags).out;
+  assert!(out.contains(
+    "1=A: 2 and 2 rows, ratio 0.00%, diff 0.00E0, 2 cells compared, PASS"
+  ));
+  assert!(out.contains("1=B: 2 and 2 rows, ratio 10.00% (line 4, column 2)"));
+}
+
+#[test]
+fn test_applied_waivers() {
+  let dir = scratch_dir("applied-waivers", &[]);
+  std::fs::create_dir_all(&dir).unwrap();
+  let path = dir.join("waivers.toml");
+  std::fs::write(
+    &path,
+    "[[waiver]]\ncolumn = 2\nreason = \"T3 drifts\"\nticket = \"NAS-2\"\n\
+     expires = 2999-12-31\n",
+  )
+  .unwrap();
+  let texts = ("1,1.0E+00\n2,3.0E+00\n", "1,1.0E+00\n2,3.3E+00\n");
+  let flags = ["-r", "0.01", "--waivers", path.to_str().unwrap()];
+  let json = [&flags[..], &["--format", "json"]].concat();
+  let out = reported("applied-waivers-json", texts, &json).out;
+  let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+  assert_eq!(json["status"], "WAIVED");
+  assert_eq!(
+    json["waivers"],
+    serde_json::json!([{
+      "ticket": "NAS-2",
+      "column": 2,
+      "reason": "T3 drifts",
+      "expires": "2999-12-31"
+    }])
+  );
+  let junit = [&flags[..], &["--format", "junit"]].concat();
+  let xml = reported("applied-waivers-junit", texts, &junit).out;
+  assert!(xml.contains(
+    "<property name=\"waiver\" value=\"NAS-2 on column 2 (T3 drifts), \
+     until 2999-12-31\"/>"
+  ));
+}
+
+#[test]
+fn test_status_cells() {
+  let text = "1,1.0E+00,2.0E+00\n2,3.0E+00,4.0E+00\n";
+  // every gate passes, but too few columns were compared
+  let flags = ["-r", "0.01", "--min-compared-columns", "3"];
+  let formats = ["--align=left", "--format=markdown", "--format=csv"];
+  for format in [None].into_iter().chain(formats.map(Some)) {
+    let flags = [&flags[..], format.as_slice()].concat();
+    let reported = reported("status-cells", (text, text), &flags);
+    assert!(!reported.report.passed());
+    let out = reported.out;
+    assert!(
+      out.contains("FAIL") && !out.contains("PASS"),
+      "{format:?}: {out}"
+    );
+  }
+}
+
+#[test]
+fn test_annotation_status() {
+  // the values agree, but only csv2 flags the second one
+  let texts = ("1,1.0E+00\n2,3.0E+00\n", "1,1.0E+00\n2,3.0E+00*\n");
+  let flags = ["-r", "0.01", "--strip-suffix", "--compare-annotations"];
+  let plain = reported("annotation-status", texts, &flags);
+  assert!(!plain.report.passed());
+  assert!(plain.out.contains(" FAIL ") && !plain.out.contains("PASS"));
+  let junit = [&flags[..], &["--format", "junit"]].concat();
+  let xml = reported("annotation-status-junit", texts, &junit).out;
+  assert!(xml.contains("tests=\"2\" failures=\"1\""));
+  assert!(xml.contains("name=\"annotations\">"));
+  assert!(xml.contains("1 cell(s) annotated differently: "));
+}
@@ -0,0 +1,60 @@
+//! Column map files, for --col-map: which column of csv2 each column of csv1
+//! is compared with, when an exporter writes them in another order.
+//!
+//! Each line pairs a column of csv1 with one of csv2, separated by a comma,
+//! e.g. `T1,T1` or `2,3`. A column is a 1-based number, or with
+//! --has-headers a header name. Lines starting with `#` are comments.
+
+use std::path::Path;
+
+use csv::{ReaderBuilder, StringRecord};
+
+use f06csvdiff::compare::ColumnMap;
+
+/// Reads a column map, naming columns by the headers of csv1 and csv2 if
+/// there are any.
+pub(crate) fn load(
+  path: &Path,
+  headers: Option<(&StringRecord, &StringRecord)>,
+) -> Result<ColumnMap, String> {
+  let bad = |e: &dyn std::fmt::Display| {
+    format!("bad column map {}: {e}", path.display())
+  };
+  let text = std::fs::read_to_string(path)
+    .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+  let mut reader = ReaderBuilder::new()
+    .has_headers(false)
+    .comment(Some(b'#'))
+    .trim(csv::Trim::All)
+    .from_reader(text.as_bytes());
+  let mut pairs = Vec::new();
+  for record in reader.records() {
+    let record = record.map_err(|e| bad(&e))?;
+    let line = record.position().map_or(0, |p| p.line());
+    if record.len() != 2 {
+      return Err(bad(&format!("line {line} isn't two columns")));
+    }
+    let column = |file: usize, col: &str| {
+      let header = headers.map(|(h1, h2)| if file == 1 { h1 } else { h2 });
+      resolve(col, header).map_err(|e| bad(&format!("line {line}: {e}")))
+    };
+    pairs.push((column(1, &record[0])?, column(2, &record[1])?));
+  }
+  ColumnMap::new(&pairs).map_err(|col1| {
+    bad(&format!("column {} of csv1 is paired twice", col1 + 1))
+  })
+}
+
+/// The (0-based) column a map names, by number or header name.
+fn resolve(col: &str, header: Option<&StringRecord>) -> Result<usize, String> {
+  if let Ok(col) = col.parse::<usize>() {
+    return col.checked_sub(1).ok_or("columns start at 1".to_string());
+  }
+  let Some(header) = header else {
+    return Err(format!("column {col} needs --has-headers"));
+  };
+  header
+    .iter()
+    .position(|name| name == col)
+    .ok_or_else(|| format!("no column named {col} in the header"))
+}
@@ -0,0 +1,112 @@
+//! ANSI coloring of the report.
+
+/// When to color the output.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorChoice {
+  /// Only when the report goes to a terminal and NO_COLOR isn't set.
+  #[default]
+  Auto,
+  /// Always, even when piped.
+  Always,
+  /// Never.
+  Never,
+}
+
+impl std::str::FromStr for ColorChoice {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "auto" => Ok(ColorChoice::Auto),
+      "always" => Ok(ColorChoice::Always),
+      "never" => Ok(ColorChoice::Never),
+      _ => Err(format!(
+        "Invalid color choice: {s}. Must be auto, always, or never"
+      )),
+    }
+  }
+}
+
+/// How a piece of report text should look.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Paint {
+  /// Left as is.
+  Plain,
+  /// A passing status.
+  Pass,
+  /// A failing status.
+  Fail,
+  /// A failing status that a waiver excuses.
+  Waived,
+  /// A status only a warning tolerance flags.
+  Warned,
+  /// A value that exceeds its tolerance.
+  Offending,
+}
+
+/// Applies (or doesn't) ANSI escapes to report text.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Painter {
+  /// Whether escapes are emitted at all.
+  enabled: bool,
+}
+
+impl Painter {
+  /// Decides whether to color output going to a terminal or not, following
+  /// the NO_COLOR convention.
+  pub fn new(choice: ColorChoice, to_terminal: bool) -> Self {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let enabled = match choice {
+      ColorChoice::Always => true,
+      ColorChoice::Never => false,
+      ColorChoice::Auto => !no_color && to_terminal,
+    };
+    Self { enabled }
+  }
+
+  /// A painter that always colors; for tests.
+  #[cfg(test)]
+  pub fn always() -> Self {
+    Self { enabled: true }
+  }
+
+  /// Wraps text in the escapes for its paint.
+  pub fn paint(&self, text: &str, paint: Paint) -> String {
+    let code = match paint {
+      _ if !self.enabled => return text.to_string(),
+      Paint::Plain => return text.to_string(),
+      Paint::Pass => "32",
+      Paint::Fail => "31",
+      Paint::Waived | Paint::Warned => "33",
+      Paint::Offending => "1;33",
+    };
+    format!("\x1b[{code}m{text}\x1b[0m")
+  }
+
+  /// A PASS/FAIL status word.
+  pub fn status(&self, passed: bool) -> String {
+    if passed {
+      self.paint("PASS", Paint::Pass)
+    } else {
+      self.paint("FAIL", Paint::Fail)
+    }
+  }
+
+  /// A PASS/WARN status word, for the warning tolerances.
+  pub fn warning(&self, warned: bool) -> String {
+    if warned {
+      self.paint("WARN", Paint::Warned)
+    } else {
+      self.paint("PASS", Paint::Pass)
+    }
+  }
+
+  /// Highlights a value if it exceeds its tolerance.
+  pub fn value(&self, text: &str, passed: bool) -> String {
+    if passed {
+      text.to_string()
+    } else {
+      self.paint(text, Paint::Offending)
+    }
+  }
+}
@@ -0,0 +1,3033 @@
+//! The comparison engine proper: float-column detection and max-tracking.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Debug;
+use std::ops::Range;
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::Instant;
+
+use csv::{Position, StringRecord};
+use regex::Regex;
+
+use crate::annotations::AnnotationMismatch;
+use crate::assertions::AssertionFailure;
+use crate::complex::PhaseReport;
+use crate::error::DiffError;
+use crate::groups::GroupReport;
+use crate::histogram::Histogram;
+use crate::impact::{Impact, ImpactReport};
+use crate::matrix::MatrixReport;
+use crate::normalize::ColumnNorms;
+use crate::overrides::ColumnOverride;
+use crate::percent::PercentColumns;
+use crate::percentiles::Percentiles;
+use crate::profile::{Phase, PhaseTimes, Samples};
+use crate::progress::Progress;
+use crate::verdict::{self, Check, ColumnSum, Gate, GateResult, Tolerances};
+
+/// The regex that a cell must match to be considered a float, unless
+/// another is configured.
+pub const FLOAT_PATTERN: &str = r"[-+]?[0-9]*\.?[0-9]+[Ee][-+]?[0-9]+";
+
+/// The regex a cell holding a plain integer of up to 19 digits matches,
+/// such as a node count or an iteration number. Adding it to the float
+/// regex as an alternative takes integers for floats too.
+pub const INTEGER_PATTERN: &str = r"^[-+]?[0-9]{1,19}$";
+
+/// A way floats are written, to take cells for floats by without writing a
+/// regex.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FloatFormat {
+  /// Scientific notation with an E exponent, e.g. 1.5E+00 or .5E+00:
+  /// [`FLOAT_PATTERN`].
+  #[default]
+  Standard,
+  /// Also as old Fortran writers print them: D exponents, e.g. 1.5D-03,
+  /// and no digits after the point, e.g. 1.E+00.
+  Fortran,
+  /// Also as Nastran's bulk data has them: the Fortran forms, and exponents
+  /// without their letter after a decimal point, e.g. 1.5-3 or -2.+4.
+  Nastran,
+}
+
+impl FloatFormat {
+  /// The regex a cell written this way matches.
+  pub fn pattern(self) -> &'static str {
+    match self {
+      FloatFormat::Standard => FLOAT_PATTERN,
+      FloatFormat::Fortran => {
+        r"[-+]?(?:[0-9]+\.?[0-9]*|\.[0-9]+)[EeDd][-+]?[0-9]+"
+      }
+      FloatFormat::Nastran => {
+        r"[-+]?(?:[0-9]+\.?[0-9]*|\.[0-9]+)[EeDd][-+]?[0-9]+|[-+]?(?:[0-9]+\.[0-9]*|\.[0-9]+)[-+][0-9]+"
+      }
+    }
+  }
+}
+
+impl std::str::FromStr for FloatFormat {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "standard" => Ok(FloatFormat::Standard),
+      "fortran" => Ok(FloatFormat::Fortran),
+      "nastran" => Ok(FloatFormat::Nastran),
+      _ => Err(format!(
+        "Invalid float format: {s}. Must be standard, fortran, or nastran"
+      )),
+    }
+  }
+}
+
+/// The longest cell taken for a float, in bytes. No exporter prints a float
+/// anywhere near this long, so a longer cell is text by its length alone,
+/// without scanning what may be megabytes of it.
+pub const MAX_FLOAT_LEN: usize = 256;
+
+/// The worst cell seen for one criterion.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Extremum {
+  /// The value of the metric at the worst cell.
+  pub value: f64,
+  /// The two values at the worst cell.
+  pub vals: (f64, f64),
+  /// The (1-based) physical line of the worst cell, in csv1.
+  pub line: usize,
+  /// The (1-based) column of the worst cell, in csv1.
+  pub col: usize,
+}
+
+impl Extremum {
+  /// An extremum that hasn't seen any cells yet.
+  pub(crate) fn new(value: f64) -> Self {
+    Self {
+      value,
+      vals: (0.0, 0.0),
+      line: 0,
+      col: 0,
+    }
+  }
+
+  /// Moves its cell's (line, column) by `map`.
+  fn map_cell(&mut self, map: &dyn Fn((usize, usize)) -> (usize, usize)) {
+    (self.line, self.col) = map((self.line, self.col));
+  }
+
+  /// Moves to a cell if it's worse, or as bad but earlier in (line,
+  /// column) order, which rows sorted before comparing needn't come in.
+  pub(crate) fn raise(
+    &mut self,
+    value: f64,
+    vals: (f64, f64),
+    line: usize,
+    col: usize,
+  ) {
+    let earlier = self.line != 0
+      && value == self.value
+      && (line, col) < (self.line, self.col);
+    if value > self.value || earlier {
+      *self = Self {
+        value,
+        vals,
+        line,
+        col,
+      };
+    }
+  }
+
+  /// Takes on the extremum of another set of columns if it's worse, or as
+  /// bad but earlier in row-major order, as a single pass would have.
+  pub(crate) fn merge(&mut self, other: Self) {
+    if other.line != 0
+      && (other.value > self.value
+        || other.value == self.value
+          && (other.line, other.col) < (self.line, self.col))
+    {
+      *self = other;
+    }
+  }
+}
+
+/// The cells tied with the worst cell of one criterion: those exactly as
+/// bad, and those a ULP (unit in the last place) better, so that a maximum
+/// rounding a hair differently in one cell doesn't hide the others. Only
+/// the first few are located, in (line, column) order; the rest are
+/// counted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ties {
+  /// The starting value of their criterion's extremum: no cell this good
+  /// ties with anything.
+  pub start: f64,
+  /// The worst value seen, above `start`, or else `start`.
+  pub value: f64,
+  /// How many cells are exactly at `value`, the worst cell included.
+  pub at: usize,
+  /// How many cells are a ULP below `value`.
+  pub below: usize,
+  /// The first cells exactly at `value`, as (line, column), at most
+  /// `cap` + 1 of them, so that `cap` are left besides the worst cell.
+  pub at_cells: Vec<(usize, usize)>,
+  /// The first cells a ULP below `value`, as (line, column), at most `cap`
+  /// + 1 of them.
+  pub below_cells: Vec<(usize, usize)>,
+  /// How many tied cells to locate.
+  pub cap: usize,
+}
+
+impl Ties {
+  /// Ties that haven't seen any cells yet, with the starting value of
+  /// their criterion's extremum, locating up to `cap` cells.
+  pub(crate) fn new(value: f64, cap: usize) -> Self {
+    Self {
+      start: value,
+      value,
+      cap,
+      ..Default::default()
+    }
+  }
+
+  /// Counts a cell, at a line and (1-based) column, against the worst
+  /// value so far.
+  pub(crate) fn add(&mut self, value: f64, line: usize, col: usize) {
+    let kept = self.cap + 1;
+    // rows sorted before comparing needn't come in line order
+    let push = |cells: &mut Vec<(usize, usize)>| {
+      let at = cells.partition_point(|&cell| cell < (line, col));
+      if at < kept {
+        cells.insert(at, (line, col));
+        cells.truncate(kept);
+      }
+    };
+    if value > self.value {
+      let adjacent = self.at > 0 && value.next_down() == self.value;
+      self.below = if adjacent { self.at } else { 0 };
+      self.below_cells = match adjacent {
+        true => std::mem::take(&mut self.at_cells),
+        false => Vec::new(),
+      };
+      self.at_cells.clear();
+      (self.value, self.at) = (value, 1);
+      push(&mut self.at_cells);
+    } else if self.at > 0 && value == self.value {
+      self.at += 1;
+      push(&mut self.at_cells);
+    } else if self.at > 0
+      && value == self.value.next_down()
+      && value > self.start
+    {
+      self.below += 1;
+      push(&mut self.below_cells);
+    }
+  }
+
+  /// Takes in the ties of another set of columns, as a single pass over
+  /// them all would have counted them.
+  pub(crate) fn merge(&mut self, mut other: Self) {
+    if other.at == 0 {
+      return;
+    }
+    if self.at == 0 || other.value > self.value {
+      std::mem::swap(self, &mut other);
+      if other.at == 0 {
+        return;
+      }
+    }
+    let (at, below) = if other.value == self.value {
+      self.at += other.at;
+      self.below += other.below;
+      (other.at_cells, other.below_cells)
+    } else if other.value == self.value.next_down() && other.value > self.start
+    {
+      self.below += other.at;
+      (Vec::new(), other.at_cells)
+    } else {
+      return;
+    };
+    let kept = self.cap + 1;
+    for (cells, more) in
+      [(&mut self.at_cells, at), (&mut self.below_cells, below)]
+    {
+      cells.extend(more);
+      cells.sort_unstable();
+      cells.truncate(kept);
+    }
+  }
+
+  /// How many cells tie with the worst one, besides it.
+  pub fn others(&self) -> usize {
+    (self.at + self.below).saturating_sub(1)
+  }
+
+  /// The first of the cells tied with a worst cell, besides it, as (line,
+  /// column), in that order: at most `cap` of them.
+  pub fn listed(&self, worst: &Extremum) -> Vec<(usize, usize)> {
+    let mut cells = [&self.at_cells[..], &self.below_cells[..]].concat();
+    cells.sort_unstable();
+    cells.retain(|&cell| cell != (worst.line, worst.col));
+    cells.truncate(self.cap);
+    cells
+  }
+
+  /// Moves its cells' (line, column) by `map`.
+  fn map_cells(&mut self, map: &dyn Fn((usize, usize)) -> (usize, usize)) {
+    for cells in [&mut self.at_cells, &mut self.below_cells] {
+      cells.iter_mut().for_each(|cell| *cell = map(*cell));
+      cells.sort_unstable();
+    }
+  }
+}
+
+/// Which way a compared cell's values differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+  /// csv2's value is above csv1's: an over-prediction.
+  Over,
+  /// csv2's value isn't above csv1's: an under-prediction.
+  Under,
+}
+
+impl Direction {
+  /// The direction of a cell, by its values as measured: after csv2's signs
+  /// are flipped, if its column is, and as magnitudes, if its column is
+  /// compared by magnitude, so that a cell's direction is always that of
+  /// the difference its tolerances are checked against.
+  pub fn of(a1: f64, a2: f64) -> Self {
+    if a2 > a1 {
+      Direction::Over
+    } else {
+      Direction::Under
+    }
+  }
+
+  /// The name of the direction in reports.
+  pub fn name(self) -> &'static str {
+    match self {
+      Direction::Over => "over",
+      Direction::Under => "under",
+    }
+  }
+}
+
+/// The worst cells of one direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Directed {
+  /// The worst relative difference, as the report's `max_ratio` is
+  /// measured.
+  pub max_ratio: Extremum,
+  /// The worst absolute difference.
+  pub max_diff: Extremum,
+}
+
+/// The worst cells of each direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Directions {
+  /// The cells where csv2 is above csv1.
+  pub over: Directed,
+  /// The cells where csv2 isn't above csv1.
+  pub under: Directed,
+}
+
+impl Directions {
+  /// Directions that haven't seen any cells yet.
+  pub(crate) fn new(rel_mode: RelMode) -> Self {
+    let directed = Directed {
+      max_ratio: Extremum::new(rel_mode.identical()),
+      max_diff: Extremum::new(0.0),
+    };
+    Self {
+      over: directed,
+      under: directed,
+    }
+  }
+
+  /// A direction's worst cells.
+  pub fn of(&self, direction: Direction) -> &Directed {
+    match direction {
+      Direction::Over => &self.over,
+      Direction::Under => &self.under,
+    }
+  }
+
+  /// Moves a direction's worst cells to a compared cell, at a physical line
+  /// and (1-based) column, if it's worse.
+  fn raise(
+    &mut self,
+    cell: &Measures,
+    vals: (f64, f64),
+    line: usize,
+    col: usize,
+  ) {
+    let directed = match cell.direction {
+      Direction::Over => &mut self.over,
+      Direction::Under => &mut self.under,
+    };
+    directed.max_ratio.raise(cell.ratio, vals, line, col);
+    directed.max_diff.raise(cell.diff, vals, line, col);
+  }
+
+  /// Takes on the worst cells of another set of columns where they're
+  /// worse.
+  fn merge(&mut self, other: Self) {
+    self.over.max_ratio.merge(other.over.max_ratio);
+    self.over.max_diff.merge(other.over.max_diff);
+    self.under.max_ratio.merge(other.under.max_ratio);
+    self.under.max_diff.merge(other.under.max_diff);
+  }
+}
+
+/// Which float columns may be compared with csv2's signs flipped.
+#[derive(Clone, Debug, Default)]
+pub enum SignFlip {
+  /// Never flip signs.
+  #[default]
+  Never,
+  /// Any float column may be flipped.
+  AnyColumn,
+  /// Only the listed (0-based) columns may be flipped.
+  Columns(Vec<usize>),
+}
+
+impl SignFlip {
+  /// Whether a (0-based) column may be flipped.
+  pub fn allows(&self, col: usize) -> bool {
+    match self {
+      SignFlip::Never => false,
+      SignFlip::AnyColumn => true,
+      SignFlip::Columns(cols) => cols.contains(&col),
+    }
+  }
+}
+
+/// Which float columns are compared by their values' magnitudes, for
+/// quantities whose signs may legitimately differ cell by cell.
+#[derive(Clone, Debug, Default)]
+pub enum AbsCompare {
+  /// Compare signed values.
+  #[default]
+  Never,
+  /// Every float column is compared by magnitude.
+  AnyColumn,
+  /// Only the listed (0-based) columns are.
+  Columns(Vec<usize>),
+}
+
+impl AbsCompare {
+  /// Whether a (0-based) column is compared by magnitude.
+  pub fn applies(&self, col: usize) -> bool {
+    match self {
+      AbsCompare::Never => false,
+      AbsCompare::AnyColumn => true,
+      AbsCompare::Columns(cols) => cols.contains(&col),
+    }
+  }
+}
+
+/// Which column of csv2 each column of csv1 is compared with, for files
+/// whose columns come in different orders.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ColumnMap {
+  /// The (0-based) column of csv2 paired with each column of csv1, by csv1's
+  /// column; csv1's columns past its end, or paired with `None`, aren't
+  /// compared.
+  cols: Vec<Option<usize>>,
+}
+
+impl ColumnMap {
+  /// A map from (0-based) pairs of columns, csv1's then csv2's. Fails with
+  /// the column of csv1 that's paired twice, if one is.
+  pub fn new(pairs: &[(usize, usize)]) -> Result<Self, usize> {
+    let mut cols = Vec::new();
+    for &(col1, col2) in pairs {
+      if cols.len() <= col1 {
+        cols.resize(col1 + 1, None);
+      }
+      if cols[col1].replace(col2).is_some() {
+        return Err(col1);
+      }
+    }
+    Ok(Self { cols })
+  }
+
+  /// The (0-based) column of csv2 paired with a column of csv1, if any.
+  pub fn get(&self, col1: usize) -> Option<usize> {
+    self.cols.get(col1).copied().flatten()
+  }
+
+  /// The pairs of (0-based) columns, in csv1's column order.
+  pub fn pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+    (0..self.cols.len()).filter_map(|col1| Some((col1, self.get(col1)?)))
+  }
+}
+
+/// How the relative difference of two values is measured, for the ratio
+/// gate. Each mode has its own way with the special cases: a zero value,
+/// and values of opposite signs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RelMode {
+  /// max(|a|,|b|)/min(|a|,|b|): symmetric and blind to signs, but infinite
+  /// when either value is zero.
+  #[default]
+  Ratio,
+  /// |a-b|/|a|, relative to csv1 as the baseline: infinite when a is zero.
+  Rel1,
+  /// |a-b|/|b|, relative to csv2: infinite when b is zero.
+  Rel2,
+  /// |a-b|/max(|a|,|b|): 1 when either value is zero, and up to 2 when the
+  /// signs are opposite.
+  Symmetric,
+}
+
+impl std::str::FromStr for RelMode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "ratio" => Ok(RelMode::Ratio),
+      "rel1" => Ok(RelMode::Rel1),
+      "rel2" => Ok(RelMode::Rel2),
+      "symmetric" => Ok(RelMode::Symmetric),
+      _ => Err(format!(
+        "Invalid relative difference mode: {s}. Must be ratio, rel1, rel2, \
+         or symmetric"
+      )),
+    }
+  }
+}
+
+impl RelMode {
+  /// The name of the mode, as --rel-mode takes it.
+  pub fn name(self) -> &'static str {
+    match self {
+      RelMode::Ratio => "ratio",
+      RelMode::Rel1 => "rel1",
+      RelMode::Rel2 => "rel2",
+      RelMode::Symmetric => "symmetric",
+    }
+  }
+
+  /// The relative difference of two values, not both zero, with the
+  /// denominator raised to at least `floor`. The ratio's numerator is raised
+  /// too, so that values both below the floor count as equal.
+  pub fn measure(self, a: f64, b: f64, floor: f64) -> f64 {
+    let diff = (a - b).abs();
+    let (a, b) = (a.abs(), b.abs());
+    match self {
+      RelMode::Ratio => a.max(b).max(floor) / a.min(b).max(floor),
+      RelMode::Rel1 => diff / a.max(floor),
+      RelMode::Rel2 => diff / b.max(floor),
+      RelMode::Symmetric => diff / a.max(b).max(floor),
+    }
+  }
+
+  /// Whether [`measure`](Self::measure) raises the denominator to the floor
+  /// for these values.
+  pub fn floors(self, a: f64, b: f64, floor: f64) -> bool {
+    let (a, b) = (a.abs(), b.abs());
+    let denominator = match self {
+      RelMode::Ratio => a.min(b),
+      RelMode::Rel1 => a,
+      RelMode::Rel2 => b,
+      RelMode::Symmetric => a.max(b),
+    };
+    denominator < floor
+  }
+
+  /// What [`measure`](Self::measure) gives for identical values.
+  pub fn identical(self) -> f64 {
+    match self {
+      RelMode::Ratio => 1.0,
+      _ => 0.0,
+    }
+  }
+
+  /// A measured relative difference in percent, as -r's tolerance is
+  /// compared against.
+  pub fn percent(self, value: f64) -> f64 {
+    match self {
+      RelMode::Ratio => ((value - 1.0) * 100.0).abs(),
+      _ => value * 100.0,
+    }
+  }
+
+  /// The relative difference |a-b|/max(|a|,|b|,floor) the tolerance allows
+  /// at a cell, which is what violation masses are measured in.
+  fn allowed_rel(self, a: f64, b: f64, tolerance: f64, floor: f64) -> f64 {
+    let max = a.abs().max(b.abs()).max(floor);
+    // dividing first, since multiplying a subnormal value underflows
+    match self {
+      // a ratio of 1+r is a relative difference of r/(1+r)
+      RelMode::Ratio => tolerance / (1.0 + tolerance),
+      RelMode::Rel1 => tolerance * (a.abs().max(floor) / max),
+      RelMode::Rel2 => tolerance * (b.abs().max(floor) / max),
+      RelMode::Symmetric => tolerance,
+    }
+  }
+}
+
+/// Which of a cell's values must be below its column's cutoff for the cell
+/// to be skipped. Values both zero are skipped whatever the mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ThresholdMode {
+  /// Both: a small value against a large one is still compared.
+  #[default]
+  Both,
+  /// Either: a small value against anything is skipped, zero against noise
+  /// included.
+  Either,
+  /// csv1's, the trusted baseline, whatever csv2's is.
+  Baseline,
+}
+
+impl std::str::FromStr for ThresholdMode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "both" => Ok(ThresholdMode::Both),
+      "either" => Ok(ThresholdMode::Either),
+      "baseline" => Ok(ThresholdMode::Baseline),
+      _ => Err(format!(
+        "Invalid threshold mode: {s}. Must be both, either, or baseline"
+      )),
+    }
+  }
+}
+
+impl ThresholdMode {
+  /// The name of the mode, as --threshold-mode takes it.
+  pub fn name(self) -> &'static str {
+    match self {
+      ThresholdMode::Both => "both",
+      ThresholdMode::Either => "either",
+      ThresholdMode::Baseline => "baseline",
+    }
+  }
+
+  /// Which of a cell's values the mode skips it for, by whether each is
+  /// below the cutoff, if it skips it.
+  fn skips(self, below1: bool, below2: bool) -> Option<Skip> {
+    let skipped = match self {
+      ThresholdMode::Both => below1 && below2,
+      ThresholdMode::Either => below1 || below2,
+      ThresholdMode::Baseline => below1,
+    };
+    match (below1, below2) {
+      _ if !skipped => None,
+      (true, true) => Some(Skip::BothBelow),
+      (true, false) => Some(Skip::Below1),
+      _ => Some(Skip::Below2),
+    }
+  }
+}
+
+/// Why a cell was skipped rather than compared.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Skip {
+  /// Both values are zero.
+  Zeros,
+  /// Both values are below the column's cutoff.
+  BothBelow,
+  /// Only csv1's value is below the cutoff.
+  Below1,
+  /// Only csv2's value is below the cutoff.
+  Below2,
+}
+
+/// The cells skipped, by what skipped them, so that a threshold skipping
+/// more than it should shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SkippedCells {
+  /// Those whose values are both zero.
+  pub zeros: usize,
+  /// Those whose values are both below the cutoff, and not both zero.
+  pub both_below: usize,
+  /// Those whose csv1 value only is below the cutoff.
+  pub below1: usize,
+  /// Those whose csv2 value only is below the cutoff.
+  pub below2: usize,
+}
+
+impl SkippedCells {
+  /// Counts a skipped cell.
+  pub(crate) fn add(&mut self, skip: Skip) {
+    match skip {
+      Skip::Zeros => self.zeros += 1,
+      Skip::BothBelow => self.both_below += 1,
+      Skip::Below1 => self.below1 += 1,
+      Skip::Below2 => self.below2 += 1,
+    }
+  }
+
+  /// Takes in the skipped cells of another set of columns.
+  pub(crate) fn merge(&mut self, other: Self) {
+    self.zeros += other.zeros;
+    self.both_below += other.both_below;
+    self.below1 += other.below1;
+    self.below2 += other.below2;
+  }
+
+  /// How many were skipped by the threshold, rather than for being zero.
+  pub fn below(&self) -> usize {
+    self.both_below + self.below1 + self.below2
+  }
+}
+
+/// Whether each float column's sums over the compared cells are checked
+/// against the ratio and absolute difference tolerances, as a force
+/// balance's totals are, and what they decide.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SumCheck {
+  /// Not checked.
+  #[default]
+  Off,
+  /// Checked, and any failing fails the comparison as a gate would.
+  Combined,
+  /// Checked instead of the cells: the ratio, absolute difference and
+  /// digits gates don't decide the verdict.
+  Only,
+}
+
+impl SumCheck {
+  /// Whether the sums are checked at all.
+  pub fn enabled(self) -> bool {
+    self != SumCheck::Off
+  }
+}
+
+/// A compensated sum (Neumaier's variant of Kahan's), so that a column
+/// whose large values cancel still sums to its small ones. Values are
+/// halved as they're added, so that no partial sum of finite values
+/// overflows; the smallest subnormal values lose their last bit to it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sum {
+  /// The running sum of the halved values.
+  sum: f64,
+  /// What rounding took off `sum` so far.
+  compensation: f64,
+}
+
+impl Sum {
+  /// Adds a value.
+  pub fn add(&mut self, value: f64) {
+    let value = value / 2.0;
+    let sum = self.sum + value;
+    self.compensation += match self.sum.abs() >= value.abs() {
+      true => (self.sum - sum) + value,
+      false => (value - sum) + self.sum,
+    };
+    self.sum = sum;
+  }
+
+  /// The sum of the values added so far.
+  pub fn value(&self) -> f64 {
+    (self.sum + self.compensation) * 2.0
+  }
+}
+
+/// Where the absolute difference tolerance of each row is scaled from, e.g.
+/// a load level, so that rows at low load are held to tighter differences.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffScale {
+  /// The (0-based) column of csv1 whose magnitude, in each row, the
+  /// tolerance is multiplied by there.
+  pub col: usize,
+  /// The smallest a row's scale gets, so that no row is held to zero.
+  pub floor: f64,
+}
+
+/// The worst compared cell by its absolute difference over its row's scale,
+/// which the absolute difference tolerance is checked against when it's
+/// scaled by a column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScaledDiff {
+  /// The worst cell, its value being its difference divided by its row's
+  /// scale.
+  pub worst: Extremum,
+  /// The worst cell's absolute difference.
+  pub diff: f64,
+  /// The worst cell's row's scale: its tolerance is the tolerance times it.
+  pub scale: f64,
+}
+
+impl ScaledDiff {
+  /// A scaled difference that hasn't seen any cells yet.
+  pub(crate) fn new() -> Self {
+    Self {
+      worst: Extremum::new(0.0),
+      diff: 0.0,
+      scale: 1.0,
+    }
+  }
+
+  /// Moves to a cell if it's worse for its row's scale.
+  pub(crate) fn raise(
+    &mut self,
+    (diff, scale): (f64, f64),
+    vals: (f64, f64),
+    line: usize,
+    col: usize,
+  ) {
+    let before = self.worst;
+    self.worst.raise(diff / scale, vals, line, col);
+    if self.worst != before {
+      (self.diff, self.scale) = (diff, scale);
+    }
+  }
+
+  /// Takes on the worst cell of another set of columns if it's worse, as
+  /// [`Extremum::merge`] does.
+  pub(crate) fn merge(&mut self, other: Self) {
+    let before = self.worst;
+    self.worst.merge(other.worst);
+    if self.worst != before {
+      (self.diff, self.scale) = (other.diff, other.scale);
+    }
+  }
+}
+
+/// The compared cells zero in exactly one file, whose relative difference
+/// is infinite or, with a denominator floor, only as meaningful as the
+/// floor: 0.0 against 5.0E+3 isn't 0.0 against 1.0E-30.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ZeroMismatches {
+  /// How many there are.
+  pub cells: usize,
+  /// The one whose nonzero value is largest in magnitude, its value being
+  /// that magnitude.
+  pub worst: Extremum,
+  /// The magnitude nonzero values must stay below, if they're held to one
+  /// instead of the ratio tolerance.
+  pub limit: Option<f64>,
+  /// How many have a nonzero value reaching `limit`.
+  pub failing: usize,
+}
+
+impl ZeroMismatches {
+  /// Zero mismatches that haven't seen any cells yet, held to `limit` if
+  /// there's one.
+  pub(crate) fn new(limit: Option<f64>) -> Self {
+    Self {
+      cells: 0,
+      worst: Extremum::new(0.0),
+      limit,
+      failing: 0,
+    }
+  }
+
+  /// The magnitude of the nonzero value of a pair of values zero in
+  /// exactly one file, if they are.
+  pub(crate) fn nonzero(a1: f64, a2: f64) -> Option<f64> {
+    match (a1 == 0.0, a2 == 0.0) {
+      (true, false) => Some(a2.abs()),
+      (false, true) => Some(a1.abs()),
+      _ => None,
+    }
+  }
+
+  /// Counts a compared cell, by its values as measured and as shown, at a
+  /// line and (1-based) column, if it's zero in exactly one file.
+  pub(crate) fn add(
+    &mut self,
+    (vals, shown): ((f64, f64), (f64, f64)),
+    line: usize,
+    col: usize,
+  ) {
+    let Some(magnitude) = Self::nonzero(vals.0, vals.1) else {
+      return;
+    };
+    self.cells += 1;
+    self.failing += usize::from(self.limit.is_some_and(|l| magnitude >= l));
+    self.worst.raise(magnitude, shown, line, col);
+  }
+
+  /// Takes in the zero mismatches of another set of columns.
+  pub(crate) fn merge(&mut self, other: Self) {
+    self.cells += other.cells;
+    self.failing += other.failing;
+    self.worst.merge(other.worst);
+  }
+
+  /// Whether no nonzero value reaches the limit, if there's one.
+  pub fn passed(&self) -> bool {
+    self.failing == 0
+  }
+}
+
+/// What to do when one file has more rows than the other.
+#[derive(Clone, Debug, Default)]
+pub enum ExtraRows {
+  /// Fail with [`DiffError::RowCountMismatch`].
+  #[default]
+  Forbid,
+  /// Compare up to the shorter file's length, and account for the rest in
+  /// [`DiffReport::ignored_rows`].
+  Allow,
+  /// Like `Allow`, but only if every extra row matches this pattern, which
+  /// is tried against the row's cells joined by the delimiter.
+  AllowMatching(Regex),
+}
+
+/// What a row with a structural problem does to the comparison: a different
+/// number of fields than its file's first row or than the other file's row,
+/// a column the column map pairs missing, or a different float layout with
+/// `strict_layout`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Structure {
+  /// It fails the comparison with an error at once.
+  #[default]
+  Strict,
+  /// It's left out of the comparison and listed in
+  /// [`DiffReport::structure_issues`], which fails it. Both files' rows
+  /// without a problem are copied to compare them.
+  Lenient,
+  /// Like `Lenient`, but the issues only warn.
+  Warn,
+}
+
+/// A row left out of the comparison for a structural problem, with
+/// [`Structure::Lenient`] or [`Structure::Warn`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructureIssue {
+  /// The (1-based) physical line of the row, in csv1.
+  pub line: usize,
+  /// The problem, as the error failing the comparison otherwise says, with
+  /// the files named csv1 and csv2.
+  pub problem: String,
+}
+
+/// The rows of two files as long as each other that have no structural
+/// problem, copied with their positions, and an issue for each that has
+/// one. A row's problem is that either file's record has a different
+/// number of fields than the file's first, or any [`check_structure`]
+/// finds.
+pub(crate) fn sound_rows(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+) -> (Vec<StringRecord>, Vec<StringRecord>, Vec<StructureIssue>) {
+  let paths = ("csv1", "csv2");
+  let mut sound = (Vec::new(), Vec::new(), Vec::new());
+  for row in 0..records1.len() {
+    let line = physical_line(records1, row);
+    let widths = [(paths.0, records1), (paths.1, records2)];
+    let changed = widths.into_iter().find_map(|(file, records)| {
+      let (fields, expected) = (records[row].len(), records[0].len());
+      (fields != expected).then(|| DiffError::FieldCountChange {
+        file: file.to_string(),
+        line: physical_line(records, row),
+        fields,
+        expected,
+      })
+    });
+    let checked = match changed {
+      Some(e) => Err(e),
+      None => check_structure(row, (records1, records2), opts, paths),
+    };
+    if let Err(e) = checked {
+      let problem = e.to_string();
+      sound.2.push(StructureIssue { line, problem });
+      continue;
+    }
+    // kept where they were, for reports to locate them
+    for (records, kept) in [(records1, &mut sound.0), (records2, &mut sound.1)]
+    {
+      let mut record = records[row].clone();
+      if record.position().is_none() {
+        let mut position = Position::new();
+        position.set_line(physical_line(records, row) as u64);
+        record.set_position(Some(position));
+      }
+      kept.push(record);
+    }
+  }
+  sound
+}
+
+/// Fails with the error of the structural problem of a (0-based) row of two
+/// files, if it has one.
+fn check_structure(
+  row: usize,
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  opts: &DiffConfig,
+  paths: (&str, &str),
+) -> Result<(), DiffError> {
+  let (rec1, rec2) = (&records1[row], &records2[row]);
+  let line_num = physical_line(records1, row);
+  // with a column map, only its columns must exist
+  let (len1, len2) = (rec1.len(), rec2.len());
+  match &opts.column_map {
+    None if len1 != len2 => {
+      return Err(DiffError::ColumnCountMismatch {
+        line: line_num,
+        cols1: len1,
+        cols2: len2,
+      });
+    }
+    None => {}
+    Some(map) => {
+      let missing = map.pairs().find_map(|(i, j)| match (i, j) {
+        _ if i >= len1 => Some((paths.0, line_num, i)),
+        _ if j >= len2 => Some((paths.1, physical_line(records2, row), j)),
+        _ => None,
+      });
+      if let Some((file, line, col)) = missing {
+        return Err(DiffError::MappedColumnMissing {
+          file: file.to_string(),
+          line,
+          col: col + 1,
+        });
+      }
+    }
+  }
+  if opts.strict_layout
+    && let Some(i) = layout_mismatch(rec1, rec2, &opts.float_re, opts)
+  {
+    return Err(DiffError::FloatLayoutMismatch {
+      line: line_num,
+      col: i + 1,
+      cell1: rec1[i].to_string(),
+      cell2: opts.col2(i).map_or("", |j| &rec2[j]).to_string(),
+    });
+  }
+  Ok(())
+}
+
+/// A column of csv1 left out of the comparison for a cell, in either file,
+/// that isn't a float, such as a units row, an "N/A" or a blank: every
+/// value of it is then left out, floats or not.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DemotedColumn {
+  /// The (1-based) column, of csv1.
+  pub col: usize,
+  /// The physical (1-based) line of csv1 the first such cell is on.
+  pub line: usize,
+  /// How many rows before it were floats in both files: none for a column
+  /// of labels, more for a column of floats with a stray cell.
+  pub floats_before: usize,
+  /// The file the cell is in: 1 or 2, and 1 if it's in both.
+  pub file: usize,
+  /// The cell.
+  pub cell: String,
+}
+
+/// The trailing rows of the longer file, left out of the comparison.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IgnoredRows {
+  /// The file they're in: 1 or 2.
+  pub file: usize,
+  /// How many there are.
+  pub count: usize,
+  /// The physical (1-based) line the first of them starts on.
+  pub line: usize,
+}
+
+/// The physical (1-based) line a record starts on, or its position in the
+/// table if it wasn't read from a file. This is what reports call a line.
+pub fn physical_line(records: &[StringRecord], row: usize) -> usize {
+  records[row]
+    .position()
+    .map_or(row + 1, |p| p.line() as usize)
+}
+
+/// The trailing rows of whichever file is longer, if either is.
+pub(crate) fn extra_rows(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+) -> Option<IgnoredRows> {
+  let (file, longer, shorter) = if records1.len() > records2.len() {
+    (1, records1, records2)
+  } else {
+    (2, records2, records1)
+  };
+  (longer.len() > shorter.len()).then(|| IgnoredRows {
+    file,
+    count: longer.len() - shorter.len(),
+    line: physical_line(longer, shorter.len()),
+  })
+}
+
+/// Settings that change what gets compared and how, and against what.
+#[derive(Clone, Debug)]
+pub struct DiffConfig {
+  /// The tolerances that decide the verdict.
+  pub tolerances: Tolerances,
+  /// The column scaling the absolute difference tolerance row by row, if
+  /// any; rows without a number there keep it unscaled. Not for directional
+  /// absolute difference tolerances, whose gates are decided unscaled.
+  pub diff_scale: Option<DiffScale>,
+  /// The field delimiter of both files.
+  pub delimiter: u8,
+  /// Cells where both values are below this magnitude are skipped, or
+  /// either or csv1's as `threshold_mode` has it.
+  pub threshold: f64,
+  /// Cells where both values are below this fraction of their column's
+  /// largest magnitude, in either file, are skipped too. Zero skips nothing.
+  pub threshold_frac: f64,
+  /// Which values of a cell must be below its column's cutoff for it to be
+  /// skipped. Skipped cells reach neither `rel_floor` nor
+  /// `max_zero_mismatch`: with either or baseline, zero against a value
+  /// below the cutoff is skipped rather than floored or held to a limit.
+  pub threshold_mode: ThresholdMode,
+  /// Whether each float column's sums are checked, as [`SumCheck`] has it.
+  /// They're of the cells compared, signed, so that cells skipped or left
+  /// out are left out of both files' sums.
+  pub sum_check: SumCheck,
+  /// Sign-flip tolerance for eigenvector-like columns. Deciding whether to
+  /// flip needs a whole column, which is fine since both files are buffered.
+  pub sign_flip: SignFlip,
+  /// Which float columns are compared cell by cell by their values'
+  /// magnitudes, after any sign flip. The report still shows the worst
+  /// cells' values signed.
+  pub abs_compare: AbsCompare,
+  /// Whether a cell that is a float in one file but not in the other is an
+  /// error, rather than silently excluding its column from the comparison.
+  pub strict_layout: bool,
+  /// What a row with a structural problem does to the comparison.
+  pub structure: Structure,
+  /// How many of the cells tied with each worst cell to locate; the rest
+  /// are only counted.
+  pub listed_ties: usize,
+  /// The magnitude the nonzero value of a cell zero in exactly one file
+  /// must stay below, if such cells are held to one: they're then left
+  /// out of relative differences, measuring as identical values do, and
+  /// the ratio tolerance doesn't apply to them.
+  pub max_zero_mismatch: Option<f64>,
+  /// How many segments to split the rows into when locating where each
+  /// column's worst differences occur.
+  pub segments: usize,
+  /// Whether files with different numbers of rows are compared anyway.
+  pub extra_rows: ExtraRows,
+  /// How the ratio gate measures relative differences.
+  pub rel_mode: RelMode,
+  /// The smallest denominator of a relative difference, so that a zero
+  /// against a negligible value isn't an infinite difference. Zero against
+  /// zero is skipped regardless.
+  pub rel_floor: f64,
+  /// How many threads to split the float columns among; with 1, they're all
+  /// compared on the calling thread. The report is the same either way.
+  pub column_workers: usize,
+  /// Stop comparing after the row where this many cells have exceeded a
+  /// tolerance, leaving the rest out of the report. Comparisons stopped
+  /// early always run on the calling thread.
+  pub fail_fast: Option<usize>,
+  /// Whether subnormal values, below `f64::MIN_POSITIVE` in magnitude, are
+  /// taken as exactly zero before anything else, so that two values that
+  /// are both physically zero don't make a huge relative difference.
+  pub clamp_subnormals: bool,
+  /// The significant digits every parsed value is rounded to, in decimal,
+  /// after clamping and before anything else, if any: values printed with
+  /// more digits than the other file's then compare as printed with as few.
+  pub sig_digits: Option<u32>,
+  /// The edges, in percent, of the bins to count the compared cells'
+  /// relative differences in, if a histogram is wanted.
+  pub histogram: Option<Vec<f64>>,
+  /// The percentiles of the compared cells' relative differences to find,
+  /// if any are wanted.
+  pub percentiles: Option<Vec<f64>>,
+  /// Whether each compared value stands for the interval its printed
+  /// precision allows, one unit of its last digit wide, so that cells are
+  /// measured between the nearest points of their intervals and values
+  /// whose intervals overlap count as equal.
+  pub interval: bool,
+  /// The factors each file's values are multiplied by before anything is
+  /// measured, e.g. to convert their units. Both must be positive.
+  pub scale: (f64, f64),
+  /// Which column of csv2 each column of csv1 is compared with, if not the
+  /// one at the same position. csv1's columns it leaves unpaired aren't
+  /// compared, and rows only need the paired columns, not the same widths.
+  pub column_map: Option<ColumnMap>,
+  /// The norms each file's columns are divided by before anything is
+  /// measured, in place of the scales, if they're normalized.
+  pub normalize: Option<ColumnNorms>,
+  /// The percentage-like columns, and how each file's values of them are
+  /// converted to a common convention, if any are listed.
+  pub percent: Option<PercentColumns>,
+  /// Where to count the rows compared, for a progress bar.
+  pub progress: Option<Arc<Progress>>,
+  /// The warning tier: tolerances, tighter than `tolerances`, that a
+  /// comparison exceeding is flagged for but doesn't fail.
+  pub warn_tolerances: Tolerances,
+  /// The engineering significance of some (0-based) columns of csv1: a
+  /// cell whose values are both below it in magnitude is low-impact. If
+  /// any column has one, the compared cells are counted by impact class.
+  pub significance: BTreeMap<usize, f64>,
+  /// Whether only high-impact cells are judged: low-impact ones are left
+  /// out of the report's maxima, counts and verdict, and only counted in
+  /// their class.
+  pub high_impact_only: bool,
+  /// The cells left out of the comparison, e.g. investigated and accepted
+  /// singular-point stresses, as (physical line, 0-based column) of csv1.
+  pub excluded_cells: HashSet<(usize, usize)>,
+  /// The regex a cell must match, anywhere in it, to be taken for a float,
+  /// if it also parses: [`FLOAT_PATTERN`] unless another is configured,
+  /// such as a [`FloatFormat`]'s.
+  pub float_re: Regex,
+}
+
+impl Default for DiffConfig {
+  fn default() -> Self {
+    Self {
+      tolerances: Tolerances::default(),
+      diff_scale: None,
+      delimiter: b',',
+      threshold: 0.0,
+      threshold_frac: 0.0,
+      threshold_mode: ThresholdMode::Both,
+      sum_check: SumCheck::Off,
+      sign_flip: SignFlip::Never,
+      abs_compare: AbsCompare::Never,
+      strict_layout: false,
+      structure: Structure::Strict,
+      listed_ties: 0,
+      max_zero_mismatch: None,
+      segments: 10,
+      extra_rows: ExtraRows::Forbid,
+      rel_mode: RelMode::Ratio,
+      rel_floor: 0.0,
+      column_workers: 1,
+      fail_fast: None,
+      clamp_subnormals: true,
+      sig_digits: None,
+      histogram: None,
+      percentiles: None,
+      interval: false,
+      scale: (1.0, 1.0),
+      column_map: None,
+      normalize: None,
+      percent: None,
+      progress: None,
+      warn_tolerances: Tolerances::default(),
+      significance: BTreeMap::new(),
+      high_impact_only: false,
+      excluded_cells: HashSet::new(),
+      float_re: Regex::new(FLOAT_PATTERN).unwrap(),
+    }
+  }
+}
+
+impl DiffConfig {
+  /// The scale of a row's absolute difference tolerance, from csv1's
+  /// record: its magnitude in the scaling column, at least the floor, or
+  /// none if the tolerance isn't scaled or the row has no number there.
+  pub fn row_scale(&self, rec1: &StringRecord) -> Option<f64> {
+    let scale = self.diff_scale?;
+    let value = parse_float(rec1.get(scale.col)?.trim())?;
+    value.is_finite().then(|| value.abs().max(scale.floor))
+  }
+
+  /// A float cell's value as compared: zero if it's subnormal and those are
+  /// clamped, and rounded to the significant digits if any.
+  pub(crate) fn clamp(&self, value: f64) -> f64 {
+    let value = if self.clamp_subnormals && value.is_subnormal() {
+      0.0
+    } else {
+      value
+    };
+    match self.sig_digits {
+      Some(digits) => round_significant(value, digits),
+      None => value,
+    }
+  }
+
+  /// What the values of a (0-based) column of csv1, and of the column of
+  /// csv2 compared with it, are multiplied by before anything is measured:
+  /// one over their norms if they're normalized, and otherwise the scales,
+  /// times a percentage-like column's conversion.
+  pub fn factors(&self, col1: usize) -> (f64, f64) {
+    let Some(normalize) = &self.normalize else {
+      let (p1, p2) = self
+        .percent
+        .as_ref()
+        .map_or((1.0, 1.0), |percent| percent.factors(col1));
+      return (self.scale.0 * p1, self.scale.1 * p2);
+    };
+    let factor = |norm: f64, scale: f64| {
+      // a column of zeros stays zero either way
+      if norm > 0.0 { 1.0 / norm } else { scale }
+    };
+    let norm2 = self.col2(col1).map_or(0.0, |j| normalize.norm(2, j));
+    (
+      factor(normalize.norm(1, col1), self.scale.0),
+      factor(norm2, self.scale.1),
+    )
+  }
+
+  /// A float cell's value as compared, given its parsed value and its
+  /// factor from [`Self::factors`]: clamped, then multiplied by it.
+  pub(crate) fn value(&self, factor: f64, parsed: f64) -> f64 {
+    self.clamp(parsed) * factor
+  }
+
+  /// The (0-based) column of csv2 compared with a column of csv1, if any:
+  /// the same one unless there's a column map.
+  pub fn col2(&self, col1: usize) -> Option<usize> {
+    match &self.column_map {
+      Some(map) => map.get(col1),
+      None => Some(col1),
+    }
+  }
+
+  /// Whether the cell at a physical line and (0-based) column of csv1 is
+  /// left out of the comparison.
+  pub(crate) fn excludes(&self, line: usize, col: usize) -> bool {
+    !self.excluded_cells.is_empty()
+      && self.excluded_cells.contains(&(line, col))
+  }
+
+  /// Whether a float cell's value is clamped to zero.
+  pub(crate) fn clamps(&self, value: f64) -> bool {
+    self.clamp_subnormals && value.is_subnormal()
+  }
+
+  /// The values a compared cell is measured by: in interval mode, the
+  /// nearest points of the intervals their texts stand for, which are the
+  /// same (the larger in magnitude) if the intervals overlap. The
+  /// intervals are multiplied by the values' factors too.
+  pub(crate) fn measured(
+    &self,
+    (a1, a2): (f64, f64),
+    (text1, text2): (&str, &str),
+    (factor1, factor2): (f64, f64),
+  ) -> (f64, f64) {
+    if !self.interval {
+      return (a1, a2);
+    }
+    let (h1, h2) = (quantum(text1) / 2.0, quantum(text2) / 2.0);
+    let (h1, h2) = (h1 * factor1, h2 * factor2);
+    if (a1 - a2).abs() <= h1 + h2 {
+      let larger = if a1.abs() >= a2.abs() { a1 } else { a2 };
+      (larger, larger)
+    } else if a1 < a2 {
+      (a1 + h1, a2 - h2)
+    } else {
+      (a1 - h1, a2 + h2)
+    }
+  }
+}
+
+/// The segment a (0-based) row falls in, out of `segments` equal parts of
+/// `rows` rows.
+pub(crate) fn segment_of(row: usize, rows: usize, segments: usize) -> usize {
+  row * segments / rows.max(1)
+}
+
+/// The (1-based, inclusive) lines a segment spans; empty segments give an
+/// empty range.
+pub fn segment_lines(
+  segment: usize,
+  rows: usize,
+  segments: usize,
+) -> (usize, usize) {
+  let first = (segment * rows).div_ceil(segments);
+  let end = ((segment + 1) * rows).div_ceil(segments);
+  (first + 1, end)
+}
+
+/// Whether a cell is a float as far as the comparison is concerned.
+pub(crate) fn is_float(cell: &str, float_re: &Regex) -> bool {
+  looks_like_float(cell, float_re) && parse_float(cell).is_some()
+}
+
+/// A float cell's value, read from any form a [`FloatFormat`] takes for a
+/// float, or `None` if it doesn't parse.
+pub(crate) fn parse_float(cell: &str) -> Option<f64> {
+  cell.parse().ok().or_else(|| match standard_float(cell) {
+    Cow::Owned(text) => text.parse().ok(),
+    Cow::Borrowed(_) => None,
+  })
+}
+
+/// A float cell's text in scientific notation with an E exponent: a D
+/// exponent's letter replaced, or the letter a Nastran exponent leaves out
+/// put back before its sign. Other text is left as it is.
+pub(crate) fn standard_float(cell: &str) -> Cow<'_, str> {
+  match cell.find(['E', 'e', 'D', 'd']) {
+    Some(i) if matches!(cell.as_bytes()[i], b'D' | b'd') => {
+      Cow::Owned(format!("{}E{}", &cell[..i], &cell[i + 1..]))
+    }
+    Some(_) => Cow::Borrowed(cell),
+    // a sign after the mantissa's first character starts the exponent
+    None => match cell.get(1..).and_then(|rest| rest.rfind(['+', '-'])) {
+      Some(i) => Cow::Owned(format!("{}E{}", &cell[..=i], &cell[i + 1..])),
+      None => Cow::Borrowed(cell),
+    },
+  }
+}
+
+/// Whether a cell is written like a float, if not necessarily one that
+/// parses.
+pub(crate) fn looks_like_float(cell: &str, float_re: &Regex) -> bool {
+  cell.len() <= MAX_FLOAT_LEN && float_re.is_match(cell)
+}
+
+/// The first (0-based) column of csv1 where one record has a float and the
+/// other, at the column compared with it, doesn't.
+pub(crate) fn layout_mismatch(
+  rec1: &StringRecord,
+  rec2: &StringRecord,
+  float_re: &Regex,
+  opts: &DiffConfig,
+) -> Option<usize> {
+  rec1.iter().enumerate().position(|(i, c1)| {
+    let c2 = opts.col2(i).and_then(|j| rec2.get(j));
+    c2.is_some_and(|c2| is_float(c1, float_re) != is_float(c2, float_re))
+  })
+}
+
+/// How the excess over one tolerance is spread across lines and columns.
+///
+/// For the ratio gate the excess is measured in relative difference,
+/// |a-b|/max(|a|,|b|), which stays finite when one value is zero.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ViolationMass {
+  /// The summed excess of each (1-based) line that has any.
+  pub lines: BTreeMap<usize, f64>,
+  /// The summed excess of each (1-based) column that has any.
+  pub cols: BTreeMap<usize, f64>,
+}
+
+impl ViolationMass {
+  /// Accounts for one cell's excess over the tolerance, if positive.
+  pub(crate) fn add(&mut self, line: usize, col: usize, excess: f64) {
+    if excess > 0.0 {
+      *self.lines.entry(line).or_default() += excess;
+      *self.cols.entry(col).or_default() += excess;
+    }
+  }
+
+  /// The line carrying the largest share of the excess, as (share, line).
+  pub fn worst_line(&self) -> Option<(f64, usize)> {
+    worst_share(&self.lines)
+  }
+
+  /// The column carrying the largest share of the excess, as (share, col).
+  pub fn worst_col(&self) -> Option<(f64, usize)> {
+    worst_share(&self.cols)
+  }
+}
+
+/// The entry with the largest share of the total; the first one wins ties.
+fn worst_share(masses: &BTreeMap<usize, f64>) -> Option<(f64, usize)> {
+  let total: f64 = masses.values().sum();
+  masses
+    .iter()
+    .fold(None, |worst, (&k, &m)| match worst {
+      Some((w, _)) if w >= m => worst,
+      _ => Some((m, k)),
+    })
+    .map(|(m, k)| (m / total, k))
+}
+
+/// How many compared cells exceed each tolerance on their own, i.e. would
+/// fail its gate if they were the only ones compared.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FailingCells {
+  /// Cells whose relative difference exceeds the ratio tolerance.
+  pub ratio: usize,
+  /// Cells whose absolute difference exceeds the diff tolerance.
+  pub diff: usize,
+  /// Cells agreeing to fewer digits than the digits tolerance.
+  pub digits: usize,
+  /// Cells whose absolute difference exceeds the combined tolerance.
+  pub allclose: usize,
+  /// Cells exceeding at least one tolerance, each counted once.
+  pub cells: usize,
+}
+
+impl FailingCells {
+  /// The count for a gate.
+  pub fn of(&self, gate: Gate) -> usize {
+    match gate {
+      Gate::Ratio => self.ratio,
+      Gate::Diff => self.diff,
+      Gate::Digits => self.digits,
+      Gate::Allclose => self.allclose,
+    }
+  }
+
+  /// Adds another set of counts to these.
+  pub(crate) fn add(&mut self, other: Self) {
+    self.ratio += other.ratio;
+    self.diff += other.diff;
+    self.digits += other.digits;
+    self.allclose += other.allclose;
+    self.cells += other.cells;
+  }
+
+  /// Whether any count is nonzero.
+  pub(crate) fn any(&self) -> bool {
+    self.cells > 0
+  }
+}
+
+/// Where a single line or column carries most of a gate's violations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dominance {
+  /// A (1-based) line, with its share of the excess.
+  Line(Gate, usize, f64),
+  /// A (1-based) column, with its share of the excess.
+  Column(Gate, usize, f64),
+}
+
+/// What was seen within a single compared float column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnStats {
+  /// The (1-based) column, in csv1.
+  pub col: usize,
+  /// How many of its cells were actually compared.
+  pub compared: usize,
+  /// The worst relative difference, as |a-b|/max(|a|,|b|).
+  pub worst_rel: f64,
+  /// The worst absolute difference.
+  pub worst_diff: f64,
+  /// The column's worst relative difference as the report's `max_ratio` is
+  /// measured, by the comparison's [`RelMode`], with where it is.
+  pub max_ratio: Extremum,
+  /// The column's worst absolute difference, with where it is.
+  pub max_diff: Extremum,
+  /// The column's worst cell for the combined tolerance, as the report's
+  /// `allclose`, with where it is.
+  pub allclose: Extremum,
+  /// The column's worst cells in each direction.
+  pub directions: Directions,
+  /// The column's worst cell for its row's scale, if the absolute
+  /// difference tolerance is scaled and any cell was compared.
+  pub scaled_diff: Option<ScaledDiff>,
+  /// The fewest significant digits printed in any nonzero compared cell.
+  pub precision: Option<usize>,
+  /// The worst relative difference within each segment of the rows, if
+  /// anything was compared there.
+  pub segments: Vec<Option<f64>>,
+  /// The magnitude both values of a cell had to be below for it to be
+  /// skipped: the threshold, or the column's share of it if larger.
+  pub cutoff: f64,
+  /// The sums of its compared cells' values in each file, after factors
+  /// and sign flips but before any taking of magnitudes, if sums are
+  /// checked.
+  pub sums: Option<(Sum, Sum)>,
+}
+
+impl ColumnStats {
+  /// A column that hasn't seen any cells yet, skipping cells below `cutoff`
+  /// and measuring relative differences by `rel_mode`.
+  pub(crate) fn new(
+    col: usize,
+    segments: usize,
+    cutoff: f64,
+    rel_mode: RelMode,
+  ) -> Self {
+    Self {
+      col,
+      compared: 0,
+      worst_rel: 0.0,
+      worst_diff: 0.0,
+      max_ratio: Extremum::new(rel_mode.identical()),
+      max_diff: Extremum::new(0.0),
+      allclose: Extremum::new(0.0),
+      directions: Directions::new(rel_mode),
+      scaled_diff: None,
+      precision: None,
+      segments: vec![None; segments],
+      cutoff,
+      sums: None,
+    }
+  }
+
+  /// Adds a compared cell's signed values to the column's sums, if they're
+  /// kept.
+  pub(crate) fn add_to_sums(&mut self, (a1, a2): (f64, f64)) {
+    if let Some((sum1, sum2)) = &mut self.sums {
+      sum1.add(a1);
+      sum2.add(a2);
+    }
+  }
+
+  /// Accounts for one compared cell, in the given segment of the rows.
+  pub(crate) fn update(
+    &mut self,
+    a: f64,
+    b: f64,
+    (text1, text2): (&str, &str),
+    segment: usize,
+  ) {
+    self.compared += 1;
+    let rel = (a - b).abs() / a.abs().max(b.abs());
+    if rel > self.worst_rel {
+      self.worst_rel = rel;
+    }
+    self.worst_diff = self.worst_diff.max((a - b).abs());
+    if let Some(worst) = self.segments.get_mut(segment) {
+      *worst = Some(worst.map_or(rel, |w| w.max(rel)));
+    }
+    for digits in [printed_digits(text1), printed_digits(text2)] {
+      self.precision = match (self.precision, digits) {
+        (Some(p), Some(d)) => Some(p.min(d)),
+        (p, d) => p.or(d),
+      };
+    }
+  }
+
+  /// Moves the column's maxima to a compared cell, at a physical line, if
+  /// it's worse.
+  fn raise(&mut self, cell: &Measures, vals: (f64, f64), line: usize) {
+    self.max_diff.raise(cell.diff, vals, line, self.col);
+    self.max_ratio.raise(cell.ratio, vals, line, self.col);
+    if let Some(closeness) = cell.closeness {
+      self.allclose.raise(closeness, vals, line, self.col);
+    }
+    self.directions.raise(cell, vals, line, self.col);
+    if let Some(scale) = cell.scale {
+      let scaled = self.scaled_diff.get_or_insert_with(ScaledDiff::new);
+      scaled.raise((cell.diff, scale), vals, line, self.col);
+    }
+  }
+
+  /// The number of matching significant digits, i.e. -log10 of the worst
+  /// relative difference, clamped to [0, printed precision]. A column without
+  /// any difference scores its full printed precision. Columns where nothing
+  /// was compared have no score at all.
+  pub fn digits_of_agreement(&self) -> Option<f64> {
+    if self.compared == 0 {
+      return None;
+    }
+    let precision = self.precision.unwrap_or(0) as f64;
+    if self.worst_rel == 0.0 {
+      return Some(precision);
+    }
+    let digits = -self.worst_rel.log10();
+    Some(if digits > 0.0 {
+      digits.min(precision)
+    } else {
+      0.0
+    })
+  }
+
+  /// The segment with the worst relative difference; the first one wins
+  /// ties. Columns without any difference have none.
+  pub fn worst_segment(&self) -> Option<usize> {
+    let mut worst: Option<(usize, f64)> = None;
+    for (i, rel) in self.segments.iter().enumerate() {
+      if let Some(rel) = *rel
+        && rel > worst.map_or(0.0, |(_, w)| w)
+      {
+        worst = Some((i, rel));
+      }
+    }
+    worst.map(|(i, _)| i)
+  }
+
+  /// Draws the segments' worst differences as a row of bars, scaled to the
+  /// column's worst; segments where nothing was compared are blank.
+  pub fn sparkline(&self) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    self
+      .segments
+      .iter()
+      .map(|rel| match *rel {
+        None => ' ',
+        Some(0.0) => BARS[0],
+        Some(rel) => {
+          let level = (rel / self.worst_rel * 8.0).ceil() as usize;
+          BARS[level.clamp(1, 8) - 1]
+        }
+      })
+      .collect()
+  }
+}
+
+/// Counts the significant digits printed in a float's mantissa. Zeros have no
+/// meaningful count, so they yield `None`.
+pub(crate) fn printed_digits(cell: &str) -> Option<usize> {
+  let cell = standard_float(cell);
+  let mantissa = cell.split(['E', 'e']).next().unwrap_or("");
+  let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+  let significant = digits.trim_start_matches('0');
+  if significant.is_empty() {
+    None
+  } else {
+    Some(significant.len())
+  }
+}
+
+/// The place value of a float's last printed digit, e.g. 1E-6 for
+/// "1.234560E+00": the width of the interval its text stands for.
+pub(crate) fn quantum(cell: &str) -> f64 {
+  let cell = standard_float(cell);
+  let (mantissa, exponent) = cell.split_once(['E', 'e']).unwrap_or((&cell, ""));
+  let decimals = mantissa.split_once('.').map_or(0, |(_, d)| d.len());
+  let exponent = exponent.parse::<i32>().unwrap_or(0);
+  10f64.powi(exponent - decimals as i32)
+}
+
+/// Everything a comparison found out about two files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffReport {
+  /// The number of rows compared, i.e. in each file, or in the shorter one
+  /// when extra rows are allowed, less any left out for structural
+  /// problems.
+  pub rows: usize,
+  /// The rows of the longer file that were left out, if extra rows are
+  /// allowed and there were any.
+  pub ignored_rows: Option<IgnoredRows>,
+  /// What the rows with structural problems did to the comparison.
+  pub structure: Structure,
+  /// The rows left out for structural problems, if those were lenient; any
+  /// fails the comparison unless they only warn.
+  pub structure_issues: Vec<StructureIssue>,
+  /// The physical (1-based) line after which `fail_fast` stopped the
+  /// comparison, if it did; everything else covers only the rows up to it,
+  /// which `rows` counts.
+  pub aborted_at: Option<usize>,
+  /// How many subnormal values of the compared rows' float columns were
+  /// taken as zero, in each file.
+  pub clamped: (usize, usize),
+  /// How many compared cells of the columns compared by magnitude had
+  /// values of opposite signs.
+  pub sign_differences: usize,
+  /// The cells of float columns skipped for being zero or below their
+  /// column's cutoff, by what skipped them.
+  pub skipped: SkippedCells,
+  /// Whether the columns' sums were checked, and what they decide.
+  pub sum_check: SumCheck,
+  /// Each float column's sums and their verdict, in column order, if they
+  /// were checked; any failing fails the comparison.
+  pub sums: Vec<ColumnSum>,
+  /// The cells of float columns left out of the comparison by
+  /// `excluded_cells`, as (physical line, 1-based column) of csv1.
+  pub excluded: BTreeSet<(usize, usize)>,
+  /// The listed exclusions that left out no cell, if they were audited;
+  /// any fails the comparison.
+  pub stale_exclusions: Vec<String>,
+  /// How `max_ratio` is measured.
+  pub rel_mode: RelMode,
+  /// The denominator floor `max_ratio` was measured with.
+  pub rel_floor: f64,
+  /// The worst relative difference, by default the ratio
+  /// max(|a|,|b|)/min(|a|,|b|).
+  pub max_ratio: Extremum,
+  /// The worst absolute difference.
+  pub max_diff: Extremum,
+  /// The worst cell for the combined tolerance, if one was given: its
+  /// absolute difference as a multiple of what the tolerance allows there,
+  /// so that it's within it up to 1.
+  pub allclose: Extremum,
+  /// The cells tied with `max_ratio`'s.
+  pub ratio_ties: Ties,
+  /// The cells tied with `max_diff`'s.
+  pub diff_ties: Ties,
+  /// The compared cells zero in exactly one file; any with a nonzero value
+  /// reaching its limit, if there's one, fails the comparison.
+  pub zero_mismatches: ZeroMismatches,
+  /// The worst cell for its row's scale, which the absolute difference
+  /// tolerance is checked against in place of `max_diff`, if it's scaled.
+  pub scaled_diff: Option<ScaledDiff>,
+  /// How many compared rows had no number to scale the absolute difference
+  /// tolerance by, and kept it unscaled.
+  pub unscaled_rows: usize,
+  /// The worst cells in each direction, which directional tolerances are
+  /// checked against.
+  pub directions: Directions,
+  /// The (0-based) columns whose csv2 values were negated before comparing.
+  pub flipped_columns: Vec<usize>,
+  /// Statistics for each float column, in column order.
+  pub columns: Vec<ColumnStats>,
+  /// The columns of csv1 paired with columns of csv2 but left out of the
+  /// comparison for a cell that isn't a float, in column order.
+  pub demoted_columns: Vec<DemotedColumn>,
+  /// The requirements on the compared columns the comparison didn't meet,
+  /// if there were any; any fails the comparison.
+  pub column_failures: Vec<String>,
+  /// How many rows were compared and how many csv1 had, if only a sample
+  /// of them was: the caller samples them, so the comparison itself only
+  /// sees those compared.
+  pub sampled_rows: Option<(usize, usize)>,
+  /// How many compared cells exceed each tolerance on their own.
+  pub failing_cells: FailingCells,
+  /// Where the ratio tolerance is exceeded, if one was given.
+  pub ratio_mass: ViolationMass,
+  /// Where the absolute difference tolerance is exceeded, if one was given.
+  pub diff_mass: ViolationMass,
+  /// The distribution of the compared cells' relative differences, if
+  /// asked for.
+  pub histogram: Option<Histogram>,
+  /// The distribution of the compared cells' relative differences, for
+  /// percentiles, if asked for.
+  pub percentiles: Option<Percentiles>,
+  /// The outcome of each gate the tolerances enable.
+  pub gates: Vec<GateResult>,
+  /// The compared cells annotated differently in each file, if annotations
+  /// were compared; any fails the comparison whatever the gates say.
+  pub annotation_mismatches: Vec<AnnotationMismatch>,
+  /// The assertions of the tolerance spec that didn't hold, if it made any;
+  /// any fails the comparison whatever the gates say.
+  pub assertion_failures: Vec<AssertionFailure>,
+  /// How the complex pairs' magnitudes and phases differ, if there are any;
+  /// a phase exceeding its tolerance fails the comparison.
+  pub complex: Option<PhaseReport>,
+  /// The physical (1-based) lines of each file's rows left unpaired when
+  /// rows are matched by a column's nearest value; any fails the
+  /// comparison.
+  pub unpaired_rows: (Vec<usize>, Vec<usize>),
+  /// The (1-based) float columns of each file left unpaired when columns
+  /// are paired by MAC; any fails the comparison.
+  pub unpaired_columns: (Vec<usize>, Vec<usize>),
+  /// Each group's comparison, when rows are grouped by a column; a group
+  /// failing fails the comparison.
+  pub groups: Vec<GroupReport>,
+  /// Each column with tolerances of its own, compared on its own under
+  /// them; a column failing fails the comparison.
+  pub overrides: Vec<ColumnOverride>,
+  /// The outcome of each gate the warning tolerances enable; one failing
+  /// only warns.
+  pub warnings: Vec<GateResult>,
+  /// How the files differ as matrices, if they were taken as such; a
+  /// relative norm of the difference exceeding its tolerance fails the
+  /// comparison.
+  pub matrix: Option<MatrixReport>,
+  /// The compared cells by impact class, if any column has a
+  /// significance.
+  pub impact: Option<ImpactReport>,
+}
+
+impl Default for DiffReport {
+  fn default() -> Self {
+    Self {
+      rows: 0,
+      ignored_rows: None,
+      structure: Structure::Strict,
+      structure_issues: Vec::new(),
+      aborted_at: None,
+      clamped: (0, 0),
+      sign_differences: 0,
+      skipped: SkippedCells::default(),
+      sum_check: SumCheck::Off,
+      sums: Vec::new(),
+      excluded: BTreeSet::new(),
+      stale_exclusions: Vec::new(),
+      rel_mode: RelMode::Ratio,
+      rel_floor: 0.0,
+      max_ratio: Extremum::new(1.0),
+      max_diff: Extremum::new(0.0),
+      allclose: Extremum::new(0.0),
+      ratio_ties: Ties::new(1.0, 0),
+      diff_ties: Ties::new(0.0, 0),
+      zero_mismatches: ZeroMismatches::new(None),
+      scaled_diff: None,
+      unscaled_rows: 0,
+      directions: Directions::new(RelMode::Ratio),
+      flipped_columns: Vec::new(),
+      columns: Vec::new(),
+      demoted_columns: Vec::new(),
+      column_failures: Vec::new(),
+      sampled_rows: None,
+      failing_cells: FailingCells::default(),
+      ratio_mass: ViolationMass::default(),
+      diff_mass: ViolationMass::default(),
+      histogram: None,
+      percentiles: None,
+      gates: Vec::new(),
+      annotation_mismatches: Vec::new(),
+      assertion_failures: Vec::new(),
+      complex: None,
+      unpaired_rows: (Vec::new(), Vec::new()),
+      unpaired_columns: (Vec::new(), Vec::new()),
+      groups: Vec::new(),
+      overrides: Vec::new(),
+      warnings: Vec::new(),
+      matrix: None,
+      impact: None,
+    }
+  }
+}
+
+impl DiffReport {
+  /// Moves every cell it locates, in csv1 as transposed, to csv1 as it was
+  /// written, whose physical lines `line` gives by the transposed records'
+  /// (1-based) columns: a cell's line becomes its column, and its column's
+  /// line its line, and so do the lines and columns violations concentrate
+  /// in. Column statistics stay by the column compared.
+  pub fn untranspose(&mut self, line: &dyn Fn(usize) -> usize) {
+    let map = &|(row, col)| match row {
+      0 => (row, col),
+      _ => (line(col), row),
+    };
+    let directions = |d: &mut Directions| {
+      for directed in [&mut d.over, &mut d.under] {
+        directed.max_ratio.map_cell(map);
+        directed.max_diff.map_cell(map);
+      }
+    };
+    self.max_ratio.map_cell(map);
+    self.max_diff.map_cell(map);
+    self.allclose.map_cell(map);
+    self.ratio_ties.map_cells(map);
+    self.diff_ties.map_cells(map);
+    self.zero_mismatches.worst.map_cell(map);
+    directions(&mut self.directions);
+    let scaled = self.columns.iter_mut().map(|c| &mut c.scaled_diff);
+    for scaled in std::iter::once(&mut self.scaled_diff).chain(scaled) {
+      scaled.iter_mut().for_each(|s| s.worst.map_cell(map));
+    }
+    for column in &mut self.columns {
+      column.max_ratio.map_cell(map);
+      column.max_diff.map_cell(map);
+      column.allclose.map_cell(map);
+      directions(&mut column.directions);
+    }
+    if let Some(impact) = &mut self.impact {
+      for class in [&mut impact.low, &mut impact.high] {
+        class.max_ratio.map_cell(map);
+        class.max_diff.map_cell(map);
+      }
+    }
+    self.excluded = self.excluded.iter().map(|&cell| map(cell)).collect();
+    for mass in [&mut self.ratio_mass, &mut self.diff_mass] {
+      let lines = mass.cols.iter().map(|(&col, &m)| (line(col), m)).collect();
+      mass.cols = std::mem::replace(&mut mass.lines, lines);
+    }
+    let groups = self.groups.iter_mut().map(|g| &mut g.report);
+    let overrides = self.overrides.iter_mut().map(|o| &mut o.report);
+    for report in groups.chain(overrides) {
+      report.untranspose(line);
+    }
+  }
+
+  /// Whether every enabled gate passed, unless only the sums are checked,
+  /// every checked column sum passed, no compared cell's annotations
+  /// differ, every assertion held, no phase is out of tolerance, every row
+  /// and float column was paired, every group passed, the difference
+  /// matrix's norms are within their tolerance, no audited exclusion is
+  /// stale, no row was left out for a structural problem that fails, no
+  /// cell zero in one file only has a nonzero value reaching its limit, and
+  /// the compared columns meet their requirements.
+  pub fn passed(&self) -> bool {
+    let sums_only = self.sum_check == SumCheck::Only;
+    (sums_only || self.gates.iter().all(|g| g.passed))
+      && Check::ALL.into_iter().all(|check| self.check_passed(check))
+  }
+
+  /// Whether a check beyond the gates passed; one that wasn't made did.
+  pub fn check_passed(&self, check: Check) -> bool {
+    match check {
+      Check::Sums => self.sums.iter().all(|s| s.passed),
+      Check::Annotations => self.annotation_mismatches.is_empty(),
+      Check::Assertions => self.assertion_failures.is_empty(),
+      Check::Complex => self.complex.as_ref().is_none_or(PhaseReport::passed),
+      Check::UnpairedRows => {
+        self.unpaired_rows.0.is_empty() && self.unpaired_rows.1.is_empty()
+      }
+      Check::UnpairedColumns => {
+        self.unpaired_columns.0.is_empty() && self.unpaired_columns.1.is_empty()
+      }
+      Check::Groups => self.groups.iter().all(|g| g.report.passed()),
+      Check::Overrides => self.overrides.iter().all(|o| o.report.passed()),
+      Check::Matrix => self.matrix.as_ref().is_none_or(MatrixReport::passed),
+      Check::Exclusions => self.stale_exclusions.is_empty(),
+      Check::Structure => {
+        self.structure_issues.is_empty() || self.structure == Structure::Warn
+      }
+      Check::ZeroMismatches => self.zero_mismatches.passed(),
+      Check::Columns => self.column_failures.is_empty(),
+    }
+  }
+
+  /// The status the report gives `gate` alongside its worst cell: whether
+  /// it passed, or with [`SumCheck::Only`] whatever it found, since the
+  /// cells' gates don't decide the verdict then, and only if every other
+  /// check passed too, so that no status passes a report [`Self::passed`]
+  /// fails.
+  pub fn gate_status(&self, gate: Gate) -> bool {
+    let sums_only = self.sum_check == SumCheck::Only;
+    let passed = self.gates.iter().find(|g| g.gate == gate);
+    (sums_only || passed.is_none_or(|g| g.passed))
+      && Check::ALL.into_iter().all(|check| self.check_passed(check))
+  }
+
+  /// How many columns were compared, those with tolerances of their own
+  /// included.
+  pub fn compared_columns(&self) -> usize {
+    let overrides = self.overrides.iter().map(|o| o.report.columns.len());
+    self.columns.len() + overrides.sum::<usize>()
+  }
+
+  /// The columns left out for a cell that isn't a float, in column order,
+  /// those with tolerances of their own included.
+  pub fn demoted_columns(&self) -> Vec<DemotedColumn> {
+    let overrides = self.overrides.iter().map(|o| &o.report.demoted_columns);
+    let mut demoted: Vec<DemotedColumn> =
+      std::iter::once(&self.demoted_columns)
+        .chain(overrides)
+        .flatten()
+        .cloned()
+        .collect();
+    demoted.sort_unstable_by_key(|d| d.col);
+    demoted
+  }
+
+  /// The compared cells by impact class, those of the columns with
+  /// tolerances of their own included, if any column has a significance.
+  pub fn impact_classes(&self) -> Option<ImpactReport> {
+    let impacts = self.overrides.iter().map(|o| &o.report.impact);
+    let mut classes: Option<ImpactReport> = None;
+    for impact in std::iter::once(&self.impact).chain(impacts).flatten() {
+      match &mut classes {
+        Some(classes) => classes.merge(impact),
+        None => classes = Some(impact.clone()),
+      }
+    }
+    classes
+  }
+
+  /// The cells left out by `excluded_cells`, those of the columns with
+  /// tolerances of their own included.
+  pub fn excluded_cells(&self) -> BTreeSet<(usize, usize)> {
+    let overrides = self.overrides.iter().map(|o| &o.report.excluded);
+    std::iter::once(&self.excluded)
+      .chain(overrides)
+      .flatten()
+      .copied()
+      .collect()
+  }
+
+  /// The gates of the warning tolerances that failed where the same gate
+  /// of the tolerances, if enabled, passed: those with a cell between the
+  /// two tolerances.
+  pub fn warnings_raised(&self) -> impl Iterator<Item = &GateResult> {
+    self.warnings.iter().filter(|warning| {
+      !warning.passed
+        && self
+          .gates
+          .iter()
+          .all(|g| g.gate != warning.gate || g.passed)
+    })
+  }
+
+  /// Whether a warning was raised, or a row was left out for a structural
+  /// problem that only warns. A comparison that fails as well is a failure
+  /// all the same.
+  pub fn warned(&self) -> bool {
+    self.warnings_raised().next().is_some()
+      || (!self.structure_issues.is_empty()
+        && self.structure == Structure::Warn)
+  }
+
+  /// The worst relative difference, in percent.
+  pub fn ratio_percent(&self) -> f64 {
+    self.rel_mode.percent(self.max_ratio.value)
+  }
+
+  /// Whether the worst relative difference was measured against the
+  /// denominator floor rather than the values.
+  pub fn ratio_floored(&self) -> bool {
+    let (a, b) = self.max_ratio.vals;
+    self.max_ratio.line != 0 && self.rel_mode.floors(a, b, self.rel_floor)
+  }
+
+  /// How many cells were compared, over all columns.
+  pub fn compared(&self) -> usize {
+    self.columns.iter().map(|c| c.compared).sum()
+  }
+
+  /// The report of a single float column, by its (1-based) column in csv1,
+  /// if it was compared: its statistics and maxima alone. How many of its
+  /// cells exceed each tolerance isn't kept, so it's only fit to be judged
+  /// by its worst cells, without a `max_fail_fraction`.
+  pub fn column_report(&self, col: usize) -> Option<DiffReport> {
+    let column = self.columns.iter().find(|c| c.col == col)?;
+    Some(DiffReport {
+      rows: self.rows,
+      rel_mode: self.rel_mode,
+      rel_floor: self.rel_floor,
+      max_ratio: column.max_ratio,
+      max_diff: column.max_diff,
+      allclose: column.allclose,
+      scaled_diff: column
+        .scaled_diff
+        .or(self.scaled_diff.map(|_| ScaledDiff::new())),
+      directions: column.directions,
+      columns: vec![column.clone()],
+      ..Default::default()
+    })
+  }
+
+  /// The fraction of the compared cells that exceed a gate's tolerance on
+  /// their own; zero if nothing was compared.
+  pub fn failing_fraction(&self, gate: Gate) -> f64 {
+    match self.compared() {
+      0 => 0.0,
+      compared => self.failing_cells.of(gate) as f64 / compared as f64,
+    }
+  }
+
+  /// The column with the fewest digits of agreement, as (digits, column).
+  pub fn worst_digits(&self) -> Option<(f64, usize)> {
+    self
+      .columns
+      .iter()
+      .filter_map(|c| c.digits_of_agreement().map(|d| (d, c.col)))
+      .fold(None, |worst, (d, c)| match worst {
+        Some((w, _)) if w <= d => worst,
+        _ => Some((d, c)),
+      })
+  }
+
+  /// The lines and columns carrying more than `fraction` of a gate's excess.
+  pub fn dominance(&self, fraction: f64) -> Vec<Dominance> {
+    let mut found = Vec::new();
+    for (gate, mass) in [
+      (Gate::Ratio, &self.ratio_mass),
+      (Gate::Diff, &self.diff_mass),
+    ] {
+      if let Some((share, line)) = mass.worst_line()
+        && share > fraction
+      {
+        found.push(Dominance::Line(gate, line, share));
+      }
+      if let Some((share, col)) = mass.worst_col()
+        && share > fraction
+      {
+        found.push(Dominance::Column(gate, col, share));
+      }
+    }
+    found
+  }
+
+  /// Compares two reports field by field, returning the fields that differ
+  /// as (this, other), each written `name: value`.
+  pub fn discrepancies(&self, other: &Self) -> Vec<(String, String)> {
+    self
+      .fields()
+      .into_iter()
+      .zip(other.fields())
+      .filter(|(a, b)| a != b)
+      .collect()
+  }
+
+  /// Every field, written `name: value`, in order.
+  fn fields(&self) -> Vec<String> {
+    // taken apart whole, so that a field added can't be left out
+    let DiffReport {
+      rows,
+      ignored_rows,
+      structure,
+      structure_issues,
+      aborted_at,
+      clamped,
+      sign_differences,
+      skipped,
+      sum_check,
+      sums,
+      excluded,
+      stale_exclusions,
+      rel_mode,
+      rel_floor,
+      max_ratio,
+      max_diff,
+      allclose,
+      ratio_ties,
+      diff_ties,
+      zero_mismatches,
+      scaled_diff,
+      unscaled_rows,
+      directions,
+      flipped_columns,
+      columns,
+      demoted_columns,
+      column_failures,
+      sampled_rows,
+      failing_cells,
+      ratio_mass,
+      diff_mass,
+      histogram,
+      percentiles,
+      gates,
+      annotation_mismatches,
+      assertion_failures,
+      complex,
+      unpaired_rows,
+      unpaired_columns,
+      groups,
+      overrides,
+      warnings,
+      matrix,
+      impact,
+    } = self;
+    let fields: [(&str, &dyn Debug); 44] = [
+      ("rows", rows),
+      ("ignored_rows", ignored_rows),
+      ("structure", structure),
+      ("structure_issues", structure_issues),
+      ("aborted_at", aborted_at),
+      ("clamped", clamped),
+      ("sign_differences", sign_differences),
+      ("skipped", skipped),
+      ("sum_check", sum_check),
+      ("sums", sums),
+      ("excluded", excluded),
+      ("stale_exclusions", stale_exclusions),
+      ("rel_mode", rel_mode),
+      ("rel_floor", rel_floor),
+      ("max_ratio", max_ratio),
+      ("max_diff", max_diff),
+      ("allclose", allclose),
+      ("ratio_ties", ratio_ties),
+      ("diff_ties", diff_ties),
+      ("zero_mismatches", zero_mismatches),
+      ("scaled_diff", scaled_diff),
+      ("unscaled_rows", unscaled_rows),
+      ("directions", directions),
+      ("flipped_columns", flipped_columns),
+      ("columns", columns),
+      ("demoted_columns", demoted_columns),
+      ("column_failures", column_failures),
+      ("sampled_rows", sampled_rows),
+      ("failing_cells", failing_cells),
+      ("ratio_mass", ratio_mass),
+      ("diff_mass", diff_mass),
+      ("histogram", histogram),
+      ("percentiles", percentiles),
+      ("gates", gates),
+      ("annotation_mismatches", annotation_mismatches),
+      ("assertion_failures", assertion_failures),
+      ("complex", complex),
+      ("unpaired_rows", unpaired_rows),
+      ("unpaired_columns", unpaired_columns),
+      ("groups", groups),
+      ("overrides", overrides),
+      ("warnings", warnings),
+      ("matrix", matrix),
+      ("impact", impact),
+    ];
+    fields
+      .iter()
+      .map(|(name, value)| format!("{name}: {value:?}"))
+      .collect()
+  }
+}
+
+/// Compares the records of two files. This is the path used for actual runs;
+/// see the `reference` module for its executable specification.
+pub fn compare(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+  paths: (&str, &str),
+) -> Result<DiffReport, DiffError> {
+  compare_timed(records1, records2, opts, paths, None)
+}
+
+/// Like [`compare`], also returning the time spent classifying columns,
+/// parsing floats and comparing them. With several column workers, the
+/// parsing is counted as comparing.
+pub fn compare_profiled(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+  paths: (&str, &str),
+) -> Result<(DiffReport, PhaseTimes), DiffError> {
+  let mut times = PhaseTimes::default();
+  let report =
+    compare_timed(records1, records2, opts, paths, Some(&mut times))?;
+  Ok((report, times))
+}
+
+/// Compares the records of two files, adding the time spent in each phase
+/// to `times`, if given.
+fn compare_timed(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+  paths: (&str, &str),
+  mut times: Option<&mut PhaseTimes>,
+) -> Result<DiffReport, DiffError> {
+  let float_re = &opts.float_re;
+  let mut clock = times.is_some().then(Instant::now);
+  // the time since the last lap, if timing
+  let mut lap = |times: &mut Option<&mut PhaseTimes>, phase: Phase| {
+    if let (Some(times), Some(clock)) = (times, &mut clock) {
+      times.add(phase, clock.elapsed());
+      *clock = Instant::now();
+    }
+  };
+
+  let ignored_rows = extra_rows(records1, records2);
+  if let Some(extra) = &ignored_rows {
+    let (longer, path) = match extra.file {
+      1 => (records1, paths.0),
+      _ => (records2, paths.1),
+    };
+    let first = longer.len() - extra.count;
+    let delimiter = char::from(opts.delimiter).to_string();
+    let disallowed = match &opts.extra_rows {
+      ExtraRows::Forbid => Some(first),
+      ExtraRows::Allow => None,
+      ExtraRows::AllowMatching(pattern) => (first..longer.len()).find(|&row| {
+        let text = longer[row].iter().collect::<Vec<_>>().join(&delimiter);
+        !pattern.is_match(&text)
+      }),
+    };
+    if let Some(row) = disallowed {
+      return Err(DiffError::RowCountMismatch {
+        rows1: records1.len(),
+        rows2: records2.len(),
+        file: path.to_string(),
+        line: physical_line(longer, row),
+      });
+    }
+  }
+  let rows = records1.len().min(records2.len());
+  let (records1, records2) = (&records1[..rows], &records2[..rows]);
+  let sound = (opts.structure != Structure::Strict)
+    .then(|| sound_rows(records1, records2, opts));
+  let (records1, records2) = match &sound {
+    Some((sound1, sound2, _)) => (&sound1[..], &sound2[..]),
+    None => (records1, records2),
+  };
+
+  // First pass: determine which columns contain only floats in both files,
+  // and how large their values get if the threshold depends on it
+  let mut float_columns: Option<Vec<bool>> = None;
+  let mut magnitudes: Vec<f64> = Vec::new();
+  let mut demoted = Vec::new();
+
+  for (row, (rec1, rec2)) in records1.iter().zip(records2).enumerate() {
+    // column counts, and the layout if strict
+    check_structure(row, (records1, records2), opts, paths)?;
+    let len1 = rec1.len();
+
+    // Initialize float_columns on first row
+    if float_columns.is_none() {
+      float_columns = Some(vec![true; len1]);
+      magnitudes = vec![0.0; len1];
+    }
+
+    let float_cols = float_columns.as_mut().unwrap();
+
+    // Check each column to see if it's a float in both files
+    for (i, cell1) in rec1.iter().enumerate() {
+      let Some(cell2) = opts.col2(i).and_then(|j| rec2.get(j)) else {
+        float_cols[i] = false;
+        continue;
+      };
+      if float_cols[i] {
+        let stray = [(1, cell1), (2, cell2)]
+          .into_iter()
+          .find(|(_, cell)| !is_float(cell, float_re));
+        if let Some((file, cell)) = stray {
+          float_cols[i] = false;
+          demoted.push(DemotedColumn {
+            col: i + 1,
+            line: physical_line(records1, row),
+            floats_before: row,
+            file,
+            cell: cell.to_string(),
+          });
+        }
+      }
+      if float_cols[i] && opts.threshold_frac > 0.0 {
+        // unparsable cells are reported by the second pass
+        let factors = opts.factors(i);
+        for (factor, cell) in [(factors.0, cell1), (factors.1, cell2)] {
+          let value =
+            parse_float(cell).map_or(0.0, |v| opts.value(factor, v).abs());
+          magnitudes[i] = magnitudes[i].max(value);
+        }
+      }
+    }
+  }
+
+  let float_cols = float_columns.unwrap_or_default();
+  demoted.sort_unstable_by_key(|d: &DemotedColumn| d.col);
+  lap(&mut times, Phase::Classifying);
+
+  // Track maxima for reporting
+  let mut report = DiffReport {
+    rows: records1.len(),
+    ignored_rows,
+    structure: opts.structure,
+    structure_issues: sound.as_ref().map_or_else(Vec::new, |s| s.2.clone()),
+    rel_mode: opts.rel_mode,
+    rel_floor: opts.rel_floor,
+    sum_check: opts.sum_check,
+    max_ratio: Extremum::new(opts.rel_mode.identical()),
+    ratio_ties: Ties::new(opts.rel_mode.identical(), opts.listed_ties),
+    diff_ties: Ties::new(0.0, opts.listed_ties),
+    zero_mismatches: ZeroMismatches::new(opts.max_zero_mismatch),
+    scaled_diff: opts.diff_scale.map(|_| ScaledDiff::new()),
+    directions: Directions::new(opts.rel_mode),
+    demoted_columns: demoted,
+    histogram: opts.histogram.as_deref().map(Histogram::new),
+    percentiles: opts.percentiles.as_deref().map(Percentiles::new),
+    impact: (!opts.significance.is_empty())
+      .then(|| ImpactReport::new(opts.rel_mode)),
+    ..Default::default()
+  };
+  let mut column_index = vec![usize::MAX; float_cols.len()];
+  for (i, _) in float_cols.iter().enumerate().filter(|(_, f)| **f) {
+    column_index[i] = report.columns.len();
+    let cutoff = match magnitudes.get(i) {
+      Some(&magnitude) => opts.threshold.max(opts.threshold_frac * magnitude),
+      None => opts.threshold,
+    };
+    let mut column =
+      ColumnStats::new(i + 1, opts.segments, cutoff, opts.rel_mode);
+    column.sums = opts.sum_check.enabled().then(Default::default);
+    report.columns.push(column);
+  }
+
+  // Decide which columns compare better with csv2's signs flipped
+  let mut flipped = vec![false; float_cols.len()];
+  if !matches!(opts.sign_flip, SignFlip::Never) {
+    let mut straight = vec![0.0; float_cols.len()];
+    let mut negated = vec![0.0; float_cols.len()];
+    for (rec1, rec2) in records1.iter().zip(records2) {
+      for (i, cell1) in rec1.iter().enumerate() {
+        if float_cols[i] && opts.sign_flip.allows(i) {
+          // the first pass checked that compared columns exist in both files
+          let cell2 = &rec2[opts.col2(i).unwrap()];
+          let factors = opts.factors(i);
+          let (a1, a2) = (
+            opts.value(factors.0, parse_float(cell1).unwrap()),
+            opts.value(factors.1, parse_float(cell2).unwrap()),
+          );
+          straight[i] += (a1 - a2).abs();
+          negated[i] += (a1 + a2).abs();
+        }
+      }
+    }
+    for i in 0..float_cols.len() {
+      if negated[i] < straight[i] {
+        flipped[i] = true;
+        report.flipped_columns.push(i);
+      }
+    }
+  }
+
+  // Second pass: compare float values, on several threads if asked to
+  let parallel = opts.column_workers > 1 && report.columns.len() > 1;
+  if parallel && opts.fail_fast.is_none() {
+    if opts.diff_scale.is_some() {
+      let unscaled = records1.iter().filter(|r| opts.row_scale(r).is_none());
+      report.unscaled_rows = unscaled.count();
+    }
+    compare_columns(records1, records2, opts, &flipped, &mut report);
+    if let Some(percentiles) = &mut report.percentiles {
+      percentiles.finish();
+    }
+    report.gates = opts.tolerances.evaluate(&report);
+    report.sums = opts.tolerances.check_sums(&report);
+    report.warnings = opts.warn_tolerances.evaluate(&report);
+    lap(&mut times, Phase::Comparing);
+    return Ok(report);
+  }
+  let mut violations = 0;
+  let mut samples = Samples::default();
+  // reused from row to row, which matters for rows millions of cells wide
+  let (mut f1, mut f2) = (Vec::new(), Vec::new());
+  for (row, (rec1, rec2)) in records1.iter().zip(records2).enumerate() {
+    if let Some(progress) = &opts.progress {
+      progress.add_rows(1);
+    }
+    let line_num = physical_line(records1, row);
+    let sample = (times.is_some() && Samples::takes(row)).then(Instant::now);
+    samples.rows += 1;
+    if let Some(limit) = opts.fail_fast
+      && violations >= limit
+    {
+      report.rows = row;
+      report.aborted_at = Some(physical_line(records1, row - 1));
+      break;
+    }
+    let scale = opts.diff_scale.map(|_| opts.row_scale(rec1));
+    report.unscaled_rows += usize::from(scale == Some(None));
+    let scale = scale.map(|scale| scale.unwrap_or(1.0));
+
+    // Extract floats from float columns only, by csv1's column, reading
+    // csv2's from the columns compared with them
+    let floats = |rec: &StringRecord,
+                  col: &dyn Fn(usize) -> Option<usize>,
+                  file: &str,
+                  into: &mut Vec<(usize, f64)>| {
+      into.clear();
+      let cells = (0..float_cols.len())
+        .filter(|&i| float_cols[i])
+        .filter_map(|i| Some((i, rec.get(col(i)?)?)))
+        .filter(|(_, f)| looks_like_float(f, float_re));
+      for (i, f) in cells {
+        match parse_float(f) {
+          Some(v) => into.push((i, v)),
+          None => {
+            return Err(DiffError::ParseError {
+              file: file.to_string(),
+              line: line_num,
+              value: f.to_string(),
+            });
+          }
+        }
+      }
+      Ok(())
+    };
+    floats(rec1, &Some, paths.0, &mut f1)?;
+    floats(rec2, &|i| opts.col2(i), paths.1, &mut f2)?;
+    let sample = sample.map(|start| {
+      samples.sampled += 1;
+      samples.parsing += start.elapsed();
+      Instant::now()
+    });
+
+    if f1.is_empty() && f2.is_empty() {
+      continue;
+    }
+    if f1.len() != f2.len() {
+      return Err(DiffError::FloatCountMismatch { line: line_num });
+    }
+    report.clamped.0 += f1.iter().filter(|(_, v)| opts.clamps(*v)).count();
+    report.clamped.1 += f2.iter().filter(|(_, v)| opts.clamps(*v)).count();
+
+    // Compare
+    for ((i, v1), (_, v2)) in f1.iter().zip(&f2) {
+      if opts.excludes(line_num, *i) {
+        report.excluded.insert((line_num, i + 1));
+        continue;
+      }
+      let stats = &mut report.columns[column_index[*i]];
+      let texts = (&rec1[*i], &rec2[opts.col2(*i).unwrap()]);
+      let factors = opts.factors(*i);
+      let (v1, v2) = (opts.value(factors.0, *v1), opts.value(factors.1, *v2));
+      let cutoff = (stats.cutoff, opts.threshold_mode);
+      let (a1, a2) = match compared_values(v1, v2, flipped[*i], cutoff) {
+        Ok(values) => values,
+        Err(skip) => {
+          report.skipped.add(skip);
+          if let Some(percentiles) = &mut report.percentiles {
+            percentiles.skip();
+          }
+          continue;
+        }
+      };
+
+      let abs = opts.abs_compare.applies(*i);
+      report.sign_differences += usize::from(abs && opposite_signs(a1, a2));
+      let signed = (a1, a2);
+      stats.add_to_sums(signed);
+      let magnitudes = if abs { (a1.abs(), a2.abs()) } else { signed };
+      let (a1, a2) = opts.measured(magnitudes, texts, factors);
+      // compared by magnitude, the worst cells are still shown signed
+      let shown = if abs { signed } else { (a1, a2) };
+      let segment = segment_of(row, records1.len(), opts.segments);
+      stats.update(a1, a2, texts, segment);
+
+      let cell = Measures::of(a1, a2, opts, scale);
+      stats.raise(&cell, shown, line_num);
+      let failing = cell.failing(a1, a2, texts, opts);
+      if let Some(histogram) = &mut report.histogram {
+        histogram.add(opts.rel_mode.percent(cell.ratio));
+      }
+      if let Some(percentiles) = &mut report.percentiles {
+        percentiles.add(opts.rel_mode.percent(cell.ratio));
+      }
+      if let Some(impact) = &mut report.impact
+        && !classify(
+          impact,
+          (&cell, failing),
+          ((a1, a2), shown),
+          (line_num, *i),
+          opts,
+        )
+      {
+        continue;
+      }
+      report.failing_cells.add(failing);
+      if failing.any() {
+        violations += 1;
+      }
+      if let Some(excess) = cell.diff_excess {
+        report.diff_mass.add(line_num, i + 1, excess);
+      }
+      if let Some(excess) = cell.ratio_excess {
+        report.ratio_mass.add(line_num, i + 1, excess);
+      }
+      report.max_diff.raise(cell.diff, shown, line_num, i + 1);
+      report.max_ratio.raise(cell.ratio, shown, line_num, i + 1);
+      if let Some(closeness) = cell.closeness {
+        report.allclose.raise(closeness, shown, line_num, i + 1);
+      }
+      report.diff_ties.add(cell.diff, line_num, i + 1);
+      report.ratio_ties.add(cell.ratio, line_num, i + 1);
+      let vals = ((a1, a2), shown);
+      report.zero_mismatches.add(vals, line_num, i + 1);
+      report.directions.raise(&cell, shown, line_num, i + 1);
+      if let (Some(scaled), Some(scale)) = (&mut report.scaled_diff, scale) {
+        scaled.raise((cell.diff, scale), shown, line_num, i + 1);
+      }
+    }
+    if let Some(start) = sample {
+      samples.comparing += start.elapsed();
+    }
+  }
+
+  if let Some(percentiles) = &mut report.percentiles {
+    percentiles.finish();
+  }
+  report.gates = opts.tolerances.evaluate(&report);
+  report.sums = opts.tolerances.check_sums(&report);
+  report.warnings = opts.warn_tolerances.evaluate(&report);
+  if let Some(times) = times {
+    samples.scale_into(times);
+  }
+  Ok(report)
+}
+
+/// The values to compare at a cell, with csv2's negated if its column is
+/// flipped, or why the cell is skipped: for being zero in both files, or
+/// below the column's cutoff as the threshold mode has it.
+pub(crate) fn compared_values(
+  v1: f64,
+  v2: f64,
+  flipped: bool,
+  (cutoff, mode): (f64, ThresholdMode),
+) -> Result<(f64, f64), Skip> {
+  let a1 = v1;
+  let a2 = if flipped { -v2 } else { v2 };
+  if a1 == 0.0 && a2 == 0.0 {
+    return Err(Skip::Zeros);
+  }
+  match mode.skips(a1.abs() < cutoff, a2.abs() < cutoff) {
+    Some(skip) => Err(skip),
+    None => Ok((a1, a2)),
+  }
+}
+
+/// Rounds a value to a number of significant digits, at least one, in
+/// decimal: as it would be printed with them, half-way cases going to even.
+/// Values that aren't finite are left as they are.
+pub fn round_significant(value: f64, digits: u32) -> f64 {
+  if !value.is_finite() {
+    return value;
+  }
+  let precision = digits.max(1) as usize - 1;
+  // the formatting is exact, so the rounding is of the decimal expansion
+  format!("{value:.precision$e}").parse().unwrap()
+}
+
+/// Whether two values have opposite signs, neither being zero.
+fn opposite_signs(a: f64, b: f64) -> bool {
+  (a < 0.0 && b > 0.0) || (a > 0.0 && b < 0.0)
+}
+
+/// Counts a compared cell, found at a physical line and (0-based) column, in
+/// its impact class, by its values as measured and as shown. Returns
+/// whether it's judged, as every cell is unless only high-impact ones are.
+fn classify(
+  impact: &mut ImpactReport,
+  (cell, failing): (&Measures, FailingCells),
+  (vals, shown): ((f64, f64), (f64, f64)),
+  (line, col): (usize, usize),
+  opts: &DiffConfig,
+) -> bool {
+  let class = Impact::of(vals, opts.significance.get(&col).copied());
+  let at = (line, col + 1);
+  impact
+    .class_mut(class)
+    .add((cell.ratio, cell.diff), shown, at, failing);
+  class == Impact::High || !opts.high_impact_only
+}
+
+/// The report of comparing a single pair of values, as measured, found at
+/// a line and (1-based) column of a row whose absolute difference tolerance
+/// has `scale` if it's scaled; its gates are their verdict against each of
+/// `opts`'s tolerances.
+pub(crate) fn compare_alone(
+  (a1, a2): (f64, f64),
+  texts: (&str, &str),
+  (line, col): (usize, usize),
+  (cutoff, scale): (f64, Option<f64>),
+  opts: &DiffConfig,
+) -> DiffReport {
+  let mut alone = DiffReport {
+    rows: 1,
+    rel_mode: opts.rel_mode,
+    rel_floor: opts.rel_floor,
+    max_ratio: Extremum::new(opts.rel_mode.identical()),
+    directions: Directions::new(opts.rel_mode),
+    ..Default::default()
+  };
+  let mut column = ColumnStats::new(col, 1, cutoff, opts.rel_mode);
+  column.update(a1, a2, texts, 0);
+  let cell = Measures::of(a1, a2, opts, scale);
+  column.raise(&cell, (a1, a2), line);
+  alone.scaled_diff = column.scaled_diff;
+  alone.columns.push(column);
+  alone.max_diff.raise(cell.diff, (a1, a2), line, col);
+  alone.max_ratio.raise(cell.ratio, (a1, a2), line, col);
+  if let Some(closeness) = cell.closeness {
+    alone.allclose.raise(closeness, (a1, a2), line, col);
+  }
+  alone.directions.raise(&cell, (a1, a2), line, col);
+  alone.gates = opts.tolerances.evaluate(&alone);
+  alone.warnings = opts.warn_tolerances.evaluate(&alone);
+  alone
+}
+
+/// How far apart a compared pair of values is.
+pub(crate) struct Measures {
+  /// The absolute difference.
+  pub(crate) diff: f64,
+  /// The relative difference, as the comparison measures it.
+  pub(crate) ratio: f64,
+  /// Which way the values differ.
+  pub(crate) direction: Direction,
+  /// The scale of its row's absolute difference tolerance, if that's scaled.
+  pub(crate) scale: Option<f64>,
+  /// The absolute difference tolerance at it, scaled if it's scaled, if one
+  /// was given.
+  pub(crate) diff_allowed: Option<f64>,
+  /// The excess over the absolute difference tolerance, if one was given.
+  pub(crate) diff_excess: Option<f64>,
+  /// The excess over the ratio tolerance, in relative difference, if one was
+  /// given.
+  pub(crate) ratio_excess: Option<f64>,
+  /// The absolute difference the combined tolerance allows at it, if one
+  /// was given.
+  pub(crate) allclose_allowed: Option<f64>,
+  /// The absolute difference as a multiple of `allclose_allowed`, if that's
+  /// given: within it up to 1, and zero for equal values whatever it is.
+  pub(crate) closeness: Option<f64>,
+}
+
+impl Measures {
+  /// Measures a pair of values, as compared, in a row whose absolute
+  /// difference tolerance has this scale if it's scaled.
+  pub(crate) fn of(
+    a1: f64,
+    a2: f64,
+    opts: &DiffConfig,
+    scale: Option<f64>,
+  ) -> Self {
+    let diff = (a1 - a2).abs();
+    let (mode, floor) = (opts.rel_mode, opts.rel_floor);
+    let rel = diff / a1.abs().max(a2.abs()).max(floor);
+    let tolerances = &opts.tolerances;
+    let direction = Direction::of(a1, a2);
+    let diff_allowed = tolerances
+      .diff_limit(direction)
+      .map(|md| md * scale.unwrap_or(1.0));
+    // cells zero in one file only may be held to a magnitude instead
+    let relative = opts.max_zero_mismatch.is_none()
+      || ZeroMismatches::nonzero(a1, a2).is_none();
+    let allclose_allowed = tolerances.allclose_limit(a2);
+    Self {
+      diff,
+      ratio: match relative {
+        true => mode.measure(a1, a2, floor),
+        false => mode.identical(),
+      },
+      direction,
+      scale,
+      diff_allowed,
+      diff_excess: diff_allowed.map(|md| diff - md),
+      ratio_excess: tolerances
+        .ratio_limit(direction)
+        .filter(|_| relative)
+        .map(|mr| rel - mode.allowed_rel(a1, a2, mr, floor)),
+      allclose_allowed,
+      closeness: allclose_allowed.map(|allowed| match diff {
+        0.0 => 0.0,
+        _ => diff / allowed,
+      }),
+    }
+  }
+
+  /// Which tolerances the cell alone exceeds, i.e. the gates it would fail
+  /// if it were the only one compared, each counted once.
+  fn failing(
+    &self,
+    a1: f64,
+    a2: f64,
+    texts: (&str, &str),
+    opts: &DiffConfig,
+  ) -> FailingCells {
+    let tolerances = &opts.tolerances;
+    let percent = opts.rel_mode.percent(self.ratio);
+    let digits = || {
+      let mut alone = ColumnStats::new(0, 0, 0.0, opts.rel_mode);
+      alone.update(a1, a2, texts, 0);
+      alone.digits_of_agreement()
+    };
+    let ratio = tolerances
+      .ratio_limit(self.direction)
+      .is_some_and(|mr| verdict::exceeds_ratio(percent, mr));
+    let diff = self.diff_allowed.is_some_and(|md| self.diff > md);
+    let digits = tolerances
+      .min_digits
+      .is_some_and(|min| digits().is_some_and(|d| d < min));
+    let allclose = self.allclose_allowed.is_some_and(|md| self.diff > md);
+    FailingCells {
+      ratio: ratio.into(),
+      diff: diff.into(),
+      digits: digits.into(),
+      allclose: allclose.into(),
+      cells: (ratio || diff || digits || allclose).into(),
+    }
+  }
+}
+
+/// How many rows the calling thread hands to the column workers at a time.
+const ROW_BATCH: usize = 1024;
+
+/// How many batches may wait for a worker before the calling thread blocks.
+const QUEUED_BATCHES: usize = 4;
+
+/// What a column worker accumulates over its share of the float columns.
+struct Partial {
+  /// Its columns' stats, in column order.
+  columns: Vec<ColumnStats>,
+  /// The worst ratio among its columns.
+  max_ratio: Extremum,
+  /// The worst absolute difference among its columns.
+  max_diff: Extremum,
+  /// The worst cell for the combined tolerance among its columns.
+  allclose: Extremum,
+  /// The cells tied with its worst ratio.
+  ratio_ties: Ties,
+  /// The cells tied with its worst absolute difference.
+  diff_ties: Ties,
+  /// Its columns' cells zero in exactly one file.
+  zero_mismatches: ZeroMismatches,
+  /// The worst cell for its row's scale among its columns, if scaled.
+  scaled_diff: Option<ScaledDiff>,
+  /// The worst cells in each direction among its columns.
+  directions: Directions,
+  /// Each cell's excess over the ratio tolerance as (line, col, excess), in
+  /// row-major order. Kept per cell, since summing a line's excess in pieces
+  /// would round differently from a single pass.
+  ratio_excess: Vec<(usize, usize, f64)>,
+  /// As `ratio_excess`, for the absolute difference tolerance.
+  diff_excess: Vec<(usize, usize, f64)>,
+  /// Its columns' cells exceeding each tolerance.
+  failing_cells: FailingCells,
+  /// Its columns' subnormal values taken as zero, in each file.
+  clamped: (usize, usize),
+  /// Its columns' cells compared by magnitude with opposite signs.
+  sign_differences: usize,
+  /// Its columns' cells skipped, by what skipped them.
+  skipped: SkippedCells,
+  /// Its columns' cells left out, as (line, col).
+  excluded: Vec<(usize, usize)>,
+  /// Its columns' cells by relative difference, if counted.
+  histogram: Option<Histogram>,
+  /// Its columns' cells' relative differences, if counted for percentiles.
+  percentiles: Option<Percentiles>,
+  /// Its columns' cells by impact class, if counted.
+  impact: Option<ImpactReport>,
+}
+
+/// The second pass of [`compare`], with the float columns split among
+/// `opts.column_workers` threads in contiguous runs. The calling thread
+/// hands out rows in batches through bounded channels; each worker keeps its
+/// own stats and maxima, and they're merged in column order into `report`,
+/// giving exactly what the single-threaded pass gives.
+fn compare_columns(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+  flipped: &[bool],
+  report: &mut DiffReport,
+) {
+  let columns = std::mem::take(&mut report.columns);
+  let per_worker = columns.len().div_ceil(opts.column_workers.max(1));
+  let partials = thread::scope(|scope| {
+    let mut senders = Vec::new();
+    let mut workers = Vec::new();
+    for share in columns.chunks(per_worker.max(1)) {
+      let (sender, receiver) =
+        mpsc::sync_channel::<Range<usize>>(QUEUED_BATCHES);
+      senders.push(sender);
+      workers.push(scope.spawn(move || {
+        let mut partial = Partial {
+          columns: share.to_vec(),
+          max_ratio: Extremum::new(opts.rel_mode.identical()),
+          max_diff: Extremum::new(0.0),
+          allclose: Extremum::new(0.0),
+          ratio_ties: Ties::new(opts.rel_mode.identical(), opts.listed_ties),
+          diff_ties: Ties::new(0.0, opts.listed_ties),
+          zero_mismatches: ZeroMismatches::new(opts.max_zero_mismatch),
+          scaled_diff: opts.diff_scale.map(|_| ScaledDiff::new()),
+          directions: Directions::new(opts.rel_mode),
+          ratio_excess: Vec::new(),
+          diff_excess: Vec::new(),
+          failing_cells: FailingCells::default(),
+          clamped: (0, 0),
+          sign_differences: 0,
+          skipped: SkippedCells::default(),
+          excluded: Vec::new(),
+          histogram: opts.histogram.as_deref().map(Histogram::new),
+          percentiles: opts.percentiles.as_deref().map(Percentiles::new),
+          impact: (!opts.significance.is_empty())
+            .then(|| ImpactReport::new(opts.rel_mode)),
+        };
+        for rows in receiver {
+          for row in rows {
+            let (rec1, rec2) = (&records1[row], &records2[row]);
+            let line = physical_line(records1, row);
+            let segment = segment_of(row, records1.len(), opts.segments);
+            let scale =
+              opts.diff_scale.map(|_| opts.row_scale(rec1).unwrap_or(1.0));
+            for stats in &mut partial.columns {
+              let i = stats.col - 1;
+              let cell2 = opts.col2(i).and_then(|j| rec2.get(j));
+              let (Some(cell1), Some(cell2)) = (rec1.get(i), cell2) else {
+                continue;
+              };
+              // The first pass checked that float columns parse
+              let (v1, v2) =
+                (parse_float(cell1).unwrap(), parse_float(cell2).unwrap());
+              partial.clamped.0 += opts.clamps(v1) as usize;
+              partial.clamped.1 += opts.clamps(v2) as usize;
+              if opts.excludes(line, i) {
+                partial.excluded.push((line, i + 1));
+                continue;
+              }
+              let factors = opts.factors(i);
+              let (v1, v2) =
+                (opts.value(factors.0, v1), opts.value(factors.1, v2));
+              let cutoff = (stats.cutoff, opts.threshold_mode);
+              let (a1, a2) = match compared_values(v1, v2, flipped[i], cutoff) {
+                Ok(values) => values,
+                Err(skip) => {
+                  partial.skipped.add(skip);
+                  if let Some(percentiles) = &mut partial.percentiles {
+                    percentiles.skip();
+                  }
+                  continue;
+                }
+              };
+              let abs = opts.abs_compare.applies(i);
+              partial.sign_differences +=
+                usize::from(abs && opposite_signs(a1, a2));
+              let signed = (a1, a2);
+              stats.add_to_sums(signed);
+              let magnitudes = if abs { (a1.abs(), a2.abs()) } else { signed };
+              let (a1, a2) = opts.measured(magnitudes, (cell1, cell2), factors);
+              let shown = if abs { signed } else { (a1, a2) };
+              stats.update(a1, a2, (cell1, cell2), segment);
+              let cell = Measures::of(a1, a2, opts, scale);
+              stats.raise(&cell, shown, line);
+              let failing = cell.failing(a1, a2, (cell1, cell2), opts);
+              if let Some(histogram) = &mut partial.histogram {
+                histogram.add(opts.rel_mode.percent(cell.ratio));
+              }
+              if let Some(percentiles) = &mut partial.percentiles {
+                percentiles.add(opts.rel_mode.percent(cell.ratio));
+              }
+              if let Some(impact) = &mut partial.impact
+                && !classify(
+                  impact,
+                  (&cell, failing),
+                  ((a1, a2), shown),
+                  (line, i),
+                  opts,
+                )
+              {
+                continue;
+              }
+              partial.failing_cells.add(failing);
+              if let Some(excess) = cell.diff_excess
+                && excess > 0.0
+              {
+                partial.diff_excess.push((line, i + 1, excess));
+              }
+              if let Some(excess) = cell.ratio_excess
+                && excess > 0.0
+              {
+                partial.ratio_excess.push((line, i + 1, excess));
+              }
+              partial.max_diff.raise(cell.diff, shown, line, i + 1);
+              partial.max_ratio.raise(cell.ratio, shown, line, i + 1);
+              if let Some(closeness) = cell.closeness {
+                partial.allclose.raise(closeness, shown, line, i + 1);
+              }
+              partial.diff_ties.add(cell.diff, line, i + 1);
+              partial.ratio_ties.add(cell.ratio, line, i + 1);
+              let vals = ((a1, a2), shown);
+              partial.zero_mismatches.add(vals, line, i + 1);
+              partial.directions.raise(&cell, shown, line, i + 1);
+              if let (Some(scaled), Some(scale)) =
+                (&mut partial.scaled_diff, scale)
+              {
+                scaled.raise((cell.diff, scale), shown, line, i + 1);
+              }
+            }
+          }
+        }
+        partial
+      }));
+    }
+    for start in (0..records1.len()).step_by(ROW_BATCH) {
+      let rows = start..(start + ROW_BATCH).min(records1.len());
+      for sender in &senders {
+        // A worker only hangs up by panicking, which join reports below
+        let _ = sender.send(rows.clone());
+      }
+      if let Some(progress) = &opts.progress {
+        progress.add_rows(rows.len());
+      }
+    }
+    drop(senders);
+    workers
+      .into_iter()
+      .map(|w| w.join().unwrap())
+      .collect::<Vec<_>>()
+  });
+
+  let mut ratio_excess = Vec::new();
+  let mut diff_excess = Vec::new();
+  for partial in partials {
+    report.columns.extend(partial.columns);
+    report.max_ratio.merge(partial.max_ratio);
+    report.max_diff.merge(partial.max_diff);
+    report.allclose.merge(partial.allclose);
+    report.ratio_ties.merge(partial.ratio_ties);
+    report.diff_ties.merge(partial.diff_ties);
+    report.zero_mismatches.merge(partial.zero_mismatches);
+    if let (Some(scaled), Some(other)) =
+      (&mut report.scaled_diff, partial.scaled_diff)
+    {
+      scaled.merge(other);
+    }
+    report.directions.merge(partial.directions);
+    report.failing_cells.add(partial.failing_cells);
+    report.clamped.0 += partial.clamped.0;
+    report.clamped.1 += partial.clamped.1;
+    report.sign_differences += partial.sign_differences;
+    report.skipped.merge(partial.skipped);
+    report.excluded.extend(partial.excluded);
+    if let (Some(histogram), Some(other)) =
+      (&mut report.histogram, &partial.histogram)
+    {
+      histogram.merge(other);
+    }
+    if let (Some(percentiles), Some(other)) =
+      (&mut report.percentiles, &partial.percentiles)
+    {
+      percentiles.merge(other);
+    }
+    if let (Some(impact), Some(other)) = (&mut report.impact, &partial.impact) {
+      impact.merge(other);
+    }
+    ratio_excess.extend(partial.ratio_excess);
+    diff_excess.extend(partial.diff_excess);
+  }
+  for (mass, mut excess) in [
+    (&mut report.ratio_mass, ratio_excess),
+    (&mut report.diff_mass, diff_excess),
+  ] {
+    excess.sort_unstable_by_key(|&(line, col, _)| (line, col));
+    for (line, col, excess) in excess {
+      mass.add(line, col, excess);
+    }
+  }
+}
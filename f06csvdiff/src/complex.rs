@@ -0,0 +1,158 @@
+//! Complex pairs: columns holding the real and imaginary parts of complex
+//! values, as frequency responses are written, compared by magnitude and
+//! phase. Compared part by part, they blow up near a phase's wrap-around
+//! even when the response matches; the magnitudes are compared like any
+//! other column, and the phases here, across the wrap-around.
+
+use csv::StringRecord;
+
+use crate::compare::{DiffConfig, Extremum, parse_float, physical_line};
+
+/// A real/imaginary column pair, by the (0-based) columns of each file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComplexPair {
+  /// The real part's columns in csv1 and csv2.
+  pub re: (usize, usize),
+  /// The imaginary part's columns in csv1 and csv2.
+  pub im: (usize, usize),
+  /// The magnitude's columns, as appended to each file's records.
+  pub magnitude: (usize, usize),
+}
+
+/// How one pair's magnitudes and phases differ.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PairDeviations {
+  /// The pair.
+  pub pair: ComplexPair,
+  /// The worst absolute difference of magnitudes, as compared; its column
+  /// is the magnitude's, in csv1.
+  pub magnitude: Extremum,
+  /// The worst phase difference, in degrees, with the phases; its column
+  /// is the real part's, in csv1.
+  pub phase: Extremum,
+  /// How many rows' phases were compared, i.e. weren't both below the
+  /// threshold.
+  pub compared: usize,
+  /// How many of them differ by more than the phase tolerance.
+  pub failing: usize,
+}
+
+/// How every pair's magnitudes and phases differ.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhaseReport {
+  /// The largest allowed phase difference, in degrees, if there is one.
+  pub max_phase_deg: Option<f64>,
+  /// Each pair's deviations, in the order given.
+  pub pairs: Vec<PairDeviations>,
+}
+
+impl PhaseReport {
+  /// The pair whose phase differs most, if any phase was compared.
+  pub fn worst(&self) -> Option<&PairDeviations> {
+    self
+      .pairs
+      .iter()
+      .filter(|p| p.compared > 0)
+      .max_by(|a, b| a.phase.value.total_cmp(&b.phase.value))
+  }
+
+  /// How many rows' phases were compared, over all pairs.
+  pub fn compared(&self) -> usize {
+    self.pairs.iter().map(|p| p.compared).sum()
+  }
+
+  /// How many of them differ by more than the phase tolerance.
+  pub fn failing(&self) -> usize {
+    self.pairs.iter().map(|p| p.failing).sum()
+  }
+
+  /// Whether no phase differs by more than the tolerance.
+  pub fn passed(&self) -> bool {
+    self.failing() == 0
+  }
+}
+
+/// The phase of a complex value, in degrees from -180 to 180.
+pub fn phase(re: f64, im: f64) -> f64 {
+  im.atan2(re).to_degrees()
+}
+
+/// How far apart two phases are, in degrees, going the shorter way round:
+/// 179.9 and -179.9 are 0.2 apart.
+pub fn phase_difference(a: f64, b: f64) -> f64 {
+  let d = (a - b).rem_euclid(360.0);
+  d.min(360.0 - d)
+}
+
+/// Compares the first `rows` rows of each pair, whose magnitudes have been
+/// appended to the records. Magnitudes are measured as `opts` measures their
+/// columns; phases are skipped where both magnitudes are below its
+/// threshold, and so are cells that aren't floats.
+pub fn compare_pairs(
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  pairs: &[ComplexPair],
+  rows: usize,
+  opts: &DiffConfig,
+  max_phase_deg: Option<f64>,
+) -> PhaseReport {
+  let cell = |record: &StringRecord, col: usize| -> Option<f64> {
+    parse_float(record.get(col)?)
+  };
+  let pairs = pairs
+    .iter()
+    .map(|&pair| {
+      let mut deviations = PairDeviations {
+        pair,
+        magnitude: Extremum::new(0.0),
+        phase: Extremum::new(0.0),
+        compared: 0,
+        failing: 0,
+      };
+      let factors = opts.factors(pair.magnitude.0);
+      for row in 0..rows.min(records1.len()).min(records2.len()) {
+        let (r1, r2) = (&records1[row], &records2[row]);
+        let parts = (
+          cell(r1, pair.re.0),
+          cell(r1, pair.im.0),
+          cell(r2, pair.re.1),
+          cell(r2, pair.im.1),
+        );
+        let (Some(re1), Some(im1), Some(re2), Some(im2)) = parts else {
+          continue;
+        };
+        let (Some(m1), Some(m2)) =
+          (cell(r1, pair.magnitude.0), cell(r2, pair.magnitude.1))
+        else {
+          continue;
+        };
+        let (m1, m2) = (opts.value(factors.0, m1), opts.value(factors.1, m2));
+        let line = physical_line(records1, row);
+        deviations.magnitude.raise(
+          (m1 - m2).abs(),
+          (m1, m2),
+          line,
+          pair.magnitude.0 + 1,
+        );
+        if m1.abs() < opts.threshold && m2.abs() < opts.threshold {
+          continue;
+        }
+        let (re1, im1) = (opts.clamp(re1), opts.clamp(im1));
+        let (re2, im2) = (opts.clamp(re2), opts.clamp(im2));
+        let phases = (phase(re1, im1), phase(re2, im2));
+        let difference = phase_difference(phases.0, phases.1);
+        deviations.compared += 1;
+        if max_phase_deg.is_some_and(|max| difference > max) {
+          deviations.failing += 1;
+        }
+        deviations
+          .phase
+          .raise(difference, phases, line, pair.re.0 + 1);
+      }
+      deviations
+    })
+    .collect();
+  PhaseReport {
+    max_phase_deg,
+    pairs,
+  }
+}
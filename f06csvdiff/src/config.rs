@@ -0,0 +1,121 @@
+//! The TOML configuration file, given with --config or else looked up as
+//! f06csvdiff.toml in the working directory.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use f06csvdiff::assertions::Assertions;
+use f06csvdiff::verdict::Tolerances;
+
+/// The contents of a configuration file. Unknown keys are errors, so typos
+/// don't silently do nothing.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+  /// Defaults for command-line options, so that every invocation needn't
+  /// repeat them.
+  pub(crate) defaults: Defaults,
+  /// Tolerances and significance of columns, by (1-based) number or, with
+  /// --has-headers, header name in csv1. Each gate one leaves unset falls
+  /// back to the command line's.
+  #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+  pub(crate) columns: BTreeMap<String, ColumnSpec>,
+  /// Named tolerance sets, e.g. "engineering" and "regression".
+  #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+  pub(crate) profiles: BTreeMap<String, Tolerances>,
+  /// Tolerances of named scalars for --scalars, by name. Each gate one
+  /// leaves unset falls back to the command line's.
+  #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+  pub(crate) scalars: BTreeMap<String, Tolerances>,
+  /// What the files compared have to be like, checked after each
+  /// comparison.
+  #[serde(skip_serializing_if = "Assertions::is_empty")]
+  pub(crate) assert: Assertions,
+}
+
+/// The `[defaults]` section: options taken when they aren't given on the
+/// command line, each named as the long option, with underscores.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Defaults {
+  /// -d.
+  pub(crate) max_diff: Option<f64>,
+  /// -r.
+  pub(crate) max_ratio: Option<f64>,
+  /// --warn-ratio.
+  pub(crate) warn_ratio: Option<f64>,
+  /// --warn-diff.
+  pub(crate) warn_diff: Option<f64>,
+  /// --min-digits.
+  pub(crate) min_digits: Option<f64>,
+  /// -t.
+  pub(crate) threshold: Option<f64>,
+  /// --delim.
+  pub(crate) delim: Option<char>,
+  /// --format.
+  pub(crate) format: Option<String>,
+  /// --ignore-rows-matching, taken with --allow-extra-rows as the option is.
+  pub(crate) ignore_rows_matching: Option<String>,
+}
+
+/// A `[columns]` table: a column's own tolerances, and its engineering
+/// significance.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct ColumnSpec {
+  /// -r, for this column.
+  pub(crate) max_ratio: Option<f64>,
+  /// -d, for this column.
+  pub(crate) max_diff: Option<f64>,
+  /// --min-digits, for this column.
+  pub(crate) min_digits: Option<f64>,
+  /// --max-fail-fraction, for this column.
+  pub(crate) max_fail_fraction: Option<f64>,
+  /// The absolute value, e.g. 50 for a stress in MPa, that a difference
+  /// between two values both below is low-impact.
+  pub(crate) significance: Option<f64>,
+}
+
+impl ColumnSpec {
+  /// The column's own tolerances, unless it has none and is judged as the
+  /// others are.
+  pub(crate) fn tolerances(&self) -> Option<Tolerances> {
+    let tolerances = Tolerances {
+      max_ratio: self.max_ratio,
+      max_diff: self.max_diff,
+      min_digits: self.min_digits,
+      max_fail_fraction: self.max_fail_fraction,
+      ..Default::default()
+    };
+    (tolerances != Tolerances::default()).then_some(tolerances)
+  }
+}
+
+/// The configuration file looked up when there's no --config.
+pub(crate) const DEFAULT_PATH: &str = "f06csvdiff.toml";
+
+impl Config {
+  /// Reads and parses a configuration file.
+  pub(crate) fn load(path: &Path) -> Result<Self, String> {
+    let text = std::fs::read_to_string(path)
+      .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+    toml::from_str(&text)
+      .map_err(|e| format!("bad config file {}: {e}", path.display()))
+  }
+
+  /// Looks up profiles by name, in the given order.
+  pub(crate) fn profiles(
+    &self,
+    names: &[String],
+  ) -> Result<Vec<(String, Tolerances)>, String> {
+    names
+      .iter()
+      .map(|name| match self.profiles.get(name) {
+        Some(tol) => Ok((name.clone(), tol.clone())),
+        None => Err(format!("no profile named {name} in the config file")),
+      })
+      .collect()
+  }
+}
@@ -0,0 +1,308 @@
+//! The report's descriptions, in words, of what it found beyond its gates'
+//! worst cells, shared by the plain report and the JUnit and JSON formats.
+
+use csv::StringRecord;
+
+use crate::annotations::AnnotationMismatch;
+use crate::assertions::AssertionFailure;
+use crate::compare::{DiffReport, Extremum};
+use crate::complex::PairDeviations;
+use crate::impact::{Impact, ImpactClass, ImpactReport};
+use crate::matrix::MatrixReport;
+use crate::verdict::{Check, ColumnSum};
+
+/// Formats a count with commas between groups of thousands.
+pub fn thousands(n: usize) -> String {
+  let digits = n.to_string();
+  let mut grouped = String::new();
+  for (i, digit) in digits.chars().enumerate() {
+    if i > 0 && (digits.len() - i).is_multiple_of(3) {
+      grouped.push(',');
+    }
+    grouped.push(digit);
+  }
+  grouped
+}
+
+/// A (1-based) column as --columns and --explain-cell take it: by its name
+/// in csv1's header, if it has one that names it alone, or by number.
+pub fn column_arg(col: usize, header: Option<&StringRecord>) -> String {
+  let name = header.and_then(|header| {
+    let name = header.get(col - 1)?.trim();
+    let alone = header.iter().position(|n| n.trim() == name) == Some(col - 1);
+    let plain = !name.is_empty() && !name.contains(',');
+    (alone && plain && name.parse::<usize>().is_err()).then_some(name)
+  });
+  name.map_or_else(|| col.to_string(), str::to_string)
+}
+
+/// A worst cell's column as the result line gives it: by csv1's header
+/// name if that names it alone in a single word, else by number, 0 for no
+/// cell at all.
+pub fn worst_column(col: usize, header: Option<&StringRecord>) -> String {
+  match col {
+    0 => col.to_string(),
+    col => Some(column_arg(col, header))
+      .filter(|name| !name.contains(char::is_whitespace))
+      .unwrap_or_else(|| col.to_string()),
+  }
+}
+
+/// The checked column sums of a report, those of columns with tolerances
+/// of their own included, in column order.
+pub fn column_sums(report: &DiffReport) -> Vec<&ColumnSum> {
+  let overrides = report.overrides.iter().flat_map(|o| &o.report.sums);
+  let mut sums: Vec<&ColumnSum> = report.sums.iter().chain(overrides).collect();
+  sums.sort_by_key(|sum| sum.col);
+  sums
+}
+
+/// The rows or columns left unpaired, e.g. `line 7 of a.csv; lines 8, 9 of
+/// b.csv` for the lines `noun` names, if any.
+pub fn unpaired(
+  noun: &str,
+  (unpaired1, unpaired2): &(Vec<usize>, Vec<usize>),
+  (csv1, csv2): (&str, &str),
+) -> Option<String> {
+  let lines = |lines: &[usize], path: &str| {
+    let numbers: Vec<String> = lines.iter().map(usize::to_string).collect();
+    match lines.len() {
+      0 => None,
+      1 => Some(format!("{noun} {} of {path}", numbers[0])),
+      _ => Some(format!("{noun}s {} of {path}", numbers.join(", "))),
+    }
+  };
+  let both: Vec<String> = [lines(unpaired1, csv1), lines(unpaired2, csv2)]
+    .into_iter()
+    .flatten()
+    .collect();
+  (!both.is_empty()).then(|| both.join("; "))
+}
+
+/// A compared cell's differing annotations, e.g. `line 3, column 2: "*" vs
+/// none`.
+pub fn annotation_mismatch(mismatch: &AnnotationMismatch) -> String {
+  let show = |suffix: &Option<String>| {
+    suffix
+      .as_ref()
+      .map_or("none".to_string(), |s| format!("{s:?}"))
+  };
+  format!(
+    "line {}, column {}: {} vs {}",
+    mismatch.line,
+    mismatch.col,
+    show(&mismatch.suffixes.0),
+    show(&mismatch.suffixes.1)
+  )
+}
+
+/// How a complex pair's magnitudes and phases differ, e.g. `worst magnitude
+/// difference 1.20E-3 (line 7), worst phase difference 0.200 degrees (line
+/// 9)`.
+pub fn pair_deviations(deviations: &PairDeviations) -> String {
+  let at = |worst: &Extremum| match worst.line {
+    0 => String::new(),
+    line => format!(" (line {line})"),
+  };
+  let (magnitude, phase) = (&deviations.magnitude, &deviations.phase);
+  let phase = match deviations.compared {
+    0 => "no phases compared".to_string(),
+    _ => format!(
+      "worst phase difference {:.3} degrees{}",
+      phase.value,
+      at(phase)
+    ),
+  };
+  format!(
+    "worst magnitude difference {:.2E}{}, {phase}",
+    magnitude.value,
+    at(magnitude)
+  )
+}
+
+/// How two files differ as matrices, e.g. `||A-B||/||A|| 3.20E-4
+/// (Frobenius), largest entry of A-B 1.20E-3 (line 7, column 3)`.
+pub fn matrix_norms(matrix: &MatrixReport) -> String {
+  let mut text = format!(
+    "||A-B||/||A|| {:.2E} (Frobenius)",
+    matrix.frobenius.relative()
+  );
+  if let Some(row_sum) = &matrix.row_sum {
+    text.push_str(&format!(", {:.2E} (max row sum)", row_sum.relative()));
+  }
+  let worst = &matrix.worst;
+  if worst.line > 0 {
+    text.push_str(&format!(
+      ", largest entry of A-B {:.2E} (line {}, column {})",
+      worst.value, worst.line, worst.col
+    ));
+  }
+  text
+}
+
+/// An assertion that didn't hold, and why, e.g. `column VONMISES exists:
+/// csv2's header lacks it`.
+pub fn assertion_failure(failure: &AssertionFailure) -> String {
+  format!("{}: {}", failure.assertion, failure.reason)
+}
+
+/// An impact class of a comparison, e.g. `HIGH-IMPACT: 3 of 120 cells
+/// exceed a tolerance, worst 2.10% (line 5, column 3)`.
+pub fn impact_line(
+  impact: Impact,
+  class: &ImpactClass,
+  report: &DiffReport,
+) -> String {
+  let mut line = format!(
+    "{}: {} of {} cells exceed a tolerance",
+    impact.name(),
+    thousands(class.failing_cells.cells),
+    thousands(class.compared)
+  );
+  if class.compared > 0 {
+    let worst = &class.max_ratio;
+    line.push_str(&format!(
+      ", worst {:.2}% (line {}, column {})",
+      report.rel_mode.percent(worst.value),
+      worst.line,
+      worst.col
+    ));
+  }
+  line
+}
+
+/// The impact classes of a comparison, high first.
+pub fn impact_classes(impact: &ImpactReport) -> [(Impact, &ImpactClass); 2] {
+  [Impact::High, Impact::Low].map(|class| (class, impact.class(class)))
+}
+
+/// Why a report failed a check besides the gates, e.g. "1 of 3 column sums
+/// fail: column 4: sum1=+1.000000E+00, sum2=+1.100000E+00, 10.00%", naming
+/// csv1 and csv2 as `files` do.
+pub fn check_failure(
+  check: Check,
+  report: &DiffReport,
+  files: (&str, &str),
+) -> String {
+  match check {
+    Check::Sums => {
+      let failing: Vec<String> = report
+        .sums
+        .iter()
+        .filter(|s| !s.passed)
+        .map(|s| {
+          format!(
+            "column {}: sum1={:+.6E}, sum2={:+.6E}, {:.2}%",
+            s.col, s.sums.0, s.sums.1, s.percent
+          )
+        })
+        .collect();
+      format!(
+        "{} of {} column sums fail: {}",
+        failing.len(),
+        report.sums.len(),
+        failing.join("; ")
+      )
+    }
+    Check::Annotations => {
+      let mismatches = &report.annotation_mismatches;
+      let listed: Vec<String> =
+        mismatches.iter().map(annotation_mismatch).collect();
+      format!(
+        "{} cell(s) annotated differently: {}",
+        mismatches.len(),
+        listed.join("; ")
+      )
+    }
+    Check::Assertions => {
+      let failures = &report.assertion_failures;
+      let listed: Vec<String> =
+        failures.iter().map(assertion_failure).collect();
+      format!(
+        "{} assertion(s) failed: {}",
+        failures.len(),
+        listed.join("; ")
+      )
+    }
+    Check::Complex => {
+      let Some(complex) = &report.complex else {
+        return String::new();
+      };
+      let worst = complex.worst().map(pair_deviations).unwrap_or_default();
+      format!(
+        "{} of {} phase(s) differ by more than {:.3} degrees: {worst}",
+        complex.failing(),
+        complex.compared(),
+        complex.max_phase_deg.unwrap_or_default()
+      )
+    }
+    Check::UnpairedRows => {
+      let lines = unpaired("line", &report.unpaired_rows, files);
+      format!("unpaired {}", lines.unwrap_or_default())
+    }
+    Check::UnpairedColumns => {
+      let columns = unpaired("column", &report.unpaired_columns, files);
+      format!("unpaired {}", columns.unwrap_or_default())
+    }
+    Check::Groups => {
+      let failing: Vec<&str> = report
+        .groups
+        .iter()
+        .filter(|g| !g.report.passed())
+        .map(|g| g.value.as_str())
+        .collect();
+      format!(
+        "{} of {} groups fail: {}",
+        failing.len(),
+        report.groups.len(),
+        failing.join(", ")
+      )
+    }
+    Check::Overrides => {
+      let failing: Vec<String> = report
+        .overrides
+        .iter()
+        .filter(|o| !o.report.passed())
+        .map(|o| format!("column {}", o.col))
+        .collect();
+      format!(
+        "{} of {} columns with tolerances of their own fail: {}",
+        failing.len(),
+        report.overrides.len(),
+        failing.join(", ")
+      )
+    }
+    Check::Matrix => {
+      report.matrix.as_ref().map(matrix_norms).unwrap_or_default()
+    }
+    Check::Exclusions => {
+      let stale = &report.stale_exclusions;
+      format!(
+        "{} stale exclusion(s), which left out no cell: {}",
+        stale.len(),
+        stale.join("; ")
+      )
+    }
+    Check::Structure => {
+      let issues = &report.structure_issues;
+      let problems: Vec<&str> =
+        issues.iter().map(|i| i.problem.as_str()).collect();
+      format!(
+        "left out {} row(s) with structural problems: {}",
+        issues.len(),
+        problems.join("; ")
+      )
+    }
+    Check::ZeroMismatches => {
+      let zeros = &report.zero_mismatches;
+      format!(
+        "{} of {} cell(s) zero in one file only at least {:.2E} in \
+         magnitude",
+        zeros.failing,
+        zeros.cells,
+        zeros.limit.unwrap_or_default()
+      )
+    }
+    Check::Columns => report.column_failures.join("; "),
+  }
+}
@@ -0,0 +1,342 @@
+//! The effective configuration of each column, resolved from every option
+//! that selects or shapes columns, so that options which overlap either
+//! combine predictably or fail naming both.
+//!
+//! The options apply in this order, each to what the previous left:
+//!
+//! 1. Pairing: `--col-map`, `--match-by-header` or `--mac-match` pair
+//!    csv1's columns with csv2's, or else they pair by position. An unpaired
+//!    column isn't compared.
+//! 2. Derived columns: `--complex-pairs` takes its parts out of the
+//!    comparison, in favor of their magnitudes and phases, and so does
+//!    `--vector-only` with the `--vector-cols` components. The magnitudes
+//!    are compared as columns of their own.
+//! 3. Policies: `--allow-sign-flip`, `--allow-sign-flip-cols` and
+//!    `--normalize-columns` apply to the columns still compared, and the
+//!    `--sort-by`, `--match-rows-nearest`, `--group-by` and `--shift-col`
+//!    keys have to be paired.
+//!
+//! A column claimed twice at the same step, such as a part of two complex
+//! pairs, and a policy on a column an earlier step left out are conflicts.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::compare::SignFlip;
+
+/// An option that selects or shapes columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Source {
+  /// Columns paired by their place in each file.
+  Position,
+  /// `--col-map`.
+  ColMap,
+  /// `--match-by-header`.
+  MatchByHeader,
+  /// `--mac-match`.
+  MacMatch,
+  /// `--vector-cols`.
+  VectorCols,
+  /// `--vector-only`.
+  VectorOnly,
+  /// `--complex-pairs`.
+  ComplexPairs,
+  /// `--allow-sign-flip`.
+  SignFlip,
+  /// `--allow-sign-flip-cols`.
+  SignFlipCols,
+  /// `--normalize-columns`.
+  Normalize,
+  /// `--sort-by`.
+  SortBy,
+  /// `--match-rows-nearest`.
+  MatchRowsNearest,
+  /// `--key-col`.
+  KeyCol,
+  /// `--group-by`.
+  GroupBy,
+  /// `--shift-col`, with `--auto-shift`.
+  ShiftCol,
+}
+
+impl Source {
+  /// The option, as given on the command line.
+  pub fn name(self) -> &'static str {
+    match self {
+      Source::Position => "position",
+      Source::ColMap => "--col-map",
+      Source::MatchByHeader => "--match-by-header",
+      Source::MacMatch => "--mac-match",
+      Source::VectorCols => "--vector-cols",
+      Source::VectorOnly => "--vector-only",
+      Source::ComplexPairs => "--complex-pairs",
+      Source::SignFlip => "--allow-sign-flip",
+      Source::SignFlipCols => "--allow-sign-flip-cols",
+      Source::Normalize => "--normalize-columns",
+      Source::SortBy => "--sort-by",
+      Source::MatchRowsNearest => "--match-rows-nearest",
+      Source::KeyCol => "--key-col",
+      Source::GroupBy => "--group-by",
+      Source::ShiftCol => "--shift-col",
+    }
+  }
+}
+
+/// What becomes of a column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+  /// Compared with its partner in csv2.
+  Compared,
+  /// Not paired with a column of csv2, so not compared.
+  Unpaired,
+  /// A vector component, compared only through the vector's magnitude.
+  Component,
+  /// A complex part, compared through its pair's magnitude and phase.
+  ComplexPart,
+  /// A magnitude appended to the files, compared like any column.
+  Magnitude,
+}
+
+impl Role {
+  /// The name of the role in reports.
+  pub fn name(self) -> &'static str {
+    match self {
+      Role::Compared => "compared",
+      Role::Unpaired => "unpaired",
+      Role::Component => "component",
+      Role::ComplexPart => "complex part",
+      Role::Magnitude => "magnitude",
+    }
+  }
+}
+
+/// The options that select or shape columns, with every column given as a
+/// (0-based) column of csv1.
+#[derive(Clone, Debug)]
+pub struct ColumnOptions {
+  /// How many columns csv1 and csv2 have.
+  pub widths: (usize, usize),
+  /// What pairs the columns, and csv2's partner of each column of csv1, if
+  /// it has one.
+  pub pairing: (Source, Vec<Option<usize>>),
+  /// The `--vector-cols` groups.
+  pub vector_groups: Vec<Vec<usize>>,
+  /// Whether the components are left out of the comparison.
+  pub vector_only: bool,
+  /// The `--complex-pairs`, real part first.
+  pub complex_pairs: Vec<(usize, usize)>,
+  /// The columns whose signs may be flipped.
+  pub sign_flip: SignFlip,
+  /// Whether columns are normalized.
+  pub normalize: bool,
+  /// The key columns rows are sorted, matched, grouped or aligned on, and
+  /// the option naming them.
+  pub keys: Vec<(usize, Source)>,
+}
+
+/// A column's effective configuration.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EffectiveColumn {
+  /// The (1-based) column of csv1, a magnitude's as appended to it.
+  pub col: usize,
+  /// The (1-based) column of csv2 it's paired with, if any.
+  pub col2: Option<usize>,
+  /// What becomes of it.
+  pub role: Role,
+  /// Whether csv2's signs may be flipped.
+  pub sign_flip: bool,
+  /// Whether it's divided by its norm.
+  pub normalized: bool,
+  /// Whether rows are sorted, matched, grouped or aligned on it.
+  pub key: bool,
+  /// The options that made it so, in the order they apply.
+  pub sources: Vec<Source>,
+}
+
+/// Two options that claim a column in ways that can't both hold.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+  /// The (1-based) column of csv1.
+  pub col: usize,
+  /// The option that claimed it first, and how.
+  pub first: (Source, &'static str),
+  /// The option that claims it again, and how.
+  pub second: (Source, &'static str),
+}
+
+impl fmt::Display for Conflict {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let ((first, how1), (second, how2)) = (self.first, self.second);
+    if self.first == self.second {
+      return write!(
+        f,
+        "column {} is {how1} twice by {}",
+        self.col,
+        first.name()
+      );
+    }
+    write!(
+      f,
+      "column {} is {how1} by {}, but {how2} by {}",
+      self.col,
+      first.name(),
+      second.name()
+    )
+  }
+}
+
+/// Resolves the options into each column's effective configuration: csv1's
+/// columns, then the magnitudes of the vectors and complex pairs, in that
+/// order. Columns past csv1's width are left for the options' own checks.
+pub fn resolve(
+  options: &ColumnOptions,
+) -> Result<Vec<EffectiveColumn>, Conflict> {
+  let (pairing, partners) = &options.pairing;
+  let (width, width2) = options.widths;
+  let mut columns: Vec<EffectiveColumn> = (0..width)
+    .map(|col| {
+      let col2 = partners.get(col).copied().flatten();
+      EffectiveColumn {
+        col: col + 1,
+        col2: col2.map(|c| c + 1),
+        role: match col2 {
+          Some(_) => Role::Compared,
+          None => Role::Unpaired,
+        },
+        sign_flip: false,
+        normalized: false,
+        key: false,
+        sources: vec![*pairing],
+      }
+    })
+    .collect();
+  let conflict = |col: usize, first, second| Conflict {
+    col: col + 1,
+    first,
+    second,
+  };
+  // whether a column exists and is paired, as a derived column needs
+  let paired =
+    |columns: &[EffectiveColumn], col: usize, claim| match columns.get(col) {
+      Some(c) if c.col2.is_none() => {
+        Err(conflict(col, (*pairing, "left unpaired"), claim))
+      }
+      _ => Ok(()),
+    };
+
+  // 2. derived columns
+  let mut claimed: Vec<Option<Source>> = vec![None; width];
+  for group in &options.vector_groups {
+    let claim = (Source::VectorCols, "made a vector component");
+    for (at, &col) in group.iter().enumerate() {
+      if group[..at].contains(&col) {
+        return Err(conflict(col, claim, claim));
+      }
+      paired(&columns, col, claim)?;
+      if let Some(column) = columns.get_mut(col) {
+        if !column.sources.contains(&Source::VectorCols) {
+          column.sources.push(Source::VectorCols);
+        }
+        if options.vector_only {
+          column.role = Role::Component;
+          column.sources.push(Source::VectorOnly);
+        }
+        claimed[col] = Some(Source::VectorCols);
+      }
+    }
+  }
+  let part = (Source::ComplexPairs, "made a complex part");
+  for &(re, im) in &options.complex_pairs {
+    if re == im {
+      return Err(conflict(re, part, part));
+    }
+    for col in [re, im] {
+      paired(&columns, col, part)?;
+      let Some(column) = columns.get_mut(col) else {
+        continue;
+      };
+      match claimed[col] {
+        Some(Source::VectorCols) => {
+          let component = (Source::VectorCols, "made a vector component");
+          return Err(conflict(col, component, part));
+        }
+        Some(_) => return Err(conflict(col, part, part)),
+        None => {}
+      }
+      column.role = Role::ComplexPart;
+      column.sources.push(Source::ComplexPairs);
+      claimed[col] = Some(Source::ComplexPairs);
+    }
+  }
+
+  let magnitudes = options
+    .vector_groups
+    .iter()
+    .map(|_| Source::VectorCols)
+    .chain(options.complex_pairs.iter().map(|_| Source::ComplexPairs));
+  for (m, source) in magnitudes.enumerate() {
+    columns.push(EffectiveColumn {
+      col: width + m + 1,
+      col2: Some(width2 + m + 1),
+      role: Role::Magnitude,
+      sign_flip: false,
+      normalized: false,
+      key: false,
+      sources: vec![source],
+    });
+  }
+
+  // 3. policies
+  let compared = |column: &EffectiveColumn| {
+    matches!(column.role, Role::Compared | Role::Magnitude)
+  };
+  match &options.sign_flip {
+    SignFlip::Never => {}
+    SignFlip::AnyColumn => {
+      for column in columns.iter_mut().filter(|c| compared(c)) {
+        column.sign_flip = true;
+        column.sources.push(Source::SignFlip);
+      }
+    }
+    SignFlip::Columns(cols) => {
+      let flip = (Source::SignFlipCols, "allowed a sign flip");
+      let left_out = "left out of the comparison";
+      for &col in cols.iter().filter(|&&col| col < width) {
+        let column = &mut columns[col];
+        match column.role {
+          Role::Unpaired => {
+            return Err(conflict(col, (*pairing, "left unpaired"), flip));
+          }
+          Role::Component => {
+            return Err(conflict(col, (Source::VectorOnly, left_out), flip));
+          }
+          Role::ComplexPart => {
+            let part = (Source::ComplexPairs, left_out);
+            return Err(conflict(col, part, flip));
+          }
+          Role::Compared | Role::Magnitude => {}
+        }
+        column.sign_flip = true;
+        column.sources.push(Source::SignFlipCols);
+      }
+    }
+  }
+  for &(col, source) in &options.keys {
+    if col >= width {
+      continue;
+    }
+    paired(&columns, col, (source, "made a key"))?;
+    let column = &mut columns[col];
+    column.key = true;
+    column.sources.push(source);
+  }
+  if options.normalize {
+    for column in columns.iter_mut().filter(|c| compared(c)) {
+      column.normalized = true;
+      column.sources.push(Source::Normalize);
+    }
+  }
+  Ok(columns)
+}
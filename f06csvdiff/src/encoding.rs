@@ -0,0 +1,166 @@
+//! Decoding input files to UTF-8 before they're parsed, for files that
+//! aren't quite UTF-8: Excel exports that start with a byte order mark,
+//! which would otherwise stick to the first cell and keep its column from
+//! being compared, and legacy post-processors writing Latin-1, e.g. a degree
+//! sign in a header, which the CSV reader rejects.
+
+use std::io::{self, Read};
+
+/// The UTF-8 byte order mark.
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// How many bytes are read from the file at a time.
+const CHUNK: usize = 8192;
+
+/// How a file's bytes are decoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+  /// UTF-8, with its byte order mark, if any, taken off; anything else is
+  /// an error.
+  Utf8,
+  /// Latin-1 (ISO 8859-1): each byte is the character of that code point.
+  Latin1,
+  /// UTF-8 with its byte order mark, if any, taken off, until a byte that
+  /// isn't; from there on, Latin-1.
+  #[default]
+  Auto,
+}
+
+impl std::str::FromStr for Encoding {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "utf8" | "utf-8" => Ok(Encoding::Utf8),
+      "latin1" | "latin-1" => Ok(Encoding::Latin1),
+      "auto" => Ok(Encoding::Auto),
+      _ => Err(format!(
+        "Invalid encoding: {s}. Must be utf8, latin1, or auto"
+      )),
+    }
+  }
+}
+
+impl Encoding {
+  /// The name of the encoding, as --encoding takes it.
+  pub fn name(self) -> &'static str {
+    match self {
+      Encoding::Utf8 => "utf8",
+      Encoding::Latin1 => "latin1",
+      Encoding::Auto => "auto",
+    }
+  }
+}
+
+/// A reader decoding another's bytes to UTF-8. Bytes that aren't valid in
+/// the encoding fail the read with [`io::ErrorKind::InvalidData`], giving
+/// their offset in the file.
+pub struct Decoder<R> {
+  inner: R,
+  encoding: Encoding,
+  /// Whether the start of the file, with any byte order mark, is past.
+  started: bool,
+  /// Whether the rest of the file is Latin-1.
+  latin1: bool,
+  /// The offset in the file of the first byte of `pending`.
+  offset: u64,
+  /// Bytes read but not decoded yet, e.g. an incomplete UTF-8 sequence.
+  pending: Vec<u8>,
+  /// Bytes decoded but not read yet.
+  decoded: Vec<u8>,
+  /// How many of `decoded` have been read.
+  taken: usize,
+}
+
+impl<R: Read> Decoder<R> {
+  /// Decodes `inner`, read from its start, as `encoding`.
+  pub fn new(inner: R, encoding: Encoding) -> Self {
+    Self {
+      inner,
+      encoding,
+      started: false,
+      latin1: encoding == Encoding::Latin1,
+      offset: 0,
+      pending: Vec::new(),
+      decoded: Vec::new(),
+      taken: 0,
+    }
+  }
+
+  /// Reads and decodes the next chunk of the file into `decoded`, which
+  /// stays empty at its end.
+  fn fill(&mut self) -> io::Result<()> {
+    self.decoded.clear();
+    self.taken = 0;
+    while self.decoded.is_empty() {
+      let mut chunk = [0; CHUNK];
+      let read = self.inner.read(&mut chunk)?;
+      let end = read == 0;
+      self.pending.extend_from_slice(&chunk[..read]);
+      if !self.started {
+        // the byte order mark has to be whole to be seen
+        if !end && self.pending.len() < BOM.len() {
+          continue;
+        }
+        self.started = true;
+        if !self.latin1 && self.pending.starts_with(BOM) {
+          self.pending.drain(..BOM.len());
+          self.offset += BOM.len() as u64;
+        }
+      }
+      self.decode(end)?;
+      if end {
+        break;
+      }
+    }
+    Ok(())
+  }
+
+  /// Decodes as much of `pending` as is complete, or all of it at the end
+  /// of the file.
+  fn decode(&mut self, end: bool) -> io::Result<()> {
+    if !self.latin1 {
+      let valid = match std::str::from_utf8(&self.pending) {
+        Ok(_) => self.pending.len(),
+        // an incomplete sequence may be completed by the next chunk
+        Err(e) if e.error_len().is_none() && !end => e.valid_up_to(),
+        Err(e) => {
+          let at = self.offset + e.valid_up_to() as u64;
+          if self.encoding == Encoding::Utf8 {
+            return Err(io::Error::new(
+              io::ErrorKind::InvalidData,
+              format!("invalid UTF-8 at byte {at}"),
+            ));
+          }
+          self.latin1 = true;
+          e.valid_up_to()
+        }
+      };
+      self.decoded.extend(self.pending.drain(..valid));
+      self.offset += valid as u64;
+      if !self.latin1 {
+        return Ok(());
+      }
+    }
+    for byte in self.pending.drain(..) {
+      let mut utf8 = [0; 2];
+      let c = char::from(byte).encode_utf8(&mut utf8);
+      self.decoded.extend_from_slice(c.as_bytes());
+      self.offset += 1;
+    }
+    Ok(())
+  }
+}
+
+impl<R: Read> Read for Decoder<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.taken == self.decoded.len() {
+      self.fill()?;
+    }
+    let rest = &self.decoded[self.taken..];
+    let n = rest.len().min(buf.len());
+    buf[..n].copy_from_slice(&rest[..n]);
+    self.taken += n;
+    Ok(n)
+  }
+}
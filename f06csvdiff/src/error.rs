@@ -0,0 +1,307 @@
+//! The ways a comparison can fail to reach a verdict.
+
+use std::fmt::Display;
+
+/// An error that prevented comparing two files.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DiffError {
+  /// The files have different numbers of rows, and the extra ones aren't
+  /// allowed.
+  RowCountMismatch {
+    /// The rows in the first file.
+    rows1: usize,
+    /// The rows in the second file.
+    rows2: usize,
+    /// The longer file.
+    file: String,
+    /// The physical (1-based) line of its first disallowed extra row.
+    line: usize,
+  },
+  /// A row has different numbers of columns in each file.
+  ColumnCountMismatch {
+    /// The (1-based) line of the row.
+    line: usize,
+    /// The columns in the first file's row.
+    cols1: usize,
+    /// The columns in the second file's row.
+    cols2: usize,
+  },
+  /// A cell in a float column couldn't be parsed.
+  ParseError {
+    /// The file the cell is in.
+    file: String,
+    /// The (1-based) line of the cell.
+    line: usize,
+    /// The cell's contents.
+    value: String,
+  },
+  /// A row has different numbers of floats in each file.
+  FloatCountMismatch {
+    /// The (1-based) line of the row.
+    line: usize,
+  },
+  /// A cell is a float in one file but not in the other (strict layout).
+  FloatLayoutMismatch {
+    /// The (1-based) line of the cell.
+    line: usize,
+    /// The (1-based) column of the cell.
+    col: usize,
+    /// The cell in the first file.
+    cell1: String,
+    /// The cell in the second file.
+    cell2: String,
+  },
+  /// A cell asked about isn't among the compared rows and columns.
+  NoSuchCell {
+    /// The (1-based) physical line asked about, in the first file.
+    line: usize,
+    /// The (1-based) column asked about.
+    col: usize,
+  },
+  /// A header has the same name twice, so columns can't be matched by name.
+  DuplicateHeader {
+    /// The file the header is in.
+    file: String,
+    /// The name.
+    name: String,
+    /// The (1-based) columns it names.
+    cols: (usize, usize),
+  },
+  /// A row lacks a column the column map pairs.
+  MappedColumnMissing {
+    /// The file the row is in.
+    file: String,
+    /// The (1-based) physical line of the row.
+    line: usize,
+    /// The (1-based) column it lacks.
+    col: usize,
+  },
+  /// A record of a file has a different number of fields than the first.
+  FieldCountChange {
+    /// The file.
+    file: String,
+    /// The (1-based) physical line of the record.
+    line: usize,
+    /// How many fields it has.
+    fields: usize,
+    /// How many the first record has.
+    expected: usize,
+  },
+  /// A row lacks a column its file is sorted on.
+  SortKeyMissing {
+    /// The file the row is in.
+    file: String,
+    /// The (1-based) physical line of the row.
+    line: usize,
+    /// The (1-based) key column it lacks.
+    col: usize,
+  },
+  /// A row lacks a component of a vector, or has one that isn't a float.
+  VectorComponent {
+    /// The file the row is in.
+    file: String,
+    /// The (1-based) physical line of the row.
+    line: usize,
+    /// The (1-based) column of the component.
+    col: usize,
+  },
+  /// A row lacks a number in the column rows are matched on.
+  MatchKey {
+    /// The file the row is in.
+    file: String,
+    /// The (1-based) physical line of the row.
+    line: usize,
+    /// The (1-based) column matched on.
+    col: usize,
+  },
+  /// A row lacks the column rows are paired by key on.
+  KeyMissing {
+    /// The file the row is in.
+    file: String,
+    /// The (1-based) physical line of the row.
+    line: usize,
+    /// The (1-based) key column.
+    col: usize,
+  },
+  /// A row lacks a number in the column rows are aligned on.
+  ShiftKey {
+    /// The file the row is in.
+    file: String,
+    /// The (1-based) physical line of the row.
+    line: usize,
+    /// The (1-based) column aligned on.
+    col: usize,
+  },
+  /// A row lacks the column rows are grouped by.
+  GroupKeyMissing {
+    /// The file the row is in.
+    file: String,
+    /// The (1-based) physical line of the row.
+    line: usize,
+    /// The (1-based) column grouped by.
+    col: usize,
+  },
+  /// A group of rows is in one file but not in the other.
+  GroupMissing {
+    /// The group's value.
+    group: String,
+    /// The file it's in.
+    file: String,
+    /// The file it isn't in.
+    other: String,
+  },
+  /// A row of a scalars file isn't a name and a value.
+  ScalarLayout {
+    /// The file the row is in.
+    file: String,
+    /// The (1-based) line of the row.
+    line: usize,
+    /// How many fields it has.
+    fields: usize,
+  },
+  /// A name appears twice in a scalars file.
+  DuplicateScalar {
+    /// The file the name is in.
+    file: String,
+    /// The (1-based) line of its second appearance.
+    line: usize,
+    /// The name.
+    name: String,
+  },
+  /// A file's size or modification time changed while it was being read,
+  /// e.g. because it was still being written.
+  InputChanged {
+    /// The file.
+    file: String,
+  },
+  /// A result block couldn't be extracted from an .f06 file.
+  Extraction {
+    /// The .f06 file.
+    file: String,
+    /// The block, by name.
+    block: &'static str,
+    /// What went wrong.
+    message: String,
+  },
+  /// A file couldn't be opened or read.
+  Io {
+    /// The file.
+    file: String,
+    /// What went wrong.
+    source: std::io::Error,
+  },
+}
+
+impl Display for DiffError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::RowCountMismatch {
+        rows1,
+        rows2,
+        file,
+        line,
+      } => write!(
+        f,
+        "files have different number of rows ({rows1} vs {rows2}): line \
+         {line} of {file} has no counterpart"
+      ),
+      Self::ColumnCountMismatch { line, cols1, cols2 } => write!(
+        f,
+        "column count differs at line {line}: {cols1} vs {cols2} columns"
+      ),
+      Self::ParseError { file, line, value } => {
+        write!(f, "could not parse '{value}' in {file} at line {line}")
+      }
+      Self::FloatCountMismatch { line } => {
+        write!(f, "float layout differs at line {line}")
+      }
+      Self::FloatLayoutMismatch {
+        line,
+        col,
+        cell1,
+        cell2,
+      } => write!(
+        f,
+        "float layout differs at line {line}, column {col}: '{cell1}' vs \
+         '{cell2}'"
+      ),
+      Self::NoSuchCell { line, col } => write!(
+        f,
+        "no compared cell at line {line}, column {col} of the first file"
+      ),
+      Self::DuplicateHeader { file, name, cols } => write!(
+        f,
+        "the header of {file} names columns {} and {} both {name}",
+        cols.0, cols.1
+      ),
+      Self::MappedColumnMissing { file, line, col } => write!(
+        f,
+        "line {line} of {file} has no column {col}, which the column map pairs"
+      ),
+      Self::FieldCountChange {
+        file,
+        line,
+        fields,
+        expected,
+      } => write!(
+        f,
+        "line {line} of {file} has {fields} field(s), but the first row has \
+         {expected}"
+      ),
+      Self::SortKeyMissing { file, line, col } => {
+        write!(f, "line {line} of {file} has no column {col} to sort on")
+      }
+      Self::VectorComponent { file, line, col } => write!(
+        f,
+        "line {line} of {file} has no float in column {col}, a vector \
+         component"
+      ),
+      Self::MatchKey { file, line, col } => write!(
+        f,
+        "line {line} of {file} has no number in column {col} to match rows \
+         on"
+      ),
+      Self::KeyMissing { file, line, col } => write!(
+        f,
+        "line {line} of {file} has no column {col} to pair rows by key on"
+      ),
+      Self::ShiftKey { file, line, col } => write!(
+        f,
+        "line {line} of {file} has no number in column {col} to align rows \
+         on"
+      ),
+      Self::GroupKeyMissing { file, line, col } => {
+        write!(f, "line {line} of {file} has no column {col} to group by")
+      }
+      Self::GroupMissing { group, file, other } => {
+        write!(f, "group {group} is in {file} but not in {other}")
+      }
+      Self::ScalarLayout { file, line, fields } => write!(
+        f,
+        "line {line} of {file} has {fields} field(s), not a name and a value"
+      ),
+      Self::DuplicateScalar { file, line, name } => {
+        write!(f, "{name} appears again at line {line} of {file}")
+      }
+      Self::InputChanged { file } => {
+        write!(f, "input changed during read: {file}")
+      }
+      Self::Extraction {
+        file,
+        block,
+        message,
+      } => write!(f, "extracting the {block} block of {file}: {message}"),
+      Self::Io { file, source } => write!(f, "could not read {file}: {source}"),
+    }
+  }
+}
+
+impl std::error::Error for DiffError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Self::Io { source, .. } => Some(source),
+      _ => None,
+    }
+  }
+}
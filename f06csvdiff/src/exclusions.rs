@@ -0,0 +1,191 @@
+//! Exclusions: cells investigated and accepted, such as singular-point
+//! stresses or boundary artifacts, that are left out of the comparison
+//! rather than loosening the tolerances of the whole file.
+//!
+//! `--exclude-cells` reads them from a TOML file of `[[cell]]` tables, each
+//! naming a cell of csv1 by its physical line, or by the value of the row's
+//! key column, and its column, by number or, with `--has-headers`, by name:
+//!
+//! ```toml
+//! key_column = "GRID"
+//!
+//! [[cell]]
+//! line = 120
+//! column = 5
+//! comment = "singular stress at the re-entrant corner"
+//!
+//! [[cell]]
+//! pair = "sol101.csv"
+//! key = "1042"
+//! column = "SXX"
+//! expires = "once the mesh is refined, NAS-1234"
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use csv::StringRecord;
+use serde::Deserialize;
+
+use f06csvdiff::compare::physical_line;
+use f06csvdiff::waivers;
+
+/// The cells each exclusion names, as (physical line, 0-based column) of
+/// csv1, by the exclusion's index.
+pub(crate) type Resolved = Vec<(usize, Vec<(usize, usize)>)>;
+
+/// The contents of an exclusions file.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Exclusions {
+  /// The column of csv1 holding each row's key, for exclusions naming
+  /// their row by key.
+  pub(crate) key_column: Option<Column>,
+  /// The exclusions.
+  pub(crate) cell: Vec<Exclusion>,
+}
+
+/// A column of csv1, by (1-based) number or by header name.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Column {
+  /// The (1-based) column.
+  Number(usize),
+  /// The column of this name in csv1's header.
+  Name(String),
+}
+
+/// A cell left out of the comparison. It names its row by line or by key.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Exclusion {
+  /// The pair it applies to: either file's path or name, or both paths as
+  /// in result lines, "csv1|csv2". Every pair if left out.
+  pub(crate) pair: Option<String>,
+  /// The (1-based) physical line of csv1.
+  pub(crate) line: Option<usize>,
+  /// The value of the key column: every row of csv1 holding it.
+  pub(crate) key: Option<String>,
+  /// The column.
+  pub(crate) column: Column,
+  /// Why it's accepted.
+  pub(crate) comment: Option<String>,
+  /// When it should be revisited, as a note; it isn't enforced.
+  pub(crate) expires: Option<String>,
+}
+
+impl Exclusions {
+  /// Reads and parses an exclusions file.
+  pub(crate) fn load(path: &Path) -> Result<Self, String> {
+    let bad = |e: &dyn std::fmt::Display| {
+      format!("bad exclusions file {}: {e}", path.display())
+    };
+    let text = std::fs::read_to_string(path)
+      .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+    let exclusions: Self = toml::from_str(&text).map_err(|e| bad(&e))?;
+    if exclusions.key_column == Some(Column::Number(0)) {
+      return Err(bad(&"key_column: columns start at 1"));
+    }
+    for exclusion in &exclusions.cell {
+      let at = exclusion.describe();
+      match (exclusion.line, &exclusion.key) {
+        (Some(_), Some(_)) | (None, None) => {
+          return Err(bad(&format!("{at}: give either a line or a key")));
+        }
+        (Some(0), _) => return Err(bad(&format!("{at}: lines start at 1"))),
+        (None, Some(_)) if exclusions.key_column.is_none() => {
+          return Err(bad(&format!("{at}: a key needs a key_column")));
+        }
+        _ => {}
+      }
+      if exclusion.column == Column::Number(0) {
+        return Err(bad(&format!("{at}: columns start at 1")));
+      }
+    }
+    Ok(exclusions)
+  }
+
+  /// The cells each exclusion applying to this pair names, as (physical
+  /// line, 0-based column) of csv1, by the exclusion's index; none for one
+  /// naming a line, key or column csv1 doesn't have. Fails if a column is
+  /// named without csv1's header.
+  pub(crate) fn resolve(
+    &self,
+    paths: (&str, &str),
+    records1: &[StringRecord],
+    header: Option<&StringRecord>,
+  ) -> Result<Resolved, String> {
+    let index = |column: &Column| match column {
+      Column::Number(col) => Ok(Some(col - 1)),
+      Column::Name(name) => match header {
+        Some(header) => Ok(header.iter().position(|n| n.trim() == name)),
+        None => Err(format!(
+          "--exclude-cells: column {name} needs --has-headers"
+        )),
+      },
+    };
+    let applying: Vec<(usize, &Exclusion)> = self
+      .cell
+      .iter()
+      .enumerate()
+      .filter(|(_, e)| e.pair.as_ref().is_none_or(|p| waivers::names(p, paths)))
+      .collect();
+    // the rows of each key, gathered once for every exclusion by key
+    let mut keyed: HashMap<&str, Vec<usize>> = HashMap::new();
+    if applying.iter().any(|(_, e)| e.key.is_some())
+      && let Some(key_column) = &self.key_column
+      && let Some(col) = index(key_column)?
+    {
+      for (row, record) in records1.iter().enumerate() {
+        if let Some(key) = record.get(col) {
+          let line = physical_line(records1, row);
+          keyed.entry(key.trim()).or_default().push(line);
+        }
+      }
+    }
+    let lines: HashSet<usize> = (0..records1.len())
+      .map(|row| physical_line(records1, row))
+      .collect();
+    let mut resolved = Vec::new();
+    for (i, exclusion) in applying {
+      let rows = match (&exclusion.key, exclusion.line) {
+        (Some(key), _) => keyed.get(key.trim()).cloned().unwrap_or_default(),
+        (None, Some(line)) if lines.contains(&line) => vec![line],
+        _ => Vec::new(),
+      };
+      let cells = match index(&exclusion.column)? {
+        Some(col) => rows.into_iter().map(|line| (line, col)).collect(),
+        None => Vec::new(),
+      };
+      resolved.push((i, cells));
+    }
+    Ok(resolved)
+  }
+}
+
+impl Exclusion {
+  /// Describes it for reports, e.g. "line 120, column 5 (singular stress
+  /// at the re-entrant corner)".
+  pub(crate) fn describe(&self) -> String {
+    let row = match (&self.key, self.line) {
+      (Some(key), _) => format!("key {key}"),
+      (None, Some(line)) => format!("line {line}"),
+      (None, None) => "no row".to_string(),
+    };
+    let column = match &self.column {
+      Column::Number(col) => format!("column {col}"),
+      Column::Name(name) => format!("column {name}"),
+    };
+    let notes: Vec<String> = [
+      self.comment.clone(),
+      self.expires.as_ref().map(|e| format!("expires {e}")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    match notes.is_empty() {
+      true => format!("{row}, {column}"),
+      false => format!("{row}, {column} ({})", notes.join("; ")),
+    }
+  }
+}
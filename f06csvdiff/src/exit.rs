@@ -0,0 +1,111 @@
+//! Exit statuses and reporting of fatal errors.
+//!
+//! Like diff(1), we exit with 0 when everything passed, 1 when some
+//! comparison failed, and 2 when something prevented comparing at all. An
+//! input that changed while it was read gets 3 of its own, since running
+//! again later may well succeed, and failures that waivers excuse get 4, a
+//! warning rather than an error. Comparisons that pass but exceed a warning
+//! tolerance (--warn-ratio, --warn-diff) get 5. Both only warn, but they
+//! stay apart: a waived comparison did fail, and fails again once its
+//! waiver expires, so a pipeline may want to hold it up where it lets a
+//! warning through. A batch with both exits with 4.
+
+use std::fmt::Display;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::bar;
+
+/// Some comparison failed its tolerances.
+pub(crate) const FAILED: i32 = 1;
+/// Something went wrong before a verdict could be reached.
+pub(crate) const ERROR: i32 = 2;
+/// An input file changed while it was being read.
+pub(crate) const CHANGED: i32 = 3;
+/// Some comparison failed, but waivers excuse every failure.
+pub(crate) const WAIVED: i32 = 4;
+/// Every comparison passed, but some exceeded a warning tolerance.
+pub(crate) const WARNED: i32 = 5;
+
+/// Whether notes to stderr are suppressed (-q).
+static QUIET: AtomicBool = AtomicBool::new(false);
+/// Whether messages to stderr are suppressed (-qq).
+static SILENT: AtomicBool = AtomicBool::new(false);
+
+/// Suppresses the notes on stderr from now on, leaving warnings and errors.
+pub(crate) fn quiet() {
+  QUIET.store(true, Ordering::Relaxed);
+}
+
+/// Suppresses every message to stderr from now on.
+pub(crate) fn silence() {
+  SILENT.store(true, Ordering::Relaxed);
+}
+
+/// Whether messages to stderr are suppressed.
+pub(crate) fn silenced() -> bool {
+  SILENT.load(Ordering::Relaxed)
+}
+
+/// Prints a warning or note to stderr, unless silenced.
+pub(crate) fn notice(msg: impl Display) {
+  if !silenced() {
+    bar::interrupt();
+    eprintln!("{msg}");
+  }
+}
+
+/// Prints a note to stderr, something the report tells as well as the
+/// command to reproduce it, unless quiet.
+pub(crate) fn note(msg: impl Display) {
+  if !QUIET.load(Ordering::Relaxed) {
+    notice(msg);
+  }
+}
+
+/// Prints an error to stderr, unless silenced, and exits.
+pub(crate) fn die(msg: impl Display) -> ! {
+  die_with(ERROR, msg)
+}
+
+/// Prints an error to stderr, unless silenced, and exits with this status.
+pub(crate) fn die_with(status: i32, msg: impl Display) -> ! {
+  notice(msg);
+  std::process::exit(status)
+}
+
+/// An error ending the run, returned up to where it's reported.
+#[derive(Debug)]
+pub(crate) struct Fatal {
+  /// The status to exit with.
+  status: i32,
+  /// What went wrong, as printed to stderr.
+  message: String,
+}
+
+impl Fatal {
+  /// Something that prevented comparing, exiting with [`ERROR`].
+  pub(crate) fn new(msg: impl Display) -> Self {
+    Self::with(ERROR, msg)
+  }
+
+  /// Something ending the run with this status.
+  pub(crate) fn with(status: i32, msg: impl Display) -> Self {
+    Self {
+      status,
+      message: msg.to_string(),
+    }
+  }
+
+  /// Prints it to stderr, unless silenced, and exits with its status.
+  pub(crate) fn die(self) -> ! {
+    die_with(self.status, self.message)
+  }
+}
+
+/// The report couldn't be written.
+impl From<io::Error> for Fatal {
+  fn from(e: io::Error) -> Self {
+    Self::new(format!("Error writing the report: {e}"))
+  }
+}
@@ -0,0 +1,241 @@
+//! Expected reports, for golden-report workflows: a run's per-column maxima
+//! are saved, and later runs are judged by how far they drift from them
+//! rather than by absolute tolerances.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::compare::{
+  AbsCompare, ColumnStats, DiffConfig, DiffReport, ExtraRows, RelMode,
+};
+
+/// The layout of saved reports; a file of another format is stale.
+pub const FORMAT: u32 = 1;
+
+/// A report saved as the expectation for later runs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedReport {
+  /// The layout of the file, [`FORMAT`] when written by this version.
+  pub format: u32,
+  /// The version of f06csvdiff that wrote it.
+  pub version: String,
+  /// The comparison options that shape the maxima, as
+  /// [`fingerprint`] gives them.
+  pub options: BTreeMap<String, String>,
+  /// The number of rows compared.
+  pub rows: usize,
+  /// The maxima of each compared float column, in column order.
+  pub columns: Vec<SavedColumn>,
+  /// The percentiles of the compared cells' relative differences, if they
+  /// were asked for. They're kept for reading, not judged by.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub percentiles: Option<SavedPercentiles>,
+}
+
+/// The percentiles of a saved report.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedPercentiles {
+  /// Whether they're exact, rather than within
+  /// [`ACCURACY`](crate::percentiles::ACCURACY).
+  pub exact: bool,
+  /// How many cells they're of.
+  pub counted: usize,
+  /// How many cells were skipped, and left out.
+  pub skipped: usize,
+  /// How many compared cells had undefined differences, and were left out.
+  pub undefined: usize,
+  /// Each percentile, with its relative difference in percent; none if no
+  /// cells were counted.
+  pub values: Vec<SavedPercentile>,
+}
+
+/// A percentile of a saved report.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedPercentile {
+  /// The percentile, e.g. 95.
+  pub percentile: f64,
+  /// The relative difference, in percent, that many cells are at or below.
+  pub value: Option<f64>,
+}
+
+/// A float column's maxima in a saved report.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedColumn {
+  /// The (1-based) column.
+  pub col: usize,
+  /// How many of its cells were compared.
+  pub compared: usize,
+  /// The worst relative difference, as |a-b|/max(|a|,|b|).
+  pub worst_rel: f64,
+  /// The worst absolute difference.
+  pub worst_diff: f64,
+}
+
+/// The comparison options that change what a report finds, by name. Two
+/// reports are only comparable if these agree.
+pub fn fingerprint(opts: &DiffConfig) -> BTreeMap<String, String> {
+  let extra_rows = match &opts.extra_rows {
+    ExtraRows::Forbid => "forbid".to_string(),
+    ExtraRows::Allow => "allow".to_string(),
+    ExtraRows::AllowMatching(pattern) => format!("allow matching {pattern}"),
+  };
+  let column_map = match &opts.column_map {
+    Some(map) => map
+      .pairs()
+      .map(|(col1, col2)| format!("{}:{}", col1 + 1, col2 + 1))
+      .collect::<Vec<_>>()
+      .join(" "),
+    None => "none".to_string(),
+  };
+  [
+    ("delimiter", char::from(opts.delimiter).to_string()),
+    ("threshold", opts.threshold.to_string()),
+    ("threshold_frac", opts.threshold_frac.to_string()),
+    ("sign_flip", format!("{:?}", opts.sign_flip)),
+    ("extra_rows", extra_rows),
+    ("rel_mode", opts.rel_mode.name().to_string()),
+    ("rel_floor", opts.rel_floor.to_string()),
+    ("fail_fast", format!("{:?}", opts.fail_fast)),
+    ("clamp_subnormals", opts.clamp_subnormals.to_string()),
+    ("interval", opts.interval.to_string()),
+    ("scale", format!("{} {}", opts.scale.0, opts.scale.1)),
+    ("column_map", column_map),
+  ]
+  .into_iter()
+  // left out unless set, so that reports saved before it still compare
+  .chain(match &opts.abs_compare {
+    AbsCompare::Never => None,
+    cols => Some(("abs_compare", format!("{cols:?}"))),
+  })
+  .chain((!opts.excluded_cells.is_empty()).then(|| {
+    let mut cells: Vec<_> = opts.excluded_cells.iter().copied().collect();
+    cells.sort_unstable();
+    let cells: Vec<String> = cells
+      .iter()
+      .map(|(line, col)| format!("{line}:{}", col + 1))
+      .collect();
+    ("excluded_cells", cells.join(" "))
+  }))
+  .chain(
+    opts
+      .sig_digits
+      .map(|digits| ("sig_digits", digits.to_string())),
+  )
+  .chain(
+    opts.normalize.as_ref().map(|normalize| {
+      ("normalize", normalize.normalization.name().to_string())
+    }),
+  )
+  .chain(opts.percent.as_ref().map(|percent| {
+    let cols: Vec<String> = percent
+      .cols
+      .iter()
+      .map(|col| (col + 1).to_string())
+      .collect();
+    let (c1, c2) = percent.conventions;
+    let value = format!(
+      "{} from {} and {} as {}",
+      cols.join(","),
+      c1.name(),
+      c2.name(),
+      percent.compared_as.name()
+    );
+    ("percent", value)
+  }))
+  .map(|(name, value)| (name.to_string(), value))
+  .collect()
+}
+
+impl SavedReport {
+  /// The expectation a report sets, as found with `opts`.
+  pub fn new(report: &DiffReport, opts: &DiffConfig) -> Self {
+    Self {
+      format: FORMAT,
+      version: env!("CARGO_PKG_VERSION").to_string(),
+      options: fingerprint(opts),
+      rows: report.rows,
+      columns: report
+        .columns
+        .iter()
+        .map(|c| SavedColumn {
+          col: c.col,
+          compared: c.compared,
+          worst_rel: c.worst_rel,
+          worst_diff: c.worst_diff,
+        })
+        .collect(),
+      percentiles: report.percentiles.as_ref().map(|p| SavedPercentiles {
+        exact: p.exact(),
+        counted: p.counted(),
+        skipped: p.skipped,
+        undefined: p.undefined,
+        values: p
+          .values()
+          .into_iter()
+          .map(|(percentile, value)| SavedPercentile { percentile, value })
+          .collect(),
+      }),
+    }
+  }
+
+  /// Reads a saved report.
+  pub fn from_json(text: &str) -> serde_json::Result<Self> {
+    serde_json::from_str(text)
+  }
+
+  /// Writes the report for [`from_json`](Self::from_json).
+  pub fn to_json(&self) -> String {
+    // plain data with string keys always serializes
+    serde_json::to_string_pretty(self).unwrap() + "\n"
+  }
+
+  /// Why the report can't be the expectation for a run with `opts`: each
+  /// difference in format, version or options. Empty if it can.
+  pub fn staleness(&self, opts: &DiffConfig) -> Vec<String> {
+    let mut found = Vec::new();
+    if self.format != FORMAT {
+      found.push(format!("format {} (expected {FORMAT})", self.format));
+    }
+    let version = env!("CARGO_PKG_VERSION");
+    if self.version != version {
+      found.push(format!(
+        "written by version {} (this is {version})",
+        self.version
+      ));
+    }
+    let current = fingerprint(opts);
+    let names = self.options.keys().chain(current.keys());
+    let mut names: Vec<&String> = names.collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+      let (saved, now) = (self.options.get(name), current.get(name));
+      if saved != now {
+        let show = |v: Option<&String>| v.map_or("unset".into(), String::clone);
+        found.push(format!("{name} was {} (now {})", show(saved), show(now)));
+      }
+    }
+    found
+  }
+
+  /// The saved maxima as a report, with only its rows and columns filled
+  /// in, e.g. to judge a run by with
+  /// [`RegressionGate::evaluate`](crate::regression::RegressionGate::evaluate).
+  pub fn to_report(&self) -> DiffReport {
+    DiffReport {
+      rows: self.rows,
+      columns: self
+        .columns
+        .iter()
+        .map(|c| ColumnStats {
+          compared: c.compared,
+          worst_rel: c.worst_rel,
+          worst_diff: c.worst_diff,
+          ..ColumnStats::new(c.col, 0, 0.0, RelMode::default())
+        })
+        .collect(),
+      ..Default::default()
+    }
+  }
+}
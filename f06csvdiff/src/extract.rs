@@ -0,0 +1,100 @@
+//! Extracting a result block of an .f06 file as the table f06csv writes for
+//! it, so that an .f06 file can be compared as it is, against another or
+//! against an archived CSV of the same block, without converting it first.
+//! The table is only ever kept in memory.
+
+use std::path::Path;
+
+use csv::{Position, StringRecord};
+use f06::prelude::{F06File, OnePassParser};
+use nas_csv::formatting::{
+  Alignment, BlankDisplay, CsvFormatting, FloatFormat,
+};
+use nas_csv::from_f06::templates::all_converters;
+use nas_csv::from_f06::to_records;
+use nas_csv::layout::CsvBlockId;
+
+use crate::error::DiffError;
+
+/// Whether a path names an .f06 file, by its extension.
+pub fn is_f06(path: &str) -> bool {
+  Path::new(path)
+    .extension()
+    .is_some_and(|ext| ext.eq_ignore_ascii_case("f06"))
+}
+
+/// Parses an .f06 file and extracts one of its result blocks, each record
+/// formatted as f06csv formats it by default, and preceded by the block's
+/// header if `headers`. A record's line is its (1-based) row in the table.
+/// Fails if the file can't be read or has no such block.
+pub fn extract(
+  path: &str,
+  block: CsvBlockId,
+  headers: bool,
+) -> Result<Vec<StringRecord>, DiffError> {
+  let error = |message: String| DiffError::Extraction {
+    file: path.to_string(),
+    block: block.name(),
+    message,
+  };
+  let mut f06 = OnePassParser::parse_file(path)
+    .map_err(|e| error(format!("could not read it: {e}")))?;
+  // as f06csv prepares a file
+  f06.merge_blocks(true);
+  f06.merge_potential_headers();
+  f06.sort_all_blocks();
+
+  let format = CsvFormatting {
+    reals: FloatFormat::default(),
+    blanks: BlankDisplay::default(),
+    align: Alignment::None,
+  };
+  let converters = all_converters();
+  let mut records = Vec::new();
+  // the blocks it has, sorted as f06csv writes them
+  let mut found: Vec<CsvBlockId> = Vec::new();
+  for record in to_records(&f06, &converters) {
+    if found.last() != Some(&record.block_id) {
+      found.push(record.block_id);
+    }
+    if record.block_id != block {
+      continue;
+    }
+    if headers && records.is_empty() {
+      records.push(StringRecord::from_iter(record.header_as_iter()));
+    }
+    records.push(
+      record
+        .to_fields()
+        .map(|field| format.to_string(field))
+        .collect(),
+    );
+  }
+  if records.is_empty() {
+    return Err(error(missing(&f06, &found)));
+  }
+  for (row, record) in records.iter_mut().enumerate() {
+    let mut position = Position::new();
+    position.set_line(row as u64 + 1).set_record(row as u64);
+    record.set_position(Some(position));
+  }
+  Ok(records)
+}
+
+/// Why a block wasn't found: the result blocks the file has instead, and
+/// the first fatal error the solver reported, which may explain it.
+fn missing(f06: &F06File, found: &[CsvBlockId]) -> String {
+  let found: Vec<&str> = found
+    .iter()
+    .filter(|&&id| id != CsvBlockId::Metadata)
+    .map(|id| id.shorthand())
+    .collect();
+  let mut message = match found.is_empty() {
+    true => "it has no result blocks".to_string(),
+    false => format!("it has no such block, only {}", found.join(", ")),
+  };
+  if let Some((line, fatal)) = f06.fatal_errors.iter().next() {
+    message += &format!(" (fatal error at line {line}: {})", fatal.trim());
+  }
+  message
+}
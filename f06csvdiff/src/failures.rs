@@ -0,0 +1,147 @@
+//! Every compared cell exceeding a tolerance, for seeing all of a run's
+//! regressions at once rather than only the worst cell of each gate.
+
+use csv::StringRecord;
+
+use crate::compare::{
+  DiffConfig, DiffReport, compare_alone, compared_values, parse_float,
+  physical_line,
+};
+use crate::impact::Impact;
+use crate::verdict::{Gate, Tolerances};
+
+/// A compared cell exceeding a tolerance on its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FailingCell {
+  /// The (1-based) physical line of the cell, in csv1.
+  pub line: usize,
+  /// The (1-based) column, in csv1.
+  pub col: usize,
+  /// Its values, as the report shows the worst cells': signed, even where
+  /// compared by magnitude.
+  pub vals: (f64, f64),
+  /// Its relative difference in percent, as the comparison measures it.
+  pub percent: f64,
+  /// Its absolute difference.
+  pub diff: f64,
+  /// The gates whose tolerances it exceeds.
+  pub gates: Vec<Gate>,
+}
+
+/// The cells of a comparison exceeding a tolerance, the worst first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Failures {
+  /// The cells listed: every one, or the worst of them up to a limit.
+  pub cells: Vec<FailingCell>,
+  /// How many cells exceed a tolerance, listed or not.
+  pub total: usize,
+}
+
+impl Failures {
+  /// Takes in the failing cells of another comparison of the files, such as
+  /// that of a column with tolerances of its own, keeping the worst cells
+  /// of both up to `limit` if there's one.
+  pub fn merge(&mut self, other: Failures, limit: Option<usize>) {
+    self.cells.extend(other.cells);
+    self.total += other.total;
+    worst_first(&mut self.cells, limit);
+  }
+}
+
+/// Sorts failing cells by relative difference, then absolute difference,
+/// the worst first and ties in file order, keeping up to `limit` of them if
+/// there's one.
+fn worst_first(cells: &mut Vec<FailingCell>, limit: Option<usize>) {
+  cells.sort_by(|a, b| {
+    let worse = b.percent.total_cmp(&a.percent);
+    worse
+      .then(b.diff.total_cmp(&a.diff))
+      .then((a.line, a.col).cmp(&(b.line, b.col)))
+  });
+  cells.truncate(limit.unwrap_or(cells.len()));
+}
+
+/// Finds every cell of the compared rows and columns of a comparison,
+/// reported by `report` (found with `opts`), that exceeds a tolerance on its
+/// own, as the report's failing cells count them: cells left out, skipped
+/// or, when only high-impact ones are judged, of low impact aren't. Cells
+/// are listed the worst first, up to `limit` of them if there's one.
+pub fn failing_cells(
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  opts: &DiffConfig,
+  report: &DiffReport,
+  limit: Option<usize>,
+) -> Failures {
+  // a cell alone fails whatever fraction of the cells may
+  let alone_opts = DiffConfig {
+    tolerances: Tolerances {
+      max_fail_fraction: None,
+      ..opts.tolerances.clone()
+    },
+    ..opts.clone()
+  };
+  let mut cells = Vec::new();
+  let rows = report.rows.min(records1.len()).min(records2.len());
+  for row in 0..rows {
+    let (rec1, rec2) = (&records1[row], &records2[row]);
+    let line = physical_line(records1, row);
+    let scale = opts.diff_scale.map(|_| opts.row_scale(rec1).unwrap_or(1.0));
+    for stats in &report.columns {
+      let i = stats.col - 1;
+      let text2 = opts.col2(i).and_then(|j| rec2.get(j));
+      let (Some(text1), Some(text2)) = (rec1.get(i), text2) else {
+        continue;
+      };
+      let (Some(v1), Some(v2)) = (parse_float(text1), parse_float(text2))
+      else {
+        continue;
+      };
+      if opts.excludes(line, i) {
+        continue;
+      }
+      let factors = opts.factors(i);
+      let (v1, v2) = (opts.value(factors.0, v1), opts.value(factors.1, v2));
+      let flipped = report.flipped_columns.contains(&i);
+      let cutoff = (stats.cutoff, opts.threshold_mode);
+      let Ok(signed) = compared_values(v1, v2, flipped, cutoff) else {
+        continue;
+      };
+      let abs = opts.abs_compare.applies(i);
+      let magnitudes = match abs {
+        true => (signed.0.abs(), signed.1.abs()),
+        false => signed,
+      };
+      let texts = (text1, text2);
+      let values = opts.measured(magnitudes, texts, factors);
+      let significance = opts.significance.get(&i).copied();
+      if opts.high_impact_only
+        && Impact::of(values, significance) == Impact::Low
+      {
+        continue;
+      }
+      let at = (line, stats.col);
+      let limits = (stats.cutoff, scale);
+      let alone = compare_alone(values, texts, at, limits, &alone_opts);
+      let gates: Vec<Gate> = alone
+        .gates
+        .iter()
+        .filter(|g| !g.passed)
+        .map(|g| g.gate)
+        .collect();
+      if gates.is_empty() {
+        continue;
+      }
+      cells.push(FailingCell {
+        line,
+        col: stats.col,
+        vals: if abs { signed } else { values },
+        percent: alone.ratio_percent(),
+        diff: alone.max_diff.value,
+        gates,
+      });
+    }
+  }
+  let total = cells.len();
+  worst_first(&mut cells, limit);
+  Failures { cells, total }
+}
@@ -0,0 +1,120 @@
+//! GitHub Actions workflow commands, so verdicts show up as annotations on
+//! the run. They always go to stdout, where the runner looks for them.
+
+use f06csvdiff::compare::DiffReport;
+use f06csvdiff::verdict::{Gate, GateResult, Tolerances};
+
+/// Escapes the message of a workflow command.
+pub(crate) fn escape_data(text: &str) -> String {
+  text
+    .replace('%', "%25")
+    .replace('\r', "%0D")
+    .replace('\n', "%0A")
+}
+
+/// Escapes a property value of a workflow command.
+pub(crate) fn escape_property(text: &str) -> String {
+  escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Describes a failed gate, and the line it points at if any.
+fn failure(
+  result: &GateResult,
+  tolerances: &Tolerances,
+  report: &DiffReport,
+) -> (String, Option<usize>) {
+  let (worst, observed, allowed) = match result.gate {
+    Gate::Ratio => (
+      &report.max_ratio,
+      format!("{:.2}%", report.ratio_percent()),
+      format!("{:.2}%", tolerances.max_ratio.unwrap_or(0.0) * 100.0),
+    ),
+    Gate::Diff => (
+      &report.max_diff,
+      format!("{:.2E}", report.max_diff.value),
+      format!("{:.2E}", tolerances.max_diff.unwrap_or(0.0)),
+    ),
+    Gate::Digits => {
+      let (digits, col) = report.worst_digits().unwrap_or((0.0, 0));
+      let min = tolerances.min_digits.unwrap_or(0.0);
+      return (
+        format!(
+          "digits of agreement {digits:.2} in column {col} is below {min:.2}"
+        ),
+        None,
+      );
+    }
+    Gate::Allclose => {
+      let worst = &report.allclose;
+      return (
+        format!(
+          "abs diff {:.2}x what allclose allows (values {:+.6E} and \
+           {:+.6E}, column {})",
+          worst.value, worst.vals.0, worst.vals.1, worst.col
+        ),
+        Some(worst.line),
+      );
+    }
+  };
+  let name = match result.gate {
+    Gate::Ratio => "max ratio",
+    _ => "max abs diff",
+  };
+  (
+    format!(
+      "{name} {observed} exceeds {allowed} (values {:+.6E} and {:+.6E}, \
+       column {})",
+      worst.vals.0, worst.vals.1, worst.col
+    ),
+    Some(worst.line),
+  )
+}
+
+/// The annotations for a comparison: an error per failed gate, pointing at
+/// the offending line of csv2, or a notice with the maxima if all passed.
+/// Failures waivers excuse are warnings instead, naming the waivers.
+pub(crate) fn annotations(
+  paths: (&str, &str),
+  tolerances: &Tolerances,
+  report: &DiffReport,
+  waiver: Option<&str>,
+) -> Vec<String> {
+  let results = tolerances.evaluate(report);
+  let file = escape_property(paths.1);
+  let failures: Vec<String> = results
+    .iter()
+    .filter(|r| !r.passed)
+    .map(|r| {
+      let (message, line) = failure(r, tolerances, report);
+      let line = line.map(|l| format!(",line={l}")).unwrap_or_default();
+      match waiver {
+        Some(waiver) => format!(
+          "::warning file={file}{line}::{} (waived by {waiver})",
+          escape_data(&message),
+          waiver = escape_data(waiver)
+        ),
+        None => format!("::error file={file}{line}::{}", escape_data(&message)),
+      }
+    })
+    .collect();
+  if !failures.is_empty() {
+    return failures;
+  }
+  let maxima: Vec<String> = results
+    .iter()
+    .map(|r| match r.gate {
+      Gate::Ratio => format!("max ratio {:.2}%", report.ratio_percent()),
+      Gate::Diff => format!("max abs diff {:.2E}", report.max_diff.value),
+      Gate::Digits => match report.worst_digits() {
+        Some((d, _)) => format!("min digits of agreement {d:.2}"),
+        None => "no digits of agreement".to_string(),
+      },
+      Gate::Allclose => {
+        format!("worst allclose {:.2}x its tolerance", report.allclose.value)
+      }
+    })
+    .collect();
+  let message =
+    format!("{} vs {} passed: {}", paths.0, paths.1, maxima.join(", "));
+  vec![format!("::notice file={file}::{}", escape_data(&message))]
+}
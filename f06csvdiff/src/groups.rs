@@ -0,0 +1,95 @@
+//! Groups of rows, such as the subcases of a combined table, compared each on
+//! its own so that a regression in one isn't hidden by a single global
+//! maximum. A group is the rows whose cell in a column holds a value; within
+//! a group, rows are paired in the order they come in each file.
+
+use csv::{Position, StringRecord};
+
+use crate::compare::{self, DiffConfig, DiffReport, physical_line};
+use crate::error::DiffError;
+
+/// A group's comparison.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupReport {
+  /// The value the group's rows hold, trimmed.
+  pub value: String,
+  /// How many rows it has in each file.
+  pub rows: (usize, usize),
+  /// What comparing its rows found.
+  pub report: DiffReport,
+}
+
+/// Each group's rows, by the group's value, in the order the groups first
+/// come. Fails with [`DiffError::GroupKeyMissing`] if a row lacks the
+/// column.
+fn partition(
+  records: &[StringRecord],
+  col: usize,
+  file: &str,
+) -> Result<Vec<(String, Vec<StringRecord>)>, DiffError> {
+  let mut groups: Vec<(String, Vec<StringRecord>)> = Vec::new();
+  for row in 0..records.len() {
+    let line = physical_line(records, row);
+    let mut record = records[row].clone();
+    // a group's rows keep the lines they were read from
+    if record.position().is_none() {
+      let mut position = Position::new();
+      position.set_line(line as u64);
+      record.set_position(Some(position));
+    }
+    let value = record.get(col).ok_or_else(|| DiffError::GroupKeyMissing {
+      file: file.to_string(),
+      line,
+      col: col + 1,
+    })?;
+    let value = value.trim().to_string();
+    match groups.iter_mut().find(|(v, _)| *v == value) {
+      Some((_, rows)) => rows.push(record),
+      None => groups.push((value, vec![record])),
+    }
+  }
+  Ok(groups)
+}
+
+/// Compares each group of rows, by their values in a (0-based) column of
+/// each file, as `opts` compares files. Fails with
+/// [`DiffError::GroupMissing`] if a group is in one file only, or as
+/// comparing a group fails.
+pub fn compare_groups(
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  (col1, col2): (usize, usize),
+  opts: &DiffConfig,
+  (file1, file2): (&str, &str),
+) -> Result<Vec<GroupReport>, DiffError> {
+  let groups1 = partition(records1, col1, file1)?;
+  let mut groups2 = partition(records2, col2, file2)?;
+  if let Some((value, _)) = groups2
+    .iter()
+    .find(|(v, _)| groups1.iter().all(|(w, _)| w != v))
+  {
+    return Err(DiffError::GroupMissing {
+      group: value.clone(),
+      file: file2.to_string(),
+      other: file1.to_string(),
+    });
+  }
+  groups1
+    .into_iter()
+    .map(|(value, rows1)| {
+      let Some(at) = groups2.iter().position(|(v, _)| *v == value) else {
+        return Err(DiffError::GroupMissing {
+          group: value,
+          file: file1.to_string(),
+          other: file2.to_string(),
+        });
+      };
+      let (_, rows2) = groups2.swap_remove(at);
+      let report = compare::compare(&rows1, &rows2, opts, (file1, file2))?;
+      Ok(GroupReport {
+        value,
+        rows: (rows1.len(), rows2.len()),
+        report,
+      })
+    })
+    .collect()
+}
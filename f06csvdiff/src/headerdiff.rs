@@ -0,0 +1,211 @@
+//! The differences between two files' headers, for when their schemas have
+//! drifted: the columns only one has, those both have in different places,
+//! and those likely renamed. The headers are aligned on their longest common
+//! subsequence of names, so that a column inserted or removed shows as just
+//! that, rather than as every column after it moving.
+
+use csv::StringRecord;
+use serde::Serialize;
+
+/// A column of a header.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HeaderColumn {
+  /// The (1-based) column.
+  pub col: usize,
+  /// Its name, as given.
+  pub name: String,
+}
+
+/// A name both headers give, at places the alignment doesn't pair.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Moved {
+  /// The name.
+  pub name: String,
+  /// Its (1-based) column in csv1.
+  pub from: usize,
+  /// Its (1-based) column in csv2.
+  pub to: usize,
+}
+
+/// Why a column only csv1 names and one only csv2 names may be the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameHint {
+  /// Their names differ only by case or whitespace.
+  Spelling,
+  /// They're in the same place: between the same aligned columns, in the
+  /// same order among as many others.
+  Place,
+}
+
+/// A column that may have been renamed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Renamed {
+  /// The column in csv1.
+  pub from: HeaderColumn,
+  /// The column in csv2.
+  pub to: HeaderColumn,
+  /// Why it may be the same column.
+  pub hint: RenameHint,
+}
+
+/// How two headers differ.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct HeaderDiff {
+  /// How many columns are named alike, in the same order, in both.
+  pub aligned: usize,
+  /// The columns only csv1 names.
+  pub removed: Vec<HeaderColumn>,
+  /// The columns only csv2 names.
+  pub added: Vec<HeaderColumn>,
+  /// The names both give, out of order.
+  pub moved: Vec<Moved>,
+  /// The columns that may have been renamed.
+  pub renamed: Vec<Renamed>,
+}
+
+impl HeaderDiff {
+  /// Whether the headers are the same.
+  pub fn is_empty(&self) -> bool {
+    self.removed.is_empty()
+      && self.added.is_empty()
+      && self.moved.is_empty()
+      && self.renamed.is_empty()
+  }
+}
+
+/// A name with case and runs of whitespace evened out.
+fn spelling(name: &str) -> String {
+  name
+    .split_whitespace()
+    .collect::<Vec<_>>()
+    .join(" ")
+    .to_lowercase()
+}
+
+/// The longest common subsequence of two lists of names, as pairs of
+/// (0-based) positions, in order.
+fn longest_common(names1: &[&str], names2: &[&str]) -> Vec<(usize, usize)> {
+  let (n, m) = (names1.len(), names2.len());
+  // lengths[i][j]: of the longest common subsequence of the tails from i, j
+  let mut lengths = vec![vec![0; m + 1]; n + 1];
+  for i in (0..n).rev() {
+    for j in (0..m).rev() {
+      lengths[i][j] = if names1[i] == names2[j] {
+        lengths[i + 1][j + 1] + 1
+      } else {
+        lengths[i + 1][j].max(lengths[i][j + 1])
+      };
+    }
+  }
+  let mut pairs = Vec::new();
+  let (mut i, mut j) = (0, 0);
+  while i < n && j < m {
+    if names1[i] == names2[j] {
+      pairs.push((i, j));
+      (i, j) = (i + 1, j + 1);
+    } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+      i += 1;
+    } else {
+      j += 1;
+    }
+  }
+  pairs
+}
+
+/// Takes the columns left in each header that `paired` says go together off
+/// them, first come first served, and returns them paired.
+fn pair_off(
+  (left1, left2): (&mut Vec<usize>, &mut Vec<usize>),
+  paired: impl Fn(usize, usize) -> bool,
+) -> Vec<(usize, usize)> {
+  let mut pairs = Vec::new();
+  left1.retain(|&i| {
+    let Some(at) = left2.iter().position(|&j| paired(i, j)) else {
+      return true;
+    };
+    pairs.push((i, left2.remove(at)));
+    false
+  });
+  pairs
+}
+
+/// Diffs two headers. Columns are aligned on equal names; of the rest, a
+/// name both give is moved, names differing only by case or whitespace are
+/// a rename, and so are the columns left alone in the same place. Whatever
+/// remains is only in one header.
+pub fn diff_headers(
+  header1: &StringRecord,
+  header2: &StringRecord,
+) -> HeaderDiff {
+  let names1: Vec<&str> = header1.iter().collect();
+  let names2: Vec<&str> = header2.iter().collect();
+  let aligned = longest_common(&names1, &names2);
+  let mut left1: Vec<usize> = (0..names1.len())
+    .filter(|&i| !aligned.iter().any(|&(a, _)| a == i))
+    .collect();
+  let mut left2: Vec<usize> = (0..names2.len())
+    .filter(|&j| !aligned.iter().any(|&(_, b)| b == j))
+    .collect();
+  let column = |names: &[&str], col: usize| HeaderColumn {
+    col: col + 1,
+    name: names[col].to_string(),
+  };
+  let mut diff = HeaderDiff {
+    aligned: aligned.len(),
+    ..Default::default()
+  };
+
+  for (i, j) in
+    pair_off((&mut left1, &mut left2), |i, j| names1[i] == names2[j])
+  {
+    diff.moved.push(Moved {
+      name: names1[i].to_string(),
+      from: i + 1,
+      to: j + 1,
+    });
+  }
+  let spelled = pair_off((&mut left1, &mut left2), |i, j| {
+    spelling(names1[i]) == spelling(names2[j])
+  });
+  // the aligned columns a column comes after, and its place among the
+  // columns left there
+  let gap = |col: usize, file: usize| {
+    aligned
+      .iter()
+      .filter(|&&(a, b)| if file == 1 { a < col } else { b < col })
+      .count()
+  };
+  let places = |left: &[usize], file: usize| -> Vec<(usize, usize)> {
+    left.iter().map(|&col| (gap(col, file), col)).collect()
+  };
+  let (places1, places2) = (places(&left1, 1), places(&left2, 2));
+  let in_gap = |places: &[(usize, usize)], gap: usize| -> Vec<usize> {
+    places.iter().filter(|p| p.0 == gap).map(|p| p.1).collect()
+  };
+  let same_place = |i: usize, j: usize| {
+    let (gap1, gap2) = (gap(i, 1), gap(j, 2));
+    let (cols1, cols2) = (in_gap(&places1, gap1), in_gap(&places2, gap2));
+    gap1 == gap2
+      && cols1.len() == cols2.len()
+      && cols1.iter().position(|&c| c == i)
+        == cols2.iter().position(|&c| c == j)
+  };
+  let placed = pair_off((&mut left1, &mut left2), same_place);
+
+  for (pairs, hint) in
+    [(spelled, RenameHint::Spelling), (placed, RenameHint::Place)]
+  {
+    for (i, j) in pairs {
+      diff.renamed.push(Renamed {
+        from: column(&names1, i),
+        to: column(&names2, j),
+        hint,
+      });
+    }
+  }
+  diff.renamed.sort_by_key(|r| r.from.col);
+  diff.removed = left1.iter().map(|&i| column(&names1, i)).collect();
+  diff.added = left2.iter().map(|&j| column(&names2, j)).collect();
+  diff
+}
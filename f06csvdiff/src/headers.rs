@@ -0,0 +1,103 @@
+//! Header rows: the first row of each file in header mode, the copies of it
+//! left through the body when files are concatenated with `cat`, and the
+//! pairing of columns by the names they give. Also the rows around the
+//! table that aren't part of it, such as a title block or a totals row.
+
+use csv::StringRecord;
+
+use crate::compare::{ColumnMap, physical_line};
+use crate::error::DiffError;
+
+/// What was taken off a file in header mode.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Headers {
+  /// The header row.
+  pub header: StringRecord,
+  /// The physical (1-based) lines of the copies of it removed from the body.
+  pub repeats: Vec<usize>,
+}
+
+/// Takes the header row off the front of a file's records and, with `dedup`,
+/// every row of the body identical to it. Empty files have no header.
+pub fn take_headers(
+  records: &mut Vec<StringRecord>,
+  dedup: bool,
+) -> Option<Headers> {
+  let first = records.first()?;
+  let repeats: Vec<usize> = if dedup {
+    (1..records.len())
+      .filter(|&row| records[row] == *first)
+      .map(|row| physical_line(records, row))
+      .collect()
+  } else {
+    Vec::new()
+  };
+  let header = records.remove(0);
+  if !repeats.is_empty() {
+    records.retain(|record| *record != header);
+  }
+  Some(Headers { header, repeats })
+}
+
+/// Drops `head` records from the start of a file and `tail` from its end,
+/// e.g. a title block and a totals row. The rest keep their positions. Fails
+/// with the number of records if there are fewer than that.
+pub fn skip_rows(
+  records: &mut Vec<StringRecord>,
+  (head, tail): (usize, usize),
+) -> Result<(), usize> {
+  let len = records.len();
+  if head + tail > len {
+    return Err(len);
+  }
+  records.truncate(len - tail);
+  records.drain(..head);
+  Ok(())
+}
+
+/// How the columns of two headers pair up by name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NameMatch {
+  /// The columns named in both headers, paired.
+  pub map: ColumnMap,
+  /// The (0-based) columns of each header whose names the other lacks.
+  pub only: (Vec<usize>, Vec<usize>),
+}
+
+/// Pairs the columns of two files' headers by name, trimmed and case-folded.
+/// Fails with [`DiffError::DuplicateHeader`] if a header has a name twice.
+pub fn match_by_name(
+  (file1, header1): (&str, &StringRecord),
+  (file2, header2): (&str, &StringRecord),
+) -> Result<NameMatch, DiffError> {
+  let names = |file: &str, header: &StringRecord| {
+    let names: Vec<String> = header
+      .iter()
+      .map(|name| name.trim().to_lowercase())
+      .collect();
+    for (col, name) in names.iter().enumerate() {
+      if let Some(first) = names[..col].iter().position(|n| n == name) {
+        return Err(DiffError::DuplicateHeader {
+          file: file.to_string(),
+          name: header[col].trim().to_string(),
+          cols: (first + 1, col + 1),
+        });
+      }
+    }
+    Ok(names)
+  };
+  let (names1, names2) = (names(file1, header1)?, names(file2, header2)?);
+  let col2 = |name: &String| names2.iter().position(|n| n == name);
+  let pairs: Vec<(usize, usize)> = names1
+    .iter()
+    .enumerate()
+    .filter_map(|(col1, name)| Some((col1, col2(name)?)))
+    .collect();
+  let only1 = (0..names1.len()).filter(|&c| col2(&names1[c]).is_none());
+  let only2 = (0..names2.len()).filter(|c| !names1.contains(&names2[*c]));
+  Ok(NameMatch {
+    // the names are unique, so no column is paired twice
+    map: ColumnMap::new(&pairs).unwrap(),
+    only: (only1.collect(), only2.collect()),
+  })
+}
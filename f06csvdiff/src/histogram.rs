@@ -0,0 +1,70 @@
+//! The distribution of the compared cells' relative differences, in
+//! logarithmic bins, for judging whether a tolerance is reasonable.
+
+/// The default bin edges, in percent.
+pub const DEFAULT_EDGES: [f64; 5] = [0.001, 0.01, 0.1, 1.0, 10.0];
+
+/// How many compared cells have relative differences in each bin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+  /// The ascending bin edges, in percent: bin 0 is below the first, bin i
+  /// from edge i-1 up to edge i, and the last bin from the last edge up.
+  pub edges: Vec<f64>,
+  /// The number of cells in each bin, one more than there are edges.
+  pub counts: Vec<usize>,
+  /// The number of cells whose relative difference is infinite or
+  /// undefined, such as a zero against a non-zero value.
+  pub undefined: usize,
+}
+
+impl Histogram {
+  /// An empty histogram with these edges, which must be ascending.
+  pub fn new(edges: &[f64]) -> Self {
+    Self {
+      edges: edges.to_vec(),
+      counts: vec![0; edges.len() + 1],
+      undefined: 0,
+    }
+  }
+
+  /// Counts a cell, by its relative difference in percent.
+  pub fn add(&mut self, percent: f64) {
+    if percent.is_finite() {
+      let bin = self.edges.partition_point(|&edge| edge <= percent);
+      self.counts[bin] += 1;
+    } else {
+      self.undefined += 1;
+    }
+  }
+
+  /// Adds the counts of a histogram with the same edges.
+  pub fn merge(&mut self, other: &Self) {
+    for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+      *count += other;
+    }
+    self.undefined += other.undefined;
+  }
+
+  /// The number of cells counted.
+  pub fn total(&self) -> usize {
+    self.counts.iter().sum::<usize>() + self.undefined
+  }
+
+  /// The label of each bin, e.g. "0.01-0.1%", then "undefined".
+  pub fn labels(&self) -> Vec<String> {
+    let edge = |e: f64| format!("{e}");
+    let n = self.edges.len();
+    let mut labels: Vec<String> = (0..=n)
+      .map(|bin| match bin {
+        0 if n == 0 => "all".to_string(),
+        0 => format!("<{}%", edge(self.edges[0])),
+        _ if bin == n => format!(">={}%", edge(self.edges[n - 1])),
+        _ => {
+          format!("{}-{}%", edge(self.edges[bin - 1]), edge(self.edges[bin]))
+        }
+      })
+      .collect();
+    labels.push("undefined".to_string());
+    labels
+  }
+}
@@ -0,0 +1,377 @@
+//! A history of results in an SQLite database, for --sqlite and the query
+//! subcommand.
+//!
+//! Each run appends a row to `runs`, one to `pairs` for each pair of files it
+//! compares, and one to `column_results` for each float column of the pair:
+//!
+//! - `runs(id, started_at, command_line, passed)`, where `started_at` is in
+//!   Unix seconds and `passed` stays NULL until the run is over;
+//! - `pairs(id, run_id, csv1, csv2, passed, rows, max_ratio_percent,
+//!   max_diff)`, where `max_ratio_percent` is the worst relative difference
+//!   as the run's --rel-mode measured it;
+//! - `column_results(pair_id, col, name, compared, worst_rel_percent,
+//!   worst_diff, digits)`, where `col` is 1-based, `name` is the header cell
+//!   in header mode, and `worst_rel_percent` is |a-b|/max(|a|,|b|) whatever
+//!   the mode.
+//!
+//! The schema's version is kept in `PRAGMA user_version`, and older databases
+//! are brought up to date on opening. Writes go in immediate transactions
+//! under a generous busy timeout, so runs sharing a database take turns.
+//!
+//! Without the `sqlite` feature, nothing is compiled in and every entry point
+//! fails.
+
+use std::path::Path;
+
+use csv::StringRecord;
+use f06csvdiff::DiffReport;
+
+/// The canned queries of the query subcommand.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Query {
+  /// The worst column of each run, oldest first.
+  WorstColumns,
+  /// How many runs failed each pair, most failed first.
+  Failures,
+  /// Every column result over --above percent, newest first.
+  Exceeded,
+}
+
+impl std::str::FromStr for Query {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "worst-columns" => Ok(Query::WorstColumns),
+      "failures" => Ok(Query::Failures),
+      "exceeded" => Ok(Query::Exceeded),
+      _ => Err(format!(
+        "Invalid query: {s}. Must be worst-columns, failures, or exceeded"
+      )),
+    }
+  }
+}
+
+/// What narrows down a query.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub(crate) struct Filter {
+  /// Only this column, by header name or 1-based number.
+  pub column: Option<String>,
+  /// Only relative differences over this, in percent (for exceeded).
+  pub above: f64,
+  /// At most this many rows.
+  pub limit: Option<u32>,
+}
+
+/// A query's result: its column headers and its rows.
+pub(crate) type Table = (Vec<String>, Vec<Vec<String>>);
+
+/// An open history, recording one run.
+pub(crate) struct History {
+  #[cfg(feature = "sqlite")]
+  db: sqlite::Recorder,
+}
+
+impl History {
+  /// Opens the database, creating or migrating its schema, and starts a run.
+  pub(crate) fn open(path: &Path, command_line: &str) -> Result<Self, String> {
+    #[cfg(feature = "sqlite")]
+    {
+      sqlite::Recorder::open(path, command_line)
+        .map(|db| History { db })
+        .map_err(|e| format!("{}: {e}", path.display()))
+    }
+    #[cfg(not(feature = "sqlite"))]
+    {
+      let _ = (path, command_line);
+      Err(UNSUPPORTED.to_string())
+    }
+  }
+
+  /// Records the comparison of a pair of files, with csv1's header in header
+  /// mode.
+  pub(crate) fn record(
+    &mut self,
+    (csv1, csv2): (&str, &str),
+    report: &DiffReport,
+    header: Option<&StringRecord>,
+  ) -> Result<(), String> {
+    #[cfg(feature = "sqlite")]
+    {
+      self
+        .db
+        .record((csv1, csv2), report, header)
+        .map_err(|e| e.to_string())
+    }
+    #[cfg(not(feature = "sqlite"))]
+    {
+      let _ = (csv1, csv2, report, header);
+      Err(UNSUPPORTED.to_string())
+    }
+  }
+
+  /// Records the verdict of the whole run.
+  pub(crate) fn finish(&mut self, passed: bool) -> Result<(), String> {
+    #[cfg(feature = "sqlite")]
+    {
+      self.db.finish(passed).map_err(|e| e.to_string())
+    }
+    #[cfg(not(feature = "sqlite"))]
+    {
+      let _ = passed;
+      Err(UNSUPPORTED.to_string())
+    }
+  }
+}
+
+/// Runs a canned query against a database.
+pub(crate) fn query(
+  path: &Path,
+  query: Query,
+  filter: &Filter,
+) -> Result<Table, String> {
+  #[cfg(feature = "sqlite")]
+  {
+    sqlite::query(path, query, filter)
+      .map_err(|e| format!("{}: {e}", path.display()))
+  }
+  #[cfg(not(feature = "sqlite"))]
+  {
+    let _ = (path, query, filter);
+    Err(UNSUPPORTED.to_string())
+  }
+}
+
+/// Why nothing works without the feature.
+#[cfg(not(feature = "sqlite"))]
+const UNSUPPORTED: &str = "--sqlite and query need the sqlite feature";
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+  use std::path::Path;
+  use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+  use csv::StringRecord;
+  use f06csvdiff::DiffReport;
+  use rusqlite::types::ValueRef;
+  use rusqlite::{Connection, TransactionBehavior, params};
+
+  use super::{Filter, Query, Table};
+
+  /// How long to wait for another run to finish writing.
+  const BUSY_TIMEOUT: Duration = Duration::from_secs(60);
+
+  /// The schema changes, in order: a database of version N has had the first
+  /// N applied. Only ever append to this.
+  const MIGRATIONS: &[&str] = &["
+    CREATE TABLE runs (
+      id INTEGER PRIMARY KEY,
+      started_at INTEGER NOT NULL,
+      command_line TEXT NOT NULL,
+      passed INTEGER
+    );
+    CREATE TABLE pairs (
+      id INTEGER PRIMARY KEY,
+      run_id INTEGER NOT NULL REFERENCES runs(id),
+      csv1 TEXT NOT NULL,
+      csv2 TEXT NOT NULL,
+      passed INTEGER NOT NULL,
+      rows INTEGER NOT NULL,
+      max_ratio_percent REAL NOT NULL,
+      max_diff REAL NOT NULL
+    );
+    CREATE INDEX pairs_by_run ON pairs(run_id);
+    CREATE TABLE column_results (
+      pair_id INTEGER NOT NULL REFERENCES pairs(id),
+      col INTEGER NOT NULL,
+      name TEXT,
+      compared INTEGER NOT NULL,
+      worst_rel_percent REAL NOT NULL,
+      worst_diff REAL NOT NULL,
+      digits REAL,
+      PRIMARY KEY (pair_id, col)
+    );
+  "];
+
+  /// Opens a database, bringing its schema up to date.
+  fn connect(path: &Path) -> rusqlite::Result<Connection> {
+    let mut conn = Connection::open(path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    // another run may be migrating too, so look at the version under the lock
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version: usize =
+      tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for migration in MIGRATIONS.iter().skip(version) {
+      tx.execute_batch(migration)?;
+    }
+    if version < MIGRATIONS.len() {
+      tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+    }
+    tx.commit()?;
+    Ok(conn)
+  }
+
+  /// A database recording one run.
+  pub(super) struct Recorder {
+    conn: Connection,
+    run_id: i64,
+  }
+
+  impl Recorder {
+    /// Opens the database and starts a run.
+    pub(super) fn open(
+      path: &Path,
+      command_line: &str,
+    ) -> rusqlite::Result<Self> {
+      let conn = connect(path)?;
+      let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+      conn.execute(
+        "INSERT INTO runs (started_at, command_line) VALUES (?1, ?2)",
+        params![started_at, command_line],
+      )?;
+      let run_id = conn.last_insert_rowid();
+      Ok(Recorder { conn, run_id })
+    }
+
+    /// Records a pair and its columns, all at once.
+    pub(super) fn record(
+      &mut self,
+      (csv1, csv2): (&str, &str),
+      report: &DiffReport,
+      header: Option<&StringRecord>,
+    ) -> rusqlite::Result<()> {
+      let tx = self
+        .conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)?;
+      tx.execute(
+        "INSERT INTO pairs (run_id, csv1, csv2, passed, rows, \
+         max_ratio_percent, max_diff) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+          self.run_id,
+          csv1,
+          csv2,
+          report.passed(),
+          report.rows as i64,
+          report.ratio_percent(),
+          report.max_diff.value,
+        ],
+      )?;
+      let pair_id = tx.last_insert_rowid();
+      {
+        let mut insert = tx.prepare(
+          "INSERT INTO column_results (pair_id, col, name, compared, \
+           worst_rel_percent, worst_diff, digits) \
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for column in &report.columns {
+          insert.execute(params![
+            pair_id,
+            column.col as i64,
+            header.and_then(|h| h.get(column.col - 1)),
+            column.compared as i64,
+            column.worst_rel * 100.0,
+            column.worst_diff,
+            column.digits_of_agreement(),
+          ])?;
+        }
+      }
+      tx.commit()
+    }
+
+    /// Records the run's verdict.
+    pub(super) fn finish(&mut self, passed: bool) -> rusqlite::Result<()> {
+      self.conn.execute(
+        "UPDATE runs SET passed = ?1 WHERE id = ?2",
+        params![passed, self.run_id],
+      )?;
+      Ok(())
+    }
+  }
+
+  /// The SQL of a canned query. Its parameters are the column filter (for
+  /// all but failures), the percentage (for exceeded) and the limit, in that
+  /// order; the column and the limit may be NULL.
+  fn sql(query: Query) -> &'static str {
+    match query {
+      // SQLite takes the bare columns from the row that gives the MAX
+      Query::WorstColumns => {
+        "SELECT datetime(r.started_at, 'unixepoch') AS time, p.csv1, p.csv2,
+                c.col, c.name, MAX(c.worst_rel_percent) AS worst_rel_percent,
+                c.worst_diff
+         FROM runs r
+         JOIN pairs p ON p.run_id = r.id
+         JOIN column_results c ON c.pair_id = p.id
+         WHERE ?1 IS NULL OR c.name = ?1 OR c.col = ?1
+         GROUP BY r.id
+         ORDER BY r.started_at, r.id
+         LIMIT coalesce(?2, -1)"
+      }
+      Query::Failures => {
+        "SELECT p.csv1, p.csv2, count(*) AS runs,
+                sum(NOT p.passed) AS failures,
+                datetime(max(CASE WHEN p.passed THEN NULL
+                                  ELSE r.started_at END),
+                         'unixepoch') AS last_failure
+         FROM pairs p
+         JOIN runs r ON r.id = p.run_id
+         GROUP BY p.csv1, p.csv2
+         ORDER BY failures DESC, p.csv1, p.csv2
+         LIMIT coalesce(?1, -1)"
+      }
+      Query::Exceeded => {
+        "SELECT datetime(r.started_at, 'unixepoch') AS time, p.csv1, p.csv2,
+                c.col, c.name, c.worst_rel_percent, c.worst_diff
+         FROM runs r
+         JOIN pairs p ON p.run_id = r.id
+         JOIN column_results c ON c.pair_id = p.id
+         WHERE (?1 IS NULL OR c.name = ?1 OR c.col = ?1)
+           AND c.worst_rel_percent > ?2
+         ORDER BY r.started_at DESC, r.id DESC, p.id, c.col
+         LIMIT coalesce(?3, -1)"
+      }
+    }
+  }
+
+  /// Runs a canned query, rendering every value as text.
+  pub(super) fn query(
+    path: &Path,
+    query: Query,
+    filter: &Filter,
+  ) -> rusqlite::Result<Table> {
+    let conn = connect(path)?;
+    let mut statement = conn.prepare(sql(query))?;
+    let headers: Vec<String> = statement
+      .column_names()
+      .into_iter()
+      .map(String::from)
+      .collect();
+    let width = headers.len();
+    let (column, limit) = (&filter.column, filter.limit);
+    let params = match query {
+      Query::WorstColumns => params![column, limit].to_vec(),
+      Query::Failures => params![limit].to_vec(),
+      Query::Exceeded => params![column, filter.above, limit].to_vec(),
+    };
+    let rows = statement
+      .query_map(params.as_slice(), |row| {
+        (0..width).map(|i| row.get_ref(i).map(text)).collect()
+      })?
+      .collect::<rusqlite::Result<_>>()?;
+    Ok((headers, rows))
+  }
+
+  /// A value as the query subcommand prints it.
+  fn text(value: ValueRef) -> String {
+    match value {
+      ValueRef::Null => "-".to_string(),
+      ValueRef::Integer(i) => i.to_string(),
+      ValueRef::Real(r) => format!("{r:.4E}"),
+      ValueRef::Text(t) | ValueRef::Blob(t) => {
+        String::from_utf8_lossy(t).into_owned()
+      }
+    }
+  }
+}
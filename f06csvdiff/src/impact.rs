@@ -0,0 +1,128 @@
+//! Impact classes of the compared cells, by their columns' engineering
+//! significance: a difference between two values both below it, such as a
+//! stress in a lightly loaded region, is low-impact, and any other,
+//! including any in a column without one, is high-impact. Each class's
+//! violations and worst cells are counted apart, and a comparison may be
+//! judged by its high-impact cells alone.
+
+use crate::compare::{Extremum, FailingCells, RelMode};
+
+/// The impact class of a compared cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Impact {
+  /// Both values are below their column's significance.
+  Low,
+  /// Either value is at least its column's significance, or the column
+  /// has none.
+  High,
+}
+
+impl Impact {
+  /// The class of a cell with these values, in a column of this
+  /// significance, if it has one.
+  pub fn of((a, b): (f64, f64), significance: Option<f64>) -> Self {
+    match significance {
+      Some(s) if a.abs() < s && b.abs() < s => Impact::Low,
+      _ => Impact::High,
+    }
+  }
+
+  /// The name of the class in reports.
+  pub fn name(self) -> &'static str {
+    match self {
+      Impact::Low => "LOW-IMPACT",
+      Impact::High => "HIGH-IMPACT",
+    }
+  }
+}
+
+/// What was seen within the cells of one impact class.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImpactClass {
+  /// How many cells were compared.
+  pub compared: usize,
+  /// How many exceed each tolerance on their own.
+  pub failing_cells: FailingCells,
+  /// The worst relative difference, as the report's `max_ratio` is
+  /// measured.
+  pub max_ratio: Extremum,
+  /// The worst absolute difference.
+  pub max_diff: Extremum,
+}
+
+impl ImpactClass {
+  /// A class that hasn't seen any cells yet.
+  fn new(rel_mode: RelMode) -> Self {
+    Self {
+      compared: 0,
+      failing_cells: FailingCells::default(),
+      max_ratio: Extremum::new(rel_mode.identical()),
+      max_diff: Extremum::new(0.0),
+    }
+  }
+
+  /// Accounts for a compared cell of the class, at a physical line and
+  /// (1-based) column: its relative and absolute differences, its values
+  /// and the tolerances it fails on its own.
+  pub(crate) fn add(
+    &mut self,
+    (ratio, diff): (f64, f64),
+    vals: (f64, f64),
+    (line, col): (usize, usize),
+    failing: FailingCells,
+  ) {
+    self.compared += 1;
+    self.failing_cells.add(failing);
+    self.max_ratio.raise(ratio, vals, line, col);
+    self.max_diff.raise(diff, vals, line, col);
+  }
+
+  /// Takes in the cells of the same class of other columns or rows.
+  fn merge(&mut self, other: &Self) {
+    self.compared += other.compared;
+    self.failing_cells.add(other.failing_cells);
+    self.max_ratio.merge(other.max_ratio);
+    self.max_diff.merge(other.max_diff);
+  }
+}
+
+/// The compared cells of a comparison, by impact class.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImpactReport {
+  /// The low-impact cells.
+  pub low: ImpactClass,
+  /// The high-impact cells.
+  pub high: ImpactClass,
+}
+
+impl ImpactReport {
+  /// A report that hasn't seen any cells yet.
+  pub(crate) fn new(rel_mode: RelMode) -> Self {
+    Self {
+      low: ImpactClass::new(rel_mode),
+      high: ImpactClass::new(rel_mode),
+    }
+  }
+
+  /// A class's cells.
+  pub fn class(&self, impact: Impact) -> &ImpactClass {
+    match impact {
+      Impact::Low => &self.low,
+      Impact::High => &self.high,
+    }
+  }
+
+  /// A class's cells, to account for another.
+  pub(crate) fn class_mut(&mut self, impact: Impact) -> &mut ImpactClass {
+    match impact {
+      Impact::Low => &mut self.low,
+      Impact::High => &mut self.high,
+    }
+  }
+
+  /// Takes in the cells of other columns or rows.
+  pub fn merge(&mut self, other: &Self) {
+    self.low.merge(&other.low);
+    self.high.merge(&other.high);
+  }
+}
@@ -0,0 +1,123 @@
+//! Resolution of the positional arguments into pairs of files.
+//!
+//! Shells on Unix expand globs before we ever see them, but cmd.exe doesn't,
+//! so we expand them ourselves: an argument that contains glob metacharacters
+//! (`*`, `?` or `[`) and doesn't name an existing file is expanded, and the
+//! matches are sorted so the result is deterministic.
+//!
+//! If both arguments expand to several files, each file of the first set is
+//! paired to the file of the second set with the same path relative to its
+//! pattern's literal directory prefix (so `a/*.csv` and `b/*.csv` pair
+//! `a/x.csv` to `b/x.csv`). Otherwise, every argument must resolve to exactly
+//! one file.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// What the positional arguments resolved to.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Inputs {
+  /// A single pair of files.
+  Single(String, String),
+  /// Several pairs of files, plus the files that had no counterpart.
+  Batch {
+    /// The pairs, sorted by their common relative path.
+    pairs: Vec<(String, String)>,
+    /// Files from the first argument without a counterpart.
+    unpaired1: Vec<String>,
+    /// Files from the second argument without a counterpart.
+    unpaired2: Vec<String>,
+  },
+}
+
+/// Whether an argument contains glob metacharacters.
+pub(crate) fn has_glob_chars(arg: &str) -> bool {
+  arg.contains(['*', '?', '['])
+}
+
+/// Expands an argument into a sorted list of paths. Arguments that don't look
+/// like globs, or that name an existing file, are returned as-is.
+pub(crate) fn expand(arg: &str) -> Result<Vec<PathBuf>, String> {
+  if !has_glob_chars(arg) || Path::new(arg).exists() {
+    return Ok(vec![PathBuf::from(arg)]);
+  }
+  let paths = glob::glob(arg).map_err(|e| format!("bad pattern {arg}: {e}"))?;
+  let mut matches = paths
+    .filter_map(Result::ok)
+    .filter(|p| p.is_file())
+    .collect::<Vec<_>>();
+  matches.sort();
+  if matches.is_empty() {
+    return Err(format!("pattern {arg} matched no files"));
+  }
+  Ok(matches)
+}
+
+/// The part of a pattern before its first path component containing glob
+/// metacharacters.
+fn literal_prefix(pattern: &str) -> PathBuf {
+  Path::new(pattern)
+    .components()
+    .take_while(|c| !has_glob_chars(&c.as_os_str().to_string_lossy()))
+    .collect()
+}
+
+/// Keys matches by their path relative to the pattern's literal prefix.
+fn keyed(
+  pattern: &str,
+  paths: Vec<PathBuf>,
+) -> Result<BTreeMap<PathBuf, String>, String> {
+  let prefix = literal_prefix(pattern);
+  let mut keyed = BTreeMap::new();
+  for path in paths {
+    let key = path.strip_prefix(&prefix).unwrap_or(&path).to_path_buf();
+    let display = path.to_string_lossy().to_string();
+    if let Some(other) = keyed.insert(key, display.clone()) {
+      return Err(format!(
+        "{pattern} has two files that pair alike: {other} and {display}"
+      ));
+    }
+  }
+  Ok(keyed)
+}
+
+/// Formats a list of ambiguous matches for an error message.
+fn ambiguous(arg: &str, matches: &[PathBuf]) -> String {
+  let list = matches
+    .iter()
+    .map(|p| format!("  {}", p.display()))
+    .collect::<Vec<_>>()
+    .join("\n");
+  format!("{arg} matches several files where one is required:\n{list}")
+}
+
+/// Resolves the two positional arguments.
+pub(crate) fn resolve(arg1: &str, arg2: &str) -> Result<Inputs, String> {
+  let m1 = expand(arg1)?;
+  let m2 = expand(arg2)?;
+  match (m1.len(), m2.len()) {
+    (1, 1) => Ok(Inputs::Single(
+      m1[0].to_string_lossy().to_string(),
+      m2[0].to_string_lossy().to_string(),
+    )),
+    (1, _) => Err(ambiguous(arg2, &m2)),
+    (_, 1) => Err(ambiguous(arg1, &m1)),
+    _ => {
+      let k1 = keyed(arg1, m1)?;
+      let mut k2 = keyed(arg2, m2)?;
+      let mut pairs = Vec::new();
+      let mut unpaired1 = Vec::new();
+      for (key, p1) in k1 {
+        match k2.remove(&key) {
+          Some(p2) => pairs.push((p1, p2)),
+          None => unpaired1.push(p1),
+        }
+      }
+      Ok(Inputs::Batch {
+        pairs,
+        unpaired1,
+        unpaired2: k2.into_values().collect(),
+      })
+    }
+  }
+}
@@ -0,0 +1,288 @@
+//! JSON output, for --format json and --group-format json: a line of JSON
+//! per pair of files, or per group.
+
+use crate::compare::{DiffReport, Dominance, Extremum};
+use crate::groups::GroupReport;
+use crate::verdict::{Gate, GateResult, Tolerances};
+use crate::waivers::Waiver;
+
+/// A pair's line of JSON, for --format json: its status, as its result
+/// line gives it, the counts of its cells, each enabled gate's verdict
+/// against `tolerances` with its worst cell, and the warning tier's against
+/// `warn_tolerances`, each column's digits of agreement, the lines and
+/// columns carrying more than `dominance` of a gate's excess, its groups,
+/// how its rows were sampled, the percentiles and histogram if asked for,
+/// and the waivers excusing it.
+pub fn report(
+  (name1, name2): (&str, &str),
+  report: &DiffReport,
+  (tolerances, warn_tolerances): (&Tolerances, &Tolerances),
+  (status, waivers): (&str, &[Waiver]),
+  dominance: f64,
+) -> String {
+  /// A gate's verdict, as JSON.
+  #[derive(serde::Serialize)]
+  struct GateJson {
+    gate: &'static str,
+    passed: bool,
+    limit: Option<f64>,
+    worst: Option<f64>,
+    margin: f64,
+    line: Option<usize>,
+    col: Option<usize>,
+    values: Option<(f64, f64)>,
+    failing_cells: Option<usize>,
+  }
+  /// A compared column's agreement, as JSON.
+  #[derive(serde::Serialize)]
+  struct ColumnJson {
+    col: usize,
+    compared: usize,
+    digits: Option<f64>,
+  }
+  /// A line or column carrying most of a gate's excess, as JSON.
+  #[derive(serde::Serialize)]
+  struct DominanceJson {
+    gate: &'static str,
+    line: Option<usize>,
+    col: Option<usize>,
+    share: f64,
+  }
+  /// The percentiles of the relative differences, as JSON.
+  #[derive(serde::Serialize)]
+  struct PercentilesJson {
+    exact: bool,
+    counted: usize,
+    skipped: usize,
+    undefined: usize,
+    values: Vec<(f64, Option<f64>)>,
+  }
+  /// The histogram of the relative differences, as JSON.
+  #[derive(serde::Serialize)]
+  struct HistogramJson<'a> {
+    edges: &'a [f64],
+    counts: &'a [usize],
+    undefined: usize,
+  }
+  /// A waiver excusing the pair's failure, as JSON.
+  #[derive(serde::Serialize)]
+  struct WaiverJson<'a> {
+    ticket: &'a str,
+    column: Option<usize>,
+    reason: &'a str,
+    expires: String,
+  }
+  /// A pair's comparison, as JSON.
+  #[derive(serde::Serialize)]
+  struct PairJson<'a> {
+    csv1: &'a str,
+    csv2: &'a str,
+    status: &'a str,
+    passed: bool,
+    warned: bool,
+    rows: usize,
+    sampled_rows: Option<usize>,
+    total_rows: Option<usize>,
+    compared: usize,
+    failing_cells: usize,
+    skipped: usize,
+    excluded: usize,
+    gates: Vec<GateJson>,
+    warnings: Vec<GateJson>,
+    columns: Vec<ColumnJson>,
+    dominance: Vec<DominanceJson>,
+    groups: Vec<GroupJson<'a>>,
+    percentiles: Option<PercentilesJson>,
+    histogram: Option<HistogramJson<'a>>,
+    waivers: Vec<WaiverJson<'a>>,
+  }
+  let located = |worst: &Extremum| {
+    let at = (worst.line != 0).then_some(worst);
+    (at.map(|w| w.line), at.map(|w| w.col), at.map(|w| w.vals))
+  };
+  let gate_json = |result: &GateResult, tolerances: &Tolerances| {
+    let gate = result.gate;
+    let (limit, worst, (line, col, values)) = match gate {
+      Gate::Ratio => (
+        tolerances.max_ratio.map(|mr| mr * 100.0),
+        Some(report.ratio_percent()),
+        located(&report.max_ratio),
+      ),
+      Gate::Diff => (
+        tolerances.max_diff,
+        Some(report.max_diff.value),
+        located(&report.max_diff),
+      ),
+      Gate::Digits => {
+        let worst = report.worst_digits();
+        let col = worst.map(|(_, col)| col);
+        (
+          tolerances.min_digits,
+          worst.map(|(d, _)| d),
+          (None, col, None),
+        )
+      }
+      // the worst difference as a multiple of what it's allowed
+      Gate::Allclose => (
+        Some(1.0),
+        Some(report.allclose.value),
+        located(&report.allclose),
+      ),
+    };
+    GateJson {
+      gate: gate.name(),
+      passed: result.passed,
+      limit,
+      worst,
+      margin: result.margin,
+      line,
+      col,
+      values,
+      failing_cells: Some(report.failing_cells.of(gate)),
+    }
+  };
+  let columns = report
+    .columns
+    .iter()
+    .map(|c| ColumnJson {
+      col: c.col,
+      compared: c.compared,
+      digits: c.digits_of_agreement(),
+    })
+    .collect();
+  let dominance = report
+    .dominance(dominance)
+    .into_iter()
+    .map(|d| match d {
+      Dominance::Line(gate, line, share) => DominanceJson {
+        gate: gate.name(),
+        line: Some(line),
+        col: None,
+        share,
+      },
+      Dominance::Column(gate, col, share) => DominanceJson {
+        gate: gate.name(),
+        line: None,
+        col: Some(col),
+        share,
+      },
+    })
+    .collect();
+  let percentiles = report.percentiles.as_ref().map(|p| PercentilesJson {
+    exact: p.exact(),
+    counted: p.counted(),
+    skipped: p.skipped,
+    undefined: p.undefined,
+    values: p.values(),
+  });
+  let histogram = report.histogram.as_ref().map(|h| HistogramJson {
+    edges: &h.edges,
+    counts: &h.counts,
+    undefined: h.undefined,
+  });
+  let waivers = waivers
+    .iter()
+    .map(|w| WaiverJson {
+      ticket: &w.ticket,
+      column: w.column,
+      reason: &w.reason,
+      expires: w.expiry().to_string(),
+    })
+    .collect();
+  let skipped = &report.skipped;
+  let json = PairJson {
+    csv1: name1,
+    csv2: name2,
+    status,
+    passed: report.passed(),
+    warned: report.warned(),
+    rows: report.rows,
+    sampled_rows: report.sampled_rows.map(|(rows, _)| rows),
+    total_rows: report.sampled_rows.map(|(_, total)| total),
+    compared: report.compared(),
+    failing_cells: report.failing_cells.cells,
+    skipped: skipped.zeros + skipped.below(),
+    excluded: report.excluded_cells().len(),
+    gates: report
+      .gates
+      .iter()
+      .map(|r| gate_json(r, tolerances))
+      .collect(),
+    // the cells exceeding a tolerance are counted for the failing tier only
+    warnings: report
+      .warnings
+      .iter()
+      .map(|r| GateJson {
+        failing_cells: None,
+        ..gate_json(r, warn_tolerances)
+      })
+      .collect(),
+    columns,
+    dominance,
+    groups: report.groups.iter().map(GroupJson::of).collect(),
+    percentiles,
+    histogram,
+    waivers,
+  };
+  // plain data with string keys always serializes; infinities become null
+  serde_json::to_string(&json).unwrap()
+}
+
+/// A group's comparison, as JSON.
+#[derive(serde::Serialize)]
+struct GroupJson<'a> {
+  group: &'a str,
+  rows: (usize, usize),
+  compared: usize,
+  max_ratio_pct: f64,
+  max_ratio_line: usize,
+  max_diff: f64,
+  max_diff_line: usize,
+  min_digits: Option<f64>,
+  passed: bool,
+  warned: bool,
+}
+
+impl<'a> GroupJson<'a> {
+  /// The JSON of a group's comparison.
+  fn of(group: &'a GroupReport) -> Self {
+    let report = &group.report;
+    GroupJson {
+      group: &group.value,
+      rows: group.rows,
+      compared: report.compared(),
+      max_ratio_pct: report.ratio_percent(),
+      max_ratio_line: report.max_ratio.line,
+      max_diff: report.max_diff.value,
+      max_diff_line: report.max_diff.line,
+      min_digits: report.worst_digits().map(|(digits, _)| digits),
+      passed: report.passed(),
+      warned: report.warned(),
+    }
+  }
+}
+
+/// A group's line of JSON, for --group-format json.
+pub fn group_line(
+  (csv1, csv2): (&str, &str),
+  col: &str,
+  group: &GroupReport,
+) -> String {
+  /// A group's comparison with its pair and column, as JSON.
+  #[derive(serde::Serialize)]
+  struct GroupLineJson<'a> {
+    csv1: &'a str,
+    csv2: &'a str,
+    column: &'a str,
+    #[serde(flatten)]
+    group: GroupJson<'a>,
+  }
+  let json = GroupLineJson {
+    csv1,
+    csv2,
+    column: col,
+    group: GroupJson::of(group),
+  };
+  // plain data with string keys always serializes
+  serde_json::to_string(&json).unwrap()
+}
@@ -0,0 +1,225 @@
+//! JUnit XML output, for CI dashboards.
+//!
+//! Every compared pair of files becomes a `<testsuite>` with one `<testcase>`
+//! per enabled gate, or with --sum-check-only none, and one per other check
+//! made, named as the check is, e.g. `sums` for the columns' sums with
+//! --sum-check or `columns` for --min-compared-columns, so that a suite fails
+//! exactly when its pair does; all suites of a run are wrapped in one
+//! `<testsuites>`.
+//! A failure a waiver excuses is reported as skipped, naming the waiver,
+//! and each waiver applied to a pair is a `waiver` property of its suite,
+//! with its ticket, reason and expiry.
+//! The gates of the warning tolerances are cases of their own, named
+//! `warn.ratio` and `warn.diff`, which pass, with any warning as their
+//! output, as are the impact classes, `impact.high` and `impact.low`, with
+//! their violations. A suite of sampled rows says so in its properties.
+
+use crate::compare::{DiffReport, SumCheck};
+use crate::describe::{check_failure, impact_classes, impact_line};
+use crate::impact::Impact;
+use crate::verdict::{Check, Gate, GateResult, Tolerances};
+
+/// The start of a JUnit document.
+pub const HEADER: &str =
+  "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>";
+
+/// The end of a JUnit document.
+pub const FOOTER: &str = "</testsuites>";
+
+/// Escapes text for use in XML attributes and character data.
+pub fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      _ => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+/// A single test case: a gate, and why it failed if it did.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestCase {
+  /// The case's name, e.g. "ratio" or "regression.diff".
+  pub name: String,
+  /// The failure message, if the gate failed.
+  pub failure: Option<String>,
+  /// The waivers excusing its failure, described, if any do.
+  pub waiver: Option<String>,
+  /// Why it warned, for a gate of the warning tolerances that did.
+  pub warning: Option<String>,
+}
+
+/// Describes why a gate failed, naming the worst value and where it is.
+pub fn failure_message(result: &GateResult, report: &DiffReport) -> String {
+  let worst = match result.gate {
+    Gate::Ratio => &report.max_ratio,
+    Gate::Diff => &report.max_diff,
+    Gate::Allclose => &report.allclose,
+    Gate::Digits => {
+      let (digits, col) = report.worst_digits().unwrap_or((0.0, 0));
+      return format!(
+        "{digits:.2} digits of agreement in column {col}, margin {}",
+        result.margin_text()
+      );
+    }
+  };
+  let value = match result.gate {
+    Gate::Ratio => format!("{:.2}%", report.ratio_percent()),
+    Gate::Allclose => format!("{:.2}x its tolerance", worst.value),
+    _ => format!("{:.2E}", worst.value),
+  };
+  format!(
+    "max {} {value} with values {:+.6E} and {:+.6E} at line {}, column {}, \
+     margin {}",
+    result.gate.name(),
+    worst.vals.0,
+    worst.vals.1,
+    worst.line,
+    worst.col,
+    result.margin_text()
+  )
+}
+
+/// Formats a test suite, with properties if it has any.
+pub fn testsuite(
+  name: &str,
+  properties: &[(&str, String)],
+  cases: &[TestCase],
+) -> String {
+  let failed = |c: &&TestCase| c.failure.is_some();
+  let failures = cases.iter().filter(failed).count();
+  let skipped = cases.iter().filter(failed).filter(|c| c.waiver.is_some());
+  let skipped = skipped.count();
+  let name = escape(name);
+  let mut xml = format!(
+    "  <testsuite name=\"{name}\" tests=\"{}\" failures=\"{}\" \
+     errors=\"0\" skipped=\"{skipped}\">\n",
+    cases.len(),
+    failures - skipped
+  );
+  if !properties.is_empty() {
+    xml.push_str("    <properties>\n");
+    for (key, value) in properties {
+      xml.push_str(&format!(
+        "      <property name=\"{}\" value=\"{}\"/>\n",
+        escape(key),
+        escape(value)
+      ));
+    }
+    xml.push_str("    </properties>\n");
+  }
+  for case in cases {
+    let case_name = escape(&case.name);
+    match (&case.failure, &case.waiver, &case.warning) {
+      (None, _, Some(warning)) => {
+        let warning = escape(&format!("warning: {warning}"));
+        xml.push_str(&format!(
+          "    <testcase classname=\"{name}\" name=\"{case_name}\">\n      \
+           <system-out>{warning}</system-out>\n    </testcase>\n"
+        ));
+      }
+      (None, _, None) => xml.push_str(&format!(
+        "    <testcase classname=\"{name}\" name=\"{case_name}\"/>\n"
+      )),
+      (Some(message), Some(waiver), _) => {
+        let message = escape(&format!("waived by {waiver}: {message}"));
+        xml.push_str(&format!(
+          "    <testcase classname=\"{name}\" name=\"{case_name}\">\n      \
+           <skipped message=\"{message}\"/>\n    </testcase>\n"
+        ));
+      }
+      (Some(message), None, _) => {
+        let message = escape(message);
+        xml.push_str(&format!(
+          "    <testcase classname=\"{name}\" name=\"{case_name}\">\n      \
+           <failure message=\"{message}\">{message}</failure>\n    \
+           </testcase>\n"
+        ));
+      }
+    }
+  }
+  xml.push_str("  </testsuite>");
+  xml
+}
+
+/// The JUnit test cases for a report: one per gate, prefixed by the profile
+/// name in profile mode, unless only the sums decide, and one per check
+/// made besides them, or failed anyway, which together fail exactly when
+/// the report does. Failures are excused by the waivers described, if any.
+pub fn cases(
+  profiles: &[(String, Tolerances)],
+  tolerances: &Tolerances,
+  (report, checks): (&DiffReport, &[Check]),
+  files: (&str, &str),
+  waiver: Option<&str>,
+) -> Vec<TestCase> {
+  let sets = if profiles.is_empty() {
+    vec![(None, tolerances)]
+  } else {
+    profiles
+      .iter()
+      .map(|(name, tol)| (Some(name), tol))
+      .collect()
+  };
+  let mut cases = Vec::new();
+  let sums_only = report.sum_check == SumCheck::Only;
+  for (profile, tol) in sets.into_iter().filter(|_| !sums_only) {
+    for result in tol.evaluate(report) {
+      let name = match profile {
+        Some(profile) => format!("{profile}.{}", result.gate.name()),
+        None => result.gate.name().to_string(),
+      };
+      let failure = (!result.passed).then(|| failure_message(&result, report));
+      cases.push(TestCase {
+        name,
+        failure,
+        waiver: waiver.map(String::from),
+        warning: None,
+      });
+    }
+  }
+  // the checks besides the gates, each failing the pair on its own
+  for &check in checks {
+    let failure = (!report.check_passed(check))
+      .then(|| check_failure(check, report, files));
+    cases.push(TestCase {
+      name: check.name().to_string(),
+      failure,
+      waiver: waiver.map(String::from),
+      warning: None,
+    });
+  }
+  // the impact classes, which never fail either, the gates judging them
+  if let Some(impact) = &report.impact_classes() {
+    for (class, cells) in impact_classes(impact) {
+      let violated = cells.failing_cells.cells > 0;
+      cases.push(TestCase {
+        name: match class {
+          Impact::High => "impact.high",
+          Impact::Low => "impact.low",
+        }
+        .to_string(),
+        failure: None,
+        waiver: None,
+        warning: violated.then(|| impact_line(class, cells, report)),
+      });
+    }
+  }
+  // the warning tier's gates, which never fail
+  for result in &report.warnings {
+    let raised = report.warnings_raised().any(|w| w.gate == result.gate);
+    cases.push(TestCase {
+      name: format!("warn.{}", result.gate.name()),
+      failure: None,
+      waiver: None,
+      warning: raised.then(|| failure_message(result, report)),
+    });
+  }
+  cases
+}
@@ -0,0 +1,236 @@
+//! Pairing rows by their key, such as an element ID, for files whose rows
+//! come in different orders or different sets. Rows are paired in csv1's
+//! order, and the keys themselves are reconciled first: those several rows
+//! of a file share, and those only one file has. Records keep their
+//! positions, so reports still give the lines they were read from.
+
+use std::collections::HashMap;
+
+use csv::{Position, StringRecord};
+use regex::Regex;
+
+use crate::compare::{looks_like_float, parse_float, physical_line};
+use crate::error::DiffError;
+
+/// What to do with a key several rows of a file share.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Duplicates {
+  /// Fail: the pairing of their rows would be a guess.
+  #[default]
+  Error,
+  /// Pair the first of them, leaving the rest out.
+  First,
+  /// Pair a row of their sums, such as element contributions split across
+  /// rows: cells that are floats in all of them are summed, and the first
+  /// row's other cells are kept.
+  Sum,
+}
+
+impl std::str::FromStr for Duplicates {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "error" => Ok(Duplicates::Error),
+      "first" => Ok(Duplicates::First),
+      "sum" => Ok(Duplicates::Sum),
+      _ => Err(format!(
+        "Invalid duplicate keys policy: {s}. Must be error, first, or sum"
+      )),
+    }
+  }
+}
+
+impl Duplicates {
+  /// The name of the policy, as --duplicate-keys takes it.
+  pub fn name(self) -> &'static str {
+    match self {
+      Duplicates::Error => "error",
+      Duplicates::First => "first",
+      Duplicates::Sum => "sum",
+    }
+  }
+}
+
+/// A key and the rows of each file that have it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyRows {
+  /// The key, as its first row gives it.
+  pub key: String,
+  /// The (0-based) rows of csv1 with it, in order.
+  pub rows1: Vec<usize>,
+  /// The (0-based) rows of csv2 with it, in order.
+  pub rows2: Vec<usize>,
+  /// The physical (1-based) lines of those rows of csv1.
+  pub lines1: Vec<usize>,
+  /// The physical lines of those rows of csv2.
+  pub lines2: Vec<usize>,
+}
+
+impl KeyRows {
+  /// Whether several rows of either file have it.
+  pub fn duplicated(&self) -> bool {
+    self.rows1.len() > 1 || self.rows2.len() > 1
+  }
+
+  /// How it reconciles: duplicated, only_in_1, only_in_2 or matched.
+  pub fn status(&self) -> &'static str {
+    if self.duplicated() {
+      "duplicated"
+    } else if self.rows2.is_empty() {
+      "only_in_1"
+    } else if self.rows1.is_empty() {
+      "only_in_2"
+    } else {
+      "matched"
+    }
+  }
+}
+
+/// The keys of two files, reconciled.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeyMatching {
+  /// The (0-based) key column of each file.
+  pub cols: (usize, usize),
+  /// Every key: csv1's in the order they first come, then those only csv2
+  /// has in theirs.
+  pub keys: Vec<KeyRows>,
+}
+
+impl KeyMatching {
+  /// The keys several rows of either file share.
+  pub fn duplicated(&self) -> impl Iterator<Item = &KeyRows> {
+    self.keys.iter().filter(|k| k.duplicated())
+  }
+
+  /// The keys only one file (1 or 2) has.
+  pub fn only_in(&self, file: usize) -> impl Iterator<Item = &KeyRows> {
+    self.keys.iter().filter(move |k| match file {
+      1 => k.rows2.is_empty(),
+      _ => k.rows1.is_empty(),
+    })
+  }
+
+  /// The physical lines of each file's rows whose key the other file
+  /// lacks, which are left unpaired.
+  pub fn unpaired(&self) -> (Vec<usize>, Vec<usize>) {
+    let lines = |file: usize| {
+      let mut lines: Vec<usize> = self
+        .only_in(file)
+        .flat_map(|k| if file == 1 { &k.lines1 } else { &k.lines2 })
+        .copied()
+        .collect();
+      lines.sort_unstable();
+      lines
+    };
+    (lines(1), lines(2))
+  }
+}
+
+/// A cell's key: numbers by value, so that 101 and 1.01E+02 are the same
+/// element, and other text trimmed.
+fn normalized(cell: &str) -> String {
+  let cell = cell.trim();
+  match parse_float(cell) {
+    Some(value) => value.to_string(),
+    None => cell.to_string(),
+  }
+}
+
+/// Reconciles the keys of two files, in a (0-based) column of each, giving
+/// records without a position their place in the file first. Fails with
+/// [`DiffError::KeyMissing`] if a row lacks the column.
+pub fn reconcile(
+  (file1, records1): (&str, &mut [StringRecord]),
+  (file2, records2): (&str, &mut [StringRecord]),
+  (col1, col2): (usize, usize),
+) -> Result<KeyMatching, DiffError> {
+  let mut matching = KeyMatching {
+    cols: (col1, col2),
+    ..Default::default()
+  };
+  let mut index: HashMap<String, usize> = HashMap::new();
+  let files = [(1, file1, records1, col1), (2, file2, records2, col2)];
+  for (n, file, records, col) in files {
+    for row in 0..records.len() {
+      let line = physical_line(records, row);
+      let record = &mut records[row];
+      if record.position().is_none() {
+        let mut position = Position::new();
+        position.set_line(line as u64);
+        record.set_position(Some(position));
+      }
+      let cell = record.get(col).ok_or_else(|| DiffError::KeyMissing {
+        file: file.to_string(),
+        line,
+        col: col + 1,
+      })?;
+      let key = *index.entry(normalized(cell)).or_insert_with(|| {
+        matching.keys.push(KeyRows {
+          key: cell.trim().to_string(),
+          ..Default::default()
+        });
+        matching.keys.len() - 1
+      });
+      let key = &mut matching.keys[key];
+      match n {
+        1 => (key.rows1.push(row), key.lines1.push(line)),
+        _ => (key.rows2.push(row), key.lines2.push(line)),
+      };
+    }
+  }
+  Ok(matching)
+}
+
+/// Pairs the rows of two files by their reconciled keys, in csv1's order,
+/// taking off the rows of keys only one file has. The rows of a duplicated
+/// key become one, as `duplicates` has it; with [`Duplicates::Error`], the
+/// first of them is taken too, so callers fail on duplicates beforehand.
+/// Sums are of the cells other than the key that look like floats, by
+/// `float_re`, in all the rows, written in full precision at the first
+/// row's line.
+pub fn pair(
+  (records1, records2): (&mut Vec<StringRecord>, &mut Vec<StringRecord>),
+  matching: &KeyMatching,
+  duplicates: Duplicates,
+  float_re: &Regex,
+) {
+  let merged = |records: &[StringRecord], rows: &[usize], key: usize| {
+    let first = records[rows[0]].clone();
+    if duplicates != Duplicates::Sum || rows.len() == 1 {
+      return first;
+    }
+    let mut record = StringRecord::new();
+    for (i, cell) in first.iter().enumerate() {
+      let value = |row: usize| {
+        let cell = records[row].get(i)?.trim();
+        parse_float(cell).filter(|_| looks_like_float(cell, float_re))
+      };
+      let sum = match i == key {
+        true => None,
+        false => rows
+          .iter()
+          .try_fold(0.0, |sum, &row| Some(sum + value(row)?)),
+      };
+      match sum {
+        Some(sum) => record.push_field(&format!("{sum:E}")),
+        None => record.push_field(cell),
+      }
+    }
+    record.set_position(first.position().cloned());
+    record
+  };
+  let paired: Vec<(StringRecord, StringRecord)> = matching
+    .keys
+    .iter()
+    .filter(|k| !k.rows1.is_empty() && !k.rows2.is_empty())
+    .map(|k| {
+      let (col1, col2) = matching.cols;
+      (
+        merged(records1, &k.rows1, col1),
+        merged(records2, &k.rows2, col2),
+      )
+    })
+    .collect();
+  (*records1, *records2) = paired.into_iter().unzip();
+}
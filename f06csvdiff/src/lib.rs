@@ -0,0 +1,436 @@
+//! The comparison engine behind f06csvdiff: diffs floating-point numbers at
+//! corresponding positions within two CSVs, and judges the differences
+//! against tolerances.
+//!
+//! ```no_run
+//! use f06csvdiff::{DiffConfig, Tolerances, compare_files};
+//!
+//! let config = DiffConfig {
+//!   tolerances: Tolerances {
+//!     max_ratio: Some(0.01),
+//!     ..Default::default()
+//!   },
+//!   ..Default::default()
+//! };
+//! let report = compare_files("a.csv", "b.csv", &config)?;
+//! println!("worst ratio at line {}", report.max_ratio.line);
+//! assert!(report.passed());
+//! # Ok::<(), f06csvdiff::DiffError>(())
+//! ```
+//!
+//! A report is written out as the command line writes it by the formats'
+//! modules, each taking a [`DiffReport`]: [`table`] for the aligned table,
+//! its Markdown and CSV rows, [`json`] and [`junit`].
+
+#![warn(missing_docs)]
+
+use std::fs::{File, Metadata};
+use std::io::{ErrorKind, Read};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use csv::{ReaderBuilder, StringRecord};
+use encoding::{Decoder, Encoding};
+use profile::Timed;
+use progress::{Counted, Progress};
+
+pub mod annotations;
+pub mod assertions;
+pub mod cell;
+pub mod checks;
+pub mod color;
+pub mod compare;
+pub mod complex;
+pub mod describe;
+pub mod effective;
+pub mod encoding;
+pub mod error;
+pub mod expected;
+pub mod extract;
+pub mod failures;
+pub mod groups;
+pub mod headerdiff;
+pub mod headers;
+pub mod histogram;
+pub mod impact;
+pub mod json;
+pub mod junit;
+pub mod keys;
+pub mod mac;
+pub mod markdown;
+pub mod matrix;
+pub mod nearest;
+pub mod normalize;
+pub mod overrides;
+pub mod percent;
+pub mod percentiles;
+pub mod profile;
+pub mod progress;
+pub mod reference;
+pub mod regression;
+pub mod scalars;
+pub mod shift;
+pub mod sort;
+pub mod table;
+pub mod transpose;
+pub mod vectors;
+pub mod verdict;
+pub mod waivers;
+
+pub use checks::Checks;
+pub use compare::{DiffConfig, DiffReport, ExtraRows, RelMode, SignFlip};
+pub use error::DiffError;
+pub use verdict::{Check, Gate, GateResult, Tolerances};
+
+/// Reads every record of a CSV. The file name is only used in errors.
+pub fn read_records<R: Read>(
+  reader: R,
+  delimiter: u8,
+  file: &str,
+) -> Result<Vec<StringRecord>, DiffError> {
+  read_csv(reader, delimiter, file, false)
+}
+
+/// Reads every record of a CSV, allowing records with different numbers of
+/// fields if `ragged`. Each record is read into the same buffer, which only
+/// grows for the longest, and kept as an exact copy of it, so that a record
+/// of many megabytes is neither regrown from scratch nor kept with twice the
+/// room it needs.
+fn read_csv<R: Read>(
+  reader: R,
+  delimiter: u8,
+  file: &str,
+  ragged: bool,
+) -> Result<Vec<StringRecord>, DiffError> {
+  let mut reader = ReaderBuilder::new()
+    .has_headers(false)
+    .delimiter(delimiter)
+    .flexible(ragged)
+    .from_reader(reader);
+  let mut buffer = StringRecord::new();
+  let mut records = Vec::new();
+  loop {
+    match reader.read_record(&mut buffer) {
+      Ok(true) => {}
+      Ok(false) => return Ok(records),
+      Err(e) => {
+        return Err(DiffError::Io {
+          file: file.to_string(),
+          source: e.into(),
+        });
+      }
+    }
+    let mut record =
+      StringRecord::with_capacity(buffer.as_slice().len(), buffer.len());
+    record.extend(buffer.iter());
+    record.set_position(buffer.position().cloned());
+    records.push(record);
+  }
+}
+
+/// Fails with [`DiffError::FieldCountChange`] unless every record has as
+/// many fields as the first, as [`read_records`] requires, e.g. to check
+/// what's left of a file read with [`ReadOptions::ragged`].
+pub fn check_field_counts(
+  records: &[StringRecord],
+  file: &str,
+) -> Result<(), DiffError> {
+  let Some(first) = records.first() else {
+    return Ok(());
+  };
+  match records.iter().position(|r| r.len() != first.len()) {
+    Some(row) => Err(DiffError::FieldCountChange {
+      file: file.to_string(),
+      line: compare::physical_line(records, row),
+      fields: records[row].len(),
+      expected: first.len(),
+    }),
+    None => Ok(()),
+  }
+}
+
+/// Takes the empty trailing fields off every record, e.g. of an export
+/// ending every line with a delimiter. A field of whitespace alone counts
+/// as empty.
+pub fn strip_trailing_empty(records: &mut [StringRecord]) {
+  for record in records {
+    let empty = record.iter().rev().take_while(|f| f.trim().is_empty());
+    let len = record.len() - empty.count();
+    record.truncate(len);
+  }
+}
+
+/// Pads every record of both files with empty fields to the widest record
+/// of either, taking absent trailing fields for empty ones, as an export
+/// leaving out empty trailing fields writes them. A field in a column the
+/// other file has in none of its records can't have been left out, so
+/// fails with [`DiffError::ColumnCountMismatch`] at the first record with
+/// one that isn't empty.
+pub fn pad_fields(
+  records1: &mut [StringRecord],
+  records2: &mut [StringRecord],
+) -> Result<(), DiffError> {
+  let widest = |records: &[StringRecord]| {
+    records.iter().map(StringRecord::len).max().unwrap_or(0)
+  };
+  let (width1, width2) = (widest(records1), widest(records2));
+  let (wider, narrower) = match width1 >= width2 {
+    true => (&*records1, width2),
+    false => (&*records2, width1),
+  };
+  let extra =
+    |r: &StringRecord| r.iter().skip(narrower).any(|f| !f.trim().is_empty());
+  if let Some(row) = wider.iter().position(extra) {
+    let line = compare::physical_line(wider, row);
+    let fields = wider[row].len();
+    return Err(match width1 >= width2 {
+      true => DiffError::ColumnCountMismatch {
+        line,
+        cols1: fields,
+        cols2: width2,
+      },
+      false => DiffError::ColumnCountMismatch {
+        line,
+        cols1: width1,
+        cols2: fields,
+      },
+    });
+  }
+  let width = width1.max(width2);
+  for record in records1.iter_mut().chain(records2.iter_mut()) {
+    for _ in record.len()..width {
+      record.push_field("");
+    }
+  }
+  Ok(())
+}
+
+/// Trims ASCII whitespace off every cell, then the quotes around what's
+/// left and the whitespace within them, e.g. for a fixed-width table saved
+/// as CSV with cells like ` "1.2345E-03" `, which the CSV reader takes
+/// quotes and all. A cell of whitespace alone is left empty.
+pub fn trim_cells(records: &mut [StringRecord]) {
+  for record in records {
+    if record
+      .iter()
+      .all(|cell| trim_cell(cell).len() == cell.len())
+    {
+      continue;
+    }
+    let mut trimmed =
+      StringRecord::with_capacity(record.as_slice().len(), record.len());
+    trimmed.extend(record.iter().map(trim_cell));
+    trimmed.set_position(record.position().cloned());
+    *record = trimmed;
+  }
+}
+
+/// A cell trimmed as [`trim_cells`] trims it.
+fn trim_cell(cell: &str) -> &str {
+  let cell = cell.trim_ascii();
+  match cell.strip_prefix('"').and_then(|c| c.strip_suffix('"')) {
+    Some(quoted) => quoted.trim_ascii(),
+    None => cell,
+  }
+}
+
+/// How to read a CSV file. The default reads it as the command line does,
+/// trimmed, without a lock, and decoded as [`Encoding::Auto`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadOptions {
+  /// Hold a shared lock on the file while reading it, as
+  /// [`read_file_locked`] does.
+  pub lock: bool,
+  /// Allow records with different numbers of fields, e.g. a title block
+  /// ahead of the table, for [`check_field_counts`] to check the table
+  /// alone once it's taken off.
+  pub ragged: bool,
+  /// How the file's bytes are decoded.
+  pub encoding: Encoding,
+  /// Trim every cell as [`trim_cells`] does, unless the command line's
+  /// --no-trim would keep them as they are.
+  pub trim: bool,
+}
+
+impl Default for ReadOptions {
+  fn default() -> Self {
+    Self {
+      lock: false,
+      ragged: false,
+      encoding: Encoding::default(),
+      trim: true,
+    }
+  }
+}
+
+/// What a file looked like when it was opened.
+#[derive(PartialEq)]
+struct Snapshot {
+  len: u64,
+  modified: Option<SystemTime>,
+}
+
+impl Snapshot {
+  fn of(metadata: &Metadata) -> Self {
+    Self {
+      len: metadata.len(),
+      modified: metadata.modified().ok(),
+    }
+  }
+}
+
+/// Reads every record of a CSV file, decoded as [`Encoding::Auto`] and
+/// trimmed, as [`ReadOptions::default`] reads it, failing with
+/// [`DiffError::InputChanged`] if its size or modification time changed by
+/// the time the read completed.
+pub fn read_file(
+  path: &str,
+  delimiter: u8,
+) -> Result<Vec<StringRecord>, DiffError> {
+  read_file_with(path, delimiter, ReadOptions::default())
+}
+
+/// Like [`read_file`], but holds a shared lock on the file while reading it,
+/// so that writers which lock it exclusively are waited for. The lock is
+/// advisory, and skipped where the platform doesn't support it.
+pub fn read_file_locked(
+  path: &str,
+  delimiter: u8,
+) -> Result<Vec<StringRecord>, DiffError> {
+  let options = ReadOptions {
+    lock: true,
+    ..Default::default()
+  };
+  read_file_with(path, delimiter, options)
+}
+
+/// Like [`read_file`], with options.
+pub fn read_file_with(
+  path: &str,
+  delimiter: u8,
+  options: ReadOptions,
+) -> Result<Vec<StringRecord>, DiffError> {
+  read_file_timed(path, delimiter, options).map(|(records, _)| records)
+}
+
+/// Like [`read_file_with`], also returning the time spent reading and
+/// decoding the file's bytes, as opposed to parsing them.
+pub fn read_file_timed(
+  path: &str,
+  delimiter: u8,
+  options: ReadOptions,
+) -> Result<(Vec<StringRecord>, Duration), DiffError> {
+  read_watched(path, delimiter, options, |file| file)
+}
+
+/// Like [`read_file_timed`], also counting the bytes read into `progress`
+/// as the file is read.
+pub fn read_file_counted(
+  path: &str,
+  delimiter: u8,
+  options: ReadOptions,
+  progress: &Arc<Progress>,
+) -> Result<(Vec<StringRecord>, Duration), DiffError> {
+  read_watched(path, delimiter, options, |file| {
+    Counted::new(file, Arc::clone(progress))
+  })
+}
+
+/// Reads a CSV file through `wrap`, comparing its size and modification time
+/// before and after. A change wins over any error reading it, since a file
+/// caught mid-write is likely malformed too.
+fn read_watched<R: Read>(
+  path: &str,
+  delimiter: u8,
+  options: ReadOptions,
+  wrap: impl FnOnce(File) -> R,
+) -> Result<(Vec<StringRecord>, Duration), DiffError> {
+  let io = |source| DiffError::Io {
+    file: path.to_string(),
+    source,
+  };
+  let file = File::open(path).map_err(io)?;
+  if options.lock {
+    match file.lock_shared() {
+      Err(e) if e.kind() != ErrorKind::Unsupported => return Err(io(e)),
+      _ => {}
+    }
+  }
+  let before = Snapshot::of(&file.metadata().map_err(io)?);
+  let (records, spent) = read_decoded(wrap(file), delimiter, path, options);
+  let after = Snapshot::of(&std::fs::metadata(path).map_err(io)?);
+  if before != after {
+    return Err(DiffError::InputChanged {
+      file: path.to_string(),
+    });
+  }
+  Ok((records?, spent))
+}
+
+/// Reads every record of a CSV decoded and trimmed as `options` say, also
+/// returning the time spent decoding it, whether or not the read succeeded.
+fn read_decoded<R: Read>(
+  reader: R,
+  delimiter: u8,
+  file: &str,
+  options: ReadOptions,
+) -> (Result<Vec<StringRecord>, DiffError>, Duration) {
+  let mut decoded = Timed::new(Decoder::new(reader, options.encoding));
+  let records = read_csv(&mut decoded, delimiter, file, options.ragged).map(
+    |mut records| {
+      if options.trim {
+        trim_cells(&mut records);
+      }
+      records
+    },
+  );
+  (records, decoded.spent())
+}
+
+/// Compares two CSVs read from anywhere, decoded and trimmed as
+/// [`read_file`] decodes and trims them; errors call them csv1 and csv2.
+pub fn compare_readers<R1: Read, R2: Read>(
+  reader1: R1,
+  reader2: R2,
+  config: &DiffConfig,
+) -> Result<DiffReport, DiffError> {
+  let options = ReadOptions::default();
+  let (records1, _) = read_decoded(reader1, config.delimiter, "csv1", options);
+  let (records2, _) = read_decoded(reader2, config.delimiter, "csv2", options);
+  compare::compare(&records1?, &records2?, config, ("csv1", "csv2"))
+}
+
+/// Compares two CSV files, read as [`read_file`] reads them, so that the
+/// verdict is the command line's on the same files.
+pub fn compare_files(
+  path1: &str,
+  path2: &str,
+  config: &DiffConfig,
+) -> Result<DiffReport, DiffError> {
+  compare_files_checked(path1, path2, config, &Checks::default())
+}
+
+/// Like [`compare_files`], then makes `checks` of the files, completing the
+/// report as the command line does. Columns `checks` overrides must already
+/// be left out of `config`, as [`overrides::without`] leaves them out.
+pub fn compare_files_checked(
+  path1: &str,
+  path2: &str,
+  config: &DiffConfig,
+  checks: &Checks,
+) -> Result<DiffReport, DiffError> {
+  let records1 = read_file(path1, config.delimiter)?;
+  let records2 = read_file(path2, config.delimiter)?;
+  let mut report =
+    compare::compare(&records1, &records2, config, (path1, path2))?;
+  let inputs = checks::Inputs {
+    records: (&records1, &records2),
+    headers: None,
+    files: (path1, path2),
+  };
+  checks::complete(&mut report, inputs, config, checks)?;
+  Ok(report)
+}
+
+#[cfg(test)]
+mod tests;
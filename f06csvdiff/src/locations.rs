@@ -0,0 +1,183 @@
+//! Locations of the worst cells as compilers print them, `path:line:col:
+//! message`, for --locations-style gcc: terminals make them links, and
+//! Vim's quickfix and VS Code's problem matchers read them as they are.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+
+use csv::StringRecord;
+
+use f06csvdiff::compare::{DiffConfig, DiffReport, Direction, physical_line};
+use f06csvdiff::failures::FailingCell;
+use f06csvdiff::verdict::{self, Tolerances};
+
+/// Where locations point: which file, and how columns are counted.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Locating<'a> {
+  /// The file's path, as given.
+  pub(crate) path: &'a str,
+  /// The file: 1 or 2.
+  pub(crate) file: usize,
+  /// Whether columns are the (1-based) byte offsets of the fields' starts
+  /// in their lines, rather than the fields' (1-based) numbers.
+  pub(crate) bytes: bool,
+}
+
+/// A location line for the worst cell of each gate that has one: the ratio
+/// and the absolute difference gates', the latter's scaled row by row if
+/// the tolerance is. Cells without a row in the file located in are left
+/// out.
+pub(crate) fn gate_locations(
+  at: Locating,
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  report: &DiffReport,
+  opts: &DiffConfig,
+) -> Vec<String> {
+  let tolerances = &opts.tolerances;
+  let mut lines = Vec::new();
+  let worst_ratio = &report.max_ratio;
+  if let Some(mr) = ratio_limit(tolerances, worst_ratio.vals)
+    && worst_ratio.line != 0
+  {
+    let percent = report.ratio_percent();
+    let verb = match verdict::exceeds_ratio(percent, mr) {
+      true => "exceeds",
+      false => "within",
+    };
+    let (v1, v2) = worst_ratio.vals;
+    let message = format!(
+      "ratio {percent:.2}% {verb} {:.2}% ({v1:.4E} vs {v2:.4E})",
+      mr * 100.0
+    );
+    lines.push((worst_ratio.line, worst_ratio.col, message));
+  }
+  let (worst_diff, diff, scale) = match &report.scaled_diff {
+    Some(scaled) => (&scaled.worst, scaled.diff, scaled.scale),
+    None => (&report.max_diff, report.max_diff.value, 1.0),
+  };
+  let direction = Direction::of(worst_diff.vals.0, worst_diff.vals.1);
+  if let Some(md) = tolerances.diff_limit(direction)
+    && worst_diff.line != 0
+  {
+    let allowed = md * scale;
+    let verb = if diff > allowed { "exceeds" } else { "within" };
+    let (v1, v2) = worst_diff.vals;
+    let message = format!(
+      "abs diff {diff:.2E} {verb} {allowed:.2E} ({v1:.4E} vs {v2:.4E})"
+    );
+    lines.push((worst_diff.line, worst_diff.col, message));
+  }
+  lines
+    .into_iter()
+    .filter_map(|(line, col, message)| {
+      let (line, col) = locate(at, (records1, records2), (line, col), opts)?;
+      Some(format!("{}:{line}:{col}: {message}", at.path))
+    })
+    .collect()
+}
+
+/// A location line for each cell --list-failures lists, e.g.
+/// "out.csv:12:4: ratio 50.00%, abs diff 5.00E-1 exceed ratio, diff
+/// (1.0000E0 vs 1.5000E0)". Cells without a row in the file located in are
+/// left out.
+pub(crate) fn cell_locations(
+  at: Locating,
+  records: (&[StringRecord], &[StringRecord]),
+  cells: &[FailingCell],
+  opts: &DiffConfig,
+) -> Vec<String> {
+  cells
+    .iter()
+    .filter_map(|cell| {
+      let (line, col) = locate(at, records, (cell.line, cell.col), opts)?;
+      let gates: Vec<&str> = cell.gates.iter().map(|g| g.name()).collect();
+      let (v1, v2) = cell.vals;
+      Some(format!(
+        "{}:{line}:{col}: ratio {:.2}%, abs diff {:.2E} exceed {} ({v1:.4E} \
+         vs {v2:.4E})",
+        at.path,
+        cell.percent,
+        cell.diff,
+        gates.join(", ")
+      ))
+    })
+    .collect()
+}
+
+/// The ratio tolerance of a cell whose values are these, if there's one.
+fn ratio_limit(tolerances: &Tolerances, (v1, v2): (f64, f64)) -> Option<f64> {
+  tolerances.ratio_limit(Direction::of(v1, v2))
+}
+
+/// The line and column of the file located in of a cell, given by csv1's
+/// physical line and (1-based) column. Cells of csv1 are located where the
+/// report puts them, whatever became of its rows; cells of csv2 by the row
+/// compared with csv1's, if there is one.
+fn locate(
+  at: Locating,
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  (line, col): (usize, usize),
+  opts: &DiffConfig,
+) -> Option<(usize, usize)> {
+  let rows = records1.len().min(records2.len());
+  let row = (0..rows).find(|&row| physical_line(records1, row) == line);
+  let (record, line, col) = match at.file {
+    1 => (row.map(|row| &records1[row]), line, col),
+    _ => {
+      let row = row?;
+      let col2 = opts.col2(col - 1).map_or(col, |j| j + 1);
+      (Some(&records2[row]), physical_line(records2, row), col2)
+    }
+  };
+  let bytes = record
+    .filter(|_| at.bytes)
+    .and_then(|record| byte_column(at.path, record, col - 1, opts.delimiter));
+  Some((line, bytes.unwrap_or(col)))
+}
+
+/// The (1-based) byte offset of a (0-based) field's start in the line of
+/// the file its record was read from, if the line can still be read there.
+/// A record's position is where the reader left off, before any blank
+/// lines it skipped.
+fn byte_column(
+  path: &str,
+  record: &StringRecord,
+  field: usize,
+  delimiter: u8,
+) -> Option<usize> {
+  let mut file = File::open(path).ok()?;
+  file.seek(SeekFrom::Start(record.position()?.byte())).ok()?;
+  let mut reader = BufReader::new(file);
+  let mut line = Vec::new();
+  while line.iter().all(|b| matches!(b, b'\r' | b'\n')) {
+    line.clear();
+    if reader.read_until(b'\n', &mut line).ok()? == 0 {
+      return None;
+    }
+  }
+  field_start(&line, field, delimiter).map(|offset| offset + 1)
+}
+
+/// The (0-based) offset of a (0-based) field's start in a line of CSV, with
+/// delimiters in quotes left alone.
+fn field_start(line: &[u8], field: usize, delimiter: u8) -> Option<usize> {
+  if field == 0 {
+    return Some(0);
+  }
+  let mut quoted = false;
+  let mut fields = 0;
+  for (i, &byte) in line.iter().enumerate() {
+    match byte {
+      b'"' => quoted = !quoted,
+      b'\n' | b'\r' if !quoted => return None,
+      _ if byte == delimiter && !quoted => {
+        fields += 1;
+        if fields == field {
+          return Some(i + 1);
+        }
+      }
+      _ => {}
+    }
+  }
+  None
+}
@@ -0,0 +1,161 @@
+//! Pairing columns by the Modal Assurance Criterion, for mode shapes whose
+//! columns come in a different order in each run. Each file's float columns
+//! are taken as vectors, the MAC of every column of csv1 with every column
+//! of csv2 is computed, and columns are paired best MAC first, as long as
+//! it reaches a threshold. The MAC ignores signs, so a column may pair with
+//! one of opposite sign, as eigenvectors are only defined up to one.
+//!
+//! This needs every row of both files, i.e. whole columns, before any cell
+//! is compared.
+
+use csv::StringRecord;
+use regex::Regex;
+
+use crate::compare::{is_float, parse_float};
+
+/// The MAC of every float column of csv1 with every float column of csv2.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MacMatrix {
+  /// The (0-based) float columns of csv1 and of csv2.
+  pub cols: (Vec<usize>, Vec<usize>),
+  /// The MAC of each of csv1's columns (a row) with each of csv2's.
+  pub values: Vec<Vec<f64>>,
+}
+
+/// A pair of columns, by the (0-based) column of each file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MacPair {
+  /// The column of csv1.
+  pub col1: usize,
+  /// The column of csv2.
+  pub col2: usize,
+  /// Their MAC.
+  pub mac: f64,
+  /// Whether they point opposite ways, i.e. their dot product is negative.
+  pub opposed: bool,
+}
+
+/// How the float columns of two files were paired.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnMatching {
+  /// The MAC matrix the columns were paired by.
+  pub matrix: MacMatrix,
+  /// The pairs, in csv1's order.
+  pub pairs: Vec<MacPair>,
+  /// The (0-based) float columns of each file left unpaired.
+  pub unpaired: (Vec<usize>, Vec<usize>),
+}
+
+/// The Modal Assurance Criterion of two vectors: the square of their dot
+/// product over the product of their squared norms, from 0 (orthogonal) to 1
+/// (parallel, either way). A vector of zeros has a MAC of 0 with anything.
+pub fn mac(a: &[f64], b: &[f64]) -> f64 {
+  let dot =
+    |x: &[f64], y: &[f64]| -> f64 { x.iter().zip(y).map(|(x, y)| x * y).sum() };
+  let norms = dot(a, a) * dot(b, b);
+  match norms > 0.0 {
+    true => dot(a, b).powi(2) / norms,
+    false => 0.0,
+  }
+}
+
+/// The (0-based) columns holding a float on each of the first `rows` rows,
+/// and each one's values.
+fn float_columns(
+  records: &[StringRecord],
+  rows: usize,
+  float_re: &Regex,
+) -> (Vec<usize>, Vec<Vec<f64>>) {
+  let records = &records[..rows];
+  let width = records.iter().map(StringRecord::len).min().unwrap_or(0);
+  (0..width)
+    .filter(|&col| {
+      !records.is_empty() && records.iter().all(|r| is_float(&r[col], float_re))
+    })
+    .map(|col| {
+      // is_float checked that they parse
+      let values = records.iter().map(|r| parse_float(&r[col]).unwrap());
+      let values = values.collect();
+      (col, values)
+    })
+    .unzip()
+}
+
+/// Pairs the float columns of two files, over the rows both have, by their
+/// MAC: best first, and among as good, nearest in place first. Columns whose
+/// best remaining MAC is below `threshold` are left unpaired. Cells are
+/// taken for floats by `float_re`.
+pub fn match_columns(
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  threshold: f64,
+  float_re: &Regex,
+) -> ColumnMatching {
+  let rows = records1.len().min(records2.len());
+  let (cols1, values1) = float_columns(records1, rows, float_re);
+  let (cols2, values2) = float_columns(records2, rows, float_re);
+  let values: Vec<Vec<f64>> = values1
+    .iter()
+    .map(|a| values2.iter().map(|b| mac(a, b)).collect())
+    .collect();
+
+  let mut candidates: Vec<(f64, usize, usize)> = Vec::new();
+  for (i, row) in values.iter().enumerate() {
+    for (j, &value) in row.iter().enumerate() {
+      if value >= threshold {
+        candidates.push((value, i, j));
+      }
+    }
+  }
+  candidates.sort_by(|a, b| {
+    b.0
+      .total_cmp(&a.0)
+      .then(
+        cols1[a.1]
+          .abs_diff(cols2[a.2])
+          .cmp(&cols1[b.1].abs_diff(cols2[b.2])),
+      )
+      .then((a.1, a.2).cmp(&(b.1, b.2)))
+  });
+  let mut partner1: Vec<Option<usize>> = vec![None; cols1.len()];
+  let mut taken2 = vec![false; cols2.len()];
+  for (_, i, j) in candidates {
+    if partner1[i].is_none() && !taken2[j] {
+      partner1[i] = Some(j);
+      taken2[j] = true;
+    }
+  }
+
+  let pairs = partner1
+    .iter()
+    .enumerate()
+    .filter_map(|(i, j)| {
+      let j = (*j)?;
+      let dot: f64 =
+        values1[i].iter().zip(&values2[j]).map(|(a, b)| a * b).sum();
+      Some(MacPair {
+        col1: cols1[i],
+        col2: cols2[j],
+        mac: values[i][j],
+        opposed: dot < 0.0,
+      })
+    })
+    .collect();
+  let unpaired = (
+    (0..cols1.len())
+      .filter(|&i| partner1[i].is_none())
+      .map(|i| cols1[i])
+      .collect(),
+    (0..cols2.len())
+      .filter(|&j| !taken2[j])
+      .map(|j| cols2[j])
+      .collect(),
+  );
+  ColumnMatching {
+    matrix: MacMatrix {
+      cols: (cols1, cols2),
+      values,
+    },
+    pairs,
+    unpaired,
+  }
+}
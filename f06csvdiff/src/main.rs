@@ -1,479 +1,7267 @@
-use clap::Parser;
-use csv::ReaderBuilder;
-use regex::Regex;
-use std::path::PathBuf;
+mod aggregate;
+mod badge;
+mod bar;
+#[cfg(test)]
+mod cli_tests;
+mod colmap;
+mod config;
+mod exclusions;
+mod exit;
+mod gha;
+mod history;
+mod inputs;
+mod locations;
+mod manifest;
+mod memstats;
+mod repro;
+mod summary;
+mod triage;
+
+use aggregate::Summary;
+use badge::Badge;
+use clap::parser::ValueSource;
+use clap::{
+  ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand,
+};
+use config::{ColumnSpec, Config, Defaults};
+use csv::StringRecord;
+use exclusions::Exclusions;
+use exit::Fatal;
+use f06csvdiff::annotations::{self, Annotation};
+use f06csvdiff::assertions::Assertions;
+use f06csvdiff::cell::{self, CellExplanation, Outcome};
+use f06csvdiff::checks::{self, Checks, MatrixNorms};
+use f06csvdiff::color::{ColorChoice, Paint, Painter};
+use f06csvdiff::compare::{
+  self, AbsCompare, ColumnMap, DemotedColumn, DiffConfig, DiffReport,
+  DiffScale, Direction, Dominance, ExtraRows, Extremum, FloatFormat,
+  INTEGER_PATTERN, RelMode, ScaledDiff, SignFlip, Skip, SkippedCells,
+  Structure, SumCheck, ThresholdMode, Ties, ZeroMismatches, physical_line,
+  segment_lines,
+};
+use f06csvdiff::complex::ComplexPair;
+use f06csvdiff::describe::{
+  annotation_mismatch, assertion_failure, column_arg, column_sums,
+  impact_classes, impact_line, matrix_norms, pair_deviations, thousands,
+  unpaired, worst_column,
+};
+use f06csvdiff::effective::{
+  self, ColumnOptions, EffectiveColumn, Role, Source,
+};
+use f06csvdiff::encoding::Encoding;
+use f06csvdiff::expected::SavedReport;
+use f06csvdiff::extract;
+use f06csvdiff::failures::{self, FailingCell, Failures};
+use f06csvdiff::groups::GroupReport;
+use f06csvdiff::headerdiff::{self, HeaderColumn, HeaderDiff, RenameHint};
+use f06csvdiff::headers;
+use f06csvdiff::histogram::Histogram;
+use f06csvdiff::impact::ImpactReport;
+use f06csvdiff::keys::{self, Duplicates, KeyMatching, KeyRows};
+use f06csvdiff::mac::{self, ColumnMatching, MacPair};
+use f06csvdiff::matrix::{self, DiffMetric};
+use f06csvdiff::nearest::{self, RowMatching};
+use f06csvdiff::normalize::{ColumnNorms, Normalization};
+use f06csvdiff::overrides::{self, ColumnOverride};
+use f06csvdiff::percent::{self, Convention, Inference, PercentColumns};
+use f06csvdiff::percentiles::{self, Percentiles};
+use f06csvdiff::profile::{self, Phase, PhaseTimes};
+use f06csvdiff::reference;
+use f06csvdiff::regression::RegressionGate;
+use f06csvdiff::scalars::{self, ScalarOutcome};
+use f06csvdiff::shift::{self, Excluded};
+use f06csvdiff::sort;
+use f06csvdiff::table::{
+  Alignment, CSV_FIELDS, Cell, Precision, align_table, aligned_row, csv_row,
+  per_column_table, profile_matrix, report_row, status_cell, value_cell,
+  waiver_cell,
+};
+use f06csvdiff::transpose::{self, Transposed};
+use f06csvdiff::vectors;
+use f06csvdiff::verdict::{Check, ColumnSum, Gate, GateResult, Tolerances};
+use f06csvdiff::waivers::{self, Waiver, Waivers};
+use f06csvdiff::{DiffError, ReadOptions, json, junit, markdown};
+use history::History;
+use inputs::Inputs;
+use manifest::Manifest;
+use nas_csv::layout::CsvBlockId;
+use repro::Shell;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::ops::RangeInclusive;
+use std::path::{Component, Path, PathBuf};
 use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+use summary::ResultLine;
 
-#[derive(Clone, Debug)]
-enum Alignment {
-  Left,
-  Right,
-  Center,
+/// The format of the report.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Format {
+  /// The usual text report.
+  #[default]
+  Plain,
+  /// JUnit XML, with a test case per gate.
+  Junit,
+  /// A Markdown table, like the aligned output.
+  Markdown,
+  /// A CSV row per pair of files, with the same fields whichever gates are
+  /// enabled.
+  Csv,
+  /// A line of JSON per pair of files, with each gate's verdict and worst
+  /// cell, and everything else the plain report finds.
+  Json,
+}
+
+impl Format {
+  /// The name of the format, as --format takes it.
+  fn name(self) -> &'static str {
+    match self {
+      Format::Plain => "plain",
+      Format::Junit => "junit",
+      Format::Markdown => "markdown",
+      Format::Csv => "csv",
+      Format::Json => "json",
+    }
+  }
 }
 
-impl std::str::FromStr for Alignment {
+impl std::str::FromStr for Format {
   type Err = String;
 
   fn from_str(s: &str) -> Result<Self, Self::Err> {
     match s.to_lowercase().as_str() {
-      "left" => Ok(Alignment::Left),
-      "right" => Ok(Alignment::Right),
-      "center" => Ok(Alignment::Center),
+      "plain" => Ok(Format::Plain),
+      "junit" => Ok(Format::Junit),
+      "markdown" => Ok(Format::Markdown),
+      "csv" => Ok(Format::Csv),
+      "json" => Ok(Format::Json),
       _ => Err(format!(
-        "Invalid alignment: {s}. Must be left, right, or center"
+        "Invalid format: {s}. Must be plain, junit, markdown, csv, or json"
       )),
     }
   }
 }
 
+/// How to split a comparison among threads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Parallel {
+  /// Each thread compares a share of the float columns.
+  Columns,
+}
+
+impl std::str::FromStr for Parallel {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "columns" => Ok(Parallel::Columns),
+      _ => Err(format!("Invalid parallel mode: {s}. Must be columns")),
+    }
+  }
+}
+
+/// A cell asked about with --explain-cell: a physical line of csv1 and a
+/// column, by number or header name.
+#[derive(Clone, Debug)]
+struct CellRef {
+  line: usize,
+  col: String,
+}
+
+impl std::str::FromStr for CellRef {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || format!("Invalid cell: {s}. Must be LINE:COL");
+    let (line, col) = s.split_once(':').ok_or_else(invalid)?;
+    let line = line.parse().map_err(|_| invalid())?;
+    if col.is_empty() {
+      return Err(invalid());
+    }
+    Ok(CellRef {
+      line,
+      col: col.to_string(),
+    })
+  }
+}
+
 /// Diffs floating-point numbers at corresponding positions within two CSVs.
 ///
 /// Made for usage alongside f06csv.
 ///
+/// Exits with 0 if every comparison passed, 1 if any failed, 2 on errors, 3
+/// if an input file changed while it was being read, 4 if every failure is
+/// excused by --waivers, and 5 if every comparison passed but some exceeded
+/// --warn-ratio or --warn-diff.
+///
 /// Author: Bruno Borges Paschoalinoto <bruno@paschoalinoto.com>
 #[derive(Parser)]
-#[command(author, version, about)]
+#[command(
+  author,
+  version,
+  about,
+  args_conflicts_with_subcommands = true,
+  subcommand_negates_reqs = true,
+  // --headers stands for both, for the options needing either
+  group(
+    ArgGroup::new("header_row")
+      .args(["has_headers", "headers"])
+      .multiple(true)
+  ),
+  group(
+    ArgGroup::new("header_names")
+      .args(["match_by_header", "headers"])
+      .multiple(true)
+  )
+)]
 struct Args {
+  #[command(subcommand)]
+  command: Option<Command>,
   #[arg(short = 'd', long, value_name = "REAL")]
   max_diff: Option<f64>,
+  /// Fail if any relative difference exceeds this (0.01 is 1%), as measured
+  /// by --rel-mode.
   #[arg(short = 'r', long, value_name = "REAL")]
   max_ratio: Option<f64>,
-  #[arg(short = 't', long, value_name = "REAL", default_value = "0")]
-  threshold: f64,
-  #[arg(long, value_name = "CHAR", default_value = ",")]
-  delim: char,
+  /// -r in percent: fail if any relative difference exceeds this many
+  /// percent (1 is 1%), as measured by --rel-mode.
+  #[arg(long, value_name = "PERCENT", conflicts_with = "max_ratio")]
+  max_percent: Option<f64>,
+  /// -r for the cells where csv2 is above csv1, i.e. over-predicted, which
+  /// -r still judges elsewhere. A cell's direction is that of its values as
+  /// compared: after --allow-sign-flip flips csv2's signs, and of their
+  /// magnitudes with --abs-compare. Needs -r or --max-percent.
+  #[arg(long, value_name = "REAL")]
+  max_ratio_over: Option<f64>,
+  /// -r for the cells where csv2 isn't above csv1, i.e. under-predicted, as
+  /// --max-ratio-over.
+  #[arg(long, value_name = "REAL")]
+  max_ratio_under: Option<f64>,
+  /// -d for the cells where csv2 is above csv1, as --max-ratio-over. Needs
+  /// -d.
+  #[arg(long, value_name = "REAL")]
+  max_diff_over: Option<f64>,
+  /// -d for the cells where csv2 isn't above csv1, as --max-ratio-over.
+  /// Needs -d.
+  #[arg(long, value_name = "REAL")]
+  max_diff_under: Option<f64>,
+  /// Judge each cell by -d and -r combined, as numpy's allclose does: it
+  /// passes if |a-b| <= d + r*|b|, b being csv2's value, the reference, so
+  /// that neither a ratio near zero nor a difference at large values fails
+  /// it alone. Needs -d or -r; the one not given is zero.
+  #[arg(
+    long,
+    conflicts_with_all = [
+      "max_ratio_over", "max_ratio_under", "max_diff_over", "max_diff_under",
+      "scale_tolerance_by",
+    ]
+  )]
+  allclose: bool,
+  /// Multiply -d, and --warn-diff, in each row by the magnitude of this
+  /// column of csv1 there (a number, or with --has-headers a header name),
+  /// e.g. a load level, so that rows at low load get proportionally tighter
+  /// checks. Rows without a number there keep -d as it is, and are counted
+  /// in a warning.
+  #[arg(
+    long,
+    value_name = "COL",
+    conflicts_with_all = ["max_diff_over", "max_diff_under", "scalars"]
+  )]
+  scale_tolerance_by: Option<String>,
+  /// The smallest scale --scale-tolerance-by gives a row, so that no row is
+  /// held to a zero difference.
+  #[arg(
+    long,
+    value_name = "REAL",
+    default_value = "0",
+    requires = "scale_tolerance_by"
+  )]
+  scale_tolerance_floor: f64,
+  /// Warn, without failing, if any relative difference exceeds this, as -r
+  /// measures it: with -r 0.01 --warn-ratio 0.005, differences between 0.5%
+  /// and 1% are flagged as WARNED.
+  #[arg(long, value_name = "REAL")]
+  warn_ratio: Option<f64>,
+  /// Warn, without failing, if any absolute difference exceeds this, as -d
+  /// measures it.
+  #[arg(long, value_name = "REAL")]
+  warn_diff: Option<f64>,
+  /// Hold cells zero in exactly one file to this magnitude instead of -r:
+  /// such a cell fails if its nonzero value is at least MAG in magnitude,
+  /// and relative differences leave it out, so that 0.0 against 5.0E+3
+  /// isn't the same infinite difference as 0.0 against 1.0E-30. -d still
+  /// applies to it. Without it, these cells are only counted.
+  #[arg(long, value_name = "MAG")]
+  max_zero_mismatch: Option<f64>,
+  /// Also check each float column's sums over its compared cells, such as
+  /// a force balance's total reactions, against -r and -d, as a single
+  /// cell would be: rows may shift load between each other as long as the
+  /// totals agree. Cells skipped by the threshold or left out are left out
+  /// of both files' sums. A column's sums failing fails the comparison.
+  #[arg(long)]
+  sum_check: bool,
+  /// With --sum-check, judge by the sums alone: the cells' -r, -d and
+  /// --min-digits gates are still reported but don't fail the comparison.
+  #[arg(long, requires = "sum_check")]
+  sum_check_only: bool,
+  /// Fail if any float column agrees to fewer significant digits than this.
+  #[arg(long, value_name = "REAL")]
+  min_digits: Option<f64>,
+  /// Pass each of -d, -r and --min-digits anyway if fewer than this fraction
+  /// of the compared cells exceed it on their own (0.001 is 0.1%).
+  #[arg(long, value_name = "F")]
+  max_fail_fraction: Option<f64>,
+  /// Judge -d, -r and --min-digits by the high-impact cells alone: those
+  /// where either value is at least its column's significance, as the
+  /// config file's [columns] tables give it, or whose column has none.
+  /// Low-impact cells are still counted apart in the report.
+  #[arg(
+    long,
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  fail_only_high_impact: bool,
+  #[command(flatten)]
+  comparison: ComparisonArgs,
+  /// Divide each file's columns by their own norms before comparing: max
+  /// (the largest magnitude), l2, or none. A uniformly scaled column then
+  /// compares as identical, for mode shapes or load distributions whose
+  /// shape matters. Replaces --scale1 and --scale2; columns of zeros are
+  /// compared as they are.
+  #[arg(
+    long,
+    value_name = "NORM",
+    default_value = "none",
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  normalize_columns: Normalization,
+  /// Bring these percentage-like columns of csv1 (e.g. 4,5, or with
+  /// --has-headers by name), and those of csv2 compared with them, to a
+  /// common convention before comparing, for exporters that write them as
+  /// percent (73.5) or as fractions (0.735). Each file's convention is
+  /// inferred from its values there, percent if any is above 1, and noted;
+  /// values out of range once converted are flagged.
+  #[arg(
+    long,
+    value_name = "COLS",
+    conflicts_with_all = [
+      "normalize_columns", "regression_gate", "expected_report", "scalars",
+    ]
+  )]
+  percent_columns: Option<String>,
+  /// The convention --percent-columns are compared in: percent, fraction,
+  /// or auto, csv1's.
+  #[arg(
+    long,
+    value_name = "CONVENTION",
+    default_value = "auto",
+    value_parser = ["auto", "percent", "fraction"],
+    requires = "percent_columns"
+  )]
+  percent_convention: String,
+  /// How floats are written, to take cells for floats by: standard, e.g.
+  /// 1.5E+00 or .5E+00; fortran, also 1.5D+00 and 1.E+00; or nastran, also
+  /// exponents without their letter, e.g. 1.5-3.
+  #[arg(long, value_name = "FORMAT", default_value = "standard")]
+  float_format: FloatFormat,
+  /// Take cells matching this regex, anywhere in them, for floats instead
+  /// of --float-format's, as long as they parse; D exponents and Nastran's
+  /// exponents without a letter read as E ones.
+  #[arg(long, value_name = "REGEX", conflicts_with = "float_format")]
+  float_pattern: Option<String>,
+  /// Take cells holding plain integers, such as node counts or iteration
+  /// numbers, for floats too, so that their columns are compared; a column
+  /// of integers in one file and floats in the other compares by value.
+  #[arg(long)]
+  include_integers: bool,
   #[arg(long)]
   explain: bool,
+  /// With --explain, which it implies, also print the whole rows of both
+  /// files holding each gate's worst cell, the cell marked >>like this<<,
+  /// under their headers with --has-headers.
+  #[arg(long)]
+  show_rows: bool,
+  /// Cut rows --show-rows prints down to about this many characters, by
+  /// leaving out cells away from the marked one.
+  #[arg(
+    long,
+    value_name = "CHARS",
+    default_value = "160",
+    requires = "show_rows"
+  )]
+  show_rows_width: usize,
+  /// Before the report, print everything about how the cell at this line of
+  /// csv1 and this column was compared: its text and values in both files,
+  /// what skipped it if anything, and its verdict against each gate. COL is
+  /// a number, or with --has-headers a header name. May be repeated.
+  #[arg(long, value_name = "LINE:COL", conflicts_with = "format")]
+  explain_cell: Vec<CellRef>,
+  /// Compare only these physical lines of csv1, FIRST-LAST, START:END
+  /// (either side of which may be left out) or a single LINE, and the rows
+  /// of csv2 paired with them, once rows are sorted, matched or shifted.
+  /// Whatever looks at whole columns, such as --threshold-frac, sign flips
+  /// or --normalize-columns, sees only these.
+  #[arg(
+    long,
+    value_name = "LINES",
+    value_parser = line_range,
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  rows: Option<RangeInclusive<usize>>,
+  /// Compare only every Nth row of those --rows keeps, or of all of them,
+  /// starting with the first, for spot checks of huge files. A sampled run
+  /// says how many rows it compared, and its result line and JUnit suite
+  /// carry the count, since its PASS vouches only for those.
+  #[arg(
+    long,
+    value_name = "N",
+    value_parser = clap::value_parser!(u32).range(1..),
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  stride: Option<u32>,
+  /// Compare only these columns of csv1 (e.g. 3,5, or with --has-headers by
+  /// name), and those of csv2 compared with them.
+  #[arg(
+    long,
+    value_name = "COLS",
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  columns: Option<String>,
+  /// After each pair's report, a table of every compared float column: its
+  /// worst relative and absolute differences, their lines, and whether its
+  /// worst cells pass the tolerances (--max-fail-fraction aside). The
+  /// pair's verdict is unchanged.
+  #[arg(
+    long,
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  per_column: bool,
+  /// The shell to quote the command reproducing a failure for: posix or
+  /// powershell. Failing pairs get one, the same options narrowed to the
+  /// worst violation with --rows, --columns and --explain-cell, in
+  /// --explain's report or otherwise on stderr.
+  #[arg(long, value_name = "SHELL", default_value = "posix")]
+  repro_shell: Shell,
   #[arg(long, value_name = "ALIGNMENT")]
   align: Option<Alignment>,
   #[arg(short = 'w', long, value_name = "WIDTH")]
   width: Option<usize>,
+  /// Print the names of the aligned report's columns above its row. The
+  /// files' own header rows are read with --has-headers or --headers
+  /// instead.
   #[arg(long)]
   header: bool,
-  csv1: String,
-  csv2: String,
+  /// What the report calls csv1, instead of its file name, e.g. when both
+  /// files share one. Needs a single pair.
+  #[arg(long, value_name = "NAME")]
+  label1: Option<String>,
+  /// What the report calls csv2, instead of its file name. Needs a single
+  /// pair.
+  #[arg(long, value_name = "NAME")]
+  label2: Option<String>,
+  /// Call each file by its path as given rather than its file name, unless
+  /// --label1 or --label2 names it. Files of the same name are otherwise
+  /// called by as much of their paths as tells them apart.
+  #[arg(long)]
+  full_paths: bool,
+  /// The digits after the point of the values in the report, as in
+  /// +1.234568E2 for 6.
+  #[arg(
+    long,
+    value_name = "N",
+    default_value = "6",
+    value_parser = clap::value_parser!(u32).range(..=16)
+  )]
+  report_precision: u32,
+  /// The digits after the point of percentages in the report.
+  #[arg(
+    long,
+    value_name = "N",
+    default_value = "2",
+    value_parser = clap::value_parser!(u32).range(..=16)
+  )]
+  percent_precision: u32,
+  /// The report format: plain; junit for CI dashboards (one test suite per
+  /// pair of files, one test case per gate); markdown, a table with a row
+  /// per pair of files, for pull requests; or csv, a row per pair of files
+  /// with the fields of both -r and -d, empty for a gate not enabled, to
+  /// concatenate runs into one table; or json, an object per pair of files
+  /// on a line of its own, with its status, counts, and each gate's
+  /// verdict, limit, worst value (percent for ratio) and where that is, as
+  /// for the warning tolerances, each column's digits of agreement, its
+  /// groups, its --histogram and --percentiles, and the waivers excusing
+  /// it.
+  #[arg(long, value_name = "FORMAT", default_value = "plain")]
+  format: Format,
+  /// With --format csv, start with a header row naming the fields.
+  #[arg(long)]
+  csv_header: bool,
+  /// Also print GitHub Actions annotations to stdout: an error per failed
+  /// gate, pointing at the offending line of csv2, or a notice with the
+  /// maxima. This comes on top of the report, whatever its format.
+  #[arg(long)]
+  gha: bool,
+  /// Locate the cells tied with each worst cell, as bad or a ULP better,
+  /// up to N of them (default 20), after the count of them that plain and
+  /// --explain reports give anyway.
+  #[arg(
+    long,
+    value_name = "N",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = "20"
+  )]
+  list_ties: Option<usize>,
+  /// List every compared cell exceeding a tolerance on its own, or the
+  /// worst N of them, with its line, column and both values: all of a
+  /// run's regressions at once, rather than only each gate's worst cell.
+  /// They're listed by relative difference, then absolute difference.
+  #[arg(
+    long,
+    value_name = "N",
+    num_args = 0..=1,
+    require_equals = true,
+    conflicts_with = "transpose1"
+  )]
+  list_failures: Option<Option<usize>>,
+  /// How to print where the worst cells are: plain, only in the report; or
+  /// gcc, also a line per gate with a worst cell, and per cell
+  /// --list-failures lists, as compilers print errors, "csv2.csv:4521:7:
+  /// ratio 3.41% exceeds 1.00% (1.2345E-2 vs 1.1987E-2)", for terminals and
+  /// editors to jump to.
+  #[arg(
+    long,
+    value_name = "STYLE",
+    default_value = "plain",
+    value_parser = ["plain", "gcc"],
+    conflicts_with_all = ["transpose1", "transpose2"]
+  )]
+  locations_style: String,
+  /// Where --locations-style gcc prints its lines: stderr or stdout.
+  #[arg(
+    long,
+    value_name = "STREAM",
+    default_value = "stderr",
+    value_parser = ["stderr", "stdout"]
+  )]
+  locations_to: String,
+  /// Which file --locations-style gcc points into: 1 or 2.
+  #[arg(
+    long,
+    value_name = "FILE",
+    default_value_t = 2,
+    value_parser = clap::value_parser!(u32).range(1..=2)
+  )]
+  locate_in: u32,
+  /// With --locations-style gcc, make columns the byte offsets of the
+  /// fields' starts in their lines, rather than the fields' numbers.
+  #[arg(long)]
+  byte_columns: bool,
+  /// Write an SVG status badge: the pair, "csv1 vs csv2" by basename, its
+  /// status (PASSED, WARNED, WAIVED or FAILED), and its worst percent
+  /// difference.
+  /// In batch mode, a directory to write a badge per pair into, named after
+  /// the pair, and all.svg for the whole batch.
+  #[arg(
+    long,
+    value_name = "PATH",
+    conflicts_with_all = ["regression_gate", "expected_report"]
+  )]
+  badge: Option<PathBuf>,
+  /// Like --badge, but write the JSON of a shields.io endpoint badge.
+  #[arg(
+    long,
+    value_name = "PATH",
+    conflicts_with_all = ["regression_gate", "expected_report"]
+  )]
+  badge_json: Option<PathBuf>,
+  /// The JUnit test suite name (default: "csv1 vs csv2", as the report
+  /// names them).
+  #[arg(long, value_name = "NAME")]
+  suite_name: Option<String>,
+  /// When to color the output: auto (only on a terminal, unless NO_COLOR is
+  /// set), always, or never.
+  #[arg(long, value_name = "WHEN", default_value = "auto")]
+  color: ColorChoice,
+  /// Also run the reference implementation and fail if the two disagree.
+  #[arg(long)]
+  verify: bool,
+  /// Compare a float column against csv2's negated values when that matches
+  /// better, e.g. for mode shapes. Forces buffering whole columns.
+  #[arg(long)]
+  allow_sign_flip: bool,
+  /// Like --allow-sign-flip, but only for these (1-based) columns.
+  #[arg(
+    long,
+    value_name = "COLS",
+    value_delimiter = ',',
+    value_parser = clap::value_parser!(u64).range(1..),
+    conflicts_with = "allow_sign_flip"
+  )]
+  allow_sign_flip_cols: Vec<u64>,
+  /// Compare every cell by its values' magnitudes, for quantities whose
+  /// signs may legitimately differ, e.g. reaction forces at symmetric
+  /// boundaries. Unlike --allow-sign-flip, it's cell by cell. The worst
+  /// cells' values are still shown signed, and the cells whose signs differ
+  /// are counted.
+  #[arg(long)]
+  abs_compare: bool,
+  /// Like --abs-compare, but only for these (1-based) columns.
+  #[arg(
+    long,
+    value_name = "COLS",
+    value_delimiter = ',',
+    value_parser = clap::value_parser!(u64).range(1..),
+    conflicts_with = "abs_compare"
+  )]
+  abs_compare_cols: Vec<u64>,
+  /// Fail when a cell is a float in one file but not in the other, instead
+  /// of silently leaving its column out of the comparison.
+  #[arg(long)]
+  strict_layout: bool,
+  /// Don't report the columns left out of the comparison for a cell that
+  /// isn't a float, e.g. "compared 14 of 22 columns; columns 3, 7, 19
+  /// excluded (first non-float at lines 2, 2, 4481)".
+  #[arg(long)]
+  no_column_report: bool,
+  /// Fail a pair with a column of floats left out of the comparison for a
+  /// later cell that isn't a float, such as a stray "N/A". Columns that
+  /// aren't floats from their first compared row on, such as labels, are
+  /// left out as usual.
+  #[arg(long)]
+  require_all_float_columns: bool,
+  /// Fail a pair if fewer than N columns are compared.
+  #[arg(long, value_name = "N")]
+  min_compared_columns: Option<usize>,
+  /// Leave rows with structural problems out of the comparison instead of
+  /// stopping at the first: a different number of fields than their file's
+  /// first row or the other file's row, a column the column map pairs
+  /// missing, or with --strict-layout a different float layout. The rest
+  /// are compared as usual, and the problems listed; any fails the
+  /// comparison.
+  #[arg(long, conflicts_with_all = ["transpose1", "write_diff_csv"])]
+  lenient_structure: bool,
+  /// With --lenient-structure, only warn about rows with structural
+  /// problems.
+  #[arg(long, requires = "lenient_structure")]
+  structure_warn_only: bool,
+  /// List at most this many of the rows --lenient-structure leaves out.
+  #[arg(
+    long,
+    value_name = "N",
+    default_value = "20",
+    requires = "lenient_structure"
+  )]
+  max_structure_report: usize,
+  /// If an input file changes while it's read (e.g. it's still being
+  /// written), wait a second and read it again, up to this many times.
+  #[arg(long, value_name = "N", default_value = "0")]
+  retries_on_change: u32,
+  /// Hold a shared lock on each input file while reading it, to wait for
+  /// writers that lock it exclusively. Skipped where unsupported.
+  #[arg(long)]
+  lock_inputs: bool,
+  /// How input files are decoded: utf8, latin1, or auto, which takes off a
+  /// UTF-8 byte order mark and falls back to Latin-1 from the first byte
+  /// that isn't UTF-8.
+  #[arg(long, value_name = "ENCODING", default_value = "auto")]
+  encoding: Encoding,
+  /// Keep the whitespace around cells, and quotes left within it, which are
+  /// otherwise trimmed off before anything is read from them.
+  #[arg(long)]
+  no_trim: bool,
+  /// Compare files with different numbers of rows up to the shorter one's
+  /// length, warning about the rows left over instead of failing.
+  #[arg(long)]
+  allow_extra_rows: bool,
+  /// Only allow leftover rows that match this regex, tried against each
+  /// row's cells joined by the delimiter.
+  #[arg(long, value_name = "REGEX", requires = "allow_extra_rows")]
+  ignore_rows_matching: Option<String>,
+  /// Judge csv2, a new version, by how much further from csv1, the
+  /// reference, it is than the --old version: fail any float column whose
+  /// worst absolute difference exceeds the old one's by more than this
+  /// fraction (0.05 is 5%).
+  #[arg(
+    long,
+    value_name = "REAL",
+    requires = "old",
+    conflicts_with_all = [
+      "max_diff", "max_ratio", "max_percent", "min_digits",
+      "max_ratio_over", "max_ratio_under", "max_diff_over", "max_diff_under",
+      "max_fail_fraction", "profiles", "manifest",
+      "explain", "explain_cell", "align", "format", "gha", "sqlite",
+    ]
+  )]
+  regression_gate: Option<f64>,
+  /// The previously qualified version of csv2, for --regression-gate.
+  #[arg(long, value_name = "PATH", requires = "regression_gate")]
+  old: Option<String>,
+  /// The difference --regression-gate always allows, in each column's
+  /// units. It's what a column the old version matched exactly falls back
+  /// to; with the default of 0, any difference there fails.
+  #[arg(
+    long,
+    value_name = "REAL",
+    default_value = "0",
+    requires = "regression_gate"
+  )]
+  regression_floor: f64,
+  /// Write the report's worst differences per float column to this file,
+  /// as the expectation for --expected-report. Needs a single pair.
+  #[arg(
+    long,
+    value_name = "FILE",
+    conflicts_with_all = ["manifest", "regression_gate"]
+  )]
+  save_report: Option<PathBuf>,
+  /// Write the difference at each cell to this CSV, in the shape of csv1:
+  /// float columns hold --diff-metric where compared, and other columns are
+  /// copied. It's written even if the comparison fails. Needs a single pair.
+  #[arg(
+    long,
+    value_name = "PATH",
+    conflicts_with_all = ["manifest", "regression_gate", "expected_report"]
+  )]
+  write_diff_csv: Option<PathBuf>,
+  /// What --write-diff-csv writes at compared cells: abs (|b-a|), rel
+  /// (|b-a|/max(|a|,|b|)) or signed (b-a).
+  #[arg(
+    long,
+    value_name = "METRIC",
+    default_value = "abs",
+    requires = "write_diff_csv"
+  )]
+  diff_metric: DiffMetric,
+  /// What --write-diff-csv writes at cells that weren't compared, such as
+  /// those below the threshold or zero in both files.
+  #[arg(
+    long,
+    value_name = "TEXT",
+    default_value = "",
+    requires = "write_diff_csv"
+  )]
+  diff_empty: String,
+  /// Judge the pair by how far it drifts from the report saved in this file
+  /// with --save-report, instead of by tolerances: fail any float column
+  /// whose worst absolute difference exceeds the saved one by more than
+  /// --report-margin. The report must have been saved by this version, with
+  /// the same comparison options.
+  #[arg(
+    long,
+    value_name = "FILE",
+    conflicts_with_all = [
+      "max_diff", "max_ratio", "max_percent", "min_digits",
+      "max_ratio_over", "max_ratio_under", "max_diff_over", "max_diff_under",
+      "max_fail_fraction", "profiles", "manifest", "regression_gate",
+      "explain", "explain_cell", "align", "format", "gha", "sqlite",
+    ]
+  )]
+  expected_report: Option<PathBuf>,
+  /// How much further the pair may be from the expected report, relative
+  /// to each column's saved difference (0.05 is 5%).
+  #[arg(
+    long,
+    value_name = "REAL",
+    default_value = "0",
+    requires = "expected_report"
+  )]
+  report_margin: f64,
+  /// Compare files of named scalars, such as model totals: a name and a
+  /// value on each row, matched by name in any order. Each name may have
+  /// its own tolerances in the config file's [scalars.NAME] tables, falling
+  /// back to -d, -r and --min-digits; a name in only one file fails unless
+  /// --allow-extra-rows allows it. The report lists every scalar.
+  #[arg(
+    long,
+    conflicts_with_all = [
+      "profiles", "regression_gate", "expected_report", "save_report",
+      "write_diff_csv", "explain", "explain_cell", "format", "gha", "sqlite",
+      "verify", "allow_sign_flip", "allow_sign_flip_cols", "threshold_frac",
+      "abs_compare", "abs_compare_cols",
+      "fail_fast", "parallel", "histogram", "percentiles", "sort_by",
+    ]
+  )]
+  scalars: bool,
+  /// Compare each column of csv1 with the column of csv2 this file pairs it
+  /// with, for files whose columns come in different orders. Each line is a
+  /// column of csv1, a comma and a column of csv2, by number or with
+  /// --has-headers by name; unpaired columns aren't compared.
+  #[arg(
+    long,
+    value_name = "FILE",
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  col_map: Option<PathBuf>,
+  /// Compare the columns of csv1 and csv2 whose header names are the same,
+  /// trimmed and ignoring case, in whatever order they come. Columns named
+  /// in only one file are left out with a warning.
+  #[arg(
+    long,
+    requires = "header_row",
+    conflicts_with_all = [
+      "col_map", "regression_gate", "expected_report", "scalars",
+    ]
+  )]
+  match_by_header: bool,
+  /// Treat the first row of each file as a header and pair columns by its
+  /// names, for f06csv versions writing columns in different orders or
+  /// sets: --has-headers with --match-by-header. Not to be confused with
+  /// --header, which labels the report's own columns.
+  #[arg(
+    long,
+    conflicts_with_all = [
+      "col_map", "regression_gate", "expected_report", "scalars",
+    ]
+  )]
+  headers: bool,
+  /// Make a column named in only one file an error with --match-by-header.
+  #[arg(long, requires = "header_names")]
+  require_all_columns: bool,
+  /// Sort both files' rows on these key columns of csv1 (e.g. 1,2) before
+  /// comparing, for runs that write the same rows in different orders. Keys
+  /// that parse as numbers sort by value, others as text. Reports still give
+  /// the lines rows were read from.
+  #[arg(
+    long,
+    value_name = "COLS",
+    value_delimiter = ',',
+    value_parser = clap::value_parser!(u64).range(1..)
+  )]
+  sort_by: Vec<u64>,
+  /// Sort both files' rows on all their columns, first to last, before
+  /// comparing, for files with no key column whose rows may come in any
+  /// order. Cells that parse as numbers sort by value, others as text; a
+  /// value that changes can move its row, so the rows around it are paired
+  /// differently. Reports still give the lines rows were read from. Both
+  /// files are held whole while sorted, up to --max-rows rows each.
+  #[arg(
+    long,
+    conflicts_with_all = [
+      "sort_by", "match_rows_nearest", "shift_rows", "auto_shift", "col_map",
+      "match_by_header", "headers", "mac_match", "scalars",
+    ]
+  )]
+  canonical_order: bool,
+  /// The most rows a file may have for --canonical-order to sort it.
+  #[arg(long, value_name = "N", default_value = "1000000")]
+  max_rows: usize,
+  /// Pair rows by the nearest value of this column of csv1 (by number, or
+  /// with --has-headers by name) instead of by position, for tables whose
+  /// rows may swap places between runs, such as the frequencies of nearly
+  /// degenerate modes. Rows left unpaired fail the comparison.
+  #[arg(
+    long,
+    value_name = "COL",
+    conflicts_with_all = [
+      "sort_by", "regression_gate", "expected_report", "scalars"
+    ]
+  )]
+  match_rows_nearest: Option<String>,
+  /// How many rows away from its place a row may be paired with
+  /// --match-rows-nearest.
+  #[arg(
+    long,
+    value_name = "K",
+    default_value = "2",
+    requires = "match_rows_nearest"
+  )]
+  match_window: usize,
+  /// Pair rows by their key in this column of csv1 (by number, or with
+  /// --has-headers by name), such as element IDs, instead of by position:
+  /// each key's rows, in csv1's order, numbers matching by value. Keys only
+  /// one file has are listed and their rows left unpaired, which fails the
+  /// comparison; keys several rows of a file share are --duplicate-keys'.
+  #[arg(
+    long,
+    value_name = "COL",
+    conflicts_with_all = [
+      "sort_by", "canonical_order", "match_rows_nearest", "shift_rows",
+      "auto_shift", "regression_gate", "expected_report", "scalars",
+    ]
+  )]
+  key_col: Option<String>,
+  /// What to do with a --key-col key several rows of a file share: error,
+  /// listing each with its lines; first, pairing the first of its rows; or
+  /// sum, pairing a row of their sums, for element contributions split
+  /// across rows.
+  #[arg(
+    long,
+    value_name = "POLICY",
+    default_value = "error",
+    requires = "key_col"
+  )]
+  duplicate_keys: Duplicates,
+  /// Write how every --key-col key reconciles here, as CSV: the key, its
+  /// status (matched, only_in_1, only_in_2 or duplicated), and its rows'
+  /// count and lines in each file. Written before duplicates fail the run.
+  #[arg(long, value_name = "PATH", requires = "key_col")]
+  keys_report: Option<PathBuf>,
+  /// Also compare the rows of each group, such as a subcase, on their own:
+  /// the rows whose cell in this column of csv1 (a number, or with
+  /// --has-headers a header name) holds the same value, paired in order
+  /// within the group. Each group's maxima, counts and verdict are reported,
+  /// and any failing group fails the comparison; a group in only one file
+  /// is an error.
+  #[arg(
+    long,
+    value_name = "COL",
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  group_by: Option<String>,
+  /// How --group-by reports the groups: text, a line or row per group in
+  /// the report, or json, a line of JSON per group after it.
+  #[arg(
+    long,
+    value_name = "FORMAT",
+    default_value = "text",
+    value_parser = ["text", "json"],
+    requires = "group_by"
+  )]
+  group_format: String,
+  /// Pair the float columns of csv1 and csv2 by their Modal Assurance
+  /// Criterion instead of by position, for mode shapes whose columns come in
+  /// different orders: best MAC first, at least --mac-threshold. Columns
+  /// pointing opposite ways may have their signs flipped; float columns left
+  /// unpaired fail the comparison. Buffers whole columns; with --explain,
+  /// prints the MAC matrix.
+  #[arg(
+    long,
+    conflicts_with_all = [
+      "col_map", "match_by_header", "headers", "sort_by",
+      "match_rows_nearest", "regression_gate", "expected_report", "scalars",
+    ]
+  )]
+  mac_match: bool,
+  /// The lowest MAC at which --mac-match pairs two columns.
+  #[arg(
+    long,
+    value_name = "MAC",
+    default_value = "0.9",
+    requires = "mac_match"
+  )]
+  mac_threshold: f64,
+  /// Pair csv2's rows with csv1's this many rows later (or if negative,
+  /// earlier), for transient runs whose outputs are offset by a constant
+  /// number of steps, e.g. when only one of them writes t=0. Rows left
+  /// without a partner at either end aren't compared, and are noted.
+  #[arg(
+    long,
+    value_name = "N",
+    allow_hyphen_values = true,
+    conflicts_with_all = [
+      "auto_shift", "sort_by", "match_rows_nearest", "regression_gate",
+      "expected_report", "scalars",
+    ]
+  )]
+  shift_rows: Option<isize>,
+  /// Like --shift-rows, by the shift of at most MAXN rows either way under
+  /// which --shift-col differs least on average. The shift chosen is noted.
+  #[arg(
+    long,
+    value_name = "MAXN",
+    conflicts_with_all = [
+      "sort_by", "match_rows_nearest", "regression_gate", "expected_report",
+      "scalars",
+    ]
+  )]
+  auto_shift: Option<usize>,
+  /// The column --auto-shift aligns, such as time: a number, or with
+  /// --has-headers a header name.
+  #[arg(
+    long,
+    value_name = "COL",
+    default_value = "1",
+    requires = "auto_shift"
+  )]
+  shift_col: String,
+  /// Also compare the magnitude of the vector in these columns of csv1
+  /// (e.g. 3,4,5, or with --has-headers T1,T2,T3), the root sum of their
+  /// squares at each row, under the same criteria. Suits displacements
+  /// whose direction rotates slightly. May be repeated.
+  #[arg(
+    long,
+    value_name = "COLS",
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  vector_cols: Vec<String>,
+  /// Compare only the --vector-cols magnitudes, not their components.
+  #[arg(long, requires = "vector_cols")]
+  vector_only: bool,
+  /// Compare these pairs of columns of csv1 as the real and imaginary parts
+  /// of complex values (e.g. 3:4,5:6, or with --has-headers by name): by
+  /// their magnitudes, under the same criteria, instead of part by part,
+  /// and by their phases, across the wrap-around. Phases where both
+  /// magnitudes are below --threshold aren't compared.
+  #[arg(
+    long,
+    value_name = "PAIRS",
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  complex_pairs: Option<String>,
+  /// Fail if a --complex-pairs phase differs by more than this many
+  /// degrees.
+  #[arg(long, value_name = "DEG", requires = "complex_pairs")]
+  max_phase_deg: Option<f64>,
+  /// Take the compared float columns of the compared rows of each file as a
+  /// dense matrix, A and B, and report ||A-B||/||A|| in the Frobenius norm,
+  /// with the entry that differs most. Files with different numbers of rows
+  /// are an error even if extra rows are allowed.
+  #[arg(
+    long,
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  matrix_mode: bool,
+  /// With --matrix-mode, report the max row-sum norm as well.
+  #[arg(long, requires = "matrix_mode")]
+  matrix_row_sum: bool,
+  /// Fail if a --matrix-mode norm of the difference exceeds this fraction
+  /// of the same norm of csv1's matrix.
+  #[arg(long, value_name = "FRACTION", requires = "matrix_mode")]
+  max_matrix_rel: Option<f64>,
+  /// Before the report, print each column's effective configuration: its
+  /// role, the column of csv2 it's compared with, its tolerances and
+  /// policies, and the options that set them. Options that claim a column
+  /// in ways that can't both hold are an error either way.
+  #[arg(
+    long,
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  show_effective_columns: bool,
+  /// Take trailing annotations matching this regex (default: a single `*`)
+  /// off numbers such as "1.2345E+03*" before comparing them, so that
+  /// annotated cells still count as floats.
+  #[arg(
+    long,
+    value_name = "REGEX",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = annotations::DEFAULT_SUFFIX
+  )]
+  strip_suffix: Option<String>,
+  /// Fail a pair whose compared cells are annotated differently in each
+  /// file, reported apart from their values' differences.
+  #[arg(
+    long,
+    requires = "strip_suffix",
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  compare_annotations: bool,
+  /// Leave out this many rows at the start of both files, e.g. a title
+  /// block. With --has-headers, the header is the row after them.
+  #[arg(long, value_name = "N", default_value = "0")]
+  skip_rows: usize,
+  /// Overrides --skip-rows for csv1.
+  #[arg(long, value_name = "N")]
+  skip_rows1: Option<usize>,
+  /// Overrides --skip-rows for csv2.
+  #[arg(long, value_name = "N")]
+  skip_rows2: Option<usize>,
+  /// Leave out this many rows at the end of both files, e.g. a totals row.
+  #[arg(long, value_name = "M", default_value = "0")]
+  skip_tail: usize,
+  /// Take fields absent at the end of a row for empty ones, padding the
+  /// rows of both files to the widest, e.g. for an export leaving out empty
+  /// trailing fields. A field in a column only one file has must be empty.
+  #[arg(long)]
+  pad_missing: bool,
+  /// Take empty trailing fields off every row before the column counts are
+  /// checked, e.g. for an export ending every line with a delimiter.
+  #[arg(long)]
+  ignore_trailing_empty: bool,
+  /// Transpose csv1 as it's read, for a file written a row per quantity
+  /// and a column per grid point: each column becomes a row, before
+  /// anything else is done with it. Cells are still reported at csv1's own
+  /// lines and columns, so --rows, --explain-cell and --show-rows, which
+  /// would name them by the transposed rows, can't be used with it.
+  #[arg(
+    long,
+    conflicts_with_all = [
+      "rows", "explain_cell", "show_rows", "regression_gate",
+      "expected_report", "scalars"
+    ]
+  )]
+  transpose1: bool,
+  /// Transpose csv2 as it's read, as --transpose1 does csv1.
+  #[arg(
+    long,
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  transpose2: bool,
+  /// Treat the first row of each file as a header, left out of the
+  /// comparison.
+  #[arg(long)]
+  has_headers: bool,
+  /// The result block to extract from csv1 or csv2 when it's an .f06 file,
+  /// which is then compared as the table f06csv writes for that block; by
+  /// f06csv's name or number, e.g. disp, stress or 2.
+  #[arg(long, value_name = "BLOCK")]
+  f06_block: Option<CsvBlockId>,
+  /// Before the report, show how the headers differ, as text or json: the
+  /// columns only one file names, names out of order, and likely renames.
+  /// Differing headers are shown on stderr anyway when columns are paired
+  /// by position.
+  #[arg(
+    long,
+    value_name = "FORMAT",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = "text",
+    value_parser = ["text", "json"],
+    requires = "header_row",
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  diff_headers: Option<String>,
+  /// Show on stderr where each comparison's time went, as a table or as a
+  /// line of json: reading and decoding the files, parsing them, finding
+  /// their float columns, parsing the floats, comparing them, and
+  /// reporting. Parsing the floats and comparing them are estimated from
+  /// one row in 64.
+  #[arg(
+    long,
+    value_name = "FORMAT",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = "text",
+    value_parser = ["text", "json"],
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  profile_phases: Option<String>,
+  /// Also leave out rows identical to the header anywhere in the body, as
+  /// concatenating files with headers leaves them. Both files must repeat
+  /// their header at the same lines.
+  #[arg(long, requires = "header_row")]
+  dedup_headers: bool,
+  /// Let --dedup-headers remove repeated headers at different lines in each
+  /// file.
+  #[arg(long, requires = "dedup_headers")]
+  lenient_dedup: bool,
+  /// Stop comparing a pair after the row where N cells (default 1) have
+  /// exceeded a tolerance, and report on the rows up to it. It fails the
+  /// pair, and turns off --parallel.
+  #[arg(
+    long,
+    value_name = "N",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = "1",
+    value_parser = clap::value_parser!(u64).range(1..)
+  )]
+  fail_fast: Option<u64>,
+  /// Split each comparison among threads: "columns" gives each thread a
+  /// share of the float columns, which pays off for wide files. The report
+  /// is the same as without it.
+  #[arg(long, value_name = "MODE")]
+  parallel: Option<Parallel>,
+  /// How many threads --parallel uses (default: one per core).
+  #[arg(
+    long,
+    value_name = "N",
+    requires = "parallel",
+    value_parser = clap::value_parser!(u16).range(1..)
+  )]
+  threads: Option<u16>,
+  /// A TOML configuration file (default: f06csvdiff.toml, if there's one),
+  /// e.g. with [defaults] for options not given on the command line,
+  /// [columns.COL] tables of a column's own tolerances, tolerance profiles,
+  /// or an [assert] section of assertions about the files that fail the
+  /// comparison if they don't hold.
+  #[arg(long, value_name = "PATH")]
+  config: Option<PathBuf>,
+  /// Print the configuration in effect, the config file's merged with the
+  /// command line, as TOML, and exit.
+  #[arg(long)]
+  print_config: bool,
+  /// Evaluate these profiles from the config file in one pass and report a
+  /// verdict matrix instead of the usual report.
+  #[arg(
+    long,
+    value_name = "NAMES",
+    value_delimiter = ',',
+    requires = "config"
+  )]
+  profiles: Vec<String>,
+  /// The profile whose verdict sets the exit status (default: the first).
+  #[arg(long, value_name = "NAME", requires = "profiles")]
+  gate_profile: Option<String>,
+  /// Write the report to this file instead of stdout, truncating it.
+  /// Warnings and errors still go to stderr.
+  #[arg(short = 'o', long, value_name = "PATH")]
+  output: Option<PathBuf>,
+  /// Append to the --output file instead of truncating it, e.g. to collect
+  /// the result lines of many runs.
+  #[arg(long, requires = "output")]
+  append: bool,
+  /// Print statistics about the run to stderr, such as the peak memory use
+  /// (which needs the mem-stats feature).
+  #[arg(long)]
+  stats: bool,
+  /// After the report, show how many compared cells have relative
+  /// differences in each of a few logarithmic bins, or with --format json
+  /// give them in its object.
+  #[arg(long)]
+  histogram: bool,
+  /// The edges of the --histogram bins, in percent, ascending.
+  #[arg(
+    long,
+    value_name = "EDGES",
+    value_delimiter = ',',
+    default_value = "0.001,0.01,0.1,1,10",
+    requires = "histogram"
+  )]
+  histogram_bins: Vec<f64>,
+  /// After the report, show these percentiles of the compared cells'
+  /// relative differences (e.g. 50,95,99), leaving out the cells skipped
+  /// for being zero or below the threshold and those whose difference is
+  /// undefined, and counting both. They're exact up to 1048576 cells, and
+  /// within 0.5% of their true values past that, where the differences are
+  /// counted in buckets to bound the memory used. Also given by --format
+  /// json, and saved by --save-report.
+  #[arg(long, value_name = "P", value_delimiter = ',')]
+  percentiles: Vec<f64>,
+  /// Don't end the output with the F06CSVDIFF-RESULT line, which otherwise
+  /// sums up each pair (and in batch mode, the whole batch) in a fixed
+  /// format for log monitoring. It's left out by default when a --format
+  /// other than plain goes to stdout, and with -q.
+  #[arg(long)]
+  no_result_line: bool,
+  /// Append the run's results, per pair and per float column, to this
+  /// SQLite database, creating it if need be (see the query subcommand).
+  /// Needs the sqlite feature.
+  #[arg(long, value_name = "FILE")]
+  sqlite: Option<PathBuf>,
+  /// Exit with the error status if the peak memory use exceeds this many
+  /// bytes. Needs the mem-stats feature.
+  #[arg(long, value_name = "BYTES")]
+  max_peak_memory: Option<usize>,
+  /// Show where each column's worst differences occur along the rows, in
+  /// --explain, which it implies: the rows split into N equal segments
+  /// (default 10), and each column's worst in each segment drawn as a
+  /// sparkline.
+  #[arg(
+    long,
+    value_name = "N",
+    num_args = 0..=1,
+    require_equals = true,
+    default_missing_value = "10",
+    value_parser = clap::value_parser!(u16).range(1..)
+  )]
+  segments: Option<u16>,
+  /// Warn when a single line or column carries more than this fraction of
+  /// the total excess over a tolerance.
+  #[arg(long, value_name = "FRACTION", default_value = "0.5")]
+  dominance: f64,
+  /// Print no report, nor the notes and the command to reproduce it on
+  /// stderr; the exit status tells the verdict. Give it twice to also
+  /// silence errors and warnings on stderr.
+  #[arg(
+    short = 'q',
+    long,
+    action = clap::ArgAction::Count,
+    conflicts_with_all = ["explain", "align"]
+  )]
+  quiet: u8,
+  /// Show a progress bar on stderr, with the time left, while each file is
+  /// read and compared. It's only drawn when stderr is a terminal.
+  #[arg(long)]
+  progress: bool,
+  /// Compare the pairs of files listed in this manifest (see gen-manifest)
+  /// instead of csv1 and csv2. Its tolerances override the command line's,
+  /// except with --profiles.
+  #[arg(long, value_name = "PATH", conflicts_with_all = ["csv1", "csv2"])]
+  manifest: Option<PathBuf>,
+  /// In batch mode, take a quick look at every pair first (sampled lines,
+  /// identical files, sizes), and compare the likeliest to fail first. Each
+  /// pair is reported as it's done; the verdicts don't change, and a summary
+  /// in the usual order follows.
+  #[arg(long, conflicts_with_all = ["regression_gate", "expected_report"])]
+  triage: bool,
+  /// Report failures that a waiver in this TOML file covers as WAIVED
+  /// rather than FAILED, until it expires, and exit with 4 if waivers
+  /// excuse every failure: a warning, kept apart from --warn-ratio's 5
+  /// since the failure comes back when the waiver expires. Expired waivers
+  /// are ignored.
+  #[arg(
+    long,
+    value_name = "PATH",
+    conflicts_with_all = ["regression_gate", "expected_report", "scalars"]
+  )]
+  waivers: Option<PathBuf>,
+  /// Leave out the cells listed in this TOML file, e.g. singular-point
+  /// stresses investigated and accepted, instead of loosening the
+  /// tolerances of the whole file. Each names its line of csv1, or its
+  /// row's value in the file's key_column, and its column; the report
+  /// counts the cells left out.
+  #[arg(long, value_name = "PATH", conflicts_with = "scalars")]
+  exclude_cells: Option<PathBuf>,
+  /// Fail a pair if an exclusion of --exclude-cells applying to it left out
+  /// no cell, so that stale ones get cleaned up.
+  #[arg(long, requires = "exclude_cells")]
+  audit_exclusions: bool,
+  /// The first CSV. Globs are expanded here if the shell didn't do it; when
+  /// both arguments match several files, files are paired by their path
+  /// relative to each pattern's literal directory prefix.
+  #[arg(required_unless_present_any = ["manifest", "print_config"])]
+  csv1: Option<String>,
+  /// The second CSV. Globs are expanded as for csv1.
+  #[arg(required_unless_present_any = ["manifest", "print_config"])]
+  csv2: Option<String>,
 }
 
-fn align_text(text: &str, width: usize, alignment: &Alignment) -> String {
-  if text.len() >= width {
-    return text.to_string();
-  }
+#[derive(Subcommand)]
+enum Command {
+  /// Write a manifest pairing the files of two directory trees, the way
+  /// batch mode pairs glob matches.
+  GenManifest(GenManifestArgs),
+  /// Query a --sqlite database: worst-columns gives each run's worst column,
+  /// failures counts each pair's failed runs, and exceeded lists every
+  /// column result over --above. Needs the sqlite feature.
+  Query(QueryArgs),
+  /// Summarize the results of many runs, read from files of their
+  /// F06CSVDIFF-RESULT lines or --format csv rows: how many comparisons
+  /// passed, warned, were waived or failed, and the worst differences and
+  /// their pairs. Exits as a batch of them all would.
+  Aggregate(AggregateArgs),
+}
 
-  let padding = width - text.len();
-  match alignment {
-    Alignment::Left => format!("{text}{}", " ".repeat(padding)),
-    Alignment::Right => format!("{}{text}", " ".repeat(padding)),
-    Alignment::Center => {
-      let left_pad = padding / 2;
-      let right_pad = padding - left_pad;
-      format!("{}{text}{}", " ".repeat(left_pad), " ".repeat(right_pad))
-    }
-  }
+/// The options of how cells are read and measured, which comparisons share
+/// with gen-manifest's suggested tolerances.
+#[derive(clap::Args)]
+struct ComparisonArgs {
+  /// Skip cells where both values are below this in magnitude, or as
+  /// --threshold-mode has it.
+  #[arg(short = 't', long, value_name = "REAL", default_value = "0")]
+  threshold: f64,
+  /// Also skip cells where both values are below this fraction of their
+  /// column's largest magnitude, so that each column gets a threshold in its
+  /// own units. --explain shows the cutoffs.
+  #[arg(long, value_name = "F", default_value = "0")]
+  threshold_frac: f64,
+  /// Which values of a cell the threshold skips it for: both, only if both
+  /// are below it; either, if either is, so that 0.0 against 1.0E-12 noise
+  /// is skipped rather than infinitely off; or baseline, if csv1's is,
+  /// whatever csv2's. Cells zero in both files are skipped in any mode, and
+  /// skipped cells never reach --rel-floor or --max-zero-mismatch.
+  #[arg(long, value_name = "MODE", default_value = "both")]
+  threshold_mode: ThresholdMode,
+  /// Compare subnormal values (below 2.2E-308 in magnitude, as underflow
+  /// leaves them) as they are, instead of as exactly zero. Their ratios are
+  /// huge even when both are physically zero.
+  #[arg(long)]
+  no_clamp_subnormals: bool,
+  /// Round every value of both files to N significant digits, in decimal,
+  /// before comparing, e.g. 6 to compare a file printed with 13 digits with
+  /// one printed with 6 as if both were printed with 6. --explain also
+  /// shows the worst cells' values as printed.
+  #[arg(
+    long,
+    value_name = "N",
+    value_parser = clap::value_parser!(u32).range(1..=17),
+    conflicts_with = "interval"
+  )]
+  sig_digits: Option<u32>,
+  /// Take each value as the interval its printed precision allows, one unit
+  /// of its last digit wide, and measure cells between the nearest points of
+  /// their intervals: values whose intervals overlap count as equal. Suits
+  /// files printed with different precisions.
+  #[arg(long)]
+  interval: bool,
+  /// Multiply every value of csv1 by this factor before comparing, e.g.
+  /// 1000 to compare a file in m with one in mm. Must be positive.
+  #[arg(
+    long,
+    value_name = "REAL",
+    default_value = "1",
+    value_parser = positive_scale
+  )]
+  scale1: f64,
+  /// Multiply every value of csv2 by this factor before comparing.
+  #[arg(
+    long,
+    value_name = "REAL",
+    default_value = "1",
+    value_parser = positive_scale
+  )]
+  scale2: f64,
+  /// The field delimiter of both files.
+  #[arg(long, value_name = "CHAR", default_value = ",")]
+  delim: char,
+  /// How -r measures relative differences: ratio, max(|a|,|b|)/min(|a|,|b|)
+  /// less one; rel1, |a-b|/|a|, relative to csv1; rel2, |a-b|/|b|; or
+  /// symmetric, |a-b|/max(|a|,|b|).
+  #[arg(long, value_name = "MODE", default_value = "ratio")]
+  rel_mode: RelMode,
+  /// Divide relative differences by at least this much, so that a zero
+  /// against a negligible value passes -r instead of being infinitely off.
+  #[arg(long, value_name = "EPS", default_value = "0")]
+  rel_floor: f64,
 }
 
-fn truncate_and_align_text(
-  text: &str,
-  width: usize,
-  alignment: &Alignment,
-) -> String {
-  if width == 0 {
-    return String::new();
+impl ComparisonArgs {
+  /// The comparison configuration these options give, the rest left as
+  /// [`DiffConfig::default`] has it.
+  fn config(&self) -> Result<DiffConfig, Fatal> {
+    Ok(DiffConfig {
+      delimiter: delimiter(self.delim)?,
+      threshold: self.threshold,
+      threshold_frac: self.threshold_frac,
+      threshold_mode: self.threshold_mode,
+      rel_mode: self.rel_mode,
+      rel_floor: self.rel_floor,
+      clamp_subnormals: !self.no_clamp_subnormals,
+      sig_digits: self.sig_digits,
+      interval: self.interval,
+      scale: (self.scale1, self.scale2),
+      ..Default::default()
+    })
   }
+}
 
-  let truncated = if text.len() > width {
-    if width >= 3 {
-      format!("{}...", &text[..width - 3])
-    } else {
-      ".".repeat(width)
-    }
-  } else {
-    text.to_string()
-  };
+#[derive(clap::Args)]
+struct GenManifestArgs {
+  /// The pattern, relative to each directory, of the files to pair.
+  #[arg(long, value_name = "GLOB", default_value = "**/*.csv")]
+  glob: String,
+  /// Compare each pair once and fill in the tolerances it would just pass,
+  /// instead of placeholders.
+  #[arg(long)]
+  with_suggested_tolerances: bool,
+  #[command(flatten)]
+  comparison: ComparisonArgs,
+  /// Write the manifest here instead of stdout.
+  #[arg(short = 'o', long, value_name = "PATH")]
+  output: Option<PathBuf>,
+  /// The first directory tree.
+  dir1: String,
+  /// The second directory tree.
+  dir2: String,
+}
 
-  align_text(&truncated, width, alignment)
+#[derive(clap::Args)]
+struct QueryArgs {
+  /// Only this column, by header name or 1-based number (not for failures).
+  #[arg(long, value_name = "NAME")]
+  column: Option<String>,
+  /// The relative difference, in percent, that exceeded lists the column
+  /// results over.
+  #[arg(long, value_name = "PERCENT", default_value = "0")]
+  above: f64,
+  /// Show at most this many rows.
+  #[arg(long, value_name = "N")]
+  limit: Option<u32>,
+  /// The database.
+  database: PathBuf,
+  /// The query: worst-columns, failures, or exceeded.
+  query: history::Query,
 }
-fn format_aligned_output(
-  filenames: (&str, &str),
-  max_ratio_info: Option<(f64, (f64, f64), usize, bool)>,
-  max_diff_info: Option<(f64, (f64, f64), usize, bool)>,
-  alignment: &Alignment,
-  fixed_width: Option<usize>,
-  show_header: bool,
-) {
-  let mut rows = Vec::new();
-  let mut headers = vec!["csv1".to_string(), "csv2".to_string()];
-
-  let mut first_row = vec![filenames.0.to_string(), filenames.1.to_string()];
-  if let Some((ratio, (v1, v2), line, passed)) = max_ratio_info {
-    let percent = ((ratio - 1.0) * 100.0).abs();
-    first_row.extend([
-      format!("{percent:.2}"),
-      format!("{v1:+.6E}"),
-      format!("{v2:+.6E}"),
-      line.to_string(),
-      if passed {
-        "PASS".to_string()
-      } else {
-        "FAIL".to_string()
-      },
-    ]);
-    headers.extend(
-      ["ratio_%", "val1_r", "val2_r", "line_r", "status_r"]
-        .iter()
-        .map(|s| s.to_string()),
-    );
+
+/// Writes the --per-column table, indented under a pair's report.
+fn write_per_column(
+  out: &mut dyn Write,
+  report: &DiffReport,
+  tolerances: &Tolerances,
+  header: Option<&StringRecord>,
+  (painter, precision): (&Painter, Precision),
+) -> io::Result<()> {
+  let (headers, rows) = per_column_table(precision, report, tolerances, header);
+  let table =
+    align_table(&headers, &rows, &Alignment::Left, None, true, painter);
+  for line in table.lines() {
+    writeln!(out, "  {}", line.trim_end())?;
   }
+  Ok(())
+}
 
-  if let Some((diff, (v1, v2), line, passed)) = max_diff_info {
-    first_row.extend([
-      format!("{diff:.2E}"),
-      format!("{v1:+.6E}"),
-      format!("{v2:+.6E}"),
-      line.to_string(),
-      if passed {
-        "PASS".to_string()
-      } else {
-        "FAIL".to_string()
-      },
-    ]);
-    headers.extend(
-      ["abs_diff", "val1_d", "val2_d", "line_d", "status_d"]
-        .iter()
-        .map(|s| s.to_string()),
-    );
+/// The cell of a report nearest its row's absolute difference tolerance,
+/// scaled from -d, e.g. "line 12, column 4: difference 2.00E-2, allowed
+/// 5.00E-2 (scale 5.00E-1)".
+fn scaled_worst(
+  scaled: &ScaledDiff,
+  md: f64,
+  opts: &DiffConfig,
+  headers: Option<&(StringRecord, StringRecord)>,
+) -> String {
+  format!(
+    "line {}, {}: difference {:.2E}, allowed {:.2E} (scale {:.2E})",
+    scaled.worst.line,
+    column_label(opts, scaled.worst.col, headers),
+    scaled.diff,
+    md * scaled.scale,
+    scaled.scale
+  )
+}
+
+/// The cells of a comparison exceeding a tolerance, as --list-failures
+/// lists them: those of the columns with tolerances of their own too, held
+/// to their own, whose comparison is `whole` under them. The worst come
+/// first, up to `limit` of them if there's one.
+fn list_failures(
+  records: (&[StringRecord], &[StringRecord]),
+  (opts, whole): (&DiffConfig, &DiffConfig),
+  report: &DiffReport,
+  limit: Option<usize>,
+) -> Failures {
+  let mut found = failures::failing_cells(records, opts, report, limit);
+  for column in &report.overrides {
+    let col1 = column.col - 1;
+    let Some(col2) = whole.col2(col1) else {
+      continue;
+    };
+    let alone = overrides::alone(whole, (col1, col2), &column.tolerances);
+    let own = failures::failing_cells(records, &alone, &column.report, limit);
+    found.merge(own, limit);
   }
+  found
+}
 
-  rows.push(first_row);
+/// A cell exceeding a tolerance, e.g. "line 12, column 4: +1.000000E0 vs
+/// +1.500000E0, ratio 50.00%, abs diff 5.00E-1, exceeds ratio, diff".
+fn failing_cell(
+  cell: &FailingCell,
+  opts: &DiffConfig,
+  headers: Option<&(StringRecord, StringRecord)>,
+) -> String {
+  let gates: Vec<&str> = cell.gates.iter().map(|g| g.name()).collect();
+  format!(
+    "line {}, {}: {:+.6E} vs {:+.6E}, ratio {:.2}%, abs diff {:.2E}, \
+     exceeds {}",
+    cell.line,
+    column_label(opts, cell.col, headers),
+    cell.vals.0,
+    cell.vals.1,
+    cell.percent,
+    cell.diff,
+    gates.join(", ")
+  )
+}
 
-  // Calculate column widths
-  let col_widths: Vec<usize> = if let Some(fixed_width) = fixed_width {
-    // Use fixed width for all columns
-    vec![fixed_width; headers.len()]
-  } else {
-    // Calculate optimal width for each column
-    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
-    for row in &rows {
-      for (i, cell) in row.iter().enumerate() {
-        if i < widths.len() {
-          widths[i] = widths[i].max(cell.len());
-        }
-      }
-    }
-    widths
+/// What --list-failures found, e.g. "3 cell(s) of a.csv and b.csv exceed a
+/// tolerance, the worst 2 listed".
+fn failures_summary(failures: &Failures, (csv1, csv2): (&str, &str)) -> String {
+  let listed = match failures.cells.len() {
+    n if n < failures.total => format!(", the worst {n} listed"),
+    _ => String::new(),
   };
+  format!(
+    "{} cell(s) of {csv1} and {csv2} exceed a tolerance{listed}",
+    thousands(failures.total)
+  )
+}
 
-  // Print aligned output
-  if show_header {
-    let aligned_headers: Vec<String> = headers
-      .iter()
-      .zip(&col_widths)
-      .map(|(header, &width)| {
-        if fixed_width.is_some() {
-          truncate_and_align_text(header, width, alignment)
-        } else {
-          align_text(header, width, alignment)
-        }
-      })
-      .collect();
-    println!("{}", aligned_headers.join(" "));
+/// How many cells tie with a worst cell, e.g. "...and 41 other cells with
+/// the same difference", then where the first of them are, if any were
+/// located, and how many more there are; nothing without ties.
+fn tie_lines(
+  ties: &Ties,
+  worst: &Extremum,
+  opts: &DiffConfig,
+  headers: Option<&(StringRecord, StringRecord)>,
+) -> Vec<String> {
+  let others = ties.others();
+  let mut lines = match others {
+    0 => return Vec::new(),
+    1 => vec!["...and 1 other cell with the same difference".to_string()],
+    _ => vec![format!(
+      "...and {others} other cells with the same difference"
+    )],
+  };
+  let listed = ties.listed(worst);
+  for &(line, col) in &listed {
+    lines.push(format!(
+      "  line {line}, {}",
+      column_label(opts, col, headers)
+    ));
   }
-
-  for row in &rows {
-    let aligned_row: Vec<String> = row
-      .iter()
-      .zip(&col_widths)
-      .map(|(cell, &width)| {
-        if fixed_width.is_some() {
-          truncate_and_align_text(cell, width, alignment)
-        } else {
-          align_text(cell, width, alignment)
-        }
-      })
-      .collect();
-    println!("{}", aligned_row.join(" "));
+  if !listed.is_empty() && others > listed.len() {
+    lines.push(format!("  ... and {} more", others - listed.len()));
   }
+  lines
 }
 
-fn main() {
-  let args = Args::parse();
-  if args.max_diff.is_none() && args.max_ratio.is_none() {
-    eprintln!("Error: at least one of -d or -r must be specified.");
-    process::exit(1);
-  }
-
-  let delim = args.delim.try_into().unwrap();
-  let mut rdr1 = ReaderBuilder::new()
-    .has_headers(false)
-    .delimiter(delim)
-    .from_path(&args.csv1)
-    .unwrap_or_else(|e| {
-      eprintln!("Error opening {}: {}", &args.csv1, e);
-      process::exit(1)
-    });
-  let mut rdr2 = ReaderBuilder::new()
-    .has_headers(false)
-    .delimiter(delim)
-    .from_path(&args.csv2)
-    .unwrap_or_else(|e| {
-      eprintln!("Error opening {}: {}", &args.csv2, e);
-      process::exit(1)
-    });
+/// The cells of a report zero in one file only, e.g. "3 cell(s) zero in
+/// one file only, the largest nonzero value 5.000000E3 in magnitude (line
+/// 12, column 4)".
+fn zero_mismatches(
+  zeros: &ZeroMismatches,
+  opts: &DiffConfig,
+  headers: Option<&(StringRecord, StringRecord)>,
+) -> String {
+  let worst = &zeros.worst;
+  format!(
+    "{} cell(s) zero in one file only, the largest nonzero value {:.6E} in \
+     magnitude (line {}, {})",
+    zeros.cells,
+    worst.value,
+    worst.line,
+    column_label(opts, worst.col, headers)
+  )
+}
 
-  let float_re = Regex::new(r"[-+]?[0-9]*\.?[0-9]+[Ee][-+]?[0-9]+").unwrap();
+/// The cells of a report skipped by the threshold, by which values were
+/// below it, e.g. "skipped 5 cell(s) below the threshold (--threshold-mode
+/// either: 2 with both values below, 3 with csv1's only, 0 with csv2's
+/// only), and 7 zero in both files".
+fn skipped_summary(skipped: &SkippedCells, mode: ThresholdMode) -> String {
+  format!(
+    "skipped {} cell(s) below the threshold (--threshold-mode {}: {} with \
+     both values below, {} with csv1's only, {} with csv2's only), and {} \
+     zero in both files",
+    skipped.below(),
+    mode.name(),
+    skipped.both_below,
+    skipped.below1,
+    skipped.below2,
+    skipped.zeros
+  )
+}
 
-  // First pass: determine which columns contain only floats in both files
-  let mut float_columns: Option<Vec<bool>> = None;
+/// A column's sums and their verdict, e.g. "column 3 (T3):
+/// sum1=+1.000000E5, sum2=+1.000012E5, 0.00%, PASS".
+fn column_sum(
+  sum: &ColumnSum,
+  status: &str,
+  opts: &DiffConfig,
+  headers: Option<&(StringRecord, StringRecord)>,
+) -> String {
+  format!(
+    "{}: sum1={:+.6E}, sum2={:+.6E}, {:.2}%, {status}",
+    column_label(opts, sum.col, headers),
+    sum.sums.0,
+    sum.sums.1,
+    sum.percent
+  )
+}
 
-  // Read all records to determine float columns
-  let records1: Vec<_> = rdr1
-    .records()
-    .collect::<Result<Vec<_>, _>>()
-    .unwrap_or_else(|e| {
-      eprintln!("Error reading {}: {}", &args.csv1, e);
-      process::exit(1);
-    });
-  let records2: Vec<_> = rdr2
-    .records()
-    .collect::<Result<Vec<_>, _>>()
-    .unwrap_or_else(|e| {
-      eprintln!("Error reading {}: {}", &args.csv2, e);
-      process::exit(1);
-    });
+/// The columns of a report left out for a cell that isn't a float, e.g.
+/// "compared 14 of 22 columns; columns 3, 7, 19 excluded (first non-float
+/// at lines 2, 2, 4481)".
+fn demoted_summary(report: &DiffReport, demoted: &[DemotedColumn]) -> String {
+  let compared = report.compared_columns();
+  let list = |field: &dyn Fn(&DemotedColumn) -> usize| {
+    let fields: Vec<String> =
+      demoted.iter().map(|d| field(d).to_string()).collect();
+    fields.join(", ")
+  };
+  let (column, line) = match demoted.len() {
+    1 => ("column", "line"),
+    _ => ("columns", "lines"),
+  };
+  format!(
+    "compared {compared} of {} columns; {column} {} excluded (first \
+     non-float at {line} {})",
+    compared + demoted.len(),
+    list(&|d| d.col),
+    list(&|d| d.line)
+  )
+}
 
-  if records1.len() != records2.len() {
-    eprintln!(
-      "Error: files have different number of rows ({} vs {})",
-      records1.len(),
-      records2.len()
-    );
-    process::exit(1);
+/// The requirements on a report's compared columns it doesn't meet.
+fn column_failures(args: &Args, report: &DiffReport) -> Vec<String> {
+  let mut failures = Vec::new();
+  if args.require_all_float_columns {
+    let stray = report.demoted_columns().into_iter();
+    for column in stray.filter(|d| d.floats_before > 0) {
+      failures.push(format!(
+        "--require-all-float-columns: column {} has {:?} at line {}, after \
+         {} row(s) of floats",
+        column.col, column.cell, column.line, column.floats_before
+      ));
+    }
   }
+  let compared = report.compared_columns();
+  if let Some(min) = args.min_compared_columns
+    && compared < min
+  {
+    failures.push(format!(
+      "--min-compared-columns {min}: only {compared} column(s) compared"
+    ));
+  }
+  failures
+}
 
-  for (line_num, (rec1, rec2)) in records1.iter().zip(&records2).enumerate() {
-    let line_num = line_num + 1;
+/// Writes a row of --format csv, quoted as needed.
+fn write_csv(out: &mut dyn Write, row: &[String]) -> io::Result<()> {
+  let mut writer = csv::Writer::from_writer(out);
+  writer.write_record(row)?;
+  writer.flush()
+}
 
-    // Column count check
-    let len1 = rec1.len();
-    let len2 = rec2.len();
-    if len1 != len2 {
-      eprintln!(
-        "Error: column count differs at line {}: {} has {}, {} has {}",
-        line_num, &args.csv1, len1, &args.csv2, len2
-      );
-      process::exit(1);
+/// The checks besides the gates the options and the tolerance spec's
+/// assertions made of a pair, in [`Check::ALL`]'s order, with any the
+/// report failed anyway.
+fn checks_made(
+  args: &Args,
+  assertions: &Assertions,
+  report: &DiffReport,
+) -> Vec<Check> {
+  let made = |check| match check {
+    Check::Sums => report.sum_check.enabled(),
+    Check::Annotations => args.compare_annotations,
+    Check::Assertions => !assertions.is_empty(),
+    Check::Complex => report.complex.is_some(),
+    Check::UnpairedRows => {
+      args.match_rows_nearest.is_some() || args.key_col.is_some()
     }
-
-    // Initialize float_columns on first row
-    if float_columns.is_none() {
-      float_columns = Some(vec![true; len1]);
+    Check::UnpairedColumns => args.mac_match,
+    Check::Groups => args.group_by.is_some(),
+    Check::Overrides => !report.overrides.is_empty(),
+    Check::Matrix => report.matrix.is_some(),
+    Check::Exclusions => args.audit_exclusions,
+    Check::Structure => report.structure == Structure::Lenient,
+    Check::ZeroMismatches => report.zero_mismatches.limit.is_some(),
+    Check::Columns => {
+      args.require_all_float_columns || args.min_compared_columns.is_some()
     }
+  };
+  Check::ALL
+    .into_iter()
+    .filter(|&check| made(check) || !report.check_passed(check))
+    .collect()
+}
 
-    let float_cols = float_columns.as_mut().unwrap();
+/// Describes a concentration of violations, naming a line by its first cell
+/// when that isn't a compared float (e.g. an element ID).
+fn dominance_note(
+  dominance: &Dominance,
+  records: &[StringRecord],
+  report: &DiffReport,
+) -> String {
+  let (gate, place, share) = match *dominance {
+    Dominance::Line(gate, line, share) => {
+      let label = (0..records.len())
+        .find(|&row| physical_line(records, row) == line)
+        .and_then(|row| records[row].get(0))
+        .filter(|_| report.columns.first().is_none_or(|c| c.col != 1))
+        .map(|cell| format!(" ({})", cell.trim()))
+        .unwrap_or_default();
+      (gate, format!("line {line}{label}"), share)
+    }
+    Dominance::Column(gate, col, share) => {
+      (gate, format!("column {col}"), share)
+    }
+  };
+  format!(
+    "{:.0}% of the {} violation magnitude comes from {place}",
+    share * 100.0,
+    gate.name()
+  )
+}
 
-    // Check each column to see if it's a float in both files
-    for (i, (cell1, cell2)) in rec1.iter().zip(rec2.iter()).enumerate() {
-      if float_cols[i] {
-        let is_float1 =
-          float_re.is_match(cell1) && cell1.parse::<f64>().is_ok();
-        let is_float2 =
-          float_re.is_match(cell2) && cell2.parse::<f64>().is_ok();
-        if !is_float1 || !is_float2 {
-          float_cols[i] = false;
-        }
+fn main() {
+  let started = Instant::now();
+  let matches = Args::command().get_matches();
+  let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+  percent_to_ratio(&mut args);
+  args.explain |= args.show_rows || args.segments.is_some();
+  args.has_headers |= args.headers;
+  args.match_by_header |= args.headers;
+  if args.quiet >= 1 {
+    exit::quiet();
+  }
+  if args.quiet >= 2 {
+    exit::silence();
+  }
+  if args.progress {
+    bar::start();
+  }
+  if let Some(Command::GenManifest(gen_args)) = &args.command {
+    gen_manifest(gen_args);
+    return;
+  }
+  if let Some(Command::Query(query_args)) = &args.command {
+    query(query_args);
+    return;
+  }
+  if let Some(Command::Aggregate(aggregate_args)) = &args.command {
+    aggregate(aggregate_args);
+    return;
+  }
+  // an explicit --config has to exist, the default one needn't
+  let config_path = args.config.clone().or_else(|| {
+    let path = PathBuf::from(config::DEFAULT_PATH);
+    path.is_file().then_some(path)
+  });
+  let config = match &config_path {
+    Some(path) => Config::load(path).unwrap_or_else(|e| {
+      exit::die(format!("Error: {e}"));
+    }),
+    None => Config::default(),
+  };
+  let taken = apply_defaults(&mut args, &matches, &config.defaults)
+    .unwrap_or_else(|e| {
+      // config_path is set, or there would be no defaults
+      let path = config_path.as_deref().unwrap_or(Path::new(""));
+      exit::die(format!("Error: bad config file {}: {e}", path.display()))
+    });
+  if args.print_config {
+    print_config(&args, &config, config_path.as_deref(), &taken);
+    return;
+  }
+  // directional tolerances refine the symmetric ones, which must be there
+  if (args.max_ratio_over.is_some() || args.max_ratio_under.is_some())
+    && args.max_ratio.is_none()
+  {
+    exit::die(
+      "Error: --max-ratio-over and --max-ratio-under need -r or --max-percent",
+    );
+  }
+  if (args.max_diff_over.is_some() || args.max_diff_under.is_some())
+    && args.max_diff.is_none()
+  {
+    exit::die("Error: --max-diff-over and --max-diff-under need -d");
+  }
+  if args.allclose && args.max_diff.is_none() && args.max_ratio.is_none() {
+    exit::die("Error: --allclose needs -d, -r or --max-percent");
+  }
+  if !config.columns.is_empty()
+    && (!args.profiles.is_empty()
+      || args.regression_gate.is_some()
+      || args.expected_report.is_some()
+      || args.scalars)
+  {
+    exit::die(
+      "Error: the config file's [columns] tolerances can't be used with \
+       --profiles, --regression-gate, --expected-report or --scalars",
+    );
+  }
+  if args.fail_only_high_impact
+    && config
+      .columns
+      .values()
+      .all(|spec| spec.significance.is_none())
+  {
+    exit::die(
+      "Error: --fail-only-high-impact needs the config file's [columns] \
+       tables to give some column a significance",
+    );
+  }
+  if let Some((col, _)) = config.columns.iter().find(|(_, spec)| {
+    spec
+      .significance
+      .is_some_and(|s| !(s.is_finite() && s > 0.0))
+  }) {
+    exit::die(format!(
+      "Error: the config file's [columns.{col}] significance must be \
+       positive"
+    ));
+  }
+  let manifest = args.manifest.as_ref().map(|path| {
+    Manifest::load(path).unwrap_or_else(|e| exit::die(format!("Error: {e}")))
+  });
+  let command_line = command_line_tolerances(&args);
+  // a manifest may give every pair its own tolerances
+  let manifest_suffices = manifest.as_ref().is_some_and(|m| {
+    m.pair
+      .iter()
+      .all(|e| !e.tolerances(&command_line).gates().is_empty())
+  });
+  let waivers: Vec<Waiver> = match &args.waivers {
+    Some(path) => {
+      let waivers = Waivers::load(path)
+        .unwrap_or_else(|e| exit::die(format!("Error: {e}")));
+      let today = waivers::today();
+      let (expired, active) =
+        waivers.waiver.into_iter().partition(|w| w.expired(today));
+      let expired: Vec<Waiver> = expired;
+      for waiver in &expired {
+        exit::notice(format!(
+          "WARNING: IGNORING EXPIRED WAIVER {} ({}), which expired on {}",
+          waiver.ticket,
+          waiver.reason,
+          waiver.expiry()
+        ));
       }
+      active
     }
+    None => Vec::new(),
+  };
+  let exclusions = args.exclude_cells.as_ref().map(|path| {
+    Exclusions::load(path).unwrap_or_else(|e| exit::die(format!("Error: {e}")))
+  });
+  // as may the config file every named scalar
+  let scalars_suffice = args.scalars && !config.scalars.is_empty();
+  if command_line.gates().is_empty()
+    && !scalars_suffice
+    && args.profiles.is_empty()
+    && !manifest_suffices
+    && args.regression_gate.is_none()
+    && args.expected_report.is_none()
+    && args.save_report.is_none()
+    && args.max_matrix_rel.is_none()
+  {
+    exit::die(
+      "Error: at least one of -d, -r, --max-percent, --min-digits, \
+       --profiles, --regression-gate, --expected-report, --save-report or \
+       --max-matrix-rel must be specified.",
+    );
   }
 
-  let float_cols = float_columns.unwrap_or_default();
-
-  // Track maxima for reporting
-  let mut max_abs_diff = 0.0;
-  let mut max_abs_vals = (0.0, 0.0);
-  let mut max_diff_line = 0;
-  let mut max_ratio = 1.0; // Initialize to 1.0 (no difference)
-  let mut max_ratio_vals = (0.0, 0.0);
-  let mut max_ratio_line = 0;
+  let profiles = config.profiles(&args.profiles).unwrap_or_else(|e| {
+    exit::die(format!("Error: {e}"));
+  });
+  if args.format != Format::Plain && (args.explain || args.align.is_some()) {
+    exit::die("Error: --format can't be used with --explain or --align");
+  }
+  if args.csv_header && args.format != Format::Csv {
+    exit::die("Error: --csv-header needs --format csv");
+  }
+  if !matches!(args.format, Format::Plain | Format::Json) && args.histogram {
+    exit::die(format!(
+      "Error: --format {} can't be used with --histogram",
+      args.format.name()
+    ));
+  }
+  if args.histogram_bins.iter().any(|e| !e.is_finite())
+    || args.histogram_bins.windows(2).any(|w| w[0] >= w[1])
+  {
+    exit::die("Error: --histogram-bins must be finite and ascending");
+  }
+  if !matches!(args.format, Format::Plain | Format::Json)
+    && !args.percentiles.is_empty()
+  {
+    exit::die(format!(
+      "Error: --format {} can't be used with --percentiles",
+      args.format.name()
+    ));
+  }
+  if args.percentiles.iter().any(|p| !(*p > 0.0 && *p <= 100.0)) {
+    exit::die("Error: --percentiles must be above 0 and at most 100");
+  }
+  if args
+    .max_phase_deg
+    .is_some_and(|d| !(0.0..=180.0).contains(&d))
+  {
+    exit::die("Error: --max-phase-deg must be from 0 to 180");
+  }
+  if args.max_matrix_rel.is_some_and(|f| f.is_nan() || f < 0.0) {
+    exit::die("Error: --max-matrix-rel must be at least 0");
+  }
+  if !(0.0..=1.0).contains(&args.mac_threshold) {
+    exit::die("Error: --mac-threshold must be from 0 to 1");
+  }
+  if args.max_peak_memory.is_some() && memstats::peak().is_none() {
+    exit::die("Error: --max-peak-memory needs the mem-stats feature");
+  }
+  if let Some(gate) = &args.gate_profile
+    && !args.profiles.contains(gate)
+  {
+    exit::die(format!(
+      "Error: gate profile {gate} is not one of --profiles"
+    ));
+  }
 
-  // Second pass: compare float values
-  for (line_num, (rec1, rec2)) in records1.iter().zip(&records2).enumerate() {
-    let line_num = line_num + 1;
+  let (mut out, to_terminal): (Box<dyn Write>, bool) = match &args.output {
+    Some(path) => {
+      let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(args.append)
+        .truncate(!args.append)
+        .open(path)
+        .unwrap_or_else(|e| {
+          exit::die(format!("Error opening {}: {e}", path.display()))
+        });
+      (Box::new(BufWriter::new(file)), false)
+    }
+    None => (Box::new(io::stdout().lock()), io::stdout().is_terminal()),
+  };
+  let painter = Painter::new(args.color, to_terminal);
+  let mut history = args.sqlite.as_ref().map(|path| {
+    let command_line: Vec<String> = std::env::args().collect();
+    History::open(path, &command_line.join(" "))
+      .unwrap_or_else(|e| exit::die(format!("Error: {e}")))
+  });
+  if let Some(margin) = args.regression_gate {
+    let gate = RegressionGate {
+      margin,
+      floor: args.regression_floor,
+    };
+    let passed = regression(&args, &gate, painter, &mut out)
+      .and_then(|passed| Ok(out.flush().map(|()| passed)?))
+      .unwrap_or_else(|e| e.die());
+    let csv2 = args.csv2.as_deref().unwrap_or_default();
+    let old = args.old.as_deref().unwrap_or_default();
+    result_line(
+      &args,
+      &ResultLine {
+        pair: Some((csv2.to_string(), old.to_string())),
+        passed,
+        elapsed: started.elapsed(),
+        ..Default::default()
+      },
+    );
+    process::exit(if passed { 0 } else { exit::FAILED });
+  }
+  if let Some(path) = &args.expected_report {
+    let gate = RegressionGate {
+      margin: args.report_margin,
+      floor: 0.0,
+    };
+    let passed = expected(&args, path, &gate, painter, &mut out)
+      .and_then(|passed| Ok(out.flush().map(|()| passed)?))
+      .unwrap_or_else(|e| e.die());
+    let csv1 = args.csv1.as_deref().unwrap_or_default();
+    let csv2 = args.csv2.as_deref().unwrap_or_default();
+    result_line(
+      &args,
+      &ResultLine {
+        pair: Some((csv1.to_string(), csv2.to_string())),
+        passed,
+        elapsed: started.elapsed(),
+        ..Default::default()
+      },
+    );
+    process::exit(if passed { 0 } else { exit::FAILED });
+  }
+  let junit = args.format == Format::Junit && args.quiet == 0;
+  if junit {
+    writeln!(out, "{}", junit::HEADER)
+      .unwrap_or_else(|e| exit::die(format!("Error writing the report: {e}")));
+  }
+  if args.csv_header && args.quiet == 0 {
+    write_csv(&mut out, &CSV_FIELDS.map(String::from))
+      .unwrap_or_else(|e| exit::die(format!("Error writing the report: {e}")));
+  }
+  // in profile mode, the gate profile's tolerances decide the exit status
+  let tolerances = match profiles.first() {
+    Some((first, _)) => {
+      let gate_name = args.gate_profile.as_ref().unwrap_or(first);
+      profiles
+        .iter()
+        .find(|(name, _)| name == gate_name)
+        .map(|(_, tol)| tol.clone())
+        .unwrap_or_default()
+    }
+    None => command_line.clone(),
+  };
+  // Markdown rows of every pair go in one table, while the headers last
+  let mut table_headers = None;
+  // the waivers excusing each waived pair, for the summary
+  let mut waived = Vec::new();
+  let mut run = |csv1: &str, csv2: &str, tolerances: &Tolerances| {
+    let headers = &mut table_headers;
+    let start = Instant::now();
+    if args.scalars {
+      let line = scalars(
+        &args,
+        tolerances,
+        &config.scalars,
+        painter,
+        &mut out,
+        csv1,
+        csv2,
+      )
+      .unwrap_or_else(|e| e.die());
+      let line = ResultLine {
+        elapsed: start.elapsed(),
+        ..line
+      };
+      result_line(&args, &line);
+      return line;
+    }
+    let mut phases = PhaseTimes::default();
+    let (report, excusing) = run(
+      &args,
+      &profiles,
+      tolerances,
+      (&config.assert, &config.columns),
+      (&waivers, exclusions.as_ref()),
+      painter,
+      &mut out,
+      headers,
+      history.as_mut(),
+      csv1,
+      csv2,
+      &mut phases,
+    )
+    .unwrap_or_else(|e| e.die());
+    if let Some(format) = &args.profile_phases {
+      let reporting = start.elapsed().saturating_sub(phases.total());
+      phases.add(Phase::Reporting, reporting);
+      write_profile(format, (csv1, csv2), &phases);
+    }
+    let line = ResultLine {
+      waived: usize::from(!excusing.is_empty()),
+      waivers: excusing.iter().map(|w| w.ticket.clone()).collect(),
+      ..ResultLine::of_pair((csv1, csv2), &report, start.elapsed())
+    };
+    result_line(&args, &line);
+    if !excusing.is_empty() {
+      waived.push((csv1.to_string(), csv2.to_string(), excusing));
+    }
+    line
+  };
 
-    // Extract floats from float columns only
-    let f1: Vec<(usize, f64)> = rec1
+  let mut batch = manifest.is_some();
+  let pairs: Vec<(String, String, Tolerances)> = match &manifest {
+    Some(manifest) => manifest
+      .pair
       .iter()
-      .enumerate()
-      .filter_map(|(i, f)| {
-        if float_cols[i] && float_re.is_match(f) {
-          match f.parse() {
-            Ok(v) => Some((i, v)),
-            Err(_) => {
-              eprintln!(
-                "Error parsing '{}' in {} at line {}",
-                f, &args.csv1, line_num
-              );
-              process::exit(1);
-            }
-          }
+      .map(|entry| {
+        let tolerances = if profiles.is_empty() {
+          entry.tolerances(&command_line)
         } else {
-          None
-        }
+          tolerances.clone()
+        };
+        (entry.csv1.clone(), entry.csv2.clone(), tolerances)
       })
-      .collect();
-    let f2: Vec<(usize, f64)> = rec2
-      .iter()
-      .enumerate()
-      .filter_map(|(i, f)| {
-        if float_cols[i] && float_re.is_match(f) {
-          match f.parse() {
-            Ok(v) => Some((i, v)),
-            Err(_) => {
-              eprintln!(
-                "Error parsing '{}' in {} at line {}",
-                f, &args.csv2, line_num
-              );
-              process::exit(1);
-            }
+      .collect(),
+    None => {
+      let csv1 = args.csv1.as_deref().unwrap_or_default();
+      let csv2 = args.csv2.as_deref().unwrap_or_default();
+      match inputs::resolve(csv1, csv2) {
+        Ok(Inputs::Single(csv1, csv2)) => vec![(csv1, csv2, tolerances)],
+        Ok(Inputs::Batch {
+          pairs,
+          unpaired1,
+          unpaired2,
+        }) => {
+          batch = true;
+          for path in &unpaired1 {
+            exit::notice(format!(
+              "warning: {path} has no counterpart in {csv2}"
+            ));
           }
-        } else {
-          None
+          for path in &unpaired2 {
+            exit::notice(format!(
+              "warning: {path} has no counterpart in {csv1}"
+            ));
+          }
+          pairs
+            .into_iter()
+            .map(|(csv1, csv2)| (csv1, csv2, tolerances.clone()))
+            .collect()
         }
-      })
-      .collect();
-
-    if f1.is_empty() && f2.is_empty() {
-      continue;
+        Err(e) => exit::die(format!("Error: {e}")),
+      }
+    }
+  };
+  for (given, flag) in [
+    (args.save_report.is_some(), "--save-report"),
+    (args.write_diff_csv.is_some(), "--write-diff-csv"),
+    (args.label1.is_some(), "--label1"),
+    (args.label2.is_some(), "--label2"),
+  ] {
+    if given && pairs.len() != 1 {
+      exit::die(format!("Error: {flag} needs a single pair of files"));
+    }
+  }
+  let order = match args.triage && batch {
+    true => {
+      let delimiter =
+        delimiter(args.comparison.delim).unwrap_or_else(|e| e.die());
+      let suspicions: Vec<f64> = pairs
+        .iter()
+        .map(|(csv1, csv2, _)| triage::suspicion((csv1, csv2), delimiter))
+        .collect();
+      triage::order(&suspicions)
+    }
+    false => (0..pairs.len()).collect(),
+  };
+  // every pair is compared and reported, even after a failure
+  let mut results: Vec<(usize, ResultLine)> = order
+    .into_iter()
+    .map(|i| {
+      let (csv1, csv2, tolerances) = &pairs[i];
+      (i, run(csv1, csv2, tolerances))
+    })
+    .collect();
+  results.sort_by_key(|(i, _)| *i);
+  let results: Vec<ResultLine> =
+    results.into_iter().map(|(_, line)| line).collect();
+  if args.triage && batch {
+    exit::notice("summary, in pair order:");
+    for (line, (csv1, csv2, _)) in results.iter().zip(&pairs) {
+      exit::notice(format!("  {} {csv1} {csv2}", line.status()));
+    }
+  }
+  let passed = results.iter().all(|line| line.passed);
+  let excused = results.iter().all(|line| line.passed || line.waived > 0);
+  for (csv1, csv2, excusing) in &waived {
+    for waiver in excusing {
+      exit::notice(format!(
+        "waived: failure of {csv1} vs {csv2}, by {}",
+        waiver.describe()
+      ));
+    }
+  }
+  if let Some(history) = &mut history {
+    history
+      .finish(passed)
+      .unwrap_or_else(|e| exit::die(format!("Error recording the run: {e}")));
+  }
+  if junit {
+    writeln!(out, "{}", junit::FOOTER)
+      .unwrap_or_else(|e| exit::die(format!("Error writing the report: {e}")));
+  }
+  if let Err(e) = out.flush() {
+    exit::die(format!("Error writing the report: {e}"));
+  }
+  let aggregate =
+    batch.then(|| ResultLine::aggregate(&results, started.elapsed()));
+  if let Some(aggregate) = &aggregate {
+    result_line(&args, aggregate);
+  }
+  let labels: Vec<String> = pairs
+    .iter()
+    .map(|(csv1, csv2, _)| {
+      let (name1, name2) = report_names(&args, (csv1, csv2));
+      format!("{name1} vs {name2}")
+    })
+    .collect();
+  for (path, extension) in [(&args.badge, "svg"), (&args.badge_json, "json")] {
+    if let Some(path) = path {
+      write_badges(path, extension, &labels, &results, aggregate.as_ref());
     }
-    if f1.len() != f2.len() {
-      eprintln!("Error: float layout differs at line {line_num}");
-      process::exit(1);
+  }
+  let peak = memstats::peak();
+  if args.stats {
+    match peak {
+      Some(bytes) => exit::notice(format!("stats: peak memory {bytes} bytes")),
+      None => exit::notice("stats: peak memory not tracked (no mem-stats)"),
     }
+  }
+  if let (Some(max), Some(bytes)) = (args.max_peak_memory, peak)
+    && bytes > max
+  {
+    exit::die(format!(
+      "Error: peak memory use of {bytes} bytes exceeds the budget of {max}"
+    ));
+  }
+  if !passed {
+    process::exit(if excused { exit::WAIVED } else { exit::FAILED });
+  }
+  if results.iter().any(|line| line.warned) {
+    process::exit(exit::WARNED);
+  }
+}
 
-    // Compare
-    for ((_, v1), (_, v2)) in f1.iter().zip(&f2) {
-      let a1 = *v1;
-      let a2 = *v2;
-      if a1 == 0.0 && a2 == 0.0 {
-        continue;
-      }
-      if a1.abs() < args.threshold && a2.abs() < args.threshold {
-        continue;
-      }
+#[derive(clap::Args)]
+struct AggregateArgs {
+  /// How to write the summary: plain, markdown, or json.
+  #[arg(
+    long,
+    value_name = "FORMAT",
+    default_value = "plain",
+    value_parser = ["plain", "markdown", "json"]
+  )]
+  format: String,
+  /// The files of result lines.
+  #[arg(required = true)]
+  results: Vec<PathBuf>,
+}
 
-      // Check abs diff
-      let diff = (a1 - a2).abs();
-      if diff > max_abs_diff {
-        max_abs_diff = diff;
-        max_abs_vals = (a1, a2);
-        max_diff_line = line_num;
-      }
+/// Prints the summary of the aggregate subcommand and exits with the status
+/// a batch of its comparisons would.
+fn aggregate(args: &AggregateArgs) {
+  let mut lines = Vec::new();
+  for path in &args.results {
+    let read = aggregate::read(path)
+      .unwrap_or_else(|e| exit::die(format!("Error: {e}")));
+    lines.extend(read);
+  }
+  let summary = Summary::of(&lines);
+  let mut out = io::stdout().lock();
+  write_summary(&mut out, &args.format, &summary)
+    .unwrap_or_else(|e| exit::die(format!("Error: {e}")));
+  if summary.failed > 0 {
+    process::exit(exit::FAILED);
+  }
+  if summary.waived > 0 {
+    process::exit(exit::WAIVED);
+  }
+  if summary.warned > 0 {
+    process::exit(exit::WARNED);
+  }
+}
 
-      // Check ratio
-      let ratio = if a1 == 0.0 || a2 == 0.0 {
-        f64::INFINITY
-      } else {
-        a1.abs().max(a2.abs()) / a1.abs().min(a2.abs())
+/// Writes the aggregate subcommand's summary in a --format of it.
+fn write_summary(
+  out: &mut dyn Write,
+  format: &str,
+  summary: &Summary,
+) -> io::Result<()> {
+  let worst = |worst: &Option<aggregate::Worst>,
+               text: &dyn Fn(f64) -> String| {
+    let pair = |w: &aggregate::Worst| format!("{} vs {}", w.file1, w.file2);
+    worst.as_ref().map(|w| (text(w.value), pair(w)))
+  };
+  let ratio = worst(&summary.worst_ratio_pct, &|v| format!("{v:.2}%"));
+  let diff = worst(&summary.worst_diff, &|v| format!("{v:.2E}"));
+  match format {
+    "json" => {
+      writeln!(out, "{}", serde_json::to_string_pretty(summary).unwrap())
+    }
+    "markdown" => {
+      let headers: Vec<String> = [
+        "comparisons",
+        "passed",
+        "warned",
+        "waived",
+        "failed",
+        "worst %",
+        "worst % pair",
+        "worst diff",
+        "worst diff pair",
+      ]
+      .iter()
+      .map(|h| h.to_string())
+      .collect();
+      let count = |n: usize, paint| match n {
+        0 => (n.to_string(), Paint::Plain),
+        _ => (n.to_string(), paint),
       };
-      if ratio > max_ratio {
-        max_ratio = ratio;
-        max_ratio_vals = (a1, a2);
-        max_ratio_line = line_num;
+      let mut row = vec![
+        (summary.comparisons.to_string(), Paint::Plain),
+        (summary.passed.to_string(), Paint::Plain),
+        count(summary.warned, Paint::Warned),
+        count(summary.waived, Paint::Waived),
+        count(summary.failed, Paint::Fail),
+      ];
+      for worst in [ratio, diff] {
+        let (value, pair) = worst.unwrap_or(("-".into(), "-".into()));
+        row.extend([(value, Paint::Plain), (pair, Paint::Plain)]);
+      }
+      writeln!(out, "{}", markdown::header(&headers))?;
+      writeln!(out, "{}", markdown::row(&row))
+    }
+    _ => {
+      writeln!(
+        out,
+        "{} of {} comparisons failed ({} passed, {} warned, {} waived)",
+        summary.failed,
+        summary.comparisons,
+        summary.passed,
+        summary.warned,
+        summary.waived
+      )?;
+      for (name, worst) in [("percent", ratio), ("absolute", diff)] {
+        if let Some((value, pair)) = worst {
+          writeln!(out, "worst {name} difference: {value} ({pair})")?;
+        }
       }
+      Ok(())
     }
   }
+}
 
-  let pb1 = PathBuf::from(&args.csv1);
-  let pb2 = PathBuf::from(&args.csv2);
-  let bn1 = pb1
-    .file_name()
-    .map(|s| s.to_string_lossy())
-    .unwrap_or(std::borrow::Cow::Borrowed("<?>"));
-  let bn2 = pb2
-    .file_name()
-    .map(|s| s.to_string_lossy())
-    .unwrap_or(std::borrow::Cow::Borrowed("<?>"));
+/// Prints the result of the query subcommand.
+fn query(args: &QueryArgs) {
+  let filter = history::Filter {
+    column: args.column.clone(),
+    above: args.above,
+    limit: args.limit,
+  };
+  let (headers, rows) = history::query(&args.database, args.query, &filter)
+    .unwrap_or_else(|e| exit::die(format!("Error: {e}")));
+  let rows: Vec<Vec<Cell>> = rows
+    .into_iter()
+    .map(|row| row.into_iter().map(|v| (v, Paint::Plain)).collect())
+    .collect();
+  let painter = Painter::new(ColorChoice::Never, false);
+  let table =
+    align_table(&headers, &rows, &Alignment::Left, None, true, &painter);
+  for line in table.lines() {
+    println!("{}", line.trim_end());
+  }
+}
 
-  // Report
-  if args.explain {
-    println!("files: {bn1} and {bn2}\n");
-    if let Some(mr) = args.max_ratio {
-      let percentage_diff = ((max_ratio - 1.0) * 100.0).abs();
-      println!("maximum percent difference seen: {percentage_diff:.2}%",);
-      println!(
-        "the values: {:+.6E} and {:+.6E} (line {})",
-        max_ratio_vals.0, max_ratio_vals.1, max_ratio_line
-      );
-      let status = if percentage_diff > mr * 100.0 {
-        "FAIL"
-      } else {
-        "PASS"
+/// Writes a manifest for the gen-manifest subcommand.
+fn gen_manifest(args: &GenManifestArgs) {
+  let pattern =
+    |dir: &str| format!("{}/{}", dir.trim_end_matches('/'), args.glob);
+  let (pairs, unpaired) =
+    match inputs::resolve(&pattern(&args.dir1), &pattern(&args.dir2)) {
+      Ok(Inputs::Single(csv1, csv2)) => (vec![(csv1, csv2)], Vec::new()),
+      Ok(Inputs::Batch {
+        pairs,
+        unpaired1,
+        unpaired2,
+      }) => (pairs, [unpaired1, unpaired2].concat()),
+      Err(e) => exit::die(format!("Error: {e}")),
+    };
+  let pairs: Vec<_> = pairs
+    .into_iter()
+    .map(|(csv1, csv2)| {
+      let suggested = args.with_suggested_tolerances.then(|| {
+        let config = args.comparison.config().unwrap_or_else(|e| e.die());
+        let report = f06csvdiff::compare_files(&csv1, &csv2, &config)
+          .unwrap_or_else(|e| {
+            exit::die(format!("Error comparing {csv1} and {csv2}: {e}"))
+          });
+        let ratio = report.ratio_percent() / 100.0;
+        Tolerances {
+          max_ratio: ratio.is_finite().then(|| manifest::round_up(ratio)),
+          max_diff: Some(manifest::round_up(report.max_diff.value)),
+          min_digits: None,
+          max_fail_fraction: None,
+          ..Default::default()
+        }
+      });
+      (csv1, csv2, suggested)
+    })
+    .collect();
+  let text = manifest::generate(&pairs, &unpaired);
+  let written = match &args.output {
+    Some(path) => std::fs::write(path, text),
+    None => io::stdout().write_all(text.as_bytes()),
+  };
+  if let Err(e) = written {
+    exit::die(format!("Error writing the manifest: {e}"));
+  }
+}
+
+/// The tolerances the command line gives; with --allclose, -d and -r are
+/// the parts of the combined tolerance instead of tolerances of their own.
+fn command_line_tolerances(args: &Args) -> Tolerances {
+  let (separate, combined) = (!args.allclose, args.allclose);
+  Tolerances {
+    max_ratio: args.max_ratio.filter(|_| separate),
+    max_diff: args.max_diff.filter(|_| separate),
+    min_digits: args.min_digits,
+    max_fail_fraction: args.max_fail_fraction,
+    max_ratio_over: args.max_ratio_over,
+    max_ratio_under: args.max_ratio_under,
+    max_diff_over: args.max_diff_over,
+    max_diff_under: args.max_diff_under,
+    atol: args.max_diff.filter(|_| combined),
+    rtol: args.max_ratio.filter(|_| combined),
+  }
+}
+
+/// Parses --rows: a physical line, or the first and last of a range of them
+/// as FIRST-LAST or START:END, whose START defaults to the first line and
+/// END to the last.
+fn line_range(s: &str) -> Result<RangeInclusive<usize>, String> {
+  let invalid =
+    || format!("Invalid lines: {s}. Must be LINE, FIRST-LAST or START:END");
+  let line = |text: &str, open: Option<usize>| match text.trim() {
+    "" => open.ok_or_else(invalid),
+    text => text.parse::<usize>().map_err(|_| invalid()),
+  };
+  let (first, last) = match s.split_once(':') {
+    Some((first, last)) => {
+      (line(first, Some(1))?, line(last, Some(usize::MAX))?)
+    }
+    None => {
+      let (first, last) = s.split_once('-').unwrap_or((s, s));
+      (line(first, None)?, line(last, None)?)
+    }
+  };
+  match first >= 1 && first <= last {
+    true => Ok(first..=last),
+    false => Err(invalid()),
+  }
+}
+
+/// Parses a --scale1 or --scale2 factor, which must be positive.
+fn positive_scale(s: &str) -> Result<f64, String> {
+  match s.parse::<f64>() {
+    Ok(scale) if scale > 0.0 && scale.is_finite() => Ok(scale),
+    Ok(_) => Err(format!("Invalid scale: {s}. Must be positive")),
+    Err(e) => Err(format!("Invalid scale: {s}. {e}")),
+  }
+}
+
+/// Each compared column's norms in both files, by csv1's (1-based) column,
+/// if they're normalized.
+fn column_norms(
+  opts: &DiffConfig,
+  report: &DiffReport,
+) -> Vec<(usize, f64, f64)> {
+  let Some(normalize) = &opts.normalize else {
+    return Vec::new();
+  };
+  report
+    .columns
+    .iter()
+    .map(|column| {
+      let i = column.col - 1;
+      let norm2 = opts.col2(i).map_or(0.0, |j| normalize.norm(2, j));
+      (column.col, normalize.norm(1, i), norm2)
+    })
+    .collect()
+}
+
+/// What --normalize-columns divides columns by, in words.
+fn norms_noun(normalization: Normalization) -> &'static str {
+  match normalization {
+    Normalization::Max => "largest magnitudes",
+    Normalization::L2 => "L2 norms",
+    Normalization::None => "nothing",
+  }
+}
+
+/// How far apart a column's norms are, as the ratio of the larger to the
+/// smaller.
+fn skew(&(_, n1, n2): &(usize, f64, f64)) -> f64 {
+  n1.max(n2) / n1.min(n2)
+}
+
+/// Writes how a worst cell's values were printed, if the files were
+/// rounded, scaled or normalized, so that its values can be found in them.
+fn write_printed(
+  out: &mut dyn Write,
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  worst: &Extremum,
+  opts: &DiffConfig,
+) -> io::Result<()> {
+  let scale = opts.factors(worst.col.saturating_sub(1));
+  if scale == (1.0, 1.0) && opts.sig_digits.is_none() {
+    return Ok(());
+  }
+  let row = (0..records1.len().min(records2.len()))
+    .find(|&row| physical_line(records1, row) == worst.line);
+  let texts = row.and_then(|row| {
+    let i = worst.col.checked_sub(1)?;
+    Some((records1[row].get(i)?, records2[row].get(opts.col2(i)?)?))
+  });
+  let Some((text1, text2)) = texts else {
+    return Ok(());
+  };
+  let mut steps = Vec::new();
+  if let Some(digits) = opts.sig_digits {
+    steps.push(format!("rounding to {digits} significant digits"));
+  }
+  if opts.normalize.is_some() {
+    steps.push("dividing by their columns' norms".to_string());
+  } else if scale != (1.0, 1.0) {
+    steps.push(format!("scaling by {} and {}", scale.0, scale.1));
+  }
+  writeln!(
+    out,
+    "(printed as {text1} and {text2}, before {})",
+    steps.join(" and ")
+  )
+}
+
+/// Writes the rows of both files holding a worst cell, for --show-rows,
+/// each under its file's header if there is one.
+fn write_rows(
+  out: &mut dyn Write,
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  worst: &Extremum,
+  headers: Option<&(StringRecord, StringRecord)>,
+  (args, opts): (&Args, &DiffConfig),
+) -> io::Result<()> {
+  let row = (0..records1.len().min(records2.len()))
+    .find(|&row| physical_line(records1, row) == worst.line);
+  let (Some(row), Some(col1)) = (row, worst.col.checked_sub(1)) else {
+    return Ok(());
+  };
+  let col2 = opts.col2(col1).unwrap_or(col1);
+  let shown = |record: &StringRecord, col| {
+    shown_row(record, col, args.comparison.delim, args.show_rows_width)
+  };
+  for (file, records, header, col) in [
+    ("csv1", records1, headers.map(|h| &h.0), col1),
+    ("csv2", records2, headers.map(|h| &h.1), col2),
+  ] {
+    let header = header.map(|h| (h, physical_line(std::slice::from_ref(h), 0)));
+    let body = (&records[row], physical_line(records, row));
+    for (record, line) in header.into_iter().chain([body]) {
+      writeln!(out, "  {file} line {line}: {}", shown(record, col))?;
+    }
+  }
+  Ok(())
+}
+
+/// A row as --show-rows prints it: its cells joined by the delimiter, the
+/// one at (0-based) `col` marked >>like this<<, and cut down to about
+/// `width` characters by putting "..." for cells left out on either side
+/// of it, which is always kept.
+fn shown_row(
+  record: &StringRecord,
+  col: usize,
+  delim: char,
+  width: usize,
+) -> String {
+  let cells: Vec<String> = record
+    .iter()
+    .enumerate()
+    .map(|(i, cell)| match i == col {
+      true => format!(">>{cell}<<"),
+      false => cell.to_string(),
+    })
+    .collect();
+  let Some(marked) = cells.get(col) else {
+    return cells.join(&delim.to_string());
+  };
+  // widen around the marked cell, a cell to each side in turn, while the
+  // cells and their delimiters fit
+  let (mut first, mut last) = (col, col);
+  let mut len = marked.chars().count();
+  loop {
+    let left = first.checked_sub(1).map(|i| (i, cells[i].chars().count()));
+    let right = cells
+      .get(last + 1)
+      .map(|cell| (last + 1, cell.chars().count()));
+    let next = match (left, right) {
+      (Some(l), Some(r)) => match last - col < col - first {
+        true => r,
+        false => l,
+      },
+      (Some(side), None) | (None, Some(side)) => side,
+      (None, None) => break,
+    };
+    if len + 1 + next.1 > width {
+      break;
+    }
+    len += 1 + next.1;
+    match next.0 < first {
+      true => first = next.0,
+      false => last = next.0,
+    }
+  }
+  let mut shown: Vec<&str> =
+    cells[first..=last].iter().map(String::as_str).collect();
+  if first > 0 {
+    shown.insert(0, "...");
+  }
+  if last + 1 < cells.len() {
+    shown.push("...");
+  }
+  shown.join(&delim.to_string())
+}
+
+/// The delimiter as a byte, which it must fit in.
+fn delimiter(delim: char) -> Result<u8, Fatal> {
+  delim.try_into().map_err(|_| {
+    Fatal::new(format!(
+      "Error: the delimiter {delim:?} isn't a single byte"
+    ))
+  })
+}
+
+/// How long to wait before reading a file again after it changed.
+const CHANGE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Reads every record of a CSV file, retrying as asked if it changes.
+fn read_records(args: &Args, path: &str) -> Result<Vec<StringRecord>, Fatal> {
+  read_records_timed(args, path).map(|(records, _)| records)
+}
+
+/// Like [`read_records`], also returning the time spent reading and
+/// decoding the file's bytes.
+fn read_records_timed(
+  args: &Args,
+  path: &str,
+) -> Result<(Vec<StringRecord>, Duration), Fatal> {
+  if extract::is_f06(path) {
+    let Some(block) = args.f06_block else {
+      return Err(Fatal::new(format!(
+        "Error: {path} is an .f06 file; --f06-block must say which result \
+         block of it to compare"
+      )));
+    };
+    let start = Instant::now();
+    let records = extract::extract(path, block, args.has_headers)
+      .map_err(|e| Fatal::new(format!("Error: {e}")))?;
+    return Ok((records, start.elapsed()));
+  }
+  let options = ReadOptions {
+    lock: args.lock_inputs,
+    encoding: args.encoding,
+    trim: !args.no_trim,
+    // the rows left out, or ones with fields left out at the end, may not
+    // be shaped like the table
+    ragged: reads_ragged(args),
+  };
+  let mut retries = args.retries_on_change;
+  let counter = bar::counter();
+  loop {
+    let delim = delimiter(args.comparison.delim)?;
+    let read = match &counter {
+      Some(progress) => {
+        let size = std::fs::metadata(path).map_or(0, |m| m.len());
+        bar::begin(format!("reading {path}"), bar::Unit::Bytes, size);
+        let read =
+          f06csvdiff::read_file_counted(path, delim, options, progress);
+        bar::end();
+        read
+      }
+      None => f06csvdiff::read_file_timed(path, delim, options),
+    };
+    match read {
+      Ok(read) => return Ok(read),
+      Err(DiffError::InputChanged { .. }) if retries > 0 => {
+        retries -= 1;
+        exit::note(format!(
+          "note: {path} changed while it was being read; retrying"
+        ));
+        thread::sleep(CHANGE_RETRY_DELAY);
+      }
+      Err(e @ DiffError::InputChanged { .. }) => {
+        return Err(Fatal::with(exit::CHANGED, format!("Error: {e}")));
+      }
+      Err(e) => return Err(Fatal::new(format!("Error: {e}"))),
+    }
+  }
+}
+
+/// The comparison settings the arguments ask for, with these tolerances.
+fn diff_config(
+  args: &Args,
+  tolerances: &Tolerances,
+) -> Result<DiffConfig, Fatal> {
+  let sign_flip = if !args.allow_sign_flip_cols.is_empty() {
+    SignFlip::Columns(
+      args
+        .allow_sign_flip_cols
+        .iter()
+        .map(|&c| c as usize - 1)
+        .collect(),
+    )
+  } else if args.allow_sign_flip {
+    SignFlip::AnyColumn
+  } else {
+    SignFlip::Never
+  };
+  let abs_compare = if !args.abs_compare_cols.is_empty() {
+    AbsCompare::Columns(
+      args
+        .abs_compare_cols
+        .iter()
+        .map(|&c| c as usize - 1)
+        .collect(),
+    )
+  } else if args.abs_compare {
+    AbsCompare::AnyColumn
+  } else {
+    AbsCompare::Never
+  };
+  Ok(DiffConfig {
+    tolerances: tolerances.clone(),
+    diff_scale: None,
+    sum_check: match (args.sum_check, args.sum_check_only) {
+      (false, _) => SumCheck::Off,
+      (true, false) => SumCheck::Combined,
+      (true, true) => SumCheck::Only,
+    },
+    sign_flip,
+    abs_compare,
+    strict_layout: args.strict_layout,
+    structure: match (args.lenient_structure, args.structure_warn_only) {
+      (false, _) => Structure::Strict,
+      (true, false) => Structure::Lenient,
+      (true, true) => Structure::Warn,
+    },
+    listed_ties: args.list_ties.unwrap_or(0),
+    max_zero_mismatch: args.max_zero_mismatch,
+    segments: args.segments.map_or(0, usize::from),
+    extra_rows: extra_rows(args)?,
+    column_workers: column_workers(args),
+    fail_fast: args.fail_fast.map(|n| n as usize),
+    histogram: args.histogram.then(|| args.histogram_bins.clone()),
+    percentiles: (!args.percentiles.is_empty())
+      .then(|| args.percentiles.clone()),
+    // the columns' significance is resolved per pair, as --col-map is
+    significance: BTreeMap::new(),
+    // as are the cells --exclude-cells names
+    excluded_cells: HashSet::new(),
+    float_re: float_regex(args)?,
+    high_impact_only: args.fail_only_high_impact,
+    // --col-map may name columns, so it's resolved per pair
+    column_map: None,
+    // and the norms are those of the pair's columns
+    normalize: None,
+    // as are the percent columns' conventions
+    percent: None,
+    progress: bar::counter(),
+    warn_tolerances: Tolerances {
+      max_ratio: args.warn_ratio,
+      max_diff: args.warn_diff,
+      ..Default::default()
+    },
+    ..args.comparison.config()?
+  })
+}
+
+/// Compares csv1, the reference, with csv2, the new version, and with the
+/// --old version, and writes the regression gate's verdict on each float
+/// column. Returns whether every column passed.
+fn regression(
+  args: &Args,
+  gate: &RegressionGate,
+  painter: Painter,
+  out: &mut dyn Write,
+) -> Result<bool, Fatal> {
+  let reference = args.csv1.as_deref().unwrap_or_default();
+  let new = args.csv2.as_deref().unwrap_or_default();
+  let old = args.old.as_deref().unwrap_or_default();
+  let config = diff_config(args, &Tolerances::default())?;
+  let records = read_records(args, reference)?;
+  let against = |path: &str| {
+    let mut records = records.clone();
+    let mut other = read_records(args, path)?;
+    strip_headers(args, (reference, &mut records), (path, &mut other))?;
+    sort_rows(args, &config, (reference, &mut records), (path, &mut other))?;
+    strip_suffixes(args, (reference, &mut records), (path, &mut other))?;
+    compare::compare(&records, &other, &config, (reference, path)).map_err(
+      |e| Fatal::new(format!("Error comparing {reference} and {path}: {e}")),
+    )
+  };
+  let columns = gate.evaluate(&against(new)?, &against(old)?);
+  let passed = columns.iter().all(|c| c.passed);
+  if args.quiet > 0 {
+    return Ok(passed);
+  }
+
+  let headers =
+    ["col", "new_diff", "old_diff", "allowed", "status"].map(String::from);
+  let rows: Vec<Vec<Cell>> = columns
+    .iter()
+    .map(|c| {
+      vec![
+        (c.col.to_string(), Paint::Plain),
+        value_cell(format!("{:.2E}", c.new_diff), c.passed),
+        (format!("{:.2E}", c.old_diff), Paint::Plain),
+        (format!("{:.2E}", c.allowed), Paint::Plain),
+        status_cell(c.passed),
+      ]
+    })
+    .collect();
+  writeln!(
+    out,
+    "regression of {new} from {reference}, against {old} (margin {:.2}%, \
+     floor {:.2E}):",
+    gate.margin * 100.0,
+    gate.floor
+  )?;
+  let table =
+    align_table(&headers, &rows, &Alignment::Left, None, true, &painter);
+  for line in table.lines() {
+    writeln!(out, "  {}", line.trim_end())?;
+  }
+  writeln!(out, "result: {}", painter.status(passed))?;
+  Ok(passed)
+}
+
+/// Compares csv1 and csv2, and writes the verdict on each float column of
+/// judging it by the report saved in `path`. Returns whether every column
+/// passed. A saved report that no longer fits the comparison is an error.
+fn expected(
+  args: &Args,
+  path: &Path,
+  gate: &RegressionGate,
+  painter: Painter,
+  out: &mut dyn Write,
+) -> Result<bool, Fatal> {
+  let csv1 = args.csv1.as_deref().unwrap_or_default();
+  let csv2 = args.csv2.as_deref().unwrap_or_default();
+  let config = diff_config(args, &Tolerances::default())?;
+  let saved = std::fs::read_to_string(path)
+    .map_err(|e| e.to_string())
+    .and_then(|text| SavedReport::from_json(&text).map_err(|e| e.to_string()))
+    .map_err(|e| {
+      Fatal::new(format!("Error reading {}: {e}", path.display()))
+    })?;
+  let stale = saved.staleness(&config);
+  if !stale.is_empty() {
+    return Err(Fatal::new(format!(
+      "Error: {} is stale, save it again with --save-report: {}",
+      path.display(),
+      stale.join(", ")
+    )));
+  }
+  let mut records1 = read_records(args, csv1)?;
+  let mut records2 = read_records(args, csv2)?;
+  strip_headers(args, (csv1, &mut records1), (csv2, &mut records2))?;
+  sort_rows(args, &config, (csv1, &mut records1), (csv2, &mut records2))?;
+  strip_suffixes(args, (csv1, &mut records1), (csv2, &mut records2))?;
+  let report = compare::compare(&records1, &records2, &config, (csv1, csv2))
+    .map_err(|e| {
+      Fatal::new(format!("Error comparing {csv1} and {csv2}: {e}"))
+    })?;
+  if let Some(path) = &args.save_report {
+    save_report(path, &report, &config)?;
+  }
+  let columns = gate.evaluate(&report, &saved.to_report());
+  let passed = columns.iter().all(|c| c.passed);
+  if args.quiet > 0 {
+    return Ok(passed);
+  }
+
+  let headers =
+    ["col", "diff", "expected", "allowed", "status"].map(String::from);
+  let rows: Vec<Vec<Cell>> = columns
+    .iter()
+    .map(|c| {
+      vec![
+        (c.col.to_string(), Paint::Plain),
+        value_cell(format!("{:.2E}", c.new_diff), c.passed),
+        (format!("{:.2E}", c.old_diff), Paint::Plain),
+        (format!("{:.2E}", c.allowed), Paint::Plain),
+        status_cell(c.passed),
+      ]
+    })
+    .collect();
+  writeln!(
+    out,
+    "drift of {csv1} vs {csv2} from {} (margin {:.2}%):",
+    path.display(),
+    gate.margin * 100.0
+  )?;
+  let table =
+    align_table(&headers, &rows, &Alignment::Left, None, true, &painter);
+  for line in table.lines() {
+    writeln!(out, "  {}", line.trim_end())?;
+  }
+  writeln!(out, "result: {}", painter.status(passed))?;
+  Ok(passed)
+}
+
+/// Writes the report, as found with `opts`, to `path` for --expected-report.
+fn save_report(
+  path: &Path,
+  report: &DiffReport,
+  opts: &DiffConfig,
+) -> Result<(), Fatal> {
+  let saved = SavedReport::new(report, opts);
+  std::fs::write(path, saved.to_json())
+    .map_err(|e| Fatal::new(format!("Error writing {}: {e}", path.display())))
+}
+
+/// Writes a difference matrix as CSV, after csv1's header if there is one.
+fn write_diff_csv(
+  path: &Path,
+  header: Option<&StringRecord>,
+  matrix: &[StringRecord],
+  delimiter: u8,
+) -> csv::Result<()> {
+  let mut writer = csv::WriterBuilder::new()
+    .delimiter(delimiter)
+    .flexible(true)
+    .from_path(path)?;
+  for record in header.into_iter().chain(matrix) {
+    writer.write_record(record)?;
+  }
+  writer.flush()?;
+  Ok(())
+}
+
+/// The regex cells are taken for floats by: --float-pattern's, or else
+/// --float-format's, and with --include-integers integers'.
+fn float_regex(args: &Args) -> Result<regex::Regex, Fatal> {
+  let pattern = match &args.float_pattern {
+    Some(pattern) => pattern,
+    None => args.float_format.pattern(),
+  };
+  let pattern = match args.include_integers {
+    true => format!("(?:{pattern})|{INTEGER_PATTERN}"),
+    false => pattern.to_string(),
+  };
+  regex::Regex::new(&pattern)
+    .map_err(|e| Fatal::new(format!("Error: bad --float-pattern: {e}")))
+}
+
+/// Which extra rows to allow.
+fn extra_rows(args: &Args) -> Result<ExtraRows, Fatal> {
+  Ok(match &args.ignore_rows_matching {
+    Some(pattern) => {
+      ExtraRows::AllowMatching(regex::Regex::new(pattern).map_err(|e| {
+        Fatal::new(format!("Error: bad --ignore-rows-matching: {e}"))
+      })?)
+    }
+    None if args.allow_extra_rows => ExtraRows::Allow,
+    None => ExtraRows::Forbid,
+  })
+}
+
+/// How many threads to split the float columns among.
+fn column_workers(args: &Args) -> usize {
+  match args.parallel {
+    None => 1,
+    Some(Parallel::Columns) => args.threads.map_or_else(
+      || thread::available_parallelism().map_or(1, |n| n.get()),
+      usize::from,
+    ),
+  }
+}
+
+/// Whether --skip-rows or --skip-tail leave out any rows.
+fn skips_rows(args: &Args) -> bool {
+  let heads = [Some(args.skip_rows), args.skip_rows1, args.skip_rows2];
+  args.skip_tail > 0 || heads.iter().any(|&n| n.is_some_and(|n| n > 0))
+}
+
+/// Transposes a file's records in place if asked to, with --transpose1 or
+/// --transpose2, returning how to find its cells' lines.
+fn transpose_records(
+  asked: bool,
+  (path, records): (&str, &mut Vec<StringRecord>),
+) -> Result<Option<Transposed>, Fatal> {
+  if !asked {
+    return Ok(None);
+  }
+  let mut transposed = transpose::transpose(records, path)
+    .map_err(|e| Fatal::new(format!("Error: can't transpose {path}: {e}")))?;
+  *records = std::mem::take(&mut transposed.records);
+  Ok(Some(transposed))
+}
+
+/// Whether files are read with rows of any number of fields, for
+/// [`strip_headers`] to even out and check.
+fn reads_ragged(args: &Args) -> bool {
+  skips_rows(args)
+    || args.pad_missing
+    || args.ignore_trailing_empty
+    || args.lenient_structure
+}
+
+/// Takes the rows --skip-rows and --skip-tail leave out off a pair of
+/// files, evens out the rest's trailing fields as --ignore-trailing-empty
+/// and --pad-missing ask, then in header mode takes their headers and
+/// reports any repeats of them removed from the body. Returns both headers.
+fn strip_headers(
+  args: &Args,
+  (csv1, records1): (&str, &mut Vec<StringRecord>),
+  (csv2, records2): (&str, &mut Vec<StringRecord>),
+) -> Result<Option<(StringRecord, StringRecord)>, Fatal> {
+  for (path, records, head) in [
+    (csv1, &mut *records1, args.skip_rows1),
+    (csv2, &mut *records2, args.skip_rows2),
+  ] {
+    let head = head.unwrap_or(args.skip_rows);
+    headers::skip_rows(records, (head, args.skip_tail)).map_err(|len| {
+      Fatal::new(format!(
+        "Error: can't skip {head} leading and {} trailing row(s) of {path}, \
+         which has {len}",
+        args.skip_tail
+      ))
+    })?;
+    if args.ignore_trailing_empty {
+      f06csvdiff::strip_trailing_empty(records);
+    }
+  }
+  if args.pad_missing {
+    f06csvdiff::pad_fields(records1, records2)
+      .map_err(|e| Fatal::new(format!("Error: {e}")))?;
+  }
+  // rows whose widths change are left out later if structure is lenient
+  if reads_ragged(args) && !args.lenient_structure {
+    for (path, records) in [(csv1, &*records1), (csv2, &*records2)] {
+      f06csvdiff::check_field_counts(records, path)
+        .map_err(|e| Fatal::new(format!("Error: {e}")))?;
+    }
+  }
+  if !args.has_headers {
+    return Ok(None);
+  }
+  let headers1 = headers::take_headers(records1, args.dedup_headers);
+  let headers2 = headers::take_headers(records2, args.dedup_headers);
+  let repeats = |h: &Option<headers::Headers>| {
+    h.as_ref().map(|h| h.repeats.clone()).unwrap_or_default()
+  };
+  let (repeats1, repeats2) = (repeats(&headers1), repeats(&headers2));
+  if repeats1 != repeats2 && !args.lenient_dedup {
+    let at = repeats1
+      .iter()
+      .zip(&repeats2)
+      .position(|(l1, l2)| l1 != l2)
+      .unwrap_or(repeats1.len().min(repeats2.len()));
+    let line = |repeats: &[usize]| {
+      repeats
+        .get(at)
+        .map_or("none".to_string(), |l| format!("line {l}"))
+    };
+    return Err(Fatal::new(format!(
+      "Error: {csv1} and {csv2} repeat their header at different lines \
+       ({} vs {}); --lenient-dedup allows that",
+      line(&repeats1),
+      line(&repeats2)
+    )));
+  }
+  if !repeats1.is_empty() || !repeats2.is_empty() {
+    exit::note(format!(
+      "note: removed {} repeated header row(s) from {csv1} and {} from \
+       {csv2}",
+      repeats1.len(),
+      repeats2.len()
+    ));
+  }
+  let (Some(headers1), Some(headers2)) = (headers1, headers2) else {
+    return Ok(None);
+  };
+  let (header1, header2) = (headers1.header, headers2.header);
+  // columns paired by position had better be named alike
+  let by_position = !args.match_by_header && args.col_map.is_none();
+  if by_position && args.diff_headers.is_none() && header1 != header2 {
+    let diff = headerdiff::diff_headers(&header1, &header2);
+    exit::notice(format!("warning: {}", header_diff(&diff, (csv1, csv2))));
+  }
+  Ok(Some((header1, header2)))
+}
+
+/// Describes how two headers differ, over several lines.
+fn header_diff(diff: &HeaderDiff, (csv1, csv2): (&str, &str)) -> String {
+  let columns = |columns: &[HeaderColumn]| {
+    let columns: Vec<String> = columns
+      .iter()
+      .map(|c| format!("column {} {:?}", c.col, c.name))
+      .collect();
+    columns.join(", ")
+  };
+  let mut text = format!(
+    "the headers of {csv1} and {csv2} differ; {} column(s) align",
+    diff.aligned
+  );
+  if !diff.removed.is_empty() {
+    text += &format!("\n  only in {csv1}: {}", columns(&diff.removed));
+  }
+  if !diff.added.is_empty() {
+    text += &format!("\n  only in {csv2}: {}", columns(&diff.added));
+  }
+  for moved in &diff.moved {
+    text += &format!(
+      "\n  moved: {:?} from column {} to {}",
+      moved.name, moved.from, moved.to
+    );
+  }
+  for renamed in &diff.renamed {
+    let hint = match renamed.hint {
+      RenameHint::Spelling => "case or whitespace",
+      RenameHint::Place => "same place",
+    };
+    text += &format!(
+      "\n  renamed?: column {} {:?} to column {} {:?} ({hint})",
+      renamed.from.col, renamed.from.name, renamed.to.col, renamed.to.name
+    );
+  }
+  text
+}
+
+/// Writes how the headers differ, as --diff-headers asks: as text, or as a
+/// line of JSON.
+fn write_header_diff(
+  out: &mut dyn Write,
+  format: &str,
+  (csv1, header1): (&str, &StringRecord),
+  (csv2, header2): (&str, &StringRecord),
+) -> io::Result<()> {
+  /// A pair's header diff, as JSON.
+  #[derive(serde::Serialize)]
+  struct PairHeaders<'a> {
+    csv1: &'a str,
+    csv2: &'a str,
+    headers: &'a HeaderDiff,
+  }
+  let diff = headerdiff::diff_headers(header1, header2);
+  if format == "json" {
+    let pair = PairHeaders {
+      csv1,
+      csv2,
+      headers: &diff,
+    };
+    // plain data with string keys always serializes
+    return writeln!(out, "{}", serde_json::to_string(&pair).unwrap());
+  }
+  match diff.is_empty() {
+    true => writeln!(out, "the headers of {csv1} and {csv2} are the same\n"),
+    false => writeln!(out, "{}\n", header_diff(&diff, (csv1, csv2))),
+  }
+}
+
+/// Shows on stderr where a pair's time went, as --profile-phases asks: as a
+/// table, or as a line of JSON.
+fn write_profile(
+  format: &str,
+  (csv1, csv2): (&str, &str),
+  phases: &PhaseTimes,
+) {
+  /// A phase's time, as JSON.
+  #[derive(serde::Serialize)]
+  struct PhaseTime {
+    phase: Phase,
+    seconds: f64,
+    estimated: bool,
+  }
+  /// A pair's profile, as JSON.
+  #[derive(serde::Serialize)]
+  struct PairProfile<'a> {
+    csv1: &'a str,
+    csv2: &'a str,
+    phases: Vec<PhaseTime>,
+    seconds: f64,
+    sampled_every: usize,
+  }
+  let total = phases.total().as_secs_f64();
+  if format == "json" {
+    let profile = PairProfile {
+      csv1,
+      csv2,
+      phases: Phase::ALL
+        .into_iter()
+        .map(|phase| PhaseTime {
+          phase,
+          seconds: phases.get(phase).as_secs_f64(),
+          estimated: phase.sampled(),
+        })
+        .collect(),
+      seconds: total,
+      sampled_every: profile::SAMPLE_EVERY,
+    };
+    // plain data with string keys always serializes
+    exit::notice(serde_json::to_string(&profile).unwrap());
+    return;
+  }
+  let mut lines = vec![format!("time spent on {csv1} and {csv2}:")];
+  for phase in Phase::ALL {
+    let seconds = phases.get(phase).as_secs_f64();
+    let share = match total > 0.0 {
+      true => seconds / total * 100.0,
+      false => 0.0,
+    };
+    let name = match phase.sampled() {
+      true => format!("{}*", phase.name()),
+      false => phase.name().to_string(),
+    };
+    lines.push(format!("  {name:<14} {seconds:>9.4}s {share:>5.1}%"));
+  }
+  lines.push(format!("  {:<14} {total:>9.4}s", "total"));
+  lines.push(format!(
+    "  (* estimated from one row in {})",
+    profile::SAMPLE_EVERY
+  ));
+  exit::notice(lines.join("\n"));
+}
+
+/// With --vector-cols, appends each group's magnitudes to both files' rows,
+/// and to their headers, as columns paired with each other; with
+/// --vector-only, the components are left out of the column map. Returns
+/// each group's label, e.g. "|T1,T2,T3|", and its (1-based) column in csv1.
+fn vectors(
+  args: &Args,
+  opts: &mut DiffConfig,
+  (csv1, records1): (&str, &mut [StringRecord]),
+  (csv2, records2): (&str, &mut [StringRecord]),
+  headers: Option<&mut (StringRecord, StringRecord)>,
+) -> Result<Vec<(String, usize)>, Fatal> {
+  if args.vector_cols.is_empty() {
+    return Ok(Vec::new());
+  }
+  let header1 = headers.as_ref().map(|(header1, _)| header1);
+  let groups1: Vec<Vec<usize>> = args
+    .vector_cols
+    .iter()
+    .map(|group| vector_group(group, header1))
+    .collect::<Result<_, _>>()?;
+  let groups2: Vec<Vec<usize>> = groups1
+    .iter()
+    .map(|group| {
+      let col2 = |&col: &usize| {
+        opts.col2(col).ok_or_else(|| {
+          Fatal::new(format!(
+            "Error: --vector-cols: column {} of {csv1} isn't compared with \
+             any column of {csv2}",
+            col + 1
+          ))
+        })
+      };
+      group.iter().map(col2).collect()
+    })
+    .collect::<Result<_, _>>()?;
+  let width = |records: &[StringRecord]| records.first().map_or(0, |r| r.len());
+  let (width1, width2) = (width(records1), width(records2));
+  for (path, width, groups) in
+    [(csv1, width1, &groups1), (csv2, width2, &groups2)]
+  {
+    if let Some(col) = groups.concat().into_iter().find(|&col| col >= width) {
+      return Err(Fatal::new(format!(
+        "Error: --vector-cols: {path} has no column {}, only {width}",
+        col + 1
+      )));
+    }
+  }
+  for (path, records, groups) in
+    [(csv1, records1, &groups1), (csv2, records2, &groups2)]
+  {
+    vectors::append_magnitudes(records, groups, path, &float_regex(args)?)
+      .map_err(|e| Fatal::new(format!("Error: {e}")))?;
+  }
+
+  let magnitudes = (0..groups1.len()).map(|g| (width1 + g, width2 + g));
+  if opts.column_map.is_some() || args.vector_only {
+    let components: Vec<usize> = groups1.concat();
+    let pairs: Vec<(usize, usize)> = match &opts.column_map {
+      Some(map) => map.pairs().collect(),
+      None => (0..width1).map(|col| (col, col)).collect(),
+    };
+    let pairs: Vec<(usize, usize)> = pairs
+      .into_iter()
+      .filter(|(col1, _)| !args.vector_only || !components.contains(col1))
+      .chain(magnitudes)
+      .collect();
+    // the magnitudes come after every column of csv1
+    opts.column_map = Some(ColumnMap::new(&pairs).unwrap());
+  }
+  let labels: Vec<String> = groups1
+    .iter()
+    .map(|group| {
+      let names: Vec<String> = group
+        .iter()
+        .map(|&col| match header1.and_then(|h| h.get(col)) {
+          Some(name) => name.trim().to_string(),
+          None => (col + 1).to_string(),
+        })
+        .collect();
+      format!("|{}|", names.join(","))
+    })
+    .collect();
+  if let Some((header1, header2)) = headers {
+    for label in &labels {
+      header1.push_field(label);
+      header2.push_field(label);
+    }
+  }
+  Ok(
+    labels
+      .into_iter()
+      .enumerate()
+      .map(|(g, label)| (label, width1 + g + 1))
+      .collect(),
+  )
+}
+
+/// The (0-based) column of csv1 a flag names, by number or by header name.
+fn flag_column(
+  flag: &str,
+  col: &str,
+  header: Option<&StringRecord>,
+) -> Result<usize, Fatal> {
+  let col = col.trim();
+  if let Ok(col) = col.parse::<usize>() {
+    return col
+      .checked_sub(1)
+      .ok_or_else(|| Fatal::new(format!("Error: {flag}: columns start at 1")));
+  }
+  let Some(header) = header else {
+    return Err(Fatal::new(format!(
+      "Error: {flag}: column {col} needs --has-headers"
+    )));
+  };
+  header
+    .iter()
+    .position(|name| name.trim() == col)
+    .ok_or_else(|| {
+      Fatal::new(format!(
+        "Error: {flag}: no column named {col} in the header"
+      ))
+    })
+}
+
+/// The (0-based) columns of csv1 a --vector-cols group names, by number or
+/// by header name.
+fn vector_group(
+  group: &str,
+  header: Option<&StringRecord>,
+) -> Result<Vec<usize>, Fatal> {
+  let cols: Vec<usize> = group
+    .split(',')
+    .map(|col| flag_column("--vector-cols", col, header))
+    .collect::<Result<_, _>>()?;
+  if cols.len() < 2 {
+    return Err(Fatal::new(format!(
+      "Error: --vector-cols: {group} needs at least two columns"
+    )));
+  }
+  Ok(cols)
+}
+
+/// The (0-based) columns of csv1 each pair of --complex-pairs names, real
+/// part first, by number or by header name.
+fn complex_parts(
+  spec: &str,
+  header: Option<&StringRecord>,
+) -> Result<Vec<(usize, usize)>, Fatal> {
+  let flag = "--complex-pairs";
+  spec
+    .split(',')
+    .map(|pair| match pair.split_once(':') {
+      Some((re, im)) => Ok((
+        flag_column(flag, re, header)?,
+        flag_column(flag, im, header)?,
+      )),
+      None => Err(Fatal::new(format!("Error: {flag}: {pair} isn't REAL:IMAG"))),
+    })
+    .collect()
+}
+
+/// Resolves the options that select or shape columns into each column's
+/// effective configuration, failing naming both options if two claim a
+/// column in ways that can't both hold.
+fn effective_columns(
+  args: &Args,
+  opts: &DiffConfig,
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  header: Option<&StringRecord>,
+) -> Result<Vec<EffectiveColumn>, Fatal> {
+  let width = |records: &[StringRecord]| records.first().map_or(0, |r| r.len());
+  let widths = (width(records1), width(records2));
+  let pairing = match (args.match_by_header, &args.col_map) {
+    _ if args.mac_match => Source::MacMatch,
+    (true, _) => Source::MatchByHeader,
+    (false, Some(_)) => Source::ColMap,
+    (false, None) => Source::Position,
+  };
+  let mut keys: Vec<(usize, Source)> = args
+    .sort_by
+    .iter()
+    .map(|&col| (col as usize - 1, Source::SortBy))
+    .collect();
+  if let Some(col) = &args.match_rows_nearest {
+    let col = flag_column("--match-rows-nearest", col, header)?;
+    keys.push((col, Source::MatchRowsNearest));
+  }
+  if let Some(col) = &args.key_col {
+    keys.push((flag_column("--key-col", col, header)?, Source::KeyCol));
+  }
+  if let Some(col) = &args.group_by {
+    keys.push((flag_column("--group-by", col, header)?, Source::GroupBy));
+  }
+  if args.auto_shift.is_some() {
+    let col = flag_column("--shift-col", &args.shift_col, header)?;
+    keys.push((col, Source::ShiftCol));
+  }
+  let options = ColumnOptions {
+    widths,
+    pairing: (pairing, (0..widths.0).map(|col| opts.col2(col)).collect()),
+    vector_groups: args
+      .vector_cols
+      .iter()
+      .map(|group| vector_group(group, header))
+      .collect::<Result<_, _>>()?,
+    vector_only: args.vector_only,
+    complex_pairs: args
+      .complex_pairs
+      .as_ref()
+      .map_or_else(|| Ok(Vec::new()), |spec| complex_parts(spec, header))?,
+    sign_flip: opts.sign_flip.clone(),
+    normalize: args.normalize_columns != Normalization::None,
+    keys,
+  };
+  effective::resolve(&options).map_err(|e| Fatal::new(format!("Error: {e}")))
+}
+
+/// Writes the --show-effective-columns table: a row per column of csv1 and
+/// per magnitude, named by the header, after the magnitudes were appended
+/// to it, or by the magnitudes' labels.
+fn write_effective_columns(
+  out: &mut dyn Write,
+  args: &Args,
+  (csv1, csv2): (&str, &str),
+  columns: &[EffectiveColumn],
+  opts: &DiffConfig,
+  (header, magnitudes): (Option<&StringRecord>, &[(String, usize)]),
+) -> io::Result<()> {
+  let tolerances = &opts.tolerances;
+  let mut limits = Vec::new();
+  if let Some(r) = tolerances.max_ratio {
+    limits.push(format!("ratio {:.4}%", r * 100.0));
+  }
+  if let Some(d) = tolerances.max_diff {
+    limits.push(format!("diff {d:.2E}"));
+  }
+  if let Some(m) = tolerances.min_digits {
+    limits.push(format!("digits {m}"));
+  }
+  if let Some(f) = tolerances.max_fail_fraction {
+    limits.push(format!("fail fraction {:.4}%", f * 100.0));
+  }
+  let rows: Vec<Vec<Cell>> = columns
+    .iter()
+    .map(|column| {
+      let name = match column.role {
+        Role::Magnitude => magnitudes
+          .iter()
+          .find(|(_, col)| *col == column.col)
+          .map(|(label, _)| label.clone()),
+        _ => header
+          .and_then(|h| h.get(column.col - 1))
+          .map(|name| name.trim().to_string()),
+      };
+      let compared = matches!(column.role, Role::Compared | Role::Magnitude);
+      let mut tolerances = match compared {
+        true => limits.clone(),
+        false => Vec::new(),
+      };
+      if column.role == Role::Magnitude
+        && column.sources.contains(&Source::ComplexPairs)
+        && let Some(max) = args.max_phase_deg
+      {
+        tolerances.push(format!("phase {max} deg"));
+      }
+      let mut policies = Vec::new();
+      if column.sign_flip {
+        policies.push("sign flip".to_string());
+      }
+      if column.normalized {
+        policies.push(format!("{} norm", args.normalize_columns.name()));
+      }
+      if column.sources.contains(&Source::SortBy) {
+        policies.push("sort key".to_string());
+      }
+      if column.sources.contains(&Source::MatchRowsNearest) {
+        policies.push("match key".to_string());
+      }
+      if column.sources.contains(&Source::KeyCol) {
+        policies.push("pairing key".to_string());
+      }
+      if column.sources.contains(&Source::GroupBy) {
+        policies.push("group key".to_string());
+      }
+      if column.sources.contains(&Source::ShiftCol) {
+        policies.push("shift key".to_string());
+      }
+      let list = |items: Vec<String>| match items.is_empty() {
+        true => "-".to_string(),
+        false => items.join(", "),
+      };
+      let sources: Vec<String> = column
+        .sources
+        .iter()
+        .map(|s| s.name().to_string())
+        .collect();
+      [
+        column.col.to_string(),
+        name.unwrap_or_else(|| "-".to_string()),
+        column.role.name().to_string(),
+        column
+          .col2
+          .map_or_else(|| "-".to_string(), |c| c.to_string()),
+        list(tolerances),
+        list(policies),
+        sources.join(", "),
+      ]
+      .into_iter()
+      .map(|text| (text, Paint::Plain))
+      .collect()
+    })
+    .collect();
+  let headers: Vec<String> = [
+    "column",
+    "name",
+    "role",
+    "against",
+    "tolerances",
+    "policies",
+    "set by",
+  ]
+  .map(String::from)
+  .to_vec();
+  let painter = Painter::new(ColorChoice::Never, false);
+  writeln!(out, "effective columns of {csv1} and {csv2}:")?;
+  let table =
+    align_table(&headers, &rows, &Alignment::Left, None, true, &painter);
+  for line in table.lines() {
+    writeln!(out, "  {}", line.trim_end())?;
+  }
+  Ok(())
+}
+
+/// A complex pair's label, e.g. "|T1R+iT1I|", and its magnitude's (1-based)
+/// column in csv1.
+type Magnitude = (String, usize);
+
+/// With --complex-pairs, appends each pair's magnitudes to both files' rows,
+/// and to their headers, as columns paired with each other, and leaves the
+/// parts out of the column map. Returns the pairs, with each one's label,
+/// e.g. "|T1R+iT1I|", and its magnitude's (1-based) column in csv1.
+fn complex_pairs(
+  args: &Args,
+  opts: &mut DiffConfig,
+  (csv1, records1): (&str, &mut [StringRecord]),
+  (csv2, records2): (&str, &mut [StringRecord]),
+  headers: Option<&mut (StringRecord, StringRecord)>,
+) -> Result<(Vec<ComplexPair>, Vec<Magnitude>), Fatal> {
+  let Some(spec) = &args.complex_pairs else {
+    return Ok((Vec::new(), Vec::new()));
+  };
+  let flag = "--complex-pairs";
+  let header1 = headers.as_ref().map(|(header1, _)| header1);
+  let parts = complex_parts(spec, header1)?;
+  let col2 = |col: usize| {
+    opts.col2(col).ok_or_else(|| {
+      Fatal::new(format!(
+        "Error: {flag}: column {} of {csv1} isn't compared with any column \
+         of {csv2}",
+        col + 1
+      ))
+    })
+  };
+  let parts2: Vec<(usize, usize)> = parts
+    .iter()
+    .map(|&(re, im)| Ok((col2(re)?, col2(im)?)))
+    .collect::<Result<_, Fatal>>()?;
+  let width = |records: &[StringRecord]| records.first().map_or(0, |r| r.len());
+  let (width1, width2) = (width(records1), width(records2));
+  for (path, records, width, parts) in [
+    (csv1, records1, width1, &parts),
+    (csv2, records2, width2, &parts2),
+  ] {
+    let groups: Vec<Vec<usize>> =
+      parts.iter().map(|&(re, im)| vec![re, im]).collect();
+    if let Some(col) = groups.concat().into_iter().find(|&col| col >= width) {
+      return Err(Fatal::new(format!(
+        "Error: {flag}: {path} has no column {}, only {width}",
+        col + 1
+      )));
+    }
+    vectors::append_magnitudes(records, &groups, path, &float_regex(args)?)
+      .map_err(|e| Fatal::new(format!("Error: {e}")))?;
+  }
+
+  let pairs: Vec<ComplexPair> = parts
+    .iter()
+    .zip(&parts2)
+    .enumerate()
+    .map(|(p, (&(re1, im1), &(re2, im2)))| ComplexPair {
+      re: (re1, re2),
+      im: (im1, im2),
+      magnitude: (width1 + p, width2 + p),
+    })
+    .collect();
+  // the parts are compared only through their magnitudes and phases
+  let components: Vec<usize> =
+    parts.iter().flat_map(|&(re, im)| [re, im]).collect();
+  let compared: Vec<(usize, usize)> = match &opts.column_map {
+    Some(map) => map.pairs().collect(),
+    None => (0..width1).map(|col| (col, col)).collect(),
+  };
+  let compared: Vec<(usize, usize)> = compared
+    .into_iter()
+    .filter(|(col1, _)| !components.contains(col1))
+    .chain(pairs.iter().map(|pair| pair.magnitude))
+    .collect();
+  // the magnitudes come after every column of csv1
+  opts.column_map = Some(ColumnMap::new(&compared).unwrap());
+  let labels: Vec<String> = parts
+    .iter()
+    .map(|&(re, im)| {
+      let name = |col: usize| match header1.and_then(|h| h.get(col)) {
+        Some(name) => name.trim().to_string(),
+        None => (col + 1).to_string(),
+      };
+      format!("|{}+i{}|", name(re), name(im))
+    })
+    .collect();
+  if let Some((header1, header2)) = headers {
+    for label in &labels {
+      header1.push_field(label);
+      header2.push_field(label);
+    }
+  }
+  let magnitudes = labels
+    .into_iter()
+    .enumerate()
+    .map(|(p, label)| (label, width1 + p + 1))
+    .collect();
+  Ok((pairs, magnitudes))
+}
+
+/// With --match-rows-nearest, pairs both files' rows by the column's nearest
+/// values, in csv1's order, leaving out those unpaired. Returns the column,
+/// as given, and how the rows were paired.
+fn match_rows(
+  args: &Args,
+  opts: &DiffConfig,
+  (csv1, records1): (&str, &mut Vec<StringRecord>),
+  (csv2, records2): (&str, &mut Vec<StringRecord>),
+  header: Option<&StringRecord>,
+) -> Result<Option<(String, RowMatching)>, Fatal> {
+  let Some(col) = &args.match_rows_nearest else {
+    return Ok(None);
+  };
+  let col1 = flag_column("--match-rows-nearest", col, header)?;
+  let col2 = opts.col2(col1).ok_or_else(|| {
+    Fatal::new(format!(
+      "Error: --match-rows-nearest column {col} isn't paired with a column \
+       of {csv2}"
+    ))
+  })?;
+  let matching = nearest::match_nearest(
+    (csv1, records1),
+    (csv2, records2),
+    (col1, col2),
+    args.match_window,
+  )
+  .map_err(|e| Fatal::new(format!("Error: --match-rows-nearest: {e}")))?;
+  Ok(Some((col.clone(), matching)))
+}
+
+/// How many keys --key-col diagnostics list; --keys-report has them all.
+const LISTED_KEYS: usize = 20;
+
+/// With --key-col, pairs both files' rows by their keys, in csv1's order,
+/// leaving out those whose key the other file lacks, and writes
+/// --keys-report. Fails on duplicated keys unless --duplicate-keys says
+/// how to pair them. Returns the column, as given, and the keys.
+fn pair_keys(
+  args: &Args,
+  opts: &DiffConfig,
+  (csv1, records1): (&str, &mut Vec<StringRecord>),
+  (csv2, records2): (&str, &mut Vec<StringRecord>),
+  header: Option<&StringRecord>,
+) -> Result<Option<(String, KeyMatching)>, Fatal> {
+  let Some(col) = &args.key_col else {
+    return Ok(None);
+  };
+  let col1 = flag_column("--key-col", col, header)?;
+  let col2 = opts.col2(col1).ok_or_else(|| {
+    Fatal::new(format!(
+      "Error: --key-col column {col} isn't paired with a column of {csv2}"
+    ))
+  })?;
+  let matching =
+    keys::reconcile((csv1, records1), (csv2, records2), (col1, col2))
+      .map_err(|e| Fatal::new(format!("Error: --key-col: {e}")))?;
+  if let Some(path) = &args.keys_report {
+    write_keys_report(path, &matching).map_err(|e| {
+      Fatal::new(format!("Error writing {}: {e}", path.display()))
+    })?;
+  }
+  if args.duplicate_keys == Duplicates::Error {
+    let duplicated = listed_keys(matching.duplicated(), |k| {
+      unpaired("line", &(k.lines1.clone(), k.lines2.clone()), (csv1, csv2))
+        .unwrap_or_default()
+    });
+    if !duplicated.is_empty() {
+      return Err(Fatal::new(format!(
+        "Error: --key-col: keys shared by several rows, which \
+         --duplicate-keys first or sum would pair:\n  {}",
+        duplicated.join("\n  ")
+      )));
+    }
+  }
+  keys::pair(
+    (records1, records2),
+    &matching,
+    args.duplicate_keys,
+    &float_regex(args)?,
+  );
+  Ok(Some((col.clone(), matching)))
+}
+
+/// Lines describing keys, e.g. "key 101: lines 3, 7 of a.csv", at most
+/// [`LISTED_KEYS`] of them and then how many more there are.
+fn listed_keys<'a>(
+  keys: impl Iterator<Item = &'a KeyRows>,
+  describe: impl Fn(&KeyRows) -> String,
+) -> Vec<String> {
+  let keys: Vec<&KeyRows> = keys.collect();
+  let mut lines: Vec<String> = keys
+    .iter()
+    .take(LISTED_KEYS)
+    .map(|k| format!("key {}: {}", k.key, describe(k)))
+    .collect();
+  if keys.len() > LISTED_KEYS {
+    lines.push(format!("... and {} more", keys.len() - LISTED_KEYS));
+  }
+  lines
+}
+
+/// The keys only one file (1 or 2) has, e.g. "key 101: 1 row (line 9)".
+fn keys_only_in(matching: &KeyMatching, file: usize) -> Vec<String> {
+  listed_keys(matching.only_in(file), |k| {
+    let lines = if file == 1 { &k.lines1 } else { &k.lines2 };
+    let numbers: Vec<String> = lines.iter().map(usize::to_string).collect();
+    match lines.len() {
+      1 => format!("1 row (line {})", numbers[0]),
+      n => format!("{n} rows (lines {})", numbers.join(", ")),
+    }
+  })
+}
+
+/// Writes --keys-report: a CSV row per key, with its status and its rows'
+/// count and (space-separated) lines in each file.
+fn write_keys_report(path: &Path, matching: &KeyMatching) -> io::Result<()> {
+  let mut writer = csv::Writer::from_path(path)?;
+  writer
+    .write_record(["key", "status", "rows1", "rows2", "lines1", "lines2"])?;
+  let lines = |lines: &[usize]| {
+    let numbers: Vec<String> = lines.iter().map(usize::to_string).collect();
+    numbers.join(" ")
+  };
+  for k in &matching.keys {
+    writer.write_record([
+      k.key.clone(),
+      k.status().to_string(),
+      k.rows1.len().to_string(),
+      k.rows2.len().to_string(),
+      lines(&k.lines1),
+      lines(&k.lines2),
+    ])?;
+  }
+  writer.flush()
+}
+
+/// A shift of the pairing of rows: by how many rows, the mean difference of
+/// --shift-col under it if --auto-shift chose it, and the rows left out.
+type Shifted = (isize, Option<f64>, Excluded);
+
+/// How --duplicate-keys pairs a duplicated key's rows, e.g. "their sums".
+fn duplicated_pairing(duplicates: Duplicates) -> &'static str {
+  match duplicates {
+    Duplicates::Sum => "their sums",
+    _ => "their first rows",
+  }
+}
+
+/// With --rows, keeps only the rows at those physical lines of csv1, and
+/// the rows of csv2 paired with them; with --stride, only every Nth of
+/// those. Gives how many rows were kept and how many csv1 had, if it left
+/// any out.
+fn keep_rows(
+  args: &Args,
+  (csv1, records1): (&str, &mut Vec<StringRecord>),
+  records2: &mut Vec<StringRecord>,
+) -> Result<Option<(usize, usize)>, Fatal> {
+  if args.rows.is_none() && args.stride.is_none() {
+    return Ok(None);
+  }
+  let lines = args.rows.clone().unwrap_or(1..=usize::MAX);
+  let stride = args.stride.unwrap_or(1) as usize;
+  let mut within = 0;
+  let kept: Vec<bool> = (0..records1.len())
+    .map(|row| {
+      let contained = lines.contains(&physical_line(records1, row));
+      within += usize::from(contained);
+      contained && (within - 1).is_multiple_of(stride)
+    })
+    .collect();
+  let (rows, total) = (kept.iter().filter(|&&k| k).count(), records1.len());
+  if rows == 0 && args.rows.is_some() {
+    let to = match *lines.end() {
+      usize::MAX => String::new(),
+      end => format!(" to {end}"),
+    };
+    return Err(Fatal::new(format!(
+      "Error: --rows: {csv1} has no rows from line {}{to}",
+      lines.start()
+    )));
+  }
+  for records in [records1, records2] {
+    let mut row = 0;
+    records.retain(|_| {
+      row += 1;
+      kept.get(row - 1).copied().unwrap_or(false)
+    });
+  }
+  Ok((rows < total).then_some((rows, total)))
+}
+
+/// With --columns, leaves every other column of csv1 out of `opts`'s
+/// comparison; `width` is how many columns csv1 has.
+fn keep_columns(
+  args: &Args,
+  opts: DiffConfig,
+  header: Option<&StringRecord>,
+  width: usize,
+) -> Result<DiffConfig, Fatal> {
+  let Some(cols) = &args.columns else {
+    return Ok(opts);
+  };
+  let kept: Vec<usize> = cols
+    .split(',')
+    .map(|col| flag_column("--columns", col, header))
+    .collect::<Result<_, _>>()?;
+  let others: Vec<usize> = (0..width).filter(|i| !kept.contains(i)).collect();
+  Ok(overrides::without(&opts, &others, width))
+}
+
+/// With --shift-rows or --auto-shift, shifts the pairing of both files'
+/// rows, leaving out those without a partner.
+fn shift_rows(
+  args: &Args,
+  opts: &DiffConfig,
+  (csv1, records1): (&str, &mut Vec<StringRecord>),
+  (csv2, records2): (&str, &mut Vec<StringRecord>),
+  header: Option<&StringRecord>,
+) -> Result<Option<Shifted>, Fatal> {
+  let (shift, mean_diff) = match (args.shift_rows, args.auto_shift) {
+    (Some(shift), _) => (shift, None),
+    (None, Some(max)) => {
+      let col1 = flag_column("--shift-col", &args.shift_col, header)?;
+      let col2 = opts.col2(col1).ok_or_else(|| {
+        Fatal::new(format!(
+          "Error: --shift-col column {} isn't paired with a column of {csv2}",
+          args.shift_col
+        ))
+      })?;
+      let best = shift::best_shift(
+        (csv1, records1),
+        (csv2, records2),
+        (col1, col2),
+        max,
+      )
+      .map_err(|e| Fatal::new(format!("Error: --auto-shift: {e}")))?;
+      (best.shift, Some(best.mean_diff))
+    }
+    (None, None) => return Ok(None),
+  };
+  let excluded = shift::shift_rows(records1, records2, shift);
+  Ok(Some((shift, mean_diff, excluded)))
+}
+
+/// How a shift of rows came about and what it left out, e.g. `by 2 (chosen
+/// by --auto-shift: column t differs by 0.00E0 on average); left out 2
+/// row(s) at the start of a.csv`.
+fn shifted_rows(
+  args: &Args,
+  (shift, mean_diff, excluded): &Shifted,
+  (csv1, csv2): (&str, &str),
+) -> String {
+  let chosen = match mean_diff {
+    Some(mean) => format!(
+      " (chosen by --auto-shift: column {} differs by {mean:.2E} on average)",
+      args.shift_col
+    ),
+    None => String::new(),
+  };
+  let left_out: Vec<String> = [
+    (excluded.start.0, "start", csv1),
+    (excluded.start.1, "start", csv2),
+    (excluded.end.0, "end", csv1),
+    (excluded.end.1, "end", csv2),
+  ]
+  .into_iter()
+  .filter(|(rows, _, _)| *rows > 0)
+  .map(|(rows, end, path)| format!("{rows} row(s) at the {end} of {path}"))
+  .collect();
+  let left_out = match left_out.is_empty() {
+    true => "no rows".to_string(),
+    false => left_out.join(", "),
+  };
+  format!("by {shift}{chosen}; left out {left_out}")
+}
+
+/// With --percent-columns, infers each file's convention, sets the
+/// conversion to the common one and flags the values out of range once
+/// converted. Returns the inferences.
+fn percent_columns(
+  args: &Args,
+  opts: &mut DiffConfig,
+  (csv1, records1): (&str, &[StringRecord]),
+  (csv2, records2): (&str, &[StringRecord]),
+  header: Option<&StringRecord>,
+) -> Result<Option<(Inference, Inference)>, Fatal> {
+  let flag = "--percent-columns";
+  let Some(cols) = &args.percent_columns else {
+    return Ok(None);
+  };
+  let cols1: Vec<usize> = cols
+    .split(',')
+    .map(|col| flag_column(flag, col, header))
+    .collect::<Result<_, _>>()?;
+  let cols2: Vec<usize> = cols1
+    .iter()
+    .map(|&col| {
+      opts.col2(col).ok_or_else(|| {
+        Fatal::new(format!(
+          "Error: {flag}: column {} of {csv1} isn't compared with any column \
+           of {csv2}",
+          col + 1
+        ))
+      })
+    })
+    .collect::<Result<_, _>>()?;
+  let width = |records: &[StringRecord]| records.first().map_or(0, |r| r.len());
+  for (path, records, cols) in
+    [(csv1, records1, &cols1), (csv2, records2, &cols2)]
+  {
+    let width = width(records);
+    if let Some(col) = cols.iter().find(|&&col| col >= width) {
+      return Err(Fatal::new(format!(
+        "Error: {flag}: {path} has no column {}, only {width}",
+        col + 1
+      )));
+    }
+  }
+  let float_re = float_regex(args)?;
+  let inferred = (
+    percent::infer(records1, &cols1, &float_re),
+    percent::infer(records2, &cols2, &float_re),
+  );
+  let compared_as = match args.percent_convention.as_str() {
+    "percent" => Convention::Percent,
+    "fraction" => Convention::Fraction,
+    _ => inferred.0.convention,
+  };
+  let conventions = (inferred.0.convention, inferred.1.convention);
+  let converted = PercentColumns {
+    cols: cols1.clone(),
+    conventions,
+    compared_as,
+  };
+  for (path, records, cols, convention) in [
+    (csv1, records1, &cols1, conventions.0),
+    (csv2, records2, &cols2, conventions.1),
+  ] {
+    let factor = convention.factor_to(compared_as);
+    let outside =
+      percent::out_of_range(records, cols, (factor, compared_as), &float_re);
+    if let Some(first) = outside.first() {
+      exit::notice(format!(
+        "warning: {} value(s) of the percent columns of {path} are outside \
+         0 to {} as {}, e.g. line {}, column {}: {:.6E}",
+        outside.len(),
+        compared_as.full(),
+        compared_as.name(),
+        first.line,
+        first.col,
+        first.value
+      ));
+    }
+  }
+  opts.percent = Some(converted);
+  Ok(Some(inferred))
+}
+
+/// How the --percent-columns conventions were inferred, e.g. `a.csv taken
+/// as percent (largest value 7.35E1), b.csv as fraction (largest value
+/// 7.35E-1); compared as percent`.
+fn percent_conventions(
+  (inferred1, inferred2): &(Inference, Inference),
+  compared_as: Convention,
+  (csv1, csv2): (&str, &str),
+) -> String {
+  format!(
+    "{csv1} taken as {} (largest value {:.2E}), {csv2} as {} (largest value \
+     {:.2E}); compared as {}",
+    inferred1.convention.name(),
+    inferred1.largest,
+    inferred2.convention.name(),
+    inferred2.largest,
+    compared_as.name()
+  )
+}
+
+/// The rows paired out of order, e.g. `line 9 with line 10, line 10 with
+/// line 9`.
+fn reordered_rows(reordered: &[(usize, usize)]) -> String {
+  let pairs: Vec<String> = reordered
+    .iter()
+    .map(|(line1, line2)| format!("line {line1} with line {line2}"))
+    .collect();
+  pairs.join(", ")
+}
+
+/// With --sort-by, sorts both files' rows on the key columns, csv2's through
+/// the column map, and warns about keys several rows share. With
+/// --canonical-order, sorts them on all their columns instead.
+fn sort_rows(
+  args: &Args,
+  opts: &DiffConfig,
+  (csv1, records1): (&str, &mut [StringRecord]),
+  (csv2, records2): (&str, &mut [StringRecord]),
+) -> Result<(), Fatal> {
+  if args.canonical_order {
+    for (path, records) in [(csv1, records1), (csv2, records2)] {
+      if records.len() > args.max_rows {
+        return Err(Fatal::new(format!(
+          "Error: --canonical-order: {path} has {} rows, more than \
+           --max-rows {}",
+          records.len(),
+          args.max_rows
+        )));
+      }
+      sort::canonical_order(records);
+    }
+    return Ok(());
+  }
+  if args.sort_by.is_empty() {
+    return Ok(());
+  }
+  let keys1: Vec<usize> =
+    args.sort_by.iter().map(|&c| c as usize - 1).collect();
+  let keys2: Vec<usize> = keys1
+    .iter()
+    .map(|&col| {
+      opts.col2(col).ok_or_else(|| {
+        Fatal::new(format!(
+          "Error: --sort-by column {} isn't paired with a column of {csv2}",
+          col + 1
+        ))
+      })
+    })
+    .collect::<Result<_, _>>()?;
+  for (path, records, keys) in
+    [(csv1, records1, keys1), (csv2, records2, keys2)]
+  {
+    let shared = sort::sort_records(records, &keys, path)
+      .map_err(|e| Fatal::new(format!("Error: --sort-by: {e}")))?;
+    if let Some(first) = shared.first() {
+      let lines: Vec<String> = first.iter().map(usize::to_string).collect();
+      exit::notice(format!(
+        "warning: {} --sort-by key(s) are shared by several rows of {path} \
+         (e.g. at lines {}), which are paired in the order they come",
+        shared.len(),
+        lines.join(", ")
+      ));
+    }
+  }
+  Ok(())
+}
+
+/// The annotations taken off each of a pair of files.
+type Annotated = (Vec<Annotation>, Vec<Annotation>);
+
+/// With --strip-suffix, takes the annotations off a pair of files and notes
+/// how many cells of each had one. Returns what was taken off each.
+fn strip_suffixes(
+  args: &Args,
+  (csv1, records1): (&str, &mut [StringRecord]),
+  (csv2, records2): (&str, &mut [StringRecord]),
+) -> Result<Option<Annotated>, Fatal> {
+  let Some(pattern) = &args.strip_suffix else {
+    return Ok(None);
+  };
+  let suffix = regex::Regex::new(pattern)
+    .map_err(|e| Fatal::new(format!("Error: bad --strip-suffix: {e}")))?;
+  let float_re = float_regex(args)?;
+  let taken1 = annotations::take_annotations(records1, &suffix, &float_re);
+  let taken2 = annotations::take_annotations(records2, &suffix, &float_re);
+  if !taken1.is_empty() || !taken2.is_empty() {
+    exit::note(format!(
+      "note: took annotations off {} cell(s) of {csv1} and {} of {csv2}",
+      taken1.len(),
+      taken2.len()
+    ));
+  }
+  Ok(Some((taken1, taken2)))
+}
+
+/// The worst violation of the first gate that failed, or with none the
+/// first column whose sums failed: a physical line of csv1, unless judged
+/// per column, and a (1-based) column. The gates don't count with
+/// --sum-check-only.
+fn worst_violation(report: &DiffReport) -> Option<(Option<usize>, usize)> {
+  let sums_only = report.sum_check == SumCheck::Only;
+  let failed = report.gates.iter().find(|g| !g.passed && !sums_only);
+  let Some(failed) = failed else {
+    let sum = column_sums(report).into_iter().find(|s| !s.passed)?;
+    return Some((None, sum.col));
+  };
+  let at = |worst: &Extremum| {
+    (worst.line > 0).then_some((Some(worst.line), worst.col))
+  };
+  match failed.gate {
+    Gate::Ratio => at(&report.max_ratio),
+    // scaled row by row, the cell nearest its own limit
+    Gate::Diff => match &report.scaled_diff {
+      Some(scaled) => at(&scaled.worst),
+      None => at(&report.max_diff),
+    },
+    Gate::Digits => report.worst_digits().map(|(_, col)| (None, col)),
+    Gate::Allclose => at(&report.allclose),
+  }
+}
+
+/// The command re-running a failing pair on the scope of its worst
+/// violation, quoted for --repro-shell: the options of `argv`, the command
+/// line `args` were parsed from, less those writing elsewhere, with --rows,
+/// --columns and --explain-cell pointing at it. Options that look at whole
+/// columns, or across columns, would see only the cell, so with them the
+/// rows, or the columns, are left as the run had them. None with
+/// --transpose1, which they can't be used with.
+fn reproduction(
+  args: &Args,
+  argv: &[String],
+  pair: (&str, &str),
+  tolerances: &Tolerances,
+  (report, header): (&DiffReport, Option<&StringRecord>),
+) -> Option<String> {
+  if args.transpose1 {
+    return None;
+  }
+  let (line, col) = worst_violation(report)?;
+  let col = column_arg(col, header);
+  let whole_columns = args.normalize_columns != Normalization::None
+    || args.comparison.threshold_frac > 0.0
+    || args.allow_sign_flip
+    || !args.allow_sign_flip_cols.is_empty()
+    || args.sum_check
+    || args.percent_columns.is_some()
+    || args.matrix_mode;
+  let across_columns = args.mac_match
+    || !args.vector_cols.is_empty()
+    || args.complex_pairs.is_some()
+    || args.matrix_mode;
+  let mut scope = Vec::new();
+  if let Some(line) = line
+    && !whole_columns
+  {
+    scope.extend(["--rows".to_string(), line.to_string()]);
+  }
+  if !across_columns {
+    scope.extend(["--columns".to_string(), col.clone()]);
+  }
+  if let Some(line) = line {
+    scope.extend(["--explain-cell".to_string(), format!("{line}:{col}")]);
+  }
+  // a manifest's tolerances are the pair's, unless profiles override them
+  let manifest =
+    (args.manifest.is_some() && args.profiles.is_empty()).then_some(tolerances);
+  let arguments =
+    repro::arguments(&Args::command(), argv, pair, manifest, &scope);
+  Some(args.repro_shell.command(&argv[0], &arguments))
+}
+
+/// The waivers excusing a failing pair: the first covering the whole pair,
+/// or else those covering columns it compared, if it passes without them.
+/// Empty if none do.
+fn waive(
+  waivers: &[Waiver],
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  (opts, report): (&DiffConfig, &DiffReport),
+  annotated: Option<&Annotated>,
+  (csv1, csv2): (&str, &str),
+) -> Vec<Waiver> {
+  let covering: Vec<&Waiver> =
+    waivers.iter().filter(|w| w.covers((csv1, csv2))).collect();
+  if let Some(waiver) = covering.iter().find(|w| w.column.is_none()) {
+    return vec![(*waiver).clone()];
+  }
+  let compared = |col: usize| report.columns.iter().any(|c| c.col == col);
+  let columns: Vec<&Waiver> = covering
+    .into_iter()
+    .filter(|w| w.column.is_some_and(compared))
+    .collect();
+  if columns.is_empty() {
+    return Vec::new();
+  }
+  // compare again without the waived columns
+  let waived = |col: usize| columns.iter().any(|w| w.column == Some(col + 1));
+  let width = records1.first().map_or(0, StringRecord::len);
+  let pairs: Vec<(usize, usize)> = (0..width)
+    .filter(|&col| !waived(col))
+    .filter_map(|col| Some((col, opts.col2(col)?)))
+    .collect();
+  let opts = DiffConfig {
+    // the pairs come from a map, or are all different
+    column_map: Some(ColumnMap::new(&pairs).unwrap()),
+    ..opts.clone()
+  };
+  let Ok(mut rest) = compare::compare(records1, records2, &opts, (csv1, csv2))
+  else {
+    return Vec::new();
+  };
+  if let Some((taken1, taken2)) = annotated {
+    rest.annotation_mismatches =
+      annotations::mismatches(records1, (taken1, taken2), &opts, &rest);
+  }
+  match rest.passed() {
+    true => columns.into_iter().cloned().collect(),
+    false => Vec::new(),
+  }
+}
+
+/// Writes the waivers excusing a failure, and the WAIVED result, for
+/// --explain.
+fn write_waivers(
+  out: &mut dyn Write,
+  painter: &Painter,
+  waivers: &[Waiver],
+) -> io::Result<()> {
+  writeln!(out)?;
+  for waiver in waivers {
+    writeln!(out, "waived by {}", waiver.describe())?;
+  }
+  writeln!(out, "result: {}", painter.paint("WAIVED", Paint::Waived))
+}
+
+/// The column map of a pair of files with these headers, from --col-map or
+/// --match-by-header if either was given, after noting the columns of each
+/// file it leaves out.
+fn column_map(
+  args: &Args,
+  (csv1, records1): (&str, &[StringRecord]),
+  (csv2, records2): (&str, &[StringRecord]),
+  headers: Option<&(StringRecord, StringRecord)>,
+) -> Result<Option<ColumnMap>, Fatal> {
+  if args.match_by_header {
+    let Some((header1, header2)) = headers else {
+      return Ok(None);
+    };
+    let matched = headers::match_by_name((csv1, header1), (csv2, header2))
+      .map_err(|e| Fatal::new(format!("Error: {e}")))?;
+    let (only1, only2) = &matched.only;
+    for (path, other, only, header) in
+      [(csv1, csv2, only1, header1), (csv2, csv1, only2, header2)]
+    {
+      if only.is_empty() {
+        continue;
+      }
+      let names: Vec<&str> = only.iter().map(|&i| header[i].trim()).collect();
+      let missing = format!(
+        "column(s) {} of {path} aren't in the header of {other}",
+        names.join(", ")
+      );
+      if args.require_all_columns {
+        let diff = headerdiff::diff_headers(header1, header2);
+        exit::notice(header_diff(&diff, (csv1, csv2)));
+        return Err(Fatal::new(format!("Error: {missing}")));
+      }
+      exit::notice(format!("warning: {missing}, so not compared"));
+    }
+    return Ok(Some(matched.map));
+  }
+  let Some(path) = &args.col_map else {
+    return Ok(None);
+  };
+  let headers = headers.map(|(h1, h2)| (h1, h2));
+  let map = colmap::load(path, headers)
+    .map_err(|e| Fatal::new(format!("Error: {e}")))?;
+  let width = |records: &[StringRecord]| records.first().map_or(0, |r| r.len());
+  let paired2: Vec<usize> = map.pairs().map(|(_, col2)| col2).collect();
+  let unpaired1: Vec<String> = (0..width(records1))
+    .filter(|&col1| map.get(col1).is_none())
+    .map(|col1| (col1 + 1).to_string())
+    .collect();
+  let unpaired2: Vec<String> = (0..width(records2))
+    .filter(|col2| !paired2.contains(col2))
+    .map(|col2| (col2 + 1).to_string())
+    .collect();
+  for (path, unpaired) in [(csv1, unpaired1), (csv2, unpaired2)] {
+    if !unpaired.is_empty() {
+      exit::note(format!(
+        "note: not comparing column(s) {} of {path}, which --col-map \
+         leaves unpaired",
+        unpaired.join(", ")
+      ));
+    }
+  }
+  Ok(Some(map))
+}
+
+/// The column map pairing the columns --mac-match paired.
+fn mac_column_map(matching: &ColumnMatching) -> ColumnMap {
+  let pairs: Vec<(usize, usize)> =
+    matching.pairs.iter().map(|p| (p.col1, p.col2)).collect();
+  // each column is paired at most once
+  ColumnMap::new(&pairs).unwrap()
+}
+
+/// Allows the signs of the columns --mac-match paired with columns pointing
+/// the opposite way to be flipped, on top of those already allowed.
+fn allow_opposed_flips(opts: &mut DiffConfig, matching: &ColumnMatching) {
+  let opposed = matching.pairs.iter().filter(|p| p.opposed).map(|p| p.col1);
+  match &mut opts.sign_flip {
+    SignFlip::AnyColumn => {}
+    SignFlip::Columns(cols) => cols.extend(opposed),
+    SignFlip::Never => {
+      let cols: Vec<usize> = opposed.collect();
+      if !cols.is_empty() {
+        opts.sign_flip = SignFlip::Columns(cols);
+      }
+    }
+  }
+}
+
+/// The columns --mac-match paired out of place or with opposite signs, e.g.
+/// `3 with 4 (MAC 0.998), 4 with 3 (MAC 0.997, opposed)`.
+fn mac_pairs(pairs: &[MacPair]) -> String {
+  let pairs: Vec<String> = pairs
+    .iter()
+    .filter(|p| p.col1 != p.col2 || p.opposed)
+    .map(|p| {
+      let opposed = if p.opposed { ", opposed" } else { "" };
+      format!(
+        "{} with {} (MAC {:.3}{opposed})",
+        p.col1 + 1,
+        p.col2 + 1,
+        p.mac
+      )
+    })
+    .collect();
+  match pairs.is_empty() {
+    true => "none".to_string(),
+    false => pairs.join(", "),
+  }
+}
+
+/// Writes the MAC matrix --mac-match paired columns by, a row per float
+/// column of csv1 and a column per float column of csv2, marking the pairs
+/// with `*`.
+fn write_mac_matrix(
+  out: &mut dyn Write,
+  matching: &ColumnMatching,
+) -> io::Result<()> {
+  let (cols1, cols2) = &matching.matrix.cols;
+  let headers: Vec<String> = std::iter::once("csv1\\csv2".to_string())
+    .chain(cols2.iter().map(|col| (col + 1).to_string()))
+    .collect();
+  let rows: Vec<Vec<Cell>> = cols1
+    .iter()
+    .zip(&matching.matrix.values)
+    .map(|(&col1, values)| {
+      let cells = cols2.iter().zip(values).map(|(&col2, value)| {
+        let paired = matching
+          .pairs
+          .iter()
+          .any(|p| (p.col1, p.col2) == (col1, col2));
+        let mark = if paired { "*" } else { "" };
+        (format!("{value:.3}{mark}"), Paint::Plain)
+      });
+      std::iter::once(((col1 + 1).to_string(), Paint::Plain))
+        .chain(cells)
+        .collect()
+    })
+    .collect();
+  let painter = Painter::new(ColorChoice::Never, false);
+  let table =
+    align_table(&headers, &rows, &Alignment::Left, None, true, &painter);
+  for line in table.lines() {
+    writeln!(out, "  {}", line.trim_end())?;
+  }
+  Ok(())
+}
+
+/// A group's line in the report, e.g. `SUBCASE=2: 10 and 10 rows, ratio
+/// 0.12% (line 14, column 3), 20 cells compared, FAIL`.
+fn group_line(
+  col: &str,
+  group: &GroupReport,
+  tolerances: &Tolerances,
+  painter: &Painter,
+) -> String {
+  let rows = format!("{} and {} rows", group.rows.0, group.rows.1);
+  let label = format!("{col}={}", group.value);
+  scope_line(&label, vec![rows], &group.report, tolerances, painter)
+}
+
+/// A column's line, when it has tolerances of its own, e.g. `column 4
+/// (Stress): ratio 5.00% (line 3, column 4), 10 cells compared, FAIL`.
+fn override_line(
+  column: &ColumnOverride,
+  header: Option<&StringRecord>,
+  painter: &Painter,
+) -> String {
+  let label = match header.and_then(|h| h.get(column.col - 1)) {
+    Some(name) => format!("column {} ({})", column.col, name.trim()),
+    None => format!("column {}", column.col),
+  };
+  scope_line(
+    &label,
+    Vec::new(),
+    &column.report,
+    &column.tolerances,
+    painter,
+  )
+}
+
+/// A gate of the warning tolerances, e.g. `max ratio 0.73% with values
+/// +1.000000E+00 and +1.007300E+00 at line 3, column 2, margin -0.23` when
+/// it's raised, or `ratio within the warning tolerance, margin +0.27`.
+fn warning_line(result: &GateResult, report: &DiffReport) -> String {
+  match report.warnings_raised().any(|w| w.gate == result.gate) {
+    true => junit::failure_message(result, report),
+    false => format!(
+      "{} within the warning tolerance, margin {}",
+      result.gate.name(),
+      result.margin_text()
+    ),
+  }
+}
+
+/// Writes the explanation of the impact classes: each column's
+/// significance, then each class's violations and worst cells.
+fn write_impact(
+  out: &mut dyn Write,
+  impact: &ImpactReport,
+  report: &DiffReport,
+  opts: &DiffConfig,
+  headers: Option<&(StringRecord, StringRecord)>,
+) -> io::Result<()> {
+  let significance: Vec<String> = opts
+    .significance
+    .iter()
+    .map(|(col, s)| format!("column {} {s:.2E}", col + 1))
+    .collect();
+  writeln!(
+    out,
+    "\nimpact classes, by significance ({}):",
+    significance.join(", ")
+  )?;
+  for (class, cells) in impact_classes(impact) {
+    writeln!(out, "  {}", impact_line(class, cells, report))?;
+    if cells.compared == 0 {
+      continue;
+    }
+    let worst = &cells.max_diff;
+    writeln!(
+      out,
+      "    worst absolute difference {:.2E}, the values {:+.6E} and {:+.6E} \
+       (line {}, {})",
+      worst.value,
+      worst.vals.0,
+      worst.vals.1,
+      worst.line,
+      column_label(opts, worst.col, headers)
+    )?;
+  }
+  if opts.high_impact_only {
+    writeln!(
+      out,
+      "(only the HIGH-IMPACT cells are judged, per --fail-only-high-impact)"
+    )?;
+  }
+  Ok(())
+}
+
+/// A direction's worst cells against its tolerances, e.g. `over (csv2
+/// above csv1): worst 2.10% (allowed 1.00%) at line 5, column 3; worst
+/// absolute difference 3.00E-02 (allowed 5.00E-02) at line 4, column 2`.
+fn direction_line(
+  direction: Direction,
+  report: &DiffReport,
+  tolerances: &Tolerances,
+) -> String {
+  let directed = report.directions.of(direction);
+  let at = |worst: &Extremum| match worst.line {
+    0 => String::new(),
+    line => format!(" at line {line}, column {}", worst.col),
+  };
+  let mut parts = Vec::new();
+  if let Some(mr) = tolerances.ratio_limit(direction) {
+    let worst = &directed.max_ratio;
+    parts.push(format!(
+      "worst {:.2}% (allowed {:.2}%){}",
+      report.rel_mode.percent(worst.value),
+      mr * 100.0,
+      at(worst)
+    ));
+  }
+  if let Some(md) = tolerances.diff_limit(direction) {
+    let worst = &directed.max_diff;
+    parts.push(format!(
+      "worst absolute difference {:.2E} (allowed {md:.2E}){}",
+      worst.value,
+      at(worst)
+    ));
+  }
+  let relation = match direction {
+    Direction::Over => "above",
+    Direction::Under => "not above",
+  };
+  format!(
+    "{} (csv2 {relation} csv1): {}",
+    direction.name(),
+    parts.join("; ")
+  )
+}
+
+/// A tolerance's limits over and under, if they differ.
+fn directed_limits(
+  limit: impl Fn(Direction) -> Option<f64>,
+) -> Option<(f64, f64)> {
+  match (limit(Direction::Over), limit(Direction::Under)) {
+    (Some(over), Some(under)) if over != under => Some((over, under)),
+    _ => None,
+  }
+}
+
+/// Writes the explanation of directional tolerances: each direction's
+/// worst cells against its tolerances, with their values.
+fn write_directions(
+  out: &mut dyn Write,
+  report: &DiffReport,
+  tolerances: &Tolerances,
+  opts: &DiffConfig,
+  headers: Option<&(StringRecord, StringRecord)>,
+) -> io::Result<()> {
+  writeln!(
+    out,
+    "\nworst cells by direction, of the values as compared:"
+  )?;
+  for direction in [Direction::Over, Direction::Under] {
+    writeln!(out, "  {}", direction_line(direction, report, tolerances))?;
+    let directed = report.directions.of(direction);
+    let judged = [
+      ("ratio", &directed.max_ratio, tolerances.max_ratio.is_some()),
+      (
+        "absolute difference",
+        &directed.max_diff,
+        tolerances.max_diff.is_some(),
+      ),
+    ];
+    for (name, worst, judged) in judged {
+      if !judged || worst.line == 0 {
+        continue;
+      }
+      writeln!(
+        out,
+        "    worst {name}: the values {:+.6E} and {:+.6E} (line {}, {})",
+        worst.vals.0,
+        worst.vals.1,
+        worst.line,
+        column_label(opts, worst.col, headers)
+      )?;
+    }
+  }
+  Ok(())
+}
+
+/// A line on part of a comparison: its label, then `fields`, each gate's
+/// worst value and where it is, if anywhere, the cells compared and the
+/// verdict.
+fn scope_line(
+  label: &str,
+  mut fields: Vec<String>,
+  report: &DiffReport,
+  tolerances: &Tolerances,
+  painter: &Painter,
+) -> String {
+  // nowhere, with no compared cell differing
+  let located = |worst: &Extremum| match worst.line {
+    0 => String::new(),
+    line => format!(" (line {line}, column {})", worst.col),
+  };
+  if tolerances.max_ratio.is_some() {
+    fields.push(format!(
+      "ratio {:.2}%{}",
+      report.ratio_percent(),
+      located(&report.max_ratio)
+    ));
+  }
+  if tolerances.max_diff.is_some() {
+    let worst = &report.max_diff;
+    fields.push(format!("diff {:.2E}{}", worst.value, located(worst)));
+  }
+  if tolerances.min_digits.is_some() {
+    fields.push(match report.worst_digits() {
+      Some((digits, col)) => format!("{digits:.2} digits (column {col})"),
+      None => "digits n/a".to_string(),
+    });
+  }
+  fields.push(format!("{} cells compared", thousands(report.compared())));
+  fields.push(painter.status(report.passed()));
+  format!("{label}: {}", fields.join(", "))
+}
+
+/// Compares a pair of scalars files, as --scalars asks, and writes a row
+/// per scalar with both values, their differences and its verdict. Returns
+/// the pair's result line, but for the time taken.
+fn scalars(
+  args: &Args,
+  tolerances: &Tolerances,
+  named: &BTreeMap<String, Tolerances>,
+  painter: Painter,
+  out: &mut dyn Write,
+  csv1: &str,
+  csv2: &str,
+) -> Result<ResultLine, Fatal> {
+  let mut records1 = read_records(args, csv1)?;
+  let mut records2 = read_records(args, csv2)?;
+  strip_headers(args, (csv1, &mut records1), (csv2, &mut records2))?;
+  strip_suffixes(args, (csv1, &mut records1), (csv2, &mut records2))?;
+  let opts = diff_config(args, tolerances)?;
+  let scalars =
+    scalars::compare_scalars(&records1, &records2, &opts, named, (csv1, csv2))
+      .map_err(|e| {
+        Fatal::new(format!("Error comparing {csv1} and {csv2}: {e}"))
+      })?;
+  if opts.scale != (1.0, 1.0) {
+    exit::note(format!(
+      "note: values of {csv1} scaled by {}, of {csv2} by {}, before comparing",
+      opts.scale.0, opts.scale.1
+    ));
+  }
+  for scalar in &scalars {
+    if let ScalarOutcome::Missing {
+      file,
+      allowed: true,
+    } = scalar.outcome
+    {
+      let path = if file == 1 { csv2 } else { csv1 };
+      exit::notice(format!("warning: {} is only in {path}", scalar.name));
+    }
+  }
+
+  let compared: Vec<&DiffReport> = scalars
+    .iter()
+    .filter_map(|s| match &s.outcome {
+      ScalarOutcome::Compared(alone) => Some(alone.as_ref()),
+      _ => None,
+    })
+    .collect();
+  let passed = scalars.iter().all(|s| s.passed());
+  let line = ResultLine {
+    pair: Some((csv1.to_string(), csv2.to_string())),
+    passed,
+    worst_ratio_pct: compared
+      .iter()
+      .map(|alone| alone.ratio_percent())
+      .reduce(f64::max),
+    worst_diff: compared
+      .iter()
+      .map(|alone| alone.max_diff.value)
+      .reduce(f64::max),
+    cells: Some(compared.len()),
+    violations: Some(scalars.iter().filter(|s| !s.passed()).count()),
+    ..Default::default()
+  };
+  if args.quiet > 0 {
+    return Ok(line);
+  }
+
+  let headers = [
+    "name", "value1", "value2", "abs_diff", "ratio_%", "digits", "status",
+    "note",
+  ]
+  .map(String::from);
+  let precision = precision(args);
+  let rows: Vec<Vec<Cell>> = scalars
+    .iter()
+    .map(|scalar| {
+      let passed = scalar.passed();
+      let value =
+        |value: Option<f64>, text: &Option<String>| match (value, text) {
+          (Some(v), _) => precision.value(v),
+          (None, Some(text)) => text.clone(),
+          (None, None) => "-".to_string(),
+        };
+      let (v1, v2) = scalar.values;
+      let mut row = vec![
+        (scalar.name.clone(), Paint::Plain),
+        value_cell(value(v1, &scalar.text.0), passed),
+        value_cell(value(v2, &scalar.text.1), passed),
+      ];
+      let (measures, note) = match &scalar.outcome {
+        ScalarOutcome::Compared(alone) => {
+          let digits = alone
+            .worst_digits()
+            .map_or("n/a".to_string(), |(d, _)| format!("{d:.2}"));
+          let measures = [
+            format!("{:.2E}", alone.max_diff.value),
+            precision.percent(alone.ratio_percent()),
+            digits,
+          ];
+          (Some(measures), String::new())
+        }
+        ScalarOutcome::Skipped => (None, "below the threshold".to_string()),
+        ScalarOutcome::NotFloat => (None, "not a number".to_string()),
+        ScalarOutcome::Missing { file, .. } => {
+          let path = if *file == 1 { csv1 } else { csv2 };
+          (None, format!("missing from {path}"))
+        }
+      };
+      let measures = measures.unwrap_or_else(|| ["-"; 3].map(String::from));
+      row.extend(measures.into_iter().map(|m| value_cell(m, passed)));
+      row.push(status_cell(passed));
+      row.push((note, Paint::Plain));
+      row
+    })
+    .collect();
+  let (name1, name2) = report_names(args, (csv1, csv2));
+  writeln!(out, "files: {name1} and {name2}\n")?;
+  let align = args.align.as_ref().unwrap_or(&Alignment::Left);
+  let table = align_table(&headers, &rows, align, args.width, true, &painter);
+  for line in table.lines() {
+    writeln!(out, "  {}", line.trim_end())?;
+  }
+  writeln!(out, "\nresult: {}", painter.status(passed))?;
+  Ok(line)
+}
+
+/// The (1-based) column an --explain-cell names.
+fn cell_column(
+  cell: &CellRef,
+  header: Option<&StringRecord>,
+) -> Result<usize, Fatal> {
+  if let Ok(col) = cell.col.parse() {
+    return Ok(col);
+  }
+  let Some(header) = header else {
+    return Err(Fatal::new(format!(
+      "Error: --explain-cell: column {} needs --has-headers",
+      cell.col
+    )));
+  };
+  match header.iter().position(|name| name == cell.col) {
+    Some(i) => Ok(i + 1),
+    None => Err(Fatal::new(format!(
+      "Error: --explain-cell: no column named {} in the header",
+      cell.col
+    ))),
+  }
+}
+
+/// How a report names a (1-based) column of csv1, by its header name if
+/// known. With a column map, the column of csv2 compared with it is named
+/// too.
+fn column_label(
+  opts: &DiffConfig,
+  col: usize,
+  headers: Option<&(StringRecord, StringRecord)>,
+) -> String {
+  let name = |header: Option<&StringRecord>, i: usize| {
+    header
+      .and_then(|h| h.get(i))
+      .map_or(String::new(), |n| format!(" ({n})"))
+  };
+  let Some(i) = col.checked_sub(1) else {
+    return format!("column {col}");
+  };
+  let label = format!("column {col}{}", name(headers.map(|h| &h.0), i));
+  match (&opts.column_map, opts.col2(i)) {
+    (Some(_), Some(j)) => format!(
+      "{label}, against csv2's column {}{}",
+      j + 1,
+      name(headers.map(|h| &h.1), j)
+    ),
+    _ => label,
+  }
+}
+
+/// Writes everything about how a cell was compared.
+fn explain_cell(
+  out: &mut dyn Write,
+  (painter, precision): (&Painter, Precision),
+  cell: &CellExplanation,
+  headers: Option<&(StringRecord, StringRecord)>,
+  opts: &DiffConfig,
+) -> io::Result<()> {
+  writeln!(
+    out,
+    "cell at line {}, {}:",
+    cell.line,
+    column_label(opts, cell.col, headers)
+  )?;
+  writeln!(out, "  csv1 text: {:?} (line {})", cell.text.0, cell.line)?;
+  writeln!(out, "  csv2 text: {:?} (line {})", cell.text.1, cell.line2)?;
+  let value =
+    |v: Option<f64>| v.map_or("-".to_string(), |v| precision.value(v));
+  writeln!(
+    out,
+    "  values: {} and {}{}",
+    value(cell.values.0),
+    value(cell.values.1),
+    if cell.flipped {
+      " (csv2's column is sign-flipped)"
+    } else {
+      ""
+    }
+  )?;
+  for (file, clamped) in [("csv1", cell.clamped.0), ("csv2", cell.clamped.1)] {
+    if clamped {
+      writeln!(out, "  {file}'s value is subnormal, so taken as zero")?;
+    }
+  }
+  if let Some(cutoff) = cell.cutoff
+    && cutoff > 0.0
+  {
+    let which = match opts.threshold_mode {
+      ThresholdMode::Both => "both are",
+      ThresholdMode::Either => "either is",
+      ThresholdMode::Baseline => "csv1's is",
+    };
+    writeln!(
+      out,
+      "  skipped if {which} below: {cutoff:.2E} (--threshold {:.2E}, \
+       --threshold-frac {}, --threshold-mode {})",
+      opts.threshold,
+      opts.threshold_frac,
+      opts.threshold_mode.name()
+    )?;
+  }
+  let alone = match &cell.outcome {
+    Outcome::NotCompared => {
+      return writeln!(
+        out,
+        "  not compared: column {} isn't all floats in both files",
+        cell.col
+      );
+    }
+    Outcome::Excluded => {
+      return writeln!(out, "  skipped: listed in --exclude-cells");
+    }
+    Outcome::BothZero => {
+      return writeln!(out, "  skipped: both values are zero");
+    }
+    Outcome::BelowCutoff(skip) => {
+      let which = match skip {
+        Skip::Below1 => "csv1's value is",
+        Skip::Below2 => "csv2's value is",
+        _ => "both values are",
       };
-      println!("result: {status}");
+      return writeln!(out, "  skipped: {which} below the cutoff");
+    }
+    Outcome::Compared(alone) => alone,
+  };
+  writeln!(out, "  abs diff: {:.2E}", alone.max_diff.value)?;
+  writeln!(
+    out,
+    "  relative difference (--rel-mode {}): {}%",
+    alone.rel_mode.name(),
+    precision.percent(alone.ratio_percent())
+  )?;
+  if alone.ratio_floored() {
+    writeln!(
+      out,
+      "  (measured against the --rel-floor of {:.2E}, not the values)",
+      alone.rel_floor
+    )?;
+  }
+  if let Some((digits, _)) = alone.worst_digits() {
+    writeln!(out, "  digits of agreement: {digits:.2}")?;
+  }
+  for gate in &alone.gates {
+    writeln!(
+      out,
+      "  {} gate: {} (margin {})",
+      gate.gate.name(),
+      painter.status(gate.passed),
+      gate.margin_text()
+    )?;
+  }
+  Ok(())
+}
+
+/// How many digits the report gives values and percentages, as
+/// --report-precision and --percent-precision ask.
+fn precision(args: &Args) -> Precision {
+  Precision {
+    values: args.report_precision as usize,
+    percent: args.percent_precision as usize,
+  }
+}
+
+/// How wide the report may be: the terminal's width when writing to one,
+/// going by $COLUMNS, and otherwise a fixed 80 columns, so that reports in
+/// files are the same wherever they're made.
+fn report_width(args: &Args) -> usize {
+  let columns = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok());
+  match columns {
+    Some(columns) if args.output.is_none() && io::stdout().is_terminal() => {
+      columns
+    }
+    _ => 80,
+  }
+}
+
+/// Writes a histogram of relative differences, one bin a line, with a bar
+/// of '#' scaled so that the fullest bin fills a line `width` wide.
+fn write_histogram(
+  out: &mut dyn Write,
+  histogram: &Histogram,
+  width: usize,
+) -> io::Result<()> {
+  let labels = histogram.labels();
+  let counts: Vec<usize> = histogram
+    .counts
+    .iter()
+    .copied()
+    .chain([histogram.undefined])
+    .collect();
+  let total = histogram.total();
+  writeln!(
+    out,
+    "relative differences of the {} compared cells:",
+    thousands(total)
+  )?;
+  let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+  let count_width = counts.iter().map(|&c| thousands(c).len()).max();
+  let count_width = count_width.unwrap_or(0);
+  // the indent, label, count and percentage come first
+  let bar_width = width.saturating_sub(label_width + count_width + 14);
+  let fullest = counts.iter().copied().max().unwrap_or(0).max(1);
+  for (label, &count) in labels.iter().zip(&counts) {
+    let percent = match total {
+      0 => 0.0,
+      total => count as f64 * 100.0 / total as f64,
+    };
+    let bar = "#".repeat((count * bar_width.max(1)).div_ceil(fullest));
+    let line = format!(
+      "  {label:<label_width$}  {:>count_width$}  {percent:6.2}% {bar}",
+      thousands(count)
+    );
+    writeln!(out, "{}", line.trim_end())?;
+  }
+  Ok(())
+}
+
+/// Writes the percentiles of the relative differences, one a line, after
+/// how many cells they're of and how many were left out.
+fn write_percentiles(
+  out: &mut dyn Write,
+  percentiles: &Percentiles,
+) -> io::Result<()> {
+  let accuracy = match percentiles.exact() {
+    true => "exact".to_string(),
+    false => format!("within {}%", percentiles::ACCURACY * 100.0),
+  };
+  writeln!(
+    out,
+    "relative difference percentiles of the {} compared cells ({accuracy}; \
+     {} skipped, {} undefined left out):",
+    thousands(percentiles.counted()),
+    thousands(percentiles.skipped),
+    thousands(percentiles.undefined)
+  )?;
+  let labels: Vec<String> =
+    percentiles.at.iter().map(|p| format!("p{p}")).collect();
+  let width = labels.iter().map(String::len).max().unwrap_or(0);
+  for (label, (_, value)) in labels.iter().zip(percentiles.values()) {
+    match value {
+      Some(value) => writeln!(out, "  {label:<width$}  {value:.4E}%")?,
+      None => writeln!(out, "  {label:<width$}  -")?,
+    }
+  }
+  Ok(())
+}
+
+/// Sets -r from --max-percent, which gives it in percent.
+fn percent_to_ratio(args: &mut Args) {
+  if let Some(percent) = args.max_percent {
+    args.max_ratio = Some(percent / 100.0);
+  }
+}
+
+/// Takes the config file's defaults for the options not given on the
+/// command line. Returns the names of those taken, in the config file's
+/// terms, or fails naming a default that isn't a valid value.
+fn apply_defaults(
+  args: &mut Args,
+  matches: &ArgMatches,
+  defaults: &Defaults,
+) -> Result<Vec<&'static str>, String> {
+  let given =
+    |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+  let mut taken = Vec::new();
+  if let Some(max_diff) = defaults.max_diff
+    && !given("max_diff")
+  {
+    args.max_diff = Some(max_diff);
+    taken.push("max_diff");
+  }
+  if let Some(max_ratio) = defaults.max_ratio
+    && !given("max_ratio")
+    && !given("max_percent")
+  {
+    args.max_ratio = Some(max_ratio);
+    taken.push("max_ratio");
+  }
+  if let Some(warn_ratio) = defaults.warn_ratio
+    && !given("warn_ratio")
+  {
+    args.warn_ratio = Some(warn_ratio);
+    taken.push("warn_ratio");
+  }
+  if let Some(warn_diff) = defaults.warn_diff
+    && !given("warn_diff")
+  {
+    args.warn_diff = Some(warn_diff);
+    taken.push("warn_diff");
+  }
+  if let Some(min_digits) = defaults.min_digits
+    && !given("min_digits")
+  {
+    args.min_digits = Some(min_digits);
+    taken.push("min_digits");
+  }
+  if let Some(threshold) = defaults.threshold
+    && !given("threshold")
+  {
+    args.comparison.threshold = threshold;
+    taken.push("threshold");
+  }
+  if let Some(delim) = defaults.delim
+    && !given("delim")
+  {
+    args.comparison.delim = delim;
+    taken.push("delim");
+  }
+  if let Some(format) = &defaults.format
+    && !given("format")
+  {
+    args.format = format
+      .parse()
+      .map_err(|e| format!("defaults.format: {e}"))?;
+    taken.push("format");
+  }
+  if let Some(pattern) = &defaults.ignore_rows_matching
+    && !given("ignore_rows_matching")
+  {
+    args.ignore_rows_matching = Some(pattern.clone());
+    taken.push("ignore_rows_matching");
+  }
+  Ok(taken)
+}
+
+/// Prints the configuration in effect as TOML, for --print-config: the
+/// config file's, with the defaults as merged with the command line, and
+/// comments on where they came from.
+fn print_config(
+  args: &Args,
+  config: &Config,
+  path: Option<&Path>,
+  taken: &[&str],
+) {
+  let effective = Config {
+    defaults: Defaults {
+      max_diff: args.max_diff,
+      max_ratio: args.max_ratio,
+      warn_ratio: args.warn_ratio,
+      warn_diff: args.warn_diff,
+      min_digits: args.min_digits,
+      threshold: Some(args.comparison.threshold),
+      delim: Some(args.comparison.delim),
+      format: Some(args.format.name().to_string()),
+      ignore_rows_matching: args.ignore_rows_matching.clone(),
+    },
+    ..config.clone()
+  };
+  let text = toml::to_string(&effective).unwrap_or_else(|e| {
+    exit::die(format!("Error: could not print the configuration: {e}"))
+  });
+  match path {
+    Some(path) => println!("# config file: {}", path.display()),
+    None => println!("# no config file"),
+  }
+  if !taken.is_empty() {
+    println!("# defaults taken from it: {}", taken.join(", "));
+  }
+  println!("# other defaults are the command line's or built in\n");
+  print!("{text}");
+}
+
+/// The config file's [columns] tables, by (0-based) column of csv1, in
+/// column order. `width` is how many columns csv1 has.
+fn column_specs<'a>(
+  specs: &'a BTreeMap<String, ColumnSpec>,
+  (csv1, width): (&str, usize),
+  header: Option<&StringRecord>,
+) -> Result<Vec<(usize, &'a ColumnSpec)>, Fatal> {
+  let flag = "config file [columns]";
+  let mut resolved: Vec<(usize, &ColumnSpec)> = specs
+    .iter()
+    .map(|(col, spec)| {
+      let col = flag_column(flag, col, header)?;
+      if col >= width {
+        return Err(Fatal::new(format!(
+          "Error: {flag}: {csv1} has no column {}, only {width}",
+          col + 1
+        )));
+      }
+      Ok((col, spec))
+    })
+    .collect::<Result<_, _>>()?;
+  resolved.sort_by_key(|(col, _)| *col);
+  if let Some(twice) = resolved.windows(2).find(|w| w[0].0 == w[1].0) {
+    return Err(Fatal::new(format!(
+      "Error: {flag}: column {} has two tables, by number and by name",
+      twice[0].0 + 1
+    )));
+  }
+  Ok(resolved)
+}
+
+/// A path's file name, or `<?>` if it has none.
+fn basename(path: &str) -> String {
+  match Path::new(path).file_name() {
+    Some(name) => name.to_string_lossy().into_owned(),
+    None => "<?>".to_string(),
+  }
+}
+
+/// The trailing parts of two paths, as few as tell them apart, e.g.
+/// `baseline/result.csv` and `candidate/result.csv`, or the whole paths if
+/// none do.
+fn distinguishing_names(path1: &str, path2: &str) -> (String, String) {
+  let parts1: Vec<Component> = Path::new(path1).components().rev().collect();
+  let parts2: Vec<Component> = Path::new(path2).components().rev().collect();
+  let tail = |parts: &[Component], n: usize| {
+    let path: PathBuf = parts.iter().take(n).rev().collect();
+    path.display().to_string()
+  };
+  (1..=parts1.len().max(parts2.len()))
+    .map(|n| (tail(&parts1, n), tail(&parts2, n)))
+    .find(|(name1, name2)| name1 != name2)
+    .unwrap_or_else(|| (path1.to_string(), path2.to_string()))
+}
+
+/// What the report calls a file: its label, given by --label1 for csv1 and
+/// --label2 for csv2, or else its name (its path with --full-paths), and
+/// for an .f06 file the block extracted from it, e.g. `run.f06:Stresses`.
+fn report_name(args: &Args, path: &str, label: Option<&String>) -> String {
+  if let Some(label) = label {
+    return label.clone();
+  }
+  let name = match args.full_paths {
+    true => path.to_string(),
+    false => basename(path),
+  };
+  block_name(args, path, name)
+}
+
+/// A file's name in the report, followed for an .f06 file by the block
+/// extracted from it.
+fn block_name(args: &Args, path: &str, name: String) -> String {
+  match args.f06_block {
+    Some(block) if extract::is_f06(path) => {
+      format!("{name}:{}", block.name())
+    }
+    _ => name,
+  }
+}
+
+/// What the report calls both files of a pair. Unlabeled files of the same
+/// name, e.g. baseline/result.csv and candidate/result.csv, are called by
+/// as much of their paths as tells them apart.
+fn report_names(args: &Args, (csv1, csv2): (&str, &str)) -> (String, String) {
+  let names = (
+    report_name(args, csv1, args.label1.as_ref()),
+    report_name(args, csv2, args.label2.as_ref()),
+  );
+  let labeled = args.label1.is_some() || args.label2.is_some();
+  if names.0 != names.1 || labeled || args.full_paths {
+    return names;
+  }
+  let (name1, name2) = distinguishing_names(csv1, csv2);
+  (block_name(args, csv1, name1), block_name(args, csv2, name2))
+}
+
+/// A file's name in the plain single-line report, quoted if it holds
+/// whitespace, so each line still splits into the same fields.
+fn plain_name(name: &str) -> String {
+  match name.contains(char::is_whitespace) || name.contains('"') {
+    true => format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\"")),
+    false => name.to_string(),
+  }
+}
+
+/// Writes the badges of a run, as SVG or JSON by `extension`: the pair's
+/// badge to `path`, or in batch mode each pair's and the aggregate's into
+/// the directory at `path`.
+fn write_badges(
+  path: &Path,
+  extension: &str,
+  labels: &[String],
+  results: &[ResultLine],
+  aggregate: Option<&ResultLine>,
+) {
+  let write = |path: &Path, badge: Badge| {
+    let text = match extension {
+      "svg" => badge.svg(),
+      _ => badge.json(),
+    };
+    std::fs::write(path, text).unwrap_or_else(|e| {
+      exit::die(format!("Error writing {}: {e}", path.display()))
+    });
+  };
+  let Some(aggregate) = aggregate else {
+    if let (Some(label), Some(line)) = (labels.first(), results.first()) {
+      write(path, Badge::new(label, line));
+    }
+    return;
+  };
+  std::fs::create_dir_all(path).unwrap_or_else(|e| {
+    exit::die(format!("Error creating {}: {e}", path.display()))
+  });
+  let names = badge::file_names(labels, extension);
+  for ((name, label), line) in names.iter().zip(labels).zip(results) {
+    write(&path.join(name), Badge::new(label, line));
+  }
+  let label = format!("{} pairs", results.len());
+  write(
+    &path.join(format!("all.{extension}")),
+    Badge::new(&label, aggregate),
+  );
+}
+
+/// Prints a result line on stdout, unless they're turned off.
+fn result_line(args: &Args, line: &ResultLine) {
+  write_result_line(args, line, &mut io::stdout())
+    .unwrap_or_else(|e| exit::die(format!("Error writing the report: {e}")));
+}
+
+/// Writes a result line to `stdout`, unless they're turned off, by
+/// --no-result-line or -q, or the report is there in another format.
+fn write_result_line(
+  args: &Args,
+  line: &ResultLine,
+  stdout: &mut dyn Write,
+) -> io::Result<()> {
+  let report_on_stdout = args.format != Format::Plain && args.output.is_none();
+  if args.no_result_line || args.quiet > 0 || report_on_stdout {
+    return Ok(());
+  }
+  writeln!(stdout, "{line}")
+}
+
+/// A pair of files compared, and what the report on them is written from.
+struct Compared {
+  /// The comparison's report.
+  report: DiffReport,
+  /// The waivers excusing its failure, if any do.
+  excusing: Vec<Waiver>,
+  /// The waivers excusing it, described for the report.
+  waiver: Option<String>,
+  /// The options the cells were compared with.
+  opts: DiffConfig,
+  /// The rows compared, of csv1 and of csv2.
+  records: (Vec<StringRecord>, Vec<StringRecord>),
+  /// The header of each file, with --has-headers.
+  headers: Option<(StringRecord, StringRecord)>,
+  /// How csv1 was transposed, with --transpose1.
+  transposed1: Option<Transposed>,
+  /// The vectors of --vector-cols, with each one's magnitude's column.
+  vectors: Vec<(String, usize)>,
+  /// The magnitudes of --complex-pairs, with each one's column.
+  magnitudes: Vec<Magnitude>,
+  /// The cells each listed exclusion names.
+  excluded: Option<exclusions::Resolved>,
+  /// The convention --percent-columns inferred of each file.
+  inferred: Option<(Inference, Inference)>,
+  /// The rows --match-rows-nearest paired, and by which column.
+  matching: Option<(String, RowMatching)>,
+  /// The rows --key-col paired, and by which column.
+  keyed: Option<(String, KeyMatching)>,
+  /// The columns --mac-match paired.
+  mac_matching: Option<ColumnMatching>,
+  /// The shift of the rows' pairing, with --shift-rows or --auto-shift.
+  shifted: Option<Shifted>,
+  /// The failing cells of --list-failures.
+  failures: Option<Failures>,
+}
+
+/// Compares a single pair of files and writes the report. Returns the
+/// comparison's report, and the waivers excusing its failure if any do.
+#[allow(clippy::too_many_arguments)]
+fn run(
+  args: &Args,
+  profiles: &[(String, Tolerances)],
+  tolerances: &Tolerances,
+  (assertions, specs): (&Assertions, &BTreeMap<String, ColumnSpec>),
+  (waivers, exclusions): (&[Waiver], Option<&Exclusions>),
+  painter: Painter,
+  out: &mut dyn Write,
+  table_headers: &mut Option<Vec<String>>,
+  history: Option<&mut History>,
+  csv1: &str,
+  csv2: &str,
+  phases: &mut PhaseTimes,
+) -> Result<(DiffReport, Vec<Waiver>), Fatal> {
+  let compared = compare_pair(
+    args,
+    tolerances,
+    (assertions, specs),
+    (waivers, exclusions),
+    out,
+    (csv1, csv2),
+    phases,
+  )?;
+  let Compared {
+    report,
+    excusing,
+    waiver,
+    opts,
+    records: (records1, records2),
+    headers,
+    transposed1,
+    vectors,
+    magnitudes,
+    inferred,
+    matching,
+    keyed,
+    mac_matching,
+    shifted,
+    failures,
+    ..
+  } = &compared;
+  let header = headers.as_ref().map(|(header1, _)| header1);
+  let gate_name = profiles
+    .first()
+    .map(|(first, _)| args.gate_profile.as_ref().unwrap_or(first));
+
+  let max_abs_diff = report.max_diff.value;
+  let ratio_percent = report.ratio_percent();
+  let worst_digits = report.worst_digits();
+  let (bn1, bn2) = report_names(args, (csv1, csv2));
+  let precision = precision(args);
+  let argv: Vec<String> = std::env::args().collect();
+  let repro =
+    reproduction(args, &argv, (csv1, csv2), tolerances, (report, header));
+  if let Some(command) = &repro
+    && !args.explain
+  {
+    exit::note(format!("to reproduce: {command}"));
+  }
+
+  let flipped = report
+    .flipped_columns
+    .iter()
+    .map(|c| (c + 1).to_string())
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  if let Some(extra) = &report.ignored_rows {
+    let path = if extra.file == 1 { csv1 } else { csv2 };
+    exit::notice(format!(
+      "warning: ignored {} extra row(s) at the end of {path}, from line {}",
+      extra.count, extra.line
+    ));
+  }
+  if let Some(col) = &args.scale_tolerance_by
+    && report.unscaled_rows > 0
+  {
+    exit::notice(format!(
+      "warning: {} row(s) of {csv1} have no number in column {col}, so \
+       --scale-tolerance-by leaves -d unscaled there",
+      report.unscaled_rows
+    ));
+  }
+  if let (Some(scaled), Some(md)) = (&report.scaled_diff, tolerances.max_diff)
+    && scaled.worst.line != 0
+    && !args.explain
+  {
+    let worst = scaled_worst(scaled, md, opts, headers.as_ref());
+    exit::note(format!("note: nearest its scaled -d: {worst}"));
+  }
+  let tied = [
+    (
+      tolerances.max_ratio,
+      "ratio",
+      &report.max_ratio,
+      &report.ratio_ties,
+    ),
+    (
+      tolerances.max_diff,
+      "abs diff",
+      &report.max_diff,
+      &report.diff_ties,
+    ),
+  ];
+  for (_, name, worst, ties) in tied.iter().filter(|t| t.0.is_some()) {
+    if args.explain || ties.others() == 0 {
+      continue;
+    }
+    let col = column_label(opts, worst.col, headers.as_ref());
+    let lines = tie_lines(ties, worst, opts, headers.as_ref());
+    exit::note(format!(
+      "note: worst {name} at line {}, {col}: {}",
+      worst.line, lines[0]
+    ));
+    for line in &lines[1..] {
+      exit::notice(line.to_string());
+    }
+  }
+  let zeros = &report.zero_mismatches;
+  if zeros.cells > 0 && !args.explain {
+    let severity = if zeros.passed() { "note" } else { "error" };
+    let reaching = match zeros.limit {
+      Some(limit) => format!(
+        "; {} at least --max-zero-mismatch {limit:.2E}",
+        zeros.failing
+      ),
+      None => String::new(),
+    };
+    exit::notice(format!(
+      "{severity}: {}{reaching}",
+      zero_mismatches(zeros, opts, headers.as_ref())
+    ));
+  }
+  let issues = &report.structure_issues;
+  if !issues.is_empty() {
+    let severity = match report.structure {
+      Structure::Warn => "warning",
+      _ => "error",
+    };
+    exit::notice(format!(
+      "{severity}: left out {} row(s) of {csv1} (csv1) and {csv2} (csv2) \
+       with structural problems:",
+      issues.len()
+    ));
+    for issue in issues.iter().take(args.max_structure_report) {
+      exit::notice(format!("  {}", issue.problem));
+    }
+    if issues.len() > args.max_structure_report {
+      let more = issues.len() - args.max_structure_report;
+      exit::notice(format!("  ... and {more} more"));
+    }
+  }
+  let norms = column_norms(opts, report);
+  if let Some(normalize) = &opts.normalize
+    && !args.explain
+  {
+    let name = norms_noun(normalize.normalization);
+    let skewed = norms
+      .iter()
+      .filter(|(_, n1, n2)| *n1 > 0.0 && *n2 > 0.0)
+      .max_by(|a, b| skew(a).total_cmp(&skew(b)));
+    let skewed = match skewed {
+      Some((col, n1, n2)) => format!(
+        "; they differ most in column {col}: {n1:.6E} and {n2:.6E} (ratio \
+         {:.6})",
+        n2 / n1
+      ),
+      None => String::new(),
+    };
+    exit::note(format!(
+      "note: columns of {csv1} and {csv2} divided by their {name} before \
+       comparing{skewed}"
+    ));
+  }
+  // empty unless normalized
+  for &(col, n1, n2) in &norms {
+    for (path, norm) in [(csv1, n1), (csv2, n2)] {
+      if norm == 0.0 {
+        exit::notice(format!(
+          "warning: column {col} of {path} is all zeros, so it's compared \
+           unnormalized"
+        ));
+      }
+    }
+  }
+  if !args.explain {
+    for (label, col) in vectors {
+      exit::note(format!(
+        "note: column {col} of the report is the magnitude {label}"
+      ));
+    }
+  }
+  if let Some((col, matching)) = &matching
+    && !args.explain
+  {
+    if !matching.reordered.is_empty() {
+      exit::note(format!(
+        "note: {} row(s) of {csv2} paired out of order by the nearest value \
+         of column {col}: {}",
+        matching.reordered.len(),
+        reordered_rows(&matching.reordered)
+      ));
+    }
+    if let Some(unpaired) =
+      unpaired("line", &report.unpaired_rows, (csv1, csv2))
+    {
+      exit::notice(format!("rows left unpaired: {unpaired}"));
+    }
+  }
+  if let Some((col, keyed)) = &keyed
+    && !args.explain
+  {
+    let duplicated = listed_keys(keyed.duplicated(), |k| {
+      unpaired("line", &(k.lines1.clone(), k.lines2.clone()), (csv1, csv2))
+        .unwrap_or_default()
+    });
+    if !duplicated.is_empty() {
+      exit::note(format!(
+        "note: {} key(s) of column {col} shared by several rows, paired by \
+         {}:\n  {}",
+        keyed.duplicated().count(),
+        duplicated_pairing(args.duplicate_keys),
+        duplicated.join("\n  ")
+      ));
+    }
+    for (file, path) in [(1, csv1), (2, csv2)] {
+      let only = keys_only_in(keyed, file);
+      if !only.is_empty() {
+        exit::notice(format!(
+          "keys of column {col} only in {path} ({}):\n  {}",
+          keyed.only_in(file).count(),
+          only.join("\n  ")
+        ));
+      }
+    }
+  }
+  if let Some(inferred) = &inferred
+    && let Some(percent) = &opts.percent
+    && !args.explain
+  {
+    exit::note(format!(
+      "note: percent columns {}",
+      percent_conventions(inferred, percent.compared_as, (csv1, csv2))
+    ));
+  }
+  if let Some(shifted) = &shifted
+    && !args.explain
+  {
+    exit::note(format!(
+      "note: rows of {csv2} shifted {}",
+      shifted_rows(args, shifted, (csv1, csv2))
+    ));
+  }
+  if let Some(matching) = &mac_matching
+    && !args.explain
+  {
+    if matching.pairs.iter().any(|p| p.col1 != p.col2 || p.opposed) {
+      exit::note(format!(
+        "note: columns of {csv1} paired with those of {csv2} out of place or \
+         with opposite signs by MAC: {}",
+        mac_pairs(&matching.pairs)
+      ));
+    }
+    if let Some(unpaired) =
+      unpaired("column", &report.unpaired_columns, (csv1, csv2))
+    {
+      exit::notice(format!(
+        "float columns left unpaired by MAC (below {}): {unpaired}",
+        args.mac_threshold
+      ));
+    }
+  }
+  if let Some(phases) = &report.complex
+    && !args.explain
+  {
+    for (deviations, (label, _)) in phases.pairs.iter().zip(magnitudes) {
+      exit::note(format!(
+        "note: complex pair {label}: {}",
+        pair_deviations(deviations)
+      ));
+    }
+    if let Some(max) = phases.max_phase_deg
+      && !phases.passed()
+    {
+      exit::notice(format!(
+        "phase criterion: {} of {} phases of complex pairs differ by more \
+         than {max} degrees",
+        thousands(phases.failing()),
+        thousands(phases.compared())
+      ));
+    }
+  }
+  if let Some(matrix) = &report.matrix
+    && !args.explain
+  {
+    exit::note(format!("note: as matrices, {}", matrix_norms(matrix)));
+    if let Some(max) = matrix.max_rel
+      && !matrix.passed()
+    {
+      exit::notice(format!(
+        "matrix criterion: a relative norm of the difference exceeds {max}"
+      ));
+    }
+  }
+  if opts.scale != (1.0, 1.0) && !args.explain {
+    exit::note(format!(
+      "note: values of {csv1} scaled by {}, of {csv2} by {}, before comparing",
+      opts.scale.0, opts.scale.1
+    ));
+  }
+  if let Some(digits) = opts.sig_digits
+    && !args.explain
+  {
+    exit::note(format!(
+      "note: values rounded to {digits} significant digits before comparing"
+    ));
+  }
+  if report.clamped != (0, 0) {
+    exit::note(format!(
+      "note: took {} subnormal value(s) of {csv1} and {} of {csv2} as zero",
+      report.clamped.0, report.clamped.1
+    ));
+  }
+  if report.sign_differences > 0 && !args.explain {
+    exit::note(format!(
+      "note: {} cell(s) compared by magnitude have opposite signs in {csv1} \
+       and {csv2}",
+      report.sign_differences
+    ));
+  }
+  if let Some((rows, total)) = report.sampled_rows
+    && !args.explain
+  {
+    exit::note(format!(
+      "note: sampled: compared {} of {} rows; the verdict covers only those",
+      thousands(rows),
+      thousands(total)
+    ));
+  }
+  if report.skipped.below() > 0 && !args.explain {
+    let summary =
+      skipped_summary(&report.skipped, args.comparison.threshold_mode);
+    exit::note(format!("note: {summary}"));
+  }
+  if let Some(failures) = failures
+    && !args.explain
+  {
+    let summary = failures_summary(failures, (csv1, csv2));
+    let colon = if failures.cells.is_empty() { "" } else { ":" };
+    exit::note(format!("note: {summary}{colon}"));
+    for cell in &failures.cells {
+      let line = failing_cell(cell, opts, headers.as_ref());
+      exit::notice(format!("  {line}"));
+    }
+  }
+  let sums = column_sums(report);
+  if !sums.is_empty() && !args.explain {
+    let failing = sums.iter().filter(|s| !s.passed).count();
+    let severity = if failing == 0 { "note" } else { "error" };
+    let deciding = match report.sum_check {
+      SumCheck::Only => "; they alone decide the verdict",
+      _ => "",
+    };
+    exit::notice(format!(
+      "{severity}: column sums of {csv1} and {csv2}, {failing} of {} \
+       failing{deciding}:",
+      sums.len()
+    ));
+    for sum in &sums {
+      let status = if sum.passed { "PASS" } else { "FAIL" };
+      let line = column_sum(sum, status, opts, headers.as_ref());
+      exit::notice(format!("  {line}"));
+    }
+  }
+  if !args.no_column_report && !args.explain {
+    let demoted = report.demoted_columns();
+    if !demoted.is_empty() {
+      exit::note(format!("note: {}", demoted_summary(report, &demoted)));
+    }
+  }
+  if !args.explain {
+    for failure in &report.column_failures {
+      exit::notice(format!("error: {failure}"));
+    }
+  }
+  if args.exclude_cells.is_some() && !args.explain {
+    exit::note(format!(
+      "note: left out {} cell(s) of {csv1} and {csv2} listed in \
+       --exclude-cells",
+      report.excluded_cells().len()
+    ));
+    for stale in &report.stale_exclusions {
+      exit::notice(format!(
+        "stale exclusion for {csv1} and {csv2}, which left out no cell: \
+         {stale}"
+      ));
+    }
+  }
+  if !args.explain {
+    for mismatch in &report.annotation_mismatches {
+      exit::notice(format!(
+        "warning: annotations of {csv1} and {csv2} differ at {}",
+        annotation_mismatch(mismatch)
+      ));
+    }
+  }
+  if !args.explain {
+    for failure in &report.assertion_failures {
+      exit::notice(format!(
+        "assertion failed for {csv1} and {csv2}: {}",
+        assertion_failure(failure)
+      ));
+    }
+  }
+  if let Some(line) = report.aborted_at {
+    exit::note(format!(
+      "note: comparison of {csv1} and {csv2} aborted early at line {line}; \
+       the report covers only the lines up to it"
+    ));
+  }
+
+  if let Some(history) = history {
+    history
+      .record((csv1, csv2), report, header)
+      .map_err(|e| Fatal::new(format!("Error recording {csv1}: {e}")))?;
+  }
+
+  let passed = report.passed();
+  if args.quiet > 0 {
+    return Ok((compared.report, compared.excusing));
+  }
+
+  let dominance: Vec<String> = report
+    .dominance(args.dominance)
+    .iter()
+    // a line of csv1 as written isn't a row of it transposed
+    .map(|d| match transposed1 {
+      Some(_) => dominance_note(d, &[], report),
+      None => dominance_note(d, records1, report),
+    })
+    .collect();
+  if !args.explain {
+    for note in &dominance {
+      exit::note(format!("note: {note}"));
     }
+  }
 
-    if args.max_diff.is_some() && args.max_ratio.is_some() {
-      println!();
+  if args.gha {
+    let tolerances = &opts.tolerances;
+    let waiver = waiver.as_deref();
+    for annotation in gha::annotations((csv1, csv2), tolerances, report, waiver)
+    {
+      println!("{annotation}");
+    }
+    if let Some(impact) = &report.impact_classes() {
+      let file = gha::escape_property(csv2);
+      for (class, cells) in impact_classes(impact) {
+        let line = impact_line(class, cells, report);
+        println!("::notice file={file}::{}", gha::escape_data(&line));
+      }
     }
+  }
 
-    if let Some(md) = args.max_diff {
-      println!("maximum absolute difference seen: {max_abs_diff:.2E}");
-      println!(
-        "the values: {:+.6E} and {:+.6E} (line {})",
-        max_abs_vals.0, max_abs_vals.1, max_diff_line
-      );
-      let status = if max_abs_diff > md { "FAIL" } else { "PASS" };
-      println!("result: {status}");
+  if args.locations_style == "gcc" {
+    let at = locations::Locating {
+      path: if args.locate_in == 1 { csv1 } else { csv2 },
+      file: args.locate_in as usize,
+      bytes: args.byte_columns,
+    };
+    let records = (records1.as_slice(), records2.as_slice());
+    let listed = failures.as_ref().map_or(&[][..], |f| &f.cells);
+    let lines = locations::gate_locations(at, records, report, opts)
+      .into_iter()
+      .chain(locations::cell_locations(at, records, listed, opts));
+    for line in lines {
+      match args.locations_to.as_str() {
+        "stdout" => println!("{line}"),
+        _ => eprintln!("{line}"),
+      }
+    }
+  }
+
+  for cell in &args.explain_cell {
+    let col = cell_column(cell, header)?;
+    let explanation =
+      cell::explain(records1, records2, opts, report, (cell.line, col))
+        .map_err(|e| Fatal::new(format!("Error: --explain-cell: {e}")))?;
+    let style = (&painter, precision);
+    explain_cell(out, style, &explanation, headers.as_ref(), opts)?;
+    writeln!(out)?;
+  }
+
+  if args.format == Format::Junit {
+    let suite = args
+      .suite_name
+      .clone()
+      .unwrap_or_else(|| format!("{bn1} vs {bn2}"));
+    let waivers = excusing.iter().map(|w| ("waiver", w.describe()));
+    let properties: Vec<_> = report
+      .sampled_rows
+      .iter()
+      .flat_map(|&(rows, total)| {
+        [
+          ("sampled_rows", rows.to_string()),
+          ("total_rows", total.to_string()),
+        ]
+      })
+      .chain(waivers)
+      .collect();
+    writeln!(
+      out,
+      "{}",
+      junit::testsuite(
+        &suite,
+        &properties,
+        &junit::cases(
+          profiles,
+          &opts.tolerances,
+          (report, &checks_made(args, assertions, report)),
+          (&bn1, &bn2),
+          waiver.as_deref()
+        )
+      )
+    )?;
+    return Ok((compared.report, compared.excusing));
+  }
+  if args.format == Format::Csv {
+    write_csv(out, &csv_row((&bn1, &bn2), report, tolerances, precision))?;
+    return Ok((compared.report, compared.excusing));
+  }
+  if args.format == Format::Json {
+    let line = ResultLine {
+      waived: usize::from(!excusing.is_empty()),
+      ..ResultLine::of_pair((csv1, csv2), report, Duration::ZERO)
+    };
+    let status = line.status();
+    writeln!(
+      out,
+      "{}",
+      json::report(
+        (&bn1, &bn2),
+        report,
+        (tolerances, &opts.warn_tolerances),
+        (status, excusing),
+        args.dominance
+      )
+    )?;
+    return Ok((compared.report, compared.excusing));
+  }
+
+  if let Some(gate_name) = gate_name {
+    let (headers, rows) = profile_matrix(profiles, report);
+    if args.format == Format::Markdown {
+      let files = [bn1.to_string(), bn2.to_string()].map(|f| (f, Paint::Plain));
+      let rows: Vec<Vec<Cell>> = rows
+        .into_iter()
+        .map(|row| [&files[..], &row].concat())
+        .collect();
+      let headers = [vec!["csv1".to_string(), "csv2".to_string()], headers];
+      markdown::write_rows(out, table_headers, headers.concat(), &rows)?;
+      return Ok((compared.report, compared.excusing));
+    }
+    if args.explain {
+      writeln!(out, "files: {bn1} and {bn2}\n")?;
+      for note in &dominance {
+        writeln!(out, "note: {note}")?;
+      }
+      if !dominance.is_empty() {
+        writeln!(out)?;
+      }
+      writeln!(out, "verdict matrix:")?;
+      let table =
+        align_table(&headers, &rows, &Alignment::Left, None, true, &painter);
+      for line in table.lines() {
+        writeln!(out, "  {}", line.trim_end())?;
+      }
+      writeln!(
+        out,
+        "\nresult (profile {gate_name}): {}",
+        painter.status(passed)
+      )?;
+      if let Some(command) = &repro {
+        writeln!(out, "\nto reproduce the worst violation:\n  {command}")?;
+      }
+      if !excusing.is_empty() {
+        write_waivers(out, &painter, excusing)?;
+      }
+    } else if let Some(align) = &args.align {
+      let table =
+        align_table(&headers, &rows, align, args.width, args.header, &painter);
+      writeln!(out, "{table}")?;
+    } else {
+      // without a header line, each status is preceded by its gate's name
+      for row in &rows {
+        let cells: Vec<String> = row
+          .iter()
+          .zip(&headers)
+          .enumerate()
+          .map(|(i, ((c, p), h))| match i % 2 {
+            1 => format!("{h} {}", painter.paint(c, *p)),
+            _ => painter.paint(c, *p),
+          })
+          .collect();
+        let (name1, name2) = (plain_name(&bn1), plain_name(&bn2));
+        writeln!(out, "{name1} {name2} {}", cells.join(" "))?;
+      }
+    }
+    if let Some(histogram) = &report.histogram {
+      writeln!(out)?;
+      write_histogram(out, histogram, report_width(args))?;
+    }
+    if let Some(percentiles) = &report.percentiles {
+      writeln!(out)?;
+      write_percentiles(out, percentiles)?;
+    }
+    return Ok((compared.report, compared.excusing));
+  }
+
+  // Report
+  if args.explain {
+    let style = (&painter, precision);
+    let notes = (dominance.as_slice(), repro.as_deref());
+    let files = (csv1, csv2);
+    let checks = (tolerances, assertions);
+    write_explanation(out, args, checks, style, files, &compared, notes)?;
+  } else if args.align.is_some() || args.format == Format::Markdown {
+    if !flipped.is_empty() {
+      exit::note(format!("note: sign-flipped columns of {bn2}: {flipped}"));
+    }
+    let uncolored = Painter::new(ColorChoice::Never, false);
+    for column in report.overrides.iter().filter(|c| !c.report.passed()) {
+      let line = override_line(column, header, &uncolored);
+      exit::note(format!("note: {line}"));
+    }
+    for result in report.warnings_raised() {
+      let line = warning_line(result, report);
+      exit::notice(format!("warning: {bn1} vs {bn2}: {line}"));
+    }
+    if let Some(impact) = &report.impact_classes() {
+      for (class, cells) in impact_classes(impact) {
+        let line = impact_line(class, cells, report);
+        exit::note(format!("note: {bn1} vs {bn2}: {line}"));
+      }
     }
-  } else if let Some(align) = &args.align {
     // Use aligned output format
-    let max_ratio_info = args.max_ratio.map(|mr| {
-      let percentage_diff = ((max_ratio - 1.0) * 100.0).abs();
-      let passed = percentage_diff <= mr * 100.0;
-      (max_ratio, max_ratio_vals, max_ratio_line, passed)
+    let max_ratio_info = tolerances.max_ratio.map(|_| {
+      (
+        report.max_ratio,
+        ratio_percent,
+        report.gate_status(Gate::Ratio),
+      )
     });
 
-    let max_diff_info = args.max_diff.map(|md| {
-      let passed = max_abs_diff <= md;
-      (max_abs_diff, max_abs_vals, max_diff_line, passed)
-    });
+    let max_diff_info = tolerances
+      .max_diff
+      .map(|_| (report.max_diff, report.gate_status(Gate::Diff)));
+
+    let min_digits_info = tolerances
+      .min_digits
+      .map(|_| (worst_digits, report.gate_status(Gate::Digits)));
+    let allclose_info = tolerances
+      .allclose()
+      .map(|_| (report.allclose, report.gate_status(Gate::Allclose)));
+    let cells = (report.compared(), report.failing_cells);
+    let waiver_cell = args.waivers.as_ref().map(|_| waiver_cell(excusing));
 
-    format_aligned_output(
-      (&bn1, &bn2),
+    let (mut headers, row) = aligned_row(
+      precision,
+      ((&bn1, &bn2), header),
       max_ratio_info,
       max_diff_info,
-      align,
-      args.width,
-      args.header,
+      min_digits_info,
+      allclose_info,
+      cells,
+      waiver_cell.clone(),
     );
-  } else {
-    print!("{bn1} {bn2} ");
-    if let Some(mr) = args.max_ratio {
-      let percentage_diff = ((max_ratio - 1.0) * 100.0).abs();
-      print!("{percentage_diff:.2}");
-      print!(
-        " {:+.6E} {:+.6E} {}",
-        max_ratio_vals.0, max_ratio_vals.1, max_ratio_line
-      );
-      let status = if percentage_diff > mr * 100.0 {
-        "FAIL"
+    let mut rows = vec![row];
+    if let Some(col) = &args.group_by
+      && args.group_format == "text"
+    {
+      // the pair's own row covers every group
+      headers.insert(2, "group".to_string());
+      rows[0].insert(2, ("*".to_string(), Paint::Plain));
+      for group in &report.groups {
+        let (_, mut row) = report_row(
+          precision,
+          ((&bn1, &bn2), header),
+          &group.report,
+          tolerances,
+          waiver_cell.clone(),
+        );
+        row.insert(2, (format!("{col}={}", group.value), Paint::Plain));
+        rows.push(row);
+      }
+    }
+    let mut tables = vec![(headers, rows)];
+    if args.per_column {
+      tables.push(per_column_table(precision, report, tolerances, header));
+    }
+    for (headers, rows) in tables {
+      if args.format == Format::Markdown {
+        markdown::write_rows(out, table_headers, headers, &rows)?;
       } else {
-        "PASS"
+        let aligned = align_table(
+          &headers,
+          &rows,
+          args.align.as_ref().unwrap_or(&Alignment::Left),
+          args.width,
+          args.header,
+          &painter,
+        );
+        writeln!(out, "{aligned}")?;
+      }
+    }
+  } else {
+    if !flipped.is_empty() {
+      exit::note(format!("note: sign-flipped columns of {bn2}: {flipped}"));
+    }
+    write!(out, "{} {} ", plain_name(&bn1), plain_name(&bn2))?;
+    if tolerances.max_ratio.is_some() {
+      let passed = report.gate_status(Gate::Ratio);
+      write!(
+        out,
+        "{}",
+        painter.value(&precision.percent(ratio_percent), passed)
+      )?;
+      let worst = &report.max_ratio;
+      write!(
+        out,
+        " {} {} {} {}",
+        painter.value(&precision.value(worst.vals.0), passed),
+        painter.value(&precision.value(worst.vals.1), passed),
+        worst.line,
+        worst_column(worst.col, header)
+      )?;
+      write!(out, " {}", painter.status(passed))?;
+    }
+
+    if tolerances.max_diff.is_some() && tolerances.max_ratio.is_some() {
+      write!(out, " ")?;
+    }
+
+    if tolerances.max_diff.is_some() {
+      let passed = report.gate_status(Gate::Diff);
+      write!(
+        out,
+        "{} ",
+        painter.value(&format!("{max_abs_diff:.2E}"), passed)
+      )?;
+      let worst = &report.max_diff;
+      write!(
+        out,
+        "{} {} {} {}",
+        painter.value(&precision.value(worst.vals.0), passed),
+        painter.value(&precision.value(worst.vals.1), passed),
+        worst.line,
+        worst_column(worst.col, header)
+      )?;
+      write!(out, " {}", painter.status(passed))?;
+    }
+
+    if tolerances.min_digits.is_some() {
+      if tolerances.max_diff.is_some() || tolerances.max_ratio.is_some() {
+        write!(out, " ")?;
+      }
+      let passed = report.gate_status(Gate::Digits);
+      match worst_digits {
+        Some((d, col)) => write!(
+          out,
+          "{} {}",
+          painter.value(&format!("{d:.2}"), passed),
+          worst_column(col, header)
+        )?,
+        None => write!(out, "n/a 0")?,
+      }
+      write!(out, " {}", painter.status(passed))?;
+    }
+
+    if tolerances.allclose().is_some() {
+      if tolerances.max_diff.is_some()
+        || tolerances.max_ratio.is_some()
+        || tolerances.min_digits.is_some()
+      {
+        write!(out, " ")?;
+      }
+      let passed = report.gate_status(Gate::Allclose);
+      let worst = &report.allclose;
+      write!(
+        out,
+        "{} {} {} {} {}",
+        painter.value(&format!("{:.2}", worst.value), passed),
+        painter.value(&precision.value(worst.vals.0), passed),
+        painter.value(&precision.value(worst.vals.1), passed),
+        worst.line,
+        worst_column(worst.col, header)
+      )?;
+      write!(out, " {}", painter.status(passed))?;
+    }
+    // then the failing cells of each gate, the compared ones, and with
+    // --waivers those excusing the pair
+    for gate in tolerances.gates() {
+      write!(out, " {}", report.failing_cells.of(gate))?;
+    }
+    write!(out, " {}", report.compared())?;
+    if args.waivers.is_some() {
+      let (text, paint) = waiver_cell(excusing);
+      write!(out, " {}", painter.paint(&text, paint))?;
+    }
+    writeln!(out)?;
+    if let Some(col) = &args.group_by
+      && args.group_format == "text"
+    {
+      for group in &report.groups {
+        writeln!(out, "  {}", group_line(col, group, tolerances, &painter))?;
+      }
+    }
+    for column in &report.overrides {
+      writeln!(out, "  {}", override_line(column, header, &painter))?;
+    }
+    for result in report.warnings_raised() {
+      let line = format!("warning: {}", warning_line(result, report));
+      writeln!(out, "  {}", painter.paint(&line, Paint::Warned))?;
+    }
+    if tolerances.directional() {
+      for direction in [Direction::Over, Direction::Under] {
+        writeln!(out, "  {}", direction_line(direction, report, tolerances))?;
+      }
+    }
+    if let Some(impact) = &report.impact_classes() {
+      for (class, cells) in impact_classes(impact) {
+        writeln!(out, "  {}", impact_line(class, cells, report))?;
+      }
+    }
+    if args.per_column {
+      let style = (&painter, precision);
+      write_per_column(out, report, tolerances, header, style)?;
+    }
+  }
+  if let Some(col) = &args.group_by
+    && args.group_format == "json"
+  {
+    for group in &report.groups {
+      writeln!(out, "{}", json::group_line((&bn1, &bn2), col, group))?;
+    }
+  }
+  if let Some(histogram) = &report.histogram {
+    writeln!(out)?;
+    write_histogram(out, histogram, report_width(args))?;
+  }
+  if let Some(percentiles) = &report.percentiles {
+    writeln!(out)?;
+    write_percentiles(out, percentiles)?;
+  }
+
+  Ok((compared.report, compared.excusing))
+}
+
+/// Writes the --explain report on a pair of files compared: each check
+/// spelled out, with what it found and its result.
+fn write_explanation(
+  out: &mut dyn Write,
+  args: &Args,
+  (tolerances, assertions): (&Tolerances, &Assertions),
+  (painter, precision): (&Painter, Precision),
+  (csv1, csv2): (&str, &str),
+  compared: &Compared,
+  (dominance, repro): (&[String], Option<&str>),
+) -> io::Result<()> {
+  let Compared {
+    report,
+    excusing,
+    opts,
+    records: (records1, records2),
+    headers,
+    transposed1,
+    vectors,
+    magnitudes,
+    excluded,
+    inferred,
+    matching,
+    keyed,
+    mac_matching,
+    shifted,
+    failures,
+    ..
+  } = compared;
+  let header = headers.as_ref().map(|(header1, _)| header1);
+  let (bn1, bn2) = report_names(args, (csv1, csv2));
+  let max_abs_diff = report.max_diff.value;
+  let ratio_percent = report.ratio_percent();
+  let worst_digits = report.worst_digits();
+  // a gate may pass on --max-fail-fraction with its worst cell out of bounds
+  let gate_passed = |gate: Gate| {
+    report
+      .gates
+      .iter()
+      .find(|g| g.gate == gate)
+      .is_none_or(|g| g.passed)
+  };
+  let failing_cells = |gate: Gate, exceed: String| {
+    let compared = report.compared();
+    let allowed = match tolerances.max_fail_fraction {
+      Some(f) => {
+        format!(" (--max-fail-fraction allows under {:.3}%)", f * 100.0)
+      }
+      None => String::new(),
+    };
+    format!(
+      "{} criterion: {} of {} cells ({:.3}%) {exceed}{allowed}",
+      gate.name(),
+      thousands(report.failing_cells.of(gate)),
+      thousands(compared),
+      report.failing_fraction(gate) * 100.0
+    )
+  };
+  let flipped = report
+    .flipped_columns
+    .iter()
+    .map(|c| (c + 1).to_string())
+    .collect::<Vec<_>>()
+    .join(", ");
+  let norms = column_norms(opts, report);
+  writeln!(out, "files: {bn1} and {bn2}\n")?;
+  if opts.scale != (1.0, 1.0) {
+    writeln!(
+      out,
+      "(values of {bn1} scaled by {}, of {bn2} by {}, before comparing)\n",
+      opts.scale.0, opts.scale.1
+    )?;
+  }
+  if let Some(digits) = opts.sig_digits {
+    writeln!(
+      out,
+      "(values rounded to {digits} significant digits before comparing; \
+       the worst cells' values are also shown as printed)\n"
+    )?;
+  }
+  if let Some(line) = report.aborted_at {
+    writeln!(out, "(comparison aborted early at line {line})\n")?;
+  }
+  for (label, col) in vectors {
+    writeln!(out, "(column {col} is the magnitude {label})")?;
+  }
+  if !vectors.is_empty() {
+    writeln!(out)?;
+  }
+  if !args.sort_by.is_empty() {
+    let keys: Vec<String> = args.sort_by.iter().map(u64::to_string).collect();
+    writeln!(
+      out,
+      "(rows sorted on column(s) {} before comparing; lines are those rows \
+       were read from)\n",
+      keys.join(", ")
+    )?;
+  }
+  if args.canonical_order {
+    writeln!(
+      out,
+      "(rows sorted on all columns before comparing; lines are those rows \
+       were read from)\n"
+    )?;
+  }
+  if let Some(inferred) = &inferred
+    && let Some(percent) = &opts.percent
+  {
+    let cols: Vec<String> = percent
+      .cols
+      .iter()
+      .map(|col| (col + 1).to_string())
+      .collect();
+    writeln!(
+      out,
+      "(percent columns {}: {})\n",
+      cols.join(", "),
+      percent_conventions(inferred, percent.compared_as, (&bn1, &bn2))
+    )?;
+  }
+  if let Some(shifted) = &shifted {
+    writeln!(
+      out,
+      "(rows of {bn2} shifted {}; lines are those rows were read from)\n",
+      shifted_rows(args, shifted, (&bn1, &bn2))
+    )?;
+  }
+  if let Some(matching) = &mac_matching {
+    writeln!(
+      out,
+      "(float columns paired by MAC, at least {}; out of place or with \
+       opposite signs: {})\n",
+      args.mac_threshold,
+      mac_pairs(&matching.pairs)
+    )?;
+  }
+  if let Some((col, matching)) = &matching {
+    let reordered = match matching.reordered.is_empty() {
+      true => "none".to_string(),
+      false => reordered_rows(&matching.reordered),
+    };
+    writeln!(
+      out,
+      "(rows paired by the nearest value of column {col}, within {} rows; \
+       paired out of order: {reordered})\n",
+      args.match_window
+    )?;
+  }
+  if let Some((col, keyed)) = &keyed {
+    let matched = keyed.keys.iter().filter(|k| k.status() == "matched");
+    writeln!(
+      out,
+      "rows paired by their key in column {col}: {} key(s) matched, {} \
+       duplicated (paired by {}), {} only in {bn1}, {} only in {bn2}",
+      matched.count(),
+      keyed.duplicated().count(),
+      duplicated_pairing(args.duplicate_keys),
+      keyed.only_in(1).count(),
+      keyed.only_in(2).count()
+    )?;
+    let duplicated = listed_keys(keyed.duplicated(), |k| {
+      unpaired("line", &(k.lines1.clone(), k.lines2.clone()), (&bn1, &bn2))
+        .unwrap_or_default()
+    });
+    if !duplicated.is_empty() {
+      writeln!(out, "keys shared by several rows:")?;
+      for line in duplicated {
+        writeln!(out, "  {line}")?;
+      }
+    }
+    for (file, name) in [(1, &bn1), (2, &bn2)] {
+      let only = keys_only_in(keyed, file);
+      if !only.is_empty() {
+        writeln!(out, "keys only in {name}:")?;
+        for line in only {
+          writeln!(out, "  {line}")?;
+        }
+      }
+    }
+    writeln!(out)?;
+  }
+  if args.comparison.interval {
+    writeln!(
+      out,
+      "(interval mode: differences are between the nearest points of the \
+       values' printed-precision intervals)\n"
+    )?;
+  }
+  if !flipped.is_empty() {
+    writeln!(out, "sign-flipped columns of {bn2}: {flipped}\n")?;
+  }
+  let by_magnitude = match &opts.abs_compare {
+    AbsCompare::Never => None,
+    AbsCompare::AnyColumn => Some("every float column".to_string()),
+    AbsCompare::Columns(cols) => {
+      let cols: Vec<String> =
+        cols.iter().map(|col| (col + 1).to_string()).collect();
+      Some(format!("column(s) {}", cols.join(", ")))
+    }
+  };
+  if let Some(columns) = by_magnitude {
+    writeln!(
+      out,
+      "(cells of {columns} compared by magnitude, the worst cells' values \
+       shown signed; cells with opposite signs: {})\n",
+      report.sign_differences
+    )?;
+  }
+  if let Some((rows, total)) = report.sampled_rows {
+    writeln!(
+      out,
+      "(sampled: compared {} of {} rows; the verdict covers only those)\n",
+      thousands(rows),
+      thousands(total)
+    )?;
+  }
+  if report.skipped != SkippedCells::default() {
+    let summary =
+      skipped_summary(&report.skipped, args.comparison.threshold_mode);
+    writeln!(out, "({summary})\n")?;
+  }
+  if args.exclude_cells.is_some() {
+    writeln!(
+      out,
+      "(cells listed in --exclude-cells left out: {})\n",
+      report.excluded_cells().len()
+    )?;
+  }
+  if let Some(normalize) = &opts.normalize {
+    writeln!(
+      out,
+      "columns divided by their {} before comparing, per column ({bn1}, \
+       {bn2}, ratio):",
+      norms_noun(normalize.normalization)
+    )?;
+    for &(col, n1, n2) in &norms {
+      let ratio = match n1 > 0.0 && n2 > 0.0 {
+        true => format!("{:.6}", n2 / n1),
+        false => "- (all zeros, compared unnormalized)".to_string(),
+      };
+      writeln!(out, "  column {col}: {n1:.6E}, {n2:.6E}, {ratio}")?;
+    }
+    writeln!(out)?;
+  }
+  if args.comparison.threshold_frac > 0.0 && !report.columns.is_empty() {
+    let which = match args.comparison.threshold_mode {
+      ThresholdMode::Both => "both values are",
+      ThresholdMode::Either => "either value is",
+      ThresholdMode::Baseline => "csv1's value is",
+    };
+    writeln!(
+      out,
+      "cells skipped when {which} below, per column (--threshold {:.2E}, \
+       --threshold-frac {}):",
+      args.comparison.threshold, args.comparison.threshold_frac
+    )?;
+    for column in &report.columns {
+      writeln!(out, "  column {}: {:.2E}", column.col, column.cutoff)?;
+    }
+    writeln!(out)?;
+  }
+  for note in dominance {
+    writeln!(out, "note: {note}")?;
+  }
+  if !dominance.is_empty() {
+    writeln!(out)?;
+  }
+  let segments = args.segments.map_or(0, usize::from);
+  let located: Vec<_> = report
+    .columns
+    .iter()
+    .filter_map(|c| c.worst_segment().map(|s| (c, s)))
+    .collect();
+  if !located.is_empty() {
+    writeln!(out, "where the worst differences occur, per column:")?;
+    for (column, segment) in located {
+      // segments follow the rows' order, which sorting changes
+      let sorted = !args.sort_by.is_empty() || args.canonical_order;
+      let (one, many) = match sorted {
+        false => ("line", "lines"),
+        true => ("sorted row", "sorted rows"),
+      };
+      // by physical line, past any header, unless sorted
+      let (first, last) = segment_lines(segment, records1.len(), segments);
+      let (first, last) = match sorted {
+        false => (
+          physical_line(records1, first - 1),
+          physical_line(records1, last - 1),
+        ),
+        true => (first, last),
       };
-      print!(" {status}");
+      let lines = match first == last {
+        true => format!("{one} {first}"),
+        false => format!("{many} {first}-{last}"),
+      };
+      writeln!(
+        out,
+        "  {}: [{}] segment {}/{segments} ({lines})",
+        column_label(opts, column.col, headers.as_ref()),
+        column.sparkline(),
+        segment + 1
+      )?;
+    }
+    writeln!(out)?;
+  }
+  if let Some(mr) = tolerances.max_ratio {
+    let passed = gate_passed(Gate::Ratio);
+    writeln!(
+      out,
+      "maximum percent difference allowed: {}%, observed: {}",
+      precision.percent(mr * 100.0),
+      painter.value(&format!("{}%", precision.percent(ratio_percent)), passed)
+    )?;
+    let worst = &report.max_ratio;
+    writeln!(
+      out,
+      "the values: {} and {} (line {}, {})",
+      painter.value(&precision.value(worst.vals.0), passed),
+      painter.value(&precision.value(worst.vals.1), passed),
+      worst.line,
+      column_label(opts, worst.col, headers.as_ref())
+    )?;
+    let ties = &report.ratio_ties;
+    for line in tie_lines(ties, worst, opts, headers.as_ref()) {
+      writeln!(out, "{line}")?;
+    }
+    // csv1's lines aren't rows of it transposed
+    if transposed1.is_none() {
+      write_printed(out, (records1, records2), worst, opts)?;
     }
+    if args.show_rows {
+      let records = (&records1[..], &records2[..]);
+      write_rows(out, records, worst, headers.as_ref(), (args, opts))?;
+    }
+    if report.ratio_floored() {
+      writeln!(
+        out,
+        "(measured against the --rel-floor of {:.2E}, not the values)",
+        report.rel_floor
+      )?;
+    }
+    let exceed = match directed_limits(|d| tolerances.ratio_limit(d)) {
+      Some((over, under)) => format!(
+        "exceed {}% over, {}% under",
+        precision.percent(over * 100.0),
+        precision.percent(under * 100.0)
+      ),
+      None => format!("exceed {}%", precision.percent(mr * 100.0)),
+    };
+    writeln!(out, "{}", failing_cells(Gate::Ratio, exceed))?;
+    writeln!(out, "result: {}", painter.status(passed))?;
+  }
+
+  if tolerances.max_diff.is_some() && tolerances.max_ratio.is_some() {
+    writeln!(out)?;
+  }
 
-    if args.max_diff.is_some() && args.max_ratio.is_some() {
-      print!(" ");
+  if let Some(md) = tolerances.max_diff {
+    let passed = gate_passed(Gate::Diff);
+    writeln!(
+      out,
+      "maximum absolute difference allowed: {md:.2E}, observed: {}",
+      painter.value(&format!("{max_abs_diff:.2E}"), passed)
+    )?;
+    if let Some(scaled) = &report.scaled_diff
+      && scaled.worst.line != 0
+    {
+      let worst = scaled_worst(scaled, md, opts, headers.as_ref());
+      writeln!(out, "scaled row by row, nearest its limit: {worst}")?;
+    }
+    let worst = &report.max_diff;
+    writeln!(
+      out,
+      "the values: {} and {} (line {}, {})",
+      painter.value(&precision.value(worst.vals.0), passed),
+      painter.value(&precision.value(worst.vals.1), passed),
+      worst.line,
+      column_label(opts, worst.col, headers.as_ref())
+    )?;
+    let ties = &report.diff_ties;
+    for line in tie_lines(ties, worst, opts, headers.as_ref()) {
+      writeln!(out, "{line}")?;
+    }
+    // csv1's lines aren't rows of it transposed
+    if transposed1.is_none() {
+      write_printed(out, (records1, records2), worst, opts)?;
+    }
+    if args.show_rows {
+      let records = (&records1[..], &records2[..]);
+      write_rows(out, records, worst, headers.as_ref(), (args, opts))?;
     }
+    let exceed = match directed_limits(|d| tolerances.diff_limit(d)) {
+      Some((over, under)) => {
+        format!("exceed {over:.2E} over, {under:.2E} under")
+      }
+      None => format!("exceed {md:.2E}"),
+    };
+    writeln!(out, "{}", failing_cells(Gate::Diff, exceed))?;
+    writeln!(out, "result: {}", painter.status(passed))?;
+  }
 
-    if let Some(md) = args.max_diff {
-      print!("{max_abs_diff:.2E} ");
-      print!(
-        "{:+.6E} {:+.6E} {}",
-        max_abs_vals.0, max_abs_vals.1, max_diff_line
-      );
-      let status = if max_abs_diff > md { "FAIL" } else { "PASS" };
-      print!(" {status}");
+  if let Some(min) = tolerances.min_digits {
+    if tolerances.max_diff.is_some() || tolerances.max_ratio.is_some() {
+      writeln!(out)?;
+    }
+    writeln!(out, "digits of agreement per column:")?;
+    for column in &report.columns {
+      match column.digits_of_agreement() {
+        Some(d) => writeln!(out, "  column {}: {d:.2}", column.col)?,
+        None => {
+          writeln!(out, "  column {}: n/a (nothing compared)", column.col)?
+        }
+      }
+    }
+    let passed = gate_passed(Gate::Digits);
+    if let Some((d, col)) = worst_digits {
+      writeln!(
+        out,
+        "minimum digits of agreement: {} (column {col})",
+        painter.value(&format!("{d:.2}"), passed)
+      )?;
+    }
+    let exceed = format!("agree to fewer than {min:.2} digits");
+    writeln!(out, "{}", failing_cells(Gate::Digits, exceed))?;
+    writeln!(out, "result: {}", painter.status(passed))?;
+  }
+
+  if let Some((atol, rtol)) = tolerances.allclose() {
+    if tolerances.max_diff.is_some()
+      || tolerances.max_ratio.is_some()
+      || tolerances.min_digits.is_some()
+    {
+      writeln!(out)?;
+    }
+    let passed = gate_passed(Gate::Allclose);
+    let worst = &report.allclose;
+    writeln!(
+      out,
+      "combined tolerance: |a-b| <= {atol:.2E} + {rtol:.2E}*|b|, b being \
+       csv2's value"
+    )?;
+    writeln!(
+      out,
+      "worst absolute difference, as a multiple of what it allows: {}",
+      painter.value(&format!("{:.2}", worst.value), passed)
+    )?;
+    if worst.line != 0 {
+      writeln!(
+        out,
+        "the values: {} and {} (line {}, {})",
+        painter.value(&precision.value(worst.vals.0), passed),
+        painter.value(&precision.value(worst.vals.1), passed),
+        worst.line,
+        column_label(opts, worst.col, headers.as_ref())
+      )?;
+    }
+    let exceed = "exceed it".to_string();
+    writeln!(out, "{}", failing_cells(Gate::Allclose, exceed))?;
+    writeln!(out, "result: {}", painter.status(passed))?;
+  }
+
+  let zeros = &report.zero_mismatches;
+  if zeros.cells > 0 || zeros.limit.is_some() {
+    writeln!(out)?;
+    if zeros.cells > 0 {
+      let summary = zero_mismatches(zeros, opts, headers.as_ref());
+      writeln!(out, "{summary}")?;
+    }
+    match zeros.limit {
+      Some(limit) => {
+        writeln!(
+          out,
+          "cells zero in one file only are held to --max-zero-mismatch \
+           {limit:.2E} instead of -r: {} of {} have a nonzero value at \
+           least that large",
+          zeros.failing, zeros.cells
+        )?;
+        writeln!(out, "result: {}", painter.status(zeros.passed()))?;
+      }
+      None => writeln!(
+        out,
+        "their relative differences are measured as any other's; \
+         --max-zero-mismatch would hold them to a magnitude instead"
+      )?,
+    }
+  }
+
+  if let Some(failures) = failures {
+    let summary = failures_summary(failures, (&bn1, &bn2));
+    let colon = if failures.cells.is_empty() { "" } else { ":" };
+    writeln!(out, "\n{summary}{colon}")?;
+    for cell in &failures.cells {
+      writeln!(out, "  {}", failing_cell(cell, opts, headers.as_ref()))?;
+    }
+  }
+
+  let sums = column_sums(report);
+  if report.sum_check.enabled() {
+    let deciding = match report.sum_check {
+      SumCheck::Only => "instead of the cells' gates",
+      _ => "as well as the cells' gates",
+    };
+    writeln!(
+      out,
+      "\ncolumn sums over the compared cells, held to -r and -d \
+       {deciding}:"
+    )?;
+    for sum in &sums {
+      let status = painter.status(sum.passed);
+      let line = column_sum(sum, &status, opts, headers.as_ref());
+      writeln!(out, "  {line}")?;
+    }
+    let passed = sums.iter().all(|s| s.passed);
+    writeln!(out, "result: {}", painter.status(passed))?;
+  }
+
+  if !report.warnings.is_empty() {
+    writeln!(out, "\nwarning tolerances:")?;
+    for result in &report.warnings {
+      writeln!(out, "  {}", warning_line(result, report))?;
+    }
+    writeln!(out, "result: {}", painter.warning(report.warned()))?;
+  }
+
+  if tolerances.directional() {
+    write_directions(out, report, tolerances, opts, headers.as_ref())?;
+  }
+
+  if let Some(impact) = &report.impact_classes() {
+    write_impact(out, impact, report, opts, headers.as_ref())?;
+  }
+
+  if args.per_column {
+    writeln!(out, "\nworst differences per column:")?;
+    let style = (painter, precision);
+    write_per_column(out, report, tolerances, header, style)?;
+  }
+
+  if args.compare_annotations {
+    let mismatches = &report.annotation_mismatches;
+    writeln!(
+      out,
+      "\ncompared cells annotated differently: {}",
+      painter.value(&mismatches.len().to_string(), mismatches.is_empty())
+    )?;
+    for mismatch in mismatches {
+      writeln!(out, "  {}", annotation_mismatch(mismatch))?;
+    }
+    writeln!(out, "result: {}", painter.status(mismatches.is_empty()))?;
+  }
+  if matching.is_some() || keyed.is_some() {
+    let (unpaired1, unpaired2) = &report.unpaired_rows;
+    let count = unpaired1.len() + unpaired2.len();
+    writeln!(
+      out,
+      "\nrows left unpaired: {}",
+      painter.value(&count.to_string(), count == 0)
+    )?;
+    if let Some(unpaired) =
+      unpaired("line", &report.unpaired_rows, (&bn1, &bn2))
+    {
+      writeln!(out, "  {unpaired}")?;
     }
-    println!();
+    writeln!(out, "result: {}", painter.status(count == 0))?;
   }
+  if let Some(matching) = &mac_matching {
+    writeln!(out, "\nMAC of the float columns (* paired):")?;
+    write_mac_matrix(out, matching)?;
+    let (unpaired1, unpaired2) = &report.unpaired_columns;
+    let count = unpaired1.len() + unpaired2.len();
+    writeln!(
+      out,
+      "float columns left unpaired: {}",
+      painter.value(&count.to_string(), count == 0)
+    )?;
+    if let Some(unpaired) =
+      unpaired("column", &report.unpaired_columns, (&bn1, &bn2))
+    {
+      writeln!(out, "  {unpaired}")?;
+    }
+    writeln!(out, "result: {}", painter.status(count == 0))?;
+  }
+  if let Some(phases) = &report.complex {
+    writeln!(out, "\ncomplex pairs, by magnitude and phase:")?;
+    for (deviations, (label, _)) in phases.pairs.iter().zip(magnitudes) {
+      writeln!(out, "  {label}: {}", pair_deviations(deviations))?;
+    }
+    if let Some(max) = phases.max_phase_deg {
+      let passed = phases.passed();
+      if let Some(worst) = phases.worst() {
+        let p = phases.pairs.iter().position(|p| p == worst).unwrap();
+        writeln!(
+          out,
+          "maximum phase difference seen: {} degrees ({}, line {})",
+          painter.value(&format!("{:.3}", worst.phase.value), passed),
+          magnitudes[p].0,
+          worst.phase.line
+        )?;
+      }
+      writeln!(
+        out,
+        "phase criterion: {} of {} phases exceed {max} degrees",
+        thousands(phases.failing()),
+        thousands(phases.compared())
+      )?;
+      writeln!(out, "result: {}", painter.status(passed))?;
+    }
+  }
+  if let Some(matrix) = &report.matrix {
+    let (rows, cols) = matrix.shape;
+    writeln!(out, "\nas {rows}x{cols} matrices A ({bn1}) and B ({bn2}):")?;
+    let norms = [("Frobenius", Some(matrix.frobenius))];
+    let norms = norms.into_iter().chain([("max row sum", matrix.row_sum)]);
+    for (name, norm) in norms {
+      let Some(norm) = norm else {
+        continue;
+      };
+      let passed = matrix.max_rel.is_none_or(|max| norm.relative() <= max);
+      writeln!(
+        out,
+        "  {name}: ||A|| {:.6E}, ||A-B|| {:.6E}, relative {}",
+        norm.baseline,
+        norm.difference,
+        painter.value(&format!("{:.2E}", norm.relative()), passed)
+      )?;
+    }
+    let worst = &matrix.worst;
+    if worst.line > 0 {
+      writeln!(
+        out,
+        "largest entry of A-B: {:.2E}, the values {:+.6E} and {:+.6E} \
+         (line {}, {})",
+        worst.value,
+        worst.vals.0,
+        worst.vals.1,
+        worst.line,
+        column_label(opts, worst.col, headers.as_ref())
+      )?;
+    }
+    if let Some(max) = matrix.max_rel {
+      writeln!(
+        out,
+        "matrix criterion: ||A-B|| at most {max} of ||A|| in each norm"
+      )?;
+      writeln!(out, "result: {}", painter.status(matrix.passed()))?;
+    }
+  }
+  if let Some(col) = &args.group_by
+    && args.group_format == "text"
+  {
+    let groups = &report.groups;
+    let failing = groups.iter().filter(|g| !g.report.passed()).count();
+    writeln!(
+      out,
+      "\ngroups by column {col} failing: {}",
+      painter.value(&format!("{failing} of {}", groups.len()), failing == 0)
+    )?;
+    for group in groups {
+      writeln!(out, "  {}", group_line(col, group, tolerances, painter))?;
+    }
+    writeln!(out, "result: {}", painter.status(failing == 0))?;
+  }
+  if !report.overrides.is_empty() {
+    let columns = &report.overrides;
+    let failing = columns.iter().filter(|c| !c.report.passed()).count();
+    writeln!(
+      out,
+      "\ncolumns with tolerances of their own failing: {}",
+      painter.value(&format!("{failing} of {}", columns.len()), failing == 0)
+    )?;
+    for column in columns {
+      writeln!(out, "  {}", override_line(column, header, painter))?;
+    }
+    writeln!(out, "result: {}", painter.status(failing == 0))?;
+  }
+  if !assertions.is_empty() {
+    let failures = &report.assertion_failures;
+    let held = assertions.len() - failures.len();
+    writeln!(
+      out,
+      "\nassertions of the tolerance spec held: {}",
+      painter.value(
+        &format!("{held} of {}", assertions.len()),
+        failures.is_empty()
+      )
+    )?;
+    for failure in failures {
+      writeln!(out, "  {}", assertion_failure(failure))?;
+    }
+    writeln!(out, "result: {}", painter.status(failures.is_empty()))?;
+  }
+  let demoted = report.demoted_columns();
+  if !args.no_column_report && !demoted.is_empty() {
+    writeln!(out, "\n{}:", demoted_summary(report, &demoted))?;
+    for column in &demoted {
+      writeln!(
+        out,
+        "  {}: {:?} at line {} of {}, after {} row(s) of floats",
+        column_label(opts, column.col, headers.as_ref()),
+        column.cell,
+        column.line,
+        if column.file == 1 { &bn1 } else { &bn2 },
+        column.floats_before
+      )?;
+    }
+  }
+  if args.require_all_float_columns || args.min_compared_columns.is_some() {
+    let failures = &report.column_failures;
+    writeln!(out, "\ncompared columns' requirements:")?;
+    for failure in failures {
+      writeln!(out, "  {failure}")?;
+    }
+    writeln!(out, "result: {}", painter.status(failures.is_empty()))?;
+  }
+  if let Some(excluded) = excluded
+    && args.audit_exclusions
+  {
+    let stale = &report.stale_exclusions;
+    let held = excluded.len() - stale.len();
+    writeln!(
+      out,
+      "\nexclusions that left out a cell: {}",
+      painter.value(&format!("{held} of {}", excluded.len()), stale.is_empty())
+    )?;
+    for exclusion in stale {
+      writeln!(out, "  stale: {exclusion}")?;
+    }
+    writeln!(out, "result: {}", painter.status(stale.is_empty()))?;
+  }
+  if let Some(command) = &repro {
+    writeln!(out, "\nto reproduce the worst violation:\n  {command}")?;
+  }
+  if !excusing.is_empty() {
+    write_waivers(out, painter, excusing)?;
+  }
+  Ok(())
+}
+
+/// Reads a pair of files and compares them, writing --diff-headers and
+/// --show-effective-columns to `out` as it goes, and --save-report and
+/// --write-diff-csv to their files.
+fn compare_pair(
+  args: &Args,
+  tolerances: &Tolerances,
+  (assertions, specs): (&Assertions, &BTreeMap<String, ColumnSpec>),
+  (waivers, exclusions): (&[Waiver], Option<&Exclusions>),
+  out: &mut dyn Write,
+  (csv1, csv2): (&str, &str),
+  phases: &mut PhaseTimes,
+) -> Result<Compared, Fatal> {
+  let started = Instant::now();
+  let (mut records1, read1) = read_records_timed(args, csv1)?;
+  let (mut records2, read2) = read_records_timed(args, csv2)?;
+  phases.add(Phase::Reading, read1 + read2);
+  let transposed1 = transpose_records(args.transpose1, (csv1, &mut records1))?;
+  transpose_records(args.transpose2, (csv2, &mut records2))?;
+  phases.add(
+    Phase::Parsing,
+    started.elapsed().saturating_sub(read1 + read2),
+  );
+  let mut headers =
+    strip_headers(args, (csv1, &mut records1), (csv2, &mut records2))?;
+  if let Some(format) = &args.diff_headers
+    && let Some((header1, header2)) = &headers
+  {
+    write_header_diff(out, format, (csv1, header1), (csv2, header2))?;
+  }
+
+  let float_re = float_regex(args)?;
+  let mac_matching = args.mac_match.then(|| {
+    let threshold = args.mac_threshold;
+    mac::match_columns((&records1, &records2), threshold, &float_re)
+  });
+  let mut opts = DiffConfig {
+    column_map: match &mac_matching {
+      Some(matching) => Some(mac_column_map(matching)),
+      None => column_map(
+        args,
+        (csv1, &records1),
+        (csv2, &records2),
+        headers.as_ref(),
+      )?,
+    },
+    ..diff_config(args, tolerances)?
+  };
+  if let Some(matching) = &mac_matching {
+    allow_opposed_flips(&mut opts, matching);
+  }
+  let effective = effective_columns(
+    args,
+    &opts,
+    (&records1, &records2),
+    headers.as_ref().map(|(header1, _)| header1),
+  )?;
+  sort_rows(args, &opts, (csv1, &mut records1), (csv2, &mut records2))?;
+  let matching = match_rows(
+    args,
+    &opts,
+    (csv1, &mut records1),
+    (csv2, &mut records2),
+    headers.as_ref().map(|(header1, _)| header1),
+  )?;
+  let keyed = pair_keys(
+    args,
+    &opts,
+    (csv1, &mut records1),
+    (csv2, &mut records2),
+    headers.as_ref().map(|(header1, _)| header1),
+  )?;
+  let shifted = shift_rows(
+    args,
+    &opts,
+    (csv1, &mut records1),
+    (csv2, &mut records2),
+    headers.as_ref().map(|(header1, _)| header1),
+  )?;
+  let sampled = keep_rows(args, (csv1, &mut records1), &mut records2)?;
+  let annotated =
+    strip_suffixes(args, (csv1, &mut records1), (csv2, &mut records2))?;
+  let mut vectors = vectors(
+    args,
+    &mut opts,
+    (csv1, &mut records1),
+    (csv2, &mut records2),
+    headers.as_mut(),
+  )?;
+  let (complex, magnitudes) = complex_pairs(
+    args,
+    &mut opts,
+    (csv1, &mut records1),
+    (csv2, &mut records2),
+    headers.as_mut(),
+  )?;
+  vectors.extend(magnitudes.iter().cloned());
+  let header = headers.as_ref().map(|(header1, _)| header1);
+  let inferred = percent_columns(
+    args,
+    &mut opts,
+    (csv1, &records1),
+    (csv2, &records2),
+    header,
+  )?;
+  if args.show_effective_columns {
+    write_effective_columns(
+      out,
+      args,
+      (csv1, csv2),
+      &effective,
+      &opts,
+      (header, &vectors),
+    )?;
+  }
+  let normalize = (args.normalize_columns != Normalization::None).then(|| {
+    let records = (&records1[..], &records2[..]);
+    ColumnNorms::new(args.normalize_columns, records, &opts)
+  });
+  let opts = DiffConfig { normalize, ..opts };
+  let width = records1.iter().map(StringRecord::len).max().unwrap_or(0);
+  let opts = keep_columns(args, opts, header, width)?;
+  let specs = column_specs(specs, (csv1, width), header)?;
+  // the columns with tolerances of their own
+  let overridden: Vec<(usize, Tolerances)> = specs
+    .iter()
+    .filter_map(|(col, spec)| Some((*col, spec.tolerances()?)))
+    .collect();
+  let significance = specs
+    .iter()
+    .filter_map(|(col, spec)| Some((*col, spec.significance?)))
+    .collect();
+  // the cells each listed exclusion names, for auditing them
+  let excluded = exclusions
+    .map(|exclusions| exclusions.resolve((csv1, csv2), &records1, header))
+    .transpose()
+    .map_err(|e| Fatal::new(format!("Error: {e}")))?;
+  let diff_scale = match &args.scale_tolerance_by {
+    Some(col) => Some(DiffScale {
+      col: flag_column("--scale-tolerance-by", col, header)?,
+      floor: args.scale_tolerance_floor,
+    }),
+    None => None,
+  };
+  let opts = DiffConfig {
+    significance,
+    diff_scale,
+    excluded_cells: excluded
+      .iter()
+      .flatten()
+      .flat_map(|(_, cells)| cells)
+      .copied()
+      .collect(),
+    ..opts
+  };
+  // the columns with tolerances of their own are compared on their own
+  let whole = opts;
+  let opts = match overridden.is_empty() {
+    true => whole.clone(),
+    false => {
+      let cols: Vec<usize> = overridden.iter().map(|(col, _)| *col).collect();
+      overrides::without(&whole, &cols, width)
+    }
+  };
+  let group_by = match &args.group_by {
+    Some(col) => {
+      let col1 = flag_column("--group-by", col, header)?;
+      let col2 = opts.col2(col1).ok_or_else(|| {
+        Fatal::new(format!(
+          "Error: --group-by column {col} isn't paired with a column of {csv2}"
+        ))
+      })?;
+      Some((col1, col2))
+    }
+    None => None,
+  };
+  let checks = Checks {
+    group_by,
+    overrides: (!overridden.is_empty()).then(|| checks::Overrides {
+      columns: overridden,
+      whole: whole.clone(),
+    }),
+    complex,
+    max_phase_deg: args.max_phase_deg,
+    matrix: args.matrix_mode.then_some(MatrixNorms {
+      row_sum: args.matrix_row_sum,
+      max_rel: args.max_matrix_rel,
+    }),
+    assertions: assertions.clone(),
+  };
+
+  let paths = (csv1, csv2);
+  let rows = records1.len().min(records2.len()) as u64;
+  bar::begin(
+    format!("comparing {csv1} and {csv2}"),
+    bar::Unit::Rows,
+    rows,
+  );
+  let compared = match args.profile_phases {
+    Some(_) => compare::compare_profiled(&records1, &records2, &opts, paths)
+      .map(|(report, times)| {
+        phases.merge(&times);
+        report
+      }),
+    None => compare::compare(&records1, &records2, &opts, paths),
+  };
+  bar::end();
+  let mut report = compared.map_err(|e| {
+    Fatal::new(format!("Error comparing {csv1} and {csv2}: {e}"))
+  })?;
+  // whatever the phases don't account for so far
+  phases.add(
+    Phase::Other,
+    started.elapsed().saturating_sub(phases.total()),
+  );
+  // what's judged beyond the cells, of the comparison and of the
+  // reference's with --verify alike
+  let complete = |report: &mut DiffReport| {
+    if args.compare_annotations
+      && let Some((taken1, taken2)) = &annotated
+    {
+      report.annotation_mismatches =
+        annotations::mismatches(&records1, (taken1, taken2), &opts, report);
+    }
+    if let Some((_, matching)) = &matching {
+      report.unpaired_rows = matching.unpaired.clone();
+    }
+    if let Some((_, keyed)) = &keyed {
+      report.unpaired_rows = keyed.unpaired();
+    }
+    if let Some(matching) = &mac_matching {
+      let numbers = |cols: &[usize]| cols.iter().map(|col| col + 1).collect();
+      let (unpaired1, unpaired2) = &matching.unpaired;
+      report.unpaired_columns = (numbers(unpaired1), numbers(unpaired2));
+    }
+    let inputs = checks::Inputs {
+      records: (&records1, &records2),
+      headers: headers
+        .as_ref()
+        .map(|(header1, header2)| (header1, header2)),
+      files: paths,
+    };
+    checks::complete(report, inputs, &opts, &checks).map_err(|e| {
+      Fatal::new(format!("Error comparing {csv1} and {csv2}: {e}"))
+    })
+  };
+  complete(&mut report)?;
+  let excusing = match report.passed() {
+    true => Vec::new(),
+    false => waive(
+      waivers,
+      (&records1, &records2),
+      (&opts, &report),
+      annotated.as_ref(),
+      (csv1, csv2),
+    ),
+  };
+  let waiver = (!excusing.is_empty()).then(|| {
+    let described: Vec<String> =
+      excusing.iter().map(Waiver::describe).collect();
+    described.join("; ")
+  });
+  // cells are reported where they are in csv1 as written
+  let untranspose = |report: &mut DiffReport| {
+    if let Some(transposed) = &transposed1 {
+      report.untranspose(&|col| transposed.line(col));
+    }
+  };
+  untranspose(&mut report);
+  if let Some(path) = &args.save_report {
+    save_report(path, &report, &opts)?;
+  }
+  if let Some(path) = &args.write_diff_csv {
+    let matrix = matrix::diff_matrix(
+      &records1,
+      &records2,
+      &opts,
+      &report,
+      args.diff_metric,
+      &args.diff_empty,
+    );
+    write_diff_csv(path, header, &matrix, opts.delimiter).map_err(|e| {
+      Fatal::new(format!("Error writing {}: {e}", path.display()))
+    })?;
+  }
+
+  if args.verify {
+    let mut spec = reference::compare(&records1, &records2, &opts);
+    complete(&mut spec)?;
+    untranspose(&mut spec);
+    let discrepancies = report.discrepancies(&spec);
+    if !discrepancies.is_empty() {
+      let mut msg =
+        "Error: verification failed, implementations disagree:".to_string();
+      for (fast, slow) in discrepancies {
+        msg.push_str(&format!("\n  fast: {fast}\n  reference: {slow}"));
+      }
+      return Err(Fatal::new(msg));
+    }
+  }
+
+  if let (Some(exclusions), Some(excluded)) = (exclusions, &excluded)
+    && args.audit_exclusions
+  {
+    let left_out = report.excluded_cells();
+    report.stale_exclusions = excluded
+      .iter()
+      .filter(|(_, cells)| {
+        !cells
+          .iter()
+          .any(|&(line, col)| left_out.contains(&(line, col + 1)))
+      })
+      .map(|&(i, _)| exclusions.cell[i].describe())
+      .collect();
+  }
+
+  report.column_failures = column_failures(args, &report);
+  report.sampled_rows = sampled;
+  let failures = args.list_failures.map(|limit| {
+    let records = (records1.as_slice(), records2.as_slice());
+    list_failures(records, (&opts, &whole), &report, limit)
+  });
+  Ok(Compared {
+    report,
+    excusing,
+    waiver,
+    opts,
+    records: (records1, records2),
+    headers,
+    transposed1,
+    vectors,
+    magnitudes,
+    excluded,
+    inferred,
+    matching,
+    keyed,
+    mac_matching,
+    shifted,
+    failures,
+  })
 }
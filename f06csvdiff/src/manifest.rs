@@ -0,0 +1,141 @@
+//! Manifests: TOML files listing the pairs of files a batch run compares,
+//! each with optional tolerances of its own.
+//!
+//! `gen-manifest` writes them from two directory trees, using the same
+//! pairing rules as globs in batch mode, and `--manifest` reads them back.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use f06csvdiff::verdict::Tolerances;
+
+/// The contents of a manifest.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Manifest {
+  /// The pairs, compared in order.
+  pub(crate) pair: Vec<Entry>,
+}
+
+/// A pair of files to compare. Paths are used as written, so relative ones
+/// are relative to the working directory.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Entry {
+  /// The first CSV.
+  pub(crate) csv1: String,
+  /// The second CSV.
+  pub(crate) csv2: String,
+  /// Overrides -r for this pair.
+  pub(crate) max_ratio: Option<f64>,
+  /// Overrides -d for this pair.
+  pub(crate) max_diff: Option<f64>,
+  /// Overrides --min-digits for this pair.
+  pub(crate) min_digits: Option<f64>,
+}
+
+impl Entry {
+  /// This pair's tolerances, falling back to the given ones for each gate.
+  /// With --allclose, the pair's -r and -d are the parts of its combined
+  /// tolerance, as they are on the command line.
+  pub(crate) fn tolerances(&self, fallback: &Tolerances) -> Tolerances {
+    let combined = fallback.allclose().is_some();
+    let own = Tolerances {
+      max_ratio: self.max_ratio.filter(|_| !combined),
+      max_diff: self.max_diff.filter(|_| !combined),
+      ..Default::default()
+    };
+    Tolerances {
+      max_ratio: own.max_ratio.or(fallback.max_ratio),
+      max_diff: own.max_diff.or(fallback.max_diff),
+      min_digits: self.min_digits.or(fallback.min_digits),
+      max_fail_fraction: fallback.max_fail_fraction,
+      atol: self.max_diff.filter(|_| combined).or(fallback.atol),
+      rtol: self.max_ratio.filter(|_| combined).or(fallback.rtol),
+      ..own.directional_over(fallback)
+    }
+  }
+}
+
+impl Manifest {
+  /// Reads and parses a manifest.
+  pub(crate) fn load(path: &Path) -> Result<Self, String> {
+    let text = std::fs::read_to_string(path)
+      .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+    toml::from_str(&text)
+      .map_err(|e| format!("bad manifest {}: {e}", path.display()))
+  }
+}
+
+/// Quotes a string as a TOML basic string.
+fn quote(text: &str) -> String {
+  let mut quoted = String::from("\"");
+  for c in text.chars() {
+    match c {
+      '"' => quoted.push_str("\\\""),
+      '\\' => quoted.push_str("\\\\"),
+      c if c.is_control() => {
+        let _ = write!(quoted, "\\u{:04X}", c as u32);
+      }
+      c => quoted.push(c),
+    }
+  }
+  quoted.push('"');
+  quoted
+}
+
+/// Rounds a tolerance up to two significant digits, so that the value it
+/// was suggested from still passes. This goes through decimal text so the
+/// result prints as short as it looks.
+pub(crate) fn round_up(value: f64) -> f64 {
+  if value <= 0.0 || !value.is_finite() {
+    return value;
+  }
+  let text = format!("{value:.1e}");
+  let (mantissa, exponent) = text.split_once('e').unwrap();
+  let mantissa: f64 = mantissa.parse().unwrap();
+  let at = |m: f64| format!("{m:.1}e{exponent}").parse::<f64>().unwrap();
+  let rounded = at(mantissa);
+  if rounded < value {
+    at(mantissa + 0.1)
+  } else {
+    rounded
+  }
+}
+
+/// Formats a manifest. Pairs without tolerances get commented-out
+/// placeholders; files without a counterpart are listed as comments at the
+/// end.
+pub(crate) fn generate(
+  pairs: &[(String, String, Option<Tolerances>)],
+  unpaired: &[String],
+) -> String {
+  let mut text = String::from("# Pairs of files for f06csvdiff --manifest.\n");
+  for (csv1, csv2, tolerances) in pairs {
+    let _ = write!(
+      text,
+      "\n[[pair]]\ncsv1 = {}\ncsv2 = {}\n",
+      quote(csv1),
+      quote(csv2)
+    );
+    let values = [
+      ("max_ratio", tolerances.as_ref().and_then(|t| t.max_ratio)),
+      ("max_diff", tolerances.as_ref().and_then(|t| t.max_diff)),
+    ];
+    for (key, value) in values {
+      let _ = match value {
+        Some(value) => writeln!(text, "{key} = {value:e}"),
+        None => writeln!(text, "# {key} = "),
+      };
+    }
+  }
+  if !unpaired.is_empty() {
+    text.push_str("\n# Files without a counterpart:\n");
+    for path in unpaired {
+      let _ = writeln!(text, "#   {path}");
+    }
+  }
+  text
+}
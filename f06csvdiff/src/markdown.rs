@@ -0,0 +1,60 @@
+//! GitHub-flavoured Markdown tables, for pasting into pull requests.
+
+use std::io::{self, Write};
+
+use crate::color::Paint;
+use crate::table::Cell;
+
+/// Escapes text so it can't break out of a table cell or format itself.
+pub fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    if matches!(c, '\\' | '|' | '*' | '_' | '`' | '[' | ']' | '<' | '>') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+/// Formats the header and delimiter rows of a table.
+pub fn header(headers: &[String]) -> String {
+  let names: Vec<String> = headers.iter().map(|h| escape(h)).collect();
+  let rules = vec!["---"; headers.len()];
+  format!("| {} |\n| {} |", names.join(" | "), rules.join(" | "))
+}
+
+/// Formats a row of a table, with failing cells in bold and waived or
+/// warned ones in italics.
+pub fn row(cells: &[Cell]) -> String {
+  let cells: Vec<String> = cells
+    .iter()
+    .map(|(text, paint)| match paint {
+      Paint::Fail | Paint::Offending => format!("**{}**", escape(text)),
+      Paint::Waived | Paint::Warned => format!("_{}_", escape(text)),
+      Paint::Plain | Paint::Pass => escape(text),
+    })
+    .collect();
+  format!("| {} |", cells.join(" | "))
+}
+
+/// Writes rows of a Markdown table, starting a new table unless the headers
+/// are those of the last one.
+pub fn write_rows(
+  out: &mut dyn Write,
+  last_headers: &mut Option<Vec<String>>,
+  headers: Vec<String>,
+  rows: &[Vec<Cell>],
+) -> io::Result<()> {
+  if last_headers.as_ref() != Some(&headers) {
+    if last_headers.is_some() {
+      writeln!(out)?;
+    }
+    writeln!(out, "{}", header(&headers))?;
+    *last_headers = Some(headers);
+  }
+  for cells in rows {
+    writeln!(out, "{}", row(cells))?;
+  }
+  Ok(())
+}
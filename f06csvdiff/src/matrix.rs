@@ -0,0 +1,217 @@
+//! The difference at every cell, in the shape of the inputs, for plotting
+//! where the errors are rather than only how large the worst ones get; and
+//! the norms of the difference, for files that are literal matrices, such as
+//! reduced stiffness or mass matrices.
+
+use std::str::FromStr;
+
+use csv::StringRecord;
+
+use crate::compare::{
+  DiffConfig, DiffReport, Extremum, compared_values, parse_float, physical_line,
+};
+use crate::error::DiffError;
+
+/// What each compared cell of a difference matrix holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiffMetric {
+  /// The absolute difference, |a-b|.
+  #[default]
+  Abs,
+  /// The relative difference, |a-b|/max(|a|,|b|).
+  Rel,
+  /// csv2's value less csv1's, b-a.
+  Signed,
+}
+
+impl FromStr for DiffMetric {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "abs" => Ok(DiffMetric::Abs),
+      "rel" => Ok(DiffMetric::Rel),
+      "signed" => Ok(DiffMetric::Signed),
+      _ => Err(format!(
+        "Invalid diff metric: {s}. Must be abs, rel, or signed"
+      )),
+    }
+  }
+}
+
+impl DiffMetric {
+  /// The metric of a compared pair of values.
+  pub fn of(self, a: f64, b: f64) -> f64 {
+    match self {
+      DiffMetric::Abs => (a - b).abs(),
+      DiffMetric::Rel => (a - b).abs() / a.abs().max(b.abs()),
+      DiffMetric::Signed => b - a,
+    }
+  }
+}
+
+/// csv1's records with each cell of a compared float column replaced by the
+/// metric of its values, as the comparison `report` (found with `opts`)
+/// measured them. Cells it skipped, and those of rows it didn't compare,
+/// hold `empty` instead; other columns are copied from csv1.
+pub fn diff_matrix(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+  report: &DiffReport,
+  metric: DiffMetric,
+  empty: &str,
+) -> Vec<StringRecord> {
+  let flipped = |i: usize| report.flipped_columns.contains(&i);
+  records1
+    .iter()
+    .enumerate()
+    .map(|(row, rec1)| {
+      let rec2 = records2.get(row).filter(|_| row < report.rows);
+      rec1
+        .iter()
+        .enumerate()
+        .map(|(i, text1)| {
+          let Some(stats) = report.columns.iter().find(|c| c.col == i + 1)
+          else {
+            return text1.to_string();
+          };
+          let excluded = opts.excludes(physical_line(records1, row), i);
+          let values = rec2.filter(|_| !excluded).and_then(|rec2| {
+            let text2 = rec2.get(opts.col2(i)?)?;
+            let factors = opts.factors(i);
+            let v1 = opts.value(factors.0, parse_float(text1)?);
+            let v2 = opts.value(factors.1, parse_float(text2)?);
+            let cutoff = (stats.cutoff, opts.threshold_mode);
+            let values = compared_values(v1, v2, flipped(i), cutoff).ok()?;
+            Some(opts.measured(values, (text1, text2), factors))
+          });
+          match values {
+            Some((a, b)) => format!("{:E}", metric.of(a, b)),
+            None => empty.to_string(),
+          }
+        })
+        .collect()
+    })
+    .collect()
+}
+
+/// A norm of csv1's matrix and of the difference of the two.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MatrixNorm {
+  /// The norm of csv1's matrix, A.
+  pub baseline: f64,
+  /// The norm of the difference, A-B.
+  pub difference: f64,
+}
+
+impl MatrixNorm {
+  /// The difference's norm relative to the baseline's: zero if both are
+  /// zero, and infinite if only the baseline is.
+  pub fn relative(&self) -> f64 {
+    match (self.baseline, self.difference) {
+      (_, 0.0) => 0.0,
+      (0.0, _) => f64::INFINITY,
+      (baseline, difference) => difference / baseline,
+    }
+  }
+}
+
+/// How two files differ taken as dense matrices: the compared float columns
+/// of the compared rows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatrixReport {
+  /// The matrices' rows and columns.
+  pub shape: (usize, usize),
+  /// The Frobenius norms, the root sum of squares of every entry.
+  pub frobenius: MatrixNorm,
+  /// The max row-sum norms, the largest sum of a row's absolute entries, if
+  /// asked for.
+  pub row_sum: Option<MatrixNorm>,
+  /// The entry that differs most, absolutely, to show where.
+  pub worst: Extremum,
+  /// The largest allowed relative norm of the difference, if there is one.
+  pub max_rel: Option<f64>,
+}
+
+impl MatrixReport {
+  /// Whether no relative norm of the difference exceeds the tolerance.
+  pub fn passed(&self) -> bool {
+    let norms = [Some(self.frobenius), self.row_sum];
+    self.max_rel.is_none_or(|max| {
+      norms.iter().flatten().all(|norm| norm.relative() <= max)
+    })
+  }
+}
+
+/// Takes the compared float columns of the first `report.rows` rows of each
+/// file as a dense matrix, A and B, and measures the norms of A and of A-B,
+/// the max row-sum norms as well if `row_sum`. Entries are the values as
+/// `opts` compares them, sign-flipped columns included, but none is skipped
+/// for being small. Fails with [`DiffError::RowCountMismatch`] if the files
+/// have different numbers of rows, since the matrices would have different
+/// shapes.
+pub fn matrix_norms(
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  opts: &DiffConfig,
+  report: &DiffReport,
+  row_sum: bool,
+  max_rel: Option<f64>,
+  (path1, path2): (&str, &str),
+) -> Result<MatrixReport, DiffError> {
+  if records1.len() != records2.len() {
+    let (longer, path) = match records1.len() > records2.len() {
+      true => (records1, path1),
+      false => (records2, path2),
+    };
+    return Err(DiffError::RowCountMismatch {
+      rows1: records1.len(),
+      rows2: records2.len(),
+      file: path.to_string(),
+      line: physical_line(longer, records1.len().min(records2.len())),
+    });
+  }
+  let mut squares = (0.0, 0.0);
+  let mut sums = MatrixNorm::default();
+  let mut worst = Extremum::new(0.0);
+  for row in 0..report.rows {
+    let (rec1, rec2) = (&records1[row], &records2[row]);
+    let line = physical_line(records1, row);
+    let mut sum = (0.0, 0.0);
+    for stats in &report.columns {
+      let i = stats.col - 1;
+      let cell = |rec: &StringRecord, col: Option<usize>| -> Option<f64> {
+        parse_float(rec.get(col?)?)
+      };
+      // a compared column is a float in every compared row
+      let (Some(a), Some(b)) = (cell(rec1, Some(i)), cell(rec2, opts.col2(i)))
+      else {
+        continue;
+      };
+      let factors = opts.factors(i);
+      let (a, b) = (opts.value(factors.0, a), opts.value(factors.1, b));
+      let b = match report.flipped_columns.contains(&i) {
+        true => -b,
+        false => b,
+      };
+      let difference = (a - b).abs();
+      squares.0 += a * a;
+      squares.1 += difference * difference;
+      sum.0 += a.abs();
+      sum.1 += difference;
+      worst.raise(difference, (a, b), line, stats.col);
+    }
+    sums.baseline = sums.baseline.max(sum.0);
+    sums.difference = sums.difference.max(sum.1);
+  }
+  Ok(MatrixReport {
+    shape: (report.rows, report.columns.len()),
+    frobenius: MatrixNorm {
+      baseline: squares.0.sqrt(),
+      difference: squares.1.sqrt(),
+    },
+    row_sum: row_sum.then_some(sums),
+    worst,
+    max_rel,
+  })
+}
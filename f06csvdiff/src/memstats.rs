@@ -0,0 +1,90 @@
+//! Peak memory tracking.
+//!
+//! With the `mem-stats` feature, a counting global allocator wraps the system
+//! one and keeps track of the bytes currently allocated and their peak.
+//! Without it, nothing is compiled in and no peak is known.
+
+#[cfg(feature = "mem-stats")]
+mod counting {
+  use std::alloc::{GlobalAlloc, Layout, System};
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  /// The bytes currently allocated.
+  pub(super) static CURRENT: AtomicUsize = AtomicUsize::new(0);
+  /// The most bytes ever allocated at once.
+  pub(super) static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+  /// The system allocator, counting as it goes.
+  struct Counting;
+
+  unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+      let ptr = unsafe { System.alloc(layout) };
+      if !ptr.is_null() {
+        grow(layout.size());
+      }
+      ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+      unsafe { System.dealloc(ptr, layout) };
+      CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+      let ptr = unsafe { System.alloc_zeroed(layout) };
+      if !ptr.is_null() {
+        grow(layout.size());
+      }
+      ptr
+    }
+
+    unsafe fn realloc(
+      &self,
+      ptr: *mut u8,
+      layout: Layout,
+      new_size: usize,
+    ) -> *mut u8 {
+      let new = unsafe { System.realloc(ptr, layout, new_size) };
+      if !new.is_null() {
+        if new_size > layout.size() {
+          grow(new_size - layout.size());
+        } else {
+          CURRENT.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+        }
+      }
+      new
+    }
+  }
+
+  /// Accounts for newly allocated bytes.
+  fn grow(bytes: usize) {
+    let current = CURRENT.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+  }
+
+  #[global_allocator]
+  static ALLOCATOR: Counting = Counting;
+}
+
+/// The most bytes allocated at once so far, if tracked.
+pub(crate) fn peak() -> Option<usize> {
+  #[cfg(feature = "mem-stats")]
+  {
+    Some(counting::PEAK.load(std::sync::atomic::Ordering::Relaxed))
+  }
+  #[cfg(not(feature = "mem-stats"))]
+  {
+    None
+  }
+}
+
+/// Restarts peak tracking from the bytes currently allocated, returning them;
+/// for tests.
+#[cfg(all(test, feature = "mem-stats"))]
+pub(crate) fn reset_peak() -> usize {
+  use std::sync::atomic::Ordering;
+  let current = counting::CURRENT.load(Ordering::Relaxed);
+  counting::PEAK.store(current, Ordering::Relaxed);
+  current
+}
@@ -0,0 +1,123 @@
+//! Pairing rows by the nearest value of a column, for tables whose rows may
+//! swap places between runs, such as the frequencies of nearly degenerate
+//! modes. Each row of csv1 is paired with a row of csv2 at most a window of
+//! rows away, closest pairs first, and csv2's rows are put in the order of
+//! the rows they're paired with. Records keep their positions, so reports
+//! still give the lines they were read from.
+
+use csv::{Position, StringRecord};
+
+use crate::compare::{parse_float, physical_line};
+use crate::error::DiffError;
+
+/// How the rows of two files were paired.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RowMatching {
+  /// The physical (1-based) lines of the rows paired out of order, csv1's
+  /// with csv2's, in csv1's order.
+  pub reordered: Vec<(usize, usize)>,
+  /// The physical lines of each file's rows left unpaired.
+  pub unpaired: (Vec<usize>, Vec<usize>),
+}
+
+/// Each record's value in a (0-based) column, giving records without a
+/// position their place in the file first. Fails with
+/// [`DiffError::MatchKey`] if a row lacks a number there.
+fn values(
+  records: &mut [StringRecord],
+  col: usize,
+  file: &str,
+) -> Result<Vec<f64>, DiffError> {
+  (0..records.len())
+    .map(|row| {
+      let line = physical_line(records, row);
+      let record = &mut records[row];
+      if record.position().is_none() {
+        let mut position = Position::new();
+        position.set_line(line as u64);
+        record.set_position(Some(position));
+      }
+      let value = record.get(col).and_then(|cell| parse_float(cell.trim()));
+      value.ok_or_else(|| DiffError::MatchKey {
+        file: file.to_string(),
+        line,
+        col: col + 1,
+      })
+    })
+    .collect()
+}
+
+/// Pairs the rows of two files by their values in a (0-based) column of
+/// each, each row with one at most `window` rows away from it. Pairs are
+/// made closest first, and among as close, nearest in place first. Leaves
+/// the paired rows of both files in csv1's order, and takes the unpaired
+/// rows off.
+pub fn match_nearest(
+  (file1, records1): (&str, &mut Vec<StringRecord>),
+  (file2, records2): (&str, &mut Vec<StringRecord>),
+  (col1, col2): (usize, usize),
+  window: usize,
+) -> Result<RowMatching, DiffError> {
+  let values1 = values(records1, col1, file1)?;
+  let values2 = values(records2, col2, file2)?;
+  let mut candidates: Vec<(f64, usize, usize)> = Vec::new();
+  for (i, v1) in values1.iter().enumerate() {
+    let end = (i + window + 1).min(values2.len());
+    for (j, v2) in values2.iter().enumerate().take(end).skip(i - i.min(window))
+    {
+      candidates.push(((v1 - v2).abs(), i, j));
+    }
+  }
+  candidates.sort_by(|a, b| {
+    a.0
+      .total_cmp(&b.0)
+      .then(a.1.abs_diff(a.2).cmp(&b.1.abs_diff(b.2)))
+      .then((a.1, a.2).cmp(&(b.1, b.2)))
+  });
+  let mut partner1: Vec<Option<usize>> = vec![None; values1.len()];
+  let mut taken2 = vec![false; values2.len()];
+  for (_, i, j) in candidates {
+    if partner1[i].is_none() && !taken2[j] {
+      partner1[i] = Some(j);
+      taken2[j] = true;
+    }
+  }
+
+  let pairs: Vec<(usize, usize)> = partner1
+    .iter()
+    .enumerate()
+    .filter_map(|(i, j)| Some((i, (*j)?)))
+    .collect();
+  let mut in_order: Vec<usize> = pairs.iter().map(|&(_, j)| j).collect();
+  in_order.sort_unstable();
+  let mut matching = RowMatching {
+    reordered: pairs
+      .iter()
+      .zip(&in_order)
+      .filter(|((_, j), k)| j != *k)
+      .map(|(&(i, j), _)| {
+        (physical_line(records1, i), physical_line(records2, j))
+      })
+      .collect(),
+    ..Default::default()
+  };
+  matching.unpaired.0 = (0..values1.len())
+    .filter(|&i| partner1[i].is_none())
+    .map(|i| physical_line(records1, i))
+    .collect();
+  matching.unpaired.1 = (0..values2.len())
+    .filter(|&j| !taken2[j])
+    .map(|j| physical_line(records2, j))
+    .collect();
+
+  let mut taken1: Vec<Option<StringRecord>> =
+    records1.drain(..).map(Some).collect();
+  let mut taken2: Vec<Option<StringRecord>> =
+    records2.drain(..).map(Some).collect();
+  for (i, j) in pairs {
+    // each row is paired at most once
+    records1.push(taken1[i].take().unwrap());
+    records2.push(taken2[j].take().unwrap());
+  }
+  Ok(matching)
+}
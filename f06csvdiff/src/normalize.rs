@@ -0,0 +1,118 @@
+//! Column normalization, for results whose shape matters rather than their
+//! size, such as mode shapes or load distributions: each file's columns are
+//! divided by their own norms before the comparison, so that a uniformly
+//! scaled column compares as identical and changes of shape stand out. The
+//! norms' ratio between the files is itself a measure of the scaling.
+
+use csv::StringRecord;
+
+use crate::compare::{DiffConfig, is_float, parse_float};
+
+/// The norm each column is divided by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Normalization {
+  /// None: values are compared as they are.
+  #[default]
+  None,
+  /// The largest magnitude in the column.
+  Max,
+  /// The square root of the sum of the column's squares.
+  L2,
+}
+
+impl std::str::FromStr for Normalization {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "none" => Ok(Normalization::None),
+      "max" => Ok(Normalization::Max),
+      "l2" => Ok(Normalization::L2),
+      _ => Err(format!(
+        "Invalid normalization: {s}. Must be max, l2, or none"
+      )),
+    }
+  }
+}
+
+impl Normalization {
+  /// The name of the normalization, as --normalize-columns takes it.
+  pub fn name(self) -> &'static str {
+    match self {
+      Normalization::None => "none",
+      Normalization::Max => "max",
+      Normalization::L2 => "l2",
+    }
+  }
+}
+
+/// The norms each file's columns are divided by.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnNorms {
+  /// The norm they are.
+  pub normalization: Normalization,
+  /// Each file's norms, by its own (0-based) columns. A column whose norm
+  /// is zero, being all zeros or having no floats, is compared as it is.
+  pub norms: (Vec<f64>, Vec<f64>),
+}
+
+impl ColumnNorms {
+  /// Finds the norms of both files' columns.
+  pub fn new(
+    normalization: Normalization,
+    (records1, records2): (&[StringRecord], &[StringRecord]),
+    opts: &DiffConfig,
+  ) -> Self {
+    Self {
+      normalization,
+      norms: (
+        column_norms(records1, normalization, opts),
+        column_norms(records2, normalization, opts),
+      ),
+    }
+  }
+
+  /// A file's norm of one of its (0-based) columns, zero if it has none.
+  pub fn norm(&self, file: usize, col: usize) -> f64 {
+    let norms = if file == 1 {
+      &self.norms.0
+    } else {
+      &self.norms.1
+    };
+    norms.get(col).copied().unwrap_or(0.0)
+  }
+}
+
+/// The norm of each of a file's columns, over its float cells, clamped as
+/// `opts` clamps them; zero for a column without any. Scaling a file
+/// doesn't matter, as it scales the norms alike.
+pub fn column_norms(
+  records: &[StringRecord],
+  normalization: Normalization,
+  opts: &DiffConfig,
+) -> Vec<f64> {
+  let float_re = &opts.float_re;
+  let width = records.iter().map(StringRecord::len).max().unwrap_or(0);
+  let mut norms = vec![0.0_f64; width];
+  if normalization == Normalization::None {
+    return norms;
+  }
+  for record in records {
+    for (col, cell) in record.iter().enumerate() {
+      if !is_float(cell, float_re) {
+        continue;
+      }
+      // is_float checked that it parses
+      let value = opts.clamp(parse_float(cell).unwrap());
+      match normalization {
+        Normalization::Max => norms[col] = norms[col].max(value.abs()),
+        Normalization::L2 => norms[col] += value * value,
+        Normalization::None => {}
+      }
+    }
+  }
+  if normalization == Normalization::L2 {
+    norms.iter_mut().for_each(|n| *n = n.sqrt());
+  }
+  norms
+}
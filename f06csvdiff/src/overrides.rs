@@ -0,0 +1,89 @@
+//! Columns with tolerances of their own, such as a config file's `[columns]`
+//! tables give, for a column noisier or more critical than the rest. Such a
+//! column is judged by its own tolerances instead of the others': it's left
+//! out of the comparison of the other columns and compared on its own.
+
+use csv::StringRecord;
+
+use crate::compare::{self, ColumnMap, DiffConfig, DiffReport};
+use crate::error::DiffError;
+use crate::verdict::Tolerances;
+
+/// A column's comparison under its own tolerances.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnOverride {
+  /// The (1-based) column of csv1.
+  pub col: usize,
+  /// Its tolerances, gate by gate falling back to the others'.
+  pub tolerances: Tolerances,
+  /// What comparing it found.
+  pub report: DiffReport,
+}
+
+/// The configuration the columns without tolerances of their own are
+/// compared with: `opts`, leaving out the (0-based) columns of csv1 in
+/// `cols`. `width` is how many columns csv1 has.
+pub fn without(opts: &DiffConfig, cols: &[usize], width: usize) -> DiffConfig {
+  let pairs: Vec<(usize, usize)> = match &opts.column_map {
+    Some(map) => map.pairs().collect(),
+    None => (0..width).map(|col| (col, col)).collect(),
+  };
+  let pairs: Vec<(usize, usize)> = pairs
+    .into_iter()
+    .filter(|(col1, _)| !cols.contains(col1))
+    .collect();
+  DiffConfig {
+    column_map: Some(ColumnMap::new(&pairs).unwrap()),
+    ..opts.clone()
+  }
+}
+
+/// The configuration a column with tolerances of its own is compared with:
+/// `opts`, comparing only the (0-based) columns `col1` of csv1 and `col2` of
+/// csv2, under `tolerances`.
+pub fn alone(
+  opts: &DiffConfig,
+  (col1, col2): (usize, usize),
+  tolerances: &Tolerances,
+) -> DiffConfig {
+  DiffConfig {
+    tolerances: tolerances.clone(),
+    column_map: Some(ColumnMap::new(&[(col1, col2)]).unwrap()),
+    ..opts.clone()
+  }
+}
+
+/// Compares each column with tolerances of its own, by its (0-based) column
+/// of csv1, as `opts` compares files but under those tolerances, each gate
+/// they leave unset falling back to `opts`'s. Columns not compared with any
+/// column of csv2 are skipped. Fails as comparing a column fails.
+pub fn compare_overrides(
+  (records1, records2): (&[StringRecord], &[StringRecord]),
+  overrides: &[(usize, Tolerances)],
+  opts: &DiffConfig,
+  files: (&str, &str),
+) -> Result<Vec<ColumnOverride>, DiffError> {
+  let fallback = &opts.tolerances;
+  overrides
+    .iter()
+    .filter_map(|(col1, own)| Some((*col1, opts.col2(*col1)?, own)))
+    .map(|(col1, col2, own)| {
+      let tolerances = Tolerances {
+        max_ratio: own.max_ratio.or(fallback.max_ratio),
+        max_diff: own.max_diff.or(fallback.max_diff),
+        min_digits: own.min_digits.or(fallback.min_digits),
+        max_fail_fraction: own.max_fail_fraction.or(fallback.max_fail_fraction),
+        atol: own.atol.or(fallback.atol),
+        rtol: own.rtol.or(fallback.rtol),
+        ..own.directional_over(fallback)
+      };
+      let alone = alone(opts, (col1, col2), &tolerances);
+      let report = compare::compare(records1, records2, &alone, files)?;
+      Ok(ColumnOverride {
+        col: col1 + 1,
+        tolerances,
+        report,
+      })
+    })
+    .collect()
+}
@@ -0,0 +1,148 @@
+//! Percentage-like columns, such as efficiencies, which one exporter writes
+//! as percent (73.5) and another as fractions (0.735). Each file's
+//! convention is inferred from its values, and the columns are brought to a
+//! common one before the comparison, so that the 100x apart isn't reported
+//! as a difference. Only the listed columns are converted, which a scale
+//! for the whole file can't do.
+
+use csv::StringRecord;
+use regex::Regex;
+
+use crate::compare::{is_float, parse_float, physical_line};
+
+/// How a percentage-like value is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Convention {
+  /// From 0 to 100.
+  Percent,
+  /// From 0 to 1.
+  Fraction,
+}
+
+impl Convention {
+  /// The name of the convention, as --percent-convention takes it.
+  pub fn name(self) -> &'static str {
+    match self {
+      Convention::Percent => "percent",
+      Convention::Fraction => "fraction",
+    }
+  }
+
+  /// The largest value in range.
+  pub fn full(self) -> f64 {
+    match self {
+      Convention::Percent => 100.0,
+      Convention::Fraction => 1.0,
+    }
+  }
+
+  /// What a value written in this convention is multiplied by to be in
+  /// another.
+  pub fn factor_to(self, other: Convention) -> f64 {
+    other.full() / self.full()
+  }
+}
+
+/// A file's convention, as inferred from its values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Inference {
+  /// The convention.
+  pub convention: Convention,
+  /// The largest magnitude in the file's percentage-like columns, which it
+  /// was inferred from.
+  pub largest: f64,
+}
+
+/// Infers a file's convention from its values in some (0-based) columns:
+/// percent if any magnitude is above 1, and fractions otherwise. Cells are
+/// taken for floats by `float_re`.
+pub fn infer(
+  records: &[StringRecord],
+  cols: &[usize],
+  float_re: &Regex,
+) -> Inference {
+  let largest = records
+    .iter()
+    .flat_map(|r| cols.iter().filter_map(|&col| r.get(col)))
+    .filter(|cell| is_float(cell, float_re))
+    // is_float checked that it parses
+    .map(|cell| parse_float(cell).unwrap().abs())
+    .fold(0.0, f64::max);
+  Inference {
+    convention: match largest > 1.0 {
+      true => Convention::Percent,
+      false => Convention::Fraction,
+    },
+    largest,
+  }
+}
+
+/// The percentage-like columns, and how each file's values are converted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PercentColumns {
+  /// The (0-based) columns of csv1.
+  pub cols: Vec<usize>,
+  /// The convention each file was taken to be in.
+  pub conventions: (Convention, Convention),
+  /// The convention they're compared in.
+  pub compared_as: Convention,
+}
+
+impl PercentColumns {
+  /// What each file's values of a (0-based) column of csv1, and of the
+  /// column compared with it, are multiplied by: the conversion for the
+  /// listed columns, and 1 for the others.
+  pub fn factors(&self, col1: usize) -> (f64, f64) {
+    if !self.cols.contains(&col1) {
+      return (1.0, 1.0);
+    }
+    let (c1, c2) = self.conventions;
+    (
+      c1.factor_to(self.compared_as),
+      c2.factor_to(self.compared_as),
+    )
+  }
+}
+
+/// A converted value outside its convention's range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutOfRange {
+  /// The (1-based) physical line of the cell.
+  pub line: usize,
+  /// Its (1-based) column.
+  pub col: usize,
+  /// Its value, converted.
+  pub value: f64,
+}
+
+/// The cells of a file's percentage-like (0-based) columns whose values,
+/// multiplied by `factor`, fall outside `convention`'s range. Cells are
+/// taken for floats by `float_re`.
+pub fn out_of_range(
+  records: &[StringRecord],
+  cols: &[usize],
+  (factor, convention): (f64, Convention),
+  float_re: &Regex,
+) -> Vec<OutOfRange> {
+  let mut outside = Vec::new();
+  for (row, record) in records.iter().enumerate() {
+    for &col in cols {
+      let Some(cell) = record.get(col) else {
+        continue;
+      };
+      if !is_float(cell, float_re) {
+        continue;
+      }
+      // is_float checked that it parses
+      let value = parse_float(cell).unwrap() * factor;
+      if !(0.0..=convention.full()).contains(&value) {
+        outside.push(OutOfRange {
+          line: physical_line(records, row),
+          col: col + 1,
+          value,
+        });
+      }
+    }
+  }
+  outside
+}
@@ -0,0 +1,183 @@
+//! Percentiles of the compared cells' relative differences, for judging a
+//! comparison by more than its few worst cells. They're exact up to
+//! [`EXACT_CELLS`] cells; past that, the differences are counted in
+//! logarithmic buckets instead, so that memory stays bounded, and each
+//! percentile is within [`ACCURACY`] of its true value, relatively.
+
+use std::collections::BTreeMap;
+
+/// The most cells whose differences are kept, for exact percentiles.
+pub const EXACT_CELLS: usize = 1 << 20;
+
+/// How far a percentile may be from its true value, as a fraction of it,
+/// once there are more than [`EXACT_CELLS`] cells.
+pub const ACCURACY: f64 = 0.005;
+
+/// The ratio between the bounds of each logarithmic bucket.
+fn gamma() -> f64 {
+  (1.0 + ACCURACY) / (1.0 - ACCURACY)
+}
+
+/// Relative differences counted in logarithmic buckets.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Sketch {
+  /// How many are exactly zero.
+  zeros: usize,
+  /// How many fall in each bucket, by its index: bucket i holds the
+  /// differences above gamma^(i-1), up to gamma^i.
+  buckets: BTreeMap<i32, usize>,
+}
+
+impl Sketch {
+  /// Counts a difference, which must be finite and not negative.
+  fn add(&mut self, percent: f64) {
+    if percent <= 0.0 {
+      self.zeros += 1;
+      return;
+    }
+    let bucket = (percent.ln() / gamma().ln()).ceil() as i32;
+    *self.buckets.entry(bucket).or_default() += 1;
+  }
+
+  /// The number of differences counted.
+  fn total(&self) -> usize {
+    self.zeros + self.buckets.values().sum::<usize>()
+  }
+
+  /// The difference of the given (1-based) rank, as the middle of its
+  /// bucket.
+  fn ranked(&self, rank: usize) -> f64 {
+    let mut below = self.zeros;
+    if rank <= below {
+      return 0.0;
+    }
+    for (&bucket, &count) in &self.buckets {
+      below += count;
+      if rank <= below {
+        return 2.0 * gamma().powi(bucket) / (gamma() + 1.0);
+      }
+    }
+    // ranks are at most the total
+    unreachable!()
+  }
+}
+
+/// The distribution of the compared cells' relative differences, in
+/// percent, and the percentiles of it wanted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Percentiles {
+  /// The percentiles wanted, each above 0 and at most 100.
+  pub at: Vec<f64>,
+  /// The differences counted, while there are at most [`EXACT_CELLS`];
+  /// sorted once the comparison is done.
+  values: Vec<f64>,
+  /// The differences counted once there are more.
+  sketch: Option<Sketch>,
+  /// How many cells weren't compared, both values being zero or below the
+  /// threshold, and so aren't counted.
+  pub skipped: usize,
+  /// How many compared cells have relative differences that are infinite
+  /// or undefined, such as a zero against a non-zero value, which aren't
+  /// counted either.
+  pub undefined: usize,
+}
+
+impl Percentiles {
+  /// An empty distribution, for these percentiles.
+  pub fn new(at: &[f64]) -> Self {
+    Self {
+      at: at.to_vec(),
+      values: Vec::new(),
+      sketch: None,
+      skipped: 0,
+      undefined: 0,
+    }
+  }
+
+  /// Counts a compared cell, by its relative difference in percent.
+  pub fn add(&mut self, percent: f64) {
+    if !percent.is_finite() {
+      self.undefined += 1;
+    } else if let Some(sketch) = &mut self.sketch {
+      sketch.add(percent);
+    } else {
+      self.values.push(percent);
+      if self.values.len() > EXACT_CELLS {
+        self.spill();
+      }
+    }
+  }
+
+  /// Counts a cell that wasn't compared.
+  pub fn skip(&mut self) {
+    self.skipped += 1;
+  }
+
+  /// Counts the cells of a distribution with the same percentiles.
+  pub fn merge(&mut self, other: &Self) {
+    self.skipped += other.skipped;
+    self.undefined += other.undefined;
+    let exact = self.values.len() + other.values.len() <= EXACT_CELLS;
+    if self.sketch.is_none() && other.sketch.is_none() && exact {
+      self.values.extend(&other.values);
+      return;
+    }
+    self.spill();
+    let sketch = self.sketch.as_mut().unwrap();
+    for &percent in &other.values {
+      sketch.add(percent);
+    }
+    if let Some(other) = &other.sketch {
+      sketch.zeros += other.zeros;
+      for (&bucket, &count) in &other.buckets {
+        *sketch.buckets.entry(bucket).or_default() += count;
+      }
+    }
+  }
+
+  /// Moves the differences kept into buckets, unless they already are.
+  fn spill(&mut self) {
+    let sketch = self.sketch.get_or_insert_with(Sketch::default);
+    for percent in std::mem::take(&mut self.values) {
+      sketch.add(percent);
+    }
+  }
+
+  /// Readies the differences kept for [`value`](Self::value), once
+  /// they're all counted.
+  pub(crate) fn finish(&mut self) {
+    self.values.sort_by(f64::total_cmp);
+  }
+
+  /// Whether the percentiles are exact, rather than within [`ACCURACY`].
+  pub fn exact(&self) -> bool {
+    self.sketch.is_none()
+  }
+
+  /// How many cells' differences were counted.
+  pub fn counted(&self) -> usize {
+    match &self.sketch {
+      Some(sketch) => sketch.total(),
+      None => self.values.len(),
+    }
+  }
+
+  /// The relative difference, in percent, that `p` percent of the counted
+  /// cells are at or below (the nearest rank), or `None` if none were.
+  pub fn value(&self, p: f64) -> Option<f64> {
+    let counted = self.counted();
+    if counted == 0 {
+      return None;
+    }
+    let rank = ((p / 100.0 * counted as f64).ceil() as usize).clamp(1, counted);
+    Some(match &self.sketch {
+      Some(sketch) => sketch.ranked(rank),
+      None => self.values[rank - 1],
+    })
+  }
+
+  /// Each percentile wanted, with its value.
+  pub fn values(&self) -> Vec<(f64, Option<f64>)> {
+    self.at.iter().map(|&p| (p, self.value(p))).collect()
+  }
+}
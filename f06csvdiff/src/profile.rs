@@ -0,0 +1,155 @@
+//! Where a comparison's time goes, phase by phase, so that a slow run can be
+//! reported with a breakdown rather than a flamegraph. The phases are timed
+//! at their boundaries, except inside the comparison's hot loop: there, one
+//! row in [`SAMPLE_EVERY`] is timed, split between parsing its floats and
+//! comparing them, and the samples are scaled up to every row.
+
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// One row in this many of the comparison's hot loop is timed.
+pub const SAMPLE_EVERY: usize = 64;
+
+/// A phase of a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+  /// Reading the files' bytes and decoding them.
+  Reading,
+  /// Splitting the text into records and fields.
+  Parsing,
+  /// Finding the columns that hold floats in both files.
+  Classifying,
+  /// Matching and parsing each compared cell's float.
+  FloatParsing,
+  /// Measuring and tallying the differences.
+  Comparing,
+  /// Everything after the comparison, such as checking waivers and
+  /// writing the report.
+  Reporting,
+  /// Everything else, such as taking off headers or sorting rows.
+  Other,
+}
+
+impl Phase {
+  /// Every phase, in the order of a run.
+  pub const ALL: [Phase; 7] = [
+    Phase::Reading,
+    Phase::Parsing,
+    Phase::Classifying,
+    Phase::FloatParsing,
+    Phase::Comparing,
+    Phase::Reporting,
+    Phase::Other,
+  ];
+
+  /// The name of the phase in reports.
+  pub fn name(self) -> &'static str {
+    match self {
+      Phase::Reading => "reading",
+      Phase::Parsing => "parsing",
+      Phase::Classifying => "classifying",
+      Phase::FloatParsing => "float parsing",
+      Phase::Comparing => "comparing",
+      Phase::Reporting => "reporting",
+      Phase::Other => "other",
+    }
+  }
+
+  /// Whether the phase's time is estimated from sampled rows.
+  pub fn sampled(self) -> bool {
+    matches!(self, Phase::FloatParsing | Phase::Comparing)
+  }
+}
+
+/// The time spent in each phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PhaseTimes {
+  times: [Duration; Phase::ALL.len()],
+}
+
+impl PhaseTimes {
+  /// The time spent in a phase.
+  pub fn get(&self, phase: Phase) -> Duration {
+    self.times[phase as usize]
+  }
+
+  /// Adds time spent in a phase.
+  pub fn add(&mut self, phase: Phase, time: Duration) {
+    self.times[phase as usize] += time;
+  }
+
+  /// Adds the time spent in each phase of another run.
+  pub fn merge(&mut self, other: &PhaseTimes) {
+    for phase in Phase::ALL {
+      self.add(phase, other.get(phase));
+    }
+  }
+
+  /// The time spent in every phase.
+  pub fn total(&self) -> Duration {
+    self.times.iter().sum()
+  }
+}
+
+/// The samples of the comparison's hot loop, before they're scaled up.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Samples {
+  /// How many rows the loop went through.
+  pub(crate) rows: usize,
+  /// How many of them were timed.
+  pub(crate) sampled: usize,
+  /// The time the timed rows spent parsing their floats.
+  pub(crate) parsing: Duration,
+  /// The time they spent comparing them.
+  pub(crate) comparing: Duration,
+}
+
+impl Samples {
+  /// Whether a (0-based) row of the loop is timed.
+  pub(crate) fn takes(row: usize) -> bool {
+    row.is_multiple_of(SAMPLE_EVERY)
+  }
+
+  /// Adds the samples' estimates for every row to `times`.
+  pub(crate) fn scale_into(&self, times: &mut PhaseTimes) {
+    if self.sampled == 0 {
+      return;
+    }
+    let scale = self.rows as f64 / self.sampled as f64;
+    times.add(Phase::FloatParsing, self.parsing.mul_f64(scale));
+    times.add(Phase::Comparing, self.comparing.mul_f64(scale));
+  }
+}
+
+/// A reader timing its reads of another.
+pub struct Timed<R> {
+  inner: R,
+  spent: Duration,
+}
+
+impl<R: Read> Timed<R> {
+  /// Times the reads of `inner`.
+  pub fn new(inner: R) -> Self {
+    Self {
+      inner,
+      spent: Duration::ZERO,
+    }
+  }
+
+  /// The time spent reading so far.
+  pub fn spent(&self) -> Duration {
+    self.spent
+  }
+}
+
+impl<R: Read> Read for Timed<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let start = Instant::now();
+    let read = self.inner.read(buf);
+    self.spent += start.elapsed();
+    read
+  }
+}
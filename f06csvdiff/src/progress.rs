@@ -0,0 +1,52 @@
+//! Counters of how far reading and comparing have got, for a progress bar
+//! on long comparisons. They're advanced as the work goes, and can be read
+//! from another thread at any time.
+
+use std::io::{self, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How many bytes have been read and how many rows compared, so far.
+#[derive(Debug, Default)]
+pub struct Progress {
+  bytes: AtomicU64,
+  rows: AtomicU64,
+}
+
+impl Progress {
+  /// The bytes read so far, over every file.
+  pub fn bytes(&self) -> u64 {
+    self.bytes.load(Ordering::Relaxed)
+  }
+
+  /// The rows compared so far, over every comparison.
+  pub fn rows(&self) -> u64 {
+    self.rows.load(Ordering::Relaxed)
+  }
+
+  /// Counts rows compared.
+  pub(crate) fn add_rows(&self, rows: usize) {
+    self.rows.fetch_add(rows as u64, Ordering::Relaxed);
+  }
+}
+
+/// A reader counting the bytes read through it into a [`Progress`].
+pub(crate) struct Counted<R> {
+  inner: R,
+  progress: Arc<Progress>,
+}
+
+impl<R> Counted<R> {
+  /// Counts what's read from `inner` into `progress`.
+  pub(crate) fn new(inner: R, progress: Arc<Progress>) -> Self {
+    Self { inner, progress }
+  }
+}
+
+impl<R: Read> Read for Counted<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let n = self.inner.read(buf)?;
+    self.progress.bytes.fetch_add(n as u64, Ordering::Relaxed);
+    Ok(n)
+  }
+}
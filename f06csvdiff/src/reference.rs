@@ -0,0 +1,869 @@
+//! A deliberately simple, fully-materialized implementation of the comparison.
+//!
+//! This is the executable specification for `compare::compare`: it trades
+//! speed and memory for obviousness, and is used by `--verify` and the tests
+//! to check that the production path gives identical verdicts. It assumes the
+//! structural checks (row and column counts) already passed, so extra rows
+//! are simply left out, as are rows with structural problems if those are
+//! lenient.
+
+use csv::{Position, StringRecord};
+use regex::Regex;
+
+use crate::compare::{
+  ColumnStats, DemotedColumn, DiffConfig, DiffReport, Directed, Directions,
+  Extremum, FailingCells, IgnoredRows, MAX_FLOAT_LEN, RelMode, ScaledDiff,
+  Skip, Structure, StructureIssue, Sum, ThresholdMode, Ties, ZeroMismatches,
+  physical_line,
+};
+use crate::error::DiffError;
+use crate::histogram::Histogram;
+use crate::impact::ImpactReport;
+use crate::percentiles::Percentiles;
+
+/// Parses every cell of a table, yielding `None` for cells that aren't floats.
+fn materialize(
+  records: &[StringRecord],
+  float_re: &Regex,
+) -> Vec<Vec<Option<f64>>> {
+  records
+    .iter()
+    .map(|rec| {
+      rec
+        .iter()
+        .map(|cell| {
+          if looks_like_float(cell, float_re) {
+            parse_float(cell)
+          } else {
+            None
+          }
+        })
+        .collect()
+    })
+    .collect()
+}
+
+/// Whether a cell is written like a float: no longer than a float can be,
+/// and matching the float regex somewhere.
+fn looks_like_float(cell: &str, float_re: &Regex) -> bool {
+  cell.len() <= MAX_FLOAT_LEN && float_re.find(cell).is_some()
+}
+
+/// Splits a float's text into its mantissa and its exponent, if it has one:
+/// after the first E or D, or else from the last sign past the first
+/// character, as Nastran writes exponents.
+fn split_exponent(cell: &str) -> (&str, Option<&str>) {
+  for (i, c) in cell.char_indices() {
+    if matches!(c, 'E' | 'e' | 'D' | 'd') {
+      return (&cell[..i], Some(&cell[i + 1..]));
+    }
+  }
+  for (i, c) in cell.char_indices().rev() {
+    if i > 0 && matches!(c, '+' | '-') {
+      return (&cell[..i], Some(&cell[i..]));
+    }
+  }
+  (cell, None)
+}
+
+/// A float cell's value, as Rust reads it, or else with its exponent
+/// rewritten after an E.
+fn parse_float(cell: &str) -> Option<f64> {
+  if let Ok(value) = cell.parse() {
+    return Some(value);
+  }
+  match split_exponent(cell) {
+    (mantissa, Some(exponent)) => format!("{mantissa}E{exponent}").parse().ok(),
+    (_, None) => None,
+  }
+}
+
+/// How many significant digits a float's mantissa has, leading zeros not
+/// counting; none for a zero.
+fn printed_digits(cell: &str) -> Option<usize> {
+  let (mantissa, _) = split_exponent(cell);
+  let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+  let significant = digits.trim_start_matches('0').len();
+  (significant > 0).then_some(significant)
+}
+
+/// The segment a (0-based) row falls in: segment s takes the rows r with
+/// s <= r * segments / rows < s + 1.
+fn segment_of(row: usize, rows: usize, segments: usize) -> usize {
+  (1..segments)
+    .take_while(|&s| s * rows <= row * segments)
+    .count()
+}
+
+/// The rows at the end of the longer table, if one is longer.
+fn extra_rows(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+) -> Option<IgnoredRows> {
+  let (len1, len2) = (records1.len(), records2.len());
+  if len1 > len2 {
+    let line = physical_line(records1, len2);
+    Some(IgnoredRows {
+      file: 1,
+      count: len1 - len2,
+      line,
+    })
+  } else if len2 > len1 {
+    let line = physical_line(records2, len1);
+    Some(IgnoredRows {
+      file: 2,
+      count: len2 - len1,
+      line,
+    })
+  } else {
+    None
+  }
+}
+
+/// The structural problem of a (0-based) row of two tables, if it has one,
+/// checked in the order the comparison reports them.
+fn structure_problem(
+  row: usize,
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+) -> Option<DiffError> {
+  for (file, records) in [("csv1", records1), ("csv2", records2)] {
+    if records[row].len() != records[0].len() {
+      return Some(DiffError::FieldCountChange {
+        file: file.to_string(),
+        line: physical_line(records, row),
+        fields: records[row].len(),
+        expected: records[0].len(),
+      });
+    }
+  }
+  let (rec1, rec2) = (&records1[row], &records2[row]);
+  let line = physical_line(records1, row);
+  match &opts.column_map {
+    None if rec1.len() != rec2.len() => {
+      return Some(DiffError::ColumnCountMismatch {
+        line,
+        cols1: rec1.len(),
+        cols2: rec2.len(),
+      });
+    }
+    None => {}
+    Some(map) => {
+      for (i, j) in map.pairs() {
+        if i >= rec1.len() {
+          return Some(DiffError::MappedColumnMissing {
+            file: "csv1".to_string(),
+            line,
+            col: i + 1,
+          });
+        }
+        if j >= rec2.len() {
+          return Some(DiffError::MappedColumnMissing {
+            file: "csv2".to_string(),
+            line: physical_line(records2, row),
+            col: j + 1,
+          });
+        }
+      }
+    }
+  }
+  if opts.strict_layout {
+    let is_float = |cell: &str| {
+      looks_like_float(cell, &opts.float_re) && parse_float(cell).is_some()
+    };
+    for (i, cell1) in rec1.iter().enumerate() {
+      let Some(cell2) = opts.col2(i).and_then(|j| rec2.get(j)) else {
+        continue;
+      };
+      if is_float(cell1) != is_float(cell2) {
+        return Some(DiffError::FloatLayoutMismatch {
+          line,
+          col: i + 1,
+          cell1: cell1.to_string(),
+          cell2: cell2.to_string(),
+        });
+      }
+    }
+  }
+  None
+}
+
+/// The rows of two tables as long as each other without structural
+/// problems, each carrying its physical line, and the others' problems.
+fn sound_rows(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+) -> (Vec<StringRecord>, Vec<StringRecord>, Vec<StructureIssue>) {
+  let located = |records: &[StringRecord], row: usize| {
+    let mut position = Position::new();
+    position.set_line(physical_line(records, row) as u64);
+    let mut record = records[row].clone();
+    record.set_position(Some(position));
+    record
+  };
+  let (mut sound1, mut sound2, mut issues) =
+    (Vec::new(), Vec::new(), Vec::new());
+  for row in 0..records1.len() {
+    match structure_problem(row, records1, records2, opts) {
+      Some(problem) => issues.push(StructureIssue {
+        line: physical_line(records1, row),
+        problem: problem.to_string(),
+      }),
+      None => {
+        sound1.push(located(records1, row));
+        sound2.push(located(records2, row));
+      }
+    }
+  }
+  (sound1, sound2, issues)
+}
+
+/// Compares two tables the straightforward way.
+pub fn compare(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+) -> DiffReport {
+  let threshold = opts.threshold;
+  let ignored_rows = extra_rows(records1, records2);
+  let rows = records1.len().min(records2.len());
+  let (records1, records2) = (&records1[..rows], &records2[..rows]);
+  let (sound1, sound2, structure_issues) = match opts.structure {
+    Structure::Strict => (records1.to_vec(), records2.to_vec(), Vec::new()),
+    _ => sound_rows(records1, records2, opts),
+  };
+  let rows = sound1.len();
+  let records1 = &sound1[..];
+  // csv2 is laid out as csv1, each column holding the one compared with
+  // csv1's, or nothing
+  let records2: Vec<StringRecord> = sound2
+    .iter()
+    .zip(records1)
+    .map(|(rec2, rec1)| {
+      (0..rec1.len())
+        .map(|c| opts.col2(c).and_then(|j| rec2.get(j)).unwrap_or(""))
+        .collect()
+    })
+    .collect();
+  let records2 = &records2[..];
+  let raw1 = materialize(records1, &opts.float_re);
+  let raw2 = materialize(records2, &opts.float_re);
+  // a normalized column is divided by its file's norm instead of scaled,
+  // unless that's zero
+  let factor = |c: usize, file: usize| {
+    let scale = if file == 1 {
+      opts.scale.0
+    } else {
+      opts.scale.1
+    };
+    let Some(normalize) = &opts.normalize else {
+      let percent = opts.percent.as_ref().map_or(1.0, |percent| {
+        let (p1, p2) = percent.factors(c);
+        if file == 1 { p1 } else { p2 }
+      });
+      return scale * percent;
+    };
+    let col = if file == 1 { Some(c) } else { opts.col2(c) };
+    match col.map(|col| normalize.norm(file, col)) {
+      Some(norm) if norm > 0.0 => 1.0 / norm,
+      _ => scale,
+    }
+  };
+  // subnormals are zero, and values scaled or normalized, before anything
+  // else looks at them; csv2's columns are already aligned with csv1's
+  let scaled = |table: &[Vec<Option<f64>>], file: usize| {
+    table
+      .iter()
+      .map(|row| {
+        row
+          .iter()
+          .enumerate()
+          .map(|(c, v)| v.map(|v| opts.clamp(v) * factor(c, file)))
+          .collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>()
+  };
+  let table1 = scaled(&raw1, 1);
+  let table2 = scaled(&raw2, 2);
+
+  // a column is compared iff every one of its cells is a float in both files
+  let ncols = table1.first().map(|r| r.len()).unwrap_or(0);
+  let float_cols: Vec<bool> = (0..ncols)
+    .map(|c| {
+      table1
+        .iter()
+        .zip(&table2)
+        .all(|(r1, r2)| r1[c].is_some() && r2[c].is_some())
+    })
+    .collect();
+  // each column paired with one of csv2 but not compared is so for its
+  // first cell that isn't a float, csv1's if both aren't
+  let demoted_columns = (0..ncols)
+    .filter(|&c| !float_cols[c] && opts.col2(c).is_some())
+    .filter_map(|c| {
+      let row = (0..rows)
+        .find(|&row| raw1[row][c].is_none() || raw2[row][c].is_none())?;
+      let (file, cell) = match raw1[row][c] {
+        None => (1, &records1[row][c]),
+        Some(_) => (2, &records2[row][c]),
+      };
+      Some(DemotedColumn {
+        col: c + 1,
+        line: physical_line(records1, row),
+        floats_before: row,
+        file,
+        cell: cell.to_string(),
+      })
+    })
+    .collect();
+
+  let no_cell = |value: f64| Extremum {
+    value,
+    vals: (0.0, 0.0),
+    line: 0,
+    col: 0,
+  };
+  let identical = if opts.rel_mode == RelMode::Ratio {
+    1.0
+  } else {
+    0.0
+  };
+  let directed = Directed {
+    max_ratio: no_cell(identical),
+    max_diff: no_cell(0.0),
+  };
+  // a worse cell wins, or an equally bad one earlier in (line, column)
+  // order, whatever order the rows came in
+  let worse = |value: f64, at: (usize, usize), worst: &Extremum| {
+    value > worst.value
+      || worst.line != 0 && value == worst.value && at < (worst.line, worst.col)
+  };
+  // every cell that reached the report's maxima, by its ratio and its
+  // absolute difference, for the ties
+  let mut ratios = Vec::new();
+  let mut diffs = Vec::new();
+  let mut report = DiffReport {
+    rows,
+    ignored_rows,
+    structure: opts.structure,
+    structure_issues,
+    rel_mode: opts.rel_mode,
+    rel_floor: opts.rel_floor,
+    sum_check: opts.sum_check,
+    max_ratio: no_cell(identical),
+    scaled_diff: opts.diff_scale.map(|_| ScaledDiff {
+      worst: no_cell(0.0),
+      diff: 0.0,
+      scale: 1.0,
+    }),
+    directions: Directions {
+      over: directed,
+      under: directed,
+    },
+    histogram: opts.histogram.as_deref().map(Histogram::new),
+    percentiles: opts.percentiles.as_deref().map(Percentiles::new),
+    impact: (!opts.significance.is_empty())
+      .then(|| ImpactReport::new(opts.rel_mode)),
+    demoted_columns,
+    zero_mismatches: ZeroMismatches {
+      cells: 0,
+      worst: no_cell(0.0),
+      limit: opts.max_zero_mismatch,
+      failing: 0,
+    },
+    ..Default::default()
+  };
+
+  // a cell zero in exactly one file has no relative difference when such
+  // cells are held to a magnitude instead
+  let relative = |a: f64, b: f64| {
+    opts.max_zero_mismatch.is_none() || (a == 0.0) == (b == 0.0)
+  };
+
+  // flip a column when negating csv2 lowers its sum of absolute differences
+  let sum_of = |c: usize, sign: f64| -> f64 {
+    table1
+      .iter()
+      .zip(&table2)
+      .map(|(r1, r2)| (r1[c].unwrap() - sign * r2[c].unwrap()).abs())
+      .sum()
+  };
+  let sign: Vec<f64> = (0..ncols)
+    .map(|c| {
+      let flip = float_cols[c]
+        && opts.sign_flip.allows(c)
+        && sum_of(c, -1.0) < sum_of(c, 1.0);
+      if flip {
+        report.flipped_columns.push(c);
+        -1.0
+      } else {
+        1.0
+      }
+    })
+    .collect();
+
+  // a cell is skipped if both values are below either threshold
+  let cutoffs: Vec<f64> = (0..ncols)
+    .map(|c| {
+      if !float_cols[c] {
+        return threshold;
+      }
+      let magnitude = table1
+        .iter()
+        .chain(&table2)
+        .map(|r| r[c].unwrap().abs())
+        .fold(0.0, f64::max);
+      threshold.max(opts.threshold_frac * magnitude)
+    })
+    .collect();
+  // a cell listed as excluded is left out altogether
+  let excluded = |row: usize, c: usize| {
+    let line = physical_line(records1, row);
+    opts.excluded_cells.contains(&(line, c))
+  };
+  // why a cell is skipped, if it is: zeros always, and values below the
+  // cutoff as the threshold mode has it
+  let skip = |a: f64, b: f64, c: usize| {
+    let (below1, below2) = (a.abs() < cutoffs[c], b.abs() < cutoffs[c]);
+    let skipped = match opts.threshold_mode {
+      ThresholdMode::Both => below1 && below2,
+      ThresholdMode::Either => below1 || below2,
+      ThresholdMode::Baseline => below1,
+    };
+    if a == 0.0 && b == 0.0 {
+      Some(Skip::Zeros)
+    } else if !skipped {
+      None
+    } else if below1 && below2 {
+      Some(Skip::BothBelow)
+    } else if below1 {
+      Some(Skip::Below1)
+    } else {
+      Some(Skip::Below2)
+    }
+  };
+  let is_skipped = |a: f64, b: f64, c: usize| skip(a, b, c).is_some();
+
+  // in interval mode, each value stands for the interval of its last printed
+  // digit, and a cell is measured between its intervals' nearest points
+  let half_unit = |text: &str| {
+    let (mantissa, exponent) = split_exponent(text);
+    let decimals = mantissa.find('.').map_or(0, |dot| mantissa.len() - dot - 1);
+    let exponent = exponent.and_then(|x| x.parse::<i32>().ok());
+    10f64.powi(exponent.unwrap_or(0) - decimals as i32) / 2.0
+  };
+  // a column compared by magnitude is measured on the values' magnitudes
+  let measured = |row: usize, c: usize, a: f64, b: f64| {
+    let (a, b) = match opts.abs_compare.applies(c) {
+      true => (a.abs(), b.abs()),
+      false => (a, b),
+    };
+    if !opts.interval {
+      return (a, b);
+    }
+    let h1 = half_unit(&records1[row][c]) * factor(c, 1);
+    let h2 = half_unit(&records2[row][c]) * factor(c, 2);
+    if (a - b).abs() - (h1 + h2) <= 0.0 {
+      let larger = if a.abs() >= b.abs() { a } else { b };
+      (larger, larger)
+    } else if a < b {
+      (a + h1, b - h2)
+    } else {
+      (a - h1, b + h2)
+    }
+  };
+
+  // a cell is low-impact if both its values, as measured, are below its
+  // column's significance
+  let low_impact = |c: usize, a: f64, b: f64| {
+    let significance = opts.significance.get(&c);
+    significance.is_some_and(|&s| a.abs() < s && b.abs() < s)
+  };
+
+  // a row's absolute difference tolerance is scaled by csv1's magnitude in
+  // the scaling column, if it has a number there
+  let row_scale = |row: usize| {
+    opts
+      .diff_scale
+      .map(|_| opts.row_scale(&records1[row]).unwrap_or(1.0))
+  };
+
+  // a cell is an over-prediction if csv2's value, as measured, is above
+  // csv1's, and is held to the tolerances of its direction, which default to
+  // the symmetric ones, the absolute difference one scaled by its row's scale
+  let limits = |row: usize, a: f64, b: f64| {
+    let tolerances = &opts.tolerances;
+    let (ratio, diff) = if b > a {
+      (tolerances.max_ratio_over, tolerances.max_diff_over)
+    } else {
+      (tolerances.max_ratio_under, tolerances.max_diff_under)
+    };
+    (
+      tolerances.max_ratio.map(|mr| ratio.unwrap_or(mr)),
+      tolerances
+        .max_diff
+        .map(|md| diff.unwrap_or(md) * row_scale(row).unwrap_or(1.0)),
+    )
+  };
+
+  // the tolerances a compared cell would fail on its own
+  let failing_alone = |row: usize, c: usize| {
+    let (a, b) = (table1[row][c].unwrap(), sign[c] * table2[row][c].unwrap());
+    if excluded(row, c) || is_skipped(a, b, c) {
+      return FailingCells::default();
+    }
+    let (a, b) = measured(row, c, a, b);
+    let tolerances = &opts.tolerances;
+    let rel = (a - b).abs() / a.abs().max(b.abs());
+    let precision = [&records1[row][c], &records2[row][c]]
+      .into_iter()
+      .filter_map(printed_digits)
+      .min()
+      .unwrap_or(0) as f64;
+    let digits = if rel == 0.0 {
+      precision
+    } else {
+      (-rel.log10()).clamp(0.0, precision)
+    };
+    let percent =
+      opts
+        .rel_mode
+        .percent(opts.rel_mode.measure(a, b, opts.rel_floor));
+    let (max_ratio, max_diff) = limits(row, a, b);
+    let ratio =
+      relative(a, b) && max_ratio.is_some_and(|mr| percent > mr * 100.0);
+    let diff = max_diff.is_some_and(|md| (a - b).abs() > md);
+    let digits = tolerances.min_digits.is_some_and(|min| digits < min);
+    let allclose = tolerances
+      .allclose_limit(b)
+      .is_some_and(|allowed| (a - b).abs() > allowed);
+    let count = |failed: bool| failed as usize;
+    FailingCells {
+      ratio: count(ratio),
+      diff: count(diff),
+      digits: count(digits),
+      allclose: count(allclose),
+      cells: count(ratio || diff || digits || allclose),
+    }
+  };
+
+  // only high-impact cells count when they alone are judged
+  let judged = |row: usize, c: usize| {
+    let (a, b) = (table1[row][c].unwrap(), sign[c] * table2[row][c].unwrap());
+    let (a, b) = measured(row, c, a, b);
+    !(opts.high_impact_only && low_impact(c, a, b))
+  };
+
+  // with fail_fast, only the rows up to the one where the count of judged
+  // cells failing a tolerance on their own reaches the limit are compared
+  let mut failing = 0;
+  let last = opts.fail_fast.and_then(|limit| {
+    (0..rows).find(|&row| {
+      failing += (0..ncols)
+        .filter(|&c| float_cols[c] && failing_alone(row, c).any())
+        .filter(|&c| judged(row, c))
+        .count();
+      failing >= limit
+    })
+  });
+  let scanned = match last {
+    Some(row) if row + 1 < rows => {
+      report.rows = row + 1;
+      report.aborted_at = Some(physical_line(records1, row));
+      row + 1
+    }
+    _ => rows,
+  };
+  let clamps = |table: &[Vec<Option<f64>>]| {
+    table[..scanned]
+      .iter()
+      .flat_map(|row| (0..ncols).filter(|&c| float_cols[c]).map(|c| row[c]))
+      .filter(|v| v.is_some_and(|v| opts.clamps(v)))
+      .count()
+  };
+  report.clamped = (clamps(&raw1), clamps(&raw2));
+  if opts.diff_scale.is_some() {
+    report.unscaled_rows = (0..scanned)
+      .filter(|&row| opts.row_scale(&records1[row]).is_none())
+      .count();
+  }
+
+  // per-column statistics, gathered column by column
+  for c in (0..ncols).filter(|&c| float_cols[c]) {
+    let mut cells = Vec::new();
+    // the sums are of the compared cells' values, signed
+    let mut sums = (Sum::default(), Sum::default());
+    for row in 0..scanned {
+      let (a, b) = (table1[row][c].unwrap(), sign[c] * table2[row][c].unwrap());
+      if !excluded(row, c) && !is_skipped(a, b, c) {
+        sums.0.add(a);
+        sums.1.add(b);
+        let (a, b) = measured(row, c, a, b);
+        let digits = [
+          printed_digits(&records1[row][c]),
+          printed_digits(&records2[row][c]),
+        ];
+        cells.push((
+          a,
+          b,
+          digits,
+          segment_of(row, table1.len(), opts.segments),
+        ));
+      }
+    }
+    let rel = |(a, b): (f64, f64)| (a - b).abs() / a.abs().max(b.abs());
+    let worst_rel = cells
+      .iter()
+      .map(|&(a, b, ..)| rel((a, b)))
+      .fold(0.0, f64::max);
+    let worst_diff = cells
+      .iter()
+      .map(|&(a, b, ..)| (a - b).abs())
+      .fold(0.0, f64::max);
+    let precision = cells.iter().flat_map(|(_, _, d, _)| d).flatten().min();
+    let segments = (0..opts.segments)
+      .map(|s| {
+        cells
+          .iter()
+          .filter(|&&(.., segment)| segment == s)
+          .map(|&(a, b, ..)| rel((a, b)))
+          .reduce(f64::max)
+      })
+      .collect();
+    report.columns.push(ColumnStats {
+      col: c + 1,
+      compared: cells.len(),
+      worst_rel,
+      worst_diff,
+      // raised below, with the report's
+      max_ratio: report.max_ratio,
+      max_diff: report.max_diff,
+      allclose: report.allclose,
+      directions: report.directions,
+      scaled_diff: None,
+      precision: precision.copied(),
+      segments,
+      cutoff: cutoffs[c],
+      sums: opts.sum_check.enabled().then_some(sums),
+    });
+  }
+
+  for (row, (r1, r2)) in table1.iter().zip(&table2).enumerate().take(scanned) {
+    for c in (0..ncols).filter(|&c| float_cols[c]) {
+      let (a, b) = (r1[c].unwrap(), sign[c] * r2[c].unwrap());
+      if excluded(row, c) {
+        report
+          .excluded
+          .insert((physical_line(records1, row), c + 1));
+        continue;
+      }
+      if let Some(skip) = skip(a, b, c) {
+        report.skipped.add(skip);
+        if let Some(percentiles) = &mut report.percentiles {
+          percentiles.skip();
+        }
+        continue;
+      }
+      // the worst cells are shown signed, even compared by magnitude
+      let abs = opts.abs_compare.applies(c);
+      if abs && a != 0.0 && b != 0.0 && a.signum() != b.signum() {
+        report.sign_differences += 1;
+      }
+      let signed = (a, b);
+      let (a, b) = measured(row, c, a, b);
+      let shown = if abs { signed } else { (a, b) };
+      let line = physical_line(records1, row);
+      let failing = failing_alone(row, c);
+      let diff = (a - b).abs();
+      // every denominator is at least the floor
+      let floor = opts.rel_floor;
+      let max = a.abs().max(b.abs()).max(floor);
+      let rel = diff / max;
+      let ratio = match opts.rel_mode {
+        _ if !relative(a, b) => identical,
+        RelMode::Ratio => max / a.abs().min(b.abs()).max(floor),
+        RelMode::Rel1 => diff / a.abs().max(floor),
+        RelMode::Rel2 => diff / b.abs().max(floor),
+        RelMode::Symmetric => rel,
+      };
+      // the difference as a multiple of what the combined tolerance allows
+      let closeness = opts
+        .tolerances
+        .allclose_limit(b)
+        .map(|allowed| if diff == 0.0 { 0.0 } else { diff / allowed });
+      if let Some(histogram) = &mut report.histogram {
+        histogram.add(opts.rel_mode.percent(ratio));
+      }
+      if let Some(percentiles) = &mut report.percentiles {
+        percentiles.add(opts.rel_mode.percent(ratio));
+      }
+      // strict comparisons: the first worst cell wins, in the file, in its
+      // column, in its direction and in its impact class
+      let column = report.columns.iter_mut().find(|s| s.col == c + 1).unwrap();
+      let directed = if b > a {
+        &mut column.directions.over
+      } else {
+        &mut column.directions.under
+      };
+      let allclose = closeness.map(|value| (&mut column.allclose, value));
+      for (worst, value) in [
+        (&mut column.max_diff, diff),
+        (&mut column.max_ratio, ratio),
+        (&mut directed.max_diff, diff),
+        (&mut directed.max_ratio, ratio),
+      ]
+      .into_iter()
+      .chain(allclose)
+      {
+        if worse(value, (line, c + 1), worst) {
+          *worst = Extremum {
+            value,
+            vals: shown,
+            line,
+            col: c + 1,
+          };
+        }
+      }
+      // the worst cell for its row's scale is the nearest its own tolerance
+      let worst_scaled = |scaled: &mut Option<ScaledDiff>, scale: f64| {
+        if let Some(scaled) = scaled
+          && worse(diff / scale, (line, c + 1), &scaled.worst)
+        {
+          *scaled = ScaledDiff {
+            worst: Extremum {
+              value: diff / scale,
+              vals: shown,
+              line,
+              col: c + 1,
+            },
+            diff,
+            scale,
+          };
+        }
+      };
+      if let Some(scale) = row_scale(row) {
+        column.scaled_diff.get_or_insert(ScaledDiff {
+          worst: no_cell(0.0),
+          diff: 0.0,
+          scale: 1.0,
+        });
+        worst_scaled(&mut column.scaled_diff, scale);
+      }
+      if let Some(impact) = &mut report.impact {
+        let low = low_impact(c, a, b);
+        let class = if low {
+          &mut impact.low
+        } else {
+          &mut impact.high
+        };
+        class.add((ratio, diff), shown, (line, c + 1), failing);
+        if low && opts.high_impact_only {
+          continue;
+        }
+      }
+      report.failing_cells.add(failing);
+      if let Some(scale) = row_scale(row) {
+        worst_scaled(&mut report.scaled_diff, scale);
+      }
+      let (max_ratio, max_diff) = limits(row, a, b);
+      if let Some(md) = max_diff {
+        report.diff_mass.add(line, c + 1, diff - md);
+      }
+      // masses are in relative difference, whatever the mode: the excess is
+      // over the difference the tolerance allows, divided by max(|a|,|b|)
+      // (before multiplying, which underflows for subnormal values)
+      if let Some(mr) = max_ratio.filter(|_| relative(a, b)) {
+        let allowed_rel = match opts.rel_mode {
+          // a ratio of 1+r is a relative difference of r/(1+r)
+          RelMode::Ratio => mr / (1.0 + mr),
+          RelMode::Rel1 => mr * (a.abs().max(floor) / max),
+          RelMode::Rel2 => mr * (b.abs().max(floor) / max),
+          RelMode::Symmetric => mr,
+        };
+        report.ratio_mass.add(line, c + 1, rel - allowed_rel);
+      }
+      ratios.push((ratio, (line, c + 1)));
+      diffs.push((diff, (line, c + 1)));
+      // the largest nonzero value against a zero, as measured
+      let zeros = &mut report.zero_mismatches;
+      if (a == 0.0) != (b == 0.0) {
+        let magnitude = a.abs().max(b.abs());
+        zeros.cells += 1;
+        if opts.max_zero_mismatch.is_some_and(|mag| magnitude >= mag) {
+          zeros.failing += 1;
+        }
+        if worse(magnitude, (line, c + 1), &zeros.worst) {
+          zeros.worst = Extremum {
+            value: magnitude,
+            vals: shown,
+            line,
+            col: c + 1,
+          };
+        }
+      }
+      let directed = if b > a {
+        &mut report.directions.over
+      } else {
+        &mut report.directions.under
+      };
+      let allclose = closeness.map(|value| (&mut report.allclose, value));
+      for (worst, value) in [
+        (&mut report.max_diff, diff),
+        (&mut report.max_ratio, ratio),
+        (&mut directed.max_diff, diff),
+        (&mut directed.max_ratio, ratio),
+      ]
+      .into_iter()
+      .chain(allclose)
+      {
+        if worse(value, (line, c + 1), worst) {
+          *worst = Extremum {
+            value,
+            vals: shown,
+            line,
+            col: c + 1,
+          };
+        }
+      }
+    }
+  }
+  // a cell ties with a worst cell as bad, or a ULP better, if it's worse
+  // than the extremum started at
+  let ties =
+    |cells: &mut Vec<(f64, (usize, usize))>, worst: &Extremum, start: f64| {
+      cells.sort_by_key(|&(_, cell)| cell);
+      let cap = opts.listed_ties;
+      let at = |value: f64| -> Vec<(usize, usize)> {
+        match worst.line {
+          0 => Vec::new(),
+          _ => cells
+            .iter()
+            .filter(|&&(v, _)| v == value && v > start)
+            .map(|&(_, cell)| cell)
+            .collect(),
+        }
+      };
+      let (at_cells, below_cells) =
+        (at(worst.value), at(worst.value.next_down()));
+      Ties {
+        start,
+        value: worst.value,
+        at: at_cells.len(),
+        below: below_cells.len(),
+        at_cells: at_cells.into_iter().take(cap + 1).collect(),
+        below_cells: below_cells.into_iter().take(cap + 1).collect(),
+        cap,
+      }
+    };
+  report.ratio_ties = ties(&mut ratios, &report.max_ratio, identical);
+  report.diff_ties = ties(&mut diffs, &report.max_diff, 0.0);
+  if let Some(percentiles) = &mut report.percentiles {
+    percentiles.finish();
+  }
+  report.gates = opts.tolerances.evaluate(&report);
+  report.sums = opts.tolerances.check_sums(&report);
+  report.warnings = opts.warn_tolerances.evaluate(&report);
+  report
+}
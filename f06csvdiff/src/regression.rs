@@ -0,0 +1,70 @@
+//! The regression gate, for qualifying a new version of a solver: it passes
+//! if the new version is no further from a reference than an already
+//! qualified old version was, give or take a relative margin.
+
+use crate::compare::DiffReport;
+
+/// The acceptance rule.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RegressionGate {
+  /// How much further from the reference the new version may be, relative
+  /// to the old version's difference (0.05 is 5%).
+  pub margin: f64,
+  /// The difference always allowed, in the column's own units. Without it,
+  /// a column the old version matched exactly fails on any difference.
+  pub floor: f64,
+}
+
+/// The regression gate's verdict on one float column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColumnRegression {
+  /// The (1-based) column.
+  pub col: usize,
+  /// The worst absolute difference between the new version and the
+  /// reference.
+  pub new_diff: f64,
+  /// The worst absolute difference between the old version and the
+  /// reference.
+  pub old_diff: f64,
+  /// The largest `new_diff` that passes.
+  pub allowed: f64,
+  /// Whether the column passed.
+  pub passed: bool,
+}
+
+impl RegressionGate {
+  /// The largest new difference allowed, given the old one.
+  pub fn allowed(&self, old_diff: f64) -> f64 {
+    (old_diff * (1.0 + self.margin)).max(self.floor)
+  }
+
+  /// Judges each float column of `new` (the reference against the new
+  /// version) by the same column of `old` (the reference against the old
+  /// version). Columns that weren't compared in `old` count as matched
+  /// exactly, leaving only the floor.
+  pub fn evaluate(
+    &self,
+    new: &DiffReport,
+    old: &DiffReport,
+  ) -> Vec<ColumnRegression> {
+    new
+      .columns
+      .iter()
+      .map(|column| {
+        let old_diff = old
+          .columns
+          .iter()
+          .find(|c| c.col == column.col)
+          .map_or(0.0, |c| c.worst_diff);
+        let allowed = self.allowed(old_diff);
+        ColumnRegression {
+          col: column.col,
+          new_diff: column.worst_diff,
+          old_diff,
+          allowed,
+          passed: column.worst_diff <= allowed,
+        }
+      })
+      .collect()
+  }
+}
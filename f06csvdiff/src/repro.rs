@@ -0,0 +1,232 @@
+//! Reproduction commands: the command line a run was given, narrowed to a
+//! single pair of files and to the scope of its worst violation, quoted for
+//! pasting into a shell.
+
+use clap::Arg;
+
+use f06csvdiff::verdict::Tolerances;
+
+/// The options a reproduction leaves out: those writing files or reports
+/// other than the plain one, and the manifest, since it names the pair
+/// instead.
+const DROPPED: &[&str] = &[
+  "manifest",
+  "triage",
+  "format",
+  "output",
+  "append",
+  "sqlite",
+  "badge",
+  "badge_json",
+  "save_report",
+  "write_diff_csv",
+  "gha",
+  "progress",
+  "profile_phases",
+];
+
+/// The options narrowing the scope, each with the option of a
+/// reproduction's scope that replaces it: a run's --rows and --stride are
+/// kept unless the reproduction narrows the rows itself, and so on.
+const NARROWING: &[(&str, &str)] = &[
+  ("rows", "--rows"),
+  ("stride", "--rows"),
+  ("columns", "--columns"),
+  ("explain_cell", "--explain-cell"),
+];
+
+/// The options that set the tolerances, which a manifest's override.
+const TOLERANCES: &[&str] = &[
+  "max_ratio",
+  "max_percent",
+  "max_ratio_over",
+  "max_ratio_under",
+  "max_diff",
+  "max_diff_over",
+  "max_diff_under",
+  "min_digits",
+  "max_fail_fraction",
+  "allclose",
+];
+
+/// The shell a reproduction command is quoted for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Shell {
+  /// sh, bash, zsh and the like.
+  Posix,
+  /// PowerShell.
+  PowerShell,
+}
+
+impl std::str::FromStr for Shell {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "posix" => Ok(Shell::Posix),
+      "powershell" => Ok(Shell::PowerShell),
+      _ => Err(format!("Invalid shell: {s}. Must be posix or powershell")),
+    }
+  }
+}
+
+impl Shell {
+  /// An argument, quoted if the shell would otherwise split or expand it.
+  pub(crate) fn quote(self, arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-+=.,:/@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+      return arg.to_string();
+    }
+    match self {
+      Shell::Posix => format!("'{}'", arg.replace('\'', r"'\''")),
+      Shell::PowerShell => format!("'{}'", arg.replace('\'', "''")),
+    }
+  }
+
+  /// A command line: the program, then its arguments, each quoted.
+  /// PowerShell only runs a quoted program with the call operator.
+  pub(crate) fn command(self, program: &str, args: &[String]) -> String {
+    let mut command = self.quote(program);
+    if self == Shell::PowerShell && command.starts_with('\'') {
+      command.insert_str(0, "& ");
+    }
+    for arg in args {
+      command.push(' ');
+      command.push_str(&self.quote(arg));
+    }
+    command
+  }
+}
+
+/// The option of `cli` with this long name.
+fn long<'a>(cli: &'a clap::Command, name: &str) -> Option<&'a Arg> {
+  cli.get_arguments().find(|arg| arg.get_long() == Some(name))
+}
+
+/// The option of `cli` with this short name.
+fn short(cli: &clap::Command, name: char) -> Option<&Arg> {
+  cli
+    .get_arguments()
+    .find(|arg| arg.get_short() == Some(name))
+}
+
+/// Whether an option is given a value.
+fn takes_value(arg: &Arg) -> bool {
+  arg.get_action().takes_values()
+}
+
+/// Whether an option given without a value inline takes the next argument
+/// for its value. One whose value must follow an = takes none, leaving the
+/// next argument be, e.g. an input after a bare --list-failures.
+fn takes_next(arg: &Arg) -> bool {
+  takes_value(arg) && !arg.is_require_equals_set()
+}
+
+/// The arguments of `argv` (program first) that a reproduction keeps, then
+/// `scope`, then the files compared in place of the inputs given. The
+/// options of `scope` replace those narrowing the same way. With
+/// `manifest`, the pair's tolerances replace those given.
+pub(crate) fn arguments(
+  cli: &clap::Command,
+  argv: &[String],
+  (csv1, csv2): (&str, &str),
+  manifest: Option<&Tolerances>,
+  scope: &[String],
+) -> Vec<String> {
+  let dropped = |arg: &Arg| {
+    let id = arg.get_id().as_str();
+    let narrowed = NARROWING
+      .iter()
+      .any(|(name, by)| *name == id && scope.iter().any(|arg| arg == by));
+    DROPPED.contains(&id)
+      || narrowed
+      || (manifest.is_some() && TOLERANCES.contains(&id))
+  };
+  let mut kept = Vec::new();
+  let mut tokens = argv.iter().skip(1);
+  while let Some(token) = tokens.next() {
+    // only inputs follow
+    if token == "--" {
+      break;
+    }
+    if let Some(rest) = token.strip_prefix("--") {
+      let (name, inline) = match rest.split_once('=') {
+        Some((name, _)) => (name, true),
+        None => (rest, false),
+      };
+      let Some(arg) = long(cli, name) else {
+        kept.push(token.clone());
+        continue;
+      };
+      let value = match takes_next(arg) && !inline {
+        true => tokens.next(),
+        false => None,
+      };
+      if !dropped(arg) {
+        kept.push(token.clone());
+        kept.extend(value.cloned());
+      }
+      continue;
+    }
+    let Some(shorts) = token.strip_prefix('-').filter(|s| !s.is_empty()) else {
+      // an input
+      continue;
+    };
+    // a cluster such as -qq or -r0.01: flags, up to one taking a value
+    let mut cluster = String::from("-");
+    let mut value = None;
+    for (i, c) in shorts.char_indices() {
+      let Some(arg) = short(cli, c) else {
+        break;
+      };
+      let keep = !dropped(arg);
+      if keep {
+        cluster.push(c);
+      }
+      if takes_value(arg) {
+        let rest = &shorts[i + c.len_utf8()..];
+        let given = match rest.is_empty() {
+          true if !takes_next(arg) => None,
+          true => tokens.next().cloned(),
+          false => Some(rest.to_string()),
+        };
+        if keep {
+          value = given;
+        }
+        break;
+      }
+    }
+    if cluster.len() > 1 {
+      kept.push(cluster);
+      kept.extend(value);
+    }
+  }
+  if let Some(tolerances) = manifest {
+    // the parts of a combined tolerance are given as -r and -d too
+    let given = [
+      ("-r", tolerances.max_ratio.or(tolerances.rtol)),
+      ("--max-ratio-over", tolerances.max_ratio_over),
+      ("--max-ratio-under", tolerances.max_ratio_under),
+      ("-d", tolerances.max_diff.or(tolerances.atol)),
+      ("--max-diff-over", tolerances.max_diff_over),
+      ("--max-diff-under", tolerances.max_diff_under),
+      ("--min-digits", tolerances.min_digits),
+      ("--max-fail-fraction", tolerances.max_fail_fraction),
+    ];
+    for (flag, value) in given {
+      if let Some(value) = value {
+        kept.push(flag.to_string());
+        kept.push(value.to_string());
+      }
+    }
+    if tolerances.allclose().is_some() {
+      kept.push("--allclose".to_string());
+    }
+  }
+  kept.extend(scope.iter().cloned());
+  if csv1.starts_with('-') || csv2.starts_with('-') {
+    kept.push("--".to_string());
+  }
+  kept.extend([csv1.to_string(), csv2.to_string()]);
+  kept
+}
@@ -0,0 +1,198 @@
+//! Files of named scalars, such as a model's totals: a name and a value on
+//! each row, matched by name in any order rather than by position, and each
+//! judged against its own tolerances.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use csv::StringRecord;
+
+use crate::compare::{
+  DiffConfig, DiffReport, ExtraRows, compare_alone, compared_values,
+  parse_float, physical_line,
+};
+use crate::error::DiffError;
+use crate::verdict::Tolerances;
+
+/// What became of a named scalar.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScalarOutcome {
+  /// The name is only in one file. Whether it may be follows
+  /// [`DiffConfig::extra_rows`], as for a table's extra rows.
+  Missing {
+    /// The file it's missing from: 1 or 2.
+    file: usize,
+    /// Whether that's allowed.
+    allowed: bool,
+  },
+  /// A value isn't a number, which fails the scalar.
+  NotFloat,
+  /// Both values are zero, or below the threshold, so it passes unmeasured.
+  Skipped,
+  /// It was compared: this is the report of comparing it alone, so its
+  /// gates are its verdict against each of its tolerances.
+  Compared(Box<DiffReport>),
+}
+
+/// A named scalar, and how it compared.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scalar {
+  /// Its name, from the first column.
+  pub name: String,
+  /// The (1-based) physical line it's on in each file it's in.
+  pub lines: (Option<usize>, Option<usize>),
+  /// Its value's text in each file it's in.
+  pub text: (Option<String>, Option<String>),
+  /// Its values, if they parse, as compared: with clamped subnormals zero,
+  /// and scaled.
+  pub values: (Option<f64>, Option<f64>),
+  /// The tolerances it's judged against.
+  pub tolerances: Tolerances,
+  /// What became of it.
+  pub outcome: ScalarOutcome,
+}
+
+impl Scalar {
+  /// Whether it passed.
+  pub fn passed(&self) -> bool {
+    match &self.outcome {
+      ScalarOutcome::Missing { allowed, .. } => *allowed,
+      ScalarOutcome::NotFloat => false,
+      ScalarOutcome::Skipped => true,
+      ScalarOutcome::Compared(alone) => alone.passed(),
+    }
+  }
+}
+
+/// The name on each row of a scalars file, which must be unique.
+fn names<'a>(
+  records: &'a [StringRecord],
+  file: &str,
+) -> Result<Vec<&'a str>, DiffError> {
+  let mut seen = HashSet::new();
+  records
+    .iter()
+    .enumerate()
+    .map(|(row, rec)| {
+      let line = physical_line(records, row);
+      if rec.len() != 2 {
+        return Err(DiffError::ScalarLayout {
+          file: file.to_string(),
+          line,
+          fields: rec.len(),
+        });
+      }
+      let name = rec[0].trim();
+      if !seen.insert(name) {
+        return Err(DiffError::DuplicateScalar {
+          file: file.to_string(),
+          line,
+          name: name.to_string(),
+        });
+      }
+      Ok(name)
+    })
+    .collect()
+}
+
+/// Compares two files of named scalars, a name and a value on each row.
+/// Each name's tolerances are those `tolerances` gives it, gate by gate,
+/// falling back to `opts`'s. The scalars come in csv1's order, followed by
+/// those only in csv2. Fails if a row isn't a name and a value, or a name
+/// appears twice in a file.
+pub fn compare_scalars(
+  records1: &[StringRecord],
+  records2: &[StringRecord],
+  opts: &DiffConfig,
+  tolerances: &BTreeMap<String, Tolerances>,
+  (file1, file2): (&str, &str),
+) -> Result<Vec<Scalar>, DiffError> {
+  let names1 = names(records1, file1)?;
+  let names2 = names(records2, file2)?;
+  let rows1: HashMap<&str, usize> = names1
+    .iter()
+    .enumerate()
+    .map(|(row, &n)| (n, row))
+    .collect();
+  let rows2: HashMap<&str, usize> = names2
+    .iter()
+    .enumerate()
+    .map(|(row, &n)| (n, row))
+    .collect();
+  let only2 = names2.iter().filter(|n| !rows1.contains_key(*n));
+  let delimiter = char::from(opts.delimiter).to_string();
+  let allowed = |rec: &StringRecord| match &opts.extra_rows {
+    ExtraRows::Forbid => false,
+    ExtraRows::Allow => true,
+    ExtraRows::AllowMatching(pattern) => {
+      pattern.is_match(&rec.iter().collect::<Vec<_>>().join(&delimiter))
+    }
+  };
+
+  let scalars = names1.iter().chain(only2).map(|&name| {
+    let (row1, row2) = (rows1.get(name), rows2.get(name));
+    let (rec1, rec2) =
+      (row1.map(|&r| &records1[r]), row2.map(|&r| &records2[r]));
+    let text =
+      |rec: Option<&StringRecord>| rec.map(|r| r[1].trim().to_string());
+    let value = |factor, text: &Option<String>| {
+      text
+        .as_ref()
+        .and_then(|t| parse_float(t))
+        .map(|v| opts.value(factor, v))
+    };
+    let (text1, text2) = (text(rec1), text(rec2));
+    let values = (value(opts.scale.0, &text1), value(opts.scale.1, &text2));
+    let own = tolerances.get(name).cloned().unwrap_or_default();
+    let fallback = &opts.tolerances;
+    let tolerances = Tolerances {
+      max_ratio: own.max_ratio.or(fallback.max_ratio),
+      max_diff: own.max_diff.or(fallback.max_diff),
+      min_digits: own.min_digits.or(fallback.min_digits),
+      max_fail_fraction: None,
+      ..own.directional_over(fallback)
+    };
+    let lines = (
+      row1.map(|&r| physical_line(records1, r)),
+      row2.map(|&r| physical_line(records2, r)),
+    );
+    let outcome = match (rec1, rec2, values) {
+      (None, Some(rec), _) => ScalarOutcome::Missing {
+        file: 1,
+        allowed: allowed(rec),
+      },
+      (Some(rec), None, _) => ScalarOutcome::Missing {
+        file: 2,
+        allowed: allowed(rec),
+      },
+      (_, _, (Some(v1), Some(v2))) => {
+        let cutoff = (opts.threshold, opts.threshold_mode);
+        match compared_values(v1, v2, false, cutoff) {
+          Err(_) => ScalarOutcome::Skipped,
+          Ok(values) => {
+            let (t1, t2) = (text1.as_deref(), text2.as_deref());
+            let texts = (t1.unwrap_or_default(), t2.unwrap_or_default());
+            let opts = DiffConfig {
+              tolerances: tolerances.clone(),
+              ..opts.clone()
+            };
+            let values = opts.measured(values, texts, opts.scale);
+            let line = lines.0.unwrap_or_default();
+            let cutoff = (opts.threshold, None);
+            let alone = compare_alone(values, texts, (line, 2), cutoff, &opts);
+            ScalarOutcome::Compared(Box::new(alone))
+          }
+        }
+      }
+      _ => ScalarOutcome::NotFloat,
+    };
+    Scalar {
+      name: name.to_string(),
+      lines,
+      text: (text1, text2),
+      values,
+      tolerances,
+      outcome,
+    }
+  });
+  Ok(scalars.collect())
+}
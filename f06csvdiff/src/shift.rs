@@ -0,0 +1,125 @@
+//! Shifting the pairing of rows, for transient runs whose outputs are offset
+//! by a constant number of steps, e.g. when only one of them writes t=0. A
+//! shift of N pairs csv2's row i with csv1's row i+N, and the rows without a
+//! partner at either end are left out. Records keep their positions, so
+//! reports still give the lines they were read from.
+
+use csv::{Position, StringRecord};
+
+use crate::compare::{parse_float, physical_line};
+use crate::error::DiffError;
+
+/// How many rows a shift left out of each file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Excluded {
+  /// The rows left out at the start of csv1 and of csv2.
+  pub start: (usize, usize),
+  /// The rows left out at the end of csv1 and of csv2.
+  pub end: (usize, usize),
+}
+
+/// The shift that best aligns a column of both files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BestShift {
+  /// The shift, positive when csv2 lags csv1.
+  pub shift: isize,
+  /// The mean absolute difference of the column's values under it.
+  pub mean_diff: f64,
+}
+
+/// Gives records without a position their place in the file, so that they
+/// keep their lines once rows are left out.
+fn keep_lines(records: &mut [StringRecord]) {
+  for row in 0..records.len() {
+    if records[row].position().is_none() {
+      let mut position = Position::new();
+      position.set_line(physical_line(records, row) as u64);
+      records[row].set_position(Some(position));
+    }
+  }
+}
+
+/// Shifts the pairing of two files' rows: by a positive `shift`, csv2's
+/// rows move forward, pairing with csv1's `shift` rows later, and by a
+/// negative one, backward. Takes off the rows left without a partner.
+pub fn shift_rows(
+  records1: &mut Vec<StringRecord>,
+  records2: &mut Vec<StringRecord>,
+  shift: isize,
+) -> Excluded {
+  keep_lines(records1);
+  keep_lines(records2);
+  let mut excluded = Excluded::default();
+  let skip = shift.unsigned_abs();
+  match shift > 0 {
+    true => excluded.start.0 = skip.min(records1.len()),
+    false => excluded.start.1 = skip.min(records2.len()),
+  }
+  records1.drain(..excluded.start.0);
+  records2.drain(..excluded.start.1);
+  let common = records1.len().min(records2.len());
+  excluded.end = (records1.len() - common, records2.len() - common);
+  records1.truncate(common);
+  records2.truncate(common);
+  excluded
+}
+
+/// Each record's value in a (0-based) column. Fails with
+/// [`DiffError::ShiftKey`] if a row lacks a number there.
+fn values(
+  records: &[StringRecord],
+  col: usize,
+  file: &str,
+) -> Result<Vec<f64>, DiffError> {
+  (0..records.len())
+    .map(|row| {
+      let value = records[row].get(col).and_then(|c| parse_float(c.trim()));
+      value.ok_or_else(|| DiffError::ShiftKey {
+        file: file.to_string(),
+        line: physical_line(records, row),
+        col: col + 1,
+      })
+    })
+    .collect()
+}
+
+/// Finds the shift, at most `max` rows either way, under which the values
+/// of a (0-based) column of each file differ least on average, such as a
+/// time column. Among shifts as good, the smallest wins, forward first.
+/// Shifts leaving no rows to pair aren't tried; with none left, the shift is
+/// 0.
+pub fn best_shift(
+  (file1, records1): (&str, &[StringRecord]),
+  (file2, records2): (&str, &[StringRecord]),
+  (col1, col2): (usize, usize),
+  max: usize,
+) -> Result<BestShift, DiffError> {
+  let values1 = values(records1, col1, file1)?;
+  let values2 = values(records2, col2, file2)?;
+  let max = max as isize;
+  let mut best: Option<BestShift> = None;
+  for shift in (0..=max).flat_map(|s| [s, -s]).skip(1) {
+    let skip = shift.unsigned_abs();
+    let (pairs1, pairs2) = match shift >= 0 {
+      true => (values1.get(skip..), Some(&values2[..])),
+      false => (Some(&values1[..]), values2.get(skip..)),
+    };
+    let (Some(pairs1), Some(pairs2)) = (pairs1, pairs2) else {
+      continue;
+    };
+    let common = pairs1.len().min(pairs2.len());
+    if common == 0 {
+      continue;
+    }
+    let total: f64 =
+      pairs1.iter().zip(pairs2).map(|(a, b)| (a - b).abs()).sum();
+    let mean_diff = total / common as f64;
+    if best.is_none_or(|b| mean_diff < b.mean_diff) {
+      best = Some(BestShift { shift, mean_diff });
+    }
+  }
+  Ok(best.unwrap_or(BestShift {
+    shift: 0,
+    mean_diff: 0.0,
+  }))
+}
@@ -0,0 +1,123 @@
+//! Sorting a file's rows on key columns, for runs that write the same rows
+//! in different orders, e.g. element results grouped differently. Records
+//! keep their positions through the sort, so reports still give the lines
+//! they were read from.
+
+use std::cmp::Ordering;
+
+use csv::{Position, StringRecord};
+
+use crate::compare::{parse_float, physical_line};
+use crate::error::DiffError;
+
+/// A cell of a key column: numbers sort by value, ahead of any text, which
+/// sorts by its bytes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Key<'a> {
+  /// The cell parses as a number.
+  Number(f64),
+  /// It doesn't.
+  Text(&'a str),
+}
+
+impl<'a> Key<'a> {
+  /// The key of a cell.
+  fn of(cell: &'a str) -> Self {
+    let cell = cell.trim();
+    match parse_float(cell) {
+      Some(value) => Key::Number(value),
+      None => Key::Text(cell),
+    }
+  }
+
+  /// How two keys sort.
+  fn cmp(self, other: Self) -> Ordering {
+    match (self, other) {
+      (Key::Number(a), Key::Number(b)) => a.total_cmp(&b),
+      (Key::Number(_), Key::Text(_)) => Ordering::Less,
+      (Key::Text(_), Key::Number(_)) => Ordering::Greater,
+      (Key::Text(a), Key::Text(b)) => a.cmp(b),
+    }
+  }
+}
+
+/// How two records sort on the key columns, in order.
+fn cmp_keys(a: &StringRecord, b: &StringRecord, keys: &[usize]) -> Ordering {
+  keys
+    .iter()
+    .map(|&col| Key::of(&a[col]).cmp(Key::of(&b[col])))
+    .find(|o| o.is_ne())
+    .unwrap_or(Ordering::Equal)
+}
+
+/// Gives records without a position their place in the file, so that lines
+/// still refer to it once they're sorted.
+fn keep_lines(records: &mut [StringRecord]) {
+  for row in 0..records.len() {
+    let line = physical_line(records, row);
+    let record = &mut records[row];
+    if record.position().is_none() {
+      let mut position = Position::new();
+      position.set_line(line as u64);
+      record.set_position(Some(position));
+    }
+  }
+}
+
+/// Sorts a file's records on the given (0-based) key columns, keeping rows
+/// with equal keys in their order. Records without a position are given
+/// their place in the file first, so that lines still refer to it. Returns
+/// the physical lines of each group of rows sharing a key, whose pairing
+/// with the other file's rows is ambiguous. Fails with
+/// [`DiffError::SortKeyMissing`] if a row lacks a key column.
+pub fn sort_records(
+  records: &mut [StringRecord],
+  keys: &[usize],
+  file: &str,
+) -> Result<Vec<Vec<usize>>, DiffError> {
+  for (row, record) in records.iter().enumerate() {
+    if let Some(&col) = keys.iter().find(|&&col| col >= record.len()) {
+      return Err(DiffError::SortKeyMissing {
+        file: file.to_string(),
+        line: physical_line(records, row),
+        col: col + 1,
+      });
+    }
+  }
+  keep_lines(records);
+  records.sort_by(|a, b| cmp_keys(a, b, keys));
+
+  let mut duplicates = Vec::new();
+  let mut start = 0;
+  for row in 1..=records.len() {
+    let same = row < records.len()
+      && cmp_keys(&records[start], &records[row], keys).is_eq();
+    if !same {
+      if row - start > 1 {
+        duplicates
+          .push((start..row).map(|r| physical_line(records, r)).collect());
+      }
+      start = row;
+    }
+  }
+  Ok(duplicates)
+}
+
+/// How two records sort on all their columns, in order. A record that runs
+/// out of columns first sorts ahead.
+fn cmp_all(a: &StringRecord, b: &StringRecord) -> Ordering {
+  a.iter()
+    .zip(b.iter())
+    .map(|(x, y)| Key::of(x).cmp(Key::of(y)))
+    .find(|o| o.is_ne())
+    .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+/// Sorts a file's records on all their columns, first to last, into an
+/// order that doesn't depend on the one they were written in: cells that
+/// parse as numbers sort by value, others as text, and equal rows keep
+/// their order. Records keep their lines, as with [`sort_records`].
+pub fn canonical_order(records: &mut [StringRecord]) {
+  keep_lines(records);
+  records.sort_by(cmp_all);
+}
@@ -0,0 +1,285 @@
+//! The grep-able result line ending each run, so that thousands of runs can
+//! be monitored through their logs without parsing reports: one per pair,
+//! and in batch mode an aggregate one after them.
+
+use std::fmt;
+use std::time::Duration;
+
+use f06csvdiff::DiffReport;
+
+/// What every result line starts with.
+pub const TAG: &str = "F06CSVDIFF-RESULT";
+
+/// A result line, of a pair or of a whole batch. Its keys never change;
+/// fields that don't apply are left out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResultLine {
+  /// The pair of files, unless it's an aggregate.
+  pub pair: Option<(String, String)>,
+  /// For an aggregate, how many pairs were compared, and how many failed
+  /// without a waiver excusing it.
+  pub pairs: Option<(usize, usize)>,
+  /// Whether the pair, or every pair, passed.
+  pub passed: bool,
+  /// How many failing pairs waivers excuse: for a pair, 1 if they excuse it.
+  pub waived: usize,
+  /// The tickets of the waivers excusing them, each once.
+  pub waivers: Vec<String>,
+  /// Whether the pair, or any pair, exceeded a warning tolerance.
+  pub warned: bool,
+  /// The worst relative difference in percent, if anything was compared.
+  pub worst_ratio_pct: Option<f64>,
+  /// The worst absolute difference, if anything was compared.
+  pub worst_diff: Option<f64>,
+  /// How many cells were compared.
+  pub cells: Option<usize>,
+  /// How many compared cells exceed a tolerance, if there were any.
+  pub violations: Option<usize>,
+  /// How many of them are high-impact and how many low-impact, if any
+  /// column has a significance.
+  pub impact_violations: Option<(usize, usize)>,
+  /// How many cells were left out as listed exclusions, if any were.
+  pub excluded: Option<usize>,
+  /// How many rows were compared and how many there were, if only a sample
+  /// of them was: for an aggregate, of the sampled pairs.
+  pub sampled_rows: Option<(usize, usize)>,
+  /// How long it took.
+  pub elapsed: Duration,
+}
+
+impl ResultLine {
+  /// The result line of a compared pair.
+  pub fn of_pair(
+    (csv1, csv2): (&str, &str),
+    report: &DiffReport,
+    elapsed: Duration,
+  ) -> Self {
+    let compared = report.compared() > 0;
+    Self {
+      pair: Some((csv1.to_string(), csv2.to_string())),
+      pairs: None,
+      passed: report.passed(),
+      waived: 0,
+      waivers: Vec::new(),
+      warned: report.warned(),
+      worst_ratio_pct: compared.then(|| report.ratio_percent()),
+      worst_diff: compared.then_some(report.max_diff.value),
+      cells: Some(report.compared()),
+      violations: (!report.gates.is_empty())
+        .then_some(report.failing_cells.cells),
+      impact_violations: report
+        .impact_classes()
+        .filter(|_| !report.gates.is_empty())
+        .map(|impact| {
+          (
+            impact.high.failing_cells.cells,
+            impact.low.failing_cells.cells,
+          )
+        }),
+      excluded: Some(report.excluded_cells().len()).filter(|&n| n > 0),
+      sampled_rows: report.sampled_rows,
+      elapsed,
+    }
+  }
+
+  /// The aggregate of the result lines of a batch's pairs.
+  pub fn aggregate(lines: &[ResultLine], elapsed: Duration) -> Self {
+    let worst = |of: fn(&ResultLine) -> Option<f64>| {
+      lines.iter().filter_map(of).reduce(f64::max)
+    };
+    let sum = |of: fn(&ResultLine) -> Option<usize>| {
+      lines.iter().filter_map(of).reduce(|a, b| a + b)
+    };
+    let failed = lines.iter().filter(|l| !l.passed).count();
+    let waived: usize = lines.iter().map(|l| l.waived).sum();
+    let mut waivers: Vec<String> = Vec::new();
+    for ticket in lines.iter().flat_map(|l| &l.waivers) {
+      if !waivers.contains(ticket) {
+        waivers.push(ticket.clone());
+      }
+    }
+    Self {
+      pair: None,
+      pairs: Some((lines.len(), failed - waived)),
+      passed: failed == 0,
+      waived,
+      waivers,
+      warned: lines.iter().any(|l| l.warned),
+      worst_ratio_pct: worst(|l| l.worst_ratio_pct),
+      worst_diff: worst(|l| l.worst_diff),
+      cells: sum(|l| l.cells),
+      violations: sum(|l| l.violations),
+      impact_violations: lines
+        .iter()
+        .filter_map(|l| l.impact_violations)
+        .reduce(|a, b| (a.0 + b.0, a.1 + b.1)),
+      excluded: sum(|l| l.excluded),
+      sampled_rows: lines
+        .iter()
+        .filter_map(|l| l.sampled_rows)
+        .reduce(|a, b| (a.0 + b.0, a.1 + b.1)),
+      elapsed,
+    }
+  }
+
+  /// PASSED, WARNED if it passed but exceeded a warning tolerance, WAIVED
+  /// if waivers excuse every failure, or FAILED.
+  pub fn status(&self) -> &'static str {
+    // an aggregate's failures are those no waiver excuses
+    let excused = self.pairs.is_none_or(|(_, failed)| failed == 0);
+    if self.passed && self.warned {
+      "WARNED"
+    } else if self.passed {
+      "PASSED"
+    } else if self.waived > 0 && excused {
+      "WAIVED"
+    } else {
+      "FAILED"
+    }
+  }
+}
+
+impl fmt::Display for ResultLine {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{TAG}")?;
+    if let Some((csv1, csv2)) = &self.pair {
+      let quoted = |path: &str| path.replace('\\', "\\\\").replace('"', "\\\"");
+      write!(f, " pair=\"{}|{}\"", quoted(csv1), quoted(csv2))?;
+    }
+    if let Some((pairs, failed)) = self.pairs {
+      write!(f, " pairs={pairs} failed={failed}")?;
+      if self.waived > 0 {
+        write!(f, " waived={}", self.waived)?;
+      }
+    }
+    write!(f, " status={}", self.status())?;
+    if !self.waivers.is_empty() {
+      write!(f, " waivers={}", self.waivers.join(","))?;
+    }
+    if let Some(pct) = self.worst_ratio_pct {
+      write!(f, " worst_ratio_pct={pct}")?;
+    }
+    if let Some(diff) = self.worst_diff {
+      write!(f, " worst_diff={diff:e}")?;
+    }
+    if let Some(cells) = self.cells {
+      write!(f, " cells={cells}")?;
+    }
+    if let Some(violations) = self.violations {
+      write!(f, " violations={violations}")?;
+    }
+    if let Some((high, low)) = self.impact_violations {
+      write!(f, " high_impact={high} low_impact={low}")?;
+    }
+    if let Some(excluded) = self.excluded {
+      write!(f, " excluded={excluded}")?;
+    }
+    if let Some((rows, total)) = self.sampled_rows {
+      write!(f, " sampled_rows={rows} total_rows={total}")?;
+    }
+    write!(f, " elapsed_ms={}", self.elapsed.as_millis())
+  }
+}
+
+impl std::str::FromStr for ResultLine {
+  type Err = String;
+
+  /// Reads a result line back, as written; its elapsed time is rounded to
+  /// milliseconds.
+  fn from_str(line: &str) -> Result<Self, Self::Err> {
+    let mut rest = line
+      .trim()
+      .strip_prefix(TAG)
+      .ok_or_else(|| format!("doesn't start with {TAG}"))?;
+    let mut result = ResultLine::default();
+    let mut status = None;
+    let mut impact = (None, None);
+    let mut sampled = (None, None);
+    while let Some((key, value, after)) = next_field(rest)? {
+      rest = after;
+      let number = |value: &str| {
+        value
+          .parse::<usize>()
+          .map_err(|_| format!("{key}: {value:?} isn't a count"))
+      };
+      let real = |value: &str| {
+        value
+          .parse::<f64>()
+          .map_err(|_| format!("{key}: {value:?} isn't a number"))
+      };
+      match key {
+        "pair" => {
+          let (csv1, csv2) = value
+            .split_once('|')
+            .ok_or_else(|| format!("pair: {value:?} isn't csv1|csv2"))?;
+          result.pair = Some((csv1.to_string(), csv2.to_string()));
+        }
+        "pairs" => result.pairs = Some((number(&value)?, 0)),
+        "failed" => match &mut result.pairs {
+          Some((_, failed)) => *failed = number(&value)?,
+          None => return Err("failed: only aggregates have it".to_string()),
+        },
+        "waived" => result.waived = number(&value)?,
+        "status" => status = Some(value),
+        "waivers" => {
+          result.waivers = value.split(',').map(str::to_string).collect()
+        }
+        "worst_ratio_pct" => result.worst_ratio_pct = Some(real(&value)?),
+        "worst_diff" => result.worst_diff = Some(real(&value)?),
+        "cells" => result.cells = Some(number(&value)?),
+        "violations" => result.violations = Some(number(&value)?),
+        "high_impact" => impact.0 = Some(number(&value)?),
+        "low_impact" => impact.1 = Some(number(&value)?),
+        "excluded" => result.excluded = Some(number(&value)?),
+        "sampled_rows" => sampled.0 = Some(number(&value)?),
+        "total_rows" => sampled.1 = Some(number(&value)?),
+        "elapsed_ms" => {
+          result.elapsed = Duration::from_millis(number(&value)? as u64)
+        }
+        _ => return Err(format!("unknown key {key}")),
+      }
+    }
+    if let (Some(high), Some(low)) = impact {
+      result.impact_violations = Some((high, low));
+    }
+    if let (Some(rows), Some(total)) = sampled {
+      result.sampled_rows = Some((rows, total));
+    }
+    match status.as_deref() {
+      Some("PASSED") => result.passed = true,
+      Some("WARNED") => (result.passed, result.warned) = (true, true),
+      // a pair's line doesn't count its waivers
+      Some("WAIVED") if result.pair.is_some() => result.waived = 1,
+      Some("WAIVED" | "FAILED") => {}
+      Some(other) => return Err(format!("unknown status {other}")),
+      None => return Err("no status".to_string()),
+    }
+    Ok(result)
+  }
+}
+
+/// The first `key=value` field of what's left of a result line, its value
+/// unquoted, and what's left after it; none at its end.
+fn next_field(rest: &str) -> Result<Option<(&str, String, &str)>, String> {
+  let rest = rest.trim_start();
+  if rest.is_empty() {
+    return Ok(None);
+  }
+  let (key, rest) = rest
+    .split_once('=')
+    .ok_or_else(|| format!("{rest:?} isn't key=value"))?;
+  let Some(quoted) = rest.strip_prefix('"') else {
+    let end = rest.find(' ').unwrap_or(rest.len());
+    return Ok(Some((key, rest[..end].to_string(), &rest[end..])));
+  };
+  let mut value = String::new();
+  let mut chars = quoted.char_indices();
+  while let Some((i, c)) = chars.next() {
+    match c {
+      '\\' => value.extend(chars.next().map(|(_, c)| c)),
+      '"' => return Ok(Some((key, value, &quoted[i + 1..]))),
+      c => value.push(c),
+    }
+  }
+  Err(format!("{key}: unterminated quotes"))
+}
@@ -0,0 +1,486 @@
+//! The aligned table of the plain report, and the rows of the Markdown and
+//! CSV formats, made of the same cells.
+
+use csv::StringRecord;
+
+use crate::color::{Paint, Painter};
+use crate::compare::{DiffReport, Extremum, FailingCells};
+use crate::describe::worst_column;
+use crate::verdict::{Gate, Tolerances};
+use crate::waivers::Waiver;
+
+/// How text is aligned in its column.
+#[derive(Clone, Debug)]
+pub enum Alignment {
+  /// Padded on the right.
+  Left,
+  /// Padded on the left.
+  Right,
+  /// Padded on both sides.
+  Center,
+}
+
+impl std::str::FromStr for Alignment {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "left" => Ok(Alignment::Left),
+      "right" => Ok(Alignment::Right),
+      "center" => Ok(Alignment::Center),
+      _ => Err(format!(
+        "Invalid alignment: {s}. Must be left, right, or center"
+      )),
+    }
+  }
+}
+
+/// Pads text to `width`, leaving longer text as it is.
+fn align_text(text: &str, width: usize, alignment: &Alignment) -> String {
+  if text.len() >= width {
+    return text.to_string();
+  }
+
+  let padding = width - text.len();
+  match alignment {
+    Alignment::Left => format!("{text}{}", " ".repeat(padding)),
+    Alignment::Right => format!("{}{text}", " ".repeat(padding)),
+    Alignment::Center => {
+      let left_pad = padding / 2;
+      let right_pad = padding - left_pad;
+      format!("{}{text}{}", " ".repeat(left_pad), " ".repeat(right_pad))
+    }
+  }
+}
+
+/// Pads text to `width`, cutting longer text short with "...".
+fn truncate_and_align_text(
+  text: &str,
+  width: usize,
+  alignment: &Alignment,
+) -> String {
+  if width == 0 {
+    return String::new();
+  }
+
+  let truncated = if text.len() > width {
+    if width >= 3 {
+      format!("{}...", &text[..width - 3])
+    } else {
+      ".".repeat(width)
+    }
+  } else {
+    text.to_string()
+  };
+
+  align_text(&truncated, width, alignment)
+}
+
+/// A cell of aligned output: its uncolored text and how to paint it.
+pub type Cell = (String, Paint);
+
+/// The cells for a criterion's status.
+pub fn status_cell(passed: bool) -> Cell {
+  if passed {
+    ("PASS".to_string(), Paint::Pass)
+  } else {
+    ("FAIL".to_string(), Paint::Fail)
+  }
+}
+
+/// The cell naming the waivers excusing a pair, by ticket: "WAIVED:T1,T2",
+/// or "-" if none do.
+pub fn waiver_cell(waivers: &[Waiver]) -> Cell {
+  if waivers.is_empty() {
+    return ("-".to_string(), Paint::Plain);
+  }
+  let tickets: Vec<&str> = waivers.iter().map(|w| w.ticket.as_str()).collect();
+  (format!("WAIVED:{}", tickets.join(",")), Paint::Waived)
+}
+
+/// How many digits after the point the report gives values and
+/// percentages, as --report-precision and --percent-precision ask.
+#[derive(Clone, Copy, Debug)]
+pub struct Precision {
+  /// The digits of values, in scientific notation.
+  pub values: usize,
+  /// The digits of percentages.
+  pub percent: usize,
+}
+
+impl Precision {
+  /// A value, signed in scientific notation, e.g. `+1.234568E2`.
+  pub fn value(self, v: f64) -> String {
+    format!("{v:+.precision$E}", precision = self.values)
+  }
+
+  /// A percentage, without its sign, e.g. `3.41`.
+  pub fn percent(self, pct: f64) -> String {
+    format!("{pct:.precision$}", precision = self.percent)
+  }
+}
+
+/// The cells for an offending-if-failing value.
+pub fn value_cell(text: String, passed: bool) -> Cell {
+  (
+    text,
+    if passed {
+      Paint::Plain
+    } else {
+      Paint::Offending
+    },
+  )
+}
+
+/// The headers and cells of a report's row in the aligned output, with its
+/// gates' statuses.
+pub fn report_row(
+  precision: Precision,
+  names: ((&str, &str), Option<&StringRecord>),
+  report: &DiffReport,
+  tolerances: &Tolerances,
+  waiver: Option<Cell>,
+) -> (Vec<String>, Vec<Cell>) {
+  aligned_row(
+    precision,
+    names,
+    tolerances.max_ratio.map(|_| {
+      (
+        report.max_ratio,
+        report.ratio_percent(),
+        report.gate_status(Gate::Ratio),
+      )
+    }),
+    tolerances
+      .max_diff
+      .map(|_| (report.max_diff, report.gate_status(Gate::Diff))),
+    tolerances
+      .min_digits
+      .map(|_| (report.worst_digits(), report.gate_status(Gate::Digits))),
+    tolerances
+      .allclose()
+      .map(|_| (report.allclose, report.gate_status(Gate::Allclose))),
+    (report.compared(), report.failing_cells),
+    waiver,
+  )
+}
+
+/// The --per-column table: a row per compared float column of `report`,
+/// by number and, given csv1's header, by name, with its worst relative
+/// and absolute differences, their lines, and the verdict of its worst
+/// cells against `tolerances`.
+pub fn per_column_table(
+  precision: Precision,
+  report: &DiffReport,
+  tolerances: &Tolerances,
+  header: Option<&StringRecord>,
+) -> (Vec<String>, Vec<Vec<Cell>>) {
+  // no count of failing cells is kept per column
+  let tolerances = Tolerances {
+    max_fail_fraction: None,
+    ..tolerances.clone()
+  };
+  let mut headers = vec!["col".to_string()];
+  if header.is_some() {
+    headers.push("name".to_string());
+  }
+  headers.extend(
+    ["ratio_%", "line_r", "abs_diff", "line_d", "status"].map(str::to_string),
+  );
+  let rows = report
+    .columns
+    .iter()
+    .filter_map(|column| {
+      let alone = report.column_report(column.col)?;
+      let gates = tolerances.evaluate(&alone);
+      let passed = |gate: Gate| {
+        gates
+          .iter()
+          .find(|g| g.gate == gate)
+          .is_none_or(|g| g.passed)
+      };
+      let mut row = vec![(column.col.to_string(), Paint::Plain)];
+      if let Some(header) = header {
+        let name = header.get(column.col - 1).unwrap_or_default();
+        row.push((name.to_string(), Paint::Plain));
+      }
+      let (ratio, diff) = (&column.max_ratio, &column.max_diff);
+      row.extend([
+        value_cell(
+          precision.percent(alone.ratio_percent()),
+          passed(Gate::Ratio),
+        ),
+        (ratio.line.to_string(), Paint::Plain),
+        value_cell(format!("{:.2E}", diff.value), passed(Gate::Diff)),
+        (diff.line.to_string(), Paint::Plain),
+        status_cell(gates.iter().all(|g| g.passed)),
+      ]);
+      Some(row)
+    })
+    .collect();
+  (headers, rows)
+}
+
+/// The headers and cells of the aligned output's row. After each enabled
+/// gate's fields come the counts of cells failing each one, the count of
+/// compared cells, and with --waivers the waivers excusing the pair. The
+/// worst cells' columns are given by csv1's header names, if it has them.
+#[allow(clippy::too_many_arguments)]
+pub fn aligned_row(
+  precision: Precision,
+  (filenames, header): ((&str, &str), Option<&StringRecord>),
+  max_ratio_info: Option<(Extremum, f64, bool)>,
+  max_diff_info: Option<(Extremum, bool)>,
+  min_digits_info: Option<(Option<(f64, usize)>, bool)>,
+  allclose_info: Option<(Extremum, bool)>,
+  (compared, failing): (usize, FailingCells),
+  waiver: Option<Cell>,
+) -> (Vec<String>, Vec<Cell>) {
+  let mut headers = vec!["csv1".to_string(), "csv2".to_string()];
+  let column = |col: usize| worst_column(col, header);
+
+  let mut first_row = vec![
+    (filenames.0.to_string(), Paint::Plain),
+    (filenames.1.to_string(), Paint::Plain),
+  ];
+  if let Some((ratio, percent, passed)) = max_ratio_info {
+    let (v1, v2) = ratio.vals;
+    first_row.extend([
+      value_cell(precision.percent(percent), passed),
+      value_cell(precision.value(v1), passed),
+      value_cell(precision.value(v2), passed),
+      (ratio.line.to_string(), Paint::Plain),
+      (column(ratio.col), Paint::Plain),
+      status_cell(passed),
+    ]);
+    headers.extend(
+      ["ratio_%", "val1_r", "val2_r", "line_r", "col_r", "status_r"]
+        .iter()
+        .map(|s| s.to_string()),
+    );
+  }
+
+  if let Some((diff, passed)) = max_diff_info {
+    let (v1, v2) = diff.vals;
+    first_row.extend([
+      value_cell(format!("{:.2E}", diff.value), passed),
+      value_cell(precision.value(v1), passed),
+      value_cell(precision.value(v2), passed),
+      (diff.line.to_string(), Paint::Plain),
+      (column(diff.col), Paint::Plain),
+      status_cell(passed),
+    ]);
+    headers.extend(
+      [
+        "abs_diff", "val1_d", "val2_d", "line_d", "col_d", "status_d",
+      ]
+      .iter()
+      .map(|s| s.to_string()),
+    );
+  }
+
+  if let Some((worst, passed)) = min_digits_info {
+    let (digits, col) = match worst {
+      Some((d, c)) => (format!("{d:.2}"), column(c)),
+      None => ("n/a".to_string(), "0".to_string()),
+    };
+    first_row.extend([
+      value_cell(digits, passed),
+      (col, Paint::Plain),
+      status_cell(passed),
+    ]);
+    headers.extend(
+      ["digits", "col_a", "status_a"]
+        .iter()
+        .map(|s| s.to_string()),
+    );
+  }
+
+  if let Some((worst, passed)) = allclose_info {
+    let (v1, v2) = worst.vals;
+    first_row.extend([
+      value_cell(format!("{:.2}", worst.value), passed),
+      value_cell(precision.value(v1), passed),
+      value_cell(precision.value(v2), passed),
+      (worst.line.to_string(), Paint::Plain),
+      (column(worst.col), Paint::Plain),
+      status_cell(passed),
+    ]);
+    headers.extend(
+      [
+        "allclose", "val1_c", "val2_c", "line_c", "col_c", "status_c",
+      ]
+      .iter()
+      .map(|s| s.to_string()),
+    );
+  }
+
+  for (enabled, header, count) in [
+    (max_ratio_info.is_some(), "fail_r", failing.ratio),
+    (max_diff_info.is_some(), "fail_d", failing.diff),
+    (min_digits_info.is_some(), "fail_a", failing.digits),
+    (allclose_info.is_some(), "fail_c", failing.allclose),
+    (true, "cells", compared),
+  ] {
+    if enabled {
+      headers.push(header.to_string());
+      first_row.push((count.to_string(), Paint::Plain));
+    }
+  }
+  if let Some(waiver) = waiver {
+    headers.push("waiver".to_string());
+    first_row.push(waiver);
+  }
+
+  (headers, first_row)
+}
+
+/// Lays out rows of cells in aligned columns, optionally under a header line.
+pub fn align_table(
+  headers: &[String],
+  rows: &[Vec<Cell>],
+  alignment: &Alignment,
+  fixed_width: Option<usize>,
+  show_header: bool,
+  painter: &Painter,
+) -> String {
+  // Calculate column widths, from the uncolored text
+  let col_widths: Vec<usize> = if let Some(fixed_width) = fixed_width {
+    // Use fixed width for all columns
+    vec![fixed_width; headers.len()]
+  } else {
+    // Calculate optimal width for each column
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+      for (i, (cell, _)) in row.iter().enumerate() {
+        if i < widths.len() {
+          widths[i] = widths[i].max(cell.len());
+        }
+      }
+    }
+    widths
+  };
+
+  let fit = |text: &str, width: usize| {
+    if fixed_width.is_some() {
+      truncate_and_align_text(text, width, alignment)
+    } else {
+      align_text(text, width, alignment)
+    }
+  };
+
+  // Align first, then paint, so escapes don't count towards widths
+  let mut lines = Vec::new();
+  if show_header {
+    let aligned_headers: Vec<String> = headers
+      .iter()
+      .zip(&col_widths)
+      .map(|(header, &width)| fit(header, width))
+      .collect();
+    lines.push(aligned_headers.join(" "));
+  }
+
+  for row in rows {
+    let aligned_row: Vec<String> = row
+      .iter()
+      .zip(&col_widths)
+      .map(|((cell, paint), &width)| painter.paint(&fit(cell, width), *paint))
+      .collect();
+    lines.push(aligned_row.join(" "));
+  }
+  lines.join("\n")
+}
+
+/// Builds the verdict matrix: one row per profile, with a status and margin
+/// cell per gate enabled in any profile.
+pub fn profile_matrix(
+  profiles: &[(String, Tolerances)],
+  report: &DiffReport,
+) -> (Vec<String>, Vec<Vec<Cell>>) {
+  let mut gates: Vec<Gate> =
+    profiles.iter().flat_map(|(_, tol)| tol.gates()).collect();
+  gates.sort();
+  gates.dedup();
+  let mut headers = vec!["profile".to_string()];
+  for gate in &gates {
+    headers.push(gate.name().to_string());
+    headers.push(format!("margin_{}", gate.name()));
+  }
+  let rows = profiles
+    .iter()
+    .map(|(name, tol)| {
+      let results = tol.evaluate(report);
+      let mut row = vec![(name.clone(), Paint::Plain)];
+      for gate in &gates {
+        match results.iter().find(|r| r.gate == *gate) {
+          Some(r) => {
+            row.push(status_cell(r.passed));
+            row.push(value_cell(r.margin_text(), r.passed));
+          }
+          None => {
+            row.push(("-".to_string(), Paint::Plain));
+            row.push(("-".to_string(), Paint::Plain));
+          }
+        }
+      }
+      row
+    })
+    .collect();
+  (headers, rows)
+}
+
+/// The fields of a --format csv row.
+pub const CSV_FIELDS: [&str; 12] = [
+  "file1",
+  "file2",
+  "ratio_percent",
+  "ratio_val1",
+  "ratio_val2",
+  "ratio_line",
+  "ratio_status",
+  "diff",
+  "diff_val1",
+  "diff_val2",
+  "diff_line",
+  "diff_status",
+];
+
+/// A pair's --format csv row: for the ratio and diff gates, the worst
+/// difference, its values and line and the gate's status, each left empty
+/// if the gate isn't enabled.
+pub fn csv_row(
+  (name1, name2): (&str, &str),
+  report: &DiffReport,
+  tolerances: &Tolerances,
+  precision: Precision,
+) -> Vec<String> {
+  let status = |gate: Gate| match report.gate_status(gate) {
+    true => "PASS".to_string(),
+    false => "FAIL".to_string(),
+  };
+  let fields =
+    |enabled: bool, value: String, worst: &Extremum, gate| match enabled {
+      true => vec![
+        value,
+        precision.value(worst.vals.0),
+        precision.value(worst.vals.1),
+        worst.line.to_string(),
+        status(gate),
+      ],
+      false => vec![String::new(); 5],
+    };
+  let mut row = vec![name1.to_string(), name2.to_string()];
+  row.extend(fields(
+    tolerances.max_ratio.is_some(),
+    precision.percent(report.ratio_percent()),
+    &report.max_ratio,
+    Gate::Ratio,
+  ));
+  row.extend(fields(
+    tolerances.max_diff.is_some(),
+    format!("{:.2E}", report.max_diff.value),
+    &report.max_diff,
+    Gate::Diff,
+  ));
+  row
+}
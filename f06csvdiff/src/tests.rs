@@ -0,0 +1,4113 @@
+use std::collections::BTreeMap;
+
+use csv::StringRecord;
+
+use crate::compare::{
+  self, AbsCompare, DiffConfig, RelMode, SignFlip, Skip, Sum, SumCheck,
+  ThresholdMode,
+};
+use crate::failures;
+use crate::reference;
+use crate::verdict::{Gate, Tolerances};
+
+/// A tiny xorshift generator, so property tests are reproducible without
+/// pulling in a dependency.
+struct Rng(u64);
+
+impl Rng {
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+
+  fn below(&mut self, n: u64) -> u64 {
+    self.next() % n
+  }
+}
+
+/// Generates a cell: mostly floats, sometimes zeros, subnormals, integers or
+/// text.
+fn random_cell(rng: &mut Rng) -> String {
+  match rng.below(10) {
+    0 => "0.0E+00".to_string(),
+    1 => format!("{}", rng.below(100)),
+    2 if rng.below(20) == 0 => "N/A".to_string(),
+    3 if rng.below(5) == 0 => "4.9E-324".to_string(),
+    _ => {
+      let mantissa = (rng.below(2_000_001) as f64 - 1e6) / 1e5;
+      let exponent = rng.below(21) as i32 - 10;
+      // mostly short-field precision, sometimes fewer digits
+      let decimals = [6, 6, 6, 3][rng.below(4) as usize];
+      format!("{mantissa:.decimals$}E{exponent:+03}")
+    }
+  }
+}
+
+/// Generates a pair of tables with the same shape, the second being a
+/// perturbation of the first.
+fn random_pair(
+  rng: &mut Rng,
+  rows: usize,
+  cols: usize,
+) -> (Vec<StringRecord>, Vec<StringRecord>) {
+  let mut t1 = Vec::new();
+  let mut t2 = Vec::new();
+  for _ in 0..rows {
+    let r1: Vec<String> = (0..cols).map(|_| random_cell(rng)).collect();
+    let r2: Vec<String> = r1
+      .iter()
+      .map(|c| {
+        if rng.below(4) == 0 {
+          random_cell(rng)
+        } else {
+          c.clone()
+        }
+      })
+      .collect();
+    t1.push(StringRecord::from(r1));
+    t2.push(StringRecord::from(r2));
+  }
+  (t1, t2)
+}
+
+#[test]
+fn test_compare_matches_reference() {
+  let mut rng = Rng(0x5eed_f06c_5fd1_ff00);
+  for case in 0..500 {
+    let rows = 1 + rng.below(12) as usize;
+    let cols = 1 + rng.below(6) as usize;
+    let (t1, t2) = random_pair(&mut rng, rows, cols);
+    let opts = DiffConfig {
+      threshold: [0.0, 1e-3, 1.0][case % 3],
+      threshold_frac: [0.0, 0.1, 0.5][case % 17 % 3],
+      threshold_mode: [
+        ThresholdMode::Both,
+        ThresholdMode::Either,
+        ThresholdMode::Baseline,
+      ][case % 7 % 3],
+      sum_check: [SumCheck::Off, SumCheck::Combined, SumCheck::Only]
+        [case % 89 % 3],
+      sign_flip: match case % 4 {
+        0 => SignFlip::AnyColumn,
+        1 => SignFlip::Columns(vec![0, 2]),
+        _ => SignFlip::Never,
+      },
+      abs_compare: match case % 61 % 3 {
+        0 => AbsCompare::AnyColumn,
+        1 => AbsCompare::Columns(vec![1]),
+        _ => AbsCompare::Never,
+      },
+      tolerances: Tolerances {
+        max_ratio: [None, Some(0.0), Some(0.01)][case % 3],
+        max_diff: [None, Some(1e-3), Some(0.5)][case % 5 % 3],
+        min_digits: [None, Some(3.0)][case % 2],
+        max_fail_fraction: [None, Some(0.3)][case % 23 % 2],
+        max_ratio_over: [None, Some(0.001)][case % 67 % 2],
+        max_ratio_under: [None, Some(0.05)][case % 71 % 2],
+        max_diff_over: [None, Some(0.01)][case % 73 % 2],
+        max_diff_under: [None, Some(0.0)][case % 79 % 2],
+        atol: [None, Some(1e-3)][case % 97 % 2],
+        rtol: [None, Some(0.01), Some(0.0)][case % 101 % 3],
+      },
+      segments: [0, 1, 3, 10][case % 7 % 4],
+      rel_mode: [
+        RelMode::Ratio,
+        RelMode::Rel1,
+        RelMode::Rel2,
+        RelMode::Symmetric,
+      ][case % 11 % 4],
+      rel_floor: [0.0, 1e-3][case % 13 % 2],
+      max_zero_mismatch: [None, Some(1.0)][case % 83 % 2],
+      fail_fast: [None, Some(1), Some(3)][case % 19 % 3],
+      clamp_subnormals: case % 29 % 2 == 0,
+      sig_digits: [None, Some(3)][case % 59 % 2],
+      histogram: (case % 31 % 2 == 0).then(|| vec![0.01, 1.0, 10.0]),
+      percentiles: (case % 47 % 2 == 0).then(|| vec![50.0, 99.0]),
+      significance: match case % 53 % 3 {
+        0 => BTreeMap::new(),
+        _ => BTreeMap::from([(0, 1.0), (2, 10.0)]),
+      },
+      high_impact_only: case % 53 % 3 == 2,
+      interval: case % 37 % 2 == 0,
+      scale: [(1.0, 1.0), (1e-3, 1.0), (2.0, 0.5)][case % 41 % 3],
+      // csv2's columns in reverse, but for csv1's first
+      column_map: (case % 43 % 2 == 0).then(|| {
+        let pairs: Vec<_> = (1..cols).map(|c| (c, cols - c)).collect();
+        compare::ColumnMap::new(&pairs).unwrap()
+      }),
+      ..Default::default()
+    };
+    let fast = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    let spec = reference::compare(&t1, &t2, &opts);
+    assert_eq!(fast, spec, "case {case}: {t1:?} vs {t2:?}");
+    let listed = failures::failing_cells((&t1, &t2), &opts, &fast, None);
+    assert_eq!(listed.total, fast.failing_cells.cells, "case {case}");
+  }
+}
+
+#[test]
+fn test_parallel_columns() {
+  let mut rng = Rng(0xc0de_c01d_57ab_1e00);
+  for case in 0..12 {
+    // enough rows for several batches, and coarse values so that maxima tie
+    let rows = 1 + rng.below(3000) as usize;
+    let cols = 1 + rng.below(12) as usize;
+    let coarse = |rng: &mut Rng| format!("{}.0E+00", 1 + rng.below(4));
+    let (t1, t2): (Vec<_>, Vec<_>) = (0..rows)
+      .map(|_| {
+        let r1: Vec<String> = (0..cols).map(|_| coarse(&mut rng)).collect();
+        let r2: Vec<String> = (0..cols).map(|_| coarse(&mut rng)).collect();
+        (StringRecord::from(r1), StringRecord::from(r2))
+      })
+      .unzip();
+    let mut opts = DiffConfig {
+      tolerances: Tolerances {
+        max_ratio: Some(0.5),
+        max_diff: Some(1.0),
+        min_digits: None,
+        max_fail_fraction: None,
+        max_ratio_over: [None, Some(0.1)][case % 3 % 2],
+        max_diff_under: [None, Some(0.2)][case % 7 % 2],
+        rtol: [None, Some(0.3)][case % 2],
+        ..Default::default()
+      },
+      sign_flip: [SignFlip::Never, SignFlip::AnyColumn][case % 2].clone(),
+      abs_compare: [AbsCompare::Never, AbsCompare::Columns(vec![0, 2])]
+        [case % 5 % 2]
+        .clone(),
+      threshold_frac: [0.0, 0.4][case % 3 % 2],
+      histogram: Some(crate::histogram::DEFAULT_EDGES.to_vec()),
+      percentiles: Some(vec![50.0, 95.0]),
+      significance: BTreeMap::from([(1, 2.5)]),
+      high_impact_only: case % 4 < 2,
+      ..Default::default()
+    };
+    let serial = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    for workers in [2, 3, 5, 16] {
+      opts.column_workers = workers;
+      let parallel = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+      assert_eq!(parallel, serial, "case {case}, {workers} workers");
+    }
+  }
+}
+
+#[test]
+fn test_discrepancies() {
+  let a = compare::DiffReport::default();
+  let mut b = a.clone();
+  assert!(a.discrepancies(&b).is_empty());
+  b.max_diff.line = 7;
+  let found = a.discrepancies(&b);
+  assert_eq!(found.len(), 1);
+  assert_eq!(
+    found[0],
+    (
+      format!("max_diff: {:?}", a.max_diff),
+      format!("max_diff: {:?}", b.max_diff)
+    )
+  );
+  // a field of another shape puts the fields after it out of step no more
+  b.columns
+    .push(compare::ColumnStats::new(1, 0, 0.0, RelMode::Ratio));
+  b.unscaled_rows = 3;
+  let found: Vec<String> = a
+    .discrepancies(&b)
+    .into_iter()
+    .map(|(_, other)| other.split(':').next().unwrap().to_string())
+    .collect();
+  assert_eq!(found, ["max_diff", "unscaled_rows", "columns"]);
+}
+
+/// Builds records from rows of cells.
+fn records(rows: &[&[&str]]) -> Vec<StringRecord> {
+  rows
+    .iter()
+    .map(|r| StringRecord::from(r.to_vec()))
+    .collect()
+}
+
+#[test]
+fn test_sign_flip() {
+  let t1 = records(&[&["1.0E+00", "1.0E+00"], &["-2.0E+00", "2.0E+00"]]);
+  let t2 = records(&[&["-1.0E+00", "1.0E+00"], &["2.1E+00", "2.0E+00"]]);
+  let mut opts = DiffConfig::default();
+  let plain = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(plain.flipped_columns.is_empty());
+  assert_eq!(plain.max_diff.value, 4.1);
+  opts.sign_flip = SignFlip::AnyColumn;
+  let flipped = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(flipped.flipped_columns, vec![0]);
+  assert!((flipped.max_diff.value - 0.1).abs() < 1e-12);
+  assert_eq!(flipped.max_diff.vals, (-2.0, -2.1));
+  // restricting to another column leaves column 0 alone
+  opts.sign_flip = SignFlip::Columns(vec![1]);
+  let restricted = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(restricted, plain);
+}
+
+#[test]
+fn test_worst_cell_column() {
+  let t1 =
+    records(&[&["x", "1.0E+00", "5.0E+00"], &["y", "2.0E+00", "1.0E+00"]]);
+  let t2 =
+    records(&[&["x", "1.0E+00", "5.5E+00"], &["y", "2.0E+00", "3.0E+00"]]);
+  let report =
+    compare::compare(&t1, &t2, &DiffConfig::default(), ("a", "b")).unwrap();
+  assert_eq!((report.max_diff.line, report.max_diff.col), (2, 3));
+  assert_eq!((report.max_ratio.line, report.max_ratio.col), (2, 3));
+}
+
+#[test]
+fn test_digits_of_agreement() {
+  use crate::compare::printed_digits;
+  assert_eq!(printed_digits("1.234560E+00"), Some(7));
+  assert_eq!(printed_digits("-0.001234E+03"), Some(4));
+  assert_eq!(printed_digits("0.0E+00"), None);
+  let digits = |a: &str, b: &str| {
+    let t1 = records(&[&[a]]);
+    let t2 = records(&[&[b]]);
+    let opts = DiffConfig {
+      threshold: 1e-6,
+      ..Default::default()
+    };
+    let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    report.columns[0].digits_of_agreement()
+  };
+  // identical values score the printed precision
+  assert_eq!(digits("1.23456E+00", "1.23456E+00"), Some(6.0));
+  // the coarsest printed precision caps the score
+  assert_eq!(digits("1.0E+00", "1.0000001E+00"), Some(2.0));
+  // a relative difference of 1e-3 means three digits
+  assert!((digits("1.000E+00", "1.001E+00").unwrap() - 3.0).abs() < 1e-3);
+  // zero against nonzero and opposite signs agree on nothing
+  assert_eq!(digits("0.0E+00", "1.0E+00"), Some(0.0));
+  assert_eq!(digits("1.0E+00", "-1.0E+00"), Some(0.0));
+  // skipped cells give no score at all
+  assert_eq!(digits("1.0E-09", "2.0E-09"), None);
+  assert_eq!(digits("0.0E+00", "0.0E+00"), None);
+}
+
+#[test]
+fn test_dominance() {
+  use crate::compare::Dominance;
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_diff: Some(0.1),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let base: Vec<[String; 3]> = (0..10)
+    .map(|i| [format!("{}", 40000 + i), "1.0E+00".into(), "2.0E+00".into()])
+    .collect();
+  let table = |edit: &dyn Fn(usize, usize) -> Option<&'static str>| {
+    base
+      .iter()
+      .enumerate()
+      .map(|(r, row)| {
+        let cells: Vec<&str> = row
+          .iter()
+          .enumerate()
+          .map(|(c, cell)| edit(r, c).unwrap_or(cell))
+          .collect();
+        StringRecord::from(cells)
+      })
+      .collect::<Vec<_>>()
+  };
+  let t1 = table(&|_, _| None);
+  // one wild cell among small violations: line 4 and column 3 dominate
+  let concentrated = table(&|r, c| match (r, c) {
+    (3, 2) => Some("9.0E+00"),
+    (_, 1) => Some("1.2E+00"),
+    _ => None,
+  });
+  let report = compare::compare(&t1, &concentrated, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &concentrated, &opts));
+  let found = report.dominance(0.5);
+  assert_eq!(found.len(), 2);
+  let Dominance::Line(Gate::Diff, 4, share) = found[0] else {
+    panic!("expected line 4 to dominate, got {found:?}");
+  };
+  assert!((share - 7.0 / 7.9).abs() < 1e-9);
+  assert!(matches!(found[1], Dominance::Column(Gate::Diff, 3, _)));
+  // the same violation everywhere is broad drift, not a single culprit
+  let diffuse = table(&|_, c| (c > 0).then_some("1.5E+00"));
+  let report = compare::compare(&t1, &diffuse, &opts, ("a", "b")).unwrap();
+  assert!(report.dominance(0.5).is_empty());
+  assert_eq!(report.diff_mass.lines.len(), 10);
+  // without a tolerance there is nothing to attribute
+  let report =
+    compare::compare(&t1, &concentrated, &DiffConfig::default(), ("a", "b"))
+      .unwrap();
+  assert!(report.dominance(0.0).is_empty());
+}
+
+#[test]
+fn test_layout_mismatch() {
+  use crate::compare::{FLOAT_PATTERN, layout_mismatch};
+  let float_re = regex::Regex::new(FLOAT_PATTERN).unwrap();
+  let t = records(&[
+    &["1", "x", "1.0E+00", "y", "2.0E+00"],
+    &["1", "x", "N/A", "y", "2.0E+00"],
+    &["1", "x", "N/A", "3.0E+00", "2.0E+00"],
+  ]);
+  let mismatch =
+    |rec1, rec2| layout_mismatch(rec1, rec2, &float_re, &DiffConfig::default());
+  assert_eq!(mismatch(&t[0], &t[0]), None);
+  assert_eq!(mismatch(&t[0], &t[1]), Some(2));
+  // same float count, different positions
+  assert_eq!(mismatch(&t[0], &t[2]), Some(2));
+  // the lenient comparison just leaves such a column out
+  let report =
+    compare::compare(&t[..1], &t[1..2], &DiffConfig::default(), ("a", "b"))
+      .unwrap();
+  assert_eq!(report.columns.len(), 1);
+}
+
+#[test]
+fn test_worst_segment() {
+  use crate::compare::{segment_lines, segment_of};
+  // 25 rows in 10 segments: sizes alternate between 2 and 3
+  let spans: Vec<_> = (0..10).map(|s| segment_lines(s, 25, 10)).collect();
+  assert_eq!(spans[0], (1, 3));
+  assert_eq!(spans[1], (4, 5));
+  assert_eq!(spans[9], (24, 25));
+  for row in 0..25 {
+    let (first, last) = spans[segment_of(row, 25, 10)];
+    assert!((first..=last).contains(&(row + 1)));
+  }
+  // fewer rows than segments leaves some segments empty
+  assert_eq!(segment_lines(1, 3, 10), (2, 1));
+
+  // a drifting column goes bad late, a column with a bad start goes bad early
+  let rows: Vec<[String; 2]> = (0..20)
+    .map(|i| [format!("{}.0E+00", i + 1), "1.0E+00".to_string()])
+    .collect();
+  let t1: Vec<_> = rows.iter().map(|r| StringRecord::from(&r[..])).collect();
+  let t2: Vec<_> = rows
+    .iter()
+    .enumerate()
+    .map(|(i, _)| {
+      let drift = format!("{}E+00", (i + 1) as f64 * (1.0 + i as f64 * 1e-3));
+      let start = if i == 0 { "2.0E+00" } else { "1.0E+00" };
+      StringRecord::from(vec![drift, start.to_string()])
+    })
+    .collect();
+  let opts = DiffConfig {
+    segments: 4,
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  assert_eq!(report.columns[0].worst_segment(), Some(3));
+  assert_eq!(report.columns[0].sparkline(), "▂▄▆█");
+  assert_eq!(report.columns[1].worst_segment(), Some(0));
+  assert_eq!(report.columns[1].sparkline(), "█▁▁▁");
+}
+
+/// A reader that has another thread append a row to its file before the
+/// first read, like a writer racing the comparison.
+struct Racer {
+  file: std::fs::File,
+  path: std::path::PathBuf,
+  raced: bool,
+}
+
+impl std::io::Read for Racer {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    if !self.raced {
+      self.raced = true;
+      let path = self.path.clone();
+      std::thread::spawn(move || {
+        use std::io::Write;
+        let mut file =
+          std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(b"3,3.0E+00\n").unwrap();
+      })
+      .join()
+      .unwrap();
+    }
+    self.file.read(buf)
+  }
+}
+
+#[test]
+fn test_input_changed_during_read() {
+  use crate::error::DiffError;
+  let path = std::env::temp_dir()
+    .join(format!("f06csvdiff-{}-race.csv", std::process::id()));
+  std::fs::write(&path, "1,1.0E+00\n2,2.0E+00\n").unwrap();
+  let name = path.to_str().unwrap();
+
+  // left alone, the file reads fine, locked or not
+  assert_eq!(crate::read_file(name, b',').unwrap().len(), 2);
+  assert_eq!(crate::read_file_locked(name, b',').unwrap().len(), 2);
+
+  let raced =
+    crate::read_watched(name, b',', Default::default(), |file| Racer {
+      file,
+      path: path.clone(),
+      raced: false,
+    });
+  assert!(
+    matches!(&raced, Err(DiffError::InputChanged { file }) if file == name),
+    "{raced:?}"
+  );
+
+  // once the writer is done, reading again succeeds
+  assert_eq!(crate::read_file(name, b',').unwrap().len(), 3);
+  let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_encodings() {
+  use std::io::Read;
+
+  use crate::encoding::{Decoder, Encoding};
+  use crate::{ReadOptions, read_file_with};
+  let fixture =
+    |name: &str| format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+  let read = |name: &str, encoding| {
+    let options = ReadOptions {
+      encoding,
+      ..Default::default()
+    };
+    read_file_with(&fixture(name), b',', options)
+  };
+
+  // a degree sign in Latin-1 isn't UTF-8, 9 bytes in
+  let latin1 = read("latin1.csv", Encoding::Auto).unwrap();
+  assert_eq!(&latin1[0][1], "Temp (°C)");
+  assert_eq!(read("latin1.csv", Encoding::Latin1).unwrap(), latin1);
+  let strict = read("latin1.csv", Encoding::Utf8).unwrap_err().to_string();
+  assert!(strict.contains("latin1.csv"), "{strict}");
+  assert!(strict.contains("invalid UTF-8 at byte 9"), "{strict}");
+
+  // the byte order mark is taken off, but only if it's asked for
+  let bom = read("utf8-bom.csv", Encoding::Auto).unwrap();
+  assert_eq!(bom, latin1);
+  assert_eq!(read("utf8-bom.csv", Encoding::Utf8).unwrap(), latin1);
+  let mangled = read("utf8-bom.csv", Encoding::Latin1).unwrap();
+  assert_eq!(&mangled[0][0], "\u{EF}\u{BB}\u{BF}ID");
+  assert_eq!(&mangled[0][1], "Temp (\u{C2}\u{B0}C)");
+
+  // left on, it would keep the first column from being compared
+  let path = fixture("bom-first-float.csv");
+  let opts = DiffConfig::default();
+  let report = crate::compare_files(&path, &path, &opts).unwrap();
+  assert_eq!(report.columns.len(), 2);
+  let t1 = read("bom-first-float.csv", Encoding::Latin1).unwrap();
+  let report = compare::compare(&t1, &t1, &opts, ("a", "a")).unwrap();
+  assert_eq!(report.columns.len(), 1);
+  // and readers are decoded the same way as files
+  let bytes = std::fs::read(&path).unwrap();
+  let report = crate::compare_readers(&bytes[..], &bytes[..], &opts).unwrap();
+  assert_eq!(report.columns.len(), 2);
+  let latin1 = std::fs::read(fixture("latin1.csv")).unwrap();
+  assert!(crate::compare_readers(&latin1[..], &latin1[..], &opts).is_ok());
+
+  // sequences split between reads still decode, and a file ending inside
+  // one falls back to Latin-1
+  struct Trickle<'a>(&'a [u8]);
+  impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+      let n = self.0.len().min(buf.len()).min(1);
+      buf[..n].copy_from_slice(&self.0[..n]);
+      self.0 = &self.0[n..];
+      Ok(n)
+    }
+  }
+  let decode = |bytes: &[u8], encoding| {
+    let mut text = String::new();
+    Decoder::new(Trickle(bytes), encoding)
+      .read_to_string(&mut text)
+      .map(|_| text)
+  };
+  let text = "\u{FEFF}°C, ±1".as_bytes();
+  assert_eq!(decode(text, Encoding::Utf8).unwrap(), "°C, ±1");
+  assert_eq!(decode(b"\xEF\xBB", Encoding::Auto).unwrap(), "\u{EF}\u{BB}");
+  assert_eq!(decode(b"a\xC2", Encoding::Auto).unwrap(), "a\u{C2}");
+  assert!(decode(b"a\xC2", Encoding::Utf8).is_err());
+}
+
+#[test]
+fn test_scaled_tolerance() {
+  use crate::compare::DiffScale;
+  // the first column is the load: the second row's difference is smaller,
+  // but at a hundredth of the load
+  let t1 = records(&[
+    &["1.0E+02", "1.0E+00", "5.0E+00"],
+    &["1.0E+00", "2.0E+00", "6.0E+00"],
+    &["n/a", "3.0E+00", "7.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.0E+02", "1.5E+00", "5.0E+00"],
+    &["1.0E+00", "2.2E+00", "6.0E+00"],
+    &["n/a", "3.0E+00", "7.0E+00"],
+  ]);
+  let mut opts = DiffConfig {
+    tolerances: Tolerances {
+      max_diff: Some(0.01),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let unscaled = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(unscaled.failing_cells.diff, 2);
+
+  opts.diff_scale = Some(DiffScale { col: 0, floor: 0.0 });
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  // the worst raw difference still is the first row's
+  assert_eq!(report.max_diff.line, 1);
+  let scaled = report.scaled_diff.unwrap();
+  assert_eq!((scaled.worst.line, scaled.worst.col), (2, 2));
+  assert!((scaled.diff - 0.2).abs() < 1e-12 && scaled.scale == 1.0);
+  assert_eq!((report.failing_cells.diff, report.unscaled_rows), (1, 1));
+  assert!(!report.passed());
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  let parallel = DiffConfig {
+    column_workers: 2,
+    ..opts.clone()
+  };
+  assert_eq!(
+    report,
+    compare::compare(&t1, &t2, &parallel, ("a", "b")).unwrap()
+  );
+
+  // a floor lifts the low-load row's tolerance
+  opts.diff_scale = Some(DiffScale {
+    col: 0,
+    floor: 50.0,
+  });
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(report.passed());
+  assert_eq!(report.scaled_diff.unwrap().scale, 100.0);
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+}
+
+#[test]
+fn test_ties() {
+  use crate::compare::Ties;
+  // quantized outputs: the same difference of 0.5 in four cells
+  let t1 = records(&[
+    &["1.0E+00", "4.0E+00", "9.0E+00"],
+    &["2.0E+00", "4.0E+00", "9.0E+00"],
+    &["1.0E+00", "4.0E+00", "9.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.0E+00", "4.5E+00", "9.5E+00"],
+    &["2.0E+00", "4.0E+00", "9.0E+00"],
+    &["1.5E+00", "4.5E+00", "9.0E+00"],
+  ]);
+  let mut opts = DiffConfig {
+    tolerances: Tolerances {
+      max_diff: Some(0.1),
+      ..Default::default()
+    },
+    listed_ties: 2,
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!((report.max_diff.line, report.max_diff.col), (1, 2));
+  let ties = &report.diff_ties;
+  assert_eq!(ties.others(), 3);
+  assert_eq!(ties.listed(&report.max_diff), [(1, 3), (3, 1)]);
+  // the ratio is largest in one cell only, 1.5 against 1.0
+  assert_eq!(report.ratio_ties.others(), 0);
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  for workers in [2, 3] {
+    opts.column_workers = workers;
+    let parallel = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    assert_eq!(parallel, report);
+  }
+
+  // rows sorted before comparing come out of line order, and the first
+  // worst cell by line still represents them
+  let mut sorted = t1.clone();
+  sorted.swap(0, 2);
+  let mut t2 = t2;
+  t2.swap(0, 2);
+  for (row, line) in [(0, 3), (1, 2), (2, 1)] {
+    let mut position = csv::Position::new();
+    position.set_line(line);
+    sorted[row].set_position(Some(position));
+  }
+  opts.column_workers = 1;
+  let report = compare::compare(&sorted, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!((report.max_diff.line, report.max_diff.col), (1, 2));
+  assert_eq!(report.diff_ties.listed(&report.max_diff), [(1, 3), (3, 1)]);
+  assert_eq!(report, reference::compare(&sorted, &t2, &opts));
+
+  // a cell a ULP better ties, and one two ULPs better doesn't, whichever
+  // comes first
+  let max = 0.5f64;
+  let mut ties = Ties::new(0.0, 5);
+  ties.add(max.next_down().next_down(), 1, 1);
+  ties.add(max.next_down(), 1, 2);
+  ties.add(max, 2, 1);
+  ties.add(max.next_down().next_down(), 2, 2);
+  assert_eq!((ties.at, ties.below, ties.others()), (1, 1, 1));
+  let (mut first, mut second) = (Ties::new(0.0, 5), Ties::new(0.0, 5));
+  first.add(max.next_down(), 1, 2);
+  second.add(max, 2, 1);
+  first.merge(second);
+  assert_eq!((first.at, first.below), (1, 1));
+  assert_eq!(
+    (first.at_cells, first.below_cells),
+    (vec![(2, 1)], vec![(1, 2)])
+  );
+}
+
+#[test]
+fn test_zero_mismatches() {
+  // a zero against a large value and against noise: both infinitely off
+  let t1 = records(&[
+    &["0.0E+00", "2.0E+00"],
+    &["0.0E+00", "2.0E+00"],
+    &["1.0E+00", "0.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["5.0E+03", "2.0E+00"],
+    &["1.0E-30", "2.01E+00"],
+    &["1.0E+00", "0.0E+00"],
+  ]);
+  let mut opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(report.max_ratio.value.is_infinite());
+  let zeros = report.zero_mismatches;
+  assert_eq!((zeros.cells, zeros.failing), (2, 0));
+  assert_eq!((zeros.worst.value, zeros.worst.line), (5.0e3, 1));
+  assert_eq!(report.failing_cells.ratio, 2);
+  assert!(!report.passed());
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+
+  // held to a magnitude, they're out of the ratio, which stays finite
+  opts.max_zero_mismatch = Some(1.0e-6);
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!((report.max_ratio.line, report.max_ratio.col), (2, 2));
+  assert_eq!(report.failing_cells.ratio, 0);
+  assert!(report.gates.iter().all(|g| g.passed));
+  assert_eq!(report.zero_mismatches.failing, 1);
+  assert!(!report.passed());
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+
+  opts.max_zero_mismatch = Some(1.0e4);
+  opts.column_workers = 2;
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(report.passed());
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+}
+
+#[test]
+fn test_threshold_modes() {
+  // zero against noise either way round, zeros, and values both below
+  let t1 = records(&[
+    &["0.0E+00", "1.0E+00"],
+    &["1.0E-12", "1.0E+00"],
+    &["0.0E+00", "0.0E+00"],
+    &["1.0E-16", "2.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.0E-12", "1.0E+00"],
+    &["0.0E+00", "1.01E+00"],
+    &["0.0E+00", "0.0E+00"],
+    &["3.0E-16", "2.0E+00"],
+  ]);
+  let mut opts = DiffConfig {
+    threshold: 1.0e-15,
+    ..Default::default()
+  };
+  let skipped = |opts: &DiffConfig| {
+    let report = compare::compare(&t1, &t2, opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&t1, &t2, opts));
+    let parallel = DiffConfig {
+      column_workers: 2,
+      ..opts.clone()
+    };
+    let other = compare::compare(&t1, &t2, &parallel, ("a", "b")).unwrap();
+    assert_eq!(report.skipped, other.skipped);
+    let s = report.skipped;
+    (report, (s.zeros, s.both_below, s.below1, s.below2))
+  };
+
+  // both: the zeros against noise are compared, and infinitely off
+  let (report, counts) = skipped(&opts);
+  assert_eq!(counts, (2, 1, 0, 0));
+  assert!(report.max_ratio.value.is_infinite());
+  assert_eq!(report.zero_mismatches.cells, 2);
+
+  // with a denominator floor, they're compared as equal; zero against zero
+  // is still skipped before the floor is reached
+  opts.rel_floor = 1.0e-9;
+  let (report, counts) = skipped(&opts);
+  assert_eq!(counts, (2, 1, 0, 0));
+  assert_eq!((report.max_ratio.line, report.max_ratio.col), (2, 2));
+  assert_eq!(report.zero_mismatches.cells, 2);
+
+  // either: both are skipped, so neither the floor nor the zero mismatches
+  // ever see them
+  opts.threshold_mode = ThresholdMode::Either;
+  let (report, counts) = skipped(&opts);
+  assert_eq!(counts, (2, 1, 1, 1));
+  assert_eq!((report.max_ratio.line, report.max_ratio.col), (2, 2));
+  assert_eq!(report.zero_mismatches.cells, 0);
+
+  // baseline: only csv1's zero is skipped; its noise against zero isn't
+  opts.threshold_mode = ThresholdMode::Baseline;
+  opts.rel_floor = 0.0;
+  let (report, counts) = skipped(&opts);
+  assert_eq!(counts, (2, 1, 1, 0));
+  assert!(report.max_ratio.value.is_infinite());
+  assert_eq!((report.max_ratio.line, report.max_ratio.col), (2, 1));
+  assert_eq!(report.zero_mismatches.cells, 1);
+}
+
+#[test]
+fn test_list_failures() {
+  let t1 = records(&[
+    &["1.0E+00", "1.0E+02"],
+    &["2.0E+00", "1.0E+02"],
+    &["3.0E+00", "0.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.1E+00", "1.0E+02"],
+    &["2.0E+00", "1.5E+02"],
+    &["3.3E+00", "1.0E-09"],
+  ]);
+  let mut opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.05),
+      max_diff: Some(1.0),
+      ..Default::default()
+    },
+    threshold: 1.0e-6,
+    ..Default::default()
+  };
+  let listed = |opts: &DiffConfig, limit| {
+    let report = compare::compare(&t1, &t2, opts, ("a", "b")).unwrap();
+    let found = failures::failing_cells((&t1, &t2), opts, &report, limit);
+    assert_eq!(found.total, report.failing_cells.cells);
+    let cells = found.cells.iter();
+    let cells = cells.map(|c| (c.line, c.col, c.gates.clone())).collect();
+    (found.total, cells)
+  };
+
+  // the worst first, each with the gates it fails; the last row's noise
+  // against zero is skipped
+  let (total, cells): (usize, Vec<_>) = listed(&opts, None);
+  assert_eq!(total, 3);
+  assert_eq!(
+    cells,
+    [
+      (2, 2, vec![Gate::Ratio, Gate::Diff]),
+      (1, 1, vec![Gate::Ratio]),
+      (3, 1, vec![Gate::Ratio]),
+    ]
+  );
+  // only the worst, with the total still counting every one
+  let (total, cells) = listed(&opts, Some(2));
+  assert_eq!(total, 3);
+  assert_eq!(cells.len(), 2);
+  assert_eq!((cells[1].0, cells[1].1), (1, 1));
+
+  // cells left out aren't listed
+  opts.excluded_cells.insert((2, 1));
+  let (total, cells) = listed(&opts, Some(1));
+  assert_eq!(total, 2);
+  assert_eq!(cells, [(1, 1, vec![Gate::Ratio])]);
+}
+
+#[test]
+fn test_compensated_sums() {
+  let sum = |values: &[f64]| {
+    let mut sum = Sum::default();
+    values.iter().for_each(|&v| sum.add(v));
+    sum.value()
+  };
+  // a naive sum overflows to infinity, then NaN
+  assert_eq!(sum(&[1e308, 1e308, -1e308, -1e308]), 0.0);
+  // and loses the small values to the large ones
+  assert_eq!(sum(&[1.0, 1e100, 1.0, -1e100]), 2.0);
+  assert_eq!(sum(&[1e16, 1.0, -1e16, 1.0, 1.0]), 3.0);
+  let tenths = vec![0.1; 10];
+  assert_eq!(sum(&tenths), 1.0);
+  assert_eq!(sum(&[]), 0.0);
+}
+
+#[test]
+fn test_sum_check() {
+  // the load shifts between the first two rows, but the totals agree; the
+  // last row's values are below the threshold, and differ
+  let t1 = records(&[
+    &["4.0E+04", "1.0E+00"],
+    &["6.0E+04", "1.0E+00"],
+    &["1.0E+00", "1.0E-06"],
+  ]);
+  let t2 = records(&[
+    &["4.5E+04", "1.0E+00"],
+    &["5.5E+04", "1.0E+00"],
+    &["2.0E+00", "9.0E-06"],
+  ]);
+  let mut opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.001),
+      ..Default::default()
+    },
+    threshold: 1.0e-3,
+    sum_check: SumCheck::Combined,
+    ..Default::default()
+  };
+  let checked = |opts: &DiffConfig| {
+    let report = compare::compare(&t1, &t2, opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&t1, &t2, opts));
+    let parallel = DiffConfig {
+      column_workers: 2,
+      ..opts.clone()
+    };
+    let other = compare::compare(&t1, &t2, &parallel, ("a", "b")).unwrap();
+    assert_eq!(report.sums, other.sums);
+    report
+  };
+
+  // the cells fail, the sums don't; the cells below the threshold are left
+  // out of both sums
+  let report = checked(&opts);
+  let sums: Vec<_> = report.sums.iter().map(|s| (s.col, s.sums)).collect();
+  assert_eq!(sums, [(1, (100001.0, 100002.0)), (2, (2.0, 2.0))]);
+  assert!(report.sums.iter().all(|s| s.passed));
+  assert!(!report.passed());
+  opts.sum_check = SumCheck::Only;
+  assert!(checked(&opts).passed());
+
+  // sums are held to the absolute difference tolerance too
+  opts.tolerances.max_diff = Some(1.0e-3);
+  let report = checked(&opts);
+  assert!(!report.passed());
+  assert_eq!(report.sums[0].diff, 1.0);
+  assert!(!report.sums[0].passed && report.sums[1].passed);
+
+  // cells left out are left out of both files' sums
+  opts.excluded_cells.insert((1, 0));
+  let report = checked(&opts);
+  assert_eq!(report.sums[0].sums, (60001.0, 55002.0));
+
+  // without the check, there are no sums
+  opts.sum_check = SumCheck::Off;
+  let report = checked(&opts);
+  assert!(report.sums.is_empty());
+  assert!(report.columns.iter().all(|c| c.sums.is_none()));
+}
+
+#[test]
+fn test_allclose() {
+  // near zero, the ratio is huge but the difference within atol; at large
+  // values, the difference is large but within rtol; the middle cell is
+  // within neither
+  let t1 = records(&[&["1.0E-06", "1.0E+03"], &["2.0E+00", "1.0E+00"]]);
+  let t2 = records(&[&["5.0E-04", "1.008E+03"], &["2.05E+00", "1.0E+00"]]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      atol: Some(1.0e-3),
+      rtol: Some(0.01),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  assert_eq!(opts.tolerances.gates(), [Gate::Allclose]);
+  assert_eq!(opts.tolerances.allclose_limit(-2.0), Some(1.0e-3 + 0.02));
+  let checked = |t2: &[StringRecord]| {
+    let report = compare::compare(&t1, t2, &opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&t1, t2, &opts));
+    report
+  };
+  let report = checked(&t2);
+  assert!(!report.passed());
+  assert_eq!(report.failing_cells.allclose, 1);
+  let worst = report.allclose;
+  assert_eq!((worst.line, worst.col, worst.vals), (2, 1, (2.0, 2.05)));
+  assert_eq!(worst.value, (2.05f64 - 2.0).abs() / (1.0e-3 + 0.01 * 2.05));
+  assert_eq!(report.gates[0].margin, 1.0 - worst.value);
+  let listed = failures::failing_cells((&t1, &t2), &opts, &report, None);
+  assert_eq!(listed.cells.len(), 1);
+  assert_eq!(listed.cells[0].gates, [Gate::Allclose]);
+
+  // without it, the large values are the nearest their tolerance
+  let t2 = records(&[&["5.0E-04", "1.008E+03"], &["2.0E+00", "1.0E+00"]]);
+  let report = checked(&t2);
+  assert!(report.passed());
+  assert_eq!((report.allclose.line, report.allclose.col), (1, 2));
+  assert_eq!(report.column_report(1).unwrap().allclose.line, 1);
+
+  // rtol scales csv2's value, the reference, as numpy's does: 9.5% of 1.1
+  // allows the difference of 0.1, and 9.5% of 1.0 doesn't
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      rtol: Some(0.095),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let (t1, t2) = (records(&[&["1.0E+00"]]), records(&[&["1.1E+00"]]));
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  assert!(report.passed());
+  let report = compare::compare(&t2, &t1, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t2, &t1, &opts));
+  assert!(!report.passed());
+}
+
+#[test]
+fn test_demoted_columns() {
+  // a stray "N/A" in the second column used to leave its later difference
+  // uncompared without a word
+  let t1 = records(&[
+    &["a", "1.0E+00", "2.0E+00"],
+    &["b", "N/A", "2.0E+00"],
+    &["c", "1.0E+00", "2.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["a", "1.0E+00", "2.0E+00"],
+    &["b", "1.0E+00", "2.0E+00"],
+    &["c", "9.0E+00", "2.0E+00"],
+  ]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(report.passed());
+  assert_eq!(report.compared_columns(), 1);
+  let demoted: Vec<_> = report
+    .demoted_columns
+    .iter()
+    .map(|d| (d.col, d.line, d.floats_before, d.file, d.cell.as_str()))
+    .collect();
+  assert_eq!(demoted, [(1, 1, 0, 1, "a"), (2, 2, 1, 1, "N/A")]);
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+
+  // a blank in csv2 is its cell
+  let mut t2 = t2;
+  t2[2] = StringRecord::from(vec!["c", "9.0E+00", ""]);
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  let last = report.demoted_columns.last().unwrap();
+  assert_eq!((last.col, last.line, last.file, &*last.cell), (3, 3, 2, ""));
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+}
+
+#[test]
+fn test_lenient_structure() {
+  use crate::compare::Structure;
+  use crate::error::DiffError;
+  let t1 = records(&[
+    &["1.0E+00", "2.0E+00"],
+    &["1.0E+00", "2.0E+00", "3.0E+00"],
+    &["4.0E+00", "5.0E+00"],
+    &["6.0E+00", "7.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.0E+00", "2.5E+00"],
+    &["1.0E+00", "2.0E+00", "3.0E+00"],
+    &["4.0E+00", "5.0E+00", "9.0E+00"],
+    &["6.0E+00", "7.0E+00"],
+  ]);
+  let mut opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(1.5),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let strict = compare::compare(&t1[2..], &t2[2..], &opts, ("a", "b"));
+  assert!(matches!(strict, Err(DiffError::ColumnCountMismatch { .. })));
+
+  // the rows with problems are listed, and the rest compared at their lines
+  opts.structure = Structure::Lenient;
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  let lines: Vec<usize> =
+    report.structure_issues.iter().map(|i| i.line).collect();
+  assert_eq!(lines, [2, 3]);
+  assert!(
+    report.structure_issues[0]
+      .problem
+      .contains("line 2 of csv1")
+  );
+  assert_eq!(report.rows, 2);
+  assert_eq!((report.max_ratio.line, report.max_ratio.col), (1, 2));
+  assert!(report.gates.iter().all(|g| g.passed));
+  assert!(!report.passed());
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+
+  opts.structure = Structure::Warn;
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(report.passed() && report.warned());
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+}
+
+#[test]
+fn test_trimmed_cells() {
+  use crate::{ReadOptions, read_file_with};
+  let fixture =
+    |name: &str| format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+  let read = |name: &str, trim| {
+    let options = ReadOptions {
+      trim,
+      ..Default::default()
+    };
+    read_file_with(&fixture(name), b',', options).unwrap()
+  };
+  let opts = DiffConfig::default();
+  let compared = |trim| {
+    let (t1, t2) = (read("quoted-padded.csv", trim), read("bare.csv", trim));
+    // past the header
+    let (t1, t2) = (&t1[1..], &t2[1..]);
+    let report = compare::compare(t1, t2, &opts, ("a", "b")).unwrap();
+    let cols: Vec<usize> = report.columns.iter().map(|c| c.col).collect();
+    (cols, report.max_diff.value)
+  };
+
+  // quoted, padded and bare numbers read the same once trimmed, quotes the
+  // CSV reader leaves in too, and a cell of whitespace is empty
+  let trimmed = read("quoted-padded.csv", true);
+  assert_eq!(trimmed, read("bare.csv", true));
+  assert_eq!(&trimmed[1][3], "");
+  assert_eq!(compare::physical_line(&trimmed, 2), 3);
+  assert_eq!(compared(true), (vec![2, 3], 0.0));
+  // and otherwise neither number column is one in both files
+  assert_eq!(&read("quoted-padded.csv", false)[1][1], " \"1.2345E-03\" ");
+  assert_eq!(compared(false), (vec![], 0.0));
+
+  // the library reads files trimmed by default, as the command line does
+  let (padded, bare) = (fixture("quoted-padded.csv"), fixture("bare.csv"));
+  assert_eq!(crate::read_file(&padded, b',').unwrap(), trimmed);
+  let report = crate::compare_files(&padded, &bare, &opts).unwrap();
+  let (t1, t2) = (&trimmed, &read("bare.csv", true));
+  let files = (padded.as_str(), bare.as_str());
+  assert_eq!(report, compare::compare(t1, t2, &opts, files).unwrap());
+}
+
+#[test]
+fn test_extra_rows() {
+  use crate::compare::{ExtraRows, IgnoredRows};
+  use crate::error::DiffError;
+  // the quoted note spans two physical lines
+  let t1 =
+    crate::read_records(&b"\"a\nb\",1.0E+00\nx,2.0E+00\n"[..], b',', "a")
+      .unwrap();
+  let t2 = crate::read_records(
+    &b"\"a\nb\",1.0E+00\nx,2.0E+00\nTOTAL,3.0E+00\nnote,4.0E+00\n"[..],
+    b',',
+    "b",
+  )
+  .unwrap();
+  let mut opts = DiffConfig::default();
+  let err = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap_err();
+  assert!(
+    matches!(
+      &err,
+      DiffError::RowCountMismatch { rows1: 2, rows2: 4, file, line: 4 }
+        if file == "b"
+    ),
+    "{err:?}"
+  );
+
+  opts.extra_rows = ExtraRows::Allow;
+  let report = compare::compare(&t2, &t1, &opts, ("b", "a")).unwrap();
+  assert_eq!(report.rows, 2);
+  let ignored = IgnoredRows {
+    file: 1,
+    count: 2,
+    line: 4,
+  };
+  assert_eq!(report.ignored_rows, Some(ignored));
+  assert_eq!(report, reference::compare(&t2, &t1, &opts));
+
+  // only the first extra row matches, so the second one is the divergence
+  opts.extra_rows =
+    ExtraRows::AllowMatching(regex::Regex::new("^TOTAL,").unwrap());
+  let err = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap_err();
+  assert!(
+    matches!(err, DiffError::RowCountMismatch { line: 5, .. }),
+    "{err:?}"
+  );
+  opts.extra_rows =
+    ExtraRows::AllowMatching(regex::Regex::new("^(TOTAL|note),").unwrap());
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report.ignored_rows.map(|i| i.count), Some(2));
+}
+
+#[test]
+fn test_regression_gate() {
+  use crate::regression::RegressionGate;
+  let reference = records(&[&["1.0E+00", "2.0E+00", "3.0E+00"]]);
+  let old = records(&[&["1.1E+00", "2.0E+00", "x"]]);
+  let new = records(&[&["1.15E+00", "2.001E+00", "3.5E+00"]]);
+  let opts = DiffConfig::default();
+  let against = |other: &[StringRecord]| {
+    compare::compare(&reference, other, &opts, ("ref", "other")).unwrap()
+  };
+  let (new, old) = (against(&new), against(&old));
+  assert!((new.columns[0].worst_diff - 0.15).abs() < 1e-12);
+
+  let gate = RegressionGate {
+    margin: 0.5,
+    floor: 0.0,
+  };
+  let columns = gate.evaluate(&new, &old);
+  let verdicts: Vec<_> = columns.iter().map(|c| (c.col, c.passed)).collect();
+  // 0.15 is within 50% of 0.1, but the old version matched column 2 exactly
+  // and never compared column 3
+  assert_eq!(verdicts, [(1, true), (2, false), (3, false)]);
+  assert_eq!((columns[2].old_diff, columns[2].allowed), (0.0, 0.0));
+
+  // the floor lets small differences through wherever the old one was small
+  let gate = RegressionGate {
+    margin: 0.5,
+    floor: 0.01,
+  };
+  let passed: Vec<_> =
+    gate.evaluate(&new, &old).iter().map(|c| c.passed).collect();
+  assert_eq!(passed, [true, true, false]);
+}
+
+#[test]
+fn test_expected_report() {
+  use crate::expected::SavedReport;
+  use crate::regression::RegressionGate;
+  let reference = records(&[&["1.0E+00", "2.0E+00"], &["3.0E+00", "4.0E+00"]]);
+  let first = records(&[&["1.1E+00", "2.0E+00"], &["3.0E+00", "4.0E+00"]]);
+  let next = records(&[&["1.1E+00", "2.01E+00"], &["3.0E+00", "4.0E+00"]]);
+  let opts = DiffConfig::default();
+  let against = |other: &[StringRecord]| {
+    compare::compare(&reference, other, &opts, ("ref", "other")).unwrap()
+  };
+
+  // the maxima survive the round trip through JSON exactly
+  let saved = SavedReport::new(&against(&first), &opts);
+  let saved = SavedReport::from_json(&saved.to_json()).unwrap();
+  assert_eq!(saved, SavedReport::new(&against(&first), &opts));
+  assert!(saved.staleness(&opts).is_empty());
+
+  let gate = RegressionGate {
+    margin: 0.0,
+    floor: 0.0,
+  };
+  let passed = |report| {
+    let columns = gate.evaluate(&report, &saved.to_report());
+    columns.iter().map(|c| c.passed).collect::<Vec<_>>()
+  };
+  assert_eq!(passed(against(&first)), [true, true]);
+  assert_eq!(passed(against(&next)), [true, false]);
+
+  // other options, or another version, make it stale
+  let other = DiffConfig {
+    threshold: 1e-3,
+    rel_mode: RelMode::Symmetric,
+    ..DiffConfig::default()
+  };
+  assert_eq!(
+    saved.staleness(&other),
+    [
+      "rel_mode was ratio (now symmetric)",
+      "threshold was 0 (now 0.001)"
+    ]
+  );
+  let old = SavedReport {
+    version: "0.1.0".to_string(),
+    ..saved.clone()
+  };
+  assert_eq!(old.staleness(&opts).len(), 1);
+}
+
+#[test]
+fn test_percentiles() {
+  use crate::expected::SavedReport;
+  use crate::percentiles::{ACCURACY, EXACT_CELLS, Percentiles};
+  // ten compared cells, 0% to 9% apart, one of zero against a value, one
+  // of zeros and one below the threshold
+  let column = |cells: Vec<String>| -> Vec<StringRecord> {
+    cells
+      .into_iter()
+      .map(|c| StringRecord::from(vec![c]))
+      .collect()
+  };
+  let mut t1: Vec<String> = vec!["1.00E+02".into(); 10];
+  let mut t2: Vec<String> = (0..10).map(|i| format!("1.0{i}E+02")).collect();
+  t1.extend(["0.0E+00", "0.0E+00", "1.0E-09"].map(String::from));
+  t2.extend(["1.0E+00", "0.0E+00", "2.0E-09"].map(String::from));
+  let (t1, t2) = (column(t1), column(t2));
+  let opts = DiffConfig {
+    threshold: 1e-6,
+    rel_mode: RelMode::Rel1,
+    percentiles: Some(vec![50.0, 95.0, 100.0]),
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  let percentiles = report.percentiles.as_ref().unwrap();
+  assert!(percentiles.exact());
+  assert_eq!(percentiles.counted(), 10);
+  assert_eq!((percentiles.skipped, percentiles.undefined), (2, 1));
+  let values: Vec<f64> = percentiles
+    .values()
+    .iter()
+    .map(|(_, v)| v.unwrap())
+    .collect();
+  assert!((values[0] - 4.0).abs() < 1e-9, "{values:?}");
+  assert!((values[1] - 9.0).abs() < 1e-9, "{values:?}");
+  assert!((values[2] - 9.0).abs() < 1e-9, "{values:?}");
+
+  // they're saved, and read back
+  let saved = SavedReport::new(&report, &opts);
+  let saved = SavedReport::from_json(&saved.to_json()).unwrap();
+  let kept = saved.percentiles.as_ref().unwrap();
+  assert_eq!((kept.counted, kept.skipped, kept.undefined), (10, 2, 1));
+  assert_eq!(kept.values[1].percentile, 95.0);
+  assert_eq!(kept.values[1].value, Some(values[1]));
+
+  // past the cells kept, they're within the accuracy, however they're
+  // split up
+  let mut whole = Percentiles::new(&[50.0, 99.0]);
+  let mut halves = [whole.clone(), whole.clone()];
+  let cells = EXACT_CELLS + 1000;
+  for i in 0..cells {
+    let percent = i as f64 / cells as f64;
+    whole.add(percent);
+    halves[i % 2].add(percent);
+  }
+  let [mut merged, other] = halves;
+  merged.merge(&other);
+  assert_eq!(merged, whole);
+  assert!(!whole.exact());
+  assert_eq!(whole.counted(), cells);
+  for (p, value) in whole.values() {
+    let exact =
+      (p / 100.0 * cells as f64).ceil() / cells as f64 - 1.0 / cells as f64;
+    assert!((value.unwrap() - exact).abs() <= ACCURACY * exact, "p{p}");
+  }
+  assert_eq!(Percentiles::new(&[50.0]).value(50.0), None);
+}
+
+#[test]
+fn test_impact_classes() {
+  use crate::impact::Impact;
+  // a value at the significance is high-impact, as is any without one
+  assert_eq!(Impact::of((49.9, -49.9), Some(50.0)), Impact::Low);
+  assert_eq!(Impact::of((49.9, 50.0), Some(50.0)), Impact::High);
+  assert_eq!(Impact::of((-50.0, 0.0), Some(50.0)), Impact::High);
+  assert_eq!(Impact::of((0.0, 0.0), None), Impact::High);
+
+  // the worst cell, line 1's, is low-impact; line 2 straddles the
+  // significance, line 3 is at it and line 5 above it
+  let t1 = records(&[
+    &["4.00E+01"],
+    &["4.90E+01"],
+    &["5.00E+01"],
+    &["-4.99E+01"],
+    &["5.01E+01"],
+  ]);
+  let t2 = records(&[
+    &["4.90E+01"],
+    &["5.00E+01"],
+    &["5.00E+01"],
+    &["-4.90E+01"],
+    &["5.20E+01"],
+  ]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      ..Default::default()
+    },
+    significance: BTreeMap::from([(0, 50.0)]),
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  let impact = report.impact.as_ref().unwrap();
+  assert_eq!(
+    (impact.low.compared, impact.low.failing_cells.cells),
+    (2, 2)
+  );
+  assert_eq!(
+    (impact.high.compared, impact.high.failing_cells.cells),
+    (3, 2)
+  );
+  assert_eq!(impact.low.max_ratio.line, 1);
+  assert_eq!(impact.high.max_ratio.line, 5);
+  assert_eq!(report.max_ratio.line, 1);
+  assert_eq!(report.failing_cells.cells, 4);
+
+  // judged by the high-impact cells alone, the worst is line 5's, and the
+  // low-impact ones are still counted
+  let high_only = DiffConfig {
+    high_impact_only: true,
+    ..opts.clone()
+  };
+  let report = compare::compare(&t1, &t2, &high_only, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &high_only));
+  assert_eq!(report.impact.as_ref(), Some(impact));
+  assert_eq!(report.max_ratio.line, 5);
+  assert_eq!(report.failing_cells.cells, 2);
+  assert!(!report.passed());
+  let lenient = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.05),
+      ..Default::default()
+    },
+    ..high_only
+  };
+  let report = compare::compare(&t1, &t2, &lenient, ("a", "b")).unwrap();
+  assert!(report.passed());
+  assert_eq!(report.impact.unwrap().low.failing_cells.cells, 1);
+
+  // without a significance, there are no classes
+  let report = compare::compare(&t1, &t2, &Default::default(), ("a", "b"));
+  assert_eq!(report.unwrap().impact, None);
+}
+
+#[test]
+fn test_rel_modes() {
+  use RelMode::*;
+  let modes = [Ratio, Rel1, Rel2, Symmetric];
+  let measures = |a: f64, b: f64| modes.map(|m| m.measure(a, b, 0.0));
+  let inf = f64::INFINITY;
+  // plain values: the ratio exceeds the others' baseline-relative errors
+  assert_eq!(measures(2.0, 2.5), [1.25, 0.25, 0.2, 0.2]);
+  // one value zero: infinite where it's the denominator
+  assert_eq!(measures(0.0, 2.0), [inf, inf, 1.0, 1.0]);
+  assert_eq!(measures(2.0, 0.0), [inf, 1.0, inf, 1.0]);
+  // opposite signs: the ratio doesn't see them at all
+  assert_eq!(measures(2.0, -2.0), [1.0, 2.0, 2.0, 2.0]);
+  assert_eq!(measures(-1.0, 4.0), [4.0, 5.0, 1.25, 1.25]);
+  // percentages, and identical values
+  assert_eq!(
+    modes.map(|m| m.percent(m.measure(2.0, 2.5, 0.0))),
+    [25.0, 25.0, 20.0, 20.0]
+  );
+  assert_eq!(modes.map(|m| m.identical()), measures(3.0, 3.0));
+
+  let verdicts = |t1: &[&[&str]], t2: &[&[&str]], threshold: f64| {
+    modes.map(|rel_mode| {
+      let opts = DiffConfig {
+        tolerances: Tolerances {
+          max_ratio: Some(0.21),
+          ..Default::default()
+        },
+        threshold,
+        rel_mode,
+        ..Default::default()
+      };
+      let (t1, t2) = (records(t1), records(t2));
+      let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+      assert_eq!(report, reference::compare(&t1, &t2, &opts));
+      report.passed()
+    })
+  };
+  // 2 vs 2.5 is a 25% ratio but only 20% relative to csv2
+  let (t1, t2): (&[&[&str]], &[&[&str]]) = (&[&["2.0E+00"]], &[&["2.5E+00"]]);
+  assert_eq!(verdicts(t1, t2, 0.0), [false, false, true, true]);
+  // a zero fails every mode, unless both values are below the threshold
+  let (t1, t2): (&[&[&str]], &[&[&str]]) = (&[&["0.0E+00"]], &[&["1.0E-09"]]);
+  assert_eq!(verdicts(t1, t2, 0.0), [false; 4]);
+  assert_eq!(verdicts(t1, t2, 1e-6), [true; 4]);
+  // a flipped sign passes only as a ratio
+  let (t1, t2): (&[&[&str]], &[&[&str]]) = (&[&["2.0E+00"]], &[&["-2.0E+00"]]);
+  assert_eq!(verdicts(t1, t2, 0.0), [true, false, false, false]);
+}
+
+#[test]
+fn test_rel_floor() {
+  use RelMode::*;
+  let modes = [Ratio, Rel1, Rel2, Symmetric];
+  let floor = 1e-12;
+  let measures = |a: f64, b: f64| modes.map(|m| m.measure(a, b, floor));
+  // zero against tiny: a tiny difference, rather than an infinite one
+  let zero_tiny = measures(0.0, 1e-18);
+  assert_eq!(zero_tiny[0], 1.0);
+  for rel in &zero_tiny[1..] {
+    assert!((rel - 1e-6).abs() < 1e-15, "{zero_tiny:?}");
+  }
+  // tiny against tiny: both below the floor, so still tiny
+  let tiny_tiny = measures(1e-18, 3e-18);
+  assert_eq!(tiny_tiny[0], 1.0);
+  for rel in &tiny_tiny[1..] {
+    assert!((rel - 2e-6).abs() < 1e-15, "{tiny_tiny:?}");
+  }
+  // zero against large: the floor makes no difference where it isn't hit
+  assert_eq!(measures(0.0, 2.0), [2e12, 2e12, 1.0, 1.0]);
+  assert_eq!(
+    modes.map(|m| m.floors(0.0, 2.0, floor)),
+    [true, true, false, false]
+  );
+
+  let report = |t2: &str, rel_floor: f64| {
+    let opts = DiffConfig {
+      tolerances: Tolerances {
+        max_ratio: Some(0.01),
+        ..Default::default()
+      },
+      rel_floor,
+      ..Default::default()
+    };
+    let t1 = records(&[&["0.0E+00", "1.0E+00"]]);
+    let t2 = records(&[&[t2, "1.001E+00"]]);
+    let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&t1, &t2, &opts));
+    report
+  };
+  let unfloored = report("1.0E-18", 0.0);
+  assert!(!unfloored.passed());
+  assert!(!unfloored.ratio_floored());
+  // with the floor, the worst cell is the honest 0.1% of column 2
+  let floored = report("1.0E-18", floor);
+  assert!(floored.passed());
+  assert_eq!(floored.max_ratio.col, 2);
+  assert!(!floored.ratio_floored());
+  // until the tiny value rises above the floor
+  let floored = report("1.0E-11", floor);
+  assert_eq!(floored.max_ratio.col, 1);
+  assert!(floored.ratio_floored());
+  // zero against zero is still skipped outright
+  assert_eq!(report("0.0E+00", floor).columns[0].compared, 0);
+}
+
+#[test]
+fn test_scale() {
+  // the same displacements, in m and in mm
+  let si = records(&[&["1.0E+00", "2.5E-03"], &["0.0E+00", "4.0E-01"]]);
+  let mm = records(&[&["1.0E+03", "2.5E+00"], &["1.0E-04", "4.1E+02"]]);
+  let run = |scale| {
+    let opts = DiffConfig {
+      tolerances: Tolerances {
+        max_ratio: Some(0.05),
+        ..Default::default()
+      },
+      threshold: 1e-6,
+      scale,
+      ..Default::default()
+    };
+    let report = compare::compare(&si, &mm, &opts, ("m", "mm")).unwrap();
+    assert_eq!(report, reference::compare(&si, &mm, &opts));
+    report
+  };
+  assert!(!run((1.0, 1.0)).passed());
+
+  // scaled first, the 1E-4 mm is below the threshold in m, and the worst
+  // cell is a 2.5% difference
+  let scaled = run((1.0, 1e-3));
+  assert!(scaled.passed());
+  assert_eq!(scaled.compared(), 3);
+  assert_eq!((scaled.max_ratio.line, scaled.max_ratio.col), (2, 2));
+  assert!((scaled.ratio_percent() - 2.5).abs() < 1e-9);
+  // the threshold is in the units compared, so in mm the 1E-4 isn't below it
+  assert_eq!(run((1e3, 1.0)).ratio_percent(), f64::INFINITY);
+}
+
+#[test]
+fn test_interval() {
+  use crate::compare::quantum;
+  let near = |a: f64, b: f64| (a / b - 1.0).abs() < 1e-9;
+  assert!(near(quantum("1.234560E+00"), 1e-6));
+  assert!(near(quantum("-5.E-03"), 1e-3));
+  assert!(near(quantum("12e+02"), 1e2));
+
+  // narrow against wide printed precision, in each column
+  let t1 = records(&[&["1.23456E+00", "1.000000E+03", "1.2346E+00"]]);
+  let t2 = records(&[&["1.234561E+00", "1.0001E+03", "1.23455E+00"]]);
+  let run = |interval| {
+    let opts = DiffConfig {
+      tolerances: Tolerances {
+        max_diff: Some(0.05),
+        ..Default::default()
+      },
+      interval,
+      ..Default::default()
+    };
+    let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&t1, &t2, &opts));
+    report
+  };
+  let plain = run(false);
+  assert!(near(plain.max_diff.value, 0.1));
+  assert!(!plain.passed());
+
+  // the intervals of columns 1 and 3 overlap; those of column 2 are
+  // 0.1 - (5E-4 + 5E-2) apart
+  let interval = run(true);
+  let worst: Vec<f64> = interval.columns.iter().map(|c| c.worst_diff).collect();
+  assert_eq!((worst[0], worst[2]), (0.0, 0.0));
+  assert!(near(worst[1], 0.0495));
+  assert_eq!(interval.max_diff.col, 2);
+  assert!(interval.passed());
+}
+
+#[test]
+fn test_clamp_subnormals() {
+  // underflow leftovers, physically zero, next to a real difference
+  let t1 = records(&[
+    &["4.9E-324", "1.00E+00"],
+    &["0.0E+00", "2.00E+00"],
+    &["2.2E-310", "3.00E+00"],
+  ]);
+  let t2 = records(&[
+    &["9.9E-324", "1.01E+00"],
+    &["4.9E-324", "2.00E+00"],
+    &["2.2E-310", "3.00E+00"],
+  ]);
+  let tolerances = Tolerances {
+    max_ratio: Some(0.05),
+    ..Default::default()
+  };
+  let run = |opts: &DiffConfig| {
+    let report = compare::compare(&t1, &t2, opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&t1, &t2, opts));
+    report
+  };
+
+  // as they are, the subnormals make a ratio of 2, and zero against one of
+  // them an infinite one
+  let raw = run(&DiffConfig {
+    tolerances: tolerances.clone(),
+    clamp_subnormals: false,
+    ..Default::default()
+  });
+  assert_eq!(raw.clamped, (0, 0));
+  assert_eq!(raw.max_ratio.value, f64::INFINITY);
+  assert_eq!((raw.max_ratio.line, raw.max_ratio.col), (2, 1));
+  assert_eq!(raw.columns[0].compared, 3);
+  assert!(!raw.passed());
+
+  // clamped, they're zero before the zero policy skips them
+  let clamped = run(&DiffConfig {
+    tolerances: tolerances.clone(),
+    ..Default::default()
+  });
+  assert_eq!(clamped.clamped, (2, 3));
+  assert_eq!(clamped.columns[0].compared, 0);
+  assert_eq!((clamped.max_ratio.line, clamped.max_ratio.col), (1, 2));
+  assert!(clamped.passed());
+
+  // and before the threshold, which on its own skips them too
+  let thresholded = run(&DiffConfig {
+    tolerances,
+    threshold: 1e-300,
+    clamp_subnormals: false,
+    ..Default::default()
+  });
+  assert_eq!(thresholded.clamped, (0, 0));
+  assert_eq!(thresholded.columns[0].compared, 0);
+  assert!(thresholded.passed());
+}
+
+#[test]
+fn test_sig_digits() {
+  use crate::compare::round_significant;
+  // rounded in decimal, as printed: 2.675 is just below it in binary
+  assert_eq!(round_significant(1.2345649, 6), 1.23456);
+  assert_eq!(round_significant(1.2345651, 6), 1.23457);
+  assert_eq!(round_significant(2.675, 3), 2.67);
+  assert_eq!(round_significant(-9.9999996e-3, 7), -1e-2);
+  assert_eq!(round_significant(0.0, 3), 0.0);
+  assert!(round_significant(f64::NAN, 3).is_nan());
+  assert_eq!(round_significant(f64::INFINITY, 3), f64::INFINITY);
+
+  // a file printed with 6 digits against one printed with 13: values that
+  // agree to 6 digits are exactly equal, and the others still differ
+  let t1 = records(&[&["1.23456E+02", "2.00000E-03"]]);
+  let t2 = records(&[&["1.234564321098E+02", "2.000012345678E-03"]]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.0),
+      max_diff: Some(0.0),
+      ..Default::default()
+    },
+    sig_digits: Some(6),
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  assert_eq!(report.columns[0].max_diff.value, 0.0);
+  assert_eq!(report.columns[1].max_diff.vals, (2e-3, 2.00001e-3));
+  assert_eq!(report.failing_cells.cells, 1);
+  assert_eq!(report.max_diff.col, 2);
+
+  // unrounded, both differ
+  let raw = DiffConfig {
+    sig_digits: None,
+    ..opts
+  };
+  let report = compare::compare(&t1, &t2, &raw, ("a", "b")).unwrap();
+  assert_eq!(report.failing_cells.cells, 2);
+}
+
+#[test]
+fn test_abs_compare() {
+  // reactions at a symmetric boundary: the same magnitudes, the signs
+  // differing cell by cell, and a real 2% drift on line 3
+  let t1 = records(&[
+    &["-1.00E+02", "5.00E+00"],
+    &["2.00E+01", "-3.00E+00"],
+    &["-4.00E+01", "1.00E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.00E+02", "5.00E+00"],
+    &["2.00E+01", "3.00E+00"],
+    &["4.08E+01", "1.00E+00"],
+  ]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      max_diff: Some(0.5),
+      ..Default::default()
+    },
+    abs_compare: AbsCompare::AnyColumn,
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  assert_eq!(report.sign_differences, 3);
+  assert_eq!(report.failing_cells.cells, 1);
+  // the worst cell is shown as read, signs and all
+  assert_eq!(report.max_diff.vals, (-40.0, 40.8));
+  assert_eq!(report.max_diff.line, 3);
+
+  // only column 1 by magnitude: column 2's sign difference fails it
+  let opts = DiffConfig {
+    abs_compare: AbsCompare::Columns(vec![0]),
+    ..opts
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  assert_eq!(report.sign_differences, 2);
+  assert_eq!(report.failing_cells.cells, 2);
+  assert_eq!(report.max_diff.vals, (-3.0, 3.0));
+
+  // and signed, every cell whose values differ in sign fails the diff
+  let opts = DiffConfig {
+    abs_compare: AbsCompare::Never,
+    ..opts
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(
+    (report.sign_differences, report.failing_cells.cells),
+    (0, 3)
+  );
+}
+
+#[test]
+fn test_directional_tolerances() {
+  // csv2 over-predicts by 3% on line 1 and under-predicts by 1% on line 2;
+  // column 2 is flipped, and compares -1.02 as an over-prediction of 1
+  let t1 = records(&[
+    &["1.00E+00", "1.00E+00"],
+    &["2.00E+00", "-2.00E+00"],
+    &["4.00E+00", "3.00E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.03E+00", "-1.02E+00"],
+    &["1.98E+00", "2.00E+00"],
+    &["4.00E+00", "-3.00E+00"],
+  ]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.05),
+      max_ratio_under: Some(0.005),
+      max_diff: Some(1.0),
+      ..Default::default()
+    },
+    sign_flip: SignFlip::AnyColumn,
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  assert_eq!(report.flipped_columns, vec![1]);
+  let (over, under) = (report.directions.over, report.directions.under);
+  assert_eq!((over.max_ratio.line, over.max_ratio.col), (1, 1));
+  assert_eq!(over.max_ratio.vals, (1.0, 1.03));
+  assert_eq!((under.max_ratio.line, under.max_ratio.col), (2, 1));
+  // the 3% over-prediction is allowed, the 1% under-prediction isn't
+  assert_eq!(report.failing_cells.ratio, 1);
+  assert!(!report.passed());
+  let ratio = &report.gates[0];
+  assert!((ratio.margin - (0.5 - 2.0 / 1.98 * 100.0 + 100.0)).abs() < 1e-9);
+
+  // unset, a direction falls back to -r
+  let symmetric = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio_under: None,
+      max_ratio_over: Some(0.01),
+      ..opts.tolerances.clone()
+    },
+    ..opts.clone()
+  };
+  let report = compare::compare(&t1, &t2, &symmetric, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &symmetric));
+  assert_eq!(report.failing_cells.ratio, 2);
+
+  // by magnitude, column 2's cells differ in neither direction, however
+  // their signs, and line 2's -2 against 1.98 is an under-prediction
+  let t2 = records(&[
+    &["1.03E+00", "-1.00E+00"],
+    &["-1.98E+00", "2.00E+00"],
+    &["4.00E+00", "-3.00E+00"],
+  ]);
+  let abs = DiffConfig {
+    tolerances: Tolerances {
+      max_diff: Some(1.0),
+      max_diff_over: Some(0.01),
+      ..Default::default()
+    },
+    sign_flip: SignFlip::Never,
+    abs_compare: AbsCompare::AnyColumn,
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &abs, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &abs));
+  assert_eq!(report.directions.over.max_diff.line, 1);
+  assert_eq!(report.directions.under.max_diff.vals, (2.0, -1.98));
+  assert_eq!(report.failing_cells.diff, 1);
+  assert!(!report.passed());
+
+  // a column's own -d is symmetric, while the -r it inherits stays refined
+  let own = Tolerances {
+    max_diff: Some(2.0),
+    ..Default::default()
+  };
+  let inherited = own.directional_over(&Tolerances {
+    max_ratio_under: Some(0.005),
+    ..abs.tolerances
+  });
+  assert_eq!(inherited.max_ratio_under, Some(0.005));
+  assert_eq!(inherited.max_diff_over, None);
+}
+
+#[test]
+fn test_diff_matrix() {
+  use crate::matrix::{DiffMetric, diff_matrix};
+  let t1 = records(&[
+    &["a", "1.0E+00", "2.0E+00"],
+    &["b", "0.0E+00", "4.0E+00"],
+    &["c", "1.0E-09", "8.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["a", "1.5E+00", "-2.0E+00"],
+    &["b", "0.0E+00", "-3.0E+00"],
+    &["d", "2.0E-09", "-8.0E+00"],
+  ]);
+  let opts = DiffConfig {
+    threshold: 1e-6,
+    sign_flip: SignFlip::AnyColumn,
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  let matrix = |metric| {
+    let rows = diff_matrix(&t1, &t2, &opts, &report, metric, "-");
+    rows
+      .iter()
+      .map(|r| r.iter().collect::<Vec<_>>().join(" "))
+      .collect()
+  };
+  // text is csv1's, zeros and cells below the threshold are skipped, and
+  // column 3 is compared flipped
+  let signed: Vec<String> = matrix(DiffMetric::Signed);
+  assert_eq!(signed, ["a 5E-1 0E0", "b - -1E0", "c - 0E0"]);
+  let rel: Vec<String> = matrix(DiffMetric::Rel);
+  assert_eq!(rel, ["a 3.333333333333333E-1 0E0", "b - 2.5E-1", "c - 0E0"]);
+  assert!("sum".parse::<DiffMetric>().is_err());
+}
+
+#[test]
+fn test_threshold_frac() {
+  // displacements in mm next to stresses in MPa, each with a noisy small cell
+  let t1 = records(&[
+    &["1.0E-03", "2.0E+02"],
+    &["1.0E-06", "5.0E-01"],
+    &["-2.0E-03", "1.5E+02"],
+  ]);
+  let t2 = records(&[
+    &["1.0E-03", "2.0E+02"],
+    &["2.0E-06", "6.0E-01"],
+    &["-2.0E-03", "1.5E+02"],
+  ]);
+  let report = |threshold: f64, threshold_frac: f64| {
+    let opts = DiffConfig {
+      tolerances: Tolerances {
+        max_ratio: Some(0.01),
+        ..Default::default()
+      },
+      threshold,
+      threshold_frac,
+      ..Default::default()
+    };
+    let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&t1, &t2, &opts));
+    report
+  };
+  // no single absolute threshold skips both noisy cells and nothing else
+  assert!(!report(0.0, 0.0).passed());
+  assert!(!report(1e-5, 0.0).passed());
+  assert_eq!(report(1.0, 0.0).columns[0].compared, 0);
+
+  // 1% of each column's largest magnitude does
+  let scaled = report(0.0, 0.01);
+  assert!(scaled.passed());
+  let cutoffs: Vec<f64> = scaled.columns.iter().map(|c| c.cutoff).collect();
+  assert_eq!(cutoffs, [2e-5, 2.0]);
+  assert_eq!(scaled.columns.iter().map(|c| c.compared).sum::<usize>(), 4);
+
+  // either rule skips a cell, so the larger cutoff wins
+  let both = report(1e-3, 0.01);
+  let cutoffs: Vec<f64> = both.columns.iter().map(|c| c.cutoff).collect();
+  assert_eq!(cutoffs, [1e-3, 2.0]);
+  assert_eq!(both.columns[0].compared, 2);
+}
+
+#[test]
+fn test_explain_cell() {
+  use crate::cell::{Outcome, explain};
+  use crate::error::DiffError;
+  let t1 = records(&[
+    &["x", "1.0E+00", "0.0E+00", "1.0E-09"],
+    &["y", "2.0E+00", "0.0E+00", "3.0E-09"],
+  ]);
+  let t2 = records(&[
+    &["x", "1.0E+00", "0.0E+00", "2.0E-09"],
+    &["y", "-2.1E+00", "0.0E+00", "3.0E-09"],
+  ]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      max_diff: Some(1.0),
+      min_digits: None,
+      max_fail_fraction: None,
+      ..Default::default()
+    },
+    threshold: 1e-6,
+    sign_flip: SignFlip::AnyColumn,
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  let explain = |line, col| explain(&t1, &t2, &opts, &report, (line, col));
+
+  // the worst cell, which fails the ratio gate even though the files pass
+  // the diff gate, compared with csv2's sign flipped
+  let worst = explain(2, 2).unwrap();
+  assert_eq!(worst.text, ("2.0E+00".to_string(), "-2.1E+00".to_string()));
+  assert_eq!(worst.values, (Some(2.0), Some(2.1)));
+  assert!(worst.flipped);
+  let Outcome::Compared(alone) = &worst.outcome else {
+    panic!("{worst:?}");
+  };
+  assert_eq!(alone.max_ratio, report.max_ratio);
+  let verdicts: Vec<_> = alone.gates.iter().map(|g| g.passed).collect();
+  assert_eq!(verdicts, [false, true]);
+
+  assert_eq!(explain(1, 1).unwrap().outcome, Outcome::NotCompared);
+  assert_eq!(explain(1, 3).unwrap().outcome, Outcome::BothZero);
+  let tiny = explain(1, 4).unwrap();
+  assert_eq!(tiny.outcome, Outcome::BelowCutoff(Skip::BothBelow));
+  assert_eq!(tiny.cutoff, Some(1e-6));
+  for (line, col) in [(3, 2), (1, 5), (1, 0)] {
+    assert!(matches!(
+      explain(line, col),
+      Err(DiffError::NoSuchCell { .. })
+    ));
+  }
+}
+
+#[test]
+fn test_column_map() {
+  use crate::cell::explain;
+  use crate::compare::ColumnMap;
+  use crate::error::DiffError;
+  use crate::matrix::{DiffMetric, diff_matrix};
+  let t1 = records(&[
+    &["1", "1.0E+00", "2.0E+00", "3.0E+00"],
+    &["2", "4.0E+00", "5.0E+00", "6.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1", "3.0E+00", "1.0E+00", "2.0E+00", "x"],
+    &["2", "6.0E+00", "4.5E+00", "5.0E+00", "x"],
+  ]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      ..Default::default()
+    },
+    column_map: Some(ColumnMap::new(&[(1, 2), (2, 3), (3, 1)]).unwrap()),
+    ..Default::default()
+  };
+  assert_eq!(opts.col2(0), None);
+  assert_eq!(opts.col2(1), Some(2));
+
+  // compared through the map, with the worst cell in csv1's columns, and
+  // rows of different widths
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report.compared(), 6);
+  assert_eq!((report.max_ratio.line, report.max_ratio.col), (2, 2));
+  assert_eq!(report.ratio_percent(), 12.5);
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  let cell = explain(&t1, &t2, &opts, &report, (2, 2)).unwrap();
+  assert_eq!(cell.text, ("4.0E+00".to_string(), "4.5E+00".to_string()));
+  let matrix = diff_matrix(&t1, &t2, &opts, &report, DiffMetric::Abs, "");
+  assert_eq!(&matrix[1][1], "5E-1");
+  assert_eq!(&matrix[1][2], "0E0");
+
+  // without it, the columns don't line up
+  let positional = DiffConfig {
+    column_map: None,
+    ..opts.clone()
+  };
+  assert!(matches!(
+    compare::compare(&t1, &t2, &positional, ("a", "b")),
+    Err(DiffError::ColumnCountMismatch { .. })
+  ));
+
+  // every paired column must exist
+  let far = DiffConfig {
+    column_map: Some(ColumnMap::new(&[(1, 7)]).unwrap()),
+    ..opts.clone()
+  };
+  assert!(matches!(
+    compare::compare(&t1, &t2, &far, ("a", "b")),
+    Err(DiffError::MappedColumnMissing {
+      line: 1,
+      col: 8,
+      ..
+    })
+  ));
+  assert_eq!(ColumnMap::new(&[(1, 2), (1, 3)]), Err(1));
+}
+
+#[test]
+fn test_match_by_header() {
+  use crate::error::DiffError;
+  use crate::headers::match_by_name;
+  let header = |names: &[&str]| StringRecord::from(names.to_vec());
+  let matched = |h1: &[&str], h2: &[&str]| {
+    match_by_name(("a", &header(h1)), ("b", &header(h2)))
+  };
+  let pairs = |h1: &[&str], h2: &[&str]| {
+    let matched = matched(h1, h2).unwrap();
+    (matched.map.pairs().collect::<Vec<_>>(), matched.only)
+  };
+
+  // reordered, trimmed and case-folded
+  assert_eq!(
+    pairs(&["GRID", "T1", "T2", "T3"], &["grid", " t3", "T1 ", "T2"]),
+    (vec![(0, 0), (1, 2), (2, 3), (3, 1)], (vec![], vec![]))
+  );
+  // missing from either file
+  assert_eq!(
+    pairs(&["GRID", "T1", "R1"], &["T1", "GRID", "T2", "T3"]),
+    (vec![(0, 1), (1, 0)], (vec![2], vec![2, 3]))
+  );
+  // duplicated within a file
+  for (h1, h2, duplicate) in [
+    (&["T1", "t1 "][..], &["T1"][..], ("a", (1, 2))),
+    (&["T1"][..], &["T2", "T1", "T2"][..], ("b", (1, 3))),
+  ] {
+    match matched(h1, h2) {
+      Err(DiffError::DuplicateHeader { file, cols, .. }) => {
+        assert_eq!((file.as_str(), cols), duplicate)
+      }
+      other => panic!("{other:?}"),
+    }
+  }
+
+  // the pairing lines the columns up for the comparison
+  let t1 = records(&[&["1", "1.0E+00", "2.0E+00"]]);
+  let t2 = records(&[&["2.0E+00", "1", "1.0E+00", "x"]]);
+  let map = matched(&["GRID", "T1", "T2"], &["T2", "GRID", "T1", "X"])
+    .unwrap()
+    .map;
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_diff: Some(0.0),
+      ..Default::default()
+    },
+    column_map: Some(map),
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(report.passed());
+  assert_eq!(report.compared(), 2);
+}
+
+#[test]
+fn test_strip_suffix() {
+  use crate::annotations::{
+    Annotation, AnnotationMismatch, mismatches, take_annotations,
+  };
+  let star = regex::Regex::new(crate::annotations::DEFAULT_SUFFIX).unwrap();
+  // only some cells of the middle columns are annotated
+  let mut t1 = records(&[
+    &["1", "1.0E+00", "2.0E+00", "x*"],
+    &["2", "3.0E+00*", "4.0E+00", "5.0E+00"],
+    &["3", "5.0E+00", "6.0E+00*", "7.0E+00"],
+  ]);
+  let mut t2 = records(&[
+    &["1", "1.0E+00*", "2.0E+00", "x*"],
+    &["2", "3.0E+00*", "4.0E+00", "5.0E+00"],
+    &["3", "5.0E+00", "6.0E+00", "7.0E+00"],
+  ]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_diff: Some(0.0),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  // annotated cells aren't floats, so their columns are left out
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report.columns.len(), 0);
+
+  let taken1 =
+    take_annotations(&mut t1, &star, &DiffConfig::default().float_re);
+  let taken2 =
+    take_annotations(&mut t2, &star, &DiffConfig::default().float_re);
+  let at = |row, col| Annotation {
+    row,
+    col,
+    suffix: "*".to_string(),
+  };
+  // "x*" isn't a number, so it's left alone
+  assert_eq!(taken1, vec![at(1, 1), at(2, 2)]);
+  assert_eq!(taken2, vec![at(0, 1), at(1, 1)]);
+  assert_eq!(t1[1][1], *"3.0E+00");
+  assert_eq!(t1[0][3], *"x*");
+
+  let mut report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(report.passed());
+  assert_eq!(report.compared(), 6);
+  // the values agree, but not the annotations of two cells
+  report.annotation_mismatches =
+    mismatches(&t1, (&taken1, &taken2), &opts, &report);
+  let mismatch =
+    |line, col, suffixes: (Option<&str>, Option<&str>)| AnnotationMismatch {
+      line,
+      col,
+      suffixes: (suffixes.0.map(String::from), suffixes.1.map(String::from)),
+    };
+  assert_eq!(
+    report.annotation_mismatches,
+    vec![
+      mismatch(1, 2, (None, Some("*"))),
+      mismatch(3, 3, (Some("*"), None)),
+    ]
+  );
+  assert!(!report.passed());
+  assert!(report.gates.iter().all(|g| g.passed));
+
+  // through a column map, csv2's annotations are matched by its columns
+  let mut t2 =
+    records(&[&["1", "2.0E+00", "1.0E+00*"], &["2", "4.0E+00", "3.0E+00*"]]);
+  let mut t1 = records(&[
+    &["1", "1.0E+00*", "2.0E+00"],
+    &["2", "3.0E+00*", "4.0E+00*"],
+  ]);
+  let taken1 =
+    take_annotations(&mut t1, &star, &DiffConfig::default().float_re);
+  let taken2 =
+    take_annotations(&mut t2, &star, &DiffConfig::default().float_re);
+  let opts = DiffConfig {
+    column_map: Some(compare::ColumnMap::new(&[(1, 2), (2, 1)]).unwrap()),
+    ..opts
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(
+    mismatches(&t1, (&taken1, &taken2), &opts, &report),
+    vec![mismatch(2, 3, (Some("*"), None))]
+  );
+}
+
+#[test]
+fn test_diff_headers() {
+  use crate::headerdiff::{HeaderColumn, Moved, RenameHint, diff_headers};
+  let header = |names: &[&str]| StringRecord::from(names.to_vec());
+  let diff = |h1: &[&str], h2: &[&str]| diff_headers(&header(h1), &header(h2));
+  let column = |col, name: &str| HeaderColumn {
+    col,
+    name: name.to_string(),
+  };
+  let same = diff(&["ID", "T1", "T2"], &["ID", "T1", "T2"]);
+  assert!(same.is_empty());
+  assert_eq!(same.aligned, 3);
+
+  // an inserted column is just that, not every later one moving
+  let inserted =
+    diff(&["ID", "T1", "T2", "T3"], &["ID", "X", "T1", "T2", "T3"]);
+  assert_eq!(inserted.aligned, 4);
+  assert_eq!(inserted.added, [column(2, "X")]);
+  assert!(inserted.removed.is_empty() && inserted.moved.is_empty());
+
+  // of two swapped columns, one stays aligned and the other moves
+  let swapped = diff(&["ID", "T1", "T2", "T3"], &["ID", "T2", "T1", "T3"]);
+  assert_eq!(swapped.aligned, 3);
+  let moved = Moved {
+    name: "T1".to_string(),
+    from: 2,
+    to: 3,
+  };
+  assert_eq!(swapped.moved, [moved]);
+
+  // likely renames: the same name but for case or whitespace, anywhere, or
+  // columns alone in the same place; the rest is only in one header
+  let drifted = diff(
+    &["ID", "Temp  (C)", "Fx", "Fy", "Old"],
+    &["ID", "Fx force", "Fy", "temp (C)"],
+  );
+  assert_eq!(drifted.aligned, 2);
+  let renamed: Vec<_> = drifted
+    .renamed
+    .iter()
+    .map(|r| (r.from.col, r.to.col, r.hint))
+    .collect();
+  assert_eq!(
+    renamed,
+    [(2, 4, RenameHint::Spelling), (3, 2, RenameHint::Place)]
+  );
+  assert_eq!(drifted.removed, [column(5, "Old")]);
+  assert!(drifted.added.is_empty());
+
+  // two columns left between the same aligned ones pair up in order
+  let pairs = diff(&["A", "B", "C", "D"], &["A", "X", "Y", "D", "Z"]);
+  let renamed: Vec<_> = pairs
+    .renamed
+    .iter()
+    .map(|r| (r.from.col, r.to.col))
+    .collect();
+  assert_eq!(renamed, [(2, 2), (3, 3)]);
+  assert_eq!(pairs.added, [column(5, "Z")]);
+
+  let json = serde_json::to_value(&drifted).unwrap();
+  assert_eq!(json["aligned"], 2);
+  assert_eq!(json["removed"][0]["name"], "Old");
+  assert_eq!(json["renamed"][0]["hint"], "spelling");
+  assert_eq!(json["renamed"][1]["from"]["col"], 3);
+}
+
+#[test]
+fn test_sort_records() {
+  use crate::compare::physical_line;
+  use crate::error::DiffError;
+  use crate::sort::sort_records;
+  let lines = |records: &[StringRecord]| -> Vec<usize> {
+    (0..records.len())
+      .map(|r| physical_line(records, r))
+      .collect()
+  };
+  // numbers sort by value and ahead of text; equal keys keep their order
+  let mut t1 = records(&[
+    &["20", "1", "2.0E+00"],
+    &["b", "1", "4.0E+00"],
+    &["3", "2", "3.0E+00"],
+    &["1.0E+02", "1", "1.0E+00"],
+    &["3", "1", "5.0E+00"],
+    &["a", "1", "6.0E+00"],
+    &["3", "2", "7.0E+00"],
+  ]);
+  let shared = sort_records(&mut t1, &[0, 1], "a").unwrap();
+  assert_eq!(lines(&t1), [5, 3, 7, 1, 4, 6, 2]);
+  assert_eq!(shared, [vec![3, 7]]);
+
+  // the rows pair up by key, and lines are still those of the file
+  let mut t2 = records(&[
+    &["a", "1", "6.0E+00"],
+    &["3", "2", "3.0E+00"],
+    &["3", "2", "7.0E+00"],
+    &["100", "1", "1.0E+00"],
+    &["3", "1", "5.5E+00"],
+    &["20", "1", "2.0E+00"],
+    &["b", "1", "4.0E+00"],
+  ]);
+  sort_records(&mut t2, &[0, 1], "b").unwrap();
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_diff: Some(0.0),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report.compared(), 7);
+  assert_eq!((report.max_diff.value, report.max_diff.line), (0.5, 5));
+
+  let mut short = records(&[&["1", "2"], &["3"]]);
+  match sort_records(&mut short, &[1], "c") {
+    Err(DiffError::SortKeyMissing { line, col, .. }) => {
+      assert_eq!((line, col), (2, 2))
+    }
+    other => panic!("{other:?}"),
+  }
+}
+
+#[test]
+fn test_canonical_order() {
+  use crate::sort::canonical_order;
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_diff: Some(0.0),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let mut t1 = records(&[
+    &["QUAD4", "2.0E+00", "1.0E+00"],
+    &["10", "1.0E+00", "3.0E+00"],
+    &["10", "1.0E+00", "2.0E+00"],
+    &["9", "5.0E+00", "1.0E+00"],
+  ]);
+  canonical_order(&mut t1);
+  // rows shuffled, with floats written to other precisions, still pair up
+  let mut t2 = records(&[
+    &["10", "1.00E+00", "2.00E+00"],
+    &["QUAD4", "2.00E+00", "1.00E+00"],
+    &["9", "5.00E+00", "1.00E+00"],
+    &["10", "1.00E+00", "3.00E+00"],
+  ]);
+  canonical_order(&mut t2);
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report.compared(), 8);
+  assert!(report.passed());
+
+  // a value changed without moving its row is found at its original line
+  let mut t3 = records(&[
+    &["10", "1.00E+00", "2.00E+00"],
+    &["QUAD4", "2.00E+00", "1.00E+00"],
+    &["9", "5.00E+00", "1.50E+00"],
+    &["10", "1.00E+00", "3.00E+00"],
+  ]);
+  canonical_order(&mut t3);
+  let report = compare::compare(&t1, &t3, &opts, ("a", "b")).unwrap();
+  assert!(!report.passed());
+  assert_eq!((report.max_diff.value, report.max_diff.line), (0.5, 4));
+  assert_eq!(report.max_diff.col, 3);
+}
+
+#[test]
+fn test_skip_rows() {
+  use crate::error::DiffError;
+  use crate::headers::{skip_rows, take_headers};
+  let text = "TITLE\nsubtitle\nID,T1\n1,1.0E+00\n2,2.0E+00\nTOTAL,3.0E+00\n";
+  // a title block with another shape only reads as ragged
+  assert!(crate::read_records(text.as_bytes(), b',', "a").is_err());
+  let mut t1 = crate::read_csv(text.as_bytes(), b',', "a", true).unwrap();
+  assert!(crate::check_field_counts(&t1, "a").is_err());
+
+  // the header is the first row left, and lines are still those of the file
+  skip_rows(&mut t1, (2, 1)).unwrap();
+  crate::check_field_counts(&t1, "a").unwrap();
+  let headers = take_headers(&mut t1, false).unwrap();
+  assert_eq!(&headers.header[0], "ID");
+  assert_eq!(t1.len(), 2);
+  assert_eq!(compare::physical_line(&t1, 1), 5);
+
+  let mut t2 = records(&[&["1", "1.0E+00"], &["2", "2.5E+00"]]);
+  let opts = DiffConfig::default();
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!((report.rows, report.max_ratio.line), (2, 5));
+
+  assert_eq!(skip_rows(&mut t2, (2, 1)), Err(2));
+  skip_rows(&mut t2, (1, 1)).unwrap();
+  assert!(t2.is_empty());
+
+  let mut ragged =
+    crate::read_csv("1,2\n3\n".as_bytes(), b',', "c", true).unwrap();
+  match crate::check_field_counts(&ragged, "c") {
+    Err(DiffError::FieldCountChange {
+      line,
+      fields,
+      expected,
+      ..
+    }) => assert_eq!((line, fields, expected), (2, 1, 2)),
+    other => panic!("{other:?}"),
+  }
+  skip_rows(&mut ragged, (0, 1)).unwrap();
+  crate::check_field_counts(&ragged, "c").unwrap();
+}
+
+#[test]
+fn test_trailing_fields() {
+  use crate::error::DiffError;
+  let read = |text: &str| crate::read_csv(text.as_bytes(), b',', "a", true);
+  // every line ends with a delimiter in one export, and empty trailing
+  // fields are left out in the other
+  let trailing = "1,1.0E+00,,\n2,2.0E+00,x,\n3,3.0E+00,,\n";
+  let missing = "1,1.0E+00\n2,2.5E+00,x\n3,3.0E+00\n";
+
+  let mut t1 = read(trailing).unwrap();
+  crate::strip_trailing_empty(&mut t1);
+  let lens: Vec<usize> = t1.iter().map(|r| r.len()).collect();
+  assert_eq!(lens, [2, 3, 2]);
+  assert_eq!(compare::physical_line(&t1, 2), 3);
+
+  let (mut t1, mut t2) = (read(trailing).unwrap(), read(missing).unwrap());
+  crate::pad_fields(&mut t1, &mut t2).unwrap();
+  assert!(t1.iter().chain(&t2).all(|r| r.len() == 4));
+  assert_eq!(&t2[0][3], "");
+  crate::check_field_counts(&t2, "b").unwrap();
+  let opts = DiffConfig::default();
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!((report.max_diff.line, report.max_diff.col), (2, 2));
+
+  // a column only one file has may only hold empty fields; one that holds
+  // data wasn't left out of the other
+  let (mut t1, mut t2) =
+    (read("1,1.0E+00,\n").unwrap(), read("1,1.0E+00\n").unwrap());
+  crate::pad_fields(&mut t1, &mut t2).unwrap();
+  let (mut t1, mut t2) = (
+    read("1,1.0E+00\n2,2.0E+00\n").unwrap(),
+    read(missing).unwrap(),
+  );
+  match crate::pad_fields(&mut t1, &mut t2) {
+    Err(DiffError::ColumnCountMismatch { line, cols1, cols2 }) => {
+      assert_eq!((line, cols1, cols2), (2, 2, 3))
+    }
+    other => panic!("{other:?}"),
+  }
+}
+
+#[test]
+fn test_transpose() {
+  use crate::error::DiffError;
+  use crate::transpose::transpose;
+  // a row per quantity after a title line, and a column per grid point
+  let text = "TITLE\nGRID,1,2,3\nT1,1.0E+00,2.0E+00,3.0E+00\n\
+              T2,4.0E+00,5.0E+00,6.5E+00\n";
+  let mut t1 = crate::read_csv(text.as_bytes(), b',', "a", true).unwrap();
+  // ragged rows can't be transposed
+  assert!(matches!(
+    transpose(&t1, "a"),
+    Err(DiffError::FieldCountChange { line: 2, .. })
+  ));
+  crate::headers::skip_rows(&mut t1, (1, 0)).unwrap();
+  let transposed = transpose(&t1, "a").unwrap();
+  let rows: Vec<Vec<&str>> = transposed
+    .records
+    .iter()
+    .map(|r| r.iter().collect())
+    .collect();
+  assert_eq!(rows[0], ["GRID", "T1", "T2"]);
+  assert_eq!(rows[3], ["3", "3.0E+00", "6.5E+00"]);
+  // a transposed record's line is the column it was
+  assert_eq!(compare::physical_line(&transposed.records, 3), 4);
+  assert_eq!((transposed.line(3), transposed.line(9)), (4, 9));
+
+  let t2 = records(&[
+    &["1", "1.0E+00", "4.0E+00"],
+    &["2", "2.0E+00", "5.0E+00"],
+    &["3", "3.0E+00", "6.0E+00"],
+  ]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_diff: Some(0.1),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let body = &transposed.records[1..];
+  let mut report = compare::compare(body, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!((report.max_diff.line, report.max_diff.col), (4, 3));
+  report.untranspose(&|col| transposed.line(col));
+  // the cell is where T2 of grid 3 was written in the file
+  assert_eq!((report.max_diff.line, report.max_diff.col), (4, 4));
+  assert_eq!(report.columns[1].max_diff.line, 4);
+  assert_eq!(report.diff_mass.worst_line().map(|(_, l)| l), Some(4));
+  assert_eq!(report.diff_mass.worst_col().map(|(_, c)| c), Some(4));
+
+  assert_eq!(transpose(&[], "a").unwrap().records.len(), 0);
+}
+
+#[test]
+fn test_normalize_columns() {
+  use crate::normalize::{ColumnNorms, Normalization, column_norms};
+  let t1 = records(&[
+    &["1", "1.0E+00", "3.0E+00", "0.0E+00"],
+    &["2", "-2.0E+00", "4.0E+00", "0.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1", "2.0E+00", "3.0E+00", "0.0E+00"],
+    &["2", "-4.0E+00", "4.4E+00", "1.0E+00"],
+  ]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  assert_eq!(
+    column_norms(&t1, Normalization::Max, &opts),
+    [0.0, 2.0, 4.0, 0.0]
+  );
+  assert_eq!(
+    column_norms(&t1, Normalization::L2, &opts),
+    [0.0, 5f64.sqrt(), 5.0, 0.0]
+  );
+
+  // the column scaled by 2 fails as it is
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(
+    (report.columns[0].col, report.columns[0].worst_rel),
+    (2, 0.5)
+  );
+
+  // but compares as identical normalized, so that the shape change of the
+  // next stands out; csv1's column of zeros is compared as it is
+  let normalize = ColumnNorms::new(Normalization::Max, (&t1, &t2), &opts);
+  assert_eq!(normalize.norms.1, [0.0, 4.0, 4.4, 1.0]);
+  let opts = DiffConfig {
+    normalize: Some(normalize),
+    ..opts
+  };
+  assert_eq!(opts.factors(1), (0.5, 0.25));
+  assert_eq!(opts.factors(3), (1.0, 1.0));
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report.columns[0].worst_rel, 0.0);
+  let shape = 0.75 - 3.0 / 4.4;
+  assert!((report.columns[1].worst_rel - shape / 0.75).abs() < 1e-12);
+  assert_eq!((report.max_ratio.line, report.max_ratio.col), (2, 4));
+  let spec = reference::compare(&t1, &t2, &opts);
+  assert!(report.discrepancies(&spec).is_empty());
+}
+
+#[test]
+fn test_vector_magnitudes() {
+  use crate::compare::ColumnMap;
+  use crate::error::DiffError;
+  use crate::vectors::append_magnitudes;
+  // the vector rotates a little, and its magnitude stays the same
+  let mut t1 = records(&[
+    &["1", "3.0E+00", "4.0E+00", "0.0E+00", "7.0E+00"],
+    &["2", "1.0E+00", "0.0E+00", "0.0E+00", "8.0E+00"],
+  ]);
+  let mut t2 = records(&[
+    &["1", "4.0E+00", "3.0E+00", "0.0E+00", "7.0E+00"],
+    &["2", "0.0E+00", "6.0E-01", "8.0E-01", "8.0E+00"],
+  ]);
+  append_magnitudes(
+    &mut t1,
+    &[vec![1, 2, 3]],
+    "a",
+    &DiffConfig::default().float_re,
+  )
+  .unwrap();
+  append_magnitudes(
+    &mut t2,
+    &[vec![1, 2, 3]],
+    "b",
+    &DiffConfig::default().float_re,
+  )
+  .unwrap();
+  assert_eq!(t1[0].len(), 6);
+  assert_eq!(t1[0][5].parse::<f64>().unwrap(), 5.0);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(1e-12),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(!report.passed());
+  assert_eq!(report.columns.last().unwrap().col, 6);
+  assert!(report.columns.last().unwrap().worst_rel < 1e-15);
+
+  // without the components, it passes
+  let magnitudes = ColumnMap::new(&[(4, 4), (5, 5)]).unwrap();
+  let opts = DiffConfig {
+    column_map: Some(magnitudes),
+    ..opts
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(report.passed());
+  assert_eq!(report.compared(), 4);
+
+  let mut text = records(&[&["1", "1.0E+00", "x"], &["2", "1.0E+00"]]);
+  for (group, col) in [(vec![1, 2], 3), (vec![1, 3], 4)] {
+    match append_magnitudes(
+      &mut text,
+      &[group],
+      "c",
+      &DiffConfig::default().float_re,
+    ) {
+      Err(DiffError::VectorComponent { line, col: c, .. }) => {
+        assert_eq!((line, c), (1, col))
+      }
+      other => panic!("{other:?}"),
+    }
+  }
+}
+
+#[test]
+fn test_scalars() {
+  use crate::compare::ExtraRows;
+  use crate::error::DiffError;
+  use crate::scalars::{ScalarOutcome, compare_scalars};
+  use std::collections::BTreeMap;
+  let t1 = records(&[
+    &["TOTAL_MASS", "1.0000E+02"],
+    &["EPSILON", "1.0E-12"],
+    &["FREQ_1", "1.0E+01"],
+    &["FREQ_2", "2.0E+01"],
+  ]);
+  let t2 = records(&[
+    &["FREQ_1", "1.1E+01"],
+    &[" TOTAL_MASS ", " 1.0001E+02"],
+    &["EPSILON", "0.0E+00"],
+    &["FREQ_3", "3.0E+01"],
+  ]);
+  let named = BTreeMap::from([(
+    "TOTAL_MASS".to_string(),
+    Tolerances {
+      max_ratio: Some(1e-5),
+      ..Default::default()
+    },
+  )]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.2),
+      ..Default::default()
+    },
+    threshold: 1e-6,
+    ..Default::default()
+  };
+  let compare = |opts: &DiffConfig| {
+    compare_scalars(&t1, &t2, opts, &named, ("a", "b")).unwrap()
+  };
+
+  // matched by name, in csv1's order then csv2's, each against its own
+  // tolerance: the mass fails its own, which the frequency would pass
+  let scalars = compare(&opts);
+  let names: Vec<&str> = scalars.iter().map(|s| s.name.as_str()).collect();
+  assert_eq!(
+    names,
+    ["TOTAL_MASS", "EPSILON", "FREQ_1", "FREQ_2", "FREQ_3"]
+  );
+  let passed: Vec<bool> = scalars.iter().map(|s| s.passed()).collect();
+  assert_eq!(passed, [false, true, true, false, false]);
+  assert_eq!(scalars[0].lines, (Some(1), Some(2)));
+  assert_eq!(scalars[0].values, (Some(100.0), Some(100.01)));
+  assert_eq!(scalars[0].tolerances.max_ratio, Some(1e-5));
+  assert_eq!(scalars[2].tolerances.max_ratio, Some(0.2));
+  let ScalarOutcome::Compared(alone) = &scalars[2].outcome else {
+    panic!("{:?}", scalars[2]);
+  };
+  assert!((alone.ratio_percent() - 10.0).abs() < 1e-9);
+  assert_eq!(scalars[1].outcome, ScalarOutcome::Skipped);
+  assert_eq!(
+    scalars[3].outcome,
+    ScalarOutcome::Missing {
+      file: 2,
+      allowed: false
+    }
+  );
+
+  // names in one file only follow the extra rows policy
+  let allowing = |extra_rows| {
+    let opts = DiffConfig {
+      extra_rows,
+      ..opts.clone()
+    };
+    compare(&opts)
+      .iter()
+      .map(|s| s.passed())
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(allowing(ExtraRows::Allow), [false, true, true, true, true]);
+  let pattern = regex::Regex::new("^FREQ_3,").unwrap();
+  assert_eq!(
+    allowing(ExtraRows::AllowMatching(pattern)),
+    [false, true, true, false, true]
+  );
+
+  let bad = records(&[&["A", "1.0E+00"], &["B", "1.0E+00", "x"]]);
+  let twice = records(&[&["A", "1.0E+00"], &["A", "2.0E+00"]]);
+  for (table, line) in [(&bad, 2), (&twice, 2)] {
+    match compare_scalars(table, &t2, &opts, &named, ("a", "b")) {
+      Err(DiffError::ScalarLayout {
+        line: l, fields, ..
+      }) => {
+        assert_eq!((l, fields), (line, 3))
+      }
+      Err(DiffError::DuplicateScalar { line: l, name, .. }) => {
+        assert_eq!((l, name.as_str()), (line, "A"))
+      }
+      other => panic!("{other:?}"),
+    }
+  }
+}
+
+#[test]
+fn test_fail_fast() {
+  let t1 = records(&[
+    &["1.0E+00", "1.0E+00"],
+    &["1.0E+00", "1.0E+00"],
+    &["1.0E+00", "1.0E+00"],
+    &["1.0E+00", "1.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.0E+00", "1.0E+00"],
+    &["1.1E+00", "1.2E+00"],
+    &["1.0E+00", "1.0E+00"],
+    &["1.5E+00", "1.0E+00"],
+  ]);
+  let report = |fail_fast, column_workers| {
+    let opts = DiffConfig {
+      tolerances: Tolerances {
+        max_ratio: Some(0.01),
+        ..Default::default()
+      },
+      fail_fast,
+      column_workers,
+      ..Default::default()
+    };
+    let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&t1, &t2, &opts));
+    report
+  };
+  let full = report(None, 1);
+  assert_eq!((full.aborted_at, full.rows), (None, 4));
+  assert_eq!(full.max_ratio.line, 4);
+
+  // line 2 has both violations, so the worst cell seen is there
+  for limit in [1, 2] {
+    let early = report(Some(limit), 1);
+    assert_eq!((early.aborted_at, early.rows), (Some(2), 2));
+    assert!(!early.passed());
+    assert_eq!((early.max_ratio.line, early.max_ratio.col), (2, 2));
+    assert_eq!(early.columns[0].compared, 2);
+    assert_eq!(report(Some(limit), 4), early);
+  }
+  // the third violation is on the last line, so nothing is left out
+  assert_eq!(report(Some(3), 1), full);
+}
+
+#[test]
+fn test_failing_cells() {
+  // one wild cell among agreeing ones, then everything drifting a little
+  let wild = records(&[&["1.0E+00", "2.0E+00"], &["1.0E+00", "2.0E+00"]]);
+  let wild2 = records(&[&["1.0E+00", "2.0E+00"], &["1.0E+01", "2.0E+00"]]);
+  let drift = records(&[&["1.03E+00", "2.06E+00"], &["1.03E+00", "2.06E+00"]]);
+  let report = |t2: &[StringRecord], max_fail_fraction| {
+    let opts = DiffConfig {
+      tolerances: Tolerances {
+        max_ratio: Some(0.01),
+        max_diff: Some(0.05),
+        min_digits: Some(2.0),
+        max_fail_fraction,
+        ..Default::default()
+      },
+      column_workers: 2,
+      ..Default::default()
+    };
+    let report = compare::compare(&wild, t2, &opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&wild, t2, &opts));
+    report
+  };
+  let one = report(&wild2, None);
+  assert_eq!(one.compared(), 4);
+  let expected = compare::FailingCells {
+    ratio: 1,
+    diff: 1,
+    digits: 1,
+    allclose: 0,
+    cells: 1,
+  };
+  assert_eq!(one.failing_cells, expected);
+  assert_eq!(one.failing_fraction(Gate::Ratio), 0.25);
+  let all = report(&drift, None);
+  assert_eq!(all.failing_cells.ratio, 4);
+  // the 0.03 drift of column 1 is within -d
+  assert_eq!(all.failing_cells.diff, 2);
+  // each counted once, whichever tolerances they exceed
+  assert_eq!(all.failing_cells.cells, 4);
+  assert!(!one.passed() && !all.passed());
+
+  // a quarter of the cells failing is few enough for 30%, but not for 25%
+  assert!(report(&wild2, Some(0.3)).passed());
+  assert!(!report(&wild2, Some(0.25)).passed());
+  let drifted = report(&drift, Some(0.3));
+  let verdicts: Vec<_> = drifted.gates.iter().map(|g| g.passed).collect();
+  assert_eq!(verdicts, [false, false, false]);
+  let drifted = report(&drift, Some(0.6));
+  let verdicts: Vec<_> = drifted.gates.iter().map(|g| g.passed).collect();
+  assert_eq!(verdicts, [false, true, false]);
+}
+
+#[test]
+fn test_dedup_headers() {
+  use crate::headers::take_headers;
+  let text = b"id,x\n1,1.0E+00\nid,x\n2,2.0E+00\nid,x\n3,3.0E+00\n";
+  let read = || crate::read_records(&text[..], b',', "a").unwrap();
+
+  // without dedup, only the first row goes and the repeats break detection
+  let mut plain = read();
+  let headers = take_headers(&mut plain, false).unwrap();
+  assert_eq!(headers.header, StringRecord::from(vec!["id", "x"]));
+  assert!(headers.repeats.is_empty());
+  assert_eq!(plain.len(), 5);
+
+  let mut t1 = read();
+  assert_eq!(take_headers(&mut t1, true).unwrap().repeats, [3, 5]);
+  assert_eq!(t1.len(), 3);
+  assert_eq!(take_headers(&mut Vec::new(), true), None);
+
+  // lines stay physical in the report
+  let mut t2 = read();
+  take_headers(&mut t2, true);
+  t2[2] = StringRecord::from(vec!["3", "3.3E+00"]);
+  t2[2].set_position(t1[2].position().cloned());
+  let report =
+    compare::compare(&t1, &t2, &DiffConfig::default(), ("a", "b")).unwrap();
+  assert_eq!(report.columns.len(), 1);
+  assert_eq!((report.max_diff.line, report.max_diff.col), (6, 2));
+  assert_eq!(report, reference::compare(&t1, &t2, &DiffConfig::default()));
+}
+
+#[test]
+fn test_assertions() {
+  use crate::assertions::{Assertions, Compared};
+  let assertions: Assertions = toml::from_str(
+    "column_exists = [\"VONMISES\", \"SHEAR\"]\nmin_rows_compared = 3\n\
+     group_present = [{ column = \"SUBCASE\", value = \"101\" },\n\
+     { column = \"1\", value = \"102\" }]\n\
+     [[cell_within]]\nkey = \"EID\"\nvalue = \"40012\"\n\
+     column = \"VONMISES\"\nmax_ratio = 0.001\n\
+     [[cell_within]]\nkey = \"EID\"\nvalue = \"40013\"\n\
+     column = \"VONMISES\"\nmax_diff = 1.0\n",
+  )
+  .unwrap();
+  assert_eq!(assertions.len(), 7);
+  assert!(toml::from_str::<Assertions>("min_rows = 3").is_err());
+  let header1 = StringRecord::from(vec!["SUBCASE", "EID", "VONMISES"]);
+  let header2 = StringRecord::from(vec!["SUBCASE", "EID", "VON MISES"]);
+  let t1 = records(&[
+    &["101", "40012", "1.0000E+02"],
+    &["101", "40013", "2.0000E+02"],
+  ]);
+  let t2 = records(&[
+    &["101.0", "40013", "2.0050E+02"],
+    &["101", "40012", "1.0050E+02"],
+  ]);
+  let opts = DiffConfig::default();
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert!(report.passed());
+  let failures = assertions.check(&Compared {
+    records: (&t1, &t2),
+    headers: Some((&header1, &header2)),
+    opts: &opts,
+    report: &report,
+  });
+  let failed: Vec<(&str, &str)> = failures
+    .iter()
+    .map(|f| (f.assertion.as_str(), f.reason.as_str()))
+    .collect();
+  assert_eq!(
+    failed,
+    [
+      ("column VONMISES exists", "csv2's header lacks it"),
+      ("column SHEAR exists", "neither header has it"),
+      ("at least 3 rows are compared", "only 2 were"),
+      ("a row has 1 = 102", "no row of csv1 or csv2 has it"),
+      (
+        "VONMISES at EID = 40012 is within 0.1000%",
+        "+1.000000E2 and +1.005000E2 differ by 0.5000%"
+      ),
+    ]
+  );
+
+  // without headers, columns go by number only
+  let by_name: Assertions =
+    toml::from_str("group_present = [{ column = \"EID\", value = \"1\" }]")
+      .unwrap();
+  let failures = by_name.check(&Compared {
+    records: (&t1, &t2),
+    headers: None,
+    opts: &opts,
+    report: &report,
+  });
+  assert_eq!(failures[0].reason, "column EID needs a header");
+  let mut report = report;
+  report.assertion_failures = failures;
+  assert!(!report.passed());
+}
+
+#[test]
+fn test_complex_pairs() {
+  use crate::complex::{ComplexPair, compare_pairs, phase_difference};
+  use crate::vectors::append_magnitudes;
+  assert!((phase_difference(179.9, -179.9) - 0.2).abs() < 1e-9);
+  assert!((phase_difference(-179.9, 179.9) - 0.2).abs() < 1e-9);
+  assert_eq!(phase_difference(90.0, -90.0), 180.0);
+  // the same response on either side of the wrap-around, a response whose
+  // phase moves by 1 degree, and one too small for its phase to matter
+  let mut t1 = records(&[
+    &["1", "-1.0E+00", "1.0E-03"],
+    &["2", "1.0E+00", "0.0E+00"],
+    &["3", "1.0E-09", "0.0E+00"],
+  ]);
+  let mut t2 = records(&[
+    &["1", "-1.0E+00", "-1.0E-03"],
+    &["2", "9.998477E-01", "1.745241E-02"],
+    &["3", "-1.0E-09", "0.0E+00"],
+  ]);
+  append_magnitudes(
+    &mut t1,
+    &[vec![1, 2]],
+    "a",
+    &DiffConfig::default().float_re,
+  )
+  .unwrap();
+  append_magnitudes(
+    &mut t2,
+    &[vec![1, 2]],
+    "b",
+    &DiffConfig::default().float_re,
+  )
+  .unwrap();
+  let pair = ComplexPair {
+    re: (1, 1),
+    im: (2, 2),
+    magnitude: (3, 3),
+  };
+  let opts = DiffConfig {
+    threshold: 1e-6,
+    ..Default::default()
+  };
+  let phases = compare_pairs((&t1, &t2), &[pair], 3, &opts, Some(0.5));
+  let deviations = phases.pairs[0];
+  assert_eq!(deviations.compared, 2);
+  assert_eq!(deviations.failing, 1);
+  assert_eq!(deviations.phase.line, 2);
+  assert!((deviations.phase.value - 1.0).abs() < 1e-4);
+  assert!(deviations.magnitude.value < 1e-6);
+  assert!(!phases.passed());
+
+  let mut report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  report.complex = Some(phases);
+  assert!(!report.passed());
+  let phases = compare_pairs((&t1, &t2), &[pair], 3, &opts, Some(1.5));
+  report.complex = Some(phases);
+  assert!(report.passed());
+}
+
+#[test]
+fn test_profile_phases() {
+  use std::io::Read;
+  use std::time::Duration;
+
+  use crate::profile::{Phase, PhaseTimes, SAMPLE_EVERY, Timed};
+  let mut timed = Timed::new(&b"1,2\n"[..]);
+  let mut text = String::new();
+  timed.read_to_string(&mut text).unwrap();
+  assert_eq!(text, "1,2\n");
+
+  let rows: Vec<Vec<String>> = (0..SAMPLE_EVERY * 3 + 1)
+    .map(|row| vec![row.to_string(), format!("{}.0E+00", row % 10)])
+    .collect();
+  let rows: Vec<Vec<&str>> = rows
+    .iter()
+    .map(|r| r.iter().map(String::as_str).collect())
+    .collect();
+  let rows: Vec<&[&str]> = rows.iter().map(|r| &r[..]).collect();
+  let t1 = records(&rows);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t1, &opts, ("a", "b")).unwrap();
+  let (profiled, times) =
+    compare::compare_profiled(&t1, &t1, &opts, ("a", "b")).unwrap();
+  assert_eq!(profiled, report);
+  for phase in [Phase::Classifying, Phase::FloatParsing, Phase::Comparing] {
+    assert!(times.get(phase) > Duration::ZERO, "{}", phase.name());
+  }
+  for phase in [Phase::Reading, Phase::Parsing, Phase::Reporting] {
+    assert_eq!(times.get(phase), Duration::ZERO, "{}", phase.name());
+  }
+
+  let mut total = PhaseTimes::default();
+  total.merge(&times);
+  total.add(Phase::Reading, Duration::from_secs(1));
+  assert_eq!(total.total(), times.total() + Duration::from_secs(1));
+}
+
+#[test]
+fn test_match_rows_nearest() {
+  use crate::error::DiffError;
+  use crate::nearest::match_nearest;
+  // modes 3 and 4 swap places, and csv2 has a mode more
+  let mut t1 = records(&[
+    &["1", "1.000E+01"],
+    &["2", "2.000E+01"],
+    &["3", "3.000E+01"],
+    &["4", "3.001E+01"],
+  ]);
+  let mut t2 = records(&[
+    &["1", "1.000E+01"],
+    &["2", "3.001E+01"],
+    &["3", "2.000E+01"],
+    &["4", "3.000E+01"],
+    &["5", "9.000E+01"],
+  ]);
+  let matching =
+    match_nearest(("a", &mut t1), ("b", &mut t2), (1, 1), 2).unwrap();
+  assert_eq!(matching.reordered, [(2, 3), (3, 4), (4, 2)]);
+  assert_eq!(matching.unpaired, (vec![], vec![5]));
+  let firsts: Vec<&str> = t2.iter().map(|r| &r[0]).collect();
+  assert_eq!(firsts, ["1", "3", "4", "2"]);
+  let report = compare::compare(&t1, &t2, &DiffConfig::default(), ("a", "b"));
+  assert_eq!(report.unwrap().max_diff.value, 0.0);
+
+  // without a window, rows pair by position, however far apart their values
+  let mut t1 = records(&[&["1.0E+00"], &["2.0E+00"], &["3.0E+00"]]);
+  let mut t2 = records(&[&["3.0E+00"], &["2.0E+00"]]);
+  let matching =
+    match_nearest(("a", &mut t1), ("b", &mut t2), (0, 0), 0).unwrap();
+  assert_eq!(matching.reordered, []);
+  assert_eq!(matching.unpaired, (vec![3], vec![]));
+  assert_eq!((t1.len(), t2.len()), (2, 2));
+  let mut t2 = records(&[&["3.0E+00"], &["2.0E+00"], &["9.0E+00"]]);
+  let mut t1 = records(&[&["1.0E+00"], &["2.0E+00"], &["x"]]);
+  match match_nearest(("a", &mut t1), ("b", &mut t2), (0, 0), 0) {
+    Err(DiffError::MatchKey { line, col, .. }) => {
+      assert_eq!((line, col), (3, 1))
+    }
+    other => panic!("{other:?}"),
+  }
+}
+
+/// Two files of element results keyed by element ID, with element 102 split
+/// across two rows of csv1, 101.0 written differently, and 105 and 106 in
+/// only one file each.
+fn keyed_files() -> (Vec<StringRecord>, Vec<StringRecord>) {
+  let t1 = records(&[
+    &["101", "q", "1.0E+00"],
+    &["102", "a", "2.0E+00"],
+    &["103", "q", "3.0E+00"],
+    &["102", "b", "5.0E+00"],
+    &["106", "q", "6.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["103", "q", "3.0E+00"],
+    &["1.01E+02", "q", "1.0E+00"],
+    &["102", "a", "7.0E+00"],
+    &["105", "q", "9.0E+00"],
+  ]);
+  (t1, t2)
+}
+
+#[test]
+fn test_key_reconciliation() {
+  use crate::error::DiffError;
+  use crate::keys::reconcile;
+  let (mut t1, mut t2) = keyed_files();
+  let matching = reconcile(("a", &mut t1), ("b", &mut t2), (0, 0)).unwrap();
+  let statuses: Vec<(&str, &str)> = matching
+    .keys
+    .iter()
+    .map(|k| (k.key.as_str(), k.status()))
+    .collect();
+  assert_eq!(
+    statuses,
+    [
+      ("101", "matched"),
+      ("102", "duplicated"),
+      ("103", "matched"),
+      ("106", "only_in_1"),
+      ("105", "only_in_2"),
+    ]
+  );
+  // each duplicate with all its lines, in both files
+  let duplicated: Vec<_> = matching.duplicated().collect();
+  assert_eq!(duplicated.len(), 1);
+  assert_eq!(
+    (&duplicated[0].lines1[..], &duplicated[0].lines2[..]),
+    (&[2, 4][..], &[3][..])
+  );
+  assert_eq!(matching.unpaired(), (vec![5], vec![4]));
+
+  let mut t2 = records(&[&["101", "q", "1.0E+00"], &[]]);
+  match reconcile(("a", &mut t1), ("b", &mut t2), (0, 0)) {
+    Err(DiffError::KeyMissing { file, line, col }) => {
+      assert_eq!((file.as_str(), line, col), ("b", 2, 1))
+    }
+    other => panic!("{other:?}"),
+  }
+}
+
+/// The rows of two keyed files paired under a duplicate keys policy, and
+/// their comparison.
+fn paired_by_key(
+  duplicates: crate::keys::Duplicates,
+) -> (Vec<StringRecord>, Vec<StringRecord>, compare::DiffReport) {
+  use crate::keys::{pair, reconcile};
+  let (mut t1, mut t2) = keyed_files();
+  let matching = reconcile(("a", &mut t1), ("b", &mut t2), (0, 0)).unwrap();
+  let float_re = regex::Regex::new(compare::FLOAT_PATTERN).unwrap();
+  pair((&mut t1, &mut t2), &matching, duplicates, &float_re);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_diff: Some(0.5),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report, reference::compare(&t1, &t2, &opts));
+  (t1, t2, report)
+}
+
+#[test]
+fn test_duplicate_keys_error() {
+  use crate::keys::Duplicates;
+  // the policy callers fail on; pairing anyway takes the first rows, as
+  // first does, so nothing would be summed behind their back
+  let (t1, _, report) = paired_by_key(Duplicates::Error);
+  let (first1, _, first) = paired_by_key(Duplicates::First);
+  assert_eq!((t1, report), (first1, first));
+  assert_eq!("ERROR".parse::<Duplicates>(), Ok(Duplicates::Error));
+  assert!("last".parse::<Duplicates>().is_err());
+}
+
+#[test]
+fn test_duplicate_keys_first() {
+  use crate::keys::Duplicates;
+  let (t1, t2, report) = paired_by_key(Duplicates::First);
+  // in csv1's order, without the keys only one file has
+  let keys: Vec<(&str, &str)> = t1
+    .iter()
+    .zip(&t2)
+    .map(|(r1, r2)| (&r1[0], &r2[0]))
+    .collect();
+  assert_eq!(keys, [("101", "1.01E+02"), ("102", "102"), ("103", "103")]);
+  // element 102's first row, 2.0 against 7.0, at its line in csv1
+  assert_eq!(report.max_diff.vals, (2.0, 7.0));
+  assert_eq!((report.max_diff.line, report.max_diff.col), (2, 3));
+  assert!(!report.passed());
+}
+
+#[test]
+fn test_duplicate_keys_sum() {
+  use crate::keys::Duplicates;
+  let (t1, t2, report) = paired_by_key(Duplicates::Sum);
+  // element 102's rows summed, 2.0 and 5.0 making csv2's 7.0; its key
+  // and its text are the first row's
+  let row: Vec<&str> = t1[1].iter().collect();
+  assert_eq!(row, ["102", "a", "7E0"]);
+  assert_eq!(t2.len(), 3);
+  assert_eq!(report.max_diff.value, 0.0);
+  assert!(report.passed());
+  assert_eq!(compare::physical_line(&t1, 1), 2);
+}
+
+#[test]
+fn test_effective_columns() {
+  use crate::compare::SignFlip;
+  use crate::effective::{ColumnOptions, Role, Source, resolve};
+  let plain = ColumnOptions {
+    widths: (6, 6),
+    pairing: (Source::Position, (0..6).map(Some).collect()),
+    vector_groups: vec![],
+    vector_only: false,
+    complex_pairs: vec![],
+    sign_flip: SignFlip::Never,
+    normalize: false,
+    keys: vec![],
+  };
+  // csv2 has no partner for column 2
+  let mapped = |options: ColumnOptions| ColumnOptions {
+    pairing: (
+      Source::ColMap,
+      vec![Some(0), None, Some(3), Some(2), Some(4), Some(5)],
+    ),
+    ..options
+  };
+
+  let columns = resolve(&ColumnOptions {
+    vector_groups: vec![vec![4, 5]],
+    vector_only: true,
+    complex_pairs: vec![(2, 3)],
+    sign_flip: SignFlip::Columns(vec![0]),
+    normalize: true,
+    keys: vec![(0, Source::SortBy)],
+    ..mapped(plain.clone())
+  })
+  .unwrap();
+  let roles: Vec<(usize, Option<usize>, Role)> =
+    columns.iter().map(|c| (c.col, c.col2, c.role)).collect();
+  assert_eq!(
+    roles,
+    [
+      (1, Some(1), Role::Compared),
+      (2, None, Role::Unpaired),
+      (3, Some(4), Role::ComplexPart),
+      (4, Some(3), Role::ComplexPart),
+      (5, Some(5), Role::Component),
+      (6, Some(6), Role::Component),
+      (7, Some(7), Role::Magnitude),
+      (8, Some(8), Role::Magnitude),
+    ]
+  );
+  assert_eq!(
+    columns[0].sources,
+    [
+      Source::ColMap,
+      Source::SignFlipCols,
+      Source::SortBy,
+      Source::Normalize
+    ]
+  );
+  assert!(columns[0].sign_flip && columns[0].key && columns[0].normalized);
+  assert_eq!(
+    columns[4].sources,
+    [Source::ColMap, Source::VectorCols, Source::VectorOnly]
+  );
+  assert!(!columns[4].normalized);
+  assert_eq!(
+    columns[7].sources,
+    [Source::ComplexPairs, Source::Normalize]
+  );
+  let flipped = resolve(&ColumnOptions {
+    sign_flip: SignFlip::AnyColumn,
+    complex_pairs: vec![(2, 3)],
+    ..plain.clone()
+  })
+  .unwrap();
+  let flips: Vec<bool> = flipped.iter().map(|c| c.sign_flip).collect();
+  assert_eq!(flips, [true, true, false, false, true, true, true]);
+
+  let unpaired = (Source::ColMap, "left unpaired");
+  let component = (Source::VectorCols, "made a vector component");
+  let part = (Source::ComplexPairs, "made a complex part");
+  let flip = (Source::SignFlipCols, "allowed a sign flip");
+  let left_out = "left out of the comparison";
+  let conflicts = [
+    (
+      ColumnOptions {
+        complex_pairs: vec![(2, 3), (3, 4)],
+        ..plain.clone()
+      },
+      4,
+      part,
+      part,
+    ),
+    (
+      ColumnOptions {
+        complex_pairs: vec![(2, 2)],
+        ..plain.clone()
+      },
+      3,
+      part,
+      part,
+    ),
+    (
+      ColumnOptions {
+        vector_groups: vec![vec![3, 4, 3]],
+        ..plain.clone()
+      },
+      4,
+      component,
+      component,
+    ),
+    (
+      ColumnOptions {
+        vector_groups: vec![vec![3, 4]],
+        complex_pairs: vec![(2, 3)],
+        ..plain.clone()
+      },
+      4,
+      component,
+      part,
+    ),
+    (
+      ColumnOptions {
+        vector_groups: vec![vec![0, 1]],
+        ..mapped(plain.clone())
+      },
+      2,
+      unpaired,
+      component,
+    ),
+    (
+      ColumnOptions {
+        complex_pairs: vec![(1, 2)],
+        ..mapped(plain.clone())
+      },
+      2,
+      unpaired,
+      part,
+    ),
+    (
+      ColumnOptions {
+        sign_flip: SignFlip::Columns(vec![1]),
+        ..mapped(plain.clone())
+      },
+      2,
+      unpaired,
+      flip,
+    ),
+    (
+      ColumnOptions {
+        complex_pairs: vec![(2, 3)],
+        sign_flip: SignFlip::Columns(vec![3]),
+        ..plain.clone()
+      },
+      4,
+      (Source::ComplexPairs, left_out),
+      flip,
+    ),
+    (
+      ColumnOptions {
+        vector_groups: vec![vec![4, 5]],
+        vector_only: true,
+        sign_flip: SignFlip::Columns(vec![5]),
+        ..plain.clone()
+      },
+      6,
+      (Source::VectorOnly, left_out),
+      flip,
+    ),
+    (
+      ColumnOptions {
+        keys: vec![(1, Source::MatchRowsNearest)],
+        ..mapped(plain.clone())
+      },
+      2,
+      unpaired,
+      (Source::MatchRowsNearest, "made a key"),
+    ),
+  ];
+  for (options, col, first, second) in conflicts {
+    let conflict = resolve(&options).unwrap_err();
+    assert_eq!(
+      (conflict.col, conflict.first, conflict.second),
+      (col, first, second)
+    );
+  }
+  let conflict = resolve(&ColumnOptions {
+    complex_pairs: vec![(2, 3)],
+    sign_flip: SignFlip::Columns(vec![2]),
+    ..plain
+  })
+  .unwrap_err();
+  assert_eq!(
+    conflict.to_string(),
+    "column 3 is left out of the comparison by --complex-pairs, but allowed \
+     a sign flip by --allow-sign-flip-cols"
+  );
+}
+
+#[test]
+fn test_mac_match() {
+  use crate::mac::{mac, match_columns};
+  assert_eq!(mac(&[1.0, 2.0], &[-2.0, -4.0]), 1.0);
+  assert_eq!(mac(&[1.0, 0.0], &[0.0, 3.0]), 0.0);
+  assert_eq!(mac(&[0.0, 0.0], &[0.0, 0.0]), 0.0);
+
+  // modes 1 and 2 swap columns, mode 1 flips sign, and mode 3 changes shape
+  let t1 = records(&[
+    &["1", "1.0E+00", "0.0E+00", "5.0E-01"],
+    &["2", "0.0E+00", "1.0E+00", "5.0E-01"],
+    &["3", "-1.0E+00", "1.0E+00", "1.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1", "0.0E+00", "-1.0E+00", "9.0E-01"],
+    &["2", "1.0E+00", "0.0E+00", "-1.0E-01"],
+    &["3", "1.0E+00", "1.0E+00", "2.0E-01"],
+  ]);
+  let matching =
+    match_columns((&t1, &t2), 0.9, &DiffConfig::default().float_re);
+  assert_eq!(matching.matrix.cols, (vec![1, 2, 3], vec![1, 2, 3]));
+  assert_eq!(matching.matrix.values[0][1], 1.0);
+  assert_eq!(matching.matrix.values[2][0], 0.75);
+  let pairs: Vec<(usize, usize, bool)> = matching
+    .pairs
+    .iter()
+    .map(|p| (p.col1, p.col2, p.opposed))
+    .collect();
+  assert_eq!(pairs, [(1, 2, true), (2, 1, false)]);
+  assert_eq!(matching.unpaired, (vec![3], vec![3]));
+  let matching =
+    match_columns((&t1, &t2), 0.25, &DiffConfig::default().float_re);
+  assert_eq!(matching.pairs[2].col2, 3);
+  assert_eq!(matching.unpaired, (vec![], vec![]));
+
+  // among columns as alike, the nearest in place pair first
+  let t = records(&[&["1.0E+00", "1.0E+00", "1.0E+00"]]);
+  let pairs: Vec<(usize, usize)> =
+    match_columns((&t, &t), 0.9, &DiffConfig::default().float_re)
+      .pairs
+      .iter()
+      .map(|p| (p.col1, p.col2))
+      .collect();
+  assert_eq!(pairs, [(0, 0), (1, 1), (2, 2)]);
+}
+
+#[test]
+fn test_group_by() {
+  use crate::error::DiffError;
+  use crate::groups::compare_groups;
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.05),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  // csv2 interleaves the subcases; subcase 2 regressed
+  let t1 = records(&[
+    &["1", "1.0E+00"],
+    &["1", "2.0E+00"],
+    &["2", "3.0E+00"],
+    &["2", "4.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1", "1.0E+00"],
+    &["2", "3.3E+00"],
+    &[" 1", "2.0E+00"],
+    &["2", "4.0E+00"],
+  ]);
+  let groups = compare_groups((&t1, &t2), (0, 0), &opts, ("a", "b")).unwrap();
+  let summary: Vec<(&str, (usize, usize), usize, bool)> = groups
+    .iter()
+    .map(|g| (&g.value[..], g.rows, g.report.compared(), g.report.passed()))
+    .collect();
+  assert_eq!(summary, [("1", (2, 2), 2, true), ("2", (2, 2), 2, false)]);
+  // a group's rows keep their lines
+  assert_eq!(groups[1].report.max_ratio.line, 3);
+  assert!((groups[1].report.ratio_percent() - 10.0).abs() < 1e-9);
+  let mut report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  report.groups = groups;
+  assert!(!report.passed());
+
+  let t3 = records(&[&["1", "1.0E+00"], &["3", "2.0E+00"]]);
+  match compare_groups((&t1, &t3), (0, 0), &opts, ("a", "c")) {
+    Err(DiffError::GroupMissing { group, file, .. }) => {
+      assert_eq!((&group[..], &file[..]), ("3", "c"))
+    }
+    other => panic!("{other:?}"),
+  }
+  match compare_groups((&t3, &t1), (0, 0), &opts, ("c", "a")) {
+    Err(DiffError::GroupMissing { group, file, .. }) => {
+      assert_eq!((&group[..], &file[..]), ("2", "a"))
+    }
+    other => panic!("{other:?}"),
+  }
+  match compare_groups((&t1, &t2), (2, 2), &opts, ("a", "b")) {
+    Err(DiffError::GroupKeyMissing { line, col, .. }) => {
+      assert_eq!((line, col), (1, 3))
+    }
+    other => panic!("{other:?}"),
+  }
+}
+
+#[test]
+fn test_shift_rows() {
+  use crate::shift::{Excluded, best_shift, shift_rows};
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  // csv1 writes t=0 and the first step, csv2 starts two steps later
+  let step = |t: u32| vec![format!("{t}.0E-01"), format!("{}.0E+00", t * t)];
+  let rows = |range: std::ops::Range<u32>| -> Vec<StringRecord> {
+    range.map(|t| StringRecord::from(step(t))).collect()
+  };
+  let (t1, t2) = (rows(0..6), rows(2..9));
+  let shifted = |shift: isize| {
+    let (mut r1, mut r2) = (t1.clone(), t2.clone());
+    let excluded = shift_rows(&mut r1, &mut r2, shift);
+    let report = compare::compare(&r1, &r2, &opts, ("a", "b")).unwrap();
+    (excluded, report.passed())
+  };
+  for shift in [-2, -1, 0, 1, 3] {
+    assert!(!shifted(shift).1, "shift {shift}");
+  }
+  let (excluded, ok) = shifted(2);
+  assert!(ok);
+  assert_eq!(
+    excluded,
+    Excluded {
+      start: (2, 0),
+      end: (0, 3)
+    }
+  );
+  // lines are each file's own
+  let (mut r1, mut r2) = (t1.clone(), t2.clone());
+  shift_rows(&mut r1, &mut r2, -1);
+  let report = compare::compare(&r1, &r2, &opts, ("a", "b")).unwrap();
+  let worst = report.max_ratio;
+  assert_eq!((worst.line, worst.vals.0), (1, 0.0));
+  assert_eq!(compare::physical_line(&r2, 0), 2);
+
+  let best = best_shift(("a", &t1), ("b", &t2), (0, 0), 3).unwrap();
+  assert_eq!((best.shift, best.mean_diff), (2, 0.0));
+  // a shift out of reach is never found
+  assert_ne!(
+    best_shift(("a", &t1), ("b", &t2), (0, 0), 1).unwrap().shift,
+    2
+  );
+}
+
+#[test]
+fn test_percent_columns() {
+  use crate::percent::{self, Convention, OutOfRange, PercentColumns};
+  // an efficiency in column 2, as percent in csv1 and as fractions in csv2
+  let r1 = records(&[&["1.0E+00", "7.35E+01"], &["2.0E+00", "5.0E+00"]]);
+  let r2 = records(&[&["1.0E+00", "7.35E-01"], &["2.0E+00", "5.0E-02"]]);
+  let inferred1 = percent::infer(&r1, &[1], &DiffConfig::default().float_re);
+  let inferred2 = percent::infer(&r2, &[1], &DiffConfig::default().float_re);
+  assert_eq!(
+    (inferred1.convention, inferred1.largest),
+    (Convention::Percent, 73.5)
+  );
+  assert_eq!(inferred2.convention, Convention::Fraction);
+
+  let opts = |compared_as| DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(1e-6),
+      ..Default::default()
+    },
+    percent: Some(PercentColumns {
+      cols: vec![1],
+      conventions: (inferred1.convention, inferred2.convention),
+      compared_as,
+    }),
+    ..Default::default()
+  };
+  let percent = opts(Convention::Percent).percent.unwrap();
+  assert_eq!(percent.factors(0), (1.0, 1.0));
+  assert_eq!(percent.factors(1), (1.0, 100.0));
+  for compared_as in [Convention::Percent, Convention::Fraction] {
+    let report =
+      compare::compare(&r1, &r2, &opts(compared_as), ("a", "b")).unwrap();
+    assert!(report.passed(), "{compared_as:?}");
+  }
+  // without the conversion, they're 100x apart
+  let unconverted = DiffConfig {
+    percent: None,
+    ..opts(Convention::Percent)
+  };
+  let report = compare::compare(&r1, &r2, &unconverted, ("a", "b")).unwrap();
+  assert!(!report.passed());
+
+  // a fraction above 1 is out of range once converted
+  let r3 = records(&[&["1.0E+00", "1.5E+00"], &["2.0E+00", "-1.0E-02"]]);
+  assert_eq!(
+    percent::out_of_range(
+      &r3,
+      &[1],
+      (100.0, Convention::Percent),
+      &DiffConfig::default().float_re
+    ),
+    vec![
+      OutOfRange {
+        line: 1,
+        col: 2,
+        value: 150.0
+      },
+      OutOfRange {
+        line: 2,
+        col: 2,
+        value: -1.0
+      },
+    ]
+  );
+  assert!(
+    percent::out_of_range(
+      &r1,
+      &[1],
+      (1.0, Convention::Percent),
+      &DiffConfig::default().float_re
+    )
+    .is_empty()
+  );
+}
+
+#[test]
+fn test_column_overrides() {
+  use crate::overrides::{compare_overrides, without};
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      max_diff: Some(10.0),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  // column 3 is 4% off, within its own 5%
+  let r1 =
+    records(&[&["1", "1.0E+00", "1.00E+02"], &["2", "2.0E+00", "2.0E+02"]]);
+  let r2 =
+    records(&[&["1", "1.0E+00", "1.04E+02"], &["2", "2.0E+00", "2.0E+02"]]);
+  let report = compare::compare(&r1, &r2, &opts, ("a", "b")).unwrap();
+  assert!(!report.passed());
+  let others = without(&opts, &[2], 3);
+  let report = compare::compare(&r1, &r2, &others, ("a", "b")).unwrap();
+  assert!(report.passed());
+  assert_eq!(report.compared(), 2);
+
+  let own = |max_ratio| Tolerances {
+    max_ratio: Some(max_ratio),
+    ..Default::default()
+  };
+  let columns =
+    compare_overrides((&r1, &r2), &[(2, own(0.05))], &opts, ("a", "b"))
+      .unwrap();
+  assert_eq!(columns.len(), 1);
+  assert_eq!(columns[0].col, 3);
+  assert!(columns[0].report.passed());
+  assert_eq!(columns[0].report.compared(), 2);
+  // the gates they leave unset are the others'
+  assert_eq!(columns[0].tolerances.max_diff, Some(10.0));
+  assert!((columns[0].report.ratio_percent() - 4.0).abs() < 1e-9);
+  let columns =
+    compare_overrides((&r1, &r2), &[(2, own(0.03))], &opts, ("a", "b"))
+      .unwrap();
+  assert!(!columns[0].report.passed());
+}
+
+#[test]
+fn test_progress() {
+  use crate::progress::Progress;
+  use std::sync::Arc;
+
+  let path = std::env::temp_dir()
+    .join(format!("f06csvdiff-{}-progress.csv", std::process::id()));
+  let text = "1,1.0E+00,2.0E+00\n2,2.0E+00,3.0E+00\n3,3.0E+00,4.0E+00\n";
+  std::fs::write(&path, text).unwrap();
+  let name = path.to_str().unwrap();
+
+  // every byte of the file is counted as it's read
+  let progress = Arc::new(Progress::default());
+  let (records, _) =
+    crate::read_file_counted(name, b',', Default::default(), &progress)
+      .unwrap();
+  std::fs::remove_file(&path).unwrap();
+  assert_eq!(records.len(), 3);
+  assert_eq!(progress.bytes(), text.len() as u64);
+  assert_eq!(progress.rows(), 0);
+
+  // and each row compared once, however many workers share the columns
+  for workers in [1, 2] {
+    let progress = Arc::new(Progress::default());
+    let opts = DiffConfig {
+      column_workers: workers,
+      progress: Some(Arc::clone(&progress)),
+      ..Default::default()
+    };
+    compare::compare(&records, &records, &opts, ("a", "b")).unwrap();
+    assert_eq!(progress.rows(), 3, "with {workers} workers");
+  }
+}
+
+#[test]
+fn test_wide_record() {
+  use std::time::{Duration, Instant};
+
+  // one record of floats, a couple of megabytes wide
+  let cells: Vec<String> = (0..150_000)
+    .map(|col| format!("{:.6E}", (col % 9973) as f64 / 997.0 + 1.0))
+    .collect();
+  let line = cells.join(",") + "\n";
+  let megabytes = line.len() as f64 / 1e6;
+  let start = Instant::now();
+  let wide = crate::read_records(line.as_bytes(), b',', "wide").unwrap();
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(1e-3),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let report = compare::compare(&wide, &wide, &opts, ("a", "b")).unwrap();
+  let elapsed = start.elapsed();
+  assert_eq!(report.columns.len(), cells.len());
+  assert!(report.passed());
+  // generous even for a debug build; quadratic work would take hours
+  let budget = Duration::from_secs_f64(5.0 * megabytes.max(1.0));
+  assert!(elapsed < budget, "{elapsed:?} for {megabytes:.1} MB");
+
+  // a cell too long to be a float is text, however it's written
+  let long = format!("{}E+00", "1".repeat(compare::MAX_FLOAT_LEN));
+  let records = records(&[&["1.0E+00", &long]]);
+  let report = compare::compare(&records, &records, &opts, ("a", "b")).unwrap();
+  assert_eq!(report.columns.len(), 1);
+}
+
+#[test]
+fn test_f06_extraction() {
+  use crate::extract::{extract, is_f06};
+  use nas_csv::layout::CsvBlockId;
+
+  let path = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../examples/scnastran/SB-BUSH-01-OFFSET-2b_scnas_postexport.f06"
+  );
+  assert!(is_f06(path) && is_f06("RUN.F06") && !is_f06("run.csv"));
+
+  // the block's rows, as f06csv writes them
+  let records = extract(path, CsvBlockId::Displacements, false).unwrap();
+  assert_eq!(records.len(), 2);
+  assert_eq!(
+    records[1].iter().collect::<Vec<_>>(),
+    [
+      "1",
+      "2",
+      "1",
+      "+3.190000E+00",
+      "+3.733333E+00",
+      "+3.555000E+00",
+      "+7.500000E-02",
+      "-4.600000E-01",
+      "+4.166667E-01",
+      "0",
+      "-----"
+    ]
+  );
+  assert_eq!(crate::compare::physical_line(&records, 1), 2);
+
+  // with its header first, if asked for
+  let headed = extract(path, CsvBlockId::Displacements, true).unwrap();
+  assert_eq!(headed.len(), 3);
+  assert_eq!(&headed[0][0], "Displacements");
+  assert_eq!(headed[1..], records[..]);
+
+  // a block it doesn't have is an extraction error naming those it has
+  let missing = extract(path, CsvBlockId::Eigenvalues, false).unwrap_err();
+  let message = missing.to_string();
+  assert!(message.starts_with("extracting the Eigenvalues block of"));
+  assert!(message.contains("only disp"), "{message}");
+}
+
+#[test]
+fn test_matrix_norms() {
+  use crate::error::DiffError;
+  use crate::matrix::matrix_norms;
+  // A = [[3, 0], [0, 4]] with its label column, and B off by 1 at (2, 2)
+  let t1 =
+    records(&[&["r1", "3.0E+00", "0.0E+00"], &["r2", "0.0E+00", "4.0E+00"]]);
+  let t2 =
+    records(&[&["r1", "3.0E+00", "0.0E+00"], &["r2", "0.0E+00", "3.0E+00"]]);
+  let opts = DiffConfig::default();
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  let matrix =
+    matrix_norms((&t1, &t2), &opts, &report, true, Some(0.25), ("a", "b"))
+      .unwrap();
+  assert_eq!(matrix.shape, (2, 2));
+  // ||A||_F = 5 and ||A-B||_F = 1; the row sums are at most 4 and 1
+  assert_eq!(matrix.frobenius.relative(), 0.2);
+  assert_eq!(matrix.row_sum.unwrap().relative(), 0.25);
+  assert_eq!((matrix.worst.line, matrix.worst.col), (2, 3));
+  assert_eq!(matrix.worst.vals, (4.0, 3.0));
+  assert!(matrix.passed());
+
+  // the row-sum norm is gated too, and fails the comparison
+  let mut report = report;
+  let tight =
+    matrix_norms((&t1, &t2), &opts, &report, true, Some(0.22), ("a", "b"))
+      .unwrap();
+  assert!(!tight.passed());
+  report.matrix = Some(tight);
+  assert!(!report.passed());
+  let frobenius =
+    matrix_norms((&t1, &t2), &opts, &report, false, Some(0.22), ("a", "b"))
+      .unwrap();
+  assert!(frobenius.passed());
+
+  // matrices of different shapes are a structural error, extra rows or not
+  let longer = records(&[
+    &["r1", "3.0E+00", "0.0E+00"],
+    &["r2", "0.0E+00", "3.0E+00"],
+    &["r3", "1.0E+00", "1.0E+00"],
+  ]);
+  let err =
+    matrix_norms((&t1, &longer), &opts, &report, false, None, ("a", "b"))
+      .unwrap_err();
+  assert!(matches!(
+    err,
+    DiffError::RowCountMismatch {
+      rows1: 2,
+      rows2: 3,
+      line: 3,
+      ..
+    }
+  ));
+}
+
+#[test]
+fn test_column_maxima() {
+  let t1 = records(&[
+    &["1.0E+00", "1.0E+02", "5.0E+00"],
+    &["2.0E+00", "2.0E+02", "5.0E+00"],
+    &["4.0E+00", "4.0E+02", "5.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.0E+00", "1.1E+02", "5.0E+00"],
+    &["2.2E+00", "2.0E+02", "5.0E+00"],
+    &["4.1E+00", "4.0E+02", "5.0E+00"],
+  ]);
+  for parallel in [false, true] {
+    let opts = DiffConfig {
+      column_workers: if parallel { 2 } else { 1 },
+      ..Default::default()
+    };
+    let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    // each column has its own worst cells, by ratio and by difference
+    let first = &report.columns[0];
+    assert_eq!(
+      (first.max_ratio.line, first.max_ratio.vals),
+      (2, (2.0, 2.2))
+    );
+    assert_eq!(first.max_diff.line, 2);
+    let second = &report.columns[1];
+    assert_eq!((second.max_ratio.line, second.max_ratio.col), (1, 2));
+    assert_eq!(second.max_diff.value, report.max_diff.value);
+    // identical columns have none, as a report without differences
+    assert_eq!(report.columns[2].max_ratio.line, 0);
+    assert_eq!(report.columns[2].max_diff.line, 0);
+
+    // a column alone is judged by its own worst cells
+    let tolerances = Tolerances {
+      max_ratio: Some(0.05),
+      ..Default::default()
+    };
+    let verdict = |col| tolerances.passed(&report.column_report(col).unwrap());
+    assert!(!verdict(1) && !verdict(2) && verdict(3));
+    assert!(report.column_report(4).is_none());
+  }
+}
+
+#[test]
+fn test_excluded_cells() {
+  use crate::cell::{Outcome, explain};
+  let t1 = records(&[
+    &["1.0E+00", "1.0E+02", "5.0E+00"],
+    &["2.0E+00", "2.0E+02", "5.0E+00"],
+    &["4.0E+00", "4.0E+02", "5.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.0E+00", "1.0E+02", "5.0E+00"],
+    &["2.0E+00", "9.0E+02", "5.0E+00"],
+    &["4.1E+00", "4.0E+02", "5.0E+00"],
+  ]);
+  for column_workers in [1, 2] {
+    let opts = DiffConfig {
+      column_workers,
+      excluded_cells: [(2, 1)].into_iter().collect(),
+      ..Default::default()
+    };
+    let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&t1, &t2, &opts));
+    // the wild cell is left out, so the worst is the first column's
+    assert_eq!(report.excluded_cells(), [(2, 2)].into_iter().collect());
+    assert_eq!((report.max_ratio.line, report.max_ratio.col), (3, 1));
+    assert_eq!(report.columns[1].max_diff.line, 0);
+    assert_eq!(
+      explain(&t1, &t2, &opts, &report, (2, 2)).unwrap().outcome,
+      Outcome::Excluded
+    );
+  }
+}
+
+#[test]
+fn test_float_formats() {
+  use crate::compare::{FloatFormat, parse_float};
+  use regex::Regex;
+  // a column of D exponents, one of Nastran's exponents without a letter,
+  // and one that's standard
+  let t1 = records(&[
+    &["1.0D+00", "1.5-3", "1.0E+00"],
+    &["2.0D+00", "2.5+3", "2.0E+00"],
+  ]);
+  let t2 = records(&[
+    &["1.1D+00", "1.5-3", "1.0E+00"],
+    &["2.0D+00", "2.6+3", "2.0E+00"],
+  ]);
+  let compared = |format: FloatFormat| {
+    let opts = DiffConfig {
+      float_re: Regex::new(format.pattern()).unwrap(),
+      ..Default::default()
+    };
+    let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+    assert_eq!(report, reference::compare(&t1, &t2, &opts));
+    report.columns.iter().map(|c| c.col).collect::<Vec<_>>()
+  };
+  assert_eq!(compared(FloatFormat::Standard), [3]);
+  assert_eq!(compared(FloatFormat::Fortran), [1, 3]);
+  assert_eq!(compared(FloatFormat::Nastran), [1, 2, 3]);
+
+  // a pattern of one's own may take fewer cells for floats, too
+  let opts = DiffConfig {
+    float_re: Regex::new(r"^[0-9]\.[0-9]D[-+][0-9]{2}$").unwrap(),
+    ..Default::default()
+  };
+  let report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  assert_eq!(report.columns.len(), 1);
+  assert_eq!(report.max_diff.vals, (1.0, 1.1));
+
+  // every form reads as the same value
+  for text in ["1.5E-03", "1.5e-3", "1.5D-03", "1.5d-3", "1.5-3", ".15-2"] {
+    assert_eq!(parse_float(text), Some(1.5e-3), "{text}");
+  }
+  assert_eq!(parse_float("-2.+4"), Some(-2e4));
+  for text in ["2024-10-16", "abc", "1.5D"] {
+    assert_eq!(parse_float(text), None, "{text}");
+  }
+  assert_eq!("FORTRAN".parse(), Ok(FloatFormat::Fortran));
+  assert!("octal".parse::<FloatFormat>().is_err());
+}
+
+#[test]
+fn test_checks() {
+  use crate::checks::{self, Checks, Inputs, MatrixNorms};
+  // groups of rows by their first column, and the files as matrices
+  let t1 = records(&[&["1", "1.0E+00"], &["1", "2.0E+00"], &["2", "3.0E+00"]]);
+  let t2 = records(&[&["1", "1.0E+00"], &["1", "2.0E+00"], &["2", "3.3E+00"]]);
+  let opts = DiffConfig {
+    tolerances: Tolerances {
+      max_ratio: Some(0.01),
+      ..Default::default()
+    },
+    ..Default::default()
+  };
+  let checks = Checks {
+    group_by: Some((0, 0)),
+    matrix: Some(MatrixNorms::default()),
+    ..Default::default()
+  };
+  let inputs = Inputs {
+    records: (&t1, &t2),
+    headers: None,
+    files: ("a", "b"),
+  };
+  let mut report = compare::compare(&t1, &t2, &opts, ("a", "b")).unwrap();
+  checks::complete(&mut report, inputs, &opts, &checks).unwrap();
+  assert_eq!(report.groups.len(), 2);
+  assert!(
+    report.groups[0].report.passed() && !report.groups[1].report.passed()
+  );
+  assert!(report.matrix.is_some());
+  // the reference's report, completed alike, is the same
+  let mut spec = reference::compare(&t1, &t2, &opts);
+  checks::complete(&mut spec, inputs, &opts, &checks).unwrap();
+  assert_eq!(report, spec);
+
+  // and so is that of the files
+  let dir = std::env::temp_dir()
+    .join(format!("f06csvdiff-{}-checks", std::process::id()));
+  std::fs::create_dir_all(&dir).unwrap();
+  let (path1, path2) = (dir.join("a.csv"), dir.join("b.csv"));
+  std::fs::write(&path1, "1,1.0E+00\n1,2.0E+00\n2,3.0E+00\n").unwrap();
+  std::fs::write(&path2, "1,1.0E+00\n1,2.0E+00\n2,3.3E+00\n").unwrap();
+  let (path1, path2) = (path1.to_str().unwrap(), path2.to_str().unwrap());
+  let read = crate::compare_files_checked(path1, path2, &opts, &checks);
+  assert_eq!(read.unwrap().groups, report.groups);
+  std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_colored_alignment() {
+  use crate::color::Painter;
+  use crate::compare::Extremum;
+  use crate::table::{Alignment, Precision, align_table, aligned_row};
+  let worst = Extremum {
+    value: 1.5,
+    vals: (1.0, 1.5),
+    line: 3,
+    col: 2,
+  };
+  let render = |painter: &Painter| {
+    let (headers, row) = aligned_row(
+      Precision {
+        values: 6,
+        percent: 2,
+      },
+      (("a.csv", "b.csv"), None),
+      Some((worst, 50.0, false)),
+      Some((worst, true)),
+      None,
+      None,
+      (4, Default::default()),
+      None,
+    );
+    align_table(&headers, &[row], &Alignment::Right, None, true, painter)
+  };
+  let plain = render(&Painter::default());
+  let colored = render(&Painter::always());
+  assert!(!plain.contains('\x1b'));
+  assert!(colored.contains("\x1b[31m    FAIL\x1b[0m"));
+  assert!(colored.contains("\x1b[32m    PASS\x1b[0m"));
+  assert!(colored.contains("\x1b[1;33m"));
+  // stripping the escapes gives back exactly the uncolored layout
+  let stripped = regex::Regex::new("\x1b\\[[0-9;]*m")
+    .unwrap()
+    .replace_all(&colored, "")
+    .to_string();
+  assert_eq!(stripped, plain);
+  // and the header and data lines are equally wide
+  let lines: Vec<&str> = plain.lines().collect();
+  assert_eq!(lines[0].len(), lines[1].len());
+}
+
+#[test]
+fn test_aligned_golden() {
+  use crate::color::Painter;
+  use crate::compare::Extremum;
+  use crate::table::{Alignment, Precision, align_table, aligned_row};
+  let worst = Extremum {
+    value: 0.1,
+    vals: (2.0, 2.1),
+    line: 7,
+    col: 3,
+  };
+  // the header line's and the row's fields
+  let render = |ratio: bool, diff: bool| {
+    let (headers, row) = aligned_row(
+      Precision {
+        values: 2,
+        percent: 2,
+      },
+      (("base/r.csv", "cand/r.csv"), None),
+      ratio.then_some((worst, 5.0, true)),
+      diff.then_some((worst, false)),
+      None,
+      None,
+      (12, Default::default()),
+      None,
+    );
+    let table = align_table(
+      &headers,
+      &[row],
+      &Alignment::Left,
+      None,
+      true,
+      &Painter::default(),
+    );
+    let lines: Vec<&str> = table.lines().collect();
+    // each cell starts where its header does
+    let starts = |line: &str| -> Vec<usize> {
+      let bytes = line.as_bytes();
+      (0..bytes.len())
+        .filter(|&i| bytes[i] != b' ' && (i == 0 || bytes[i - 1] == b' '))
+        .collect()
+    };
+    assert_eq!(starts(lines[0]), starts(lines[1]));
+    let fields = |line: &str| -> Vec<String> {
+      line.split_whitespace().map(String::from).collect()
+    };
+    (fields(lines[0]), fields(lines[1]))
+  };
+  let (files, counts) = (["base/r.csv", "cand/r.csv"], ["0", "12"]);
+  let ratio = ["ratio_%", "val1_r", "val2_r", "line_r", "col_r", "status_r"];
+  let ratio_cells = ["5.00", "+2.00E0", "+2.10E0", "7", "3", "PASS"];
+  let diff = [
+    "abs_diff", "val1_d", "val2_d", "line_d", "col_d", "status_d",
+  ];
+  let diff_cells = ["1.00E-1", "+2.00E0", "+2.10E0", "7", "3", "FAIL"];
+
+  let (headers, row) = render(true, false);
+  assert_eq!(
+    headers,
+    [&["csv1", "csv2"][..], &ratio, &["fail_r", "cells"]].concat()
+  );
+  assert_eq!(row, [&files[..], &ratio_cells, &counts].concat());
+
+  let (headers, row) = render(false, true);
+  assert_eq!(
+    headers,
+    [&["csv1", "csv2"][..], &diff, &["fail_d", "cells"]].concat()
+  );
+  assert_eq!(row, [&files[..], &diff_cells, &counts].concat());
+
+  let (headers, row) = render(true, true);
+  let failing = ["fail_r", "fail_d", "cells"];
+  assert_eq!(
+    headers,
+    [&["csv1", "csv2"][..], &ratio, &diff, &failing].concat()
+  );
+  let counts = ["0", "0", "12"];
+  assert_eq!(
+    row,
+    [&files[..], &ratio_cells, &diff_cells, &counts].concat()
+  );
+}
+
+#[test]
+fn test_junit_escaping() {
+  use crate::junit::{TestCase, escape, testsuite};
+  assert_eq!(
+    escape(r#"a<b>&"c'.csv"#),
+    "a&lt;b&gt;&amp;&quot;c&apos;.csv"
+  );
+  let xml = testsuite(
+    "R&D <run>.csv vs \"ref\".csv",
+    &[],
+    &[
+      TestCase {
+        name: "ratio".to_string(),
+        failure: None,
+        waiver: None,
+        warning: None,
+      },
+      TestCase {
+        name: "diff".to_string(),
+        failure: Some("max diff 1 > 0 at line 2".to_string()),
+        waiver: None,
+        warning: None,
+      },
+    ],
+  );
+  assert!(xml.contains(
+    "name=\"R&amp;D &lt;run&gt;.csv vs &quot;ref&quot;.csv\" tests=\"2\" \
+     failures=\"1\""
+  ));
+  assert!(xml.contains("<testcase classname=\"R&amp;D"));
+  assert!(xml.contains("message=\"max diff 1 &gt; 0 at line 2\""));
+  // nothing unescaped is left between the markup
+  let text = regex::Regex::new("<[^<>]*>").unwrap().replace_all(&xml, "");
+  assert!(!text.contains(['<', '>', '"']));
+}
+
+#[test]
+fn test_markdown_table() {
+  use crate::color::Paint;
+  use crate::markdown::{escape, header, row};
+  assert_eq!(escape("a|b_c*.csv"), r"a\|b\_c\*.csv");
+  assert_eq!(
+    header(&["csv1".to_string(), "x|y".to_string()]),
+    "| csv1 | x\\|y |\n| --- | --- |"
+  );
+  let cells = [
+    ("run|1.csv".to_string(), Paint::Plain),
+    ("1.50".to_string(), Paint::Offending),
+    ("FAIL".to_string(), Paint::Fail),
+    ("PASS".to_string(), Paint::Pass),
+  ];
+  let line = row(&cells);
+  assert_eq!(line, r"| run\|1.csv | **1.50** | **FAIL** | PASS |");
+  // escaped pipes don't count as cell separators
+  assert_eq!(
+    line.replace(r"\|", "").matches('|').count(),
+    cells.len() + 1
+  );
+}
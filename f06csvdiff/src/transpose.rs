@@ -0,0 +1,73 @@
+//! Transposition of a file written a row per quantity and a column per grid
+//! point to the usual row per grid point, and back from locations in it to
+//! the file's own lines and columns.
+
+use csv::{Position, StringRecord};
+
+use crate::check_field_counts;
+use crate::compare::physical_line;
+use crate::error::DiffError;
+
+/// A file's records transposed: each record is one of its columns, at the
+/// (1-based) number of the column as its line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transposed {
+  /// The records.
+  pub records: Vec<StringRecord>,
+  /// The physical (1-based) line of each of the file's records, by the
+  /// column of the transposed records it became.
+  lines: Vec<usize>,
+}
+
+impl Transposed {
+  /// The physical (1-based) line of the file's record that became this
+  /// (1-based) column of the transposed records, or the column itself if
+  /// there's no such record.
+  pub fn line(&self, col: usize) -> usize {
+    match col.checked_sub(1).and_then(|i| self.lines.get(i)) {
+      Some(&line) => line,
+      None => col,
+    }
+  }
+}
+
+/// Transposes a file's records, each column becoming a record. Fails with
+/// [`DiffError::FieldCountChange`] unless every record has as many fields
+/// as the first. Every cell is copied once, however wide the file is.
+pub fn transpose(
+  records: &[StringRecord],
+  file: &str,
+) -> Result<Transposed, DiffError> {
+  check_field_counts(records, file)?;
+  let width = records.first().map_or(0, StringRecord::len);
+  // each column's bytes, so that no record grows as it's filled
+  let mut bytes = vec![0; width];
+  for record in records {
+    for (i, cell) in record.iter().enumerate() {
+      bytes[i] += cell.len();
+    }
+  }
+  let mut transposed: Vec<StringRecord> = bytes
+    .iter()
+    .enumerate()
+    .map(|(i, &len)| {
+      let mut record = StringRecord::with_capacity(len, records.len());
+      let mut position = Position::new();
+      position.set_line(i as u64 + 1).set_record(i as u64);
+      record.set_position(Some(position));
+      record
+    })
+    .collect();
+  for record in records {
+    for (i, cell) in record.iter().enumerate() {
+      transposed[i].push_field(cell);
+    }
+  }
+  let lines = (0..records.len())
+    .map(|row| physical_line(records, row))
+    .collect();
+  Ok(Transposed {
+    records: transposed,
+    lines,
+  })
+}
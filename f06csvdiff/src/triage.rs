@@ -0,0 +1,62 @@
+//! Triage for --triage: a cheap look at every pair of a batch before the
+//! full comparisons, so that those likeliest to fail run, and report, first.
+//! It only changes the order of the work, never a verdict.
+//!
+//! A pair's suspicion is
+//! - infinite if either file can't be read, or they have different numbers
+//!   of lines, since the comparison will likely fail on the layout;
+//! - zero if the files are byte for byte identical;
+//! - otherwise the worst relative difference |a-b|/max(|a|,|b|) among the
+//!   numbers of [`SAMPLES`] lines spread evenly through the files, plus
+//!   their relative difference in size.
+
+/// How many lines of each pair are sampled.
+pub(crate) const SAMPLES: usize = 64;
+
+/// How suspicious a pair of files is: the higher, the likelier to fail.
+pub(crate) fn suspicion((csv1, csv2): (&str, &str), delimiter: u8) -> f64 {
+  let (Ok(bytes1), Ok(bytes2)) = (std::fs::read(csv1), std::fs::read(csv2))
+  else {
+    return f64::INFINITY;
+  };
+  if bytes1 == bytes2 {
+    return 0.0;
+  }
+  let lines1: Vec<&[u8]> = bytes1.split(|&b| b == b'\n').collect();
+  let lines2: Vec<&[u8]> = bytes2.split(|&b| b == b'\n').collect();
+  if lines1.len() != lines2.len() {
+    return f64::INFINITY;
+  }
+  let step = lines1.len().div_ceil(SAMPLES).max(1);
+  let worst = (0..lines1.len())
+    .step_by(step)
+    .map(|row| worst_rel(lines1[row], lines2[row], delimiter))
+    .fold(0.0, f64::max);
+  let (size1, size2) = (bytes1.len() as f64, bytes2.len() as f64);
+  worst + (size1 - size2).abs() / size1.max(size2)
+}
+
+/// The worst relative difference between the numbers at the same places
+/// of two lines, split naively on the delimiter.
+fn worst_rel(line1: &[u8], line2: &[u8], delimiter: u8) -> f64 {
+  let number =
+    |field: &[u8]| std::str::from_utf8(field).ok()?.trim().parse::<f64>().ok();
+  line1
+    .split(|&b| b == delimiter)
+    .zip(line2.split(|&b| b == delimiter))
+    .filter_map(|(field1, field2)| Some((number(field1)?, number(field2)?)))
+    .map(|(a, b)| match (a - b).abs() / a.abs().max(b.abs()) {
+      // both zero, or NaN
+      rel if rel.is_nan() => 0.0,
+      rel => rel,
+    })
+    .fold(0.0, f64::max)
+}
+
+/// The order to compare pairs in: most suspicious first, and otherwise in
+/// their given order.
+pub(crate) fn order(suspicions: &[f64]) -> Vec<usize> {
+  let mut order: Vec<usize> = (0..suspicions.len()).collect();
+  order.sort_by(|&i, &j| suspicions[j].total_cmp(&suspicions[i]));
+  order
+}
@@ -0,0 +1,47 @@
+//! Vector magnitudes: groups of columns holding the components of a vector,
+//! such as displacements T1, T2 and T3, compared by the root sum of squares
+//! of each row's components. A vector that rotates slightly can change its
+//! components a lot while its magnitude stays the same.
+
+use csv::StringRecord;
+use regex::Regex;
+
+use crate::compare::{is_float, parse_float, physical_line};
+use crate::error::DiffError;
+
+/// Appends to each record the magnitude of each group of its (0-based)
+/// columns, in order, so that they're compared like any other column.
+/// Records keep their positions. Fails with [`DiffError::VectorComponent`]
+/// if a row lacks a component, or has one that isn't a float by `float_re`.
+pub fn append_magnitudes(
+  records: &mut [StringRecord],
+  groups: &[Vec<usize>],
+  file: &str,
+  float_re: &Regex,
+) -> Result<(), DiffError> {
+  for row in 0..records.len() {
+    let mut magnitudes = Vec::with_capacity(groups.len());
+    for group in groups {
+      let mut squares = 0.0;
+      for &col in group {
+        let component = records[row]
+          .get(col)
+          .filter(|cell| is_float(cell, float_re))
+          .ok_or_else(|| DiffError::VectorComponent {
+            file: file.to_string(),
+            line: physical_line(records, row),
+            col: col + 1,
+          })?;
+        // is_float checked that it parses
+        let value = parse_float(component).unwrap();
+        squares += value * value;
+      }
+      magnitudes.push(squares.sqrt());
+    }
+    for magnitude in magnitudes {
+      // printed to the last digit, so that it passes for exact
+      records[row].push_field(&format!("{magnitude:.16E}"));
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,410 @@
+//! Turning a report into PASS/FAIL verdicts against tolerances.
+
+use serde::{Deserialize, Serialize};
+
+use crate::compare::{DiffReport, Direction};
+
+/// A pass/fail check on a report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Gate {
+  /// The worst percent difference.
+  Ratio,
+  /// The worst absolute difference.
+  Diff,
+  /// The fewest digits of agreement.
+  Digits,
+  /// The worst absolute difference for the combined absolute and relative
+  /// tolerance at its cell.
+  Allclose,
+}
+
+impl Gate {
+  /// The short name of the gate in reports.
+  pub fn name(self) -> &'static str {
+    match self {
+      Gate::Ratio => "ratio",
+      Gate::Diff => "diff",
+      Gate::Digits => "digits",
+      Gate::Allclose => "allclose",
+    }
+  }
+}
+
+/// A pass/fail check on a report beyond the gates, made as options ask.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Check {
+  /// The columns' sums.
+  Sums,
+  /// The compared cells' annotations.
+  Annotations,
+  /// A tolerance spec's assertions.
+  Assertions,
+  /// The complex pairs' phases.
+  Complex,
+  /// The pairing of rows, by key or nearest value.
+  UnpairedRows,
+  /// The pairing of float columns by MAC.
+  UnpairedColumns,
+  /// The groups of rows, each compared on its own.
+  Groups,
+  /// The columns with tolerances of their own.
+  Overrides,
+  /// The norms of the files' difference as matrices.
+  Matrix,
+  /// The audit of the excluded cells' list.
+  Exclusions,
+  /// The rows left out for structural problems.
+  Structure,
+  /// The cells zero in one file only.
+  ZeroMismatches,
+  /// The requirements on the compared columns.
+  Columns,
+}
+
+impl Check {
+  /// Every check, in the order reports give them.
+  pub const ALL: [Check; 13] = [
+    Check::Sums,
+    Check::Annotations,
+    Check::Assertions,
+    Check::Complex,
+    Check::UnpairedRows,
+    Check::UnpairedColumns,
+    Check::Groups,
+    Check::Overrides,
+    Check::Matrix,
+    Check::Exclusions,
+    Check::Structure,
+    Check::ZeroMismatches,
+    Check::Columns,
+  ];
+
+  /// The short name of the check in reports.
+  pub fn name(self) -> &'static str {
+    match self {
+      Check::Sums => "sums",
+      Check::Annotations => "annotations",
+      Check::Assertions => "assertions",
+      Check::Complex => "complex",
+      Check::UnpairedRows => "unpaired_rows",
+      Check::UnpairedColumns => "unpaired_columns",
+      Check::Groups => "groups",
+      Check::Overrides => "overrides",
+      Check::Matrix => "matrix",
+      Check::Exclusions => "exclusions",
+      Check::Structure => "structure",
+      Check::ZeroMismatches => "zero_mismatches",
+      Check::Columns => "columns",
+    }
+  }
+}
+
+/// A set of tolerances; each one enables a gate.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tolerances {
+  /// The largest allowed ratio minus one (0.01 is 1%).
+  pub max_ratio: Option<f64>,
+  /// The largest allowed absolute difference.
+  pub max_diff: Option<f64>,
+  /// The fewest allowed digits of agreement.
+  pub min_digits: Option<f64>,
+  /// Pass a gate anyway if fewer than this fraction of the compared cells
+  /// exceed its tolerance on their own (0.001 is 0.1%).
+  pub max_fail_fraction: Option<f64>,
+  /// The largest allowed ratio minus one where csv2 is above csv1, instead
+  /// of `max_ratio`, which must be given.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub max_ratio_over: Option<f64>,
+  /// The largest allowed ratio minus one where csv2 isn't above csv1,
+  /// instead of `max_ratio`, which must be given.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub max_ratio_under: Option<f64>,
+  /// The largest allowed absolute difference where csv2 is above csv1,
+  /// instead of `max_diff`, which must be given.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub max_diff_over: Option<f64>,
+  /// The largest allowed absolute difference where csv2 isn't above csv1,
+  /// instead of `max_diff`, which must be given.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub max_diff_under: Option<f64>,
+  /// The absolute part of a combined tolerance, as numpy's allclose has it:
+  /// a cell is within it if |a-b| <= atol + rtol*|b|, the reference value,
+  /// csv2's, being `b`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub atol: Option<f64>,
+  /// The relative part of the combined tolerance (0.01 is 1% of csv2's
+  /// magnitude).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub rtol: Option<f64>,
+}
+
+/// The outcome of one gate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GateResult {
+  /// Which gate this is.
+  pub gate: Gate,
+  /// Whether it passed.
+  pub passed: bool,
+  /// How far from the tolerance the observed value is; negative when failing.
+  /// Ratio margins are in percentage points.
+  pub margin: f64,
+}
+
+/// A column's sums over its compared cells, judged by the ratio and
+/// absolute difference tolerances as a single cell of them would be.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColumnSum {
+  /// The (1-based) column, in csv1.
+  pub col: usize,
+  /// Its sums in csv1 and csv2.
+  pub sums: (f64, f64),
+  /// The sums' absolute difference.
+  pub diff: f64,
+  /// The sums' relative difference in percent, as the comparison measures
+  /// relative differences.
+  pub percent: f64,
+  /// Whether the sums are within both tolerances.
+  pub passed: bool,
+}
+
+impl GateResult {
+  /// Formats the margin in the gate's usual notation.
+  pub fn margin_text(&self) -> String {
+    match self.gate {
+      Gate::Diff => format!("{:+.2E}", self.margin),
+      Gate::Ratio | Gate::Digits | Gate::Allclose => {
+        format!("{:+.2}", self.margin)
+      }
+    }
+  }
+}
+
+/// Whether a relative difference, in percent, exceeds a `max_ratio`
+/// tolerance, which is a fraction (0.01 is 1%). Every verdict on a relative
+/// difference goes through here, so that no two reports can disagree on one.
+pub fn exceeds_ratio(percent: f64, max_ratio: f64) -> bool {
+  percent > max_ratio * 100.0
+}
+
+impl Tolerances {
+  /// The gates these tolerances enable.
+  pub fn gates(&self) -> Vec<Gate> {
+    let mut gates = Vec::new();
+    if self.max_ratio.is_some() {
+      gates.push(Gate::Ratio);
+    }
+    if self.max_diff.is_some() {
+      gates.push(Gate::Diff);
+    }
+    if self.min_digits.is_some() {
+      gates.push(Gate::Digits);
+    }
+    if self.allclose().is_some() {
+      gates.push(Gate::Allclose);
+    }
+    gates
+  }
+
+  /// The combined tolerance's (atol, rtol), either defaulting to zero, if
+  /// either is given.
+  pub fn allclose(&self) -> Option<(f64, f64)> {
+    (self.atol.is_some() || self.rtol.is_some())
+      .then(|| (self.atol.unwrap_or(0.0), self.rtol.unwrap_or(0.0)))
+  }
+
+  /// The absolute difference the combined tolerance allows at a cell whose
+  /// csv2 value is `b`, if it's given.
+  pub fn allclose_limit(&self, b: f64) -> Option<f64> {
+    self.allclose().map(|(atol, rtol)| atol + rtol * b.abs())
+  }
+
+  /// The ratio tolerance of cells differing in a direction: its own, or
+  /// else `max_ratio`; none without `max_ratio`.
+  pub fn ratio_limit(&self, direction: Direction) -> Option<f64> {
+    let own = match direction {
+      Direction::Over => self.max_ratio_over,
+      Direction::Under => self.max_ratio_under,
+    };
+    self.max_ratio.map(|mr| own.unwrap_or(mr))
+  }
+
+  /// The absolute difference tolerance of cells differing in a direction:
+  /// its own, or else `max_diff`; none without `max_diff`.
+  pub fn diff_limit(&self, direction: Direction) -> Option<f64> {
+    let own = match direction {
+      Direction::Over => self.max_diff_over,
+      Direction::Under => self.max_diff_under,
+    };
+    self.max_diff.map(|md| own.unwrap_or(md))
+  }
+
+  /// Only the directional tolerances of these overriding `fallback`: their
+  /// own, or else `fallback`'s for a tolerance they leave unset, so that
+  /// overriding `max_ratio` or `max_diff` alone makes it symmetric.
+  pub fn directional_over(&self, fallback: &Tolerances) -> Tolerances {
+    let (ratio, diff) = match (self.max_ratio, self.max_diff) {
+      (None, None) => (fallback, fallback),
+      (None, Some(_)) => (fallback, self),
+      (Some(_), None) => (self, fallback),
+      (Some(_), Some(_)) => (self, self),
+    };
+    Tolerances {
+      max_ratio_over: ratio.max_ratio_over,
+      max_ratio_under: ratio.max_ratio_under,
+      max_diff_over: diff.max_diff_over,
+      max_diff_under: diff.max_diff_under,
+      ..Default::default()
+    }
+  }
+
+  /// Whether the ratio or absolute difference tolerance differs by
+  /// direction.
+  pub fn directional(&self) -> bool {
+    [
+      self.max_ratio_over,
+      self.max_ratio_under,
+      self.max_diff_over,
+      self.max_diff_under,
+    ]
+    .iter()
+    .any(Option::is_some)
+  }
+
+  /// Evaluates every enabled gate on a report.
+  pub fn evaluate(&self, report: &DiffReport) -> Vec<GateResult> {
+    let mut results = Vec::new();
+    // the margins stay those of the worst cell, even when few enough fail
+    let few_fail = |gate| {
+      self
+        .max_fail_fraction
+        .is_some_and(|f| report.failing_fraction(gate) < f)
+    };
+    if self.directional() {
+      self.evaluate_directed(report, &mut results, few_fail);
+    } else if let Some(mr) = self.max_ratio {
+      let percent = report.ratio_percent();
+      let margin = mr * 100.0 - percent;
+      results.push(GateResult {
+        gate: Gate::Ratio,
+        passed: !exceeds_ratio(percent, mr) || few_fail(Gate::Ratio),
+        margin,
+      });
+    }
+    if let Some(md) = self.max_diff.filter(|_| !self.directional()) {
+      // scaled row by row, the worst cell is the one nearest its own limit
+      let (worst, md) = match &report.scaled_diff {
+        Some(scaled) => (scaled.diff, md * scaled.scale),
+        None => (report.max_diff.value, md),
+      };
+      results.push(GateResult {
+        gate: Gate::Diff,
+        passed: worst <= md || few_fail(Gate::Diff),
+        margin: md - worst,
+      });
+    }
+    if let Some(min) = self.min_digits {
+      let (passed, margin) = match report.worst_digits() {
+        Some((d, _)) => (d >= min, d - min),
+        None => (true, f64::INFINITY),
+      };
+      results.push(GateResult {
+        gate: Gate::Digits,
+        passed: passed || few_fail(Gate::Digits),
+        margin,
+      });
+    }
+    if self.allclose().is_some() {
+      // the worst cell's difference as a multiple of what it's allowed
+      let worst = report.allclose.value;
+      results.push(GateResult {
+        gate: Gate::Allclose,
+        passed: worst <= 1.0 || few_fail(Gate::Allclose),
+        margin: 1.0 - worst,
+      });
+    }
+    results
+  }
+
+  /// Evaluates the ratio and absolute difference gates of directional
+  /// tolerances on a report: each direction's worst cells against its own
+  /// tolerance, a gate's margin being that of the direction nearer to
+  /// failing.
+  fn evaluate_directed(
+    &self,
+    report: &DiffReport,
+    results: &mut Vec<GateResult>,
+    few_fail: impl Fn(Gate) -> bool,
+  ) {
+    let directions = [Direction::Over, Direction::Under];
+    if self.max_ratio.is_some() {
+      let (mut exceeded, mut margin) = (false, f64::INFINITY);
+      for direction in directions {
+        // ratio_limit is set whenever max_ratio is
+        let mr = self.ratio_limit(direction).unwrap();
+        let worst = report.directions.of(direction).max_ratio.value;
+        let percent = report.rel_mode.percent(worst);
+        exceeded |= exceeds_ratio(percent, mr);
+        margin = margin.min(mr * 100.0 - percent);
+      }
+      results.push(GateResult {
+        gate: Gate::Ratio,
+        passed: !exceeded || few_fail(Gate::Ratio),
+        margin,
+      });
+    }
+    if self.max_diff.is_some() {
+      let (mut exceeded, mut margin) = (false, f64::INFINITY);
+      for direction in directions {
+        let md = self.diff_limit(direction).unwrap();
+        let worst = report.directions.of(direction).max_diff.value;
+        exceeded |= worst > md;
+        margin = margin.min(md - worst);
+      }
+      results.push(GateResult {
+        gate: Gate::Diff,
+        passed: !exceeded || few_fail(Gate::Diff),
+        margin,
+      });
+    }
+  }
+
+  /// Checks the sums of each column of a report that has them against the
+  /// ratio and absolute difference tolerances, those of the sums' direction
+  /// if they differ by direction. The absolute difference tolerance isn't
+  /// scaled, and `max_fail_fraction` and `min_digits` don't apply.
+  pub fn check_sums(&self, report: &DiffReport) -> Vec<ColumnSum> {
+    let mode = report.rel_mode;
+    let sums = report
+      .columns
+      .iter()
+      .filter_map(|column| Some((column.col, column.sums?)));
+    sums
+      .map(|(col, (sum1, sum2))| {
+        let (s1, s2) = (sum1.value(), sum2.value());
+        let direction = Direction::of(s1, s2);
+        let diff = (s1 - s2).abs();
+        let percent = match s1 == 0.0 && s2 == 0.0 {
+          true => 0.0,
+          false => mode.percent(mode.measure(s1, s2, report.rel_floor)),
+        };
+        let ratio = self
+          .ratio_limit(direction)
+          .is_none_or(|mr| !exceeds_ratio(percent, mr));
+        let within = self.diff_limit(direction).is_none_or(|md| diff <= md);
+        ColumnSum {
+          col,
+          sums: (s1, s2),
+          diff,
+          percent,
+          passed: ratio && within,
+        }
+      })
+      .collect()
+  }
+
+  /// Whether every enabled gate passes.
+  pub fn passed(&self, report: &DiffReport) -> bool {
+    self.evaluate(report).iter().all(|r| r.passed)
+  }
+}
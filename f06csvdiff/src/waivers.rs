@@ -0,0 +1,149 @@
+//! Waivers: failures accepted for a while, e.g. a known regression while
+//! its fix is scheduled, without loosening the tolerances or dropping the
+//! gate. A failure a waiver covers is reported as WAIVED rather than FAILED
+//! until the waiver expires; expired waivers are ignored.
+//!
+//! `--waivers` reads them from a TOML file of `[[waiver]]` tables:
+//!
+//! ```toml
+//! [[waiver]]
+//! pair = "run/sol101.csv"
+//! column = 5
+//! reason = "T3 drifts since the new element formulation"
+//! ticket = "NAS-1234"
+//! expires = 2026-12-31
+//! ```
+
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use toml::value::{Date, Datetime};
+
+/// The contents of a waivers file.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Waivers {
+  /// The waivers, in the order they're tried.
+  pub waiver: Vec<Waiver>,
+}
+
+/// A failure accepted until a date. It names a pair, a column, or both.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Waiver {
+  /// The pair it covers: either file's path or name, or both paths as in
+  /// result lines, "csv1|csv2". Every pair if left out.
+  pub pair: Option<String>,
+  /// The (1-based) column of csv1 it covers: the pair is waived if it would
+  /// pass without it. The whole pair if left out.
+  pub column: Option<usize>,
+  /// Why the failure is accepted.
+  pub reason: String,
+  /// The ticket tracking the fix.
+  pub ticket: String,
+  /// The last day it applies.
+  pub expires: Datetime,
+}
+
+impl Waivers {
+  /// Reads and parses a waivers file.
+  pub fn load(path: &Path) -> Result<Self, String> {
+    let bad = |e: &dyn std::fmt::Display| {
+      format!("bad waivers file {}: {e}", path.display())
+    };
+    let text = std::fs::read_to_string(path)
+      .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+    let waivers: Self = toml::from_str(&text).map_err(|e| bad(&e))?;
+    for waiver in &waivers.waiver {
+      let ticket = &waiver.ticket;
+      if waiver.pair.is_none() && waiver.column.is_none() {
+        return Err(bad(&format!("waiver {ticket} names no pair or column")));
+      }
+      if waiver.column == Some(0) {
+        return Err(bad(&format!("waiver {ticket}: columns start at 1")));
+      }
+      if waiver.expires.time.is_some() || waiver.expires.date.is_none() {
+        return Err(bad(&format!("waiver {ticket} must expire on a date")));
+      }
+    }
+    Ok(waivers)
+  }
+}
+
+impl Waiver {
+  /// The last day it applies.
+  pub fn expiry(&self) -> Date {
+    // load only accepts dates
+    self.expires.date.unwrap()
+  }
+
+  /// Whether it no longer applies on `today`.
+  pub fn expired(&self, today: Date) -> bool {
+    self.expiry() < today
+  }
+
+  /// Whether it covers this pair of files.
+  pub fn covers(&self, paths: (&str, &str)) -> bool {
+    self.pair.as_ref().is_none_or(|pair| names(pair, paths))
+  }
+
+  /// Describes it for reports, e.g. "NAS-1234 (T3 drifts), until
+  /// 2026-12-31".
+  pub fn describe(&self) -> String {
+    let column = match self.column {
+      Some(col) => format!(" on column {col}"),
+      None => String::new(),
+    };
+    format!(
+      "{}{column} ({}), until {}",
+      self.ticket,
+      self.reason,
+      self.expiry()
+    )
+  }
+}
+
+/// Whether a pair as waivers name it, by either file's path or name, or
+/// both paths as in result lines, "csv1|csv2", names this pair of files.
+pub fn names(pair: &str, (csv1, csv2): (&str, &str)) -> bool {
+  let named = |path: &str| {
+    path == pair || Path::new(path).file_name().is_some_and(|n| n == pair)
+  };
+  named(csv1) || named(csv2) || pair == format!("{csv1}|{csv2}")
+}
+
+/// Today's date, in UTC.
+pub fn today() -> Date {
+  let secs = SystemTime::now()
+    .duration_since(SystemTime::UNIX_EPOCH)
+    .map_or(0, |d| d.as_secs());
+  civil_date((secs / 86_400) as i64)
+}
+
+/// The date of a day counted from 1970-01-01, in the proleptic Gregorian
+/// calendar.
+pub fn civil_date(days: i64) -> Date {
+  // shifted so that years start in March, and leap days come last
+  let days = days + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days.rem_euclid(146_097);
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+    - day_of_era / 146_096)
+    / 365;
+  let day_of_year =
+    day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+  let month = if month_index < 10 {
+    month_index + 3
+  } else {
+    month_index - 9
+  };
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+  Date {
+    year: year as u16,
+    month: month as u8,
+    day: day as u8,
+  }
+}